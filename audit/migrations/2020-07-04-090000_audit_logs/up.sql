@@ -0,0 +1,19 @@
+CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
+
+CREATE TABLE audit_logs (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  actor_id uuid,
+  actor_role VARCHAR(50),
+  impersonator_id uuid,
+  organization_id uuid,
+  action VARCHAR(100) NOT NULL,
+  entity_type VARCHAR(100) NOT NULL,
+  entity_id VARCHAR(255) NOT NULL,
+  diff JSONB NOT NULL DEFAULT '{}',
+  request_id VARCHAR(255),
+  ip_address VARCHAR(45),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX audit_logs_entity_idx ON audit_logs (entity_type, entity_id, created_at);
+CREATE INDEX audit_logs_actor_id_idx ON audit_logs (actor_id, created_at);
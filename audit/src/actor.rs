@@ -0,0 +1,77 @@
+use timada_http::Context;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Actor {
+    pub user_id: Option<Uuid>,
+    pub role: Option<String>,
+    pub impersonator_id: Option<Uuid>,
+    pub organization_id: Option<Uuid>,
+    pub request_id: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl Actor {
+    pub fn system() -> Self {
+        Actor {
+            role: Some("System".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_context(context: &Context) -> Self {
+        Actor {
+            user_id: context.user.as_ref().map(|user| user.id),
+            role: context
+                .user
+                .as_ref()
+                .map(|user| user.role.as_str().to_owned()),
+            impersonator_id: context.impersonator.as_ref().map(|user| user.id),
+            organization_id: context.organization_id,
+            request_id: context.request_id.to_owned(),
+            ip_address: context.client_ip.map(|ip| ip.to_string()),
+        }
+    }
+
+    pub fn is_impersonated(&self) -> bool {
+        self.impersonator_id.is_some()
+    }
+}
+
+impl From<&Context> for Actor {
+    fn from(context: &Context) -> Self {
+        Actor::from_context(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_http::testing::ContextBuilder;
+    use timada_http::UserRole;
+    use uuid::Uuid;
+
+    use super::Actor;
+
+    #[test]
+    fn from_context() {
+        let user_id = Uuid::new_v4();
+        let impersonator = ContextBuilder::new()
+            .role(UserRole::Root)
+            .build()
+            .user
+            .unwrap();
+        let context = ContextBuilder::new()
+            .id(user_id)
+            .role(UserRole::Staff)
+            .impersonator(impersonator.clone())
+            .build();
+
+        let actor = Actor::from_context(&context);
+
+        assert_eq!(actor.user_id, Some(user_id));
+        assert_eq!(actor.role, Some("Staff".to_owned()));
+        assert_eq!(actor.impersonator_id, Some(impersonator.id));
+        assert!(actor.is_impersonated());
+        assert!(!Actor::system().is_impersonated());
+    }
+}
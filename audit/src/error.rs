@@ -0,0 +1,35 @@
+use diesel::result::Error as DieselError;
+use timada_http::Error;
+
+#[derive(Debug, PartialEq, Error)]
+pub enum AuditError {
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    #[error("{0}")]
+    Database(String),
+}
+
+impl From<DieselError> for AuditError {
+    fn from(e: DieselError) -> AuditError {
+        AuditError::Database(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AuditError {
+    fn from(e: serde_json::Error) -> AuditError {
+        AuditError::Serialization(e.to_string())
+    }
+}
+
+impl From<AuditError> for Error {
+    fn from(e: AuditError) -> Error {
+        match e {
+            AuditError::Serialization(message) | AuditError::Database(message) => {
+                Error::Internal(message)
+            }
+        }
+    }
+}
+
+pub type AuditResult<T> = Result<T, AuditError>;
@@ -0,0 +1,19 @@
+#[macro_use]
+extern crate diesel;
+
+#[macro_use]
+extern crate diesel_migrations;
+
+#[macro_use]
+extern crate thiserror;
+
+mod actor;
+mod error;
+mod migration;
+mod schema;
+mod trail;
+
+pub use crate::actor::Actor;
+pub use crate::error::{AuditError, AuditResult};
+pub use crate::migration::migrate;
+pub use crate::trail::{actor_trail, audit, audit_trail, diff, AuditLog};
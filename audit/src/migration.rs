@@ -0,0 +1,8 @@
+use diesel::PgConnection;
+use diesel_migrations::RunMigrationsError;
+
+embed_migrations!("migrations");
+
+pub fn migrate(connection: &PgConnection) -> Result<(), RunMigrationsError> {
+    embedded_migrations::run(connection)
+}
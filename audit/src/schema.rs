@@ -0,0 +1,16 @@
+table! {
+    audit_logs (id) {
+        id -> Uuid,
+        actor_id -> Nullable<Uuid>,
+        actor_role -> Nullable<Varchar>,
+        impersonator_id -> Nullable<Uuid>,
+        organization_id -> Nullable<Uuid>,
+        action -> Varchar,
+        entity_type -> Varchar,
+        entity_id -> Varchar,
+        diff -> Jsonb,
+        request_id -> Nullable<Varchar>,
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
@@ -0,0 +1,312 @@
+use async_graphql::{Connection, ID};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use timada_relay::{to_id, ConnectionError, ConnectionResult};
+use uuid::Uuid;
+
+use super::actor::Actor;
+use super::error::AuditResult;
+use super::schema::audit_logs;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Identifiable)]
+#[table_name = "audit_logs"]
+pub struct AuditLog {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub actor_role: Option<String>,
+    pub impersonator_id: Option<Uuid>,
+    pub organization_id: Option<Uuid>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub diff: Value,
+    pub request_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_graphql::Object]
+impl AuditLog {
+    #[field]
+    async fn id(&self) -> ID {
+        to_id("AuditLog", &self.id)
+    }
+
+    #[field]
+    async fn actor_id(&self) -> Option<ID> {
+        self.actor_id.as_ref().map(|id| to_id("User", id))
+    }
+
+    #[field]
+    async fn actor_role(&self) -> Option<&str> {
+        self.actor_role.as_deref()
+    }
+
+    #[field]
+    async fn impersonator_id(&self) -> Option<ID> {
+        self.impersonator_id.as_ref().map(|id| to_id("User", id))
+    }
+
+    #[field]
+    async fn action(&self) -> &str {
+        self.action.as_str()
+    }
+
+    #[field]
+    async fn entity_type(&self) -> &str {
+        self.entity_type.as_str()
+    }
+
+    #[field]
+    async fn entity_id(&self) -> &str {
+        self.entity_id.as_str()
+    }
+
+    #[field]
+    async fn diff(&self) -> String {
+        self.diff.to_string()
+    }
+
+    #[field]
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "audit_logs"]
+struct NewAuditLog<'a> {
+    actor_id: Option<Uuid>,
+    actor_role: Option<&'a str>,
+    impersonator_id: Option<Uuid>,
+    organization_id: Option<Uuid>,
+    action: &'a str,
+    entity_type: &'a str,
+    entity_id: &'a str,
+    diff: Value,
+    request_id: Option<&'a str>,
+    ip_address: Option<&'a str>,
+}
+
+pub fn audit<A: Into<Actor>>(
+    conn: &PgConnection,
+    actor: A,
+    action: &str,
+    entity_type: &str,
+    entity_id: &str,
+    diff: Value,
+) -> AuditResult<AuditLog> {
+    let actor = actor.into();
+
+    Ok(diesel::insert_into(audit_logs::table)
+        .values(&NewAuditLog {
+            actor_id: actor.user_id,
+            actor_role: actor.role.as_deref(),
+            impersonator_id: actor.impersonator_id,
+            organization_id: actor.organization_id,
+            action,
+            entity_type,
+            entity_id,
+            diff,
+            request_id: actor.request_id.as_deref(),
+            ip_address: actor.ip_address.as_deref(),
+        })
+        .get_result(conn)?)
+}
+
+pub fn diff<T: Serialize>(before: &T, after: &T) -> AuditResult<Value> {
+    let before = serde_json::to_value(before)?;
+    let after = serde_json::to_value(after)?;
+
+    let (before, after) = match (before, after) {
+        (Value::Object(before), Value::Object(after)) => (before, after),
+        (before, after) if before == after => return Ok(Value::Object(Map::new())),
+        (before, after) => return Ok(serde_json::json!({ "from": before, "to": after })),
+    };
+
+    let mut changes = Map::new();
+
+    for (key, value) in after.iter() {
+        let previous = before.get(key).cloned().unwrap_or(Value::Null);
+
+        if &previous != value {
+            changes.insert(
+                key.to_owned(),
+                serde_json::json!({ "from": previous, "to": value }),
+            );
+        }
+    }
+
+    for (key, value) in before.iter() {
+        if !after.contains_key(key) {
+            changes.insert(
+                key.to_owned(),
+                serde_json::json!({ "from": value, "to": Value::Null }),
+            );
+        }
+    }
+
+    Ok(Value::Object(changes))
+}
+
+fn to_audit_cursor(log: &AuditLog) -> (String, String) {
+    (log.id.to_string(), log.created_at.to_rfc3339())
+}
+
+fn from_audit_cursor(
+    key_value: &str,
+    order_value: &str,
+) -> ConnectionResult<(Uuid, DateTime<Utc>)> {
+    let key_value =
+        Uuid::parse_str(key_value).map_err(|e| ConnectionError::Custom(e.to_string()))?;
+    let order_value = DateTime::parse_from_rfc3339(order_value)
+        .map(DateTime::<Utc>::from)
+        .map_err(|e| ConnectionError::Custom(e.to_string()))?;
+
+    Ok((key_value, order_value))
+}
+
+pub fn audit_trail(
+    conn: &PgConnection,
+    entity_type: &str,
+    entity_id: &str,
+    first: Option<usize>,
+    after: Option<String>,
+    last: Option<usize>,
+    before: Option<String>,
+) -> ConnectionResult<Connection<AuditLog>> {
+    use super::schema::audit_logs::dsl::{created_at, id};
+
+    let table = audit_logs::table
+        .filter(audit_logs::entity_type.eq(entity_type))
+        .filter(audit_logs::entity_id.eq(entity_id))
+        .into_boxed();
+
+    timada_relay::resolve_connection!(
+        AuditLog,
+        conn,
+        table,
+        first,
+        after,
+        last,
+        before,
+        id,
+        created_at,
+        to_audit_cursor,
+        from_audit_cursor
+    )
+}
+
+pub fn actor_trail(
+    conn: &PgConnection,
+    actor_id: Uuid,
+    first: Option<usize>,
+    after: Option<String>,
+    last: Option<usize>,
+    before: Option<String>,
+) -> ConnectionResult<Connection<AuditLog>> {
+    use super::schema::audit_logs::dsl::{created_at, id};
+
+    let table = audit_logs::table
+        .filter(
+            audit_logs::actor_id
+                .eq(actor_id)
+                .or(audit_logs::impersonator_id.eq(actor_id)),
+        )
+        .into_boxed();
+
+    timada_relay::resolve_connection!(
+        AuditLog,
+        conn,
+        table,
+        first,
+        after,
+        last,
+        before,
+        id,
+        created_at,
+        to_audit_cursor,
+        from_audit_cursor
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+    use serde_json::json;
+    use timada_database::testing::test_connection;
+    use uuid::Uuid;
+
+    use super::{actor_trail, audit, audit_trail, diff, to_audit_cursor};
+    use crate::actor::Actor;
+
+    #[derive(Serialize)]
+    struct Todo {
+        text: String,
+        is_done: bool,
+    }
+
+    #[test]
+    fn changes() {
+        let before = Todo {
+            text: "Buy milk".to_owned(),
+            is_done: false,
+        };
+        let after = Todo {
+            text: "Buy milk".to_owned(),
+            is_done: true,
+        };
+
+        assert_eq!(
+            diff(&before, &after).unwrap(),
+            json!({ "is_done": { "from": false, "to": true } })
+        );
+        assert_eq!(diff(&before, &before).unwrap(), json!({}));
+        assert_eq!(
+            diff(&json!({ "a": 1 }), &json!({ "b": 2 })).unwrap(),
+            json!({ "a": { "from": 1, "to": null }, "b": { "from": null, "to": 2 } })
+        );
+    }
+
+    #[test]
+    fn trail_pages() {
+        // Rows of one transaction share CURRENT_TIMESTAMP, pages only differ by id.
+        let conn = test_connection("timada_audit_test");
+        let actor_id = Uuid::new_v4();
+        let other_id = Uuid::new_v4();
+
+        let actor = Actor {
+            user_id: Some(actor_id),
+            ..Actor::system()
+        };
+        let other = Actor {
+            user_id: Some(other_id),
+            ..Actor::system()
+        };
+
+        for _ in 0..3 {
+            audit(&conn, actor.clone(), "update", "todo", "1", json!({})).unwrap();
+            audit(&conn, other.clone(), "update", "todo", "2", json!({})).unwrap();
+        }
+
+        let first = audit_trail(&conn, "todo", "1", Some(1), None, None, None).unwrap();
+        let (key_value, order_value) = to_audit_cursor(&first.nodes[0].2);
+        let after = Some(timada_relay::to_cursor(&key_value, &order_value));
+
+        let page = audit_trail(&conn, "todo", "1", Some(10), after.clone(), None, None).unwrap();
+
+        assert_eq!(page.nodes.len(), 2);
+        assert!(page.nodes.iter().all(|(_, _, log)| log.entity_id == "1"));
+
+        let page = actor_trail(&conn, actor_id, Some(10), after, None, None).unwrap();
+
+        assert_eq!(page.nodes.len(), 2);
+        assert!(page
+            .nodes
+            .iter()
+            .all(|(_, _, log)| log.actor_id == Some(actor_id)));
+    }
+}
@@ -0,0 +1,16 @@
+CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
+
+CREATE TABLE auth_sessions (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  user_id uuid NOT NULL,
+  token_hash VARCHAR(64) NOT NULL UNIQUE,
+  user_data JSONB NOT NULL,
+  user_agent TEXT,
+  ip_address VARCHAR(45),
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  last_seen_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMP NOT NULL,
+  revoked_at TIMESTAMP
+);
+
+CREATE INDEX auth_sessions_user_id_idx ON auth_sessions (user_id);
@@ -0,0 +1,14 @@
+CREATE TABLE auth_refresh_tokens (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  family_id uuid NOT NULL,
+  user_id uuid NOT NULL,
+  token_hash VARCHAR(64) NOT NULL UNIQUE,
+  user_data JSONB NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMP NOT NULL,
+  used_at TIMESTAMP,
+  revoked_at TIMESTAMP
+);
+
+CREATE INDEX auth_refresh_tokens_family_id_idx ON auth_refresh_tokens (family_id);
+CREATE INDEX auth_refresh_tokens_user_id_idx ON auth_refresh_tokens (user_id);
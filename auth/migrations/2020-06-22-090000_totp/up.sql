@@ -0,0 +1,17 @@
+CREATE TABLE auth_totp (
+  user_id uuid PRIMARY KEY,
+  secret VARCHAR(64) NOT NULL,
+  last_used_step BIGINT,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  confirmed_at TIMESTAMP
+);
+
+CREATE TABLE auth_recovery_codes (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  user_id uuid NOT NULL,
+  code_hash VARCHAR(64) NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  used_at TIMESTAMP
+);
+
+CREATE INDEX auth_recovery_codes_user_id_idx ON auth_recovery_codes (user_id);
@@ -0,0 +1,11 @@
+CREATE TABLE auth_magic_links (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  token_hash VARCHAR(64) NOT NULL UNIQUE,
+  email VARCHAR(255) NOT NULL,
+  redirect_to TEXT,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMP NOT NULL,
+  used_at TIMESTAMP
+);
+
+CREATE INDEX auth_magic_links_email_idx ON auth_magic_links (email);
@@ -0,0 +1,15 @@
+CREATE TABLE auth_access_tokens (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  user_id uuid NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  prefix VARCHAR(16) NOT NULL,
+  token_hash VARCHAR(64) NOT NULL UNIQUE,
+  scopes TEXT[] NOT NULL DEFAULT '{}',
+  user_data JSONB NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMP,
+  last_used_at TIMESTAMP,
+  revoked_at TIMESTAMP
+);
+
+CREATE INDEX auth_access_tokens_user_id_idx ON auth_access_tokens (user_id);
@@ -0,0 +1 @@
+ALTER TABLE auth_access_tokens ALTER COLUMN expires_at DROP NOT NULL;
@@ -0,0 +1,3 @@
+UPDATE auth_access_tokens SET expires_at = created_at + INTERVAL '90 days' WHERE expires_at IS NULL;
+
+ALTER TABLE auth_access_tokens ALTER COLUMN expires_at SET NOT NULL;
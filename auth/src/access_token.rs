@@ -0,0 +1,366 @@
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{web, FromRequest, HttpRequest};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use futures::future::LocalBoxFuture;
+use serde_json::Value;
+use timada_database::Pool;
+use timada_http::{Error, User};
+use uuid::Uuid;
+
+use super::crypto::{random_token, sha256_hex};
+use super::error::{AuthError, AuthResult};
+use super::schema::auth_access_tokens;
+use super::user_store::current_user;
+
+pub const ACCESS_TOKEN_PREFIX: &str = "tp_";
+const ACCESS_TOKEN_LEN: usize = 32;
+const DISPLAY_PREFIX_LEN: usize = 8;
+const DEFAULT_TTL_DAYS: i64 = 90;
+
+#[derive(Debug, Clone)]
+pub struct AccessTokenConfig {
+    touch_interval: Duration,
+    scopes: Vec<String>,
+}
+
+impl Default for AccessTokenConfig {
+    fn default() -> Self {
+        AccessTokenConfig {
+            touch_interval: Duration::minutes(1),
+            scopes: Vec::new(),
+        }
+    }
+}
+
+impl AccessTokenConfig {
+    pub fn touch_interval(mut self, touch_interval: Duration) -> Self {
+        self.touch_interval = touch_interval;
+        self
+    }
+
+    // Scopes accepted by the route, a token holding none of them is rejected so routes
+    // without any allowed scope can't be reached with an access token at all.
+    pub fn allow(mut self, scope: &str) -> Self {
+        self.scopes.push(scope.to_owned());
+        self
+    }
+
+    fn is_allowed(&self, token: &AccessToken) -> bool {
+        self.scopes.iter().any(|scope| token.has_scope(scope))
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable)]
+#[table_name = "auth_access_tokens"]
+pub struct AccessToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    pub user_data: Value,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl AccessToken {
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|value| value == scope)
+    }
+
+    // Snapshot of the owner when the token was created.
+    pub fn user(&self) -> AuthResult<User> {
+        serde_json::from_value(self.user_data.clone())
+            .map_err(|e| AuthError::Internal(e.to_string()))
+    }
+
+    // Owner reloaded through the `UserStore`, restricted to the scopes of the token.
+    pub fn current_user(&self) -> AuthResult<User> {
+        let mut user = current_user(self.user()?)?;
+
+        user.claims.insert(
+            "scopes".to_owned(),
+            Value::Array(self.scopes.iter().cloned().map(Value::String).collect()),
+        );
+        user.claims
+            .insert("token_id".to_owned(), Value::String(self.id.to_string()));
+
+        Ok(user)
+    }
+
+    fn should_touch(&self, now: NaiveDateTime, config: &AccessTokenConfig) -> bool {
+        self.last_used_at.map_or(true, |last_used_at| {
+            now - last_used_at >= config.touch_interval
+        })
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "auth_access_tokens"]
+struct NewAccessToken<'a> {
+    user_id: Uuid,
+    name: &'a str,
+    prefix: String,
+    token_hash: String,
+    scopes: &'a [String],
+    user_data: Value,
+    expires_at: NaiveDateTime,
+}
+
+pub fn is_access_token(token: &str) -> bool {
+    token.starts_with(ACCESS_TOKEN_PREFIX)
+}
+
+pub fn create_access_token(
+    conn: &PgConnection,
+    user: &User,
+    name: &str,
+    scopes: &[String],
+    expires_at: Option<NaiveDateTime>,
+) -> AuthResult<(String, AccessToken)> {
+    if scopes.is_empty() {
+        return Err(AuthError::InvalidToken(
+            "Access tokens need at least one scope".to_owned(),
+        ));
+    }
+
+    let expires_at =
+        expires_at.unwrap_or_else(|| Utc::now().naive_utc() + Duration::days(DEFAULT_TTL_DAYS));
+    let token = format!("{}{}", ACCESS_TOKEN_PREFIX, random_token(ACCESS_TOKEN_LEN));
+    let user_data = serde_json::to_value(user).map_err(|e| AuthError::Internal(e.to_string()))?;
+
+    let access_token = diesel::insert_into(auth_access_tokens::table)
+        .values(&NewAccessToken {
+            user_id: user.id,
+            name,
+            prefix: token[..ACCESS_TOKEN_PREFIX.len() + DISPLAY_PREFIX_LEN].to_owned(),
+            token_hash: sha256_hex(&token),
+            scopes,
+            user_data,
+            expires_at,
+        })
+        .get_result(conn)?;
+
+    Ok((token, access_token))
+}
+
+pub fn find_access_token(
+    conn: &PgConnection,
+    token: &str,
+    config: &AccessTokenConfig,
+) -> AuthResult<AccessToken> {
+    if !is_access_token(token) {
+        return Err(AuthError::InvalidToken("Invalid access token".to_owned()));
+    }
+
+    let now = Utc::now().naive_utc();
+
+    let access_token: AccessToken = auth_access_tokens::table
+        .filter(auth_access_tokens::token_hash.eq(sha256_hex(token)))
+        .first(conn)
+        .optional()?
+        .filter(|access_token: &AccessToken| access_token.is_active(now))
+        .ok_or_else(|| AuthError::InvalidToken("Invalid access token".to_owned()))?;
+
+    if !access_token.should_touch(now, config) {
+        return Ok(access_token);
+    }
+
+    Ok(diesel::update(&access_token)
+        .set(auth_access_tokens::last_used_at.eq(now))
+        .get_result(conn)?)
+}
+
+pub fn list_access_tokens(conn: &PgConnection, user_id: Uuid) -> AuthResult<Vec<AccessToken>> {
+    Ok(auth_access_tokens::table
+        .filter(auth_access_tokens::user_id.eq(user_id))
+        .filter(auth_access_tokens::revoked_at.is_null())
+        .order(auth_access_tokens::created_at.desc())
+        .load(conn)?)
+}
+
+pub fn revoke_access_token(conn: &PgConnection, user_id: Uuid, id: Uuid) -> AuthResult<bool> {
+    let updated = diesel::update(
+        auth_access_tokens::table
+            .find(id)
+            .filter(auth_access_tokens::user_id.eq(user_id))
+            .filter(auth_access_tokens::revoked_at.is_null()),
+    )
+    .set(auth_access_tokens::revoked_at.eq(Utc::now().naive_utc()))
+    .execute(conn)?;
+
+    Ok(updated > 0)
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            let mut parts = value.splitn(2, ' ');
+            match (parts.next(), parts.next()) {
+                (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer") => {
+                    Some(token.trim().to_owned())
+                }
+                _ => None,
+            }
+        })
+        .filter(|token| is_access_token(token))
+}
+
+pub struct TokenUser {
+    pub token: AccessToken,
+    pub user: User,
+}
+
+impl TokenUser {
+    pub fn require_scope(&self, scope: &str) -> Result<(), Error> {
+        if !self.token.has_scope(scope) {
+            return Err(Error::Forbidden(format!("Missing scope {}", scope)));
+        }
+
+        Ok(())
+    }
+}
+
+impl FromRequest for TokenUser {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    type Config = AccessTokenConfig;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let config = req.app_data::<Self::Config>().cloned().unwrap_or_default();
+        let token = bearer_token(req);
+        let pool = req
+            .app_data::<web::Data<Pool>>()
+            .map(|pool| pool.get_ref().clone());
+
+        Box::pin(async move {
+            let token = token
+                .ok_or_else(|| AuthError::InvalidToken("Missing access token".to_owned()))
+                .map_err(Error::from)?;
+            let pool = pool.ok_or(Error::InternalServerError)?;
+
+            let (token, user) = web::block(move || {
+                let conn = pool.get().map_err(|e| AuthError::Internal(e.to_string()))?;
+                let token = find_access_token(&conn, &token, &config)?;
+
+                if !config.is_allowed(&token) {
+                    return Err(AuthError::Forbidden("Missing scope".to_owned()));
+                }
+
+                let user = token.current_user()?;
+
+                Ok((token, user))
+            })
+            .await
+            .map_err(|e| Error::from(AuthError::from(e)))?;
+
+            Ok(TokenUser { token, user })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+    use timada_http::testing::ContextBuilder;
+    use uuid::Uuid;
+
+    use super::{bearer_token, AccessToken, AccessTokenConfig};
+
+    fn access_token() -> AccessToken {
+        let user = ContextBuilder::new().build().user.unwrap();
+
+        AccessToken {
+            id: Uuid::new_v4(),
+            user_id: user.id,
+            name: "ci".to_owned(),
+            prefix: "tp_abcdefgh".to_owned(),
+            token_hash: String::new(),
+            scopes: vec!["read".to_owned()],
+            user_data: serde_json::to_value(&user).unwrap(),
+            created_at: Utc::now().naive_utc(),
+            expires_at: Utc::now().naive_utc() + Duration::days(1),
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn active() {
+        let now = Utc::now().naive_utc();
+        let mut token = access_token();
+
+        assert!(token.is_active(now));
+
+        token.expires_at = now - Duration::seconds(1);
+        assert!(!token.is_active(now));
+
+        token.expires_at = now + Duration::days(1);
+        token.revoked_at = Some(now);
+        assert!(!token.is_active(now));
+    }
+
+    #[test]
+    fn restricted_user() {
+        let token = access_token();
+        let user = token.current_user().unwrap();
+
+        assert_eq!(user.id, token.user_id);
+        assert_eq!(user.claims.get("scopes"), Some(&json!(["read"])));
+        assert!(token.has_scope("read"));
+        assert!(!token.has_scope("write"));
+    }
+
+    #[test]
+    fn allowed_scopes() {
+        let token = access_token();
+
+        assert!(!AccessTokenConfig::default().is_allowed(&token));
+        assert!(!AccessTokenConfig::default()
+            .allow("write")
+            .is_allowed(&token));
+        assert!(AccessTokenConfig::default()
+            .allow("write")
+            .allow("read")
+            .is_allowed(&token));
+    }
+
+    #[test]
+    fn touch() {
+        let now = Utc::now().naive_utc();
+        let config = AccessTokenConfig::default();
+        let mut token = access_token();
+
+        assert!(token.should_touch(now, &config));
+
+        token.last_used_at = Some(now - Duration::seconds(10));
+        assert!(!token.should_touch(now, &config));
+    }
+
+    #[test]
+    fn bearer() {
+        let req = TestRequest::default()
+            .header("authorization", "Bearer tp_secret")
+            .to_http_request();
+        assert_eq!(bearer_token(&req), Some("tp_secret".to_owned()));
+
+        let req = TestRequest::default()
+            .header("authorization", "Bearer eyJhbGciOi")
+            .to_http_request();
+        assert_eq!(bearer_token(&req), None);
+    }
+}
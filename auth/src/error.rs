@@ -0,0 +1,70 @@
+use actix_web::error::BlockingError;
+use diesel::result::Error as DieselError;
+use timada_http::Error;
+
+#[derive(Debug, PartialEq, Error)]
+pub enum AuthError {
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+
+    #[error("Invalid session")]
+    InvalidSession,
+
+    #[error("Invalid token: {0}")]
+    InvalidToken(String),
+
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("Invalid redirect {0}")]
+    InvalidRedirect(String),
+
+    #[error("Refresh token reused")]
+    TokenReused,
+
+    #[error("Provider error: {0}")]
+    Provider(String),
+
+    #[error("Password hashing failed: {0}")]
+    Hash(String),
+
+    #[error("{0}")]
+    Database(String),
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl From<DieselError> for AuthError {
+    fn from(e: DieselError) -> AuthError {
+        AuthError::Database(e.to_string())
+    }
+}
+
+impl From<BlockingError<AuthError>> for AuthError {
+    fn from(e: BlockingError<AuthError>) -> AuthError {
+        match e {
+            BlockingError::Error(e) => e,
+            BlockingError::Canceled => AuthError::Internal("Operation canceled".to_owned()),
+        }
+    }
+}
+
+impl From<AuthError> for Error {
+    fn from(e: AuthError) -> Error {
+        match e {
+            AuthError::InvalidCredentials
+            | AuthError::InvalidSession
+            | AuthError::InvalidToken(_)
+            | AuthError::TokenReused => Error::Unauthorized(e.to_string()),
+            AuthError::Forbidden(message) => Error::Forbidden(message),
+            AuthError::InvalidRedirect(_) => Error::BadRequest(e.to_string()),
+            AuthError::Hash(message)
+            | AuthError::Provider(message)
+            | AuthError::Database(message)
+            | AuthError::Internal(message) => Error::Internal(message),
+        }
+    }
+}
+
+pub type AuthResult<T> = Result<T, AuthError>;
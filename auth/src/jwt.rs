@@ -0,0 +1,293 @@
+use chrono::{Duration, Utc};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use serde::de::DeserializeOwned;
+use timada_http::User;
+
+use super::error::{AuthError, AuthResult};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub user: User,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    pub kid: String,
+    #[serde(rename = "use")]
+    pub use_: String,
+    pub alg: String,
+    pub n: String,
+    pub e: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+#[derive(Clone)]
+enum KeyMaterial {
+    Rsa {
+        encoding: Option<EncodingKey>,
+        n: String,
+        e: String,
+    },
+    Hmac(Vec<u8>),
+}
+
+#[derive(Clone)]
+pub struct JwtKey {
+    kid: String,
+    material: KeyMaterial,
+}
+
+impl JwtKey {
+    pub fn rsa(kid: &str, private_pem: &[u8], n: &str, e: &str) -> AuthResult<Self> {
+        let encoding = EncodingKey::from_rsa_pem(private_pem)
+            .map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+
+        Ok(JwtKey {
+            kid: kid.to_owned(),
+            material: KeyMaterial::Rsa {
+                encoding: Some(encoding),
+                n: n.to_owned(),
+                e: e.to_owned(),
+            },
+        })
+    }
+
+    pub fn rsa_public(kid: &str, n: &str, e: &str) -> Self {
+        JwtKey {
+            kid: kid.to_owned(),
+            material: KeyMaterial::Rsa {
+                encoding: None,
+                n: n.to_owned(),
+                e: e.to_owned(),
+            },
+        }
+    }
+
+    pub fn hmac(kid: &str, secret: &[u8]) -> Self {
+        JwtKey {
+            kid: kid.to_owned(),
+            material: KeyMaterial::Hmac(secret.to_vec()),
+        }
+    }
+
+    pub fn from_jwks(jwks: &Jwks) -> Vec<Self> {
+        jwks.keys
+            .iter()
+            .filter(|jwk| jwk.kty == "RSA")
+            .map(|jwk| JwtKey::rsa_public(&jwk.kid, &jwk.n, &jwk.e))
+            .collect()
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        match self.material {
+            KeyMaterial::Rsa { .. } => Algorithm::RS256,
+            KeyMaterial::Hmac(_) => Algorithm::HS256,
+        }
+    }
+
+    fn encoding_key(&self) -> AuthResult<EncodingKey> {
+        match &self.material {
+            KeyMaterial::Rsa {
+                encoding: Some(encoding),
+                ..
+            } => Ok(encoding.clone()),
+            KeyMaterial::Rsa { encoding: None, .. } => Err(AuthError::InvalidToken(format!(
+                "key {} can't sign tokens",
+                self.kid
+            ))),
+            KeyMaterial::Hmac(secret) => Ok(EncodingKey::from_secret(secret)),
+        }
+    }
+
+    fn decoding_key(&self) -> DecodingKey<'_> {
+        match &self.material {
+            KeyMaterial::Rsa { n, e, .. } => DecodingKey::from_rsa_components(n, e),
+            KeyMaterial::Hmac(secret) => DecodingKey::from_secret(secret),
+        }
+    }
+
+    fn jwk(&self) -> Option<Jwk> {
+        match &self.material {
+            KeyMaterial::Rsa { n, e, .. } => Some(Jwk {
+                kty: "RSA".to_owned(),
+                kid: self.kid.to_owned(),
+                use_: "sig".to_owned(),
+                alg: "RS256".to_owned(),
+                n: n.to_owned(),
+                e: e.to_owned(),
+            }),
+            KeyMaterial::Hmac(_) => None,
+        }
+    }
+}
+
+// Checks the signature, `iss`, `aud` and `exp` of a token signed by one of `keys`.
+pub(crate) fn verify_with<T: DeserializeOwned>(
+    keys: &[JwtKey],
+    token: &str,
+    issuer: &str,
+    audience: &str,
+) -> AuthResult<T> {
+    let header = decode_header(token).map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+    let kid = header
+        .kid
+        .ok_or_else(|| AuthError::InvalidToken("Missing kid".to_owned()))?;
+
+    let key = keys
+        .iter()
+        .find(|key| key.kid == kid)
+        .ok_or_else(|| AuthError::InvalidToken(format!("Unknown kid {}", kid)))?;
+
+    let mut validation = Validation::new(key.algorithm());
+    validation.iss = Some(issuer.to_owned());
+    validation.set_audience(&[audience]);
+
+    decode::<T>(token, &key.decoding_key(), &validation)
+        .map(|data| data.claims)
+        .map_err(|e| AuthError::InvalidToken(e.to_string()))
+}
+
+#[derive(Clone)]
+pub struct JwtService {
+    issuer: String,
+    audience: String,
+    ttl: Duration,
+    keys: Vec<JwtKey>,
+}
+
+impl JwtService {
+    pub fn new(issuer: &str, audience: &str) -> Self {
+        JwtService {
+            issuer: issuer.to_owned(),
+            audience: audience.to_owned(),
+            ttl: Duration::minutes(15),
+            keys: Vec::new(),
+        }
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn key(mut self, key: JwtKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    pub fn rotate(mut self, key: JwtKey) -> Self {
+        self.keys.insert(0, key);
+        self
+    }
+
+    pub fn issue(&self, user: &User) -> AuthResult<String> {
+        let key = self
+            .keys
+            .first()
+            .ok_or_else(|| AuthError::InvalidToken("No signing key".to_owned()))?;
+
+        let now = Utc::now();
+        let claims = Claims {
+            sub: user.id.to_string(),
+            iss: self.issuer.to_owned(),
+            aud: self.audience.to_owned(),
+            iat: now.timestamp(),
+            exp: (now + self.ttl).timestamp(),
+            user: user.clone(),
+        };
+
+        let mut header = Header::new(key.algorithm());
+        header.kid = Some(key.kid.to_owned());
+
+        encode(&header, &claims, &key.encoding_key()?)
+            .map_err(|e| AuthError::InvalidToken(e.to_string()))
+    }
+
+    pub fn verify(&self, token: &str) -> AuthResult<Claims> {
+        verify_with(&self.keys, token, &self.issuer, &self.audience)
+    }
+
+    pub fn jwks(&self) -> Jwks {
+        Jwks {
+            keys: self.keys.iter().filter_map(|key| key.jwk()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_http::{User, UserRole, UserState};
+    use uuid::Uuid;
+
+    use super::{JwtKey, JwtService};
+    use crate::error::AuthError;
+
+    fn user() -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: None,
+            username: Some("john".to_owned()),
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        }
+    }
+
+    fn service() -> JwtService {
+        JwtService::new("https://gateway.timada.co", "todos").key(JwtKey::hmac("v1", b"secret"))
+    }
+
+    #[test]
+    fn issue_and_verify() {
+        let user = user();
+        let token = service().issue(&user).unwrap();
+        let claims = service().verify(&token).unwrap();
+
+        assert_eq!(claims.sub, user.id.to_string());
+        assert_eq!(claims.user, user);
+    }
+
+    #[test]
+    fn verify_rotated() {
+        let token = service().issue(&user()).unwrap();
+        let rotated = service().rotate(JwtKey::hmac("v2", b"rotated"));
+
+        assert!(rotated.verify(&token).is_ok());
+        assert!(rotated.verify(&rotated.issue(&user()).unwrap()).is_ok());
+        assert!(service().verify(&rotated.issue(&user()).unwrap()).is_err());
+    }
+
+    #[test]
+    fn verify_audience() {
+        let token = service().issue(&user()).unwrap();
+        let other = JwtService::new("https://gateway.timada.co", "billing")
+            .key(JwtKey::hmac("v1", b"secret"));
+
+        match other.verify(&token) {
+            Err(AuthError::InvalidToken(_)) => {}
+            res => panic!("unexpected {:?}", res.map(|claims| claims.sub)),
+        }
+    }
+
+    #[test]
+    fn jwks() {
+        let service = service().key(JwtKey::rsa_public("rsa-1", "modulus", "AQAB"));
+        let jwks = service.jwks();
+
+        assert_eq!(jwks.keys.len(), 1);
+        assert_eq!(jwks.keys[0].kid, "rsa-1");
+        assert_eq!(JwtKey::from_jwks(&jwks).len(), 1);
+    }
+}
@@ -0,0 +1,58 @@
+#[macro_use]
+extern crate diesel;
+
+#[macro_use]
+extern crate diesel_migrations;
+
+#[macro_use]
+extern crate serde;
+
+#[macro_use]
+extern crate thiserror;
+
+mod access_token;
+mod crypto;
+mod error;
+mod jwt;
+mod magic_link;
+mod migration;
+mod oidc;
+mod password;
+mod refresh;
+mod schema;
+mod session;
+mod totp;
+mod user_store;
+
+pub use crate::access_token::{
+    create_access_token, find_access_token, is_access_token, list_access_tokens,
+    revoke_access_token, AccessToken, AccessTokenConfig, TokenUser, ACCESS_TOKEN_PREFIX,
+};
+pub use crate::crypto::{constant_time_eq, random_token, sha256_hex};
+pub use crate::error::{AuthError, AuthResult};
+pub use crate::jwt::{Claims, Jwk, Jwks, JwtKey, JwtService};
+pub use crate::magic_link::{
+    confirm_magic_link, consume_magic_link, create_magic_link, purge_magic_links, send_magic_link,
+    MagicLink, MagicLinkConfig, MagicLinkLogin, MagicLinkMailer,
+};
+pub use crate::migration::migrate;
+pub use crate::oidc::{
+    code_challenge, verify_state, AuthorizationRequest, OidcClient, OidcProvider, OidcTokens,
+};
+pub use crate::password::{
+    hash_password, needs_rehash, verify_password, PasswordConfig, PasswordHash,
+};
+pub use crate::refresh::{
+    exchange_refresh_token, issue_refresh_token, revoke_refresh_family, rotate_refresh_token,
+    RefreshConfig, RefreshToken, TokenPair,
+};
+pub use crate::session::{
+    create_session, find_session, revoke_session, revoke_user_sessions, DeviceInfo, Session,
+    SessionConfig, SessionUser,
+};
+pub use crate::totp::{
+    confirm_totp, disable_totp, enroll_totp, generate_recovery_codes, generate_totp_secret,
+    is_totp_enabled, totp_code, totp_uri, use_recovery_code, verify_totp, verify_totp_code,
+    TotpConfig, TotpDevice, TotpEnrollment,
+};
+pub use crate::user_store::{find_user, set_user_store, UserStore};
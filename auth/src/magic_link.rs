@@ -0,0 +1,334 @@
+use actix_web::dev::Payload;
+use actix_web::http::{header, Method};
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use futures::future::LocalBoxFuture;
+use std::collections::HashMap;
+use std::sync::Arc;
+use timada_database::Pool;
+use timada_http::Error;
+use url::Url;
+use uuid::Uuid;
+
+use super::crypto::{random_token, sha256_hex};
+use super::error::{AuthError, AuthResult};
+use super::schema::auth_magic_links;
+
+const MAGIC_LINK_TOKEN_LEN: usize = 32;
+
+#[async_trait::async_trait]
+pub trait MagicLinkMailer: Send + Sync {
+    async fn send(&self, email: &str, link: &str, expires_at: NaiveDateTime) -> AuthResult<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct MagicLinkConfig {
+    ttl: Duration,
+    base_url: String,
+    param_name: String,
+    allowed_origins: Vec<String>,
+}
+
+impl Default for MagicLinkConfig {
+    fn default() -> Self {
+        MagicLinkConfig {
+            ttl: Duration::minutes(15),
+            base_url: "http://localhost:8080/auth/magic-link".to_owned(),
+            param_name: "token".to_owned(),
+            allowed_origins: Vec::new(),
+        }
+    }
+}
+
+impl MagicLinkConfig {
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_owned();
+        self
+    }
+
+    pub fn param_name(mut self, param_name: &str) -> Self {
+        self.param_name = param_name.to_owned();
+        self
+    }
+
+    // Origins `redirect_to` may point to, relative paths are always allowed.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        let origin = Url::parse(origin)
+            .map(|url| url.origin().ascii_serialization())
+            .unwrap_or_else(|_| origin.to_owned());
+
+        self.allowed_origins.push(origin);
+        self
+    }
+
+    fn is_allowed_redirect(&self, redirect_to: &str) -> bool {
+        if redirect_to.starts_with('/') {
+            return !redirect_to.starts_with("//")
+                && !redirect_to.contains('\\')
+                && !redirect_to.chars().any(char::is_control);
+        }
+
+        match Url::parse(redirect_to) {
+            Ok(url) if url.scheme() == "https" || url.scheme() == "http" => self
+                .allowed_origins
+                .contains(&url.origin().ascii_serialization()),
+            _ => false,
+        }
+    }
+
+    pub fn link(&self, token: &str) -> AuthResult<String> {
+        let mut url = Url::parse(&self.base_url).map_err(|e| AuthError::Internal(e.to_string()))?;
+        url.query_pairs_mut().append_pair(&self.param_name, token);
+
+        Ok(url.into_string())
+    }
+
+    fn token(&self, req: &HttpRequest) -> Option<String> {
+        url::form_urlencoded::parse(req.query_string().as_bytes())
+            .find(|(name, _)| name == &self.param_name)
+            .map(|(_, value)| value.into_owned())
+    }
+}
+
+#[derive(Debug, Clone, Queryable)]
+pub struct MagicLink {
+    pub id: Uuid,
+    pub token_hash: String,
+    pub email: String,
+    pub redirect_to: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[table_name = "auth_magic_links"]
+struct NewMagicLink<'a> {
+    token_hash: String,
+    email: &'a str,
+    redirect_to: Option<&'a str>,
+    expires_at: NaiveDateTime,
+}
+
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+pub fn create_magic_link(
+    conn: &PgConnection,
+    email: &str,
+    redirect_to: Option<&str>,
+    config: &MagicLinkConfig,
+) -> AuthResult<(String, MagicLink)> {
+    if let Some(redirect_to) = redirect_to {
+        if !config.is_allowed_redirect(redirect_to) {
+            return Err(AuthError::InvalidRedirect(redirect_to.to_owned()));
+        }
+    }
+
+    let token = random_token(MAGIC_LINK_TOKEN_LEN);
+    let email = normalize_email(email);
+
+    let magic_link = diesel::insert_into(auth_magic_links::table)
+        .values(&NewMagicLink {
+            token_hash: sha256_hex(&token),
+            email: &email,
+            redirect_to,
+            expires_at: Utc::now().naive_utc() + config.ttl,
+        })
+        .get_result(conn)?;
+
+    Ok((config.link(&token)?, magic_link))
+}
+
+pub fn consume_magic_link(conn: &PgConnection, token: &str) -> AuthResult<MagicLink> {
+    let now = Utc::now().naive_utc();
+
+    diesel::update(
+        auth_magic_links::table
+            .filter(auth_magic_links::token_hash.eq(sha256_hex(token)))
+            .filter(auth_magic_links::used_at.is_null())
+            .filter(auth_magic_links::expires_at.gt(now)),
+    )
+    .set(auth_magic_links::used_at.eq(now))
+    .get_result(conn)
+    .optional()?
+    .ok_or_else(|| AuthError::InvalidToken("Invalid or expired magic link".to_owned()))
+}
+
+pub fn purge_magic_links(conn: &PgConnection) -> AuthResult<usize> {
+    Ok(diesel::delete(
+        auth_magic_links::table.filter(auth_magic_links::expires_at.le(Utc::now().naive_utc())),
+    )
+    .execute(conn)?)
+}
+
+pub async fn send_magic_link(
+    pool: Pool,
+    mailer: Arc<dyn MagicLinkMailer>,
+    email: String,
+    redirect_to: Option<String>,
+    config: MagicLinkConfig,
+) -> AuthResult<()> {
+    let (link, magic_link) = web::block(move || {
+        let conn = pool.get().map_err(|e| AuthError::Internal(e.to_string()))?;
+        create_magic_link(&conn, &email, redirect_to.as_deref(), &config)
+    })
+    .await?;
+
+    mailer
+        .send(&magic_link.email, &link, magic_link.expires_at)
+        .await
+}
+
+fn confirm_page(param_name: &str, token: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+  <head><meta charset="utf-8"><title>Sign in</title></head>
+  <body>
+    <form method="post">
+      <input type="hidden" name="{}" value="{}">
+      <button type="submit">Sign in</button>
+    </form>
+  </body>
+</html>"#,
+        param_name, token
+    )
+}
+
+// GET handler of the link. Mail scanners and link previews follow links, so the token is
+// only consumed by the POST of this page through `MagicLinkLogin`.
+pub async fn confirm_magic_link(req: HttpRequest) -> HttpResponse {
+    let config = req
+        .app_data::<MagicLinkConfig>()
+        .cloned()
+        .unwrap_or_default();
+
+    match config.token(&req).filter(|token| is_token(token)) {
+        Some(token) => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .header(header::CACHE_CONTROL, "no-store")
+            .header(header::REFERRER_POLICY, "no-referrer")
+            .body(confirm_page(&config.param_name, &token)),
+        None => HttpResponse::BadRequest().finish(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MagicLinkLogin {
+    pub email: String,
+    pub redirect_to: Option<String>,
+}
+
+impl FromRequest for MagicLinkLogin {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    type Config = MagicLinkConfig;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = req.app_data::<Self::Config>().cloned().unwrap_or_default();
+        let is_post = req.method() == Method::POST;
+        let form = web::Form::<HashMap<String, String>>::from_request(req, payload);
+        let pool = req
+            .app_data::<web::Data<Pool>>()
+            .map(|pool| pool.get_ref().clone());
+
+        Box::pin(async move {
+            if !is_post {
+                return Err(
+                    Error::BadRequest("Magic links are confirmed by a POST".to_owned()).into(),
+                );
+            }
+
+            let token = form
+                .await?
+                .get(&config.param_name)
+                .cloned()
+                .ok_or_else(|| AuthError::InvalidToken("Missing magic link token".to_owned()))
+                .map_err(Error::from)?;
+            let pool = pool.ok_or(Error::InternalServerError)?;
+
+            let magic_link = web::block(move || {
+                let conn = pool.get().map_err(|e| AuthError::Internal(e.to_string()))?;
+                consume_magic_link(&conn, &token)
+            })
+            .await
+            .map_err(|e| Error::from(AuthError::from(e)))?;
+
+            Ok(MagicLinkLogin {
+                email: magic_link.email,
+                redirect_to: magic_link
+                    .redirect_to
+                    .filter(|redirect_to| config.is_allowed_redirect(redirect_to)),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::{confirm_page, is_token, normalize_email, MagicLinkConfig};
+
+    #[test]
+    fn link() {
+        let config = MagicLinkConfig::default().base_url("https://app.timada.co/login?next=home");
+
+        assert_eq!(
+            config.link("abc-_123").unwrap(),
+            "https://app.timada.co/login?next=home&token=abc-_123"
+        );
+    }
+
+    #[test]
+    fn token() {
+        let config = MagicLinkConfig::default().param_name("t");
+        let req = TestRequest::with_uri("/auth/magic-link?next=home&t=abc").to_http_request();
+
+        assert_eq!(config.token(&req), Some("abc".to_owned()));
+        assert_eq!(MagicLinkConfig::default().token(&req), None);
+    }
+
+    #[test]
+    fn redirect() {
+        let config = MagicLinkConfig::default().allow_origin("https://app.timada.co/");
+
+        assert!(config.is_allowed_redirect("/todos?filter=done"));
+        assert!(config.is_allowed_redirect("https://app.timada.co/todos"));
+        assert!(!config.is_allowed_redirect("//evil.co/todos"));
+        assert!(!config.is_allowed_redirect("/\\evil.co"));
+        assert!(!config.is_allowed_redirect("https://evil.co/todos"));
+        assert!(!config.is_allowed_redirect("https://app.timada.co.evil.co/"));
+        assert!(!config.is_allowed_redirect("javascript:alert(1)"));
+        assert!(!config.is_allowed_redirect("todos"));
+    }
+
+    #[test]
+    fn confirm() {
+        assert!(is_token("abc-_123"));
+        assert!(!is_token("abc\"><script>"));
+        assert!(!is_token(""));
+        assert!(confirm_page("token", "abc").contains(r#"name="token" value="abc""#));
+    }
+
+    #[test]
+    fn email() {
+        assert_eq!(normalize_email(" John@Timada.co "), "john@timada.co");
+    }
+}
@@ -0,0 +1,568 @@
+use actix_web::client::Client;
+use actix_web::http::header;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use timada_http::{User, UserRole, UserState};
+use timada_util::config::Layers;
+use timada_util::env::{parse_list, EnvResult};
+use timada_util::secret::Secret;
+use url::Url;
+use uuid::Uuid;
+
+use super::crypto::{constant_time_eq, random_token};
+use super::error::{AuthError, AuthResult};
+use super::jwt::{verify_with, Jwks, JwtKey};
+
+const STATE_LEN: usize = 16;
+const NONCE_LEN: usize = 16;
+const CODE_VERIFIER_LEN: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct OidcProvider {
+    pub name: String,
+    pub client_id: String,
+    pub client_secret: Secret<String>,
+    pub redirect_url: String,
+    pub authorize_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    pub issuer: Option<String>,
+    pub jwks_url: Option<String>,
+    pub scopes: Vec<String>,
+    pub subject_claim: String,
+    pub email_claim: String,
+    pub email_verified_claim: Option<String>,
+    pub username_claim: String,
+}
+
+struct Preset {
+    authorize_url: &'static str,
+    token_url: &'static str,
+    userinfo_url: &'static str,
+    issuer: Option<&'static str>,
+    jwks_url: Option<&'static str>,
+    scopes: &'static str,
+    subject_claim: &'static str,
+    email_verified_claim: &'static str,
+    username_claim: &'static str,
+}
+
+fn preset(name: &str) -> Option<Preset> {
+    match name {
+        "google" => Some(Preset {
+            authorize_url: "https://accounts.google.com/o/oauth2/v2/auth",
+            token_url: "https://oauth2.googleapis.com/token",
+            userinfo_url: "https://openidconnect.googleapis.com/v1/userinfo",
+            issuer: Some("https://accounts.google.com"),
+            jwks_url: Some("https://www.googleapis.com/oauth2/v3/certs"),
+            scopes: "openid,email,profile",
+            subject_claim: "sub",
+            email_verified_claim: "email_verified",
+            username_claim: "name",
+        }),
+        "github" => Some(Preset {
+            authorize_url: "https://github.com/login/oauth/authorize",
+            token_url: "https://github.com/login/oauth/access_token",
+            userinfo_url: "https://api.github.com/user",
+            issuer: None,
+            jwks_url: None,
+            scopes: "read:user,user:email",
+            subject_claim: "id",
+            // Only a verified address can be made public on GitHub.
+            email_verified_claim: "",
+            username_claim: "login",
+        }),
+        _ => None,
+    }
+}
+
+impl OidcProvider {
+    pub fn from_env(name: &str) -> EnvResult<Self> {
+        Self::from_layers(name, &Layers::env())
+    }
+
+    pub fn from_layers(name: &str, layers: &Layers) -> EnvResult<Self> {
+        let name = name.to_lowercase();
+        let prefix = format!("OIDC_{}_", name.to_uppercase());
+        let preset = preset(&name);
+        let var = |key: &str, default: Option<&str>| {
+            layers.try_var_parsed::<String>(&format!("{}{}", prefix, key), default)
+        };
+
+        let scopes = parse_list(&var(
+            "SCOPES",
+            Some(preset.as_ref().map(|p| p.scopes).unwrap_or("openid,email")),
+        )?);
+
+        // The id_token of an openid provider is verified against its keys.
+        let (issuer, jwks_url) = if scopes.iter().any(|scope| scope == "openid") {
+            (
+                Some(var("ISSUER", preset.as_ref().and_then(|p| p.issuer))?),
+                Some(var("JWKS_URL", preset.as_ref().and_then(|p| p.jwks_url))?),
+            )
+        } else {
+            (None, None)
+        };
+
+        Ok(OidcProvider {
+            client_id: var("CLIENT_ID", None)?,
+            client_secret: Secret::new(var("CLIENT_SECRET", None)?),
+            redirect_url: var("REDIRECT_URL", None)?,
+            authorize_url: var("AUTHORIZE_URL", preset.as_ref().map(|p| p.authorize_url))?,
+            token_url: var("TOKEN_URL", preset.as_ref().map(|p| p.token_url))?,
+            userinfo_url: var("USERINFO_URL", preset.as_ref().map(|p| p.userinfo_url))?,
+            issuer,
+            jwks_url,
+            scopes,
+            subject_claim: var(
+                "SUBJECT_CLAIM",
+                Some(preset.as_ref().map(|p| p.subject_claim).unwrap_or("sub")),
+            )?,
+            email_claim: var("EMAIL_CLAIM", Some("email"))?,
+            email_verified_claim: Some(var(
+                "EMAIL_VERIFIED_CLAIM",
+                Some(
+                    preset
+                        .as_ref()
+                        .map(|p| p.email_verified_claim)
+                        .unwrap_or("email_verified"),
+                ),
+            )?)
+            .filter(|claim| !claim.is_empty()),
+            username_claim: var(
+                "USERNAME_CLAIM",
+                Some(
+                    preset
+                        .as_ref()
+                        .map(|p| p.username_claim)
+                        .unwrap_or("preferred_username"),
+                ),
+            )?,
+            name,
+        })
+    }
+
+    fn is_openid(&self) -> bool {
+        self.scopes.iter().any(|scope| scope == "openid")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcTokens {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: Option<i64>,
+    pub refresh_token: Option<String>,
+    pub id_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+}
+
+pub fn code_challenge(code_verifier: &str) -> String {
+    base64::encode_config(
+        &Sha256::digest(code_verifier.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    )
+}
+
+pub fn verify_state(expected: &str, received: &str) -> AuthResult<()> {
+    if !constant_time_eq(expected.as_bytes(), received.as_bytes()) {
+        return Err(AuthError::InvalidToken("Invalid state".to_owned()));
+    }
+
+    Ok(())
+}
+
+fn verify_id_token(
+    keys: &[JwtKey],
+    id_token: &str,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+) -> AuthResult<IdTokenClaims> {
+    let claims: IdTokenClaims = verify_with(keys, id_token, issuer, client_id)?;
+
+    match claims.nonce.as_ref() {
+        Some(value) if constant_time_eq(value.as_bytes(), nonce.as_bytes()) => Ok(claims),
+        _ => Err(AuthError::InvalidToken("Invalid nonce".to_owned())),
+    }
+}
+
+fn claim_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.to_owned()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[derive(Clone)]
+pub struct OidcClient {
+    provider: OidcProvider,
+}
+
+impl OidcClient {
+    pub fn new(provider: OidcProvider) -> Self {
+        OidcClient { provider }
+    }
+
+    pub fn provider(&self) -> &OidcProvider {
+        &self.provider
+    }
+
+    pub fn authorize(&self) -> AuthResult<AuthorizationRequest> {
+        let state = random_token(STATE_LEN);
+        let nonce = random_token(NONCE_LEN);
+        let code_verifier = random_token(CODE_VERIFIER_LEN);
+
+        let mut url = Url::parse(&self.provider.authorize_url)
+            .map_err(|e| AuthError::Internal(e.to_string()))?;
+
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.provider.client_id)
+            .append_pair("redirect_uri", &self.provider.redirect_url)
+            .append_pair("scope", &self.provider.scopes.join(" "))
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce)
+            .append_pair("code_challenge", &code_challenge(&code_verifier))
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(AuthorizationRequest {
+            url: url.into_string(),
+            state,
+            nonce,
+            code_verifier,
+        })
+    }
+
+    pub async fn exchange_code(
+        &self,
+        request: &AuthorizationRequest,
+        code: &str,
+        state: &str,
+    ) -> AuthResult<OidcTokens> {
+        verify_state(&request.state, state)?;
+
+        let params: [(&str, &str); 6] = [
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.provider.redirect_url),
+            ("client_id", &self.provider.client_id),
+            ("client_secret", self.provider.client_secret.expose()),
+            ("code_verifier", &request.code_verifier),
+        ];
+
+        let mut res = Client::default()
+            .post(&self.provider.token_url)
+            .header(header::ACCEPT, "application/json")
+            .send_form(&params)
+            .await
+            .map_err(|e| AuthError::Provider(e.to_string()))?;
+
+        if !res.status().is_success() {
+            return Err(AuthError::Provider(format!(
+                "Token endpoint responded with {}",
+                res.status()
+            )));
+        }
+
+        let tokens: OidcTokens = res
+            .json()
+            .await
+            .map_err(|e| AuthError::Provider(e.to_string()))?;
+
+        Ok(tokens)
+    }
+
+    async fn jwks(&self, jwks_url: &str) -> AuthResult<Vec<JwtKey>> {
+        let mut res = Client::default()
+            .get(jwks_url)
+            .header(header::ACCEPT, "application/json")
+            .send()
+            .await
+            .map_err(|e| AuthError::Provider(e.to_string()))?;
+
+        if !res.status().is_success() {
+            return Err(AuthError::Provider(format!(
+                "JWKS endpoint responded with {}",
+                res.status()
+            )));
+        }
+
+        let jwks: Jwks = res
+            .json()
+            .await
+            .map_err(|e| AuthError::Provider(e.to_string()))?;
+
+        Ok(JwtKey::from_jwks(&jwks))
+    }
+
+    async fn id_token_subject(&self, tokens: &OidcTokens, nonce: &str) -> AuthResult<String> {
+        let (issuer, jwks_url) = match (&self.provider.issuer, &self.provider.jwks_url) {
+            (Some(issuer), Some(jwks_url)) => (issuer, jwks_url),
+            _ => return Err(AuthError::Internal("Missing issuer or JWKS url".to_owned())),
+        };
+
+        let id_token = tokens
+            .id_token
+            .as_ref()
+            .ok_or_else(|| AuthError::InvalidToken("Missing id_token".to_owned()))?;
+
+        let keys = self.jwks(jwks_url).await?;
+
+        verify_id_token(&keys, id_token, issuer, &self.provider.client_id, nonce)
+            .map(|claims| claims.sub)
+    }
+
+    pub async fn userinfo(&self, tokens: &OidcTokens) -> AuthResult<Value> {
+        let mut res = Client::default()
+            .get(&self.provider.userinfo_url)
+            .header(header::ACCEPT, "application/json")
+            .bearer_auth(&tokens.access_token)
+            .send()
+            .await
+            .map_err(|e| AuthError::Provider(e.to_string()))?;
+
+        if !res.status().is_success() {
+            return Err(AuthError::Provider(format!(
+                "Userinfo endpoint responded with {}",
+                res.status()
+            )));
+        }
+
+        res.json()
+            .await
+            .map_err(|e| AuthError::Provider(e.to_string()))
+    }
+
+    pub fn to_user(&self, userinfo: &Value) -> AuthResult<User> {
+        let subject = userinfo
+            .get(&self.provider.subject_claim)
+            .and_then(claim_to_string)
+            .ok_or_else(|| AuthError::Provider("Missing subject claim".to_owned()))?;
+
+        let email = userinfo
+            .get(&self.provider.email_claim)
+            .and_then(claim_to_string);
+
+        // An unverified address would let anyone take over the account it gets linked to.
+        if email.is_some() && !self.is_email_verified(userinfo) {
+            return Err(AuthError::Provider("Email is not verified".to_owned()));
+        }
+
+        let mut claims = HashMap::new();
+        claims.insert(
+            "provider".to_owned(),
+            Value::String(self.provider.name.clone()),
+        );
+        claims.insert("subject".to_owned(), Value::String(subject.clone()));
+
+        Ok(User {
+            id: Uuid::new_v5(
+                &Uuid::NAMESPACE_URL,
+                format!("{}:{}", self.provider.name, subject).as_bytes(),
+            ),
+            email,
+            username: userinfo
+                .get(&self.provider.username_claim)
+                .and_then(claim_to_string),
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims,
+        })
+    }
+
+    fn is_email_verified(&self, userinfo: &Value) -> bool {
+        match &self.provider.email_verified_claim {
+            Some(claim) => match userinfo.get(claim) {
+                Some(Value::Bool(verified)) => *verified,
+                Some(Value::String(verified)) => verified == "true",
+                _ => false,
+            },
+            None => true,
+        }
+    }
+
+    pub async fn authenticate(
+        &self,
+        request: &AuthorizationRequest,
+        code: &str,
+        state: &str,
+    ) -> AuthResult<(User, OidcTokens)> {
+        let tokens = self.exchange_code(request, code, state).await?;
+        let userinfo = self.userinfo(&tokens).await?;
+        let user = self.to_user(&userinfo)?;
+
+        if self.provider.is_openid() {
+            let subject = self.id_token_subject(&tokens, &request.nonce).await?;
+
+            if user.claims.get("subject") != Some(&Value::String(subject)) {
+                return Err(AuthError::InvalidToken("Subject mismatch".to_owned()));
+            }
+        }
+
+        Ok((user, tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use timada_util::config::Layers;
+    use url::Url;
+
+    use chrono::{Duration, Utc};
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+
+    use super::{code_challenge, verify_id_token, verify_state, OidcClient, OidcProvider};
+    use crate::jwt::JwtKey;
+
+    fn github() -> OidcProvider {
+        let layers = Layers::new()
+            .set("OIDC_GITHUB_CLIENT_ID", "client")
+            .set("OIDC_GITHUB_CLIENT_SECRET", "secret")
+            .set("OIDC_GITHUB_REDIRECT_URL", "https://app.timada.co/callback");
+
+        OidcProvider::from_layers("github", &layers).unwrap()
+    }
+
+    #[test]
+    fn provider() {
+        let provider = github();
+
+        assert_eq!(
+            provider.token_url,
+            "https://github.com/login/oauth/access_token"
+        );
+        assert_eq!(provider.scopes, vec!["read:user", "user:email"]);
+        assert_eq!(provider.subject_claim, "id");
+        assert!(OidcProvider::from_layers("custom", &Layers::new()).is_err());
+
+        let layers = Layers::new()
+            .set("OIDC_GOOGLE_CLIENT_ID", "client")
+            .set("OIDC_GOOGLE_CLIENT_SECRET", "secret")
+            .set("OIDC_GOOGLE_REDIRECT_URL", "https://app.timada.co/callback");
+        let google = OidcProvider::from_layers("google", &layers).unwrap();
+
+        assert_eq!(
+            google.issuer,
+            Some("https://accounts.google.com".to_owned())
+        );
+        assert_eq!(
+            google.email_verified_claim,
+            Some("email_verified".to_owned())
+        );
+        assert_eq!(provider.issuer, None);
+        assert_eq!(provider.email_verified_claim, None);
+    }
+
+    #[test]
+    fn id_token() {
+        let keys = vec![JwtKey::hmac("v1", b"secret")];
+        let sign = |claims: serde_json::Value| {
+            let mut header = Header::new(Algorithm::HS256);
+            header.kid = Some("v1".to_owned());
+            encode(&header, &claims, &EncodingKey::from_secret(b"secret")).unwrap()
+        };
+        let claims = |iss: &str, aud: &str, exp: i64| json!({ "sub": "42", "iss": iss, "aud": aud, "exp": exp, "nonce": "nonce" });
+        let exp = (Utc::now() + Duration::minutes(5)).timestamp();
+        let issuer = "https://accounts.google.com";
+
+        let token = sign(claims(issuer, "client", exp));
+        assert_eq!(
+            verify_id_token(&keys, &token, issuer, "client", "nonce")
+                .unwrap()
+                .sub,
+            "42"
+        );
+        assert!(verify_id_token(&keys, &token, issuer, "client", "forged").is_err());
+        assert!(verify_id_token(&keys, &token, issuer, "other", "nonce").is_err());
+        assert!(verify_id_token(&keys, &token, "https://evil.co", "client", "nonce").is_err());
+
+        let expired = sign(claims(issuer, "client", Utc::now().timestamp() - 3600));
+        assert!(verify_id_token(&keys, &expired, issuer, "client", "nonce").is_err());
+
+        let forged = {
+            let mut header = Header::new(Algorithm::HS256);
+            header.kid = Some("v1".to_owned());
+            encode(
+                &header,
+                &claims(issuer, "client", exp),
+                &EncodingKey::from_secret(b"forged"),
+            )
+            .unwrap()
+        };
+        assert!(verify_id_token(&keys, &forged, issuer, "client", "nonce").is_err());
+    }
+
+    #[test]
+    fn pkce() {
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn authorize() {
+        let client = OidcClient::new(github());
+        let request = client.authorize().unwrap();
+        let url = Url::parse(&request.url).unwrap();
+        let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+
+        assert!(query.contains(&("state".to_owned(), request.state.clone())));
+        assert!(query.contains(&(
+            "code_challenge".to_owned(),
+            code_challenge(&request.code_verifier)
+        )));
+        assert!(verify_state(&request.state, &request.state).is_ok());
+        assert!(verify_state(&request.state, "forged").is_err());
+    }
+
+    #[test]
+    fn to_user() {
+        let client = OidcClient::new(github());
+        let user = client
+            .to_user(&json!({ "id": 42, "login": "snapiz", "email": "snapiz@timada.co" }))
+            .unwrap();
+
+        assert_eq!(user.username, Some("snapiz".to_owned()));
+        assert_eq!(user.email, Some("snapiz@timada.co".to_owned()));
+        assert_eq!(user.claims.get("subject"), Some(&json!("42")));
+        assert_eq!(client.to_user(&json!({ "id": "42" })).unwrap().id, user.id);
+        assert!(client.to_user(&json!({ "login": "snapiz" })).is_err());
+    }
+
+    #[test]
+    fn unverified_email() {
+        let client = OidcClient::new(OidcProvider {
+            email_verified_claim: Some("email_verified".to_owned()),
+            ..github()
+        });
+
+        assert!(client
+            .to_user(&json!({ "id": 42, "email": "snapiz@timada.co", "email_verified": true }))
+            .is_ok());
+        assert!(client
+            .to_user(&json!({ "id": 42, "email": "snapiz@timada.co", "email_verified": false }))
+            .is_err());
+        assert!(client
+            .to_user(&json!({ "id": 42, "email": "snapiz@timada.co" }))
+            .is_err());
+        assert!(client.to_user(&json!({ "id": 42 })).is_ok());
+    }
+}
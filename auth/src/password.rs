@@ -0,0 +1,165 @@
+use argon2::{Config, ThreadMode, Variant, Version};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use std::fmt;
+use std::io::Write;
+
+use super::crypto::random_bytes;
+use super::error::{AuthError, AuthResult};
+
+const SALT_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PasswordConfig {
+    pub mem_cost: u32,
+    pub time_cost: u32,
+    pub lanes: u32,
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        PasswordConfig {
+            mem_cost: 19456,
+            time_cost: 2,
+            lanes: 1,
+        }
+    }
+}
+
+impl PasswordConfig {
+    pub fn mem_cost(mut self, mem_cost: u32) -> Self {
+        self.mem_cost = mem_cost;
+        self
+    }
+
+    pub fn time_cost(mut self, time_cost: u32) -> Self {
+        self.time_cost = time_cost;
+        self
+    }
+
+    pub fn lanes(mut self, lanes: u32) -> Self {
+        self.lanes = lanes;
+        self
+    }
+
+    fn argon2(&self) -> Config<'static> {
+        Config {
+            variant: Variant::Argon2id,
+            version: Version::Version13,
+            mem_cost: self.mem_cost,
+            time_cost: self.time_cost,
+            lanes: self.lanes,
+            thread_mode: ThreadMode::Sequential,
+            ..Config::default()
+        }
+    }
+
+    fn params(&self) -> String {
+        format!("m={},t={},p={}", self.mem_cost, self.time_cost, self.lanes)
+    }
+}
+
+#[derive(Clone, PartialEq, AsExpression, FromSqlRow)]
+#[sql_type = "Text"]
+pub struct PasswordHash(String);
+
+impl PasswordHash {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for PasswordHash {
+    fn from(hash: String) -> Self {
+        PasswordHash(hash)
+    }
+}
+
+impl fmt::Debug for PasswordHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PasswordHash([REDACTED])")
+    }
+}
+
+impl ToSql<Text, Pg> for PasswordHash {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Text, Pg>::to_sql(&self.0, out)
+    }
+}
+
+impl FromSql<Text, Pg> for PasswordHash {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        FromSql::<Text, Pg>::from_sql(bytes).map(PasswordHash)
+    }
+}
+
+pub fn hash_password(password: &str, config: &PasswordConfig) -> AuthResult<PasswordHash> {
+    argon2::hash_encoded(
+        password.as_bytes(),
+        &random_bytes(SALT_LEN),
+        &config.argon2(),
+    )
+    .map(PasswordHash)
+    .map_err(|e| AuthError::Hash(e.to_string()))
+}
+
+pub fn verify_password(password: &str, hash: &PasswordHash) -> AuthResult<()> {
+    match argon2::verify_encoded(hash.as_str(), password.as_bytes()) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(AuthError::InvalidCredentials),
+        Err(e) => Err(AuthError::Hash(e.to_string())),
+    }
+}
+
+pub fn needs_rehash(hash: &PasswordHash, config: &PasswordConfig) -> bool {
+    let mut parts = hash.as_str().split('$').skip(1);
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("argon2id"), Some("v=19"), Some(params)) => params != config.params(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_password, needs_rehash, verify_password, PasswordConfig, PasswordHash};
+    use crate::error::AuthError;
+
+    fn config() -> PasswordConfig {
+        PasswordConfig::default().mem_cost(1024).time_cost(1)
+    }
+
+    #[test]
+    fn hash_and_verify() {
+        let hash = hash_password("p@ssw0rd", &config()).unwrap();
+
+        assert!(hash.as_str().starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert_eq!(verify_password("p@ssw0rd", &hash), Ok(()));
+        assert_eq!(
+            verify_password("wrong", &hash),
+            Err(AuthError::InvalidCredentials)
+        );
+        assert_ne!(hash, hash_password("p@ssw0rd", &config()).unwrap());
+    }
+
+    #[test]
+    fn rehash() {
+        let hash = hash_password("p@ssw0rd", &config()).unwrap();
+
+        assert!(!needs_rehash(&hash, &config()));
+        assert!(needs_rehash(&hash, &config().time_cost(2)));
+        assert!(needs_rehash(
+            &PasswordHash::from("$argon2i$v=19$m=1024,t=1,p=1$c2FsdA$aGFzaA".to_owned()),
+            &config()
+        ));
+    }
+
+    #[test]
+    fn redacted() {
+        let hash = hash_password("p@ssw0rd", &config()).unwrap();
+
+        assert_eq!(format!("{:?}", hash), "PasswordHash([REDACTED])");
+    }
+}
@@ -0,0 +1,232 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde_json::Value;
+use timada_http::User;
+use uuid::Uuid;
+
+use super::crypto::{random_token, sha256_hex};
+use super::error::{AuthError, AuthResult};
+use super::jwt::JwtService;
+use super::schema::auth_refresh_tokens;
+use super::user_store::current_user;
+
+const REFRESH_TOKEN_LEN: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct RefreshConfig {
+    ttl: Duration,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        RefreshConfig {
+            ttl: Duration::days(30),
+        }
+    }
+}
+
+impl RefreshConfig {
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable)]
+#[table_name = "auth_refresh_tokens"]
+pub struct RefreshToken {
+    pub id: Uuid,
+    pub family_id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub user_data: Value,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, PartialEq)]
+enum RefreshState {
+    Valid,
+    Expired,
+    Reused,
+}
+
+impl RefreshToken {
+    fn state(&self, now: NaiveDateTime) -> RefreshState {
+        if self.used_at.is_some() || self.revoked_at.is_some() {
+            RefreshState::Reused
+        } else if self.expires_at <= now {
+            RefreshState::Expired
+        } else {
+            RefreshState::Valid
+        }
+    }
+
+    pub fn user(&self) -> AuthResult<User> {
+        serde_json::from_value(self.user_data.clone())
+            .map_err(|e| AuthError::Internal(e.to_string()))
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "auth_refresh_tokens"]
+struct NewRefreshToken {
+    family_id: Uuid,
+    user_id: Uuid,
+    token_hash: String,
+    user_data: Value,
+    expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+}
+
+fn insert(
+    conn: &PgConnection,
+    family_id: Uuid,
+    user_id: Uuid,
+    user_data: Value,
+    config: &RefreshConfig,
+) -> AuthResult<(String, RefreshToken)> {
+    let token = random_token(REFRESH_TOKEN_LEN);
+
+    let refresh_token = diesel::insert_into(auth_refresh_tokens::table)
+        .values(&NewRefreshToken {
+            family_id,
+            user_id,
+            token_hash: sha256_hex(&token),
+            user_data,
+            expires_at: Utc::now().naive_utc() + config.ttl,
+        })
+        .get_result(conn)?;
+
+    Ok((token, refresh_token))
+}
+
+pub fn issue_refresh_token(
+    conn: &PgConnection,
+    user: &User,
+    config: &RefreshConfig,
+) -> AuthResult<(String, RefreshToken)> {
+    let user_data = serde_json::to_value(user).map_err(|e| AuthError::Internal(e.to_string()))?;
+
+    insert(conn, Uuid::new_v4(), user.id, user_data, config)
+}
+
+pub fn revoke_refresh_family(conn: &PgConnection, family_id: Uuid) -> AuthResult<usize> {
+    Ok(diesel::update(
+        auth_refresh_tokens::table
+            .filter(auth_refresh_tokens::family_id.eq(family_id))
+            .filter(auth_refresh_tokens::revoked_at.is_null()),
+    )
+    .set(auth_refresh_tokens::revoked_at.eq(Utc::now().naive_utc()))
+    .execute(conn)?)
+}
+
+pub fn rotate_refresh_token(
+    conn: &PgConnection,
+    token: &str,
+    config: &RefreshConfig,
+) -> AuthResult<(String, RefreshToken)> {
+    let res = conn.transaction::<_, AuthError, _>(|| {
+        let now = Utc::now().naive_utc();
+
+        let current: RefreshToken = auth_refresh_tokens::table
+            .filter(auth_refresh_tokens::token_hash.eq(sha256_hex(token)))
+            .for_update()
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| AuthError::InvalidToken("Unknown refresh token".to_owned()))?;
+
+        match current.state(now) {
+            RefreshState::Valid => {}
+            RefreshState::Expired => {
+                return Err(AuthError::InvalidToken("Expired refresh token".to_owned()))
+            }
+            RefreshState::Reused => return Ok(Err(current.family_id)),
+        }
+
+        diesel::update(&current)
+            .set(auth_refresh_tokens::used_at.eq(now))
+            .execute(conn)?;
+
+        insert(
+            conn,
+            current.family_id,
+            current.user_id,
+            current.user_data,
+            config,
+        )
+        .map(Ok)
+    })?;
+
+    match res {
+        Ok(rotated) => Ok(rotated),
+        Err(family_id) => {
+            revoke_refresh_family(conn, family_id)?;
+            Err(AuthError::TokenReused)
+        }
+    }
+}
+
+pub fn exchange_refresh_token(
+    conn: &PgConnection,
+    jwt: &JwtService,
+    token: &str,
+    config: &RefreshConfig,
+) -> AuthResult<TokenPair> {
+    let (refresh_token, rotated) = rotate_refresh_token(conn, token, config)?;
+
+    Ok(TokenPair {
+        access_token: jwt.issue(&current_user(rotated.user()?)?)?,
+        refresh_token,
+        token_type: "Bearer".to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use super::{RefreshState, RefreshToken};
+
+    fn refresh_token() -> RefreshToken {
+        let now = Utc::now().naive_utc();
+
+        RefreshToken {
+            id: Uuid::new_v4(),
+            family_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            token_hash: String::new(),
+            user_data: serde_json::json!({}),
+            created_at: now,
+            expires_at: now + Duration::days(1),
+            used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn state() {
+        let now = Utc::now().naive_utc();
+        let mut token = refresh_token();
+
+        assert_eq!(token.state(now), RefreshState::Valid);
+        assert_eq!(token.state(now + Duration::days(2)), RefreshState::Expired);
+
+        token.used_at = Some(now);
+        assert_eq!(token.state(now), RefreshState::Reused);
+
+        token.used_at = None;
+        token.revoked_at = Some(now);
+        assert_eq!(token.state(now), RefreshState::Reused);
+    }
+}
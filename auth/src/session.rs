@@ -0,0 +1,310 @@
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use futures::future::LocalBoxFuture;
+use serde_json::Value;
+use timada_database::Pool;
+use timada_http::{Error, User};
+use uuid::Uuid;
+
+use super::crypto::{random_token, sha256_hex};
+use super::error::{AuthError, AuthResult};
+use super::schema::auth_sessions;
+use super::user_store::current_user;
+
+const SESSION_TOKEN_LEN: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    ttl: Duration,
+    touch_interval: Duration,
+    cookie_name: String,
+    header_name: String,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            ttl: Duration::days(14),
+            touch_interval: Duration::minutes(1),
+            cookie_name: "session".to_owned(),
+            header_name: "x-session-token".to_owned(),
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn touch_interval(mut self, touch_interval: Duration) -> Self {
+        self.touch_interval = touch_interval;
+        self
+    }
+
+    pub fn cookie_name(mut self, cookie_name: &str) -> Self {
+        self.cookie_name = cookie_name.to_owned();
+        self
+    }
+
+    pub fn header_name(mut self, header_name: &str) -> Self {
+        self.header_name = header_name.to_lowercase();
+        self
+    }
+
+    pub fn token(&self, req: &HttpRequest) -> Option<String> {
+        req.headers()
+            .get(self.header_name.as_str())
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_owned())
+            .or_else(|| req.cookie(&self.cookie_name).map(|c| c.value().to_owned()))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl DeviceInfo {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        DeviceInfo {
+            user_agent: req
+                .headers()
+                .get("user-agent")
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_owned()),
+            ip_address: req
+                .connection_info()
+                .realip_remote_addr()
+                .map(|addr| addr.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable)]
+#[table_name = "auth_sessions"]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub user_data: Value,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl Session {
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+
+    // Snapshot taken at login, see `current_user` for the user to authorize requests with.
+    pub fn user(&self) -> AuthResult<User> {
+        serde_json::from_value(self.user_data.clone())
+            .map_err(|e| AuthError::Internal(e.to_string()))
+    }
+
+    // Reloaded through the `UserStore`, disabled users end the session.
+    pub fn current_user(&self) -> AuthResult<User> {
+        current_user(self.user()?)
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "auth_sessions"]
+struct NewSession<'a> {
+    user_id: Uuid,
+    token_hash: String,
+    user_data: Value,
+    user_agent: Option<&'a str>,
+    ip_address: Option<&'a str>,
+    expires_at: NaiveDateTime,
+}
+
+pub fn create_session(
+    conn: &PgConnection,
+    user: &User,
+    device: &DeviceInfo,
+    config: &SessionConfig,
+) -> AuthResult<(String, Session)> {
+    let token = random_token(SESSION_TOKEN_LEN);
+    let user_data = serde_json::to_value(user).map_err(|e| AuthError::Internal(e.to_string()))?;
+
+    let session = diesel::insert_into(auth_sessions::table)
+        .values(&NewSession {
+            user_id: user.id,
+            token_hash: sha256_hex(&token),
+            user_data,
+            user_agent: device.user_agent.as_deref(),
+            ip_address: device.ip_address.as_deref(),
+            expires_at: Utc::now().naive_utc() + config.ttl,
+        })
+        .get_result(conn)?;
+
+    Ok((token, session))
+}
+
+pub fn find_session(
+    conn: &PgConnection,
+    token: &str,
+    config: &SessionConfig,
+) -> AuthResult<Session> {
+    let now = Utc::now().naive_utc();
+
+    let session: Session = auth_sessions::table
+        .filter(auth_sessions::token_hash.eq(sha256_hex(token)))
+        .first(conn)
+        .optional()?
+        .ok_or(AuthError::InvalidSession)?;
+
+    if !session.is_active(now) {
+        return Err(AuthError::InvalidSession);
+    }
+
+    if now - session.last_seen_at < config.touch_interval {
+        return Ok(session);
+    }
+
+    Ok(diesel::update(&session)
+        .set((
+            auth_sessions::last_seen_at.eq(now),
+            auth_sessions::expires_at.eq(now + config.ttl),
+        ))
+        .get_result(conn)?)
+}
+
+pub fn revoke_session(conn: &PgConnection, id: Uuid) -> AuthResult<()> {
+    diesel::update(auth_sessions::table.find(id))
+        .set(auth_sessions::revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn revoke_user_sessions(conn: &PgConnection, user_id: Uuid) -> AuthResult<usize> {
+    Ok(diesel::update(
+        auth_sessions::table
+            .filter(auth_sessions::user_id.eq(user_id))
+            .filter(auth_sessions::revoked_at.is_null()),
+    )
+    .set(auth_sessions::revoked_at.eq(Utc::now().naive_utc()))
+    .execute(conn)?)
+}
+
+pub struct SessionUser {
+    pub session: Session,
+    pub user: User,
+}
+
+impl FromRequest for SessionUser {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    type Config = SessionConfig;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let config = req.app_data::<Self::Config>().cloned().unwrap_or_default();
+        let token = config.token(req);
+        let pool = req
+            .app_data::<web::Data<Pool>>()
+            .map(|pool| pool.get_ref().clone());
+
+        Box::pin(async move {
+            let token = token
+                .ok_or(AuthError::InvalidSession)
+                .map_err(Error::from)?;
+            let pool = pool.ok_or(Error::InternalServerError)?;
+
+            let (session, user) = web::block(move || {
+                let conn = pool.get().map_err(|e| AuthError::Internal(e.to_string()))?;
+                let session = find_session(&conn, &token, &config)?;
+                let user = session.current_user()?;
+
+                Ok((session, user))
+            })
+            .await
+            .map_err(|e| Error::from(AuthError::from(e)))?;
+
+            Ok(SessionUser { session, user })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use super::{Session, SessionConfig};
+    use crate::error::AuthError;
+
+    fn session() -> Session {
+        let now = Utc::now().naive_utc();
+
+        Session {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            token_hash: String::new(),
+            user_data: serde_json::json!({
+                "id": Uuid::nil(),
+                "email": null,
+                "username": null,
+                "role": "User",
+                "state": "Enabled"
+            }),
+            user_agent: None,
+            ip_address: None,
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now + Duration::hours(1),
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn active() {
+        let now = Utc::now().naive_utc();
+        let mut session = session();
+
+        assert!(session.is_active(now));
+        assert!(!session.is_active(now + Duration::hours(2)));
+
+        session.revoked_at = Some(now);
+        assert!(!session.is_active(now));
+    }
+
+    #[test]
+    fn user() {
+        assert_eq!(session().user().unwrap().id, Uuid::nil());
+        assert_eq!(session().current_user(), session().user());
+
+        let mut session = session();
+        session.user_data["state"] = serde_json::json!("Disabled");
+
+        assert_eq!(session.current_user(), Err(AuthError::InvalidSession));
+    }
+
+    #[test]
+    fn token_from_request() {
+        let config = SessionConfig::default();
+
+        let req = TestRequest::default()
+            .header("x-session-token", "header-token")
+            .to_http_request();
+        assert_eq!(config.token(&req), Some("header-token".to_owned()));
+
+        let req = TestRequest::default()
+            .cookie(actix_web::cookie::Cookie::new("session", "cookie-token"))
+            .to_http_request();
+        assert_eq!(config.token(&req), Some("cookie-token".to_owned()));
+    }
+}
@@ -0,0 +1,423 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use url::Url;
+use uuid::Uuid;
+
+use super::crypto::{constant_time_eq, random_bytes, sha256_hex};
+use super::error::{AuthError, AuthResult};
+use super::schema::{auth_recovery_codes, auth_totp};
+
+const SECRET_LEN: usize = 20;
+const RECOVERY_CODE_LEN: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct TotpConfig {
+    issuer: String,
+    digits: u32,
+    period: u64,
+    skew: u64,
+    recovery_codes: usize,
+}
+
+impl Default for TotpConfig {
+    fn default() -> Self {
+        TotpConfig {
+            issuer: "Timada".to_owned(),
+            digits: 6,
+            period: 30,
+            skew: 1,
+            recovery_codes: 10,
+        }
+    }
+}
+
+impl TotpConfig {
+    pub fn issuer(mut self, issuer: &str) -> Self {
+        self.issuer = issuer.to_owned();
+        self
+    }
+
+    pub fn digits(mut self, digits: u32) -> Self {
+        self.digits = digits;
+        self
+    }
+
+    pub fn period(mut self, period: u64) -> Self {
+        self.period = period;
+        self
+    }
+
+    pub fn skew(mut self, skew: u64) -> Self {
+        self.skew = skew;
+        self
+    }
+
+    pub fn recovery_codes(mut self, recovery_codes: usize) -> Self {
+        self.recovery_codes = recovery_codes;
+        self
+    }
+}
+
+pub fn generate_totp_secret() -> String {
+    base32::encode(
+        base32::Alphabet::RFC4648 { padding: false },
+        &random_bytes(SECRET_LEN),
+    )
+}
+
+fn decode_secret(secret: &str) -> AuthResult<Vec<u8>> {
+    base32::decode(base32::Alphabet::RFC4648 { padding: false }, secret)
+        .ok_or_else(|| AuthError::Internal("Invalid TOTP secret".to_owned()))
+}
+
+pub fn totp_uri(config: &TotpConfig, secret: &str, account: &str) -> AuthResult<String> {
+    let mut url = Url::parse("otpauth://totp/").map_err(|e| AuthError::Internal(e.to_string()))?;
+
+    url.set_path(&format!("{}:{}", config.issuer, account));
+    url.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", &config.issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &config.digits.to_string())
+        .append_pair("period", &config.period.to_string());
+
+    Ok(url.into_string())
+}
+
+fn hotp(key: &[u8], counter: u64, digits: u32) -> AuthResult<String> {
+    let mut mac = Hmac::<Sha1>::new_varkey(key).map_err(|e| AuthError::Internal(e.to_string()))?;
+    mac.input(&counter.to_be_bytes());
+
+    let hash = mac.result().code();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    Ok(format!(
+        "{:0width$}",
+        value % 10u32.pow(digits),
+        width = digits as usize
+    ))
+}
+
+pub fn totp_code(config: &TotpConfig, secret: &str, time: u64) -> AuthResult<String> {
+    hotp(&decode_secret(secret)?, time / config.period, config.digits)
+}
+
+pub fn verify_totp_code(
+    config: &TotpConfig,
+    secret: &str,
+    code: &str,
+    time: u64,
+) -> AuthResult<Option<u64>> {
+    let key = decode_secret(secret)?;
+    let step = time / config.period;
+    let first = step.saturating_sub(config.skew);
+
+    for candidate in first..=step + config.skew {
+        if constant_time_eq(
+            hotp(&key, candidate, config.digits)?.as_bytes(),
+            code.as_bytes(),
+        ) {
+            return Ok(Some(candidate));
+        }
+    }
+
+    Ok(None)
+}
+
+fn now() -> u64 {
+    Utc::now().timestamp() as u64
+}
+
+#[derive(Debug, Clone, Queryable)]
+pub struct TotpDevice {
+    pub user_id: Uuid,
+    pub secret: String,
+    pub last_used_step: Option<i64>,
+    pub created_at: NaiveDateTime,
+    pub confirmed_at: Option<NaiveDateTime>,
+}
+
+impl TotpDevice {
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "auth_totp"]
+struct NewTotpDevice<'a> {
+    user_id: Uuid,
+    secret: &'a str,
+}
+
+#[derive(Insertable)]
+#[table_name = "auth_recovery_codes"]
+struct NewRecoveryCode {
+    user_id: Uuid,
+    code_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub uri: String,
+}
+
+fn find_device(conn: &PgConnection, user_id: Uuid) -> AuthResult<Option<TotpDevice>> {
+    Ok(auth_totp::table
+        .find(user_id)
+        .for_update()
+        .first(conn)
+        .optional()?)
+}
+
+pub fn is_totp_enabled(conn: &PgConnection, user_id: Uuid) -> AuthResult<bool> {
+    Ok(auth_totp::table
+        .find(user_id)
+        .filter(auth_totp::confirmed_at.is_not_null())
+        .first::<TotpDevice>(conn)
+        .optional()?
+        .is_some())
+}
+
+pub fn enroll_totp(
+    conn: &PgConnection,
+    user_id: Uuid,
+    account: &str,
+    config: &TotpConfig,
+) -> AuthResult<TotpEnrollment> {
+    conn.transaction(|| {
+        match find_device(conn, user_id)? {
+            Some(device) if device.is_confirmed() => {
+                return Err(AuthError::Internal("TOTP already enabled".to_owned()))
+            }
+            Some(_) => {
+                diesel::delete(auth_totp::table.find(user_id)).execute(conn)?;
+            }
+            None => {}
+        }
+
+        let secret = generate_totp_secret();
+
+        diesel::insert_into(auth_totp::table)
+            .values(&NewTotpDevice {
+                user_id,
+                secret: &secret,
+            })
+            .execute(conn)?;
+
+        Ok(TotpEnrollment {
+            uri: totp_uri(config, &secret, account)?,
+            secret,
+        })
+    })
+}
+
+fn accept_code(
+    conn: &PgConnection,
+    device: &TotpDevice,
+    code: &str,
+    config: &TotpConfig,
+) -> AuthResult<()> {
+    let step = verify_totp_code(config, &device.secret, code, now())?
+        .map(|step| step as i64)
+        .filter(|step| device.last_used_step.map_or(true, |last| *step > last))
+        .ok_or(AuthError::InvalidCredentials)?;
+
+    diesel::update(auth_totp::table.find(device.user_id))
+        .set(auth_totp::last_used_step.eq(step))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn confirm_totp(
+    conn: &PgConnection,
+    user_id: Uuid,
+    code: &str,
+    config: &TotpConfig,
+) -> AuthResult<Vec<String>> {
+    conn.transaction(|| {
+        let device = find_device(conn, user_id)?
+            .filter(|device| !device.is_confirmed())
+            .ok_or(AuthError::InvalidCredentials)?;
+
+        accept_code(conn, &device, code, config)?;
+
+        diesel::update(auth_totp::table.find(user_id))
+            .set(auth_totp::confirmed_at.eq(Utc::now().naive_utc()))
+            .execute(conn)?;
+
+        generate_recovery_codes(conn, user_id, config)
+    })
+}
+
+pub fn verify_totp(
+    conn: &PgConnection,
+    user_id: Uuid,
+    code: &str,
+    config: &TotpConfig,
+) -> AuthResult<()> {
+    conn.transaction(|| {
+        let device = find_device(conn, user_id)?
+            .filter(TotpDevice::is_confirmed)
+            .ok_or(AuthError::InvalidCredentials)?;
+
+        accept_code(conn, &device, code, config)
+    })
+}
+
+pub fn disable_totp(conn: &PgConnection, user_id: Uuid) -> AuthResult<()> {
+    conn.transaction(|| {
+        diesel::delete(auth_totp::table.find(user_id)).execute(conn)?;
+        diesel::delete(auth_recovery_codes::table.filter(auth_recovery_codes::user_id.eq(user_id)))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+fn recovery_code() -> String {
+    let code = hex::encode(random_bytes(RECOVERY_CODE_LEN));
+
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+pub fn generate_recovery_codes(
+    conn: &PgConnection,
+    user_id: Uuid,
+    config: &TotpConfig,
+) -> AuthResult<Vec<String>> {
+    let codes = (0..config.recovery_codes)
+        .map(|_| recovery_code())
+        .collect::<Vec<_>>();
+
+    let values = codes
+        .iter()
+        .map(|code| NewRecoveryCode {
+            user_id,
+            code_hash: sha256_hex(&normalize_recovery_code(code)),
+        })
+        .collect::<Vec<_>>();
+
+    conn.transaction(|| {
+        diesel::delete(auth_recovery_codes::table.filter(auth_recovery_codes::user_id.eq(user_id)))
+            .execute(conn)?;
+        diesel::insert_into(auth_recovery_codes::table)
+            .values(&values)
+            .execute(conn)?;
+
+        Ok(codes)
+    })
+}
+
+pub fn use_recovery_code(conn: &PgConnection, user_id: Uuid, code: &str) -> AuthResult<()> {
+    let updated = diesel::update(
+        auth_recovery_codes::table
+            .filter(auth_recovery_codes::user_id.eq(user_id))
+            .filter(auth_recovery_codes::code_hash.eq(sha256_hex(&normalize_recovery_code(code))))
+            .filter(auth_recovery_codes::used_at.is_null()),
+    )
+    .set(auth_recovery_codes::used_at.eq(Utc::now().naive_utc()))
+    .execute(conn)?;
+
+    if updated == 0 {
+        return Err(AuthError::InvalidCredentials);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        generate_totp_secret, normalize_recovery_code, recovery_code, totp_code, totp_uri,
+        verify_totp_code, TotpConfig,
+    };
+
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn rfc6238() {
+        let config = TotpConfig::default().digits(8);
+
+        assert_eq!(totp_code(&config, RFC_SECRET, 59).unwrap(), "94287082");
+        assert_eq!(
+            totp_code(&config, RFC_SECRET, 1111111109).unwrap(),
+            "07081804"
+        );
+        assert_eq!(
+            totp_code(&config, RFC_SECRET, 2000000000).unwrap(),
+            "69279037"
+        );
+    }
+
+    #[test]
+    fn drift_window() {
+        let config = TotpConfig::default();
+        let secret = generate_totp_secret();
+        let code = totp_code(&config, &secret, 300).unwrap();
+
+        assert_eq!(
+            verify_totp_code(&config, &secret, &code, 300).unwrap(),
+            Some(10)
+        );
+        assert_eq!(
+            verify_totp_code(&config, &secret, &code, 330).unwrap(),
+            Some(10)
+        );
+        assert_eq!(
+            verify_totp_code(&config, &secret, &code, 270).unwrap(),
+            Some(10)
+        );
+        assert_eq!(
+            verify_totp_code(&config, &secret, &code, 360).unwrap(),
+            None
+        );
+        assert_eq!(
+            verify_totp_code(&config.skew(0), &secret, &code, 330).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn uri() {
+        let config = TotpConfig::default().issuer("Timada");
+
+        assert_eq!(
+            totp_uri(&config, RFC_SECRET, "john@timada.co").unwrap(),
+            format!(
+                "otpauth://totp/Timada:john@timada.co?secret={}&issuer=Timada&algorithm=SHA1&digits=6&period=30",
+                RFC_SECRET
+            )
+        );
+    }
+
+    #[test]
+    fn recovery_codes() {
+        let code = recovery_code();
+
+        assert_eq!(code.len(), 11);
+        assert_eq!(
+            normalize_recovery_code(&code.to_uppercase()),
+            code.replace("-", "")
+        );
+        assert_eq!(normalize_recovery_code(" ab12c-3d4e5 "), "ab12c3d4e5");
+    }
+}
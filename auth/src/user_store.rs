@@ -0,0 +1,126 @@
+use std::sync::{Arc, Once, RwLock};
+use timada_http::{User, UserState};
+use uuid::Uuid;
+
+use super::error::{AuthError, AuthResult};
+
+// Users live in the application, sessions and access tokens only keep the snapshot taken
+// when they were created. The store gives back the current user so a role change or a
+// disabled account applies to the next request. Called from blocking code.
+pub trait UserStore: Send + Sync {
+    // `None` once the user was deleted.
+    fn find_user(&self, id: Uuid) -> AuthResult<Option<User>>;
+}
+
+lazy_static::lazy_static! {
+    static ref STORE: RwLock<Option<Arc<dyn UserStore>>> = RwLock::new(None);
+}
+
+static MISSING_STORE: Once = Once::new();
+
+pub fn set_user_store<S: UserStore + 'static>(store: S) {
+    *STORE.write().expect("User store lock poisoned") = Some(Arc::new(store));
+}
+
+fn user_store() -> Option<Arc<dyn UserStore>> {
+    match STORE.read() {
+        Ok(store) => store.clone(),
+        Err(e) => e.into_inner().clone(),
+    }
+}
+
+fn reload(store: Option<&dyn UserStore>, snapshot: User) -> AuthResult<User> {
+    let user = match store {
+        Some(store) => store
+            .find_user(snapshot.id)?
+            .ok_or(AuthError::InvalidSession)?,
+        None => snapshot,
+    };
+
+    if user.state == UserState::Disabled {
+        return Err(AuthError::InvalidSession);
+    }
+
+    Ok(user)
+}
+
+// Used by the gateway to load the user to impersonate, there is no snapshot to fall back to.
+pub fn find_user(id: Uuid) -> AuthResult<Option<User>> {
+    match user_store() {
+        Some(store) => store.find_user(id),
+        None => Err(AuthError::Internal("No user store".to_owned())),
+    }
+}
+
+// Without a store the snapshot is all there is, role changes and disabled accounts are only
+// applied once the session expires.
+pub(crate) fn current_user(snapshot: User) -> AuthResult<User> {
+    let store = user_store();
+
+    if store.is_none() {
+        MISSING_STORE.call_once(|| {
+            log::warn!("no user store set, sessions keep the user they were created with")
+        });
+    }
+
+    reload(store.as_deref(), snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use timada_http::{User, UserRole, UserState};
+    use uuid::Uuid;
+
+    use super::{reload, UserStore};
+    use crate::error::{AuthError, AuthResult};
+
+    struct MemoryStore(HashMap<Uuid, User>);
+
+    impl UserStore for MemoryStore {
+        fn find_user(&self, id: Uuid) -> AuthResult<Option<User>> {
+            Ok(self.0.get(&id).cloned())
+        }
+    }
+
+    fn user(role: UserRole, state: UserState) -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: None,
+            username: None,
+            role,
+            state,
+            claims: Default::default(),
+        }
+    }
+
+    #[test]
+    fn current() {
+        let snapshot = user(UserRole::Admin, UserState::Enabled);
+        let demoted = User {
+            role: UserRole::User,
+            ..snapshot.clone()
+        };
+        let disabled = User {
+            state: UserState::Disabled,
+            ..snapshot.clone()
+        };
+
+        assert_eq!(reload(None, snapshot.clone()), Ok(snapshot.clone()));
+
+        let store = MemoryStore(vec![(snapshot.id, demoted.clone())].into_iter().collect());
+        assert_eq!(reload(Some(&store), snapshot.clone()), Ok(demoted));
+
+        let store = MemoryStore(vec![(snapshot.id, disabled)].into_iter().collect());
+        assert_eq!(
+            reload(Some(&store), snapshot.clone()),
+            Err(AuthError::InvalidSession)
+        );
+
+        let store = MemoryStore(HashMap::new());
+        assert_eq!(
+            reload(Some(&store), snapshot),
+            Err(AuthError::InvalidSession)
+        );
+    }
+}
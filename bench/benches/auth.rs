@@ -0,0 +1,39 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use timada_http::testing::{gateway_headers, gateway_request, impersonated_gateway_headers};
+use timada_http::{has_valid_gateway_key, Context, GatewayHeaders, User, UserRole, UserState};
+use uuid::Uuid;
+
+fn user(role: UserRole) -> User {
+    User {
+        id: Uuid::new_v4(),
+        email: Some("john@timada.co".to_owned()),
+        username: Some("john".to_owned()),
+        role,
+        state: UserState::Enabled,
+        claims: Default::default(),
+    }
+}
+
+fn auth(c: &mut Criterion) {
+    let names = GatewayHeaders::from_env();
+    let headers = gateway_headers(&user(UserRole::User));
+    let impersonated = impersonated_gateway_headers(&user(UserRole::User), &user(UserRole::Root));
+
+    c.bench_function("headers/gateway_key", |b| {
+        b.iter(|| has_valid_gateway_key(&headers, &names))
+    });
+    c.bench_function("headers/user", |b| {
+        b.iter(|| User::from_headers(&headers, &names).unwrap())
+    });
+    c.bench_function("headers/impersonated_context", |b| {
+        b.iter(|| Context::from_headers(&impersonated, &names))
+    });
+
+    let req = gateway_request(&user(UserRole::Admin)).to_http_request();
+    c.bench_function("request/context", |b| {
+        b.iter(|| Context::from_http_request(&req).unwrap())
+    });
+}
+
+criterion_group!(benches, auth);
+criterion_main!(benches);
@@ -0,0 +1,68 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use timada_bench::{bench_rows, cursor_at, resolve_todos, BenchDatabase};
+use timada_relay::{from_cursor, to_cursor};
+
+const PAGE_SIZES: &[usize] = &[10, 40, 100];
+
+fn pagination(c: &mut Criterion) {
+    let rows = bench_rows();
+    let database = BenchDatabase::new("timada_bench_dev").expect("Failed to create bench database");
+    let conn = database.seed(rows).expect("Failed to seed bench database");
+
+    let mut depths = vec![0, rows / 100, rows / 2, rows - 1];
+    depths.dedup();
+    let cursors = depths
+        .iter()
+        .map(|depth| {
+            let cursor = cursor_at(&conn, *depth).expect("Failed to find cursor");
+            (*depth, cursor)
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("connection/first");
+    for first in PAGE_SIZES.iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(first), first, |b, first| {
+            b.iter(|| resolve_todos(&conn, Some(*first), None, None, None).unwrap())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("connection/after");
+    for (depth, cursor) in cursors.iter() {
+        for first in PAGE_SIZES.iter() {
+            group.bench_with_input(
+                BenchmarkId::new(format!("depth-{}", depth), first),
+                first,
+                |b, first| {
+                    b.iter(|| {
+                        resolve_todos(&conn, Some(*first), Some(cursor.clone()), None, None)
+                            .unwrap()
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("connection/before");
+    for (depth, cursor) in cursors.iter() {
+        group.bench_with_input(BenchmarkId::new("depth", depth), cursor, |b, cursor| {
+            b.iter(|| resolve_todos(&conn, None, None, Some(40), Some(cursor.clone())).unwrap())
+        });
+    }
+    group.finish();
+
+    let (_, cursor) = &cursors[cursors.len() - 1];
+    c.bench_function("cursor/encode", |b| {
+        b.iter(|| {
+            to_cursor(
+                "0035b208-34fb-4548-ba20-cd9dcbe717fa",
+                "2020-01-07T00:00:00+00:00",
+            )
+        })
+    });
+    c.bench_function("cursor/decode", |b| b.iter(|| from_cursor(cursor).unwrap()));
+}
+
+criterion_group!(benches, pagination);
+criterion_main!(benches);
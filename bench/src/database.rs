@@ -0,0 +1,77 @@
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use diesel::PgConnection;
+use timada_database::testing::test_config;
+use timada_database::{create, DatabaseConnection, MigrationResult};
+use timada_util::env;
+
+use super::todo::bench_todos;
+
+pub const BENCH_ROWS_VAR: &str = "BENCH_ROWS";
+
+const SCHEMA: &str = r#"
+CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
+
+CREATE TABLE IF NOT EXISTS bench_todos (
+  id uuid PRIMARY KEY,
+  text VARCHAR(255) NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS bench_todos_created_at_id ON bench_todos (created_at, id);
+"#;
+
+// Three rows share each timestamp so cursors also go through the key tie-break.
+const SEED: &str = "INSERT INTO bench_todos (id, text, created_at)
+SELECT uuid_generate_v4(), 'Todo ' || i, TIMESTAMPTZ '2020-01-01 00:00:00+00' + (i / 3) * INTERVAL '1 second'
+FROM generate_series(1, $1) AS i";
+
+pub fn bench_rows() -> i64 {
+    env::var_opt(BENCH_ROWS_VAR)
+        .map(|rows| {
+            rows.parse()
+                .unwrap_or_else(|_| panic!("Invalid {} {}", BENCH_ROWS_VAR, rows))
+        })
+        .unwrap_or(100_000)
+}
+
+pub struct BenchDatabase {
+    config: DatabaseConnection,
+}
+
+impl BenchDatabase {
+    pub fn new(name: &str) -> MigrationResult<Self> {
+        let config = test_config(name);
+        create(&config)?;
+
+        Ok(BenchDatabase { config })
+    }
+
+    pub fn connection(&self) -> PgConnection {
+        self.config
+            .establish()
+            .unwrap_or_else(|e| panic!("Failed to connect to bench database: {}", e))
+    }
+
+    // Seeding is skipped when the table already has `rows` rows so nightly reruns stay fast.
+    pub fn seed(&self, rows: i64) -> QueryResult<PgConnection> {
+        let conn = self.connection();
+
+        conn.batch_execute(SCHEMA)?;
+
+        let count = bench_todos::table.count().get_result::<i64>(&conn)?;
+
+        if count != rows {
+            conn.transaction(|| {
+                conn.batch_execute("TRUNCATE bench_todos")?;
+                diesel::sql_query(SEED)
+                    .bind::<BigInt, _>(rows)
+                    .execute(&conn)
+            })?;
+
+            conn.batch_execute("ANALYZE bench_todos")?;
+        }
+
+        Ok(conn)
+    }
+}
@@ -0,0 +1,8 @@
+#[macro_use]
+extern crate diesel;
+
+mod database;
+mod todo;
+
+pub use crate::database::{bench_rows, BenchDatabase, BENCH_ROWS_VAR};
+pub use crate::todo::{cursor_at, resolve_todos, BenchTodo};
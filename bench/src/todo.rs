@@ -0,0 +1,65 @@
+use async_graphql::{Connection, ID};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use timada_relay::{to_id, ConnectionResult, Cursor};
+use uuid::Uuid;
+
+table! {
+    bench_todos (id) {
+        id -> Uuid,
+        text -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Cursor)]
+pub struct BenchTodo {
+    #[cursor(key)]
+    pub id: Uuid,
+    pub text: String,
+    #[cursor(order)]
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_graphql::Object]
+impl BenchTodo {
+    #[field]
+    async fn id(&self) -> ID {
+        to_id("Todo", &self.id)
+    }
+
+    #[field]
+    async fn text(&self) -> &str {
+        self.text.as_str()
+    }
+}
+
+pub fn resolve_todos(
+    conn: &PgConnection,
+    first: Option<usize>,
+    after: Option<String>,
+    last: Option<usize>,
+    before: Option<String>,
+) -> ConnectionResult<Connection<BenchTodo>> {
+    use self::bench_todos::dsl::{bench_todos, created_at, id};
+
+    let table = bench_todos.into_boxed();
+
+    timada_relay::resolve_connection!(
+        BenchTodo, conn, table, first, after, last, before, id, created_at
+    )
+}
+
+// Cursor of the row at `depth` in the forward order, to measure pages far from the start.
+pub fn cursor_at(conn: &PgConnection, depth: i64) -> QueryResult<String> {
+    use self::bench_todos::dsl::{bench_todos, created_at, id};
+
+    let todo = bench_todos
+        .order((created_at.asc(), id.asc()))
+        .offset(depth)
+        .first::<BenchTodo>(conn)?;
+    let (key, order) = todo.to_cursor();
+
+    Ok(timada_relay::to_cursor(&key, &order))
+}
@@ -0,0 +1,31 @@
+#[derive(Debug, PartialEq, Error)]
+pub enum CacheError {
+    #[error("Redis error: {0}")]
+    Redis(String),
+
+    #[error("Pool error: {0}")]
+    Pool(String),
+
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+}
+
+impl From<redis::RedisError> for CacheError {
+    fn from(e: redis::RedisError) -> CacheError {
+        CacheError::Redis(e.to_string())
+    }
+}
+
+impl From<r2d2::Error> for CacheError {
+    fn from(e: r2d2::Error) -> CacheError {
+        CacheError::Pool(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for CacheError {
+    fn from(e: serde_json::Error) -> CacheError {
+        CacheError::Serialization(e.to_string())
+    }
+}
+
+pub type CacheResult<T> = Result<T, CacheError>;
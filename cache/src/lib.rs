@@ -0,0 +1,12 @@
+#[macro_use]
+extern crate thiserror;
+
+mod error;
+mod lru;
+mod redis;
+mod resolver;
+
+pub use crate::error::{CacheError, CacheResult};
+pub use crate::lru::{CacheStats, LruCache};
+pub use crate::redis::{RedisCache, RedisConfig};
+pub use crate::resolver::{CacheBackend, MemoryBackend, ResolverCache};
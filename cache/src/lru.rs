@@ -0,0 +1,324 @@
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::error::CacheResult;
+use super::resolver::CacheBackend;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+struct Entry<V> {
+    value: V,
+    expires_at: Option<Instant>,
+    tick: u64,
+}
+
+struct Inner<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    order: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> Inner<K, V> {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn remove(&mut self, key: &K) -> Option<Entry<V>> {
+        let entry = self.entries.remove(key)?;
+        self.order.remove(&entry.tick);
+
+        Some(entry)
+    }
+
+    fn pop_oldest(&mut self) -> bool {
+        let tick = match self.order.keys().next() {
+            Some(tick) => *tick,
+            None => return false,
+        };
+
+        if let Some(key) = self.order.remove(&tick) {
+            self.entries.remove(&key);
+        }
+
+        true
+    }
+}
+
+pub struct LruCache<K, V> {
+    inner: Mutex<Inner<K, V>>,
+    capacity: usize,
+    ttl: Option<Duration>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl<K, V> LruCache<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    pub fn new(capacity: usize) -> Self {
+        LruCache {
+            inner: Mutex::new(Inner {
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+                tick: 0,
+            }),
+            capacity: capacity.max(1),
+            ttl: None,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner<K, V>> {
+        match self.inner.lock() {
+            Ok(inner) => inner,
+            Err(e) => e.into_inner(),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut inner = self.lock();
+        let now = Instant::now();
+
+        let expired = match inner.entries.get(key) {
+            Some(entry) => entry
+                .expires_at
+                .map_or(false, |expires_at| expires_at <= now),
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+        };
+
+        if expired {
+            inner.remove(key);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        let tick = inner.next_tick();
+        let entry = inner.entries.get_mut(key)?;
+        let previous = std::mem::replace(&mut entry.tick, tick);
+        let value = entry.value.clone();
+
+        inner.order.remove(&previous);
+        inner.order.insert(tick, key.clone());
+        self.hits.fetch_add(1, Ordering::Relaxed);
+
+        Some(value)
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        self.insert_with_ttl(key, value, self.ttl)
+    }
+
+    pub fn insert_with_ttl(&self, key: K, value: V, ttl: Option<Duration>) {
+        let mut inner = self.lock();
+
+        inner.remove(&key);
+
+        while inner.entries.len() >= self.capacity && inner.pop_oldest() {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let tick = inner.next_tick();
+        inner.order.insert(tick, key.clone());
+        inner.entries.insert(
+            key,
+            Entry {
+                value,
+                expires_at: ttl.map(|ttl| Instant::now() + ttl),
+                tick,
+            },
+        );
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.lock().remove(key).map(|entry| entry.value)
+    }
+
+    pub fn remove_where<F: Fn(&K) -> bool>(&self, predicate: F) -> usize {
+        let mut inner = self.lock();
+        let keys = inner
+            .entries
+            .keys()
+            .filter(|key| predicate(key))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for key in keys.iter() {
+            inner.remove(key);
+        }
+
+        keys.len()
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.entries.clear();
+        inner.order.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    pub async fn get_or_insert_with<F, Fut>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+
+        let value = f().await;
+        self.insert(key, value.clone());
+
+        value
+    }
+
+    pub async fn try_get_or_insert_with<E, F, Fut>(&self, key: K, f: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+
+        let value = f().await?;
+        self.insert(key, value.clone());
+
+        Ok(value)
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheBackend for LruCache<String, String> {
+    async fn get(&self, key: &str) -> CacheResult<Option<String>> {
+        Ok(LruCache::get(self, &key.to_owned()))
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> CacheResult<()> {
+        self.insert_with_ttl(key.to_owned(), value, Some(ttl));
+
+        Ok(())
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> CacheResult<usize> {
+        Ok(self.remove_where(|key| key.starts_with(prefix)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use super::{CacheStats, LruCache};
+
+    #[test]
+    fn eviction() {
+        let cache = LruCache::new(2);
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get(&"a"), Some(1));
+
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"c"), Some(3));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 3,
+                misses: 1,
+                evictions: 1
+            }
+        );
+    }
+
+    #[test]
+    fn ttl() {
+        let cache = LruCache::new(10).ttl(Duration::from_millis(20));
+
+        cache.insert("a", 1);
+        cache.insert_with_ttl("b", 2, None);
+        sleep(Duration::from_millis(40));
+
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b"), Some(2));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        let cache = LruCache::new(10);
+
+        assert_eq!(block_on(cache.get_or_insert_with("a", || async { 1 })), 1);
+        assert_eq!(block_on(cache.get_or_insert_with("a", || async { 2 })), 1);
+        assert_eq!(
+            block_on(cache.try_get_or_insert_with("b", || async { Err::<i32, _>("boom") })),
+            Err("boom")
+        );
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.stats().hit_ratio(), 0.25);
+    }
+
+    #[test]
+    fn remove_where() {
+        let cache = LruCache::new(10);
+
+        cache.insert("user:1".to_owned(), 1);
+        cache.insert("user:2".to_owned(), 2);
+        cache.insert("post:1".to_owned(), 3);
+
+        assert_eq!(cache.remove_where(|key| key.starts_with("user:")), 2);
+        assert_eq!(cache.remove(&"post:1".to_owned()), Some(3));
+        assert!(cache.is_empty());
+    }
+}
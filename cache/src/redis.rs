@@ -0,0 +1,222 @@
+use ::redis::{Client, Commands};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Duration;
+use timada_util::env::{self, EnvError};
+use timada_util::secret::{secret, Secret};
+
+use super::error::{CacheError, CacheResult};
+
+pub type Pool = r2d2::Pool<Client>;
+
+#[derive(Debug, Clone)]
+pub struct RedisConfig {
+    pub url: Secret<String>,
+    pub namespace: String,
+    pub pool_size: u32,
+    pub default_ttl: Option<Duration>,
+}
+
+impl RedisConfig {
+    pub fn new(url: &str, namespace: &str) -> Self {
+        RedisConfig {
+            url: Secret::from(url),
+            namespace: namespace.to_owned(),
+            pool_size: 10,
+            default_ttl: None,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        RedisConfig {
+            url: secret("REDIS_URL"),
+            namespace: env::var_or("CACHE_NAMESPACE", ""),
+            pool_size: match env::try_var_parsed("REDIS_POOL_SIZE") {
+                Err(EnvError::Missing(_)) => 10,
+                res => res.unwrap_or_else(|e| panic!("{}", e)),
+            },
+            default_ttl: match env::try_var_duration("CACHE_DEFAULT_TTL") {
+                Err(EnvError::Missing(_)) => None,
+                res => Some(res.unwrap_or_else(|e| panic!("{}", e))),
+            },
+        }
+    }
+
+    pub fn pool_size(mut self, pool_size: u32) -> Self {
+        self.pool_size = pool_size;
+        self
+    }
+
+    pub fn default_ttl(mut self, default_ttl: Duration) -> Self {
+        self.default_ttl = Some(default_ttl);
+        self
+    }
+}
+
+#[derive(Clone)]
+pub struct RedisCache {
+    pool: Pool,
+    namespace: String,
+    default_ttl: Option<Duration>,
+}
+
+impl RedisCache {
+    pub fn new(config: RedisConfig) -> CacheResult<Self> {
+        let client = Client::open(config.url.expose().as_str())?;
+        let pool = r2d2::Pool::builder()
+            .max_size(config.pool_size)
+            .build(client)?;
+
+        Ok(RedisCache {
+            pool,
+            namespace: config.namespace,
+            default_ttl: config.default_ttl,
+        })
+    }
+
+    pub fn from_env() -> CacheResult<Self> {
+        Self::new(RedisConfig::from_env())
+    }
+
+    pub fn pool(&self) -> &Pool {
+        &self.pool
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn key(&self, key: &str) -> String {
+        namespaced(&self.namespace, key)
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> CacheResult<Option<T>> {
+        match self.get_raw(key)? {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set<T: Serialize>(&self, key: &str, value: &T) -> CacheResult<()> {
+        match self.default_ttl {
+            Some(ttl) => self.set_ex(key, value, ttl),
+            None => {
+                let mut conn = self.pool.get()?;
+                let _: () = conn.set(self.key(key), serde_json::to_string(value)?)?;
+
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_ex<T: Serialize>(&self, key: &str, value: &T, ttl: Duration) -> CacheResult<()> {
+        self.set_raw(key, &serde_json::to_string(value)?, ttl)
+    }
+
+    pub fn delete(&self, key: &str) -> CacheResult<bool> {
+        let mut conn = self.pool.get()?;
+        let deleted: usize = conn.del(self.key(key))?;
+
+        Ok(deleted > 0)
+    }
+
+    pub fn exists(&self, key: &str) -> CacheResult<bool> {
+        let mut conn = self.pool.get()?;
+
+        Ok(conn.exists(self.key(key))?)
+    }
+
+    pub fn get_raw(&self, key: &str) -> CacheResult<Option<String>> {
+        let mut conn = self.pool.get()?;
+
+        Ok(conn.get(self.key(key))?)
+    }
+
+    pub fn set_raw(&self, key: &str, value: &str, ttl: Duration) -> CacheResult<()> {
+        let mut conn = self.pool.get()?;
+        let _: () = conn.set_ex(self.key(key), value, ttl_seconds(ttl)?)?;
+
+        Ok(())
+    }
+
+    pub fn delete_prefix(&self, prefix: &str) -> CacheResult<usize> {
+        let mut conn = self.pool.get()?;
+        let keys: Vec<String> = conn
+            .scan_match::<_, String>(format!("{}*", self.key(prefix)))?
+            .collect();
+
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        Ok(conn.del(keys)?)
+    }
+
+    pub fn get_or_set<T, F>(&self, key: &str, ttl: Duration, f: F) -> CacheResult<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> T,
+    {
+        if let Some(value) = self.get(key)? {
+            return Ok(value);
+        }
+
+        let value = f();
+        self.set_ex(key, &value, ttl)?;
+
+        Ok(value)
+    }
+}
+
+fn namespaced(namespace: &str, key: &str) -> String {
+    if namespace.is_empty() {
+        return key.to_owned();
+    }
+
+    format!("{}:{}", namespace, key)
+}
+
+fn ttl_seconds(ttl: Duration) -> CacheResult<usize> {
+    match ttl.as_secs() {
+        0 => Err(CacheError::Redis(
+            "TTL must be at least one second".to_owned(),
+        )),
+        secs => Ok(secs as usize),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use timada_util::env::test_scope;
+
+    use super::{namespaced, ttl_seconds, RedisConfig};
+
+    #[test]
+    fn namespace() {
+        assert_eq!(namespaced("users", "1"), "users:1");
+        assert_eq!(namespaced("", "1"), "1");
+    }
+
+    #[test]
+    fn ttl() {
+        assert_eq!(ttl_seconds(Duration::from_secs(60)), Ok(60));
+        assert!(ttl_seconds(Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn from_env() {
+        let _env = test_scope()
+            .set("REDIS_URL", "redis://localhost:6379")
+            .set("CACHE_NAMESPACE", "users")
+            .set("CACHE_DEFAULT_TTL", "5m")
+            .remove("REDIS_POOL_SIZE");
+
+        let config = RedisConfig::from_env();
+
+        assert_eq!(config.url.expose(), "redis://localhost:6379");
+        assert_eq!(config.namespace, "users");
+        assert_eq!(config.pool_size, 10);
+        assert_eq!(config.default_ttl, Some(Duration::from_secs(300)));
+    }
+}
@@ -0,0 +1,306 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use timada_http::{CacheScope, Context};
+use uuid::Uuid;
+
+use super::error::{CacheError, CacheResult};
+use super::redis::RedisCache;
+
+const KEY_PREFIX: &str = "resolver";
+
+#[async_trait::async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn get(&self, key: &str) -> CacheResult<Option<String>>;
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> CacheResult<()>;
+    async fn delete_prefix(&self, prefix: &str) -> CacheResult<usize>;
+}
+
+#[derive(Default)]
+pub struct MemoryBackend {
+    entries: RwLock<HashMap<String, (String, Instant)>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheBackend for MemoryBackend {
+    async fn get(&self, key: &str) -> CacheResult<Option<String>> {
+        let entries = self
+            .entries
+            .read()
+            .map_err(|e| CacheError::Pool(e.to_string()))?;
+
+        Ok(entries
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(value, _)| value.to_owned()))
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> CacheResult<()> {
+        let mut entries = self
+            .entries
+            .write()
+            .map_err(|e| CacheError::Pool(e.to_string()))?;
+
+        let now = Instant::now();
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+        entries.insert(key.to_owned(), (value, now + ttl));
+
+        Ok(())
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> CacheResult<usize> {
+        let mut entries = self
+            .entries
+            .write()
+            .map_err(|e| CacheError::Pool(e.to_string()))?;
+
+        let len = entries.len();
+        entries.retain(|key, _| !key.starts_with(prefix));
+
+        Ok(len - entries.len())
+    }
+}
+
+async fn blocking<T, F>(f: F) -> CacheResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> CacheResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| CacheError::Pool(e.to_string()))?
+}
+
+#[async_trait::async_trait]
+impl CacheBackend for RedisCache {
+    async fn get(&self, key: &str) -> CacheResult<Option<String>> {
+        let cache = self.clone();
+        let key = key.to_owned();
+
+        blocking(move || cache.get_raw(&key)).await
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> CacheResult<()> {
+        let cache = self.clone();
+        let key = key.to_owned();
+
+        blocking(move || cache.set_raw(&key, &value, ttl)).await
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> CacheResult<usize> {
+        let cache = self.clone();
+        let prefix = prefix.to_owned();
+
+        blocking(move || cache.delete_prefix(&prefix)).await
+    }
+}
+
+// Private entries are kept per user and organization, the same user resolves different
+// values in each organization. The user comes first so `invalidate_user` matches them all.
+fn scope_key(context: &Context, scope: CacheScope) -> String {
+    let organization = context
+        .organization_id
+        .map(|organization_id| organization_id.to_string())
+        .unwrap_or_else(|| "-".to_owned());
+
+    match (scope, context.user.as_ref()) {
+        (CacheScope::Public, _) => "public".to_owned(),
+        (CacheScope::Private, Some(user)) => format!("{}:{}", user.id, organization),
+        (CacheScope::Private, None) => format!("anonymous:{}", organization),
+    }
+}
+
+#[derive(Clone)]
+pub struct ResolverCache {
+    backend: Arc<dyn CacheBackend>,
+    ttl: Duration,
+}
+
+impl ResolverCache {
+    pub fn new<B: CacheBackend + 'static>(backend: B) -> Self {
+        ResolverCache {
+            backend: Arc::new(backend),
+            ttl: Duration::from_secs(60),
+        }
+    }
+
+    pub fn memory() -> Self {
+        Self::new(MemoryBackend::new())
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn key<A: Serialize>(
+        &self,
+        context: &Context,
+        name: &str,
+        scope: CacheScope,
+        args: &A,
+    ) -> CacheResult<String> {
+        let args = serde_json::to_vec(args)?;
+
+        Ok(format!(
+            "{}:{}:{}:{}",
+            KEY_PREFIX,
+            name,
+            scope_key(context, scope),
+            hex::encode(Sha256::digest(&args))
+        ))
+    }
+
+    pub async fn resolve<T, A, E, F, Fut>(
+        &self,
+        context: &Context,
+        name: &str,
+        scope: CacheScope,
+        args: &A,
+        f: F,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+        A: Serialize,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let key = match self.key(context, name, scope, args) {
+            Ok(key) => key,
+            Err(e) => {
+                log::warn!("resolver cache key for {} failed: {}", name, e);
+                return f().await;
+            }
+        };
+
+        match self.backend.get(&key).await {
+            Ok(Some(value)) => match serde_json::from_str(&value) {
+                Ok(value) => return Ok(value),
+                Err(e) => log::warn!("resolver cache entry {} is invalid: {}", key, e),
+            },
+            Ok(None) => {}
+            Err(e) => log::warn!("resolver cache get {} failed: {}", key, e),
+        }
+
+        let value = f().await?;
+
+        match serde_json::to_string(&value) {
+            Ok(serialized) => {
+                if let Err(e) = self.backend.set(&key, serialized, self.ttl).await {
+                    log::warn!("resolver cache set {} failed: {}", key, e);
+                }
+            }
+            Err(e) => log::warn!("resolver cache entry {} is not serializable: {}", key, e),
+        }
+
+        Ok(value)
+    }
+
+    pub async fn invalidate(&self, name: &str) -> CacheResult<usize> {
+        self.backend
+            .delete_prefix(&format!("{}:{}:", KEY_PREFIX, name))
+            .await
+    }
+
+    pub async fn invalidate_user(&self, name: &str, user_id: Uuid) -> CacheResult<usize> {
+        self.backend
+            .delete_prefix(&format!("{}:{}:{}:", KEY_PREFIX, name, user_id))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use timada_http::testing::ContextBuilder;
+    use timada_http::{CacheScope, Context};
+    use uuid::Uuid;
+
+    use super::ResolverCache;
+
+    fn count(cache: &ResolverCache, context: &Context, calls: &AtomicUsize, id: u32) -> u32 {
+        block_on(
+            cache.resolve(context, "count", CacheScope::Private, &id, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, ()>(id * 2)
+            }),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn resolve() {
+        let cache = ResolverCache::memory();
+        let calls = AtomicUsize::new(0);
+        let context = ContextBuilder::new().build();
+
+        assert_eq!(count(&cache, &context, &calls, 1), 2);
+        assert_eq!(count(&cache, &context, &calls, 1), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        assert_eq!(count(&cache, &context, &calls, 2), 4);
+        assert_eq!(count(&cache, &ContextBuilder::new().build(), &calls, 1), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn resolve_organization() {
+        let cache = ResolverCache::memory();
+        let calls = AtomicUsize::new(0);
+        let context = ContextBuilder::new().organization(Uuid::new_v4()).build();
+        let other = Context {
+            organization_id: Some(Uuid::new_v4()),
+            ..context.clone()
+        };
+
+        count(&cache, &context, &calls, 1);
+        count(&cache, &context, &calls, 1);
+        count(&cache, &other, &calls, 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let user_id = context.user.as_ref().unwrap().id;
+        assert_eq!(block_on(cache.invalidate_user("count", user_id)), Ok(2));
+    }
+
+    #[test]
+    fn invalidate() {
+        let cache = ResolverCache::memory();
+        let calls = AtomicUsize::new(0);
+        let context = ContextBuilder::new().build();
+        let other = ContextBuilder::new().build();
+
+        count(&cache, &context, &calls, 1);
+        count(&cache, &other, &calls, 1);
+
+        let user_id = context.user.as_ref().unwrap().id;
+        assert_eq!(block_on(cache.invalidate_user("count", user_id)), Ok(1));
+        assert_eq!(block_on(cache.invalidate("count")), Ok(1));
+        assert_eq!(block_on(cache.invalidate("count")), Ok(0));
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let cache = ResolverCache::memory();
+        let context = Context::default();
+
+        let res = block_on(
+            cache.resolve(&context, "failing", CacheScope::Public, &(), || async {
+                Err::<u32, _>("boom")
+            }),
+        );
+
+        assert_eq!(res, Err("boom"));
+        assert_eq!(block_on(cache.invalidate("failing")), Ok(0));
+    }
+}
@@ -0,0 +1,277 @@
+use super::error::{CliError, CliResult};
+
+pub const USAGE: &str = "Usage:
+    timada db <setup|migrate|reset|fixture|status>
+    timada schema export [--output <path>]
+    timada schema check [--snapshot <path>]
+    timada jobs run [--queue <name>]... [--concurrency <n>]
+    timada search backfill [<index>]... [--batch-size <n>]
+    timada config check
+    timada codegen <schema.rs> [--output <path>] [--table <name>]... [--schema-module <path>]";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DbCommand {
+    Setup,
+    Migrate,
+    Reset,
+    Fixture,
+    Status,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Db(DbCommand),
+    SchemaExport {
+        output: Option<String>,
+    },
+    SchemaCheck {
+        snapshot: String,
+    },
+    JobsRun {
+        queues: Vec<String>,
+        concurrency: Option<usize>,
+    },
+    SearchBackfill {
+        indexes: Vec<String>,
+        batch_size: Option<usize>,
+    },
+    ConfigCheck,
+    Codegen {
+        schema: String,
+        output: Option<String>,
+        tables: Vec<String>,
+        schema_module: Option<String>,
+    },
+    Help,
+}
+
+fn flag_value<'a, I: Iterator<Item = &'a str>>(flag: &str, args: &mut I) -> CliResult<&'a str> {
+    args.next()
+        .ok_or_else(|| CliError::Usage(format!("Missing value for {}", flag)))
+}
+
+pub fn parse<S: AsRef<str>>(args: &[S]) -> CliResult<Command> {
+    let mut args = args.iter().map(|arg| arg.as_ref());
+
+    let command = match (args.next(), args.next()) {
+        (None, _) | (Some("help"), _) | (Some("--help"), _) | (Some("-h"), _) => {
+            return Ok(Command::Help)
+        }
+        (Some("db"), Some("setup")) => Command::Db(DbCommand::Setup),
+        (Some("db"), Some("migrate")) => Command::Db(DbCommand::Migrate),
+        (Some("db"), Some("reset")) => Command::Db(DbCommand::Reset),
+        (Some("db"), Some("fixture")) => Command::Db(DbCommand::Fixture),
+        (Some("db"), Some("status")) => Command::Db(DbCommand::Status),
+        (Some("schema"), Some("export")) => {
+            let mut output = None;
+
+            while let Some(arg) = args.next() {
+                match arg {
+                    "--output" | "-o" => output = Some(flag_value(arg, &mut args)?.to_owned()),
+                    _ => return Err(CliError::Usage(format!("Unknown argument {}", arg))),
+                }
+            }
+
+            Command::SchemaExport { output }
+        }
+        (Some("schema"), Some("check")) => {
+            let mut snapshot = "schema.graphql".to_owned();
+
+            while let Some(arg) = args.next() {
+                match arg {
+                    "--snapshot" | "-s" => snapshot = flag_value(arg, &mut args)?.to_owned(),
+                    _ => return Err(CliError::Usage(format!("Unknown argument {}", arg))),
+                }
+            }
+
+            Command::SchemaCheck { snapshot }
+        }
+        (Some("jobs"), Some("run")) => {
+            let mut queues = Vec::new();
+            let mut concurrency = None;
+
+            while let Some(arg) = args.next() {
+                match arg {
+                    "--queue" | "-q" => queues.push(flag_value(arg, &mut args)?.to_owned()),
+                    "--concurrency" | "-c" => {
+                        let value = flag_value(arg, &mut args)?;
+                        concurrency = Some(value.parse().map_err(|_| {
+                            CliError::Usage(format!("Invalid concurrency {}", value))
+                        })?);
+                    }
+                    _ => return Err(CliError::Usage(format!("Unknown argument {}", arg))),
+                }
+            }
+
+            Command::JobsRun {
+                queues,
+                concurrency,
+            }
+        }
+        (Some("search"), Some("backfill")) => {
+            let mut indexes = Vec::new();
+            let mut batch_size = None;
+
+            while let Some(arg) = args.next() {
+                match arg {
+                    "--batch-size" | "-b" => {
+                        let value = flag_value(arg, &mut args)?;
+                        batch_size = Some(value.parse().map_err(|_| {
+                            CliError::Usage(format!("Invalid batch size {}", value))
+                        })?);
+                    }
+                    _ if arg.starts_with('-') => {
+                        return Err(CliError::Usage(format!("Unknown argument {}", arg)))
+                    }
+                    _ => indexes.push(arg.to_owned()),
+                }
+            }
+
+            Command::SearchBackfill {
+                indexes,
+                batch_size,
+            }
+        }
+        (Some("config"), Some("check")) => Command::ConfigCheck,
+        (Some("codegen"), Some(schema)) => {
+            let mut output = None;
+            let mut tables = Vec::new();
+            let mut schema_module = None;
+
+            while let Some(arg) = args.next() {
+                match arg {
+                    "--output" | "-o" => output = Some(flag_value(arg, &mut args)?.to_owned()),
+                    "--table" | "-t" => tables.push(flag_value(arg, &mut args)?.to_owned()),
+                    "--schema-module" => {
+                        schema_module = Some(flag_value(arg, &mut args)?.to_owned())
+                    }
+                    _ => return Err(CliError::Usage(format!("Unknown argument {}", arg))),
+                }
+            }
+
+            Command::Codegen {
+                schema: schema.to_owned(),
+                output,
+                tables,
+                schema_module,
+            }
+        }
+        (Some(command), Some(sub)) => {
+            return Err(CliError::Usage(format!(
+                "Unknown command {} {}",
+                command, sub
+            )))
+        }
+        (Some("codegen"), None) => return Err(CliError::Usage("Missing schema path".to_owned())),
+        (Some(command), None) => {
+            return Err(CliError::Usage(format!("Unknown command {}", command)))
+        }
+    };
+
+    match command {
+        Command::SchemaExport { .. }
+        | Command::SchemaCheck { .. }
+        | Command::JobsRun { .. }
+        | Command::SearchBackfill { .. }
+        | Command::Codegen { .. } => Ok(command),
+        _ => match args.next() {
+            Some(arg) => Err(CliError::Usage(format!("Unknown argument {}", arg))),
+            None => Ok(command),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Command, DbCommand};
+    use crate::error::CliError;
+
+    #[test]
+    fn commands() {
+        assert_eq!(parse::<&str>(&[]), Ok(Command::Help));
+        assert_eq!(parse(&["db", "setup"]), Ok(Command::Db(DbCommand::Setup)));
+        assert_eq!(parse(&["db", "status"]), Ok(Command::Db(DbCommand::Status)));
+        assert_eq!(parse(&["config", "check"]), Ok(Command::ConfigCheck));
+        assert_eq!(
+            parse(&["schema", "export", "--output", "schema.graphql"]),
+            Ok(Command::SchemaExport {
+                output: Some("schema.graphql".to_owned())
+            })
+        );
+        assert_eq!(
+            parse(&["schema", "check"]),
+            Ok(Command::SchemaCheck {
+                snapshot: "schema.graphql".to_owned()
+            })
+        );
+        assert_eq!(
+            parse(&["schema", "check", "-s", "api/schema.graphql"]),
+            Ok(Command::SchemaCheck {
+                snapshot: "api/schema.graphql".to_owned()
+            })
+        );
+        assert_eq!(
+            parse(&["jobs", "run", "-q", "mails", "--queue", "exports", "-c", "4"]),
+            Ok(Command::JobsRun {
+                queues: vec!["mails".to_owned(), "exports".to_owned()],
+                concurrency: Some(4),
+            })
+        );
+        assert_eq!(
+            parse(&["search", "backfill"]),
+            Ok(Command::SearchBackfill {
+                indexes: vec![],
+                batch_size: None,
+            })
+        );
+        assert_eq!(
+            parse(&["search", "backfill", "todos", "users", "-b", "100"]),
+            Ok(Command::SearchBackfill {
+                indexes: vec!["todos".to_owned(), "users".to_owned()],
+                batch_size: Some(100),
+            })
+        );
+        assert_eq!(
+            parse(&[
+                "codegen",
+                "src/schema.rs",
+                "-t",
+                "todos",
+                "-o",
+                "src/models.rs"
+            ]),
+            Ok(Command::Codegen {
+                schema: "src/schema.rs".to_owned(),
+                output: Some("src/models.rs".to_owned()),
+                tables: vec!["todos".to_owned()],
+                schema_module: None,
+            })
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse(&["db", "drop"]),
+            Err(CliError::Usage("Unknown command db drop".to_owned()))
+        );
+        assert_eq!(
+            parse(&["db", "setup", "now"]),
+            Err(CliError::Usage("Unknown argument now".to_owned()))
+        );
+        assert_eq!(
+            parse(&["jobs", "run", "--concurrency"]),
+            Err(CliError::Usage(
+                "Missing value for --concurrency".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse(&["jobs", "run", "-c", "many"]),
+            Err(CliError::Usage("Invalid concurrency many".to_owned()))
+        );
+        assert_eq!(
+            parse(&["search", "backfill", "--all"]),
+            Err(CliError::Usage("Unknown argument --all".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,504 @@
+use async_graphql::{ObjectType, SubscriptionType};
+use diesel::r2d2::ConnectionManager;
+use diesel::PgConnection;
+use diesel_migrations::RunMigrationsError;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+use timada_codegen::{generate, generate_file, parse_schema, Options};
+use timada_database::{DatabaseConnection, Pool};
+use timada_http::{diff_sdl, export_sdl, SchemaChange, Shutdown};
+use timada_jobs::Worker;
+use timada_util::env;
+use timada_util::secret::try_secret;
+
+use super::args::{parse, Command, DbCommand, USAGE};
+use super::error::{CliError, CliResult};
+
+type Migrate = fn(&PgConnection) -> Result<(), RunMigrationsError>;
+type Check = Box<dyn Fn() -> Result<(), Vec<String>>>;
+type Schema = Box<dyn Fn() -> String>;
+type BuildWorker = Box<dyn Fn(Pool) -> Worker>;
+type Backfill = Box<dyn Fn(&PgConnection, usize) -> CliResult<usize>>;
+
+const BACKFILL_BATCH_SIZE: usize = 500;
+
+pub struct Cli {
+    migrations: Vec<(&'static str, Migrate)>,
+    schema: Option<Schema>,
+    worker: Option<BuildWorker>,
+    backfills: Vec<(&'static str, Backfill)>,
+    checks: Vec<(String, Check)>,
+    shutdown_deadline: Duration,
+}
+
+impl Default for Cli {
+    fn default() -> Self {
+        Cli::new()
+    }
+}
+
+pub fn database() -> CliResult<DatabaseConnection> {
+    let mut errors = Vec::new();
+
+    let host = env::try_var("DB_HOST").map_err(|e| errors.push(e.to_string()));
+    let user = try_secret("DB_USER").map_err(|e| errors.push(e.to_string()));
+    let password = try_secret("DB_PASSWORD").map_err(|e| errors.push(e.to_string()));
+    let name = env::try_var("DB_NAME").map_err(|e| errors.push(e.to_string()));
+
+    match (host, user, password, name) {
+        (Ok(host), Ok(user), Ok(password), Ok(name)) => Ok(DatabaseConnection {
+            host,
+            user: user.into_inner(),
+            password,
+            name: Some(name),
+        }),
+        _ => Err(CliError::Config(errors)),
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map(|message| (*message).to_owned())
+            .unwrap_or_else(|| "panicked".to_owned()),
+    }
+}
+
+fn codegen(schema: &str, output: Option<String>, options: &Options) -> CliResult<()> {
+    match output {
+        Some(output) => {
+            if generate_file(schema, &output, options)? {
+                println!("Generated {}", output);
+            }
+        }
+        None => {
+            let source = fs::read_to_string(schema)?;
+            print!("{}", generate(&parse_schema(&source)?, options)?);
+        }
+    }
+
+    Ok(())
+}
+
+impl Cli {
+    pub fn new() -> Self {
+        Cli {
+            migrations: Vec::new(),
+            schema: None,
+            worker: None,
+            backfills: Vec::new(),
+            checks: Vec::new(),
+            shutdown_deadline: Duration::from_secs(30),
+        }
+        .check("database", || {
+            let config = database().map_err(|e| match e {
+                CliError::Config(errors) => errors,
+                e => vec![e.to_string()],
+            })?;
+
+            config
+                .establish()
+                .map(|_| ())
+                .map_err(|e| vec![e.to_string()])
+        })
+    }
+
+    // Library migrations run before the app `migrations` directory so app
+    // tables can reference them.
+    pub fn migrations(mut self, name: &'static str, migrate: Migrate) -> Self {
+        self.migrations.push((name, migrate));
+        self
+    }
+
+    pub fn library_migrations(self) -> Self {
+        #[allow(unused_mut)]
+        let mut cli = self;
+
+        #[cfg(feature = "auth")]
+        {
+            cli = cli.migrations("auth", timada_auth::migrate);
+        }
+
+        cli = cli.migrations("jobs", timada_jobs::migrate);
+
+        #[cfg(feature = "events")]
+        {
+            cli = cli.migrations("events", timada_events::migrate);
+        }
+
+        #[cfg(feature = "audit")]
+        {
+            cli = cli.migrations("audit", timada_audit::migrate);
+        }
+
+        #[cfg(feature = "flags")]
+        {
+            cli = cli.migrations("flags", timada_flags::migrate);
+        }
+
+        #[cfg(feature = "storage")]
+        {
+            cli = cli.migrations("storage", timada_storage::migrate);
+        }
+
+        #[cfg(feature = "notifications")]
+        {
+            cli = cli.migrations("notifications", timada_notifications::migrate);
+        }
+
+        cli
+    }
+
+    pub fn schema<F>(mut self, sdl: F) -> Self
+    where
+        F: Fn() -> String + 'static,
+    {
+        self.schema = Some(Box::new(sdl));
+        self
+    }
+
+    pub fn graphql_schema<Query, Mutation, Subscription>(
+        self,
+        schema: async_graphql::Schema<Query, Mutation, Subscription>,
+    ) -> Self
+    where
+        Query: ObjectType + Send + Sync + 'static,
+        Mutation: ObjectType + Send + Sync + 'static,
+        Subscription: SubscriptionType + Send + Sync + 'static,
+    {
+        self.schema(move || {
+            futures::executor::block_on(export_sdl(&schema)).unwrap_or_else(|e| panic!("{}", e))
+        })
+    }
+
+    pub fn worker<F>(mut self, worker: F) -> Self
+    where
+        F: Fn(Pool) -> Worker + 'static,
+    {
+        self.worker = Some(Box::new(worker));
+        self
+    }
+
+    // `load` reads a page of models by offset and limit, `timada search backfill` indexes
+    // them with the backend set by `set_search_backend`.
+    #[cfg(feature = "search")]
+    pub fn search_backfill<M, F>(mut self, load: F) -> Self
+    where
+        M: timada_search::Indexable,
+        F: Fn(&PgConnection, usize, usize) -> timada_search::SearchResult<Vec<M>> + 'static,
+    {
+        self.backfills.push((
+            M::INDEX,
+            Box::new(move |conn, batch_size| {
+                let backend = timada_search::search_backend()
+                    .ok_or(CliError::NotRegistered("search backend"))?;
+
+                actix_rt::System::new("timada-search")
+                    .block_on(timada_search::backfill(
+                        &*backend,
+                        batch_size,
+                        |offset, limit| load(conn, offset, limit),
+                    ))
+                    .map_err(|e| CliError::Search(e.to_string()))
+            }),
+        ));
+        self
+    }
+
+    // Checks may panic like the `from_env` constructors do, the panic is reported as a failure.
+    pub fn check<F>(mut self, name: &str, check: F) -> Self
+    where
+        F: Fn() -> Result<(), Vec<String>> + 'static,
+    {
+        self.checks.push((name.to_owned(), Box::new(check)));
+        self
+    }
+
+    pub fn shutdown_deadline(mut self, deadline: Duration) -> Self {
+        self.shutdown_deadline = deadline;
+        self
+    }
+
+    pub fn run(self) -> CliResult<()> {
+        let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+        self.run_with(&args)
+    }
+
+    pub fn run_with<S: AsRef<str>>(self, args: &[S]) -> CliResult<()> {
+        match parse(args)? {
+            Command::Help => {
+                println!("{}", USAGE);
+                Ok(())
+            }
+            Command::Db(command) => self.db(command),
+            Command::SchemaExport { output } => self.export_schema(output),
+            Command::SchemaCheck { snapshot } => self.check_schema(&snapshot).map(|_| ()),
+            Command::JobsRun {
+                queues,
+                concurrency,
+            } => self.run_jobs(queues, concurrency),
+            Command::SearchBackfill {
+                indexes,
+                batch_size,
+            } => self.backfill(&indexes, batch_size.unwrap_or(BACKFILL_BATCH_SIZE)),
+            Command::ConfigCheck => self.check_config().map(|_| ()),
+            Command::Codegen {
+                schema,
+                output,
+                tables,
+                schema_module,
+            } => {
+                let mut options = tables
+                    .iter()
+                    .fold(Options::default(), |options, table| options.table(table));
+
+                if let Some(schema_module) = schema_module {
+                    options = options.schema_module(&schema_module);
+                }
+
+                codegen(&schema, output, &options)
+            }
+        }
+    }
+
+    fn migrate(&self, config: &DatabaseConnection) -> CliResult<()> {
+        let conn = config.establish()?;
+
+        for (name, migrate) in self.migrations.iter() {
+            log::info!("Running {} migrations", name);
+            migrate(&conn)?;
+        }
+
+        Ok(timada_database::migrate(&conn, "migrations")?)
+    }
+
+    fn db(&self, command: DbCommand) -> CliResult<()> {
+        let config = database()?;
+
+        match command {
+            DbCommand::Setup => {
+                timada_database::create(&config)?;
+                self.migrate(&config)
+            }
+            DbCommand::Migrate => self.migrate(&config),
+            DbCommand::Reset => {
+                timada_database::recreate(&config)?;
+                self.migrate(&config)
+            }
+            DbCommand::Fixture => Ok(timada_database::fixture(&config)?),
+            DbCommand::Status => {
+                for (version, applied) in timada_database::status(&config, "migrations")? {
+                    println!("[{}] {}", if applied { "X" } else { " " }, version);
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn export_schema(&self, output: Option<String>) -> CliResult<()> {
+        let sdl = self
+            .schema
+            .as_ref()
+            .ok_or(CliError::NotRegistered("schema"))?();
+
+        match output {
+            Some(path) => Ok(fs::write(path, sdl)?),
+            None => {
+                println!("{}", sdl);
+                Ok(())
+            }
+        }
+    }
+
+    // Diffs the current schema against the committed snapshot, only breaking changes fail.
+    pub fn check_schema(&self, snapshot: &str) -> CliResult<Vec<SchemaChange>> {
+        let sdl = self
+            .schema
+            .as_ref()
+            .ok_or(CliError::NotRegistered("schema"))?();
+        let old = fs::read_to_string(snapshot)?;
+        let changes = diff_sdl(&old, &sdl).map_err(CliError::Schema)?;
+
+        for change in changes.iter() {
+            println!("{}", change);
+        }
+
+        let breaking = changes
+            .iter()
+            .filter(|change| change.breaking)
+            .map(|change| change.message.clone())
+            .collect::<Vec<_>>();
+
+        if breaking.is_empty() {
+            Ok(changes)
+        } else {
+            Err(CliError::BreakingChanges(breaking))
+        }
+    }
+
+    fn run_jobs(&self, queues: Vec<String>, concurrency: Option<usize>) -> CliResult<()> {
+        let build = self
+            .worker
+            .as_ref()
+            .ok_or(CliError::NotRegistered("worker"))?;
+        let config = database()?;
+        let pool = Pool::builder()
+            .build(ConnectionManager::<PgConnection>::new(
+                config.connection_url(),
+            ))
+            .map_err(|e| CliError::Database(e.to_string()))?;
+
+        let mut worker = queues
+            .iter()
+            .fold(build(pool.clone()), |worker, queue| worker.queue(queue));
+
+        if let Some(concurrency) = concurrency {
+            worker = worker.concurrency(concurrency);
+        }
+
+        log::info!("Running jobs {}", worker.job_types().join(", "));
+
+        let shutdown = Shutdown::new(self.shutdown_deadline).pool(pool);
+        let handle = shutdown.handle();
+
+        actix_rt::System::new("timada-jobs").block_on(async move {
+            actix_rt::spawn(worker.run(handle));
+            shutdown.wait().await
+        })?;
+
+        Ok(())
+    }
+
+    // Indexes every registered model when no index is given.
+    fn backfill(&self, indexes: &[String], batch_size: usize) -> CliResult<()> {
+        if self.backfills.is_empty() {
+            return Err(CliError::NotRegistered("search backfill"));
+        }
+
+        if let Some(index) = indexes
+            .iter()
+            .find(|index| !self.backfills.iter().any(|(name, _)| name == index))
+        {
+            return Err(CliError::Usage(format!("Unknown search index {}", index)));
+        }
+
+        let conn = database()?.establish()?;
+
+        for (name, backfill) in self.backfills.iter() {
+            if !indexes.is_empty() && !indexes.iter().any(|index| index == name) {
+                continue;
+            }
+
+            let indexed = backfill(&conn, batch_size)?;
+            println!("Indexed {} {} documents", indexed, name);
+        }
+
+        Ok(())
+    }
+
+    pub fn check_config(&self) -> CliResult<usize> {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+
+        let mut errors = Vec::new();
+
+        for (name, check) in self.checks.iter() {
+            let res = panic::catch_unwind(AssertUnwindSafe(|| check()))
+                .unwrap_or_else(|payload| Err(vec![panic_message(payload)]));
+
+            match res {
+                Ok(_) => println!("[ok] {}", name),
+                Err(e) => {
+                    println!("[error] {}", name);
+                    errors.extend(e.into_iter().map(|e| format!("{}: {}", name, e)));
+                }
+            }
+        }
+
+        panic::set_hook(hook);
+
+        if errors.is_empty() {
+            Ok(self.checks.len())
+        } else {
+            Err(CliError::Config(errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use timada_util::env::test_scope;
+
+    use super::Cli;
+    use crate::error::CliError;
+
+    #[test]
+    fn check_config() {
+        let _scope = test_scope().remove("DB_HOST");
+
+        let res = Cli::new()
+            .check("server", || Ok(()))
+            .check("mail", || panic!("Missing SMTP_HOST"))
+            .check_config();
+
+        match res {
+            Err(CliError::Config(errors)) => {
+                assert!(errors[0].starts_with("database: "));
+                assert_eq!(errors.last().unwrap(), "mail: Missing SMTP_HOST");
+                assert!(!errors.iter().any(|e| e.starts_with("server: ")));
+            }
+            res => panic!("unexpected {:?}", res),
+        }
+    }
+
+    #[test]
+    fn not_registered() {
+        assert_eq!(
+            Cli::new().run_with(&["schema", "export"]),
+            Err(CliError::NotRegistered("schema"))
+        );
+        assert_eq!(
+            Cli::new().run_with(&["jobs", "run"]),
+            Err(CliError::NotRegistered("worker"))
+        );
+        assert_eq!(
+            Cli::new().run_with(&["search", "backfill"]),
+            Err(CliError::NotRegistered("search backfill"))
+        );
+    }
+
+    #[test]
+    fn check_schema() {
+        let snapshot = std::env::temp_dir().join("timada-cli-check-schema.graphql");
+        let snapshot = snapshot.to_str().unwrap();
+        fs::write(
+            snapshot,
+            "type Query {\n  todos: [String!]!\n  count: Int\n}\n",
+        )
+        .unwrap();
+
+        let changes = Cli::new()
+            .schema(|| "type Query {\n  todos: [String!]!\n  count: Int!\n}\n".to_owned())
+            .check_schema(snapshot)
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert!(!changes[0].breaking);
+
+        assert_eq!(
+            Cli::new()
+                .schema(|| "type Query {\n  count: Int\n}\n".to_owned())
+                .check_schema(snapshot),
+            Err(CliError::BreakingChanges(vec![
+                "Query.todos was removed".to_owned()
+            ]))
+        );
+
+        fs::remove_file(snapshot).unwrap();
+    }
+}
@@ -0,0 +1,63 @@
+use diesel::ConnectionError;
+use diesel_migrations::RunMigrationsError;
+use timada_codegen::CodegenError;
+use timada_database::MigrationError;
+
+#[derive(Debug, PartialEq, Error)]
+pub enum CliError {
+    #[error("{0}")]
+    Usage(String),
+
+    #[error("Database error: {0}")]
+    Database(String),
+
+    #[error("Invalid configuration:\n{}", .0.join("\n"))]
+    Config(Vec<String>),
+
+    #[error("Invalid schema: {0}")]
+    Schema(String),
+
+    #[error("Breaking schema changes:\n{}", .0.join("\n"))]
+    BreakingChanges(Vec<String>),
+
+    #[error("No {0} registered")]
+    NotRegistered(&'static str),
+
+    #[error("{0}")]
+    Io(String),
+
+    #[error("Search error: {0}")]
+    Search(String),
+}
+
+impl From<MigrationError> for CliError {
+    fn from(e: MigrationError) -> CliError {
+        CliError::Database(format!("{:?}", e))
+    }
+}
+
+impl From<RunMigrationsError> for CliError {
+    fn from(e: RunMigrationsError) -> CliError {
+        CliError::Database(e.to_string())
+    }
+}
+
+impl From<ConnectionError> for CliError {
+    fn from(e: ConnectionError) -> CliError {
+        CliError::Database(e.to_string())
+    }
+}
+
+impl From<CodegenError> for CliError {
+    fn from(e: CodegenError) -> CliError {
+        CliError::Io(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(e: std::io::Error) -> CliError {
+        CliError::Io(e.to_string())
+    }
+}
+
+pub type CliResult<T> = Result<T, CliError>;
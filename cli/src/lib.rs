@@ -0,0 +1,10 @@
+#[macro_use]
+extern crate thiserror;
+
+mod args;
+mod cli;
+mod error;
+
+pub use crate::args::{parse, Command, DbCommand, USAGE};
+pub use crate::cli::{database, Cli};
+pub use crate::error::{CliError, CliResult};
@@ -0,0 +1,18 @@
+use std::process;
+use timada_cli::{Cli, CliError, USAGE};
+
+fn main() {
+    let res = Cli::new().library_migrations().run();
+
+    match res {
+        Ok(_) => {}
+        Err(CliError::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,22 @@
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CodegenError {
+    #[error("Invalid schema: {0}")]
+    Parse(String),
+
+    #[error("Unsupported type {1} for {0}")]
+    UnsupportedType(String, String),
+
+    #[error("Table {0} not found")]
+    TableNotFound(String),
+
+    #[error("{0}")]
+    Io(String),
+}
+
+impl From<std::io::Error> for CodegenError {
+    fn from(e: std::io::Error) -> CodegenError {
+        CodegenError::Io(e.to_string())
+    }
+}
+
+pub type CodegenResult<T> = Result<T, CodegenError>;
@@ -0,0 +1,470 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use super::error::{CodegenError, CodegenResult};
+use super::parse::{parse_schema, Table};
+use super::types::{rust_type, RustType};
+
+const HEADER: &str = "// @generated by timada-codegen, do not edit by hand.";
+
+pub struct Options {
+    schema_module: String,
+    tables: Vec<String>,
+    hidden: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            schema_module: "super::schema".to_owned(),
+            tables: Vec::new(),
+            hidden: vec![
+                "password".to_owned(),
+                "secret".to_owned(),
+                "_hash".to_owned(),
+            ],
+        }
+    }
+}
+
+impl Options {
+    pub fn schema_module(mut self, schema_module: &str) -> Self {
+        self.schema_module = schema_module.to_owned();
+        self
+    }
+
+    // Only generate the given tables, every table is generated by default.
+    pub fn table(mut self, table: &str) -> Self {
+        self.tables.push(table.to_owned());
+        self
+    }
+
+    // Columns containing one of these patterns stay on the struct but are not exposed to GraphQL.
+    pub fn hide(mut self, pattern: &str) -> Self {
+        self.hidden.push(pattern.to_owned());
+        self
+    }
+
+    fn is_hidden(&self, column: &str) -> bool {
+        self.hidden
+            .iter()
+            .any(|pattern| column.contains(pattern.as_str()))
+    }
+}
+
+pub fn singular(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if name.ends_with("sses") || name.ends_with("xes") || name.ends_with("ches") {
+        name[..name.len() - 2].to_owned()
+    } else if name.ends_with('s') && !name.ends_with("ss") {
+        name[..name.len() - 1].to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+pub fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+// Mirrors rustfmt ordering where lowercase segments come before uppercase ones.
+fn sort_key(value: &str) -> Vec<(u8, char)> {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_uppercase() {
+                (1, c.to_ascii_lowercase())
+            } else {
+                (0, c)
+            }
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Imports(BTreeMap<String, BTreeSet<String>>);
+
+impl Imports {
+    fn add(&mut self, path: &str, item: &str) {
+        self.0
+            .entry(path.to_owned())
+            .or_default()
+            .insert(item.to_owned());
+    }
+
+    fn render(&self) -> Vec<String> {
+        let mut lines = self
+            .0
+            .iter()
+            .map(|(path, items)| {
+                let mut items = items.iter().collect::<Vec<_>>();
+                items.sort_by_key(|item| sort_key(item));
+
+                match items.as_slice() {
+                    [item] => format!("use {}::{};", path, item),
+                    items => format!(
+                        "use {}::{{{}}};",
+                        path,
+                        items
+                            .iter()
+                            .map(|item| item.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                }
+            })
+            .collect::<Vec<_>>();
+        lines.sort_by_key(|line| sort_key(line));
+        lines
+    }
+}
+
+struct Model<'a> {
+    table: &'a Table,
+    name: String,
+    fields: Vec<(&'a str, RustType)>,
+    global_id: bool,
+    connection: bool,
+}
+
+impl<'a> Model<'a> {
+    fn new(table: &'a Table) -> CodegenResult<Self> {
+        let fields = table
+            .columns
+            .iter()
+            .map(|column| {
+                Ok((
+                    column.name.as_str(),
+                    rust_type(&column.name, &column.sql_type)?,
+                ))
+            })
+            .collect::<CodegenResult<Vec<_>>>()?;
+        let is = |name: &str, rust: &str| {
+            fields
+                .iter()
+                .any(|(column, rust_type)| *column == name && rust_type.rust == rust)
+        };
+        let global_id = table.primary_key == ["id"] && is("id", "Uuid");
+        let connection = global_id && is("created_at", "DateTime<Utc>");
+
+        Ok(Model {
+            table,
+            name: pascal_case(&singular(&table.name)),
+            fields,
+            global_id,
+            connection,
+        })
+    }
+
+    fn imports(&self, imports: &mut Imports, options: &Options) {
+        imports.add(&options.schema_module, &self.table.name);
+
+        for (_, rust_type) in self.fields.iter() {
+            for (path, item) in rust_type.imports.iter() {
+                imports.add(path, item);
+            }
+        }
+
+        if self.global_id {
+            imports.add("async_graphql", "ID");
+            imports.add("timada_relay", "to_id");
+        }
+
+        if self.connection {
+            imports.add("async_graphql", "Connection");
+            imports.add("diesel::prelude", "*");
+            imports.add("diesel", "PgConnection");
+            imports.add("timada_relay", "ConnectionResult");
+            imports.add("timada_relay", "Cursor");
+        }
+    }
+
+    fn render_struct(&self, out: &mut String) {
+        let table = &self.table.name;
+        let mut derives = vec!["Debug", "Clone", "PartialEq", "Queryable"];
+
+        if self.table.primary_key.len() == 1 {
+            derives.push("Identifiable");
+        }
+
+        if self.connection {
+            derives.push("Cursor");
+        }
+
+        out.push_str(&format!("#[derive({})]\n", derives.join(", ")));
+
+        if let [key] = self.table.primary_key.as_slice() {
+            out.push_str(&format!("#[table_name = \"{}\"]\n", table));
+
+            if key != "id" {
+                out.push_str(&format!("#[primary_key({})]\n", key));
+            }
+        }
+
+        out.push_str(&format!("pub struct {} {{\n", self.name));
+        for (column, rust_type) in self.fields.iter() {
+            match *column {
+                "id" if self.connection => out.push_str("    #[cursor(key)]\n"),
+                "created_at" if self.connection => out.push_str("    #[cursor(order)]\n"),
+                _ => {}
+            }
+
+            out.push_str(&format!("    pub {}: {},\n", column, rust_type.rust));
+        }
+        out.push_str("}\n");
+    }
+
+    fn render_object(&self, out: &mut String, options: &Options) {
+        out.push_str(&format!(
+            "#[async_graphql::Object]\nimpl {} {{\n",
+            self.name
+        ));
+
+        let mut first = true;
+        for (column, rust_type) in self.fields.iter() {
+            let (graphql, resolve) = match (&rust_type.graphql, *column) {
+                (Some(_), "id") if self.global_id => (
+                    "ID".to_owned(),
+                    format!("to_id(\"{}\", &self.id)", self.name),
+                ),
+                (Some(graphql), column) if !options.is_hidden(column) => {
+                    (graphql.to_owned(), rust_type.resolve(column))
+                }
+                _ => continue,
+            };
+
+            if !first {
+                out.push('\n');
+            }
+            first = false;
+
+            // Keyword columns like `type_` keep their ident but not the trailing underscore.
+            let attribute = if column.ends_with('_') {
+                format!("#[field(name = \"{}\")]", column.trim_end_matches('_'))
+            } else {
+                "#[field]".to_owned()
+            };
+
+            out.push_str(&format!(
+                "    {}\n    async fn {}(&self) -> {} {{\n        {}\n    }}\n",
+                attribute, column, graphql, resolve
+            ));
+        }
+
+        out.push_str("}\n");
+    }
+
+    fn render_connection(&self, out: &mut String, options: &Options) {
+        out.push_str(&format!(
+            r#"pub fn {table}_connection(
+    conn: &PgConnection,
+    first: Option<usize>,
+    after: Option<String>,
+    last: Option<usize>,
+    before: Option<String>,
+) -> ConnectionResult<Connection<{name}>> {{
+    use {schema}::{table}::dsl::{{created_at, id}};
+
+    let table = {table}::table.into_boxed();
+
+    timada_relay::resolve_connection!({name}, conn, table, first, after, last, before, id, created_at)
+}}
+"#,
+            name = self.name,
+            table = self.table.name,
+            schema = options.schema_module,
+        ));
+    }
+}
+
+pub fn generate(tables: &[Table], options: &Options) -> CodegenResult<String> {
+    for name in options.tables.iter() {
+        if !tables.iter().any(|table| &table.name == name) {
+            return Err(CodegenError::TableNotFound(name.to_owned()));
+        }
+    }
+
+    let models = tables
+        .iter()
+        .filter(|table| options.tables.is_empty() || options.tables.contains(&table.name))
+        .map(Model::new)
+        .collect::<CodegenResult<Vec<_>>>()?;
+
+    let mut imports = Imports::default();
+    let mut schema_imports = Imports::default();
+    for model in models.iter() {
+        model.imports(&mut imports, options);
+    }
+    if let Some(items) = imports.0.remove(&options.schema_module) {
+        schema_imports
+            .0
+            .insert(options.schema_module.to_owned(), items);
+    }
+
+    let mut out = format!("{}\n", HEADER);
+    for group in [imports.render(), schema_imports.render()].iter() {
+        if group.is_empty() {
+            continue;
+        }
+
+        out.push('\n');
+        for line in group {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+
+    for model in models.iter() {
+        out.push('\n');
+        model.render_struct(&mut out);
+        out.push('\n');
+        model.render_object(&mut out, options);
+
+        if model.connection {
+            out.push('\n');
+            model.render_connection(&mut out, options);
+        }
+    }
+
+    Ok(out)
+}
+
+// Only writes when the output changed so build scripts do not trigger needless rebuilds.
+pub fn generate_file<S: AsRef<Path>, O: AsRef<Path>>(
+    schema: S,
+    output: O,
+    options: &Options,
+) -> CodegenResult<bool> {
+    let source = fs::read_to_string(schema)?;
+    let code = generate(&parse_schema(&source)?, options)?;
+
+    if fs::read_to_string(output.as_ref()).ok().as_deref() == Some(code.as_str()) {
+        return Ok(false);
+    }
+
+    fs::write(output, code)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, pascal_case, singular, Options};
+    use crate::error::CodegenError;
+    use crate::parse::parse_schema;
+
+    const SCHEMA: &str = r#"
+table! {
+    todos (id) {
+        id -> Uuid,
+        text -> Varchar,
+        password_hash -> Nullable<Text>,
+        payload -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    categories (code) {
+        code -> Varchar,
+        position -> Int4,
+    }
+}
+"#;
+
+    const EXPECTED: &str = r#"// @generated by timada-codegen, do not edit by hand.
+
+use async_graphql::{Connection, ID};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde_json::Value;
+use timada_relay::{to_id, ConnectionResult, Cursor};
+use uuid::Uuid;
+
+use super::schema::{categories, todos};
+
+#[derive(Debug, Clone, PartialEq, Queryable, Identifiable, Cursor)]
+#[table_name = "todos"]
+pub struct Todo {
+    #[cursor(key)]
+    pub id: Uuid,
+    pub text: String,
+    pub password_hash: Option<String>,
+    pub payload: Value,
+    #[cursor(order)]
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_graphql::Object]
+impl Todo {
+    #[field]
+    async fn id(&self) -> ID {
+        to_id("Todo", &self.id)
+    }
+
+    #[field]
+    async fn text(&self) -> &str {
+        self.text.as_str()
+    }
+
+    #[field]
+    async fn payload(&self) -> String {
+        self.payload.to_string()
+    }
+
+    #[field]
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+pub fn todos_connection(
+"#;
+
+    #[test]
+    fn names() {
+        assert_eq!(singular("categories"), "category");
+        assert_eq!(singular("addresses"), "address");
+        assert_eq!(singular("todos"), "todo");
+        assert_eq!(pascal_case("push_subscription"), "PushSubscription");
+    }
+
+    #[test]
+    fn code() {
+        let tables = parse_schema(SCHEMA).unwrap();
+        let code = generate(&tables, &Options::default()).unwrap();
+
+        assert!(code.starts_with(EXPECTED));
+        assert!(code.contains("    timada_relay::resolve_connection!(Todo, conn, table, first, after, last, before, id, created_at)\n"));
+        assert!(code.contains(
+            "#[table_name = \"categories\"]\n#[primary_key(code)]\npub struct Category {"
+        ));
+        assert!(!code.contains("categories_connection"));
+    }
+
+    #[test]
+    fn tables() {
+        let tables = parse_schema(SCHEMA).unwrap();
+
+        let code = generate(&tables, &Options::default().table("categories")).unwrap();
+        assert!(!code.contains("pub struct Todo"));
+        assert!(code.contains("use super::schema::categories;"));
+
+        assert_eq!(
+            generate(&tables, &Options::default().table("users")),
+            Err(CodegenError::TableNotFound("users".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,12 @@
+#[macro_use]
+extern crate thiserror;
+
+mod error;
+mod generate;
+mod parse;
+mod types;
+
+pub use crate::error::{CodegenError, CodegenResult};
+pub use crate::generate::{generate, generate_file, pascal_case, singular, Options};
+pub use crate::parse::{parse_schema, Column, SqlType, Table};
+pub use crate::types::{rust_type, RustType};
@@ -0,0 +1,218 @@
+use std::fmt;
+
+use super::error::{CodegenError, CodegenResult};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlType {
+    Named(String),
+    Nullable(Box<SqlType>),
+    Array(Box<SqlType>),
+}
+
+impl fmt::Display for SqlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlType::Named(name) => write!(f, "{}", name),
+            SqlType::Nullable(inner) => write!(f, "Nullable<{}>", inner),
+            SqlType::Array(inner) => write!(f, "Array<{}>", inner),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub sql_type: SqlType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub name: String,
+    pub primary_key: Vec<String>,
+    pub columns: Vec<Column>,
+}
+
+impl Table {
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+fn parse_type(value: &str) -> SqlType {
+    let value = value.trim();
+
+    match (value.find('<'), value.rfind('>')) {
+        (Some(start), Some(end)) if start < end => {
+            let inner = Box::new(parse_type(&value[start + 1..end]));
+
+            match value[..start].rsplit("::").next().unwrap_or_default() {
+                "Nullable" => SqlType::Nullable(inner),
+                _ => SqlType::Array(inner),
+            }
+        }
+        _ => SqlType::Named(value.rsplit("::").next().unwrap_or(value).to_owned()),
+    }
+}
+
+// Returns the content between the brace at `start` and its matching brace.
+fn block(source: &str, start: usize) -> CodegenResult<(&str, usize)> {
+    let mut depth = 0;
+
+    for (index, c) in source[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Ok((&source[start + 1..start + index], start + index + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Err(CodegenError::Parse("Unclosed block".to_owned()))
+}
+
+fn strip_comments(source: &str) -> String {
+    source
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| {
+            !line.starts_with("//") && !line.starts_with("#[") && !line.starts_with("use ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn parse_table(body: &str) -> CodegenResult<Table> {
+    let body = strip_comments(body);
+    let open = body
+        .find('{')
+        .ok_or_else(|| CodegenError::Parse(format!("Missing columns in {}", body)))?;
+    let header = body[..open].trim();
+    let (columns, _) = block(&body, open)?;
+
+    let (name, primary_key) = match (header.find('('), header.rfind(')')) {
+        (Some(start), Some(end)) if start < end => (
+            header[..start].trim(),
+            header[start + 1..end]
+                .split(',')
+                .map(|key| key.trim().to_owned())
+                .filter(|key| !key.is_empty())
+                .collect(),
+        ),
+        _ => (header, vec!["id".to_owned()]),
+    };
+
+    let columns = columns
+        .split(',')
+        .map(|column| column.trim())
+        .filter(|column| !column.is_empty())
+        .map(|column| {
+            let mut parts = column.splitn(2, "->");
+
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(sql_type)) => Ok(Column {
+                    name: name.trim().to_owned(),
+                    sql_type: parse_type(sql_type),
+                }),
+                _ => Err(CodegenError::Parse(format!("Invalid column {}", column))),
+            }
+        })
+        .collect::<CodegenResult<Vec<_>>>()?;
+
+    Ok(Table {
+        name: name.rsplit('.').next().unwrap_or(name).to_owned(),
+        primary_key,
+        columns,
+    })
+}
+
+pub fn parse_schema(source: &str) -> CodegenResult<Vec<Table>> {
+    let mut tables = Vec::new();
+    let mut rest = 0;
+
+    while let Some(index) = source[rest..].find("table!") {
+        let start = rest + index;
+        let open = source[start..]
+            .find('{')
+            .map(|open| start + open)
+            .ok_or_else(|| CodegenError::Parse("Missing table! block".to_owned()))?;
+        let (body, end) = block(source, open)?;
+
+        tables.push(parse_table(body)?);
+        rest = end;
+    }
+
+    Ok(tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_schema, Column, SqlType};
+
+    const SCHEMA: &str = r#"
+table! {
+    use diesel::sql_types::*;
+
+    /// Representation of the `todos` table.
+    todos (id) {
+        id -> Uuid,
+        #[sql_name = "type"]
+        type_ -> Varchar,
+        tags -> Array<Text>,
+        done_at -> Nullable<diesel::sql_types::Timestamptz>,
+    }
+}
+
+table! {
+    public.memberships (user_id, organization_id) {
+        user_id -> Uuid,
+        organization_id -> Uuid,
+    }
+}
+
+joinable!(todos -> users (user_id));
+"#;
+
+    #[test]
+    fn tables() {
+        let tables = parse_schema(SCHEMA).unwrap();
+
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].name, "todos");
+        assert_eq!(tables[0].primary_key, vec!["id"]);
+        assert_eq!(
+            tables[0].columns,
+            vec![
+                Column {
+                    name: "id".to_owned(),
+                    sql_type: SqlType::Named("Uuid".to_owned()),
+                },
+                Column {
+                    name: "type_".to_owned(),
+                    sql_type: SqlType::Named("Varchar".to_owned()),
+                },
+                Column {
+                    name: "tags".to_owned(),
+                    sql_type: SqlType::Array(Box::new(SqlType::Named("Text".to_owned()))),
+                },
+                Column {
+                    name: "done_at".to_owned(),
+                    sql_type: SqlType::Nullable(Box::new(SqlType::Named("Timestamptz".to_owned()))),
+                },
+            ]
+        );
+        assert_eq!(tables[1].name, "memberships");
+        assert_eq!(tables[1].primary_key, vec!["user_id", "organization_id"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse_schema("table! { todos (id) { id -> Uuid, }").is_err());
+        assert!(parse_schema("table! { todos (id) { id Uuid } }").is_err());
+        assert_eq!(parse_schema("// empty"), Ok(vec![]));
+    }
+}
@@ -0,0 +1,154 @@
+use super::error::{CodegenError, CodegenResult};
+use super::parse::SqlType;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Copy,
+    Str,
+    Display,
+    Hidden,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RustType {
+    pub rust: String,
+    pub graphql: Option<String>,
+    pub imports: Vec<(&'static str, &'static str)>,
+    kind: Kind,
+    nullable: bool,
+    array: bool,
+}
+
+type Imports = &'static [(&'static str, &'static str)];
+
+fn named(name: &str) -> Option<(&'static str, Kind, Imports)> {
+    Some(match name {
+        "Uuid" => ("Uuid", Kind::Copy, &[("uuid", "Uuid")]),
+        "Text" | "Varchar" | "VarChar" | "Citext" => ("String", Kind::Str, &[]),
+        "Bool" => ("bool", Kind::Copy, &[]),
+        "Int2" | "SmallInt" => ("i16", Kind::Copy, &[]),
+        "Int4" | "Integer" => ("i32", Kind::Copy, &[]),
+        "Int8" | "BigInt" => ("i64", Kind::Copy, &[]),
+        "Float4" | "Float" => ("f32", Kind::Copy, &[]),
+        "Float8" | "Double" => ("f64", Kind::Copy, &[]),
+        "Timestamptz" => (
+            "DateTime<Utc>",
+            Kind::Copy,
+            &[("chrono", "DateTime"), ("chrono", "Utc")],
+        ),
+        "Timestamp" => (
+            "NaiveDateTime",
+            Kind::Display,
+            &[("chrono", "NaiveDateTime")],
+        ),
+        "Date" => ("NaiveDate", Kind::Display, &[("chrono", "NaiveDate")]),
+        "Jsonb" | "Json" => ("Value", Kind::Display, &[("serde_json", "Value")]),
+        "Bytea" | "Binary" => ("Vec<u8>", Kind::Hidden, &[]),
+        _ => return None,
+    })
+}
+
+pub fn rust_type(column: &str, sql_type: &SqlType) -> CodegenResult<RustType> {
+    let (inner, nullable) = match sql_type {
+        SqlType::Nullable(inner) => (inner.as_ref(), true),
+        sql_type => (sql_type, false),
+    };
+    let (inner, array) = match inner {
+        SqlType::Array(inner) => (inner.as_ref(), true),
+        inner => (inner, false),
+    };
+
+    let (rust, kind, imports) = match inner {
+        SqlType::Named(name) => named(name),
+        _ => None,
+    }
+    .ok_or_else(|| CodegenError::UnsupportedType(column.to_owned(), sql_type.to_string()))?;
+
+    let rust = match (array, nullable) {
+        (true, true) => format!("Option<Vec<{}>>", rust),
+        (true, false) => format!("Vec<{}>", rust),
+        (false, true) => format!("Option<{}>", rust),
+        (false, false) => rust.to_owned(),
+    };
+
+    let graphql = match (kind, array, nullable) {
+        (Kind::Hidden, _, _) => None,
+        (Kind::Display, true, _) => None,
+        (Kind::Str, false, false) => Some("&str".to_owned()),
+        (Kind::Str, false, true) => Some("Option<&str>".to_owned()),
+        (Kind::Display, false, false) => Some("String".to_owned()),
+        (Kind::Display, false, true) => Some("Option<String>".to_owned()),
+        _ => Some(rust.clone()),
+    };
+
+    Ok(RustType {
+        rust,
+        graphql,
+        imports: imports.to_vec(),
+        kind,
+        nullable,
+        array,
+    })
+}
+
+impl RustType {
+    pub fn resolve(&self, field: &str) -> String {
+        match (self.kind, self.array, self.nullable) {
+            (Kind::Str, false, false) => format!("self.{}.as_str()", field),
+            (Kind::Str, false, true) => format!("self.{}.as_deref()", field),
+            (Kind::Display, false, false) => format!("self.{}.to_string()", field),
+            (Kind::Display, false, true) => {
+                format!("self.{}.as_ref().map(|value| value.to_string())", field)
+            }
+            (Kind::Copy, false, _) => format!("self.{}", field),
+            _ => format!("self.{}.clone()", field),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rust_type;
+    use crate::error::CodegenError;
+    use crate::parse::SqlType;
+
+    fn named(name: &str) -> SqlType {
+        SqlType::Named(name.to_owned())
+    }
+
+    fn nullable(sql_type: SqlType) -> SqlType {
+        SqlType::Nullable(Box::new(sql_type))
+    }
+
+    #[test]
+    fn types() {
+        let text = rust_type("text", &named("Varchar")).unwrap();
+        assert_eq!(text.rust, "String");
+        assert_eq!(text.graphql.as_deref(), Some("&str"));
+        assert_eq!(text.resolve("text"), "self.text.as_str()");
+
+        let done_at = rust_type("done_at", &nullable(named("Timestamptz"))).unwrap();
+        assert_eq!(done_at.rust, "Option<DateTime<Utc>>");
+        assert_eq!(done_at.resolve("done_at"), "self.done_at");
+        assert_eq!(
+            done_at.imports,
+            vec![("chrono", "DateTime"), ("chrono", "Utc")]
+        );
+
+        let payload = rust_type("payload", &nullable(named("Jsonb"))).unwrap();
+        assert_eq!(payload.graphql.as_deref(), Some("Option<String>"));
+
+        let tags = rust_type("tags", &SqlType::Array(Box::new(named("Text")))).unwrap();
+        assert_eq!(tags.rust, "Vec<String>");
+        assert_eq!(tags.resolve("tags"), "self.tags.clone()");
+
+        assert_eq!(rust_type("data", &named("Bytea")).unwrap().graphql, None);
+        assert_eq!(
+            rust_type("status", &nullable(named("TodoStatus"))),
+            Err(CodegenError::UnsupportedType(
+                "status".to_owned(),
+                "Nullable<TodoStatus>".to_owned()
+            ))
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use diesel::pg::PgConnection;
+use timada_events::Event;
+use timada_http::{Context, Result, User, UserRole};
+use validator::Validate;
+
+use super::events::PendingEvents;
+
+pub trait Command: Validate + Send + 'static {
+    const NAME: &'static str;
+
+    type Output: Send + 'static;
+
+    fn roles(&self) -> Option<Vec<UserRole>> {
+        None
+    }
+
+    fn authorize(&self, context: &Context) -> Result<()> {
+        context.ensure_is_authorized(self.roles())?;
+
+        Ok(())
+    }
+}
+
+pub trait CommandHandler<C: Command>: Send + Sync + 'static {
+    fn handle(&self, scope: &mut CommandScope<'_>, command: C) -> Result<C::Output>;
+}
+
+impl<C, F> CommandHandler<C> for F
+where
+    C: Command,
+    F: Fn(&mut CommandScope<'_>, C) -> Result<C::Output> + Send + Sync + 'static,
+{
+    fn handle(&self, scope: &mut CommandScope<'_>, command: C) -> Result<C::Output> {
+        self(scope, command)
+    }
+}
+
+pub struct CommandScope<'a> {
+    pub conn: &'a PgConnection,
+    pub context: &'a Context,
+    pub(crate) events: PendingEvents,
+}
+
+impl<'a> CommandScope<'a> {
+    pub(crate) fn new(conn: &'a PgConnection, context: &'a Context) -> Self {
+        CommandScope {
+            conn,
+            context,
+            events: PendingEvents::default(),
+        }
+    }
+
+    pub fn user(&self) -> Result<&User> {
+        Ok(self.context.ensure_is_authorized(None)?)
+    }
+
+    // Published on the event bus once the transaction is committed.
+    pub fn emit<E: Event>(&mut self, event: E) {
+        self.events.push(event);
+    }
+}
@@ -0,0 +1,328 @@
+use diesel::Connection;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use timada_database::Pool;
+use timada_events::EventBus;
+use timada_http::{Context, Error, Result};
+
+use super::command::{Command, CommandHandler, CommandScope};
+use super::middleware::{Envelope, MessageKind, Middleware};
+use super::query::{Query, QueryHandler, QueryScope};
+
+#[derive(Clone)]
+pub struct Dispatcher {
+    pool: Pool,
+    bus: EventBus,
+    handlers: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl Dispatcher {
+    pub fn new(pool: Pool, bus: EventBus) -> Self {
+        Dispatcher {
+            pool,
+            bus,
+            handlers: HashMap::new(),
+            middleware: Vec::new(),
+        }
+    }
+
+    pub fn command<C: Command, H: CommandHandler<C>>(mut self, handler: H) -> Self {
+        let handler: Arc<dyn CommandHandler<C>> = Arc::new(handler);
+        self.handlers.insert(
+            TypeId::of::<C>(),
+            Arc::new(handler) as Arc<dyn Any + Send + Sync>,
+        );
+        self
+    }
+
+    pub fn query<Q: Query, H: QueryHandler<Q>>(mut self, handler: H) -> Self {
+        let handler: Arc<dyn QueryHandler<Q>> = Arc::new(handler);
+        self.handlers.insert(
+            TypeId::of::<Q>(),
+            Arc::new(handler) as Arc<dyn Any + Send + Sync>,
+        );
+        self
+    }
+
+    pub fn middleware<M: Middleware>(mut self, middleware: M) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    pub fn bus(&self) -> &EventBus {
+        &self.bus
+    }
+
+    pub async fn execute<C: Command>(&self, context: &Context, command: C) -> Result<C::Output> {
+        let envelope = Envelope {
+            name: C::NAME,
+            kind: MessageKind::Command,
+            context,
+        };
+
+        let res = self.run_command(&envelope, command).await;
+        self.after(&envelope, res.as_ref().err());
+
+        res
+    }
+
+    pub async fn fetch<Q: Query>(&self, context: &Context, query: Q) -> Result<Q::Output> {
+        let envelope = Envelope {
+            name: Q::NAME,
+            kind: MessageKind::Query,
+            context,
+        };
+
+        let res = self.run_query(&envelope, query).await;
+        self.after(&envelope, res.as_ref().err());
+
+        res
+    }
+
+    async fn run_command<C: Command>(
+        &self,
+        envelope: &Envelope<'_>,
+        command: C,
+    ) -> Result<C::Output> {
+        self.before(envelope)?;
+        command.authorize(envelope.context)?;
+        command.validate()?;
+
+        let handler = self.handler::<C, Arc<dyn CommandHandler<C>>>()?;
+        let pool = self.pool.clone();
+        let context = envelope.context.clone();
+
+        let (output, events) = tokio::task::spawn_blocking(move || {
+            let conn = pool.get().map_err(|e| Error::Internal(e.to_string()))?;
+
+            conn.transaction::<_, Error, _>(|| {
+                let mut scope = CommandScope::new(&conn, &context);
+                let output = handler.handle(&mut scope, command)?;
+
+                Ok((output, scope.events))
+            })
+        })
+        .await
+        .map_err(|e| Error::Internal(e.to_string()))??;
+
+        events.publish(&self.bus).await;
+
+        Ok(output)
+    }
+
+    async fn run_query<Q: Query>(&self, envelope: &Envelope<'_>, query: Q) -> Result<Q::Output> {
+        self.before(envelope)?;
+        query.authorize(envelope.context)?;
+        query.validate()?;
+
+        let handler = self.handler::<Q, Arc<dyn QueryHandler<Q>>>()?;
+        let pool = self.pool.clone();
+        let context = envelope.context.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = pool.get().map_err(|e| Error::Internal(e.to_string()))?;
+            let scope = QueryScope {
+                conn: &conn,
+                context: &context,
+            };
+
+            handler.handle(&scope, query)
+        })
+        .await
+        .map_err(|e| Error::Internal(e.to_string()))?
+    }
+
+    fn handler<M: 'static, H: Clone + 'static>(&self) -> Result<H> {
+        self.handlers
+            .get(&TypeId::of::<M>())
+            .and_then(|handler| handler.downcast_ref::<H>())
+            .cloned()
+            .ok_or_else(|| {
+                Error::Internal(format!(
+                    "No handler registered for {}",
+                    std::any::type_name::<M>()
+                ))
+            })
+    }
+
+    fn before(&self, envelope: &Envelope<'_>) -> Result<()> {
+        for middleware in self.middleware.iter() {
+            middleware.before(envelope)?;
+        }
+
+        Ok(())
+    }
+
+    fn after(&self, envelope: &Envelope<'_>, error: Option<&Error>) {
+        for middleware in self.middleware.iter().rev() {
+            middleware.after(envelope, error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::r2d2::ConnectionManager;
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use timada_database::Pool;
+    use timada_events::EventBus;
+    use timada_http::{Context, Error, Result, User, UserRole, UserState};
+    use uuid::Uuid;
+    use validator::{Validate, ValidationError, ValidationErrors};
+
+    use super::Dispatcher;
+    use crate::command::{Command, CommandScope};
+    use crate::middleware::{Envelope, Middleware};
+
+    struct CreateTodo {
+        text: String,
+    }
+
+    impl Validate for CreateTodo {
+        fn validate(&self) -> std::result::Result<(), ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+
+            if self.text.is_empty() {
+                errors.add("text", ValidationError::new("length"));
+            }
+
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(errors)
+            }
+        }
+    }
+
+    impl Command for CreateTodo {
+        const NAME: &'static str = "CreateTodo";
+
+        type Output = ();
+
+        fn roles(&self) -> Option<Vec<UserRole>> {
+            Some(vec![UserRole::Admin])
+        }
+    }
+
+    struct DeleteTodo;
+
+    impl Validate for DeleteTodo {
+        fn validate(&self) -> std::result::Result<(), ValidationErrors> {
+            Ok(())
+        }
+    }
+
+    impl Command for DeleteTodo {
+        const NAME: &'static str = "DeleteTodo";
+
+        type Output = ();
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Middleware for Recorder {
+        fn before(&self, envelope: &Envelope<'_>) -> Result<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("before:{}", envelope.name));
+            Ok(())
+        }
+
+        fn after(&self, envelope: &Envelope<'_>, error: Option<&Error>) {
+            self.calls.lock().unwrap().push(format!(
+                "after:{}:{}",
+                envelope.name,
+                error.map(|e| e.to_string()).unwrap_or_default()
+            ));
+        }
+    }
+
+    // Connections are never checked out by the rejections under test.
+    fn dispatcher() -> Dispatcher {
+        let manager = ConnectionManager::new("postgres://localhost/timada_cqrs_dev");
+
+        Dispatcher::new(Pool::builder().build_unchecked(manager), EventBus::new()).command(
+            |_: &mut CommandScope<'_>, _: CreateTodo| -> Result<()> {
+                panic!("handler must not run")
+            },
+        )
+    }
+
+    fn context(role: UserRole) -> Context {
+        Context {
+            user: Some(User {
+                id: Uuid::new_v4(),
+                email: None,
+                username: None,
+                role,
+                state: UserState::Enabled,
+                claims: HashMap::new(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rejects_before_handler() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        let dispatcher = dispatcher().middleware(recorder);
+
+        let res = block_on(dispatcher.execute(
+            &Context::default(),
+            CreateTodo {
+                text: "Todo".to_owned(),
+            },
+        ));
+        assert_eq!(res, Err(Error::Unauthorized("Anonymous".to_owned())));
+
+        let res = block_on(dispatcher.execute(
+            &context(UserRole::User),
+            CreateTodo {
+                text: "Todo".to_owned(),
+            },
+        ));
+        let forbidden = match res {
+            Err(Error::Forbidden(message)) => message,
+            res => panic!("unexpected {:?}", res),
+        };
+
+        let res = block_on(dispatcher.execute(
+            &context(UserRole::Admin),
+            CreateTodo {
+                text: "".to_owned(),
+            },
+        ));
+        assert!(matches!(res, Err(Error::UnprocessableEntity(_))));
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "before:CreateTodo".to_owned(),
+                "after:CreateTodo:Anonymous".to_owned(),
+                "before:CreateTodo".to_owned(),
+                format!("after:CreateTodo:{}", forbidden),
+                "before:CreateTodo".to_owned(),
+                "after:CreateTodo:field: text, code: length, params: [{}]".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_handler() {
+        let res = block_on(dispatcher().execute(&context(UserRole::User), DeleteTodo));
+
+        assert!(matches!(
+            res,
+            Err(Error::Internal(message)) if message.starts_with("No handler registered")
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+use futures::future::BoxFuture;
+use timada_events::{Event, EventBus, EventResult};
+
+type Publish = Box<dyn FnOnce(EventBus) -> BoxFuture<'static, EventResult<()>> + Send>;
+
+#[derive(Default)]
+pub(crate) struct PendingEvents {
+    events: Vec<(&'static str, Publish)>,
+}
+
+impl PendingEvents {
+    pub(crate) fn push<E: Event>(&mut self, event: E) {
+        self.events.push((
+            E::NAME,
+            Box::new(
+                move |bus: EventBus| -> BoxFuture<'static, EventResult<()>> {
+                    Box::pin(async move { bus.publish(event).await })
+                },
+            ),
+        ));
+    }
+
+    // The transaction is already committed, subscriber failures must not fail the command.
+    pub(crate) async fn publish(self, bus: &EventBus) {
+        for (name, publish) in self.events {
+            if let Err(e) = publish(bus.clone()).await {
+                log::error!("Failed to publish {}: {}", name, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use std::sync::{Arc, Mutex};
+    use timada_events::{Event, EventBus, EventError};
+
+    use super::PendingEvents;
+
+    #[derive(Clone)]
+    struct TodoCreated(u32);
+
+    impl Event for TodoCreated {
+        const NAME: &'static str = "TodoCreated";
+    }
+
+    #[test]
+    fn publish_in_order() {
+        let bus = EventBus::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        bus.subscribe(|_: &TodoCreated| Err(EventError::subscriber("mailer down")));
+
+        let received = calls.clone();
+        bus.subscribe(move |event: &TodoCreated| {
+            received.lock().unwrap().push(event.0);
+            Ok(())
+        });
+
+        let mut events = PendingEvents::default();
+        events.push(TodoCreated(1));
+        events.push(TodoCreated(2));
+
+        block_on(events.publish(&bus));
+
+        assert_eq!(*calls.lock().unwrap(), vec![1, 2]);
+    }
+}
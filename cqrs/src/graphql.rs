@@ -0,0 +1,15 @@
+use async_graphql::Context as GraphQLContext;
+use timada_http::{Error, Result};
+
+use super::dispatcher::Dispatcher;
+
+pub trait DispatcherContextExt {
+    fn dispatcher(&self) -> Result<&Dispatcher>;
+}
+
+impl DispatcherContextExt for GraphQLContext<'_> {
+    fn dispatcher(&self) -> Result<&Dispatcher> {
+        self.data::<Dispatcher>()
+            .map_err(|_| Error::InternalServerError)
+    }
+}
@@ -0,0 +1,12 @@
+mod command;
+mod dispatcher;
+mod events;
+mod graphql;
+mod middleware;
+mod query;
+
+pub use crate::command::{Command, CommandHandler, CommandScope};
+pub use crate::dispatcher::Dispatcher;
+pub use crate::graphql::DispatcherContextExt;
+pub use crate::middleware::{Envelope, LogMiddleware, MessageKind, Middleware};
+pub use crate::query::{Query, QueryHandler, QueryScope};
@@ -0,0 +1,47 @@
+use timada_http::{Context, Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageKind {
+    Command,
+    Query,
+}
+
+pub struct Envelope<'a> {
+    pub name: &'static str,
+    pub kind: MessageKind,
+    pub context: &'a Context,
+}
+
+pub trait Middleware: Send + Sync + 'static {
+    fn before(&self, _envelope: &Envelope<'_>) -> Result<()> {
+        Ok(())
+    }
+
+    fn after(&self, _envelope: &Envelope<'_>, _error: Option<&Error>) {}
+}
+
+pub struct LogMiddleware;
+
+impl Middleware for LogMiddleware {
+    fn after(&self, envelope: &Envelope<'_>, error: Option<&Error>) {
+        let request_id = envelope.context.request_id.as_deref().unwrap_or("-");
+
+        match error {
+            Some(e) if e.status_code().is_server_error() => log::error!(
+                "[{}] {:?} {} failed: {}",
+                request_id,
+                envelope.kind,
+                envelope.name,
+                e
+            ),
+            Some(e) => log::warn!(
+                "[{}] {:?} {} rejected: {}",
+                request_id,
+                envelope.kind,
+                envelope.name,
+                e
+            ),
+            None => log::debug!("[{}] {:?} {}", request_id, envelope.kind, envelope.name),
+        }
+    }
+}
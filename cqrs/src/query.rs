@@ -0,0 +1,44 @@
+use diesel::pg::PgConnection;
+use timada_http::{Context, Result, User, UserRole};
+use validator::Validate;
+
+pub trait Query: Validate + Send + 'static {
+    const NAME: &'static str;
+
+    type Output: Send + 'static;
+
+    fn roles(&self) -> Option<Vec<UserRole>> {
+        None
+    }
+
+    fn authorize(&self, context: &Context) -> Result<()> {
+        context.ensure_is_authorized(self.roles())?;
+
+        Ok(())
+    }
+}
+
+pub trait QueryHandler<Q: Query>: Send + Sync + 'static {
+    fn handle(&self, scope: &QueryScope<'_>, query: Q) -> Result<Q::Output>;
+}
+
+impl<Q, F> QueryHandler<Q> for F
+where
+    Q: Query,
+    F: Fn(&QueryScope<'_>, Q) -> Result<Q::Output> + Send + Sync + 'static,
+{
+    fn handle(&self, scope: &QueryScope<'_>, query: Q) -> Result<Q::Output> {
+        self(scope, query)
+    }
+}
+
+pub struct QueryScope<'a> {
+    pub conn: &'a PgConnection,
+    pub context: &'a Context,
+}
+
+impl<'a> QueryScope<'a> {
+    pub fn user(&self) -> Result<&User> {
+        Ok(self.context.ensure_is_authorized(None)?)
+    }
+}
@@ -2,7 +2,15 @@
 extern crate diesel;
 
 mod connection;
+mod listen;
 mod migration;
+mod trace;
+
+pub mod testing;
 
 pub use crate::connection::{DatabaseConnection, Pool, PooledConnection};
-pub use crate::migration::{fixture, migrate, reset, setup};
+pub use crate::listen::{notify, ListenError, ListenResult, Listener, Notification};
+pub use crate::migration::{
+    create, fixture, migrate, recreate, reset, setup, status, MigrationError, MigrationResult,
+};
+pub use crate::trace::{checkout, traced};
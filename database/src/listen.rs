@@ -0,0 +1,272 @@
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel::PgConnection;
+use fallible_iterator::FallibleIterator;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use native_tls::TlsConnector;
+use postgres::config::SslMode;
+use postgres::Config;
+use postgres_native_tls::MakeTlsConnector;
+use std::env;
+use std::error::Error;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use super::connection::DatabaseConnection;
+use super::trace::traced;
+
+const MAX_PAYLOAD_SIZE: usize = 8000;
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq)]
+pub enum ListenError {
+    InvalidChannel(String),
+    PayloadTooLarge(usize),
+    UnknownChannel(String),
+    Diesel(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for ListenError {
+    fn from(e: diesel::result::Error) -> ListenError {
+        ListenError::Diesel(e)
+    }
+}
+
+pub type ListenResult<T> = Result<T, ListenError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub channel: String,
+    pub payload: String,
+}
+
+fn validate_channel(channel: &str) -> ListenResult<()> {
+    let mut chars = channel.chars();
+    let valid = channel.len() <= 63
+        && chars
+            .next()
+            .map(|c| c.is_ascii_lowercase() || c == '_')
+            .unwrap_or(false)
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+    if !valid {
+        return Err(ListenError::InvalidChannel(channel.to_owned()));
+    }
+
+    Ok(())
+}
+
+pub fn notify(conn: &PgConnection, channel: &str, payload: &str) -> ListenResult<()> {
+    validate_channel(channel)?;
+
+    if payload.len() > MAX_PAYLOAD_SIZE {
+        return Err(ListenError::PayloadTooLarge(payload.len()));
+    }
+
+    traced("NOTIFY", channel, || {
+        diesel::sql_query("SELECT pg_notify($1, $2)")
+            .bind::<Text, _>(channel)
+            .bind::<Text, _>(payload)
+            .execute(conn)
+    })?;
+
+    Ok(())
+}
+
+type Subscribers = Mutex<Vec<(String, UnboundedSender<Notification>)>>;
+
+#[derive(Clone)]
+pub struct Listener {
+    channels: Vec<String>,
+    subscribers: Arc<Subscribers>,
+}
+
+impl Listener {
+    // LISTEN needs a dedicated connection, it is kept out of the pool and
+    // reconnects with a backoff until every `Listener` clone is dropped.
+    pub fn start(connection: &DatabaseConnection, channels: &[&str]) -> ListenResult<Self> {
+        for channel in channels {
+            validate_channel(channel)?;
+        }
+
+        let listener = Listener {
+            channels: channels
+                .iter()
+                .map(|channel| (*channel).to_owned())
+                .collect(),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let url = connection.connection_url();
+        let channels = listener.channels.clone();
+        let subscribers = Arc::downgrade(&listener.subscribers);
+
+        thread::spawn(move || run(&url, &channels, subscribers));
+
+        Ok(listener)
+    }
+
+    pub fn subscribe(&self, channel: &str) -> ListenResult<UnboundedReceiver<Notification>> {
+        if !self.channels.iter().any(|listened| listened == channel) {
+            return Err(ListenError::UnknownChannel(channel.to_owned()));
+        }
+
+        let (sender, receiver) = unbounded();
+
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push((channel.to_owned(), sender));
+        }
+
+        Ok(receiver)
+    }
+}
+
+fn dispatch(subscribers: &Subscribers, notification: &Notification) {
+    if let Ok(mut subscribers) = subscribers.lock() {
+        subscribers.retain(|(channel, sender)| {
+            channel != &notification.channel || sender.unbounded_send(notification.clone()).is_ok()
+        });
+    }
+}
+
+// Same modes as libpq for the pool, read from `PGSSLMODE` and defaulting to `prefer`.
+// `require` encrypts without checking the certificate, `verify-ca` skips the hostname.
+fn tls(mode: &str) -> Result<(SslMode, MakeTlsConnector), native_tls::Error> {
+    let mut connector = TlsConnector::builder();
+
+    let ssl_mode = match mode {
+        "disable" => SslMode::Disable,
+        "verify-full" => SslMode::Require,
+        "verify-ca" => {
+            connector.danger_accept_invalid_hostnames(true);
+            SslMode::Require
+        }
+        "require" => {
+            connector.danger_accept_invalid_certs(true);
+            SslMode::Require
+        }
+        _ => {
+            connector.danger_accept_invalid_certs(true);
+            SslMode::Prefer
+        }
+    };
+
+    Ok((ssl_mode, MakeTlsConnector::new(connector.build()?)))
+}
+
+fn listen(
+    url: &str,
+    channels: &[String],
+    subscribers: &Weak<Subscribers>,
+    backoff: &mut Duration,
+) -> Result<(), Box<dyn Error>> {
+    let (ssl_mode, connector) = tls(&env::var("PGSSLMODE").unwrap_or_default())?;
+    let mut client = url
+        .parse::<Config>()?
+        .ssl_mode(ssl_mode)
+        .connect(connector)?;
+
+    for channel in channels {
+        client.batch_execute(&format!("LISTEN \"{}\"", channel))?;
+    }
+
+    *backoff = MIN_BACKOFF;
+
+    let mut notifications = client.notifications();
+    let mut iter = notifications.timeout_iter(Duration::from_secs(5));
+
+    loop {
+        let next = iter.next()?;
+        let subscribers = match subscribers.upgrade() {
+            Some(subscribers) => subscribers,
+            None => return Ok(()),
+        };
+
+        if let Some(notification) = next {
+            dispatch(
+                &subscribers,
+                &Notification {
+                    channel: notification.channel().to_owned(),
+                    payload: notification.payload().to_owned(),
+                },
+            );
+        }
+    }
+}
+
+fn run(url: &str, channels: &[String], subscribers: Weak<Subscribers>) {
+    let mut backoff = MIN_BACKOFF;
+
+    while subscribers.strong_count() > 0 {
+        match listen(url, channels, &subscribers, &mut backoff) {
+            Ok(()) => return,
+            Err(e) => log::error!("database listener disconnected: {}", e),
+        }
+
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use std::sync::{Arc, Mutex};
+
+    use postgres::config::SslMode;
+
+    use super::{dispatch, tls, validate_channel, ListenError, Listener, Notification};
+
+    #[test]
+    fn channel() {
+        assert!(validate_channel("todo_updated").is_ok());
+        assert!(validate_channel("_todos1").is_ok());
+        assert!(validate_channel("").is_err());
+        assert!(validate_channel("1todos").is_err());
+        assert!(validate_channel("todos\"; DROP TABLE todos; --").is_err());
+        assert!(validate_channel(&"a".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn fan_out() {
+        let listener = Listener {
+            channels: vec!["todos".to_owned(), "projects".to_owned()],
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let mut first = listener.subscribe("todos").unwrap();
+        let second = listener.subscribe("todos").unwrap();
+        let mut projects = listener.subscribe("projects").unwrap();
+        assert_eq!(
+            listener.subscribe("users").err(),
+            Some(ListenError::UnknownChannel("users".to_owned()))
+        );
+
+        drop(second);
+
+        let notification = Notification {
+            channel: "todos".to_owned(),
+            payload: "{}".to_owned(),
+        };
+        dispatch(&listener.subscribers, &notification);
+
+        assert_eq!(
+            futures::executor::block_on(first.next()),
+            Some(notification)
+        );
+        assert!(projects.try_next().is_err());
+        assert_eq!(listener.subscribers.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn ssl_mode() {
+        let mode = |mode: &str| tls(mode).map(|(ssl_mode, _)| ssl_mode).unwrap();
+
+        assert_eq!(mode(""), SslMode::Prefer);
+        assert_eq!(mode("disable"), SslMode::Disable);
+        assert_eq!(mode("require"), SslMode::Require);
+        assert_eq!(mode("verify-full"), SslMode::Require);
+    }
+}
@@ -1,3 +1,4 @@
+use diesel::migration::Migration;
 use diesel::prelude::*;
 use diesel::result::Error as DieselError;
 use diesel::{ConnectionError, PgConnection};
@@ -6,6 +7,7 @@ use diesel_migrations::RunMigrationsError;
 use std::convert::From;
 use std::env;
 use std::io::stdout;
+use std::path::Path;
 
 use super::connection::DatabaseConnection;
 
@@ -100,18 +102,17 @@ pub fn migrate(connection: &PgConnection, directory: &str) -> Result<(), RunMigr
     migrations::run_pending_migrations_in_directory(connection, &migration_dir, &mut stdout())
 }
 
-pub fn setup(config: &DatabaseConnection) -> MigrationResult<()> {
+pub fn create(config: &DatabaseConnection) -> MigrationResult<()> {
     let connection = config.without_name().establish()?;
     let db_name = config
         .name
         .as_ref()
         .ok_or(MigrationError::MissingDatabaseName)?;
     create_database_if_not_exists(&connection, db_name)?;
-    let connection = config.establish()?;
-    Ok(migrate(&connection, "migrations")?)
+    Ok(())
 }
 
-pub fn reset(config: &DatabaseConnection) -> MigrationResult<()> {
+pub fn recreate(config: &DatabaseConnection) -> MigrationResult<()> {
     let db_name = config
         .name
         .as_ref()
@@ -128,11 +129,40 @@ pub fn reset(config: &DatabaseConnection) -> MigrationResult<()> {
     let connection = config.without_name().establish()?;
     drop_database_if_exists(&connection, &db_name)?;
     create_database(&connection, &db_name)?;
+    Ok(())
+}
+
+pub fn setup(config: &DatabaseConnection) -> MigrationResult<()> {
+    create(config)?;
+    let connection = config.establish()?;
+    Ok(migrate(&connection, "migrations")?)
+}
 
+pub fn reset(config: &DatabaseConnection) -> MigrationResult<()> {
+    recreate(config)?;
     let connection = config.establish()?;
     Ok(migrate(&connection, "migrations")?)
 }
 
+// Returns every migration version of the directory with whether it has been applied.
+pub fn status(
+    config: &DatabaseConnection,
+    directory: &str,
+) -> MigrationResult<Vec<(String, bool)>> {
+    let connection = config.establish()?;
+    let migration_dir = env::current_dir()
+        .expect("Failed to get current dir")
+        .join(directory);
+
+    let mut migrations =
+        migrations::mark_migrations_in_directory(&connection, Path::new(&migration_dir))?
+            .into_iter()
+            .map(|(migration, applied)| (migration.version().to_owned(), applied))
+            .collect::<Vec<_>>();
+    migrations.sort();
+    Ok(migrations)
+}
+
 pub fn fixture(config: &DatabaseConnection) -> MigrationResult<()> {
     let connection = config.establish()?;
     Ok(migrate(&connection, "fixtures")?)
@@ -141,7 +171,8 @@ pub fn fixture(config: &DatabaseConnection) -> MigrationResult<()> {
 #[cfg(test)]
 mod tests {
     use diesel::prelude::*;
-    use std::env;
+    use timada_util::env::var_or;
+    use timada_util::secret::Secret;
     use uuid::Uuid;
 
     use super::{DatabaseConnection, MigrationError};
@@ -165,9 +196,9 @@ mod tests {
     fn migratation() {
         use self::todos::dsl::{id, todos};
 
-        let host = env::var("DB_HOST").unwrap_or_else(|_| "localhost".to_owned());
-        let user = env::var("DB_USER").unwrap_or_else(|_| "root".to_owned());
-        let password = env::var("DB_PASSWORD").unwrap_or_else(|_| "root".to_owned());
+        let host = var_or("DB_HOST", "localhost");
+        let user = var_or("DB_USER", "root");
+        let password = Secret::from(var_or("DB_PASSWORD", "root"));
 
         let config = &DatabaseConnection {
             host,
@@ -215,9 +246,9 @@ mod tests {
 
     #[test]
     fn reset_bad_db_name() {
-        let host = env::var("DB_HOST").unwrap_or_else(|_| "localhost".to_owned());
-        let user = env::var("DB_USER").unwrap_or_else(|_| "root".to_owned());
-        let password = env::var("DB_PASSWORD").unwrap_or_else(|_| "root".to_owned());
+        let host = var_or("DB_HOST", "localhost");
+        let user = var_or("DB_USER", "root");
+        let password = Secret::from(var_or("DB_PASSWORD", "root"));
 
         let config = &DatabaseConnection {
             host,
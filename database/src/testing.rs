@@ -0,0 +1,88 @@
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use timada_util::env::var_or;
+use timada_util::secret::Secret;
+
+use super::connection::DatabaseConnection;
+use super::migration::{fixture, setup};
+
+lazy_static::lazy_static! {
+    static ref READY: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+pub fn test_config(name: &str) -> DatabaseConnection {
+    DatabaseConnection {
+        host: var_or("DB_HOST", "localhost"),
+        user: var_or("DB_USER", "root"),
+        password: Secret::from(var_or("DB_PASSWORD", "root")),
+        name: Some(name.to_owned()),
+    }
+}
+
+// Migrations and fixtures run once per database and process, every connection then
+// runs in a transaction that is never committed so tests can't see each other's rows.
+pub fn test_connection(name: &str) -> PgConnection {
+    let config = test_config(name);
+
+    {
+        let mut ready = READY.lock().expect("Failed to lock test databases");
+
+        if !ready.contains(name) {
+            setup(&config).unwrap_or_else(|e| panic!("Failed to setup {}: {:?}", name, e));
+
+            if Path::new("fixtures").exists() {
+                fixture(&config)
+                    .unwrap_or_else(|e| panic!("Failed to load fixtures of {}: {:?}", name, e));
+            }
+
+            ready.insert(name.to_owned());
+        }
+    }
+
+    let conn = config
+        .establish()
+        .unwrap_or_else(|e| panic!("Failed to connect to {}: {}", name, e));
+
+    conn.begin_test_transaction()
+        .unwrap_or_else(|e| panic!("Failed to begin test transaction: {}", e));
+
+    conn
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+
+    use super::test_connection;
+
+    table! {
+        todos (id) {
+            id -> Uuid,
+            text -> Varchar,
+            is_done -> Bool,
+        }
+    }
+
+    #[test]
+    fn rollback() {
+        use self::todos::dsl::{text, todos};
+
+        let conn = test_connection("timada_database_dev");
+        let count = todos.count().get_result::<i64>(&conn).unwrap();
+
+        diesel::insert_into(todos)
+            .values(text.eq("Rolled back"))
+            .execute(&conn)
+            .unwrap();
+
+        assert_eq!(todos.count().get_result::<i64>(&conn), Ok(count + 1));
+
+        drop(conn);
+
+        let conn = test_connection("timada_database_dev");
+        assert_eq!(todos.count().get_result::<i64>(&conn), Ok(count));
+    }
+}
@@ -0,0 +1,26 @@
+use diesel::r2d2::PoolError;
+
+use super::connection::{Pool, PooledConnection};
+
+// Runs a blocking query inside a `db_query` span, errors returned by `f` mark the span as failed.
+pub fn traced<T, E, F>(operation: &str, table: &str, f: F) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnOnce() -> Result<T, E>,
+{
+    let span = timada_telemetry::db_query_span(operation, table);
+    let _enter = span.enter();
+
+    let res = f();
+    if let Err(e) = res.as_ref() {
+        span.record("otel.status_code", &"ERROR");
+        tracing::error!(error = %e, "query failed");
+    }
+
+    res
+}
+
+// Waiting on a saturated pool shows up in the trace as its own span.
+pub fn checkout(pool: &Pool) -> Result<PooledConnection, PoolError> {
+    traced("CHECKOUT", "pool", || pool.get())
+}
@@ -0,0 +1,2 @@
+DROP TABLE event_store_snapshots;
+DROP TABLE event_store_events;
@@ -0,0 +1,17 @@
+CREATE TABLE event_store_events (
+  global_position BIGSERIAL PRIMARY KEY,
+  stream_id VARCHAR(255) NOT NULL,
+  version BIGINT NOT NULL,
+  event_type VARCHAR(255) NOT NULL,
+  data JSONB NOT NULL,
+  metadata JSONB NOT NULL DEFAULT '{}',
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (stream_id, version)
+);
+
+CREATE TABLE event_store_snapshots (
+  stream_id VARCHAR(255) PRIMARY KEY,
+  version BIGINT NOT NULL,
+  data JSONB NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
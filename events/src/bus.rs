@@ -0,0 +1,208 @@
+use futures::future::BoxFuture;
+use futures::lock::Mutex;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+
+use super::error::{EventError, EventResult};
+
+pub trait Event: Clone + Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+type SyncHandler = Arc<dyn Fn(&dyn Any) -> EventResult<()> + Send + Sync>;
+type AsyncHandler =
+    Arc<dyn Fn(Box<dyn Any + Send>) -> BoxFuture<'static, EventResult<()>> + Send + Sync>;
+
+#[derive(Clone)]
+enum Subscriber {
+    Sync(SyncHandler),
+    Async(AsyncHandler),
+}
+
+#[derive(Default)]
+struct Channel {
+    subscribers: Vec<Subscriber>,
+    lock: Arc<Mutex<()>>,
+}
+
+#[derive(Clone, Default)]
+pub struct EventBus {
+    channels: Arc<RwLock<HashMap<TypeId, Channel>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add<E: Event>(&self, subscriber: Subscriber) {
+        let mut channels = match self.channels.write() {
+            Ok(channels) => channels,
+            Err(e) => e.into_inner(),
+        };
+
+        channels
+            .entry(TypeId::of::<E>())
+            .or_default()
+            .subscribers
+            .push(subscriber);
+    }
+
+    pub fn subscribe<E, F>(&self, f: F)
+    where
+        E: Event,
+        F: Fn(&E) -> EventResult<()> + Send + Sync + 'static,
+    {
+        self.add::<E>(Subscriber::Sync(Arc::new(move |event| {
+            match event.downcast_ref::<E>() {
+                Some(event) => f(event),
+                None => Ok(()),
+            }
+        })));
+    }
+
+    pub fn subscribe_async<E, F, Fut>(&self, f: F)
+    where
+        E: Event,
+        F: Fn(E) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = EventResult<()>> + Send + 'static,
+    {
+        self.add::<E>(Subscriber::Async(Arc::new(move |event| {
+            let fut: BoxFuture<'static, EventResult<()>> = match event.downcast::<E>() {
+                Ok(event) => Box::pin(f(*event)),
+                Err(_) => Box::pin(async { Ok(()) }),
+            };
+
+            fut
+        })));
+    }
+
+    pub fn subscribers<E: Event>(&self) -> usize {
+        self.channels
+            .read()
+            .ok()
+            .and_then(|channels| {
+                channels
+                    .get(&TypeId::of::<E>())
+                    .map(|channel| channel.subscribers.len())
+            })
+            .unwrap_or(0)
+    }
+
+    pub async fn publish<E: Event>(&self, event: E) -> EventResult<()> {
+        let (subscribers, lock) = {
+            let channels = match self.channels.read() {
+                Ok(channels) => channels,
+                Err(e) => e.into_inner(),
+            };
+
+            match channels.get(&TypeId::of::<E>()) {
+                Some(channel) => (channel.subscribers.clone(), channel.lock.clone()),
+                None => return Ok(()),
+            }
+        };
+
+        let _guard = lock.lock().await;
+        let mut errors = Vec::new();
+
+        for subscriber in subscribers.iter() {
+            let res = match subscriber {
+                Subscriber::Sync(handler) => handler(&event),
+                Subscriber::Async(handler) => handler(Box::new(event.clone())).await,
+            };
+
+            if let Err(e) = res {
+                log::error!("{} subscriber failed: {}", E::NAME, e);
+                errors.push(e.to_string());
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(EventError::Failed(errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use std::sync::{Arc, Mutex};
+
+    use super::{Event, EventBus};
+    use crate::error::EventError;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct UserCreated {
+        id: u32,
+    }
+
+    impl Event for UserCreated {
+        const NAME: &'static str = "UserCreated";
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct UserDeleted;
+
+    impl Event for UserDeleted {
+        const NAME: &'static str = "UserDeleted";
+    }
+
+    #[test]
+    fn ordering() {
+        let bus = EventBus::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        let sync_calls = calls.clone();
+        bus.subscribe(move |event: &UserCreated| {
+            sync_calls
+                .lock()
+                .unwrap()
+                .push(format!("sync:{}", event.id));
+            Ok(())
+        });
+
+        let async_calls = calls.clone();
+        bus.subscribe_async(move |event: UserCreated| {
+            let calls = async_calls.clone();
+            async move {
+                calls.lock().unwrap().push(format!("async:{}", event.id));
+                Ok(())
+            }
+        });
+
+        block_on(bus.publish(UserCreated { id: 1 })).unwrap();
+        block_on(bus.publish(UserCreated { id: 2 })).unwrap();
+        block_on(bus.publish(UserDeleted)).unwrap();
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["sync:1", "async:1", "sync:2", "async:2"]
+        );
+        assert_eq!(bus.subscribers::<UserCreated>(), 2);
+        assert_eq!(bus.subscribers::<UserDeleted>(), 0);
+    }
+
+    #[test]
+    fn errors() {
+        let bus = EventBus::new();
+        let calls = Arc::new(Mutex::new(0));
+
+        bus.subscribe(|_: &UserCreated| Err(EventError::subscriber("mailer down")));
+
+        let counter = calls.clone();
+        bus.subscribe(move |_: &UserCreated| {
+            *counter.lock().unwrap() += 1;
+            Ok(())
+        });
+
+        assert_eq!(
+            block_on(bus.publish(UserCreated { id: 1 })),
+            Err(EventError::Failed(vec!["mailer down".to_owned()]))
+        );
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+}
@@ -0,0 +1,41 @@
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum EventError {
+    #[error("{0}")]
+    Subscriber(String),
+
+    #[error("{} subscriber(s) failed: {}", .0.len(), .0.join(", "))]
+    Failed(Vec<String>),
+
+    #[error("Stream {stream_id} expected version {expected}, found {actual:?}")]
+    Concurrency {
+        stream_id: String,
+        expected: String,
+        actual: Option<i64>,
+    },
+
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+impl EventError {
+    pub fn subscriber<E: std::fmt::Display>(e: E) -> Self {
+        EventError::Subscriber(e.to_string())
+    }
+}
+
+impl From<diesel::result::Error> for EventError {
+    fn from(e: diesel::result::Error) -> EventError {
+        EventError::Database(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for EventError {
+    fn from(e: serde_json::Error) -> EventError {
+        EventError::Serialization(e.to_string())
+    }
+}
+
+pub type EventResult<T> = Result<T, EventError>;
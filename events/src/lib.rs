@@ -0,0 +1,24 @@
+#[macro_use]
+extern crate diesel;
+
+#[macro_use]
+extern crate diesel_migrations;
+
+#[macro_use]
+extern crate thiserror;
+
+mod bus;
+mod error;
+mod migration;
+mod schema;
+mod store;
+
+pub mod testing;
+
+pub use crate::bus::{Event, EventBus};
+pub use crate::error::{EventError, EventResult};
+pub use crate::migration::migrate;
+pub use crate::store::{
+    append, load_snapshot, read_all, read_stream, save_snapshot, stream_version, ExpectedVersion,
+    NewEvent, RecordedEvent,
+};
@@ -0,0 +1,20 @@
+table! {
+    event_store_events (global_position) {
+        global_position -> Int8,
+        stream_id -> Varchar,
+        version -> Int8,
+        event_type -> Varchar,
+        data -> Jsonb,
+        metadata -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    event_store_snapshots (stream_id) {
+        stream_id -> Varchar,
+        version -> Int8,
+        data -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
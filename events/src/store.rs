@@ -0,0 +1,280 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::max;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::sql_types::BigInt;
+use diesel::PgConnection;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::error::{EventError, EventResult};
+use super::schema::{event_store_events, event_store_snapshots};
+
+// Appends are serialized by this transaction lock so global positions commit in order,
+// otherwise `read_all` could move past a position a slower transaction commits later.
+const APPEND_LOCK: i64 = 0x6576_656e_7473;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExpectedVersion {
+    Any,
+    NoStream,
+    Exact(i64),
+}
+
+impl ExpectedVersion {
+    fn check(self, stream_id: &str, current: Option<i64>) -> EventResult<()> {
+        let matches = match self {
+            ExpectedVersion::Any => true,
+            ExpectedVersion::NoStream => current.is_none(),
+            ExpectedVersion::Exact(version) => current == Some(version),
+        };
+
+        if !matches {
+            return Err(EventError::Concurrency {
+                stream_id: stream_id.to_owned(),
+                expected: format!("{:?}", self),
+                actual: current,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewEvent {
+    pub event_type: String,
+    pub data: Value,
+    pub metadata: Value,
+}
+
+impl NewEvent {
+    pub fn new<T: Serialize>(event_type: &str, data: &T) -> EventResult<Self> {
+        Ok(NewEvent {
+            event_type: event_type.to_owned(),
+            data: serde_json::to_value(data)?,
+            metadata: Value::Object(Default::default()),
+        })
+    }
+
+    pub fn metadata<T: Serialize>(mut self, metadata: &T) -> EventResult<Self> {
+        self.metadata = serde_json::to_value(metadata)?;
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable)]
+pub struct RecordedEvent {
+    pub global_position: i64,
+    pub stream_id: String,
+    pub version: i64,
+    pub event_type: String,
+    pub data: Value,
+    pub metadata: Value,
+    pub created_at: NaiveDateTime,
+}
+
+impl RecordedEvent {
+    pub fn decode<T: DeserializeOwned>(&self) -> EventResult<T> {
+        Ok(serde_json::from_value(self.data.clone())?)
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "event_store_events"]
+struct NewEventRecord<'a> {
+    stream_id: &'a str,
+    version: i64,
+    event_type: &'a str,
+    data: &'a Value,
+    metadata: &'a Value,
+}
+
+#[derive(Insertable)]
+#[table_name = "event_store_snapshots"]
+struct NewSnapshot<'a> {
+    stream_id: &'a str,
+    version: i64,
+    data: Value,
+    created_at: NaiveDateTime,
+}
+
+pub fn stream_version(conn: &PgConnection, stream_id: &str) -> EventResult<Option<i64>> {
+    Ok(event_store_events::table
+        .filter(event_store_events::stream_id.eq(stream_id))
+        .select(max(event_store_events::version))
+        .first(conn)?)
+}
+
+pub fn append(
+    conn: &PgConnection,
+    stream_id: &str,
+    expected: ExpectedVersion,
+    events: &[NewEvent],
+) -> EventResult<i64> {
+    conn.transaction(|| {
+        diesel::sql_query("SELECT pg_advisory_xact_lock($1)")
+            .bind::<BigInt, _>(APPEND_LOCK)
+            .execute(conn)?;
+
+        let current = stream_version(conn, stream_id)?;
+        expected.check(stream_id, current)?;
+
+        let first = current.map_or(1, |version| version + 1);
+        let records = events
+            .iter()
+            .enumerate()
+            .map(|(index, event)| NewEventRecord {
+                stream_id,
+                version: first + index as i64,
+                event_type: &event.event_type,
+                data: &event.data,
+                metadata: &event.metadata,
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(event_store_events::table)
+            .values(&records)
+            .execute(conn)
+            .map_err(|e| match e {
+                DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                    EventError::Concurrency {
+                        stream_id: stream_id.to_owned(),
+                        expected: format!("{:?}", expected),
+                        actual: None,
+                    }
+                }
+                e => EventError::from(e),
+            })?;
+
+        Ok(first + events.len() as i64 - 1)
+    })
+}
+
+pub fn read_stream(
+    conn: &PgConnection,
+    stream_id: &str,
+    from_version: i64,
+) -> EventResult<Vec<RecordedEvent>> {
+    Ok(event_store_events::table
+        .filter(event_store_events::stream_id.eq(stream_id))
+        .filter(event_store_events::version.ge(from_version))
+        .order(event_store_events::version.asc())
+        .load(conn)?)
+}
+
+pub fn read_all(
+    conn: &PgConnection,
+    after_position: i64,
+    limit: i64,
+) -> EventResult<Vec<RecordedEvent>> {
+    Ok(event_store_events::table
+        .filter(event_store_events::global_position.gt(after_position))
+        .order(event_store_events::global_position.asc())
+        .limit(limit)
+        .load(conn)?)
+}
+
+pub fn save_snapshot<T: Serialize>(
+    conn: &PgConnection,
+    stream_id: &str,
+    version: i64,
+    state: &T,
+) -> EventResult<()> {
+    let snapshot = NewSnapshot {
+        stream_id,
+        version,
+        data: serde_json::to_value(state)?,
+        created_at: Utc::now().naive_utc(),
+    };
+
+    diesel::insert_into(event_store_snapshots::table)
+        .values(&snapshot)
+        .on_conflict(event_store_snapshots::stream_id)
+        .do_update()
+        .set((
+            event_store_snapshots::version.eq(snapshot.version),
+            event_store_snapshots::data.eq(&snapshot.data),
+            event_store_snapshots::created_at.eq(snapshot.created_at),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn load_snapshot<T: DeserializeOwned>(
+    conn: &PgConnection,
+    stream_id: &str,
+) -> EventResult<Option<(i64, T)>> {
+    let snapshot: Option<(i64, Value)> = event_store_snapshots::table
+        .find(stream_id)
+        .select((event_store_snapshots::version, event_store_snapshots::data))
+        .first(conn)
+        .optional()?;
+
+    match snapshot {
+        Some((version, data)) => Ok(Some((version, serde_json::from_value(data)?))),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use timada_database::testing::test_connection;
+
+    use super::{append, read_all, ExpectedVersion, NewEvent};
+    use crate::error::EventError;
+
+    #[test]
+    fn expected_version() {
+        assert!(ExpectedVersion::Any.check("user-1", Some(3)).is_ok());
+        assert!(ExpectedVersion::NoStream.check("user-1", None).is_ok());
+        assert!(ExpectedVersion::Exact(3).check("user-1", Some(3)).is_ok());
+
+        assert_eq!(
+            ExpectedVersion::Exact(2).check("user-1", Some(3)),
+            Err(EventError::Concurrency {
+                stream_id: "user-1".to_owned(),
+                expected: "Exact(2)".to_owned(),
+                actual: Some(3),
+            })
+        );
+        assert!(ExpectedVersion::NoStream.check("user-1", Some(1)).is_err());
+    }
+
+    #[test]
+    fn new_event() {
+        let event = NewEvent::new("UserRenamed", &json!({ "name": "John" }))
+            .unwrap()
+            .metadata(&json!({ "request_id": "abc" }))
+            .unwrap();
+
+        assert_eq!(event.data, json!({ "name": "John" }));
+        assert_eq!(event.metadata, json!({ "request_id": "abc" }));
+    }
+
+    #[test]
+    fn append_in_order() {
+        let conn = test_connection("timada_events_test");
+        let event = NewEvent::new("UserRenamed", &json!({ "name": "John" })).unwrap();
+
+        append(&conn, "user-1", ExpectedVersion::NoStream, &[event.clone()]).unwrap();
+        append(&conn, "user-2", ExpectedVersion::NoStream, &[event.clone()]).unwrap();
+        append(&conn, "user-1", ExpectedVersion::Exact(1), &[event]).unwrap();
+
+        let events = read_all(&conn, 0, 10).unwrap();
+
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (event.stream_id.as_str(), event.version))
+                .collect::<Vec<_>>(),
+            vec![("user-1", 1), ("user-2", 1), ("user-1", 2)]
+        );
+        assert!(events
+            .windows(2)
+            .all(|pair| pair[0].global_position < pair[1].global_position));
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::{Arc, Mutex};
+
+use super::bus::{Event, EventBus};
+
+#[derive(Clone)]
+pub struct EventRecorder<E> {
+    events: Arc<Mutex<Vec<E>>>,
+}
+
+impl<E: Event> EventRecorder<E> {
+    pub fn new(bus: &EventBus) -> Self {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+
+        bus.subscribe(move |event: &E| {
+            if let Ok(mut events) = recorded.lock() {
+                events.push(event.clone());
+            }
+
+            Ok(())
+        });
+
+        EventRecorder { events }
+    }
+
+    pub fn events(&self) -> Vec<E> {
+        self.events
+            .lock()
+            .map(|events| events.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut events) = self.events.lock() {
+            events.clear();
+        }
+    }
+
+    pub fn assert_emitted<F: Fn(&E) -> bool>(&self, predicate: F) {
+        assert!(
+            self.events().iter().any(predicate),
+            "expected a matching {} event to be emitted",
+            E::NAME
+        );
+    }
+
+    pub fn assert_not_emitted(&self) {
+        assert!(
+            self.is_empty(),
+            "expected no {} event, {} emitted",
+            E::NAME,
+            self.len()
+        );
+    }
+}
+
+pub fn record<E: Event>(bus: &EventBus) -> EventRecorder<E> {
+    EventRecorder::new(bus)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::record;
+    use crate::bus::{Event, EventBus};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct OrderPlaced {
+        total: u32,
+    }
+
+    impl Event for OrderPlaced {
+        const NAME: &'static str = "OrderPlaced";
+    }
+
+    #[test]
+    fn recorder() {
+        let bus = EventBus::new();
+        let recorder = record::<OrderPlaced>(&bus);
+
+        recorder.assert_not_emitted();
+
+        block_on(bus.publish(OrderPlaced { total: 42 })).unwrap();
+
+        recorder.assert_emitted(|event| event.total == 42);
+        assert_eq!(recorder.events(), vec![OrderPlaced { total: 42 }]);
+
+        recorder.clear();
+        assert!(recorder.is_empty());
+    }
+}
@@ -0,0 +1,13 @@
+CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
+
+CREATE TABLE users (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  email VARCHAR(255) NOT NULL UNIQUE,
+  role VARCHAR(32) NOT NULL
+);
+
+CREATE TABLE posts (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  author_id uuid NOT NULL REFERENCES users (id),
+  title VARCHAR(255) NOT NULL
+);
@@ -0,0 +1,38 @@
+use diesel::{PgConnection, QueryResult};
+
+use super::factory::Factory;
+
+// A belongs-to relation of a factory, the associated row is only inserted when the
+// test didn't provide an existing one.
+pub enum Association<F: Factory> {
+    Factory(F),
+    Existing(F::Model),
+}
+
+impl<F: Factory> Association<F> {
+    pub fn resolve(self, conn: &PgConnection) -> QueryResult<F::Model> {
+        match self {
+            Association::Factory(factory) => factory.insert(conn),
+            Association::Existing(model) => Ok(model),
+        }
+    }
+}
+
+impl<F: Factory + Default> Default for Association<F> {
+    fn default() -> Self {
+        Association::Factory(F::default())
+    }
+}
+
+impl<F> Clone for Association<F>
+where
+    F: Factory + Clone,
+    F::Model: Clone,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Association::Factory(factory) => Association::Factory(factory.clone()),
+            Association::Existing(model) => Association::Existing(model.clone()),
+        }
+    }
+}
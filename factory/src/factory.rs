@@ -0,0 +1,200 @@
+use diesel::{PgConnection, QueryResult};
+
+// Factories hold the overrides of a test, defaults are resolved in `insert` so every
+// inserted row gets fresh sequence values:
+// `UserFactory::new().role(Role::Admin).insert(&conn)`
+pub trait Factory: Sized {
+    type Model;
+
+    fn insert(self, conn: &PgConnection) -> QueryResult<Self::Model>;
+
+    fn insert_many(self, conn: &PgConnection, count: usize) -> QueryResult<Vec<Self::Model>>
+    where
+        Self: Clone,
+    {
+        (0..count).map(|_| self.clone().insert(conn)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+    use diesel::{PgConnection, QueryResult};
+    use uuid::Uuid;
+
+    use super::Factory;
+    use crate::{fake, test_connection, Association, Sequence};
+
+    table! {
+        users (id) {
+            id -> Uuid,
+            email -> Varchar,
+            role -> Varchar,
+        }
+    }
+
+    table! {
+        posts (id) {
+            id -> Uuid,
+            author_id -> Uuid,
+            title -> Varchar,
+        }
+    }
+
+    static EMAILS: Sequence = Sequence::new();
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Role {
+        Admin,
+        User,
+    }
+
+    impl Role {
+        fn as_str(&self) -> &'static str {
+            match self {
+                Role::Admin => "admin",
+                Role::User => "user",
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Queryable, PartialEq)]
+    struct User {
+        id: Uuid,
+        email: String,
+        role: String,
+    }
+
+    #[derive(Debug, Queryable, PartialEq)]
+    struct Post {
+        id: Uuid,
+        author_id: Uuid,
+        title: String,
+    }
+
+    #[derive(Default, Clone)]
+    struct UserFactory {
+        email: Option<String>,
+        role: Option<Role>,
+    }
+
+    impl UserFactory {
+        fn new() -> Self {
+            UserFactory::default()
+        }
+
+        fn email(mut self, email: &str) -> Self {
+            self.email = Some(email.to_owned());
+            self
+        }
+
+        fn role(mut self, role: Role) -> Self {
+            self.role = Some(role);
+            self
+        }
+    }
+
+    impl Factory for UserFactory {
+        type Model = User;
+
+        fn insert(self, conn: &PgConnection) -> QueryResult<User> {
+            let email = self
+                .email
+                .unwrap_or_else(|| EMAILS.format("user{}@timada.co"));
+            let role = self.role.unwrap_or(Role::User);
+
+            diesel::insert_into(users::table)
+                .values((users::email.eq(email), users::role.eq(role.as_str())))
+                .get_result(conn)
+        }
+    }
+
+    #[derive(Default, Clone)]
+    struct PostFactory {
+        author: Association<UserFactory>,
+        title: Option<String>,
+    }
+
+    impl PostFactory {
+        fn new() -> Self {
+            PostFactory::default()
+        }
+
+        fn author(mut self, author: User) -> Self {
+            self.author = Association::Existing(author);
+            self
+        }
+
+        fn author_factory(mut self, author: UserFactory) -> Self {
+            self.author = Association::Factory(author);
+            self
+        }
+    }
+
+    impl Factory for PostFactory {
+        type Model = Post;
+
+        fn insert(self, conn: &PgConnection) -> QueryResult<Post> {
+            let author = self.author.resolve(conn)?;
+            let title = self.title.unwrap_or_else(|| fake(|faker| faker.sentence()));
+
+            diesel::insert_into(posts::table)
+                .values((posts::author_id.eq(author.id), posts::title.eq(title)))
+                .get_result(conn)
+        }
+    }
+
+    #[test]
+    fn defaults_and_overrides() {
+        let conn = test_connection("timada_factory_dev");
+
+        let user = UserFactory::new().insert(&conn).unwrap();
+        assert_eq!(user.role, "user");
+        assert!(user.email.ends_with("@timada.co"));
+
+        let admin = UserFactory::new()
+            .email("admin@timada.co")
+            .role(Role::Admin)
+            .insert(&conn)
+            .unwrap();
+        assert_eq!(admin.email, "admin@timada.co");
+        assert_eq!(admin.role, "admin");
+
+        let users = UserFactory::new().insert_many(&conn, 3).unwrap();
+        assert_eq!(users.len(), 3);
+        assert_ne!(users[0].email, users[1].email);
+    }
+
+    #[test]
+    fn associations() {
+        let conn = test_connection("timada_factory_dev");
+
+        let post = PostFactory::new().insert(&conn).unwrap();
+        let author = users::table
+            .find(post.author_id)
+            .first::<User>(&conn)
+            .unwrap();
+        assert_eq!(author.role, "user");
+
+        let post = PostFactory::new()
+            .author_factory(UserFactory::new().role(Role::Admin))
+            .insert(&conn)
+            .unwrap();
+        let author = users::table
+            .find(post.author_id)
+            .first::<User>(&conn)
+            .unwrap();
+        assert_eq!(author.role, "admin");
+
+        let posts = PostFactory::new()
+            .author(author.clone())
+            .insert_many(&conn, 2)
+            .unwrap();
+        assert!(posts.iter().all(|post| post.author_id == author.id));
+        assert_eq!(
+            users::table.count().get_result::<i64>(&conn),
+            Ok(2),
+            "existing authors must not be inserted again"
+        );
+    }
+}
@@ -0,0 +1,9 @@
+mod association;
+mod factory;
+mod sequence;
+
+pub use crate::association::Association;
+pub use crate::factory::Factory;
+pub use crate::sequence::Sequence;
+pub use timada_database::testing::{test_config, test_connection};
+pub use timada_util::fake::{fake, seed, Faker};
@@ -0,0 +1,41 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Process wide so values stay unique across tests running in parallel, e.g.
+// `static EMAILS: Sequence = Sequence::new();`
+pub struct Sequence(AtomicUsize);
+
+impl Sequence {
+    pub const fn new() -> Self {
+        Sequence(AtomicUsize::new(0))
+    }
+
+    pub fn next(&self) -> usize {
+        self.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    // Replaces `{}` in the template with the next value, `user{}@timada.co` -> `user1@timada.co`.
+    pub fn format(&self, template: &str) -> String {
+        template.replace("{}", &self.next().to_string())
+    }
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Sequence::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sequence;
+
+    #[test]
+    fn next() {
+        static USERS: Sequence = Sequence::new();
+
+        assert_eq!(USERS.next(), 1);
+        assert_eq!(USERS.next(), 2);
+        assert_eq!(USERS.format("user{}@timada.co"), "user3@timada.co");
+        assert_eq!(Sequence::default().format("todo-{}"), "todo-1");
+    }
+}
@@ -0,0 +1,10 @@
+CREATE TABLE feature_flags (
+  key VARCHAR(100) PRIMARY KEY,
+  description TEXT,
+  enabled BOOLEAN NOT NULL DEFAULT false,
+  percentage SMALLINT CHECK (percentage BETWEEN 0 AND 100),
+  roles TEXT[] NOT NULL DEFAULT '{}',
+  user_ids uuid[] NOT NULL DEFAULT '{}',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
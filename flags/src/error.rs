@@ -0,0 +1,32 @@
+use diesel::result::Error as DieselError;
+use timada_http::Error;
+
+#[derive(Debug, PartialEq, Error)]
+pub enum FlagError {
+    #[error("Feature flag {0} not found")]
+    NotFound(String),
+
+    #[error("Invalid feature flag: {0}")]
+    Invalid(String),
+
+    #[error("{0}")]
+    Database(String),
+}
+
+impl From<DieselError> for FlagError {
+    fn from(e: DieselError) -> FlagError {
+        FlagError::Database(e.to_string())
+    }
+}
+
+impl From<FlagError> for Error {
+    fn from(e: FlagError) -> Error {
+        match e {
+            FlagError::NotFound(_) => Error::NotFound,
+            FlagError::Invalid(message) => Error::UnprocessableEntity(message),
+            FlagError::Database(message) => Error::Internal(message),
+        }
+    }
+}
+
+pub type FlagResult<T> = Result<T, FlagError>;
@@ -0,0 +1,198 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use timada_http::User;
+use timada_util::flags::Flag;
+use uuid::Uuid;
+
+use super::error::{FlagError, FlagResult};
+use super::schema::feature_flags;
+
+#[derive(Debug, Clone, PartialEq, Queryable, Identifiable)]
+#[table_name = "feature_flags"]
+#[primary_key(key)]
+pub struct FeatureFlag {
+    pub key: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub percentage: Option<i16>,
+    pub roles: Vec<String>,
+    pub user_ids: Vec<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl FeatureFlag {
+    pub fn is_targeted(&self) -> bool {
+        self.percentage.is_some() || !self.roles.is_empty() || !self.user_ids.is_empty()
+    }
+
+    pub fn is_enabled_for(&self, user: Option<&User>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        if !self.is_targeted() {
+            return true;
+        }
+
+        let user = match user {
+            Some(user) => user,
+            None => return self.percentage.map(|p| p >= 100).unwrap_or(false),
+        };
+
+        if self.user_ids.contains(&user.id) || self.roles.iter().any(|role| user.role.is(role)) {
+            return true;
+        }
+
+        match self.percentage {
+            Some(percentage) => Flag::Percentage(percentage.max(0).min(100) as u8)
+                .is_enabled_for(&self.key, &user.id.to_string()),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Insertable, AsChangeset)]
+#[table_name = "feature_flags"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct FlagChanges {
+    pub key: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub percentage: Option<i16>,
+    pub roles: Vec<String>,
+    pub user_ids: Vec<Uuid>,
+}
+
+impl FlagChanges {
+    pub fn new(key: &str, enabled: bool) -> Self {
+        FlagChanges {
+            key: key.to_owned(),
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    pub fn validate(&self) -> FlagResult<()> {
+        let valid_key = !self.key.is_empty()
+            && self
+                .key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+
+        if !valid_key {
+            return Err(FlagError::Invalid(format!("Invalid key {:?}", self.key)));
+        }
+
+        match self.percentage {
+            Some(percentage) if !(0..=100).contains(&percentage) => Err(FlagError::Invalid(
+                "Percentage must be between 0 and 100".to_owned(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn list_flags(conn: &PgConnection) -> FlagResult<Vec<FeatureFlag>> {
+    Ok(feature_flags::table
+        .order(feature_flags::key.asc())
+        .load(conn)?)
+}
+
+pub fn find_flag(conn: &PgConnection, key: &str) -> FlagResult<FeatureFlag> {
+    feature_flags::table
+        .find(key)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| FlagError::NotFound(key.to_owned()))
+}
+
+pub fn save_flag(conn: &PgConnection, changes: &FlagChanges) -> FlagResult<FeatureFlag> {
+    changes.validate()?;
+
+    Ok(diesel::insert_into(feature_flags::table)
+        .values(changes)
+        .on_conflict(feature_flags::key)
+        .do_update()
+        .set((changes, feature_flags::updated_at.eq(Utc::now())))
+        .get_result(conn)?)
+}
+
+pub fn delete_flag(conn: &PgConnection, key: &str) -> FlagResult<bool> {
+    Ok(diesel::delete(feature_flags::table.find(key)).execute(conn)? > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use timada_http::testing::ContextBuilder;
+    use timada_http::UserRole;
+    use uuid::Uuid;
+
+    use super::{FeatureFlag, FlagChanges};
+
+    fn flag() -> FeatureFlag {
+        FeatureFlag {
+            key: "new_checkout".to_owned(),
+            description: None,
+            enabled: true,
+            percentage: None,
+            roles: Vec::new(),
+            user_ids: Vec::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn targeting() {
+        let user = ContextBuilder::new().build().user.unwrap();
+        let staff = ContextBuilder::new()
+            .role(UserRole::Staff)
+            .build()
+            .user
+            .unwrap();
+
+        let mut flag = flag();
+        assert!(flag.is_enabled_for(None));
+
+        flag.roles = vec!["Staff".to_owned()];
+        assert!(flag.is_enabled_for(Some(&staff)));
+        assert!(!flag.is_enabled_for(Some(&user)));
+        assert!(!flag.is_enabled_for(None));
+
+        flag.user_ids = vec![user.id];
+        assert!(flag.is_enabled_for(Some(&user)));
+
+        flag.enabled = false;
+        assert!(!flag.is_enabled_for(Some(&user)));
+    }
+
+    #[test]
+    fn rollout() {
+        let mut flag = flag();
+        flag.percentage = Some(30);
+
+        let enabled = (0..1000)
+            .filter(|_| {
+                let user = ContextBuilder::new().id(Uuid::new_v4()).build().user;
+                flag.is_enabled_for(user.as_ref())
+            })
+            .count();
+        assert!(enabled > 200 && enabled < 400);
+
+        flag.percentage = Some(0);
+        assert!(!flag.is_enabled_for(ContextBuilder::new().build().user.as_ref()));
+    }
+
+    #[test]
+    fn validate() {
+        assert!(FlagChanges::new("new_checkout", true).validate().is_ok());
+        assert!(FlagChanges::new("new checkout", true).validate().is_err());
+
+        let mut changes = FlagChanges::new("new_checkout", true);
+        changes.percentage = Some(120);
+        assert!(changes.validate().is_err());
+    }
+}
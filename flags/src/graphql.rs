@@ -0,0 +1,267 @@
+use async_graphql::{Context as GraphQLContext, FieldResult, ID};
+use chrono::{DateTime, Utc};
+use std::convert::TryFrom;
+use timada_http::{Error, GraphQLContextExt, UserRole};
+use timada_relay::{from_typed_id, to_id};
+
+use super::error::FlagError;
+use super::flag::{delete_flag, find_flag, list_flags, save_flag, FeatureFlag, FlagChanges};
+use super::store::FlagStore;
+
+fn ensure_admin(ctx: &GraphQLContext<'_>) -> FieldResult<()> {
+    ctx.context()
+        .and_then(|context| {
+            context
+                .ensure_is_authorized(Some(vec![UserRole::Root, UserRole::Admin]))
+                .map_err(Error::from)
+        })
+        .map(|_| ())
+        .map_err(|e| ctx.extend_error(&e))
+}
+
+fn invalidate(ctx: &GraphQLContext<'_>) {
+    if let Ok(store) = ctx.data::<FlagStore>() {
+        store.invalidate();
+    }
+}
+
+#[async_graphql::Object]
+impl FeatureFlag {
+    #[field]
+    async fn key(&self) -> &str {
+        self.key.as_str()
+    }
+
+    #[field]
+    async fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    #[field]
+    async fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[field]
+    async fn percentage(&self) -> Option<i32> {
+        self.percentage.map(i32::from)
+    }
+
+    #[field]
+    async fn roles(&self) -> Vec<String> {
+        self.roles.clone()
+    }
+
+    #[field]
+    async fn user_ids(&self) -> Vec<ID> {
+        self.user_ids.iter().map(|id| to_id("User", id)).collect()
+    }
+
+    #[field]
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+}
+
+#[async_graphql::InputObject]
+pub struct FeatureFlagInput {
+    pub key: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub percentage: Option<i32>,
+    pub roles: Option<Vec<String>>,
+    pub user_ids: Option<Vec<ID>>,
+}
+
+impl FeatureFlagInput {
+    fn into_changes(self) -> Result<FlagChanges, Error> {
+        let user_ids = self
+            .user_ids
+            .unwrap_or_default()
+            .iter()
+            .map(|id| {
+                from_typed_id("User", id)
+                    .map_err(|_| Error::BadRequest(format!("Invalid user id {}", id.as_str())))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Narrowed to the `Int2` column, the range itself is checked by `validate`.
+        let percentage = self
+            .percentage
+            .map(i16::try_from)
+            .transpose()
+            .map_err(|_| FlagError::Invalid("Percentage must be between 0 and 100".to_owned()))?;
+
+        Ok(FlagChanges {
+            key: self.key,
+            description: self.description,
+            enabled: self.enabled,
+            percentage,
+            roles: self.roles.unwrap_or_default(),
+            user_ids,
+        })
+    }
+}
+
+pub struct FeatureFlagQuery;
+
+#[async_graphql::Object]
+impl FeatureFlagQuery {
+    #[field]
+    async fn feature_flags(&self, ctx: &GraphQLContext<'_>) -> FieldResult<Vec<FeatureFlag>> {
+        ensure_admin(ctx)?;
+
+        ctx.conn()
+            .and_then(|conn| list_flags(&conn).map_err(Error::from))
+            .map_err(|e| ctx.extend_error(&e))
+    }
+
+    #[field]
+    async fn feature_flag(
+        &self,
+        ctx: &GraphQLContext<'_>,
+        key: String,
+    ) -> FieldResult<FeatureFlag> {
+        ensure_admin(ctx)?;
+
+        ctx.conn()
+            .and_then(|conn| find_flag(&conn, &key).map_err(Error::from))
+            .map_err(|e| ctx.extend_error(&e))
+    }
+}
+
+pub struct FeatureFlagMutation;
+
+#[async_graphql::Object]
+impl FeatureFlagMutation {
+    #[field]
+    async fn save_feature_flag(
+        &self,
+        ctx: &GraphQLContext<'_>,
+        input: FeatureFlagInput,
+    ) -> FieldResult<FeatureFlag> {
+        ensure_admin(ctx)?;
+
+        let flag = input
+            .into_changes()
+            .and_then(|changes| {
+                let conn = ctx.conn()?;
+                Ok(save_flag(&conn, &changes)?)
+            })
+            .map_err(|e| ctx.extend_error(&e))?;
+
+        invalidate(ctx);
+
+        Ok(flag)
+    }
+
+    #[field]
+    async fn toggle_feature_flag(
+        &self,
+        ctx: &GraphQLContext<'_>,
+        key: String,
+        enabled: bool,
+    ) -> FieldResult<FeatureFlag> {
+        ensure_admin(ctx)?;
+
+        let flag = ctx
+            .conn()
+            .and_then(|conn| {
+                let flag = find_flag(&conn, &key)?;
+                let changes = FlagChanges {
+                    key: flag.key,
+                    description: flag.description,
+                    enabled,
+                    percentage: flag.percentage,
+                    roles: flag.roles,
+                    user_ids: flag.user_ids,
+                };
+
+                Ok(save_flag(&conn, &changes)?)
+            })
+            .map_err(|e: Error| ctx.extend_error(&e))?;
+
+        invalidate(ctx);
+
+        Ok(flag)
+    }
+
+    #[field]
+    async fn delete_feature_flag(
+        &self,
+        ctx: &GraphQLContext<'_>,
+        key: String,
+    ) -> FieldResult<bool> {
+        ensure_admin(ctx)?;
+
+        let deleted = ctx
+            .conn()
+            .and_then(|conn| delete_flag(&conn, &key).map_err(Error::from))
+            .map_err(|e| ctx.extend_error(&e))?;
+
+        invalidate(ctx);
+
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::ID;
+    use timada_http::Error;
+    use timada_relay::to_id;
+    use uuid::Uuid;
+
+    use super::FeatureFlagInput;
+
+    fn input(percentage: Option<i32>) -> FeatureFlagInput {
+        FeatureFlagInput {
+            key: "new_dashboard".to_owned(),
+            description: None,
+            enabled: true,
+            percentage,
+            roles: None,
+            user_ids: None,
+        }
+    }
+
+    fn with_user_id(id: ID) -> FeatureFlagInput {
+        FeatureFlagInput {
+            user_ids: Some(vec![id]),
+            ..input(None)
+        }
+    }
+
+    #[test]
+    fn percentage() {
+        assert_eq!(input(Some(30)).into_changes().unwrap().percentage, Some(30));
+        assert_eq!(input(None).into_changes().unwrap().percentage, None);
+        assert_eq!(
+            input(Some(65636)).into_changes().err(),
+            Some(Error::UnprocessableEntity(
+                "Percentage must be between 0 and 100".to_owned()
+            ))
+        );
+        assert!(input(Some(-1)).into_changes().unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn user_ids() {
+        let user_id = Uuid::new_v4();
+
+        assert_eq!(
+            with_user_id(to_id("User", &user_id))
+                .into_changes()
+                .unwrap()
+                .user_ids,
+            vec![user_id]
+        );
+        assert_eq!(
+            with_user_id(to_id("Flag", &user_id)).into_changes().err(),
+            Some(Error::BadRequest(format!(
+                "Invalid user id {}",
+                to_id("Flag", &user_id).as_str()
+            )))
+        );
+    }
+}
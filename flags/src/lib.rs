@@ -0,0 +1,21 @@
+#[macro_use]
+extern crate diesel;
+
+#[macro_use]
+extern crate diesel_migrations;
+
+#[macro_use]
+extern crate thiserror;
+
+mod error;
+mod flag;
+mod graphql;
+mod migration;
+mod schema;
+mod store;
+
+pub use crate::error::{FlagError, FlagResult};
+pub use crate::flag::{delete_flag, find_flag, list_flags, save_flag, FeatureFlag, FlagChanges};
+pub use crate::graphql::{FeatureFlagInput, FeatureFlagMutation, FeatureFlagQuery};
+pub use crate::migration::migrate;
+pub use crate::store::{FlagProvider, FlagStore, PgFlagProvider};
@@ -0,0 +1,12 @@
+table! {
+    feature_flags (key) {
+        key -> Varchar,
+        description -> Nullable<Text>,
+        enabled -> Bool,
+        percentage -> Nullable<Int2>,
+        roles -> Array<Text>,
+        user_ids -> Array<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
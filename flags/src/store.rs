@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use timada_database::Pool;
+use timada_http::{Context, User};
+use timada_util::flags;
+
+use super::error::{FlagError, FlagResult};
+use super::flag::{list_flags, FeatureFlag};
+
+pub trait FlagProvider: Send + Sync {
+    fn load(&self) -> FlagResult<Vec<FeatureFlag>>;
+}
+
+pub struct PgFlagProvider {
+    pool: Pool,
+}
+
+impl PgFlagProvider {
+    pub fn new(pool: Pool) -> Self {
+        PgFlagProvider { pool }
+    }
+}
+
+impl FlagProvider for PgFlagProvider {
+    fn load(&self) -> FlagResult<Vec<FeatureFlag>> {
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| FlagError::Database(e.to_string()))?;
+
+        list_flags(&conn)
+    }
+}
+
+struct Snapshot {
+    flags: Arc<HashMap<String, FeatureFlag>>,
+    loaded_at: Option<Instant>,
+}
+
+#[derive(Clone)]
+pub struct FlagStore {
+    provider: Arc<dyn FlagProvider>,
+    snapshot: Arc<RwLock<Snapshot>>,
+    ttl: Duration,
+}
+
+impl FlagStore {
+    pub fn new<P: FlagProvider + 'static>(provider: P) -> Self {
+        FlagStore {
+            provider: Arc::new(provider),
+            snapshot: Arc::new(RwLock::new(Snapshot {
+                flags: Arc::new(HashMap::new()),
+                loaded_at: None,
+            })),
+            ttl: Duration::from_secs(30),
+        }
+    }
+
+    pub fn from_pool(pool: Pool) -> Self {
+        Self::new(PgFlagProvider::new(pool))
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn invalidate(&self) {
+        match self.snapshot.write() {
+            Ok(mut snapshot) => snapshot.loaded_at = None,
+            Err(e) => e.into_inner().loaded_at = None,
+        }
+    }
+
+    fn flags(&self) -> Arc<HashMap<String, FeatureFlag>> {
+        let (flags, fresh) = match self.snapshot.read() {
+            Ok(snapshot) => (
+                snapshot.flags.clone(),
+                snapshot
+                    .loaded_at
+                    .map(|loaded_at| loaded_at.elapsed() < self.ttl)
+                    .unwrap_or(false),
+            ),
+            Err(e) => (e.into_inner().flags.clone(), false),
+        };
+
+        if fresh {
+            return flags;
+        }
+
+        match self.provider.load() {
+            Ok(loaded) => {
+                let loaded = Arc::new(
+                    loaded
+                        .into_iter()
+                        .map(|flag| (flag.key.to_owned(), flag))
+                        .collect::<HashMap<_, _>>(),
+                );
+
+                if let Ok(mut snapshot) = self.snapshot.write() {
+                    snapshot.flags = loaded.clone();
+                    snapshot.loaded_at = Some(Instant::now());
+                }
+
+                loaded
+            }
+            Err(e) => {
+                log::warn!("failed to load feature flags, using cached values: {}", e);
+                flags
+            }
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<FeatureFlag> {
+        self.flags().get(key).cloned()
+    }
+
+    pub fn is_enabled_for(&self, key: &str, user: Option<&User>) -> bool {
+        match self.flags().get(key) {
+            Some(flag) => flag.is_enabled_for(user),
+            None => match user {
+                Some(user) => flags::is_enabled_for(key, &user.id.to_string()),
+                None => flags::is_enabled(key),
+            },
+        }
+    }
+
+    pub fn is_enabled(&self, key: &str, context: &Context) -> bool {
+        self.is_enabled_for(key, context.user.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use timada_http::testing::ContextBuilder;
+
+    use super::{FlagProvider, FlagStore};
+    use crate::error::FlagResult;
+    use crate::flag::FeatureFlag;
+
+    struct StaticProvider {
+        loads: Arc<AtomicUsize>,
+    }
+
+    impl FlagProvider for StaticProvider {
+        fn load(&self) -> FlagResult<Vec<FeatureFlag>> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+
+            Ok(vec![FeatureFlag {
+                key: "new_checkout".to_owned(),
+                description: None,
+                enabled: true,
+                percentage: None,
+                roles: vec!["Admin".to_owned()],
+                user_ids: Vec::new(),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            }])
+        }
+    }
+
+    #[test]
+    fn cache() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let store = FlagStore::new(StaticProvider {
+            loads: loads.clone(),
+        })
+        .ttl(Duration::from_secs(60));
+
+        let admin = ContextBuilder::new().role("Admin").build();
+        let user = ContextBuilder::new().build();
+
+        assert!(store.is_enabled("new_checkout", &admin));
+        assert!(!store.is_enabled("new_checkout", &user));
+        assert!(!store.is_enabled("flags_store_unknown", &user));
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        store.invalidate();
+        assert!(store.get("new_checkout").is_some());
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+}
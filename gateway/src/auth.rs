@@ -0,0 +1,231 @@
+use actix_web::{web, HttpRequest};
+use timada_auth::{find_session, find_user, AuthError, JwtService, SessionConfig};
+use timada_database::Pool;
+use timada_http::{User, UserRole, UserState};
+use uuid::Uuid;
+
+use super::error::{GatewayError, GatewayResult};
+
+pub(crate) const IMPERSONATE_HEADER: &str = "x-impersonate";
+
+#[derive(Clone, Default)]
+pub struct Authenticator {
+    jwt: Option<JwtService>,
+    sessions: Option<(Pool, SessionConfig)>,
+}
+
+impl Authenticator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn jwt(mut self, jwt: JwtService) -> Self {
+        self.jwt = Some(jwt);
+        self
+    }
+
+    pub fn sessions(mut self, pool: Pool, config: SessionConfig) -> Self {
+        self.sessions = Some((pool, config));
+        self
+    }
+
+    // A bearer token takes precedence over the session cookie, requests with
+    // neither are forwarded anonymously.
+    pub async fn authenticate(&self, req: &HttpRequest) -> GatewayResult<Option<User>> {
+        let user = match (bearer_token(req), self.jwt.as_ref()) {
+            (Some(token), Some(jwt)) => Some(jwt.verify(&token)?.user),
+            (Some(_), None) => {
+                return Err(GatewayError::Unauthorized(
+                    "Bearer tokens are not accepted".to_owned(),
+                ))
+            }
+            (None, _) => self.session_user(req).await?,
+        };
+
+        match user {
+            Some(user) if user.state == UserState::Disabled => Err(GatewayError::Unauthorized(
+                format!("User is {:?}", user.state),
+            )),
+            user => Ok(user),
+        }
+    }
+
+    // `x-impersonate: <user id>` lets an admin act as another user loaded from the
+    // `UserStore`, the admin is returned as the impersonator.
+    pub async fn impersonate(
+        &self,
+        req: &HttpRequest,
+        user: User,
+    ) -> GatewayResult<(User, Option<User>)> {
+        let id = match req.headers().get(IMPERSONATE_HEADER) {
+            Some(id) => id,
+            None => return Ok((user, None)),
+        };
+
+        if !user.has_any_role(&[UserRole::Root, UserRole::Admin]) {
+            return Err(GatewayError::Forbidden(
+                "Impersonation requires an admin".to_owned(),
+            ));
+        }
+
+        let id = id
+            .to_str()
+            .ok()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or_else(|| GatewayError::Forbidden("Invalid user to impersonate".to_owned()))?;
+
+        let impersonated = web::block(move || find_user(id))
+            .await
+            .map_err(AuthError::from)?
+            .ok_or_else(|| GatewayError::Forbidden("Unknown user to impersonate".to_owned()))?;
+
+        let allowed = impersonated.state != UserState::Disabled
+            && match impersonated.role {
+                UserRole::Service => false,
+                UserRole::Root => user.role == UserRole::Root,
+                _ => true,
+            };
+
+        if !allowed {
+            return Err(GatewayError::Forbidden(
+                "User can't be impersonated".to_owned(),
+            ));
+        }
+
+        Ok((impersonated, Some(user)))
+    }
+
+    async fn session_user(&self, req: &HttpRequest) -> GatewayResult<Option<User>> {
+        let (pool, config) = match self.sessions.as_ref() {
+            Some(sessions) => sessions.clone(),
+            None => return Ok(None),
+        };
+
+        let token = match config.token(req) {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+
+        let user = web::block(move || {
+            let conn = pool.get().map_err(|e| AuthError::Internal(e.to_string()))?;
+            find_session(&conn, &token, &config)?.current_user()
+        })
+        .await
+        .map_err(AuthError::from)?;
+
+        Ok(Some(user))
+    }
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            let mut parts = value.splitn(2, ' ');
+            match (parts.next(), parts.next()) {
+                (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer") => {
+                    Some(token.trim().to_owned())
+                }
+                _ => None,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use futures::executor::block_on;
+    use timada_auth::{JwtKey, JwtService};
+    use timada_http::{User, UserRole, UserState};
+    use uuid::Uuid;
+
+    use super::{bearer_token, Authenticator};
+    use crate::error::GatewayError;
+
+    fn user(state: UserState) -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: None,
+            username: Some("john".to_owned()),
+            role: UserRole::User,
+            state,
+            claims: Default::default(),
+        }
+    }
+
+    fn jwt() -> JwtService {
+        JwtService::new("https://gateway.timada.co", "todos").key(JwtKey::hmac("v1", b"secret"))
+    }
+
+    #[test]
+    fn bearer() {
+        let req = TestRequest::default()
+            .header("authorization", "Bearer abc")
+            .to_http_request();
+        assert_eq!(bearer_token(&req), Some("abc".to_owned()));
+
+        let req = TestRequest::default()
+            .header("authorization", "Basic abc")
+            .to_http_request();
+        assert_eq!(bearer_token(&req), None);
+    }
+
+    #[test]
+    fn authenticate() {
+        let authenticator = Authenticator::new().jwt(jwt());
+        let john = user(UserState::Enabled);
+
+        let req = TestRequest::default()
+            .header(
+                "authorization",
+                format!("Bearer {}", jwt().issue(&john).unwrap()),
+            )
+            .to_http_request();
+        assert_eq!(block_on(authenticator.authenticate(&req)), Ok(Some(john)));
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(block_on(authenticator.authenticate(&req)), Ok(None));
+
+        let req = TestRequest::default()
+            .header("authorization", "Bearer invalid")
+            .to_http_request();
+        assert!(matches!(
+            block_on(authenticator.authenticate(&req)),
+            Err(GatewayError::Unauthorized(_))
+        ));
+
+        let req = TestRequest::default()
+            .header(
+                "authorization",
+                format!(
+                    "Bearer {}",
+                    jwt().issue(&user(UserState::Disabled)).unwrap()
+                ),
+            )
+            .to_http_request();
+        assert!(block_on(authenticator.authenticate(&req)).is_err());
+    }
+
+    #[test]
+    fn impersonate() {
+        let authenticator = Authenticator::new();
+        let john = user(UserState::Enabled);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(
+            block_on(authenticator.impersonate(&req, john.clone())),
+            Ok((john.clone(), None))
+        );
+
+        let req = TestRequest::default()
+            .header("x-impersonate", Uuid::new_v4().to_string())
+            .to_http_request();
+        assert_eq!(
+            block_on(authenticator.impersonate(&req, john)),
+            Err(GatewayError::Forbidden(
+                "Impersonation requires an admin".to_owned()
+            ))
+        );
+    }
+}
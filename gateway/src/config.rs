@@ -0,0 +1,196 @@
+use std::time::Duration;
+use timada_http::GatewayHeaders;
+use timada_util::env::{self, EnvError};
+use timada_util::secret::{secret, Secret};
+
+use super::error::{GatewayError, GatewayResult};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upstream {
+    pub prefix: String,
+    pub url: String,
+}
+
+impl Upstream {
+    pub fn new(prefix: &str, url: &str) -> Self {
+        Upstream {
+            prefix: format!("/{}", prefix.trim_matches('/')),
+            url: url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        self.prefix == "/"
+            || path == self.prefix
+            || path
+                .strip_prefix(self.prefix.as_str())
+                .map(|rest| rest.starts_with('/'))
+                .unwrap_or(false)
+    }
+
+    // The matched prefix is stripped, `/todos/graphql` is forwarded to `<url>/graphql`.
+    pub fn target(&self, path: &str, query: &str) -> String {
+        let path = match self.prefix.as_str() {
+            "/" => path,
+            prefix => path.strip_prefix(prefix).unwrap_or(path),
+        };
+        let path = if path.is_empty() { "/" } else { path };
+
+        match query {
+            "" => format!("{}{}", self.url, path),
+            query => format!("{}{}?{}", self.url, path, query),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    pub addr: String,
+    pub upstreams: Vec<Upstream>,
+    pub gateway_key: Secret<String>,
+    pub signing_key: Secret<String>,
+    pub headers: GatewayHeaders,
+    pub timeout: Duration,
+    pub require_auth: bool,
+}
+
+impl GatewayConfig {
+    // The gateway key only authenticates the gateway to the services, users are signed
+    // with the signing key which never leaves the gateway.
+    pub fn new(gateway_key: &str, signing_key: &str) -> Self {
+        GatewayConfig {
+            addr: "0.0.0.0:8080".to_owned(),
+            upstreams: Vec::new(),
+            gateway_key: Secret::from(gateway_key),
+            signing_key: Secret::from(signing_key),
+            headers: GatewayHeaders::default(),
+            timeout: Duration::from_secs(30),
+            require_auth: false,
+        }
+    }
+
+    // `GATEWAY_UPSTREAMS=todos=http://todos:8080,users=http://users:8080`
+    pub fn from_env() -> Self {
+        // Services accept a list of keys for rotation, the gateway signs with the first one.
+        let gateway_key = env::parse_list(secret("GATEWAY_SECRET_KEY").expose())
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| panic!("GATEWAY_SECRET_KEY is empty"));
+        let signing_key = env::parse_list(secret("GATEWAY_SIGNING_KEY").expose())
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| panic!("GATEWAY_SIGNING_KEY is empty"));
+
+        if signing_key == gateway_key {
+            panic!("GATEWAY_SIGNING_KEY must differ from GATEWAY_SECRET_KEY");
+        }
+
+        let mut upstreams = env::var_map("GATEWAY_UPSTREAMS")
+            .iter()
+            .map(|(prefix, url)| Upstream::new(prefix, url))
+            .collect::<Vec<_>>();
+        upstreams.sort_by(|a, b| b.prefix.len().cmp(&a.prefix.len()));
+
+        GatewayConfig {
+            addr: env::var_or("GATEWAY_ADDR", "0.0.0.0:8080"),
+            upstreams,
+            gateway_key: Secret::new(gateway_key),
+            signing_key: Secret::new(signing_key),
+            headers: GatewayHeaders::from_env(),
+            timeout: match env::try_var_duration("GATEWAY_UPSTREAM_TIMEOUT") {
+                Err(EnvError::Missing(_)) => Duration::from_secs(30),
+                res => res.unwrap_or_else(|e| panic!("{}", e)),
+            },
+            require_auth: match env::try_var_bool("GATEWAY_REQUIRE_AUTH") {
+                Err(EnvError::Missing(_)) => false,
+                res => res.unwrap_or_else(|e| panic!("{}", e)),
+            },
+        }
+    }
+
+    pub fn upstream(mut self, prefix: &str, url: &str) -> Self {
+        self.upstreams.push(Upstream::new(prefix, url));
+        self.upstreams
+            .sort_by(|a, b| b.prefix.len().cmp(&a.prefix.len()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn require_auth(mut self) -> Self {
+        self.require_auth = true;
+        self
+    }
+
+    pub fn resolve(&self, path: &str) -> GatewayResult<&Upstream> {
+        self.upstreams
+            .iter()
+            .find(|upstream| upstream.matches(path))
+            .ok_or_else(|| GatewayError::NoUpstream(path.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use timada_util::env::test_scope;
+
+    use super::{GatewayConfig, Upstream};
+
+    #[test]
+    fn resolve() {
+        let config = GatewayConfig::new("secret", "signing")
+            .upstream("todos", "http://todos:8080/")
+            .upstream("/todos/archive", "http://archive:8080")
+            .upstream("/", "http://web:8080");
+
+        assert_eq!(config.resolve("/todos/1").unwrap().url, "http://todos:8080");
+        assert_eq!(config.resolve("/todos").unwrap().url, "http://todos:8080");
+        assert_eq!(
+            config.resolve("/todos/archive/1").unwrap().url,
+            "http://archive:8080"
+        );
+        assert_eq!(config.resolve("/todosx").unwrap().url, "http://web:8080");
+
+        assert!(GatewayConfig::new("secret", "signing")
+            .resolve("/todos")
+            .is_err());
+    }
+
+    #[test]
+    fn target() {
+        let todos = Upstream::new("todos", "http://todos:8080");
+        let web = Upstream::new("/", "http://web:8080/");
+
+        assert_eq!(
+            todos.target("/todos/graphql", ""),
+            "http://todos:8080/graphql"
+        );
+        assert_eq!(todos.target("/todos", "a=1"), "http://todos:8080/?a=1");
+        assert_eq!(web.target("/about", ""), "http://web:8080/about");
+    }
+
+    #[test]
+    fn from_env() {
+        let _env = test_scope()
+            .set("GATEWAY_SECRET_KEY", "v2,v1")
+            .set("GATEWAY_SIGNING_KEY", "s2,s1")
+            .set(
+                "GATEWAY_UPSTREAMS",
+                "todos=http://todos:8080,users=http://users:8080",
+            )
+            .set("GATEWAY_UPSTREAM_TIMEOUT", "5s")
+            .remove("GATEWAY_REQUIRE_AUTH");
+
+        let config = GatewayConfig::from_env();
+
+        assert_eq!(config.gateway_key.expose(), "v2");
+        assert_eq!(config.signing_key.expose(), "s2");
+        assert_eq!(config.resolve("/users/1").unwrap().url, "http://users:8080");
+        assert_eq!(config.timeout, Duration::from_secs(5));
+        assert!(!config.require_auth);
+    }
+}
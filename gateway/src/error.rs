@@ -0,0 +1,70 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde_json::json;
+use timada_auth::AuthError;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum GatewayError {
+    #[error("{0}")]
+    Unauthorized(String),
+
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("No upstream for {0}")]
+    NoUpstream(String),
+
+    #[error("Upstream error: {0}")]
+    Upstream(String),
+
+    #[error("Upstream timed out")]
+    Timeout,
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl From<AuthError> for GatewayError {
+    fn from(e: AuthError) -> GatewayError {
+        match e {
+            AuthError::Internal(message) | AuthError::Database(message) => {
+                GatewayError::Internal(message)
+            }
+            e => GatewayError::Unauthorized(e.to_string()),
+        }
+    }
+}
+
+impl From<awc::error::SendRequestError> for GatewayError {
+    fn from(e: awc::error::SendRequestError) -> GatewayError {
+        match e {
+            awc::error::SendRequestError::Timeout => GatewayError::Timeout,
+            e => GatewayError::Upstream(e.to_string()),
+        }
+    }
+}
+
+impl ResponseError for GatewayError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            GatewayError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            GatewayError::Forbidden(_) => StatusCode::FORBIDDEN,
+            GatewayError::NoUpstream(_) => StatusCode::NOT_FOUND,
+            GatewayError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            GatewayError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            GatewayError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status_code = ResponseError::status_code(self);
+
+        if status_code.is_server_error() {
+            log::error!("{}", self);
+        }
+
+        HttpResponse::build(status_code).json(json!({ "message": self.to_string() }))
+    }
+}
+
+pub type GatewayResult<T> = Result<T, GatewayError>;
@@ -0,0 +1,12 @@
+#[macro_use]
+extern crate thiserror;
+
+mod auth;
+mod config;
+mod error;
+mod proxy;
+
+pub use crate::auth::Authenticator;
+pub use crate::config::{GatewayConfig, Upstream};
+pub use crate::error::{GatewayError, GatewayResult};
+pub use crate::proxy::{proxy, Gateway};
@@ -0,0 +1,510 @@
+use actix_codec::{BytesCodec, FramedRead};
+use actix_http::ws::handshake;
+use actix_web::dev::Server as ActixServer;
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, UPGRADE};
+use actix_web::{middleware, web, App, HttpRequest, HttpResponse, HttpServer, ResponseError};
+use awc::Client;
+use futures::{stream, StreamExt, TryStreamExt};
+use std::io;
+use timada_http::{sign_impersonator, sign_user, User, ORGANIZATION_HEADER};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use super::auth::{Authenticator, IMPERSONATE_HEADER};
+use super::config::GatewayConfig;
+use super::error::{GatewayError, GatewayResult};
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+// Preferred over `x-forwarded-for` by the services, only the latter is rewritten with the peer.
+const FORWARDED_HEADER: &str = "forwarded";
+
+const HOP_BY_HOP_HEADERS: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+];
+
+// Negotiated again between the gateway and the upstream.
+const WEBSOCKET_HANDSHAKE_HEADERS: [&str; 3] = [
+    "sec-websocket-key",
+    "sec-websocket-version",
+    "sec-websocket-accept",
+];
+
+// Picked by the upstream and returned to the client with the gateway handshake.
+const WEBSOCKET_NEGOTIATED_HEADERS: [&str; 2] =
+    ["sec-websocket-protocol", "sec-websocket-extensions"];
+
+#[derive(Clone)]
+pub struct Gateway {
+    config: GatewayConfig,
+    authenticator: Authenticator,
+}
+
+fn header_value(value: &str) -> GatewayResult<HeaderValue> {
+    HeaderValue::from_str(value).map_err(|e| GatewayError::Internal(e.to_string()))
+}
+
+fn header_name(name: &str) -> GatewayResult<HeaderName> {
+    HeaderName::from_lowercase(name.as_bytes()).map_err(|e| GatewayError::Internal(e.to_string()))
+}
+
+fn serialize(user: &User) -> GatewayResult<String> {
+    serde_json::to_string(user).map_err(|e| GatewayError::Internal(e.to_string()))
+}
+
+// The organization picked by the client is only signed once the user is a member of it.
+fn with_organization(req: &HttpRequest, user: &User) -> GatewayResult<User> {
+    let organization_id = match req.headers().get(ORGANIZATION_HEADER) {
+        Some(value) => value,
+        None => return Ok(user.clone()),
+    };
+
+    let organization_id = organization_id
+        .to_str()
+        .ok()
+        .and_then(|value| Uuid::parse_str(value).ok())
+        .filter(|organization_id| user.is_member_of(organization_id))
+        .ok_or_else(|| GatewayError::Forbidden("Not a member of the organization".to_owned()))?;
+
+    Ok(user.clone().with_organization(organization_id))
+}
+
+impl Gateway {
+    pub fn new(config: GatewayConfig, authenticator: Authenticator) -> Self {
+        Gateway {
+            config,
+            authenticator,
+        }
+    }
+
+    pub fn config(&self) -> &GatewayConfig {
+        &self.config
+    }
+
+    // Builds the headers forwarded upstream: hop-by-hop headers and any
+    // client-supplied gateway headers are dropped before signing the user.
+    pub fn upstream_headers(
+        &self,
+        req: &HttpRequest,
+        user: Option<&User>,
+        impersonator: Option<&User>,
+        request_id: &str,
+    ) -> GatewayResult<HeaderMap> {
+        let names = &self.config.headers;
+        let reserved = [
+            names.secret_key.as_str(),
+            names.user.as_str(),
+            names.user_signature.as_str(),
+            names.impersonator.as_str(),
+            names.impersonator_signature.as_str(),
+            names.service_key.as_str(),
+            ORGANIZATION_HEADER,
+            REQUEST_ID_HEADER,
+            IMPERSONATE_HEADER,
+            FORWARDED_HEADER,
+        ];
+
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in req.headers().iter() {
+            let name_str = name.as_str();
+
+            if HOP_BY_HOP_HEADERS.contains(&name_str) || reserved.contains(&name_str) {
+                continue;
+            }
+
+            headers.append(name.clone(), value.clone());
+        }
+
+        headers.insert(header_name(REQUEST_ID_HEADER)?, header_value(request_id)?);
+        headers.insert(
+            header_name(&names.secret_key)?,
+            header_value(self.config.gateway_key.expose())?,
+        );
+
+        if let Some(peer) = req.peer_addr() {
+            let forwarded_for = match req
+                .headers()
+                .get(FORWARDED_FOR_HEADER)
+                .and_then(|value| value.to_str().ok())
+            {
+                Some(forwarded_for) => format!("{}, {}", forwarded_for, peer.ip()),
+                None => peer.ip().to_string(),
+            };
+            headers.insert(
+                header_name(FORWARDED_FOR_HEADER)?,
+                header_value(&forwarded_for)?,
+            );
+        }
+
+        let user = match user {
+            Some(user) => serialize(&with_organization(req, user)?)?,
+            None => return Ok(headers),
+        };
+        let key = self.config.signing_key.expose();
+
+        headers.insert(
+            header_name(&names.user_signature)?,
+            header_value(&sign_user(&user, key))?,
+        );
+        headers.insert(header_name(&names.user)?, header_value(&user)?);
+
+        if let Some(impersonator) = impersonator {
+            let impersonator = serialize(impersonator)?;
+
+            headers.insert(
+                header_name(&names.impersonator_signature)?,
+                header_value(&sign_impersonator(&user, &impersonator, key))?,
+            );
+            headers.insert(
+                header_name(&names.impersonator)?,
+                header_value(&impersonator)?,
+            );
+        }
+
+        Ok(headers)
+    }
+
+    pub fn run(self) -> io::Result<ActixServer> {
+        let addr = self.config.addr.clone();
+
+        Ok(HttpServer::new(move || {
+            App::new()
+                .data(self.clone())
+                .data(Client::builder().timeout(self.config.timeout).finish())
+                .wrap(middleware::Logger::default())
+                .default_service(web::route().to(proxy))
+        })
+        .bind(&addr)?
+        .run())
+    }
+}
+
+fn request_id(req: &HttpRequest) -> String {
+    req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(|value| value.to_owned())
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+fn is_websocket(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false)
+}
+
+// The client handshake is answered once the upstream accepted the gateway one, the
+// frames are then piped untouched in both directions.
+async fn websocket(
+    client: &Client,
+    req: &HttpRequest,
+    target: String,
+    headers: &HeaderMap,
+    mut payload: web::Payload,
+    request_id: &str,
+) -> Result<HttpResponse, GatewayError> {
+    let mut res = match handshake(req.head()) {
+        Ok(res) => res,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let mut upstream_req = client.ws(target);
+
+    for (name, value) in headers.iter() {
+        if !WEBSOCKET_HANDSHAKE_HEADERS.contains(&name.as_str()) {
+            upstream_req = upstream_req.header(name.clone(), value.clone());
+        }
+    }
+
+    let (upstream_res, framed) = upstream_req
+        .connect()
+        .await
+        .map_err(|e| GatewayError::Upstream(e.to_string()))?;
+
+    for (name, value) in upstream_res.headers().iter() {
+        if WEBSOCKET_NEGOTIATED_HEADERS.contains(&name.as_str()) {
+            res.header(name.clone(), value.clone());
+        }
+    }
+
+    res.header(REQUEST_ID_HEADER, request_id);
+
+    let parts = framed.into_parts();
+    let (read, mut write) = tokio::io::split(parts.io);
+
+    actix_web::rt::spawn(async move {
+        while let Some(Ok(chunk)) = payload.next().await {
+            if write.write_all(&chunk).await.is_err() {
+                break;
+            }
+        }
+
+        let _ = write.shutdown().await;
+    });
+
+    // Bytes read past the upstream handshake come first.
+    let buffered = Some(parts.read_buf)
+        .filter(|buf| !buf.is_empty())
+        .map(|buf| Ok::<_, io::Error>(buf.freeze()));
+    let upstream = FramedRead::new(read, BytesCodec).map_ok(|chunk| chunk.freeze());
+
+    Ok(res.streaming(stream::iter(buffered).chain(upstream)))
+}
+
+pub async fn proxy(
+    gateway: web::Data<Gateway>,
+    client: web::Data<Client>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, GatewayError> {
+    let request_id = request_id(&req);
+    let upstream = gateway.config.resolve(req.path())?;
+
+    let (user, impersonator) = match gateway.authenticator.authenticate(&req).await? {
+        Some(user) => {
+            let (user, impersonator) = gateway.authenticator.impersonate(&req, user).await?;
+            (Some(user), impersonator)
+        }
+        None => (None, None),
+    };
+
+    if user.is_none() && gateway.config.require_auth {
+        return Err(GatewayError::Unauthorized("Anonymous".to_owned()));
+    }
+
+    let target = upstream.target(req.path(), req.query_string());
+    let headers =
+        gateway.upstream_headers(&req, user.as_ref(), impersonator.as_ref(), &request_id)?;
+
+    if is_websocket(&req) {
+        return websocket(&client, &req, target, &headers, payload, &request_id).await;
+    }
+
+    let mut upstream_req = client.request(req.method().clone(), target).no_decompress();
+
+    for (name, value) in headers.iter() {
+        upstream_req = upstream_req.header(name.clone(), value.clone());
+    }
+
+    let upstream_res = upstream_req.send_stream(payload).await?;
+
+    let mut res = HttpResponse::build(upstream_res.status());
+
+    for (name, value) in upstream_res.headers().iter() {
+        if !HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+            res.header(name.clone(), value.clone());
+        }
+    }
+
+    res.header(REQUEST_ID_HEADER, request_id.as_str());
+
+    Ok(res.streaming(upstream_res))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use serde_json::json;
+    use timada_http::{sign_impersonator, sign_user, User, UserRole, UserState};
+    use uuid::Uuid;
+
+    use super::{is_websocket, request_id, Gateway};
+    use crate::auth::Authenticator;
+    use crate::config::GatewayConfig;
+    use crate::error::GatewayError;
+
+    fn gateway() -> Gateway {
+        Gateway::new(
+            GatewayConfig::new("gateway-secret", "gateway-signing")
+                .upstream("todos", "http://todos:8080"),
+            Authenticator::new(),
+        )
+    }
+
+    #[test]
+    fn headers() {
+        let user = User {
+            id: Uuid::new_v4(),
+            email: None,
+            username: Some("john".to_owned()),
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        let req = TestRequest::default()
+            .header("x-user", "{\"role\":\"Root\"}")
+            .header("x-gateway-key", "spoofed")
+            .header("connection", "keep-alive")
+            .header("accept-language", "fr")
+            .peer_addr("10.0.0.1:4000".parse().unwrap())
+            .to_http_request();
+
+        let headers = gateway()
+            .upstream_headers(&req, Some(&user), None, "req-1")
+            .unwrap();
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let serialized = serde_json::to_string(&user).unwrap();
+
+        assert_eq!(header("x-gateway-key"), Some("gateway-secret"));
+        assert_eq!(header("x-user"), Some(serialized.as_str()));
+        assert_eq!(
+            header("x-user-signature"),
+            Some(sign_user(&serialized, "gateway-signing").as_str())
+        );
+        assert_eq!(header("x-request-id"), Some("req-1"));
+        assert_eq!(header("x-forwarded-for"), Some("10.0.0.1"));
+        assert_eq!(header("accept-language"), Some("fr"));
+        assert_eq!(header("connection"), None);
+
+        let headers = gateway()
+            .upstream_headers(&req, None, None, "req-1")
+            .unwrap();
+        assert!(headers.get("x-user").is_none());
+    }
+
+    #[test]
+    fn forwarded() {
+        let req = TestRequest::default()
+            .header("forwarded", "for=1.2.3.4")
+            .header("x-forwarded-for", "1.2.3.4")
+            .peer_addr("10.0.0.1:4000".parse().unwrap())
+            .to_http_request();
+
+        let headers = gateway()
+            .upstream_headers(&req, None, None, "req-1")
+            .unwrap();
+
+        assert!(headers.get("forwarded").is_none());
+        assert_eq!(
+            headers
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok()),
+            Some("1.2.3.4, 10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn organization() {
+        let organization_id = Uuid::new_v4();
+        let mut user = User {
+            id: Uuid::new_v4(),
+            email: None,
+            username: Some("john".to_owned()),
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        user.claims.insert(
+            "organizations".to_owned(),
+            json!([organization_id.to_string()]),
+        );
+
+        let req = TestRequest::default()
+            .header("x-org", organization_id.to_string())
+            .to_http_request();
+        let headers = gateway()
+            .upstream_headers(&req, Some(&user), None, "req-1")
+            .unwrap();
+        let signed: User =
+            serde_json::from_str(headers.get("x-user").unwrap().to_str().unwrap()).unwrap();
+
+        assert!(headers.get("x-org").is_none());
+        assert_eq!(signed.organization_id(), Some(organization_id));
+
+        let req = TestRequest::default()
+            .header("x-org", Uuid::new_v4().to_string())
+            .to_http_request();
+
+        assert_eq!(
+            gateway().upstream_headers(&req, Some(&user), None, "req-1"),
+            Err(GatewayError::Forbidden(
+                "Not a member of the organization".to_owned()
+            ))
+        );
+
+        // Anonymous requests never carry an organization.
+        let headers = gateway()
+            .upstream_headers(&req, None, None, "req-1")
+            .unwrap();
+        assert!(headers.get("x-org").is_none());
+    }
+
+    #[test]
+    fn impersonator() {
+        let user = User {
+            id: Uuid::new_v4(),
+            email: None,
+            username: Some("john".to_owned()),
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        let admin = User {
+            id: Uuid::new_v4(),
+            username: Some("admin".to_owned()),
+            role: UserRole::Admin,
+            ..user.clone()
+        };
+        let req = TestRequest::default()
+            .header("x-impersonator", "{\"role\":\"Root\"}")
+            .header("x-impersonate", user.id.to_string())
+            .to_http_request();
+
+        let headers = gateway()
+            .upstream_headers(&req, Some(&user), Some(&admin), "req-1")
+            .unwrap();
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let serialized = serde_json::to_string(&admin).unwrap();
+        let signature = sign_impersonator(
+            &serde_json::to_string(&user).unwrap(),
+            &serialized,
+            "gateway-signing",
+        );
+
+        assert_eq!(header("x-impersonator"), Some(serialized.as_str()));
+        assert_eq!(header("x-impersonator-signature"), Some(signature.as_str()));
+        assert_eq!(header("x-impersonate"), None);
+
+        let headers = gateway()
+            .upstream_headers(&req, Some(&user), None, "req-1")
+            .unwrap();
+        assert!(headers.get("x-impersonator").is_none());
+    }
+
+    #[test]
+    fn websocket() {
+        let req = TestRequest::default()
+            .header("connection", "Upgrade")
+            .header("upgrade", "WebSocket")
+            .to_http_request();
+        assert!(is_websocket(&req));
+
+        let req = TestRequest::default()
+            .header("upgrade", "h2c")
+            .to_http_request();
+        assert!(!is_websocket(&req));
+    }
+
+    #[test]
+    fn request_id_propagation() {
+        let req = TestRequest::default()
+            .header("x-request-id", "req-1")
+            .to_http_request();
+        assert_eq!(request_id(&req), "req-1");
+
+        let req = TestRequest::default().to_http_request();
+        assert!(Uuid::parse_str(&request_id(&req)).is_ok());
+    }
+}
@@ -0,0 +1,61 @@
+use timada_http::{Context, GatewayHeaders};
+use tonic::Request;
+
+use super::metadata::MetadataHeaders;
+
+pub trait RequestContextExt {
+    fn context(&self) -> Context;
+
+    fn context_with(&self, names: &GatewayHeaders) -> Context;
+}
+
+impl<T> RequestContextExt for Request<T> {
+    fn context(&self) -> Context {
+        self.context_with(&GatewayHeaders::current())
+    }
+
+    fn context_with(&self, names: &GatewayHeaders) -> Context {
+        Context {
+            client_ip: self.remote_addr().map(|addr| addr.ip()),
+            ..Context::from_headers(&MetadataHeaders(self.metadata()), names)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_http::{GatewayHeaders, User, UserRole};
+    use timada_util::env::test_scope;
+    use tonic::Request;
+
+    use super::RequestContextExt;
+    use crate::metadata::insert_user;
+
+    #[test]
+    fn context_from_metadata() {
+        let _env = test_scope()
+            .set("GATEWAY_SECRET_KEY", "timada")
+            .set("GATEWAY_SIGNING_KEY", "signing");
+        let names = GatewayHeaders::default();
+        let user = User {
+            role: UserRole::Admin,
+            ..User::service()
+        };
+
+        let mut req = Request::new(());
+        insert_user(req.metadata_mut(), &user, "timada", "signing", &names).unwrap();
+        req.metadata_mut()
+            .insert("x-request-id", "request-1".parse().unwrap());
+
+        let context = req.context_with(&names);
+
+        assert_eq!(context.user, Some(user));
+        assert_eq!(context.request_id.as_deref(), Some("request-1"));
+        assert_eq!(context.credentials_error, None);
+
+        let context = Request::new(()).context_with(&names);
+
+        assert_eq!(context.user, None);
+        assert!(context.request_id.is_some());
+    }
+}
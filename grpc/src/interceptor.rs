@@ -0,0 +1,121 @@
+use timada_http::{has_valid_gateway_key, has_valid_service_key, GatewayHeaders, User};
+use timada_util::env::{self, EnvError};
+use tonic::metadata::MetadataMap;
+use tonic::{Interceptor, Request, Status};
+
+use super::metadata::MetadataHeaders;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrpcConfig {
+    pub headers: GatewayHeaders,
+    pub reject_invalid_credentials: bool,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        GrpcConfig {
+            headers: GatewayHeaders::default(),
+            reject_invalid_credentials: false,
+        }
+    }
+}
+
+impl GrpcConfig {
+    pub fn from_env() -> Self {
+        GrpcConfig {
+            headers: GatewayHeaders::from_env(),
+            reject_invalid_credentials: match env::try_var_bool("GRPC_REJECT_INVALID_CREDENTIALS") {
+                Err(EnvError::Missing(_)) => false,
+                res => res.unwrap_or_else(|e| panic!("{}", e)),
+            },
+        }
+    }
+
+    pub fn reject_invalid_credentials(mut self) -> Self {
+        self.reject_invalid_credentials = true;
+        self
+    }
+}
+
+pub fn authorize_metadata(metadata: &MetadataMap, config: &GrpcConfig) -> Result<(), Status> {
+    let headers = MetadataHeaders(metadata);
+    let names = &config.headers;
+
+    if !has_valid_service_key(&headers, names) && !has_valid_gateway_key(&headers, names) {
+        return Err(Status::unauthenticated("Invalid gateway key"));
+    }
+
+    if config.reject_invalid_credentials && User::has_credentials_in(&headers, names) {
+        User::from_headers(&headers, names)
+            .and_then(|_| User::impersonator_from_headers(&headers, names))
+            .map_err(Status::unauthenticated)?;
+    }
+
+    Ok(())
+}
+
+// `MyServiceServer::with_interceptor(service, gateway_interceptor(GrpcConfig::from_env()))`
+pub fn gateway_interceptor(config: GrpcConfig) -> Interceptor {
+    Interceptor::new(move |req: Request<()>| {
+        authorize_metadata(req.metadata(), &config)?;
+        Ok(req)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_http::{sign_user, User};
+    use timada_util::env::test_scope;
+    use tonic::metadata::MetadataMap;
+    use tonic::Code;
+
+    use super::{authorize_metadata, GrpcConfig};
+    use crate::metadata::insert_user;
+
+    #[test]
+    fn authorize() {
+        let _env = test_scope()
+            .set("GATEWAY_SECRET_KEY", "timada")
+            .set("GATEWAY_SIGNING_KEY", "signing");
+        let config = GrpcConfig::default();
+
+        let metadata = MetadataMap::new();
+        assert_eq!(
+            authorize_metadata(&metadata, &config).map_err(|e| e.code()),
+            Err(Code::Unauthenticated)
+        );
+
+        let mut metadata = MetadataMap::new();
+        insert_user(
+            &mut metadata,
+            &User::service(),
+            "timada",
+            "signing",
+            &config.headers,
+        )
+        .unwrap();
+        assert!(authorize_metadata(&metadata, &config).is_ok());
+    }
+
+    #[test]
+    fn reject_invalid_credentials() {
+        let _env = test_scope()
+            .set("GATEWAY_SECRET_KEY", "timada")
+            .set("GATEWAY_SIGNING_KEY", "signing");
+        let config = GrpcConfig::default().reject_invalid_credentials();
+
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-gateway-key", "timada".parse().unwrap());
+        metadata.insert("x-user", "{}".parse().unwrap());
+        metadata.insert(
+            "x-user-signature",
+            sign_user("[]", "signing").parse().unwrap(),
+        );
+
+        assert_eq!(
+            authorize_metadata(&metadata, &config).map_err(|e| e.code()),
+            Err(Code::Unauthenticated)
+        );
+        assert!(authorize_metadata(&metadata, &GrpcConfig::default()).is_ok());
+    }
+}
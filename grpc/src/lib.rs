@@ -0,0 +1,9 @@
+mod context;
+mod interceptor;
+mod metadata;
+mod status;
+
+pub use crate::context::RequestContextExt;
+pub use crate::interceptor::{authorize_metadata, gateway_interceptor, GrpcConfig};
+pub use crate::metadata::{insert_user, MetadataHeaders};
+pub use crate::status::{status, IntoStatus, StatusResultExt};
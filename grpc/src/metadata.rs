@@ -0,0 +1,73 @@
+use timada_http::{sign_user, GatewayHeaders, HeaderSource, User};
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+
+pub struct MetadataHeaders<'a>(pub &'a MetadataMap);
+
+impl HeaderSource for MetadataHeaders<'_> {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.0.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+}
+
+fn insert(metadata: &mut MetadataMap, name: &str, value: &str) -> Result<(), String> {
+    let key = MetadataKey::from_bytes(name.as_bytes()).map_err(|e| e.to_string())?;
+    let value = MetadataValue::from_str(value).map_err(|e| e.to_string())?;
+
+    metadata.insert(key, value);
+
+    Ok(())
+}
+
+// Signs the user the same way the gateway does so it can be forwarded to another service.
+pub fn insert_user(
+    metadata: &mut MetadataMap,
+    user: &User,
+    gateway_key: &str,
+    signing_key: &str,
+    names: &GatewayHeaders,
+) -> Result<(), String> {
+    let user = serde_json::to_string(user).map_err(|e| e.to_string())?;
+
+    insert(metadata, &names.secret_key, gateway_key)?;
+    insert(
+        metadata,
+        &names.user_signature,
+        &sign_user(&user, signing_key),
+    )?;
+    insert(metadata, &names.user, &user)
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_http::{GatewayHeaders, HeaderSource, User};
+    use tonic::metadata::MetadataMap;
+
+    use super::{insert_user, MetadataHeaders};
+
+    #[test]
+    fn header_source() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-request-id", "request-1".parse().unwrap());
+
+        let headers = MetadataHeaders(&metadata);
+        assert_eq!(headers.header("x-request-id"), Some("request-1"));
+        assert!(!headers.contains("x-user"));
+    }
+
+    #[test]
+    fn insert_signed_user() {
+        let names = GatewayHeaders::default();
+        let mut metadata = MetadataMap::new();
+
+        insert_user(&mut metadata, &User::service(), "timada", "signing", &names).unwrap();
+
+        let headers = MetadataHeaders(&metadata);
+        assert_eq!(headers.header(&names.secret_key), Some("timada"));
+        assert!(headers.contains(&names.user));
+        assert!(headers.contains(&names.user_signature));
+    }
+}
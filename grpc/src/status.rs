@@ -0,0 +1,121 @@
+use timada_http::{Context, ContextError, Error};
+use tonic::Status;
+
+pub fn status(e: &Error, context: Option<&Context>) -> Status {
+    match e {
+        Error::BadRequest(message) => Status::invalid_argument(message.as_str()),
+        Error::NotFound => Status::not_found(e.to_string()),
+        Error::Unauthorized(message) => Status::unauthenticated(message.as_str()),
+        Error::Forbidden(message) => Status::permission_denied(message.as_str()),
+        Error::PayloadTooLarge(message) => Status::out_of_range(message.as_str()),
+        Error::UnprocessableEntity(message) => Status::failed_precondition(message.as_str()),
+        Error::TooManyRequests(message) => Status::resource_exhausted(message.as_str()),
+        Error::InternalServerError | Error::Internal(_) => match e.mask(context) {
+            Some(error_id) => {
+                Status::internal(format!("{} ({})", Error::InternalServerError, error_id))
+            }
+            None => Status::internal(e.to_string()),
+        },
+    }
+}
+
+pub trait IntoStatus {
+    fn into_status(self, context: Option<&Context>) -> Status;
+}
+
+impl IntoStatus for Error {
+    fn into_status(self, context: Option<&Context>) -> Status {
+        status(&self, context)
+    }
+}
+
+impl IntoStatus for ContextError<'_> {
+    fn into_status(self, context: Option<&Context>) -> Status {
+        Error::from(self).into_status(context)
+    }
+}
+
+// `context.ensure_user().map_status(&context)?` in service methods returning
+// `Result<_, Status>`, the context is attached to the reported server errors.
+pub trait StatusResultExt<T> {
+    fn map_status(self, context: &Context) -> Result<T, Status>;
+}
+
+impl<T, E: IntoStatus> StatusResultExt<T> for Result<T, E> {
+    fn map_status(self, context: &Context) -> Result<T, Status> {
+        self.map_err(|e| e.into_status(Some(context)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_http::{Context, Error};
+    use timada_util::env::test_scope;
+    use tonic::Code;
+
+    use super::{status, IntoStatus, StatusResultExt};
+
+    #[test]
+    fn codes() {
+        let _env = test_scope().remove("ERROR_MASKING");
+
+        assert_eq!(
+            Error::BadRequest("invalid id".to_owned())
+                .into_status(None)
+                .code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(Error::NotFound.into_status(None).code(), Code::NotFound);
+        assert_eq!(
+            Error::Unauthorized("Anonymous".to_owned())
+                .into_status(None)
+                .code(),
+            Code::Unauthenticated
+        );
+        assert_eq!(
+            Error::Forbidden("Forbidden".to_owned())
+                .into_status(None)
+                .code(),
+            Code::PermissionDenied
+        );
+        assert_eq!(
+            Error::UnprocessableEntity("title: length".to_owned())
+                .into_status(None)
+                .message(),
+            "title: length"
+        );
+        assert_eq!(
+            Error::TooManyRequests("Rate limit exceeded".to_owned())
+                .into_status(None)
+                .code(),
+            Code::ResourceExhausted
+        );
+
+        let context = Context::default();
+
+        let res: Result<(), Error> = Err(Error::Internal("db down".to_owned()));
+        let status = res.map_status(&context).unwrap_err();
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(status.message(), "db down");
+
+        assert_eq!(
+            context
+                .ensure_user()
+                .map(|_| ())
+                .map_status(&context)
+                .unwrap_err()
+                .code(),
+            Code::Unauthenticated
+        );
+    }
+
+    #[test]
+    fn masking() {
+        let _env = test_scope().set("ERROR_MASKING", "true");
+
+        let status = status(&Error::Internal("db down".to_owned()), None);
+
+        assert_eq!(status.code(), Code::Internal);
+        assert!(status.message().starts_with("Internal Server Error"));
+    }
+}
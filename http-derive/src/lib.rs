@@ -0,0 +1,298 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, Lit, Meta, NestedMeta};
+
+#[proc_macro_derive(ApiSchema)]
+pub fn derive_api_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    skip: bool,
+    default: bool,
+    flatten: bool,
+}
+
+// Mirrors the serde attributes that change the serialized shape.
+fn serde_attrs(attrs: &[Attribute]) -> Result<SerdeAttrs, Error> {
+    let mut serde = SerdeAttrs::default();
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("serde")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            _ => continue,
+        };
+
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(value)) => {
+                    let lit = match &value.lit {
+                        Lit::Str(lit) => lit.value(),
+                        _ => continue,
+                    };
+
+                    if value.path.is_ident("rename") {
+                        serde.rename = Some(lit);
+                    } else if value.path.is_ident("rename_all") {
+                        serde.rename_all = Some(lit);
+                    } else if value.path.is_ident("default") {
+                        serde.default = true;
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(path)) => {
+                    if path.is_ident("skip") || path.is_ident("skip_serializing") {
+                        serde.skip = true;
+                    } else if path.is_ident("default") {
+                        serde.default = true;
+                    } else if path.is_ident("flatten") {
+                        serde.flatten = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(serde)
+}
+
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(value)) => match value.lit {
+                Lit::Str(lit) => Some(lit.value().trim().to_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join(" "))
+    }
+}
+
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for c in name.chars() {
+        if c == '_' {
+            if !word.is_empty() {
+                words.push(word.clone());
+                word.clear();
+            }
+        } else if c.is_uppercase() && !word.is_empty() {
+            words.push(word.clone());
+            word = c.to_lowercase().collect();
+        } else {
+            word.extend(c.to_lowercase());
+        }
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn rename(name: &str, rule: Option<&str>) -> Result<String, String> {
+    let words = words(name);
+
+    let renamed = match rule {
+        None => return Ok(name.to_owned()),
+        Some("lowercase") => words.concat(),
+        Some("UPPERCASE") => words.concat().to_uppercase(),
+        Some("snake_case") => words.join("_"),
+        Some("SCREAMING_SNAKE_CASE") => words.join("_").to_uppercase(),
+        Some("kebab-case") => words.join("-"),
+        Some("SCREAMING-KEBAB-CASE") => words.join("-").to_uppercase(),
+        Some("PascalCase") => words.iter().map(|word| capitalize(word)).collect(),
+        Some("camelCase") => words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                if i == 0 {
+                    word.clone()
+                } else {
+                    capitalize(word)
+                }
+            })
+            .collect(),
+        Some(rule) => return Err(format!("unsupported rename_all rule {}", rule)),
+    };
+
+    Ok(renamed)
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let schema_name = name.to_string();
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "ApiSchema does not support generic types",
+        ));
+    }
+
+    let container = serde_attrs(&input.attrs)?;
+    let rule = container.rename_all.as_deref();
+
+    let schema = match &input.data {
+        Data::Struct(data) => {
+            let fields = match &data.fields {
+                Fields::Named(fields) => &fields.named,
+                _ => {
+                    return Err(Error::new_spanned(
+                        input,
+                        "ApiSchema requires a struct with named fields",
+                    ))
+                }
+            };
+
+            let mut properties = Vec::new();
+
+            for field in fields.iter() {
+                let serde = serde_attrs(&field.attrs)?;
+                if serde.skip {
+                    continue;
+                }
+
+                if serde.flatten {
+                    return Err(Error::new_spanned(
+                        field,
+                        "ApiSchema does not support #[serde(flatten)]",
+                    ));
+                }
+
+                let ident = field.ident.as_ref().expect("named field");
+                let ty = &field.ty;
+                let property = match serde.rename {
+                    Some(rename) => rename,
+                    None => rename(ident.to_string().trim_start_matches("r#"), rule)
+                        .map_err(|e| Error::new_spanned(input, e))?,
+                };
+                let description = match doc(&field.attrs) {
+                    Some(doc) => quote! { Some(#doc) },
+                    None => quote! { None },
+                };
+                let required = if serde.default {
+                    quote! { false }
+                } else {
+                    quote! { <#ty as ::timada_http::ApiSchema>::required() }
+                };
+
+                properties.push(quote! {
+                    ::timada_http::Property {
+                        name: #property,
+                        schema: <#ty as ::timada_http::ApiSchema>::schema(schemas),
+                        required: #required,
+                        description: #description,
+                    }
+                });
+            }
+
+            if properties.is_empty() {
+                quote! { |_| ::timada_http::Schemas::object(Vec::new()) }
+            } else {
+                quote! { |schemas| ::timada_http::Schemas::object(vec![#(#properties),*]) }
+            }
+        }
+        Data::Enum(data) => {
+            let mut values = Vec::new();
+
+            for variant in data.variants.iter() {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(Error::new_spanned(
+                        variant,
+                        "ApiSchema only supports enums with unit variants",
+                    ));
+                }
+
+                let serde = serde_attrs(&variant.attrs)?;
+                if serde.skip {
+                    continue;
+                }
+
+                values.push(match serde.rename {
+                    Some(rename) => rename,
+                    None => rename(&variant.ident.to_string(), rule)
+                        .map_err(|e| Error::new_spanned(input, e))?,
+                });
+            }
+
+            quote! { |_| ::timada_http::Schemas::string_enum(&[#(#values),*]) }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "ApiSchema requires a struct or an enum",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl ::timada_http::ApiSchema for #name {
+            fn schema(schemas: &mut ::timada_http::Schemas) -> ::timada_http::Schema {
+                schemas.register(#schema_name, #schema)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rename;
+
+    #[test]
+    fn rename_rules() {
+        assert_eq!(rename("created_at", None), Ok("created_at".to_owned()));
+        assert_eq!(
+            rename("created_at", Some("camelCase")),
+            Ok("createdAt".to_owned())
+        );
+        assert_eq!(
+            rename("InProgress", Some("snake_case")),
+            Ok("in_progress".to_owned())
+        );
+        assert_eq!(
+            rename("InProgress", Some("SCREAMING_SNAKE_CASE")),
+            Ok("IN_PROGRESS".to_owned())
+        );
+        assert_eq!(
+            rename("in_progress", Some("PascalCase")),
+            Ok("InProgress".to_owned())
+        );
+        assert_eq!(
+            rename("InProgress", Some("kebab-case")),
+            Ok("in-progress".to_owned())
+        );
+        assert!(rename("InProgress", Some("Title Case")).is_err());
+    }
+}
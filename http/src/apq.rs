@@ -0,0 +1,212 @@
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub const PERSISTED_QUERY_NOT_FOUND: &str = "PERSISTED_QUERY_NOT_FOUND";
+pub const DEFAULT_PERSISTED_QUERIES_CAPACITY: usize = 1000;
+
+pub trait PersistedQueryStorage: Send + Sync {
+    fn get(&self, hash: &str) -> Option<String>;
+    fn set(&self, hash: &str, query: &str);
+}
+
+#[derive(Default)]
+struct Entries {
+    queries: HashMap<String, (String, u64)>,
+    tick: u64,
+}
+
+// Anyone can register a query, the least recently used one is evicted past `capacity`.
+pub struct MemoryPersistedQueryStorage {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl MemoryPersistedQueryStorage {
+    pub fn new(capacity: usize) -> Self {
+        MemoryPersistedQueryStorage {
+            capacity: capacity.max(1),
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+}
+
+impl Default for MemoryPersistedQueryStorage {
+    fn default() -> Self {
+        Self::new(DEFAULT_PERSISTED_QUERIES_CAPACITY)
+    }
+}
+
+impl PersistedQueryStorage for MemoryPersistedQueryStorage {
+    fn get(&self, hash: &str) -> Option<String> {
+        let mut entries = self.entries.lock().ok()?;
+        entries.tick += 1;
+
+        let tick = entries.tick;
+        let (query, used) = entries.queries.get_mut(hash)?;
+        *used = tick;
+
+        Some(query.clone())
+    }
+
+    fn set(&self, hash: &str, query: &str) {
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        entries.tick += 1;
+
+        if !entries.queries.contains_key(hash) && entries.queries.len() >= self.capacity {
+            let oldest = entries
+                .queries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(hash, _)| hash.clone());
+
+            if let Some(oldest) = oldest {
+                entries.queries.remove(&oldest);
+            }
+        }
+
+        let tick = entries.tick;
+        entries
+            .queries
+            .insert(hash.to_owned(), (query.to_owned(), tick));
+    }
+}
+
+#[derive(Clone)]
+pub struct PersistedQueries(pub Arc<dyn PersistedQueryStorage>);
+
+impl Default for PersistedQueries {
+    fn default() -> Self {
+        PersistedQueries(Arc::new(MemoryPersistedQueryStorage::default()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PersistedQuery {
+    pub version: u32,
+    #[serde(rename = "sha256Hash")]
+    pub sha256_hash: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PersistedQueryError {
+    NotFound,
+    HashMismatch,
+    UnsupportedVersion,
+    MissingQuery,
+}
+
+impl PersistedQueryError {
+    pub fn to_response(&self) -> Value {
+        let (message, code) = match self {
+            PersistedQueryError::NotFound => ("PersistedQueryNotFound", PERSISTED_QUERY_NOT_FOUND),
+            PersistedQueryError::HashMismatch => {
+                ("provided sha does not match query", "BAD_REQUEST")
+            }
+            PersistedQueryError::UnsupportedVersion => {
+                ("Unsupported persisted query version", "BAD_REQUEST")
+            }
+            PersistedQueryError::MissingQuery => ("Missing query", "BAD_REQUEST"),
+        };
+
+        json!({ "errors": [{ "message": message, "extensions": { "code": code } }] })
+    }
+}
+
+pub fn hash_query(query: &str) -> String {
+    hex::encode(Sha256::digest(query.as_bytes()))
+}
+
+impl PersistedQueries {
+    pub fn resolve(
+        &self,
+        query: Option<String>,
+        persisted_query: Option<&PersistedQuery>,
+    ) -> Result<String, PersistedQueryError> {
+        let persisted_query = match persisted_query {
+            Some(persisted_query) => persisted_query,
+            None => return query.ok_or(PersistedQueryError::MissingQuery),
+        };
+
+        if persisted_query.version != 1 {
+            return Err(PersistedQueryError::UnsupportedVersion);
+        }
+
+        match query {
+            Some(query) => {
+                if hash_query(&query) != persisted_query.sha256_hash {
+                    return Err(PersistedQueryError::HashMismatch);
+                }
+
+                self.0.set(&persisted_query.sha256_hash, &query);
+
+                Ok(query)
+            }
+            None => self
+                .0
+                .get(&persisted_query.sha256_hash)
+                .ok_or(PersistedQueryError::NotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        hash_query, MemoryPersistedQueryStorage, PersistedQueries, PersistedQuery,
+        PersistedQueryError, PersistedQueryStorage,
+    };
+
+    #[test]
+    fn resolve_flow() {
+        let queries = PersistedQueries::default();
+        let query = "{ todos { id } }".to_owned();
+        let persisted_query = PersistedQuery {
+            version: 1,
+            sha256_hash: hash_query(&query),
+        };
+
+        assert_eq!(
+            queries.resolve(None, Some(&persisted_query)),
+            Err(PersistedQueryError::NotFound)
+        );
+        assert_eq!(
+            queries.resolve(Some(query.clone()), Some(&persisted_query)),
+            Ok(query.clone())
+        );
+        assert_eq!(queries.resolve(None, Some(&persisted_query)), Ok(query));
+    }
+
+    #[test]
+    fn memory_capacity() {
+        let storage = MemoryPersistedQueryStorage::new(2);
+
+        storage.set("a", "{ a }");
+        storage.set("b", "{ b }");
+        assert_eq!(storage.get("a"), Some("{ a }".to_owned()));
+
+        storage.set("c", "{ c }");
+
+        assert_eq!(storage.get("a"), Some("{ a }".to_owned()));
+        assert_eq!(storage.get("b"), None);
+        assert_eq!(storage.get("c"), Some("{ c }".to_owned()));
+    }
+
+    #[test]
+    fn resolve_hash_mismatch() {
+        let queries = PersistedQueries::default();
+        let persisted_query = PersistedQuery {
+            version: 1,
+            sha256_hash: hash_query("{ todos { id } }"),
+        };
+
+        assert_eq!(
+            queries.resolve(Some("{ users { id } }".to_owned()), Some(&persisted_query)),
+            Err(PersistedQueryError::HashMismatch)
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use uuid::Uuid;
+
+use super::context::{Context, ContextError, ContextResult};
+use super::user::{User, UserRole, UserState};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Requirement {
+    Role(UserRole),
+    Owner(Uuid),
+    Organization(Uuid),
+}
+
+impl Requirement {
+    fn is_satisfied_by(&self, context: &Context, user: &User) -> bool {
+        match self {
+            Requirement::Role(role) => &user.role == role,
+            Requirement::Owner(id) => &user.id == id,
+            Requirement::Organization(id) => context.organization_id.as_ref() == Some(id),
+        }
+    }
+}
+
+pub struct Authorize<'a> {
+    context: &'a Context,
+    groups: Vec<Vec<Requirement>>,
+}
+
+impl<'a> Authorize<'a> {
+    pub fn new(context: &'a Context) -> Self {
+        Authorize {
+            context,
+            groups: Vec::new(),
+        }
+    }
+
+    pub fn and(mut self, requirement: Requirement) -> Self {
+        match self.groups.last_mut() {
+            Some(group) => group.push(requirement),
+            None => self.groups.push(vec![requirement]),
+        };
+
+        self
+    }
+
+    pub fn or(mut self, requirement: Requirement) -> Self {
+        self.groups.push(vec![requirement]);
+        self
+    }
+
+    pub fn role<R: Into<UserRole>>(self, role: R) -> Self {
+        self.and(Requirement::Role(role.into()))
+    }
+
+    pub fn owner(self, id: Uuid) -> Self {
+        self.and(Requirement::Owner(id))
+    }
+
+    pub fn organization(self, id: Uuid) -> Self {
+        self.and(Requirement::Organization(id))
+    }
+
+    pub fn check(self) -> ContextResult<'a, &'a User> {
+        let context = self.context;
+        let user = context.ensure_user()?;
+
+        if user.state != UserState::Enabled {
+            return Err(context.flag_impersonated(ContextError::UserState(&user.state)));
+        }
+
+        if self.groups.is_empty() {
+            return Ok(user);
+        }
+
+        let mut failed = Vec::new();
+
+        for group in self.groups {
+            let unsatisfied = group
+                .into_iter()
+                .filter(|requirement| !requirement.is_satisfied_by(context, user))
+                .collect::<Vec<_>>();
+
+            if unsatisfied.is_empty() {
+                return Ok(user);
+            }
+
+            failed.extend(unsatisfied);
+        }
+
+        Err(context.flag_impersonated(ContextError::Unauthorized(failed)))
+    }
+}
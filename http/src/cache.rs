@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheScope {
+    Public,
+    Private,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheHint {
+    pub max_age: u64,
+    pub scope: CacheScope,
+}
+
+impl CacheHint {
+    pub fn public(max_age: u64) -> Self {
+        CacheHint {
+            max_age,
+            scope: CacheScope::Public,
+        }
+    }
+
+    pub fn private(max_age: u64) -> Self {
+        CacheHint {
+            max_age,
+            scope: CacheScope::Private,
+        }
+    }
+
+    fn merge(self, other: CacheHint) -> Self {
+        let scope = if self.scope == CacheScope::Private || other.scope == CacheScope::Private {
+            CacheScope::Private
+        } else {
+            CacheScope::Public
+        };
+
+        CacheHint {
+            max_age: self.max_age.min(other.max_age),
+            scope,
+        }
+    }
+}
+
+// Hints are collected per root field of the operation. A root field without a hint
+// means the response can't be cached, nested fields only lower the hint of their root.
+#[derive(Debug, Clone, Default)]
+pub struct CacheControl(Arc<Mutex<HashMap<String, CacheHint>>>);
+
+impl CacheControl {
+    pub fn hint(&self, field: &str, hint: CacheHint) {
+        if let Ok(mut hints) = self.0.lock() {
+            let hint = match hints.get(field) {
+                Some(current) => current.merge(hint),
+                None => hint,
+            };
+
+            hints.insert(field.to_owned(), hint);
+        }
+    }
+
+    pub fn policy(&self, fields: &[&str]) -> Option<CacheHint> {
+        let hints = self.0.lock().ok()?;
+
+        fields
+            .iter()
+            .filter(|field| **field != "__typename")
+            .try_fold(None, |policy: Option<CacheHint>, field| {
+                let hint = *hints.get(*field)?;
+
+                Some(Some(match policy {
+                    Some(policy) => policy.merge(hint),
+                    None => hint,
+                }))
+            })?
+    }
+
+    // Responses computed for a signed user are never shared.
+    pub fn header_value(&self, fields: &[&str], is_private: bool) -> Option<String> {
+        let hint = self.policy(fields)?;
+
+        if hint.max_age == 0 {
+            return None;
+        }
+
+        let scope = match hint.scope {
+            CacheScope::Public if !is_private => "public",
+            _ => "private",
+        };
+
+        Some(format!("{}, max-age={}", scope, hint.max_age))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheControl, CacheHint};
+
+    #[test]
+    fn header_value() {
+        let cache_control = CacheControl::default();
+        assert_eq!(cache_control.header_value(&["todos"], false), None);
+
+        cache_control.hint("todos", CacheHint::public(300));
+        assert_eq!(
+            cache_control.header_value(&["todos"], false),
+            Some("public, max-age=300".to_owned())
+        );
+
+        cache_control.hint("todos", CacheHint::public(60));
+        assert_eq!(
+            cache_control.header_value(&["todos", "__typename"], false),
+            Some("public, max-age=60".to_owned())
+        );
+
+        cache_control.hint("todos", CacheHint::private(120));
+        assert_eq!(
+            cache_control.header_value(&["todos"], false),
+            Some("private, max-age=60".to_owned())
+        );
+
+        cache_control.hint("todos", CacheHint::public(0));
+        assert_eq!(cache_control.header_value(&["todos"], false), None);
+    }
+
+    #[test]
+    fn unhinted_fields() {
+        let cache_control = CacheControl::default();
+        cache_control.hint("todos", CacheHint::public(300));
+
+        assert_eq!(cache_control.header_value(&["todos", "me"], false), None);
+        assert_eq!(cache_control.header_value(&[], false), None);
+        assert_eq!(
+            cache_control.header_value(&["todos"], true),
+            Some("private, max-age=300".to_owned())
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use actix_web::dev::Payload;
+use actix_web::http::header::FORWARDED;
+use actix_web::{Error, FromRequest, HttpRequest};
+use futures::future::{ok, Ready};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+use super::context::ContextConfig;
+
+const X_FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+#[derive(Debug, PartialEq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+fn is_trusted(ip: &IpAddr, trusted_proxies: &[IpNet]) -> bool {
+    trusted_proxies.iter().any(|net| net.contains(ip))
+}
+
+fn parse_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| {
+            value
+                .trim_start_matches('[')
+                .split(']')
+                .next()
+                .and_then(|ip| ip.parse::<IpAddr>().ok())
+        })
+}
+
+fn forwarded_for(value: &str) -> Vec<Option<IpAddr>> {
+    value
+        .split(',')
+        .map(|element| {
+            element
+                .split(';')
+                .filter_map(|pair| {
+                    let mut pair = pair.splitn(2, '=');
+                    let name = pair.next()?.trim();
+
+                    if name.eq_ignore_ascii_case("for") {
+                        pair.next()
+                    } else {
+                        None
+                    }
+                })
+                .next()
+                .and_then(parse_ip)
+        })
+        .collect()
+}
+
+fn x_forwarded_for(value: &str) -> Vec<Option<IpAddr>> {
+    value.split(',').map(parse_ip).collect()
+}
+
+pub fn client_ip(req: &HttpRequest, trusted_proxies: &[IpNet]) -> Option<IpAddr> {
+    let peer = req.peer_addr().map(|addr| addr.ip())?;
+
+    if !is_trusted(&peer, trusted_proxies) {
+        return Some(peer);
+    }
+
+    let header = |name| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+
+    let chain = match header(FORWARDED.as_str()) {
+        Some(value) => forwarded_for(value),
+        None => match header(X_FORWARDED_FOR_HEADER) {
+            Some(value) => x_forwarded_for(value),
+            None => return Some(peer),
+        },
+    };
+
+    let mut client = peer;
+
+    for ip in chain.into_iter().rev() {
+        match ip {
+            Some(ip) => {
+                client = ip;
+
+                if !is_trusted(&ip, trusted_proxies) {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+
+    Some(client)
+}
+
+impl FromRequest for ClientIp {
+    type Future = Ready<Result<ClientIp, Error>>;
+    type Error = Error;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _pl: &mut Payload) -> Self::Future {
+        let trusted_proxies = req
+            .app_data::<ContextConfig>()
+            .map(|config| config.trusted_proxies.as_slice())
+            .unwrap_or(&[]);
+
+        ok(ClientIp(client_ip(req, trusted_proxies)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use ipnet::IpNet;
+    use std::net::IpAddr;
+
+    use super::client_ip;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn trusted() -> Vec<IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap()]
+    }
+
+    #[test]
+    fn untrusted_peer() {
+        let req = TestRequest::default()
+            .peer_addr("203.0.113.7:4000".parse().unwrap())
+            .header("x-forwarded-for", "198.51.100.1")
+            .to_http_request();
+
+        assert_eq!(client_ip(&req, &trusted()), Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn trusted_peer_x_forwarded_for() {
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.2:4000".parse().unwrap())
+            .header("x-forwarded-for", "198.51.100.1, 203.0.113.9, 10.0.0.5")
+            .to_http_request();
+
+        assert_eq!(client_ip(&req, &trusted()), Some(ip("203.0.113.9")));
+    }
+
+    #[test]
+    fn trusted_peer_forwarded() {
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.2:4000".parse().unwrap())
+            .header(
+                "forwarded",
+                "for=198.51.100.1;proto=https, for=\"[2001:db8::1]:4711\"",
+            )
+            .to_http_request();
+
+        assert_eq!(client_ip(&req, &trusted()), Some(ip("2001:db8::1")));
+    }
+}
@@ -0,0 +1,89 @@
+use async_graphql::{ObjectType, SchemaBuilder, SubscriptionType};
+
+const GRAPHQL_MAX_DEPTH_VAR: &str = "GRAPHQL_MAX_DEPTH";
+const GRAPHQL_MAX_COMPLEXITY_VAR: &str = "GRAPHQL_MAX_COMPLEXITY";
+const DEFAULT_PAGE_SIZE: usize = 40;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ComplexityLimits {
+    pub depth: Option<usize>,
+    pub complexity: Option<usize>,
+}
+
+fn var_usize(key: &str) -> Option<usize> {
+    std::env::var(key).ok().map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|e| panic!("couldn't parse {}: {}", key, e))
+    })
+}
+
+impl ComplexityLimits {
+    pub fn from_env() -> Self {
+        ComplexityLimits {
+            depth: var_usize(GRAPHQL_MAX_DEPTH_VAR),
+            complexity: var_usize(GRAPHQL_MAX_COMPLEXITY_VAR),
+        }
+    }
+
+    pub fn apply<Query, Mutation, Subscription>(
+        &self,
+        mut builder: SchemaBuilder<Query, Mutation, Subscription>,
+    ) -> SchemaBuilder<Query, Mutation, Subscription>
+    where
+        Query: ObjectType + Send + Sync + 'static,
+        Mutation: ObjectType + Send + Sync + 'static,
+        Subscription: SubscriptionType + Send + Sync + 'static,
+    {
+        if let Some(depth) = self.depth {
+            builder = builder.limit_depth(depth);
+        }
+
+        if let Some(complexity) = self.complexity {
+            builder = builder.limit_complexity(complexity);
+        }
+
+        builder
+    }
+}
+
+pub fn connection_complexity(
+    child_complexity: usize,
+    first: Option<usize>,
+    last: Option<usize>,
+) -> usize {
+    let page_size = first.or(last).unwrap_or(DEFAULT_PAGE_SIZE);
+
+    1 + child_complexity.saturating_mul(page_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_util::env::test_scope;
+
+    use super::{
+        connection_complexity, ComplexityLimits, GRAPHQL_MAX_COMPLEXITY_VAR, GRAPHQL_MAX_DEPTH_VAR,
+    };
+
+    #[test]
+    fn from_env() {
+        let _env = test_scope()
+            .set(GRAPHQL_MAX_DEPTH_VAR, "10")
+            .set(GRAPHQL_MAX_COMPLEXITY_VAR, "500");
+
+        assert_eq!(
+            ComplexityLimits::from_env(),
+            ComplexityLimits {
+                depth: Some(10),
+                complexity: Some(500),
+            }
+        );
+    }
+
+    #[test]
+    fn connection() {
+        assert_eq!(connection_complexity(3, Some(10), None), 31);
+        assert_eq!(connection_complexity(3, None, Some(5)), 16);
+        assert_eq!(connection_complexity(2, None, None), 81);
+    }
+}
@@ -1,31 +1,193 @@
 use actix_web::dev::Payload;
+use actix_web::error::ErrorUnauthorized;
+use actix_web::http::header::ACCEPT_LANGUAGE;
 use actix_web::{Error, FromRequest, HttpRequest, Result};
-use futures::future::{ok, Ready};
-use std::convert::TryFrom;
+use chrono_tz::Tz;
+use futures::future::{ready, Ready};
+use ipnet::IpNet;
+use std::net::IpAddr;
+use timada_util::flags;
+use uuid::Uuid;
 
+use super::client_ip::client_ip;
+use super::user::{GatewayHeaders, HeaderSource};
+
+pub use super::authorize::{Authorize, Requirement};
 pub use super::user::{User, UserRole, UserState};
 
 #[derive(Debug, PartialEq)]
 pub enum ContextError<'a> {
     Anonymous,
+    InvalidCredentials(&'a str),
     UserState(&'a UserState),
     Forbidden,
+    MissingOrganization,
+    Organization(&'a Uuid),
+    ImpersonationForbidden,
+    Impersonated(Box<ContextError<'a>>),
+    Unauthorized(Vec<Requirement>),
 }
 
 pub type ContextResult<'a, T> = Result<T, ContextError<'a>>;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct Context {
     pub user: Option<User>,
+    pub impersonator: Option<User>,
+    pub organization_id: Option<Uuid>,
+    pub credentials_error: Option<String>,
+    pub request_id: Option<String>,
+    pub trace_id: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<Tz>,
+    pub client_ip: Option<IpAddr>,
+}
+
+#[derive(Clone, Default)]
+pub struct ContextConfig {
+    reject_invalid_credentials: bool,
+    pub(crate) trusted_proxies: Vec<IpNet>,
+}
+
+impl ContextConfig {
+    pub fn reject_invalid_credentials(mut self) -> Self {
+        self.reject_invalid_credentials = true;
+        self
+    }
+
+    pub fn trusted_proxy(mut self, net: IpNet) -> Self {
+        self.trusted_proxies.push(net);
+        self
+    }
+}
+
+// Read by the gateway only, services get the organization from the signed user.
+pub const ORGANIZATION_HEADER: &str = "x-org";
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const TRACE_ID_HEADER: &str = "x-trace-id";
+const TRACEPARENT_HEADER: &str = "traceparent";
+const TIMEZONE_HEADER: &str = "x-timezone";
+const DEFAULT_LOCALE: &str = "en";
+
+fn header<'a, H: HeaderSource + ?Sized>(headers: &'a H, name: &str) -> Option<&'a str> {
+    headers.header(name).filter(|value| !value.is_empty())
+}
+
+fn parse_accept_language(value: &str) -> Option<String> {
+    value
+        .split(',')
+        .filter_map(|language| {
+            let mut parts = language.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .filter_map(|quality| quality.parse::<f32>().ok())
+                .next()
+                .unwrap_or(1.0);
+
+            if tag.is_empty() || tag == "*" || quality <= 0.0 {
+                None
+            } else {
+                Some((tag, quality))
+            }
+        })
+        .fold(
+            None,
+            |best: Option<(&str, f32)>, (tag, quality)| match best {
+                Some((_, best_quality)) if best_quality >= quality => best,
+                _ => Some((tag, quality)),
+            },
+        )
+        .map(|(tag, _)| tag.to_owned())
 }
 
 impl Context {
+    pub fn locale(&self) -> &str {
+        self.locale.as_deref().unwrap_or(DEFAULT_LOCALE)
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone.unwrap_or(Tz::UTC)
+    }
+
+    pub fn is_impersonated(&self) -> bool {
+        self.impersonator.is_some()
+    }
+
+    pub fn is_feature_enabled(&self, name: &str) -> bool {
+        match self.user.as_ref() {
+            Some(user) => flags::is_enabled_for(name, &user.id.to_string()),
+            None => flags::is_enabled(name),
+        }
+    }
+
+    pub fn try_user(&self) -> ContextResult<Option<&User>> {
+        match (self.user.as_ref(), self.credentials_error.as_ref()) {
+            (Some(user), _) => Ok(Some(user)),
+            (None, Some(e)) => Err(ContextError::InvalidCredentials(e)),
+            (None, None) => Ok(None),
+        }
+    }
+
+    pub fn ensure_user(&self) -> ContextResult<&User> {
+        self.try_user()?.ok_or(ContextError::Anonymous)
+    }
+
     pub fn ensure_is_authorized(&self, roles: Option<Vec<UserRole>>) -> ContextResult<&User> {
-        let user = self.user.as_ref().ok_or(ContextError::Anonymous)?;
+        self.check_authorized(roles)
+            .map_err(|e| self.flag_impersonated(e))
+    }
+
+    pub fn authorize(&self) -> Authorize {
+        Authorize::new(self)
+    }
 
-        let authorized = roles
-            .map(|roles| roles.iter().any(|role| &user.role == role))
-            .unwrap_or(true);
+    pub fn ensure_any(&self, requirements: Vec<Requirement>) -> ContextResult<&User> {
+        requirements
+            .into_iter()
+            .fold(self.authorize(), |authorize, requirement| {
+                authorize.or(requirement)
+            })
+            .check()
+    }
+
+    pub fn ensure_all(&self, requirements: Vec<Requirement>) -> ContextResult<&User> {
+        requirements
+            .into_iter()
+            .fold(self.authorize(), |authorize, requirement| {
+                authorize.and(requirement)
+            })
+            .check()
+    }
+
+    pub(crate) fn flag_impersonated<'a>(&self, e: ContextError<'a>) -> ContextError<'a> {
+        if self.is_impersonated() {
+            ContextError::Impersonated(Box::new(e))
+        } else {
+            e
+        }
+    }
+
+    pub fn ensure_impersonator_is(&self, roles: Vec<UserRole>) -> ContextResult<Option<&User>> {
+        let impersonator = match self.impersonator.as_ref() {
+            Some(impersonator) => impersonator,
+            None => return Ok(None),
+        };
+
+        if !impersonator.has_any_role(&roles) {
+            return Err(ContextError::ImpersonationForbidden);
+        }
+
+        match impersonator.state {
+            UserState::Enabled => Ok(Some(impersonator)),
+            _ => Err(ContextError::ImpersonationForbidden),
+        }
+    }
+
+    fn check_authorized(&self, roles: Option<Vec<UserRole>>) -> ContextResult<&User> {
+        let user = self.ensure_user()?;
+
+        let authorized = roles.map(|roles| user.has_any_role(&roles)).unwrap_or(true);
 
         if !authorized {
             return Err(ContextError::Forbidden);
@@ -36,24 +198,159 @@ impl Context {
             _ => Err(ContextError::UserState(&user.state)),
         }
     }
+
+    pub fn ensure_organization(&self) -> ContextResult<&Uuid> {
+        self.organization_id
+            .as_ref()
+            .ok_or(ContextError::MissingOrganization)
+    }
+
+    pub fn ensure_in_organization(&self, id: &Uuid) -> ContextResult<&Uuid> {
+        let organization_id = self.ensure_organization()?;
+
+        if organization_id != id {
+            return Err(ContextError::Organization(organization_id));
+        }
+
+        Ok(organization_id)
+    }
+}
+
+impl Context {
+    pub fn from_http_request(req: &HttpRequest) -> Result<Context> {
+        let config = req.app_data::<ContextConfig>();
+        let reject_invalid_credentials = config
+            .map(|config| config.reject_invalid_credentials)
+            .unwrap_or(false);
+        let client_ip = client_ip(
+            req,
+            config
+                .map(|config| config.trusted_proxies.as_slice())
+                .unwrap_or(&[]),
+        );
+
+        let names = GatewayHeaders::from_request(req);
+        let (user, impersonator, credentials_error) = credentials(req.headers(), &names);
+
+        if let (true, Some(e)) = (reject_invalid_credentials, credentials_error.as_ref()) {
+            return Err(ErrorUnauthorized(e.clone()));
+        }
+
+        #[cfg(feature = "dev-auth")]
+        let user = match (user, credentials_error.as_ref()) {
+            (None, None) => super::dev_auth::user_from(req),
+            (user, _) => user,
+        };
+
+        Ok(Self {
+            client_ip,
+            ..Self::from_parts(req.headers(), user, impersonator, credentials_error)
+        })
+    }
+
+    // Transport independent, `client_ip` is left to the caller.
+    pub fn from_headers<H: HeaderSource + ?Sized>(headers: &H, names: &GatewayHeaders) -> Self {
+        let (user, impersonator, credentials_error) = credentials(headers, names);
+
+        Self::from_parts(headers, user, impersonator, credentials_error)
+    }
+
+    fn from_parts<H: HeaderSource + ?Sized>(
+        headers: &H,
+        user: Option<User>,
+        impersonator: Option<User>,
+        credentials_error: Option<String>,
+    ) -> Self {
+        let organization_id = user.as_ref().and_then(User::organization_id);
+
+        let request_id = header(headers, REQUEST_ID_HEADER)
+            .map(|request_id| request_id.to_owned())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let trace_id = header(headers, TRACE_ID_HEADER)
+            .or_else(|| {
+                header(headers, TRACEPARENT_HEADER)
+                    .and_then(|traceparent| traceparent.split('-').nth(1))
+            })
+            .map(|trace_id| trace_id.to_owned());
+
+        let locale = header(headers, ACCEPT_LANGUAGE.as_str())
+            .and_then(parse_accept_language)
+            .or_else(|| {
+                user.as_ref()
+                    .and_then(|user| user.claim::<String>("locale"))
+            });
+        let timezone = header(headers, TIMEZONE_HEADER)
+            .map(|timezone| timezone.to_owned())
+            .or_else(|| {
+                user.as_ref()
+                    .and_then(|user| user.claim::<String>("timezone"))
+            })
+            .and_then(|timezone| timezone.parse::<Tz>().ok());
+
+        let span = tracing::Span::current();
+        timada_telemetry::record_request_id(&span, &request_id);
+        if let Some(user) = user.as_ref() {
+            timada_telemetry::record_user(&span, &user.id);
+        }
+
+        if let (Some(user), Some(impersonator)) = (user.as_ref(), impersonator.as_ref()) {
+            log::info!(
+                "[{}] user {} impersonated by {}",
+                request_id,
+                user.id,
+                impersonator.id
+            );
+        }
+
+        Self {
+            user,
+            impersonator,
+            organization_id,
+            credentials_error,
+            request_id: Some(request_id),
+            trace_id,
+            locale,
+            timezone,
+            client_ip: None,
+        }
+    }
+}
+
+fn credentials<H: HeaderSource + ?Sized>(
+    headers: &H,
+    names: &GatewayHeaders,
+) -> (Option<User>, Option<User>, Option<String>) {
+    if !User::has_credentials_in(headers, names) {
+        return (None, None, None);
+    }
+
+    match User::from_headers(headers, names).and_then(|user| {
+        User::impersonator_from_headers(headers, names).map(|impersonator| (user, impersonator))
+    }) {
+        Ok((user, impersonator)) => (Some(user), impersonator, None),
+        Err(e) => (None, None, Some(e)),
+    }
 }
 
 impl FromRequest for Context {
     type Future = Ready<Result<Context>>;
     type Error = Error;
-    type Config = ();
+    type Config = ContextConfig;
 
     fn from_request(req: &HttpRequest, _pl: &mut Payload) -> Self::Future {
-        let user = User::try_from(req).ok();
-
-        ok(Self { user })
+        ready(Self::from_http_request(req))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Context, ContextError};
+    use chrono_tz::Tz;
+    use ipnet::IpNet;
+    use std::net::IpAddr;
+
+    use super::{parse_accept_language, Context, ContextError, Requirement};
     use super::{User, UserRole, UserState};
+    use uuid::Uuid;
 
     #[test]
     fn ensure_is_authorized_anonymous() {
@@ -74,7 +371,9 @@ mod tests {
                 username: None,
                 role: UserRole::User,
                 state: UserState::Disabled,
+                claims: Default::default(),
             }),
+            ..Default::default()
         };
 
         assert_eq!(
@@ -93,7 +392,9 @@ mod tests {
                 username: None,
                 role: UserRole::User,
                 state: UserState::Disabled,
+                claims: Default::default(),
             }),
+            ..Default::default()
         };
 
         assert_eq!(
@@ -113,7 +414,9 @@ mod tests {
                 username: None,
                 role: UserRole::User,
                 state: UserState::ReadOnly,
+                claims: Default::default(),
             }),
+            ..Default::default()
         };
 
         assert_eq!(
@@ -133,7 +436,9 @@ mod tests {
                 username: None,
                 role: UserRole::User,
                 state: UserState::ReadOnly,
+                claims: Default::default(),
             }),
+            ..Default::default()
         };
 
         assert_eq!(
@@ -153,7 +458,9 @@ mod tests {
                 username: None,
                 role: UserRole::User,
                 state: UserState::Enabled,
+                claims: Default::default(),
             }),
+            ..Default::default()
         };
 
         assert_eq!(
@@ -171,7 +478,9 @@ mod tests {
                 username: None,
                 role: UserRole::User,
                 state: UserState::Enabled,
+                claims: Default::default(),
             }),
+            ..Default::default()
         };
 
         assert_eq!(
@@ -189,7 +498,9 @@ mod tests {
                 username: None,
                 role: UserRole::Admin,
                 state: UserState::Enabled,
+                claims: Default::default(),
             }),
+            ..Default::default()
         };
 
         assert_eq!(
@@ -197,4 +508,280 @@ mod tests {
             Ok(context.user.as_ref().unwrap())
         );
     }
+
+    #[test]
+    fn ensure_organization_missing() {
+        let context = Context::default();
+
+        assert_eq!(
+            context.ensure_organization(),
+            Err(ContextError::MissingOrganization)
+        );
+        assert_eq!(
+            context.ensure_in_organization(&Uuid::new_v4()),
+            Err(ContextError::MissingOrganization)
+        );
+    }
+
+    #[test]
+    fn ensure_in_organization_other() {
+        let context = Context {
+            organization_id: Some(Uuid::new_v4()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context.ensure_in_organization(&Uuid::new_v4()),
+            Err(ContextError::Organization(
+                context.organization_id.as_ref().unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn ensure_in_organization_success() {
+        let organization_id = Uuid::new_v4();
+        let context = Context {
+            organization_id: Some(organization_id),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context.ensure_in_organization(&organization_id),
+            Ok(&organization_id)
+        );
+    }
+
+    #[test]
+    fn ensure_is_authorized_impersonated_forbidden() {
+        let context = Context {
+            user: Some(User {
+                id: Default::default(),
+                email: None,
+                username: None,
+                role: UserRole::User,
+                state: UserState::Enabled,
+                claims: Default::default(),
+            }),
+            impersonator: Some(User {
+                id: Default::default(),
+                email: None,
+                username: None,
+                role: UserRole::Admin,
+                state: UserState::Enabled,
+                claims: Default::default(),
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context.ensure_is_authorized(Some(vec![UserRole::Admin])),
+            Err(ContextError::Impersonated(Box::new(
+                ContextError::Forbidden
+            )))
+        );
+    }
+
+    #[test]
+    fn ensure_impersonator_is_none() {
+        let context = Context::default();
+
+        assert_eq!(
+            context.ensure_impersonator_is(vec![UserRole::Root, UserRole::Admin]),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn ensure_impersonator_is_forbidden() {
+        let context = Context {
+            impersonator: Some(User {
+                id: Default::default(),
+                email: None,
+                username: None,
+                role: UserRole::Staff,
+                state: UserState::Enabled,
+                claims: Default::default(),
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context.ensure_impersonator_is(vec![UserRole::Root, UserRole::Admin]),
+            Err(ContextError::ImpersonationForbidden)
+        );
+    }
+
+    #[test]
+    fn ensure_impersonator_is_success() {
+        let context = Context {
+            impersonator: Some(User {
+                id: Default::default(),
+                email: None,
+                username: None,
+                role: UserRole::Admin,
+                state: UserState::Enabled,
+                claims: Default::default(),
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context.ensure_impersonator_is(vec![UserRole::Root, UserRole::Admin]),
+            Ok(context.impersonator.as_ref())
+        );
+    }
+
+    #[test]
+    fn authorize_role_or_owner() {
+        let owner_id = Uuid::new_v4();
+        let context = Context {
+            user: Some(User {
+                id: owner_id,
+                email: None,
+                username: None,
+                role: UserRole::Staff,
+                state: UserState::Enabled,
+                claims: Default::default(),
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context
+                .authorize()
+                .role(UserRole::Admin)
+                .or(Requirement::Owner(owner_id))
+                .check(),
+            Ok(context.user.as_ref().unwrap())
+        );
+    }
+
+    #[test]
+    fn authorize_lists_failed_requirements() {
+        let resource_owner_id = Uuid::new_v4();
+        let context = Context {
+            user: Some(User {
+                id: Uuid::new_v4(),
+                email: None,
+                username: None,
+                role: UserRole::Staff,
+                state: UserState::Enabled,
+                claims: Default::default(),
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context
+                .authorize()
+                .role(UserRole::Admin)
+                .or(Requirement::Role(UserRole::Staff))
+                .owner(resource_owner_id)
+                .check(),
+            Err(ContextError::Unauthorized(vec![
+                Requirement::Role(UserRole::Admin),
+                Requirement::Owner(resource_owner_id)
+            ]))
+        );
+    }
+
+    #[test]
+    fn authorize_anonymous() {
+        let context = Context::default();
+
+        assert_eq!(
+            context.authorize().role(UserRole::Admin).check(),
+            Err(ContextError::Anonymous)
+        );
+    }
+
+    #[test]
+    fn ensure_any_and_all() {
+        let organization_id = Uuid::new_v4();
+        let context = Context {
+            user: Some(User {
+                id: Uuid::new_v4(),
+                email: None,
+                username: None,
+                role: UserRole::Staff,
+                state: UserState::Enabled,
+                claims: Default::default(),
+            }),
+            organization_id: Some(organization_id),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context.ensure_any(vec![
+                Requirement::Role(UserRole::Admin),
+                Requirement::Organization(organization_id)
+            ]),
+            Ok(context.user.as_ref().unwrap())
+        );
+        assert_eq!(
+            context.ensure_all(vec![
+                Requirement::Role(UserRole::Admin),
+                Requirement::Organization(organization_id)
+            ]),
+            Err(ContextError::Unauthorized(vec![Requirement::Role(
+                UserRole::Admin
+            )]))
+        );
+    }
+
+    #[test]
+    fn try_user_anonymous() {
+        let context = Context::default();
+
+        assert_eq!(context.try_user(), Ok(None));
+        assert_eq!(context.ensure_user(), Err(ContextError::Anonymous));
+    }
+
+    #[test]
+    fn try_user_invalid_credentials() {
+        let context = Context {
+            credentials_error: Some("Invalid gateway key".to_owned()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context.try_user(),
+            Err(ContextError::InvalidCredentials("Invalid gateway key"))
+        );
+        assert_eq!(
+            context.ensure_is_authorized(None),
+            Err(ContextError::InvalidCredentials("Invalid gateway key"))
+        );
+    }
+
+    #[test]
+    fn locale_and_timezone_fallbacks() {
+        let context = Context::default();
+
+        assert_eq!(context.locale(), "en");
+        assert_eq!(context.timezone(), Tz::UTC);
+
+        let context = Context {
+            locale: Some("fr-FR".to_owned()),
+            timezone: Some(Tz::Europe__Paris),
+            ..Default::default()
+        };
+
+        assert_eq!(context.locale(), "fr-FR");
+        assert_eq!(context.timezone(), Tz::Europe__Paris);
+    }
+
+    #[test]
+    fn accept_language() {
+        assert_eq!(
+            parse_accept_language("fr-CH, fr;q=0.9, en;q=0.8, *;q=0.5"),
+            Some("fr-CH".to_owned())
+        );
+        assert_eq!(
+            parse_accept_language("en;q=0.5, de;q=0.7"),
+            Some("de".to_owned())
+        );
+        assert_eq!(parse_accept_language("*"), None);
+    }
 }
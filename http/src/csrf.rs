@@ -0,0 +1,215 @@
+use actix_service::{Service, Transform};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::{Error, HttpMessage, HttpResponse};
+use futures::future::{ok, Either, Ready};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use uuid::Uuid;
+
+use super::user::{has_valid_service_key, GatewayHeaders};
+
+const SEC_FETCH_SITE_HEADER: &str = "sec-fetch-site";
+
+#[derive(Clone)]
+pub struct CsrfConfig {
+    cookie_name: String,
+    header_name: String,
+    trust_sec_fetch_site: bool,
+    gateway_headers: GatewayHeaders,
+}
+
+impl Default for CsrfConfig {
+    fn default() -> Self {
+        CsrfConfig {
+            cookie_name: "csrf-token".to_owned(),
+            header_name: "x-csrf-token".to_owned(),
+            trust_sec_fetch_site: false,
+            gateway_headers: GatewayHeaders::from_env(),
+        }
+    }
+}
+
+impl CsrfConfig {
+    pub fn cookie_name(mut self, cookie_name: &str) -> Self {
+        self.cookie_name = cookie_name.to_owned();
+        self
+    }
+
+    pub fn header_name(mut self, header_name: &str) -> Self {
+        self.header_name = header_name.to_lowercase();
+        self
+    }
+
+    pub fn trust_sec_fetch_site(mut self) -> Self {
+        self.trust_sec_fetch_site = true;
+        self
+    }
+
+    fn is_allowed(&self, req: &ServiceRequest) -> bool {
+        if matches!(
+            *req.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+        ) {
+            return true;
+        }
+
+        if has_valid_service_key(req.headers(), &self.gateway_headers) {
+            return true;
+        }
+
+        let cookie = match req.cookie(&self.cookie_name) {
+            Some(cookie) => cookie,
+            None => return !req.headers().contains_key("cookie"),
+        };
+
+        if self.trust_sec_fetch_site {
+            let same_site = req
+                .headers()
+                .get(SEC_FETCH_SITE_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value == "same-origin" || value == "none")
+                .unwrap_or(false);
+
+            if same_site {
+                return true;
+            }
+        }
+
+        req.headers()
+            .get(self.header_name.as_str())
+            .and_then(|value| value.to_str().ok())
+            .map(|token| constant_time_eq(token.as_bytes(), cookie.value().as_bytes()))
+            .unwrap_or(false)
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+pub fn generate_csrf_token() -> String {
+    Uuid::new_v4().to_simple().to_string()
+}
+
+#[derive(Default)]
+pub struct Csrf {
+    config: Rc<CsrfConfig>,
+}
+
+impl Csrf {
+    pub fn new(config: CsrfConfig) -> Self {
+        Csrf {
+            config: Rc::new(config),
+        }
+    }
+}
+
+impl<S, B> Transform<S> for Csrf
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CsrfMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CsrfMiddleware {
+            service,
+            config: self.config.clone(),
+        })
+    }
+}
+
+pub struct CsrfMiddleware<S> {
+    service: S,
+    config: Rc<CsrfConfig>,
+}
+
+impl<S, B> Service for CsrfMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        if self.config.is_allowed(&req) {
+            Either::Left(self.service.call(req))
+        } else {
+            Either::Right(ok(
+                req.into_response(HttpResponse::Forbidden().finish().into_body())
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::Method;
+    use actix_web::test::TestRequest;
+
+    use super::CsrfConfig;
+
+    #[test]
+    fn safe_method() {
+        let req = TestRequest::default()
+            .cookie(actix_web::cookie::Cookie::new("csrf-token", "token"))
+            .to_srv_request();
+
+        assert!(CsrfConfig::default().is_allowed(&req));
+    }
+
+    #[test]
+    fn header_authenticated() {
+        let req = TestRequest::default().method(Method::POST).to_srv_request();
+
+        assert!(CsrfConfig::default().is_allowed(&req));
+    }
+
+    #[test]
+    fn double_submit() {
+        let req = TestRequest::default()
+            .method(Method::POST)
+            .cookie(actix_web::cookie::Cookie::new("csrf-token", "token"))
+            .header("x-csrf-token", "token")
+            .to_srv_request();
+
+        assert!(CsrfConfig::default().is_allowed(&req));
+
+        let req = TestRequest::default()
+            .method(Method::POST)
+            .cookie(actix_web::cookie::Cookie::new("csrf-token", "token"))
+            .header("x-csrf-token", "forged")
+            .to_srv_request();
+
+        assert!(!CsrfConfig::default().is_allowed(&req));
+    }
+
+    #[test]
+    fn sec_fetch_site() {
+        let req = TestRequest::default()
+            .method(Method::POST)
+            .cookie(actix_web::cookie::Cookie::new("csrf-token", "token"))
+            .header("sec-fetch-site", "same-origin")
+            .to_srv_request();
+
+        assert!(!CsrfConfig::default().is_allowed(&req));
+        assert!(CsrfConfig::default()
+            .trust_sec_fetch_site()
+            .is_allowed(&req));
+    }
+}
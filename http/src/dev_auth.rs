@@ -0,0 +1,111 @@
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::HttpRequest;
+use uuid::Uuid;
+
+use super::user::{User, UserRole, UserState};
+
+#[cfg(not(debug_assertions))]
+compile_error!("the `dev-auth` feature must not be enabled in release builds");
+
+const DEV_AUTH_VAR: &str = "DEV_AUTH";
+const DEV_AUTH_TOKEN_VAR: &str = "DEV_AUTH_TOKEN";
+const DEV_AUTH_USERNAME_VAR: &str = "DEV_AUTH_USERNAME";
+const DEV_AUTH_PASSWORD_VAR: &str = "DEV_AUTH_PASSWORD";
+const DEV_AUTH_USER_VAR: &str = "DEV_AUTH_USER";
+
+fn is_enabled() -> bool {
+    std::env::var(DEV_AUTH_VAR)
+        .map(|value| value == "true" || value == "1")
+        .unwrap_or(false)
+}
+
+fn dev_user() -> User {
+    std::env::var(DEV_AUTH_USER_VAR)
+        .ok()
+        .and_then(|user| serde_json::from_str(&user).ok())
+        .unwrap_or_else(|| User {
+            id: Uuid::nil(),
+            email: None,
+            username: Some("dev".to_owned()),
+            role: UserRole::Admin,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        })
+}
+
+fn is_valid_token(token: &str) -> bool {
+    std::env::var(DEV_AUTH_TOKEN_VAR)
+        .map(|dev_token| !dev_token.is_empty() && dev_token == token)
+        .unwrap_or(false)
+}
+
+fn is_valid_basic(credentials: &str) -> bool {
+    let credentials = match base64::decode(credentials)
+        .ok()
+        .and_then(|credentials| String::from_utf8(credentials).ok())
+    {
+        Some(credentials) => credentials,
+        None => return false,
+    };
+
+    let username = std::env::var(DEV_AUTH_USERNAME_VAR).unwrap_or_default();
+    let password = std::env::var(DEV_AUTH_PASSWORD_VAR).unwrap_or_default();
+
+    !username.is_empty() && credentials == format!("{}:{}", username, password)
+}
+
+pub fn user_from(req: &HttpRequest) -> Option<User> {
+    if !is_enabled() {
+        return None;
+    }
+
+    let authorization = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok())?;
+
+    let authorized = if let Some(token) = authorization.strip_prefix("Bearer ") {
+        is_valid_token(token.trim())
+    } else if let Some(credentials) = authorization.strip_prefix("Basic ") {
+        is_valid_basic(credentials.trim())
+    } else {
+        false
+    };
+
+    if authorized {
+        log::warn!("request authenticated with dev auth");
+        Some(dev_user())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use timada_util::env::test_scope;
+
+    use super::{user_from, DEV_AUTH_TOKEN_VAR, DEV_AUTH_VAR};
+
+    #[test]
+    fn dev_token() {
+        let _env = test_scope()
+            .set(DEV_AUTH_VAR, "true")
+            .set(DEV_AUTH_TOKEN_VAR, "dev-token");
+
+        let req = TestRequest::default()
+            .header("authorization", "Bearer dev-token")
+            .to_http_request();
+
+        assert_eq!(
+            user_from(&req).and_then(|user| user.username),
+            Some("dev".to_owned())
+        );
+
+        let req = TestRequest::default()
+            .header("authorization", "Bearer wrong")
+            .to_http_request();
+
+        assert_eq!(user_from(&req), None);
+    }
+}
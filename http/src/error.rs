@@ -1,8 +1,15 @@
 use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
 use async_graphql::{ErrorExtensions, FieldError};
-use serde_json::json;
+use diesel::result::Error as DieselError;
+use serde_json::{json, Value};
+use uuid::Uuid;
 use validator::{ValidationErrors, ValidationErrorsKind};
 
+use super::context::{Context, ContextError};
+use super::reporter::{self, ErrorReport};
+use super::user::UserStateError;
+
 #[derive(Debug, PartialEq, Error)]
 pub enum Error {
     #[error("{0}")]
@@ -17,11 +24,28 @@ pub enum Error {
     #[error("{0}")]
     Forbidden(String),
 
+    #[error("{0}")]
+    PayloadTooLarge(String),
+
     #[error("{0}")]
     UnprocessableEntity(String),
 
+    #[error("{0}")]
+    TooManyRequests(String),
+
     #[error("Internal Server Error")]
     InternalServerError,
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+const ERROR_MASKING_VAR: &str = "ERROR_MASKING";
+
+fn is_masking_enabled() -> bool {
+    std::env::var(ERROR_MASKING_VAR)
+        .map(|value| value == "true" || value == "1")
+        .unwrap_or(false)
 }
 
 impl From<ValidationErrors> for Error {
@@ -42,22 +66,253 @@ impl From<ValidationErrors> for Error {
     }
 }
 
-impl ErrorExtensions for Error {
-    fn extend(&self) -> FieldError {
-        let status_code = match self {
+impl From<DieselError> for Error {
+    fn from(e: DieselError) -> Error {
+        match e {
+            DieselError::NotFound => Error::NotFound,
+            e => Error::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<UserStateError> for Error {
+    fn from(e: UserStateError) -> Error {
+        match e {
+            UserStateError::InvalidTransition(from, to) => Error::UnprocessableEntity(format!(
+                "Invalid user state transition from {:?} to {:?}",
+                from, to
+            )),
+        }
+    }
+}
+
+impl From<ContextError<'_>> for Error {
+    fn from(e: ContextError<'_>) -> Error {
+        match e {
+            ContextError::Anonymous => Error::Unauthorized("Anonymous".to_owned()),
+            ContextError::InvalidCredentials(e) => Error::Unauthorized(e.to_owned()),
+            ContextError::UserState(state) => Error::Forbidden(format!("User is {:?}", state)),
+            ContextError::Forbidden => Error::Forbidden("Forbidden".to_owned()),
+            ContextError::MissingOrganization => {
+                Error::BadRequest("Missing organization".to_owned())
+            }
+            ContextError::Organization(_) => Error::Forbidden("Organization mismatch".to_owned()),
+            ContextError::ImpersonationForbidden => {
+                Error::Forbidden("Impersonation forbidden".to_owned())
+            }
+            ContextError::Unauthorized(requirements) => {
+                Error::Forbidden(format!("Unmet requirements: {:?}", requirements))
+            }
+            ContextError::Impersonated(e) => match Error::from(*e) {
+                Error::Unauthorized(message) => {
+                    Error::Unauthorized(format!("{} (impersonated)", message))
+                }
+                Error::Forbidden(message) => {
+                    Error::Forbidden(format!("{} (impersonated)", message))
+                }
+                e => e,
+            },
+        }
+    }
+}
+
+impl Error {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
             Error::BadRequest(_) => StatusCode::BAD_REQUEST,
             Error::NotFound => StatusCode::NOT_FOUND,
             Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
             Error::Forbidden(_) => StatusCode::FORBIDDEN,
+            Error::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
             Error::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
-            Error::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
-        };
+            Error::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Error::InternalServerError | Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn report(&self, error_id: Option<&str>, context: Option<&Context>) {
+        let user = context.and_then(|context| context.user.as_ref());
+
+        reporter::report(&ErrorReport {
+            error: self,
+            error_id,
+            request_id: context.and_then(|context| context.request_id.as_deref()),
+            user_id: user.map(|user| user.id),
+            user_role: user.map(|user| user.role.as_str()),
+            impersonator_id: context
+                .and_then(|context| context.impersonator.as_ref())
+                .map(|impersonator| impersonator.id),
+        });
+    }
+
+    fn to_field_error(&self, mut extensions: Value, context: Option<&Context>) -> FieldError {
+        let status_code = self.status_code();
+        extensions["statusCode"] = json!(status_code.as_u16());
+
+        if !status_code.is_server_error() {
+            return FieldError(format!("{}", self), Some(extensions));
+        }
+
+        match self.mask(context) {
+            Some(error_id) => {
+                extensions["errorId"] = json!(error_id);
+                FieldError(Error::InternalServerError.to_string(), Some(extensions))
+            }
+            None => FieldError(format!("{}", self), Some(extensions)),
+        }
+    }
+
+    // Reports a server error, the id is returned when the message has to be hidden
+    // from the client behind `ERROR_MASKING`. Shared with the other transports.
+    pub fn mask(&self, context: Option<&Context>) -> Option<String> {
+        if !is_masking_enabled() {
+            self.report(None, context);
+            return None;
+        }
+
+        let error_id = Uuid::new_v4().to_string();
+        log::error!("[{}] {}", error_id, self);
+        self.report(Some(&error_id), context);
+
+        Some(error_id)
+    }
 
-        FieldError(
-            format!("{}", self),
-            Some(json!({ "statusCode": status_code.as_u16() })),
-        )
+    fn to_json(&self) -> Value {
+        if !self.status_code().is_server_error() {
+            return json!({ "message": self.to_string() });
+        }
+
+        match self.mask(None) {
+            Some(error_id) => json!({
+                "message": Error::InternalServerError.to_string(),
+                "errorId": error_id
+            }),
+            None => json!({ "message": self.to_string() }),
+        }
+    }
+
+    pub fn extend_with_context(&self, context: &Context) -> FieldError {
+        let mut extensions = json!({});
+
+        if let Some(request_id) = context.request_id.as_ref() {
+            extensions["requestId"] = json!(request_id);
+        }
+
+        if let Some(trace_id) = context.trace_id.as_ref() {
+            extensions["traceId"] = json!(trace_id);
+        }
+
+        self.to_field_error(extensions, Some(context))
+    }
+}
+
+impl ErrorExtensions for Error {
+    fn extend(&self) -> FieldError {
+        self.to_field_error(json!({}), None)
+    }
+}
+
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        Error::status_code(self)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(Error::status_code(self)).json(self.to_json())
     }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::FieldError;
+    use serde_json::json;
+
+    use timada_util::env::test_scope;
+
+    use super::{Error, ERROR_MASKING_VAR};
+    use crate::context::{Context, ContextError};
+    use crate::user::{UserRole, UserState};
+    use crate::Requirement;
+
+    #[test]
+    fn from_context_error() {
+        assert_eq!(
+            Error::from(ContextError::Anonymous),
+            Error::Unauthorized("Anonymous".to_owned())
+        );
+        assert_eq!(
+            Error::from(ContextError::UserState(&UserState::Disabled)),
+            Error::Forbidden("User is Disabled".to_owned())
+        );
+        assert_eq!(
+            Error::from(ContextError::Unauthorized(vec![Requirement::Role(
+                UserRole::Admin
+            )])),
+            Error::Forbidden("Unmet requirements: [Role(Admin)]".to_owned())
+        );
+    }
+
+    #[test]
+    fn from_context_error_impersonated() {
+        assert_eq!(
+            Error::from(ContextError::Impersonated(Box::new(
+                ContextError::Forbidden
+            ))),
+            Error::Forbidden("Forbidden (impersonated)".to_owned())
+        );
+    }
+
+    #[test]
+    fn extend_with_context() {
+        let context = Context {
+            request_id: Some("request-1".to_owned()),
+            trace_id: Some("trace-1".to_owned()),
+            ..Default::default()
+        };
+
+        let FieldError(message, extensions) = Error::NotFound.extend_with_context(&context);
+
+        assert_eq!(message, "Not Found");
+        assert_eq!(
+            extensions,
+            Some(json!({
+                "statusCode": 404,
+                "requestId": "request-1",
+                "traceId": "trace-1"
+            }))
+        );
+    }
+
+    #[test]
+    fn extend_masked() {
+        let _env = test_scope().set(ERROR_MASKING_VAR, "true");
+
+        let FieldError(message, extensions) = Error::Internal("connection refused".to_owned())
+            .extend_with_context(&Context::default());
+        let extensions = extensions.unwrap();
+
+        assert_eq!(message, "Internal Server Error");
+        assert_eq!(extensions["statusCode"], json!(500));
+        assert!(extensions["errorId"].is_string());
+    }
+
+    #[test]
+    fn response_masked() {
+        let error = Error::Internal("connection refused".to_owned());
+
+        assert_eq!(error.to_json(), json!({ "message": "connection refused" }));
+        assert_eq!(Error::NotFound.to_json(), json!({ "message": "Not Found" }));
+
+        let _env = test_scope().set(ERROR_MASKING_VAR, "true");
+        let body = error.to_json();
+
+        assert_eq!(body["message"], json!("Internal Server Error"));
+        assert!(body["errorId"].is_string());
+        assert_eq!(
+            Error::Forbidden("Forbidden".to_owned()).to_json(),
+            json!({ "message": "Forbidden" })
+        );
+    }
+}
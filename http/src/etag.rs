@@ -0,0 +1,143 @@
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+
+use super::error::Error;
+
+pub fn weak_etag(body: &[u8]) -> String {
+    format!("W/\"{}\"", hex::encode(&Sha256::digest(body)[..16]))
+}
+
+pub fn weak_etag_from_version<V: Display>(version: V) -> String {
+    format!("W/\"{}\"", version)
+}
+
+fn opaque_tag(etag: &str) -> &str {
+    etag.trim().trim_start_matches("W/")
+}
+
+pub fn is_not_modified(req: &HttpRequest, etag: &str) -> bool {
+    let value = match req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(value) => value,
+        None => return false,
+    };
+
+    value
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque_tag(tag) == opaque_tag(etag))
+}
+
+fn not_modified(etag: &str) -> HttpResponse {
+    HttpResponse::NotModified()
+        .header(header::ETAG, etag)
+        .finish()
+}
+
+pub fn conditional<F>(req: &HttpRequest, etag: &str, f: F) -> HttpResponse
+where
+    F: FnOnce() -> HttpResponse,
+{
+    if is_not_modified(req, etag) {
+        return not_modified(etag);
+    }
+
+    let mut res = f();
+
+    if let Ok(value) = header::HeaderValue::from_str(etag) {
+        res.headers_mut().insert(header::ETAG, value);
+    }
+
+    res
+}
+
+pub fn json_with_etag<T: Serialize>(
+    req: &HttpRequest,
+    value: &T,
+) -> actix_web::Result<HttpResponse> {
+    let body = serde_json::to_vec(value)
+        .map_err(|e| ErrorInternalServerError(Error::Internal(e.to_string())))?;
+    let etag = weak_etag(&body);
+
+    if is_not_modified(req, &etag) {
+        return Ok(not_modified(&etag));
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .header(header::ETAG, etag)
+        .body(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::TestRequest;
+    use actix_web::HttpResponse;
+    use serde_json::json;
+
+    use super::{conditional, is_not_modified, json_with_etag, weak_etag, weak_etag_from_version};
+
+    #[test]
+    fn etag_format() {
+        assert_eq!(weak_etag(b"{}"), weak_etag(b"{}"));
+        assert_ne!(weak_etag(b"{}"), weak_etag(b"[]"));
+        assert!(weak_etag(b"{}").starts_with("W/\""));
+        assert_eq!(weak_etag_from_version(3), "W/\"3\"");
+    }
+
+    #[test]
+    fn if_none_match() {
+        let req = TestRequest::default()
+            .header(header::IF_NONE_MATCH, "\"1\", W/\"3\"")
+            .to_http_request();
+
+        assert!(is_not_modified(&req, "W/\"3\""));
+        assert!(is_not_modified(&req, "W/\"1\""));
+        assert!(!is_not_modified(&req, "W/\"2\""));
+
+        let req = TestRequest::default()
+            .header(header::IF_NONE_MATCH, "*")
+            .to_http_request();
+        assert!(is_not_modified(&req, "W/\"2\""));
+
+        assert!(!is_not_modified(
+            &TestRequest::default().to_http_request(),
+            "W/\"2\""
+        ));
+    }
+
+    #[test]
+    fn conditional_response() {
+        let etag = weak_etag_from_version(3);
+
+        let req = TestRequest::default().to_http_request();
+        let res = conditional(&req, &etag, || HttpResponse::Ok().finish());
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), "W/\"3\"");
+
+        let req = TestRequest::default()
+            .header(header::IF_NONE_MATCH, "W/\"3\"")
+            .to_http_request();
+        let res = conditional(&req, &etag, || unreachable!());
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn json_response() {
+        let value = json!({ "id": 1 });
+        let etag = weak_etag(&serde_json::to_vec(&value).unwrap());
+
+        let req = TestRequest::default()
+            .header(header::IF_NONE_MATCH, etag.as_str())
+            .to_http_request();
+        let res = json_with_etag(&req, &value).unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    }
+}
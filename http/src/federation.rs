@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+
+pub struct FederationService {
+    sdl: String,
+}
+
+impl FederationService {
+    pub fn new(sdl: &str) -> Self {
+        FederationService {
+            sdl: sdl.to_owned(),
+        }
+    }
+}
+
+#[async_graphql::Object(name = "_Service")]
+impl FederationService {
+    #[field]
+    async fn sdl(&self) -> &str {
+        self.sdl.as_str()
+    }
+}
+
+// Federation types the gateway adds on its own, they are left out of `_service { sdl }`.
+fn is_federation_type(line: &str) -> bool {
+    let line = line.trim_start();
+
+    line.starts_with("_entities(")
+        || line.starts_with("_service:")
+        || line.starts_with("union _Entity ")
+        || line == "scalar _Any"
+}
+
+// Adds `@key(fields: "id")` to the given entity types so the gateway can
+// compose them; entities are always keyed by their global `ID`.
+pub fn federation_sdl(sdl: &str, entities: &[&str]) -> String {
+    let entities = entities.iter().copied().collect::<HashSet<_>>();
+    let mut lines = Vec::new();
+    let mut in_service = false;
+
+    for line in sdl.lines() {
+        if in_service || line.starts_with("type _Service ") {
+            in_service = line.trim() != "}";
+            continue;
+        }
+
+        if is_federation_type(line) {
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let is_entity = match (words.next(), words.next(), words.next()) {
+            (Some("type"), Some(name), _) | (Some("extend"), Some("type"), Some(name)) => {
+                entities.contains(name.trim_end_matches('{'))
+            }
+            _ => false,
+        };
+
+        if !is_entity || line.contains("@key(") {
+            lines.push(line.to_owned());
+            continue;
+        }
+
+        lines.push(match line.rfind('{') {
+            Some(index) => format!(
+                "{} @key(fields: \"id\") {}",
+                line[..index].trim_end(),
+                &line[index..]
+            ),
+            None => format!("{} @key(fields: \"id\")", line.trim_end()),
+        });
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::federation_sdl;
+
+    #[test]
+    fn sdl() {
+        let sdl = "type Todo {\n  id: ID!\n}\n\ntype Project implements Node {\n  id: ID!\n}\n\ntype Query {\n  todos: [Todo!]!\n}";
+
+        assert_eq!(
+            federation_sdl(sdl, &["Todo", "Project"]),
+            "type Todo @key(fields: \"id\") {\n  id: ID!\n}\n\ntype Project implements Node @key(fields: \"id\") {\n  id: ID!\n}\n\ntype Query {\n  todos: [Todo!]!\n}"
+        );
+        assert_eq!(
+            federation_sdl("extend type User @key(fields: \"id\") {", &["User"]),
+            "extend type User @key(fields: \"id\") {"
+        );
+        assert_eq!(
+            federation_sdl("extend type User {", &["User"]),
+            "extend type User @key(fields: \"id\") {"
+        );
+    }
+
+    #[test]
+    fn federation_types() {
+        let sdl = "scalar _Any\n\ntype Query {\n  todos: [Todo!]!\n  _entities(representations: [_Any!]!): [_Entity]!\n  _service: _Service!\n}\n\ntype _Service {\n  sdl: String!\n}\n\nunion _Entity = Todo\n\ntype Todo {\n  id: ID!\n}";
+
+        assert_eq!(
+            federation_sdl(sdl, &["Todo"]),
+            "\ntype Query {\n  todos: [Todo!]!\n}\n\n\ntype Todo @key(fields: \"id\") {\n  id: ID!\n}"
+        );
+    }
+}
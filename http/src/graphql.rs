@@ -0,0 +1,75 @@
+use async_graphql::{Context as GraphQLContext, ErrorExtensions, FieldError, QueryPathSegment};
+use std::any::Any;
+use timada_database::{checkout, Pool, PooledConnection};
+
+use super::cache::{CacheControl, CacheHint};
+use super::context::Context;
+use super::error::{Error, Result};
+use super::request_data::RequestData;
+use super::user::{User, UserRole};
+
+pub trait GraphQLContextExt {
+    fn context(&self) -> Result<&Context>;
+    fn user(&self) -> Result<&User>;
+    fn require_role<R: Into<UserRole>>(&self, role: R) -> Result<&User>;
+    fn conn(&self) -> Result<PooledConnection>;
+    fn request_data<T: Any + Send + Sync>(&self) -> Result<&T>;
+    fn extend_error(&self, e: &Error) -> FieldError;
+    fn cache_hint(&self, hint: CacheHint);
+}
+
+impl GraphQLContextExt for GraphQLContext<'_> {
+    fn context(&self) -> Result<&Context> {
+        self.data::<Context>()
+            .map_err(|_| Error::InternalServerError)
+    }
+
+    fn user(&self) -> Result<&User> {
+        Ok(self.context()?.ensure_is_authorized(None)?)
+    }
+
+    fn require_role<R: Into<UserRole>>(&self, role: R) -> Result<&User> {
+        Ok(self
+            .context()?
+            .ensure_is_authorized(Some(vec![role.into()]))?)
+    }
+
+    fn conn(&self) -> Result<PooledConnection> {
+        let pool = self
+            .data::<Pool>()
+            .map_err(|_| Error::InternalServerError)?;
+
+        checkout(pool).map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    fn request_data<T: Any + Send + Sync>(&self) -> Result<&T> {
+        self.data::<RequestData>()
+            .ok()
+            .and_then(|data| data.get::<T>())
+            .ok_or(Error::InternalServerError)
+    }
+
+    fn extend_error(&self, e: &Error) -> FieldError {
+        match self.context() {
+            Ok(context) => e.extend_with_context(context),
+            _ => e.extend(),
+        }
+    }
+
+    fn cache_hint(&self, hint: CacheHint) {
+        let mut node = match self.path_node.as_ref() {
+            Some(node) => node,
+            None => return,
+        };
+
+        while let Some(parent) = node.parent {
+            node = parent;
+        }
+
+        if let (QueryPathSegment::Name(field), Ok(cache_control)) =
+            (&node.segment, self.data::<CacheControl>())
+        {
+            cache_control.hint(field, hint);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use async_graphql::guard::Guard;
+use async_graphql::{Context as GraphQLContext, ErrorExtensions, FieldResult};
+
+use super::context::{Context, ContextError};
+use super::error::Error;
+use super::graphql::GraphQLContextExt;
+use super::user::{UserRole, UserState};
+
+fn context<'a>(ctx: &'a GraphQLContext<'_>) -> FieldResult<&'a Context> {
+    ctx.context().map_err(|e| e.extend())
+}
+
+pub struct AuthenticatedGuard;
+
+#[async_trait::async_trait]
+impl Guard for AuthenticatedGuard {
+    async fn check(&self, ctx: &GraphQLContext<'_>) -> FieldResult<()> {
+        let context = context(ctx)?;
+
+        context
+            .ensure_is_authorized(None)
+            .map(|_| ())
+            .map_err(|e| Error::from(e).extend_with_context(context))
+    }
+}
+
+pub struct RoleGuard {
+    pub role: UserRole,
+}
+
+impl RoleGuard {
+    pub fn new<R: Into<UserRole>>(role: R) -> Self {
+        RoleGuard { role: role.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &GraphQLContext<'_>) -> FieldResult<()> {
+        let context = context(ctx)?;
+
+        context
+            .ensure_is_authorized(Some(vec![self.role.clone()]))
+            .map(|_| ())
+            .map_err(|e| Error::from(e).extend_with_context(context))
+    }
+}
+
+pub struct StateGuard {
+    pub state: UserState,
+}
+
+impl StateGuard {
+    pub fn new(state: UserState) -> Self {
+        StateGuard { state }
+    }
+}
+
+#[async_trait::async_trait]
+impl Guard for StateGuard {
+    async fn check(&self, ctx: &GraphQLContext<'_>) -> FieldResult<()> {
+        let context = context(ctx)?;
+        let user = context
+            .ensure_user()
+            .map_err(|e| Error::from(e).extend_with_context(context))?;
+
+        if user.state != self.state {
+            let e = context.flag_impersonated(ContextError::UserState(&user.state));
+            return Err(Error::from(e).extend_with_context(context));
+        }
+
+        Ok(())
+    }
+}
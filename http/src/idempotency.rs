@@ -0,0 +1,352 @@
+use actix_service::{Service, Transform};
+use actix_web::body::{Body, ResponseBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, HeaderValue, Method, StatusCode};
+use actix_web::{Error, HttpResponse};
+use bytes::BytesMut;
+use futures::future::{ok, LocalBoxFuture, Ready};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use super::user::User;
+
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdempotentResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IdempotencyState {
+    New,
+    InProgress,
+    Completed(IdempotentResponse),
+}
+
+pub trait IdempotencyStorage: Send + Sync {
+    fn start(&self, key: &str, ttl: Duration) -> IdempotencyState;
+    fn complete(&self, key: &str, response: IdempotentResponse, ttl: Duration);
+    fn abort(&self, key: &str);
+}
+
+enum Entry {
+    InProgress,
+    Completed(IdempotentResponse),
+}
+
+#[derive(Default)]
+pub struct MemoryIdempotencyStorage {
+    entries: Mutex<HashMap<String, (Instant, Entry)>>,
+}
+
+impl IdempotencyStorage for MemoryIdempotencyStorage {
+    fn start(&self, key: &str, ttl: Duration) -> IdempotencyState {
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            _ => return IdempotencyState::InProgress,
+        };
+
+        let now = Instant::now();
+        entries.retain(|_, (expires_at, _)| *expires_at > now);
+
+        match entries.get(key) {
+            Some((_, Entry::InProgress)) => IdempotencyState::InProgress,
+            Some((_, Entry::Completed(response))) => IdempotencyState::Completed(response.clone()),
+            None => {
+                entries.insert(key.to_owned(), (now + ttl, Entry::InProgress));
+                IdempotencyState::New
+            }
+        }
+    }
+
+    fn complete(&self, key: &str, response: IdempotentResponse, ttl: Duration) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(
+                key.to_owned(),
+                (Instant::now() + ttl, Entry::Completed(response)),
+            );
+        }
+    }
+
+    fn abort(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IdempotencyConfig {
+    ttl: Duration,
+    storage: Arc<dyn IdempotencyStorage>,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        IdempotencyConfig {
+            ttl: Duration::from_secs(24 * 60 * 60),
+            storage: Arc::new(MemoryIdempotencyStorage::default()),
+        }
+    }
+}
+
+impl IdempotencyConfig {
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn storage<S: IdempotencyStorage + 'static>(mut self, storage: S) -> Self {
+        self.storage = Arc::new(storage);
+        self
+    }
+
+    fn key(&self, req: &ServiceRequest) -> Option<String> {
+        if matches!(
+            *req.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+        ) {
+            return None;
+        }
+
+        let key = req
+            .headers()
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty())?;
+
+        let user = User::try_from(req.request()).ok()?;
+
+        Some(format!("{}:{}", user.id, key))
+    }
+}
+
+fn replay(response: IdempotentResponse) -> HttpResponse {
+    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK);
+    let mut builder = HttpResponse::build(status);
+
+    if let Some(content_type) = response.content_type {
+        builder.content_type(content_type);
+    }
+
+    builder
+        .header(IDEMPOTENT_REPLAYED_HEADER, "true")
+        .body(response.body)
+}
+
+#[derive(Default)]
+pub struct Idempotency {
+    config: Rc<IdempotencyConfig>,
+}
+
+impl Idempotency {
+    pub fn new(config: IdempotencyConfig) -> Self {
+        Idempotency {
+            config: Rc::new(config),
+        }
+    }
+}
+
+impl<S> Transform<S> for Idempotency
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = IdempotencyMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(IdempotencyMiddleware {
+            service,
+            config: self.config.clone(),
+        })
+    }
+}
+
+pub struct IdempotencyMiddleware<S> {
+    service: S,
+    config: Rc<IdempotencyConfig>,
+}
+
+impl<S> Service for IdempotencyMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let key = match self.config.key(&req) {
+            Some(key) => key,
+            None => return Box::pin(self.service.call(req)),
+        };
+
+        let storage = self.config.storage.clone();
+        let ttl = self.config.ttl;
+
+        match storage.start(&key, ttl) {
+            IdempotencyState::New => {}
+            IdempotencyState::InProgress => {
+                return Box::pin(ok(req.into_response(HttpResponse::Conflict().finish())))
+            }
+            IdempotencyState::Completed(response) => {
+                return Box::pin(ok(req.into_response(replay(response))))
+            }
+        }
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = match fut.await {
+                Ok(res) => res,
+                Err(e) => {
+                    storage.abort(&key);
+                    return Err(e);
+                }
+            };
+
+            if res.status().is_server_error() {
+                storage.abort(&key);
+                return Ok(res);
+            }
+
+            let mut body = BytesMut::new();
+            let mut stream = res.take_body();
+
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(chunk) => body.extend_from_slice(&chunk),
+                    Err(e) => {
+                        storage.abort(&key);
+                        return Err(e);
+                    }
+                }
+            }
+
+            let body = body.freeze();
+            let content_type = res
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(|value| value.to_owned());
+
+            storage.complete(
+                &key,
+                IdempotentResponse {
+                    status: res.status().as_u16(),
+                    content_type,
+                    body: body.to_vec(),
+                },
+                ttl,
+            );
+
+            Ok(res.map_body(|_, _| ResponseBody::Body(Body::from(body))))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::Method;
+    use actix_web::test::TestRequest;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    use super::{
+        IdempotencyConfig, IdempotencyState, IdempotencyStorage, IdempotentResponse,
+        MemoryIdempotencyStorage,
+    };
+    use crate::testing::ContextBuilder;
+
+    #[test]
+    fn memory_storage() {
+        let storage = MemoryIdempotencyStorage::default();
+        let ttl = Duration::from_secs(60);
+        let response = IdempotentResponse {
+            status: 200,
+            content_type: None,
+            body: b"{}".to_vec(),
+        };
+
+        assert_eq!(storage.start("key", ttl), IdempotencyState::New);
+        assert_eq!(storage.start("key", ttl), IdempotencyState::InProgress);
+
+        storage.complete("key", response.clone(), ttl);
+        assert_eq!(
+            storage.start("key", ttl),
+            IdempotencyState::Completed(response)
+        );
+
+        storage.abort("key");
+        assert_eq!(storage.start("key", ttl), IdempotencyState::New);
+    }
+
+    #[test]
+    fn memory_storage_expired() {
+        let storage = MemoryIdempotencyStorage::default();
+
+        assert_eq!(
+            storage.start("key", Duration::from_secs(0)),
+            IdempotencyState::New
+        );
+        assert_eq!(
+            storage.start("key", Duration::from_secs(0)),
+            IdempotencyState::New
+        );
+    }
+
+    #[test]
+    fn key_requires_user_and_header() {
+        let config = IdempotencyConfig::default();
+
+        let req = TestRequest::default()
+            .method(Method::POST)
+            .header("idempotency-key", "abc")
+            .to_srv_request();
+        assert_eq!(config.key(&req), None);
+
+        let user_id = Uuid::new_v4();
+
+        let req = ContextBuilder::new()
+            .id(user_id)
+            .request()
+            .method(Method::POST)
+            .to_srv_request();
+        assert_eq!(config.key(&req), None);
+
+        let req = ContextBuilder::new()
+            .id(user_id)
+            .request()
+            .method(Method::POST)
+            .header("idempotency-key", "abc")
+            .to_srv_request();
+        assert_eq!(config.key(&req), Some(format!("{}:abc", user_id)));
+
+        let req = ContextBuilder::new()
+            .id(user_id)
+            .request()
+            .header("idempotency-key", "abc")
+            .to_srv_request();
+        assert_eq!(config.key(&req), None);
+    }
+}
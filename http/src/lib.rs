@@ -4,10 +4,75 @@ extern crate serde;
 #[macro_use]
 extern crate thiserror;
 
+extern crate self as timada_http;
+
+mod apq;
+mod authorize;
+mod cache;
+mod client_ip;
+mod complexity;
 mod context;
+mod csrf;
+#[cfg(feature = "dev-auth")]
+mod dev_auth;
 mod error;
+mod etag;
+mod federation;
+mod graphql;
+mod guard;
+mod idempotency;
+mod listen;
+mod openapi;
+mod reporter;
+mod request_data;
+mod rest;
+mod schema_diff;
+mod sdl;
+mod server;
+mod shutdown;
+mod upload;
 mod user;
 
-pub use crate::context::{Context, ContextError, ContextResult};
+pub mod pagination;
+pub mod testing;
+
+pub use crate::apq::{
+    hash_query, MemoryPersistedQueryStorage, PersistedQueries, PersistedQueryError,
+    PersistedQueryStorage, DEFAULT_PERSISTED_QUERIES_CAPACITY,
+};
+pub use crate::authorize::{Authorize, Requirement};
+pub use crate::cache::{CacheControl, CacheHint, CacheScope};
+pub use crate::client_ip::{client_ip, ClientIp};
+pub use crate::complexity::{connection_complexity, ComplexityLimits};
+pub use crate::context::{
+    Context, ContextConfig, ContextError, ContextResult, ORGANIZATION_HEADER,
+};
+pub use crate::csrf::{generate_csrf_token, Csrf, CsrfConfig, CsrfMiddleware};
 pub use crate::error::{Error, Result};
-pub use crate::user::{User, UserRole, UserState};
+pub use crate::etag::{
+    conditional, is_not_modified, json_with_etag, weak_etag, weak_etag_from_version,
+};
+pub use crate::federation::{federation_sdl, FederationService};
+pub use crate::graphql::GraphQLContextExt;
+pub use crate::guard::{AuthenticatedGuard, RoleGuard, StateGuard};
+pub use crate::idempotency::{
+    Idempotency, IdempotencyConfig, IdempotencyMiddleware, IdempotencyState, IdempotencyStorage,
+    IdempotentResponse, MemoryIdempotencyStorage,
+};
+pub use crate::listen::{publish, subscribe, ChannelEvent};
+pub use crate::openapi::{ApiSchema, OpenApi, Operation, Property, Schema, Schemas};
+pub use crate::reporter::{
+    set_error_reporter, ErrorReport, ErrorReporter, NoopErrorReporter, RequestScope,
+};
+pub use crate::request_data::RequestData;
+pub use crate::rest::Rest;
+pub use crate::schema_diff::{diff_sdl, SchemaChange};
+pub use crate::sdl::{export_sdl, introspection_to_sdl};
+pub use crate::server::{Server, ServerConfig};
+pub use crate::shutdown::{Shutdown, ShutdownHandle, TaskGuard};
+pub use crate::upload::{Upload, UploadConfig, UploadFile};
+pub use crate::user::{
+    has_valid_gateway_key, has_valid_service_key, reload_gateway_keys, sign_impersonator,
+    sign_user, GatewayHeaders, HeaderSource, User, UserRole, UserState, UserStateError,
+};
+pub use timada_http_derive::ApiSchema;
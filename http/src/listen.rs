@@ -0,0 +1,144 @@
+use diesel::PgConnection;
+use futures::{future, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use timada_database::{notify, ListenError, Listener};
+use uuid::Uuid;
+
+use super::context::Context;
+use super::error::{Error, Result};
+
+impl From<ListenError> for Error {
+    fn from(e: ListenError) -> Error {
+        match e {
+            ListenError::InvalidChannel(channel) | ListenError::UnknownChannel(channel) => {
+                Error::Internal(format!("Invalid channel {}", channel))
+            }
+            e => Error::Internal(format!("{:?}", e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelEvent<T> {
+    pub user_id: Option<Uuid>,
+    pub organization_id: Option<Uuid>,
+    pub data: T,
+}
+
+impl<T> ChannelEvent<T> {
+    pub fn broadcast(data: T) -> Self {
+        ChannelEvent {
+            user_id: None,
+            organization_id: None,
+            data,
+        }
+    }
+
+    pub fn user(user_id: Uuid, data: T) -> Self {
+        ChannelEvent {
+            user_id: Some(user_id),
+            organization_id: None,
+            data,
+        }
+    }
+
+    pub fn organization(organization_id: Uuid, data: T) -> Self {
+        ChannelEvent {
+            user_id: None,
+            organization_id: Some(organization_id),
+            data,
+        }
+    }
+
+    fn is_visible_to(&self, user_id: Uuid, organization_id: Option<Uuid>) -> bool {
+        self.user_id.map(|id| id == user_id).unwrap_or(true)
+            && self
+                .organization_id
+                .map(|id| Some(id) == organization_id)
+                .unwrap_or(true)
+    }
+}
+
+// Only organizations the signed user is a member of, whatever the request claims to act in.
+fn member_organization(context: &Context) -> Option<Uuid> {
+    let user = context.user.as_ref()?;
+
+    context
+        .organization_id
+        .filter(|organization_id| user.is_member_of(organization_id))
+}
+
+pub fn publish<T: Serialize>(
+    conn: &PgConnection,
+    channel: &str,
+    event: &ChannelEvent<T>,
+) -> Result<()> {
+    let payload = serde_json::to_string(event).map_err(|e| Error::Internal(e.to_string()))?;
+
+    Ok(notify(conn, channel, &payload)?)
+}
+
+pub fn subscribe<T>(
+    listener: &Listener,
+    channel: &str,
+    context: &Context,
+) -> Result<impl Stream<Item = T>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let user_id = context.ensure_is_authorized(None)?.id;
+    let organization_id = member_organization(context);
+
+    Ok(listener
+        .subscribe(channel)?
+        .filter_map(move |notification| {
+            let data = match serde_json::from_str::<ChannelEvent<T>>(&notification.payload) {
+                Ok(event) if event.is_visible_to(user_id, organization_id) => Some(event.data),
+                Ok(_) => None,
+                Err(e) => {
+                    log::warn!("invalid payload on channel {}: {}", notification.channel, e);
+                    None
+                }
+            };
+
+            future::ready(data)
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{member_organization, ChannelEvent};
+    use crate::context::Context;
+    use crate::testing::ContextBuilder;
+
+    #[test]
+    fn visibility() {
+        let user_id = Uuid::new_v4();
+        let organization_id = Uuid::new_v4();
+
+        assert!(ChannelEvent::broadcast(()).is_visible_to(user_id, None));
+        assert!(ChannelEvent::user(user_id, ()).is_visible_to(user_id, None));
+        assert!(!ChannelEvent::user(Uuid::new_v4(), ()).is_visible_to(user_id, None));
+        assert!(ChannelEvent::organization(organization_id, ())
+            .is_visible_to(user_id, Some(organization_id)));
+        assert!(!ChannelEvent::organization(organization_id, ()).is_visible_to(user_id, None));
+    }
+
+    #[test]
+    fn organization() {
+        let organization_id = Uuid::new_v4();
+        let member = ContextBuilder::new().organization(organization_id).build();
+
+        assert_eq!(member_organization(&member), Some(organization_id));
+
+        let spoofed = Context {
+            organization_id: Some(organization_id),
+            ..ContextBuilder::new().build()
+        };
+
+        assert_eq!(member_organization(&spoofed), None);
+    }
+}
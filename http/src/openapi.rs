@@ -0,0 +1,556 @@
+use actix_web::http::Method;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use super::error::Error;
+use super::pagination::DEFAULT_MAX_SIZE;
+
+const OPENAPI_VERSION: &str = "3.0.3";
+
+pub type Schema = Value;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Schemas(BTreeMap<String, Value>);
+
+pub struct Property {
+    pub name: &'static str,
+    pub schema: Value,
+    pub required: bool,
+    pub description: Option<&'static str>,
+}
+
+impl Schemas {
+    // Named schemas live in `components`, the returned value is a reference to it.
+    pub fn register<F>(&mut self, name: &str, schema: F) -> Value
+    where
+        F: FnOnce(&mut Schemas) -> Value,
+    {
+        if !self.0.contains_key(name) {
+            // Reserves the name first so recursive types stop here.
+            self.0.insert(name.to_owned(), Value::Null);
+            let schema = schema(self);
+            self.0.insert(name.to_owned(), schema);
+        }
+
+        json!({ "$ref": format!("#/components/schemas/{}", name) })
+    }
+
+    pub fn object(properties: Vec<Property>) -> Value {
+        let required = properties
+            .iter()
+            .filter(|property| property.required)
+            .map(|property| property.name)
+            .collect::<Vec<_>>();
+
+        let properties = properties
+            .into_iter()
+            .map(|property| {
+                let schema = match (property.description, property.schema) {
+                    (Some(description), Value::Object(mut schema))
+                        if !schema.contains_key("$ref") =>
+                    {
+                        schema.insert("description".to_owned(), json!(description));
+                        Value::Object(schema)
+                    }
+                    (_, schema) => schema,
+                };
+
+                (property.name.to_owned(), schema)
+            })
+            .collect::<Map<_, _>>();
+
+        let mut schema = json!({ "type": "object", "properties": properties });
+        if !required.is_empty() {
+            schema["required"] = json!(required);
+        }
+
+        schema
+    }
+
+    pub fn string_enum(values: &[&str]) -> Value {
+        json!({ "type": "string", "enum": values })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+}
+
+pub trait ApiSchema {
+    fn schema(schemas: &mut Schemas) -> Value;
+
+    fn required() -> bool {
+        true
+    }
+}
+
+macro_rules! api_schema {
+    ($($ty:ty => $schema:tt),* $(,)?) => {
+        $(
+            impl ApiSchema for $ty {
+                fn schema(_schemas: &mut Schemas) -> Value {
+                    json!($schema)
+                }
+            }
+        )*
+    };
+}
+
+api_schema!(
+    bool => { "type": "boolean" },
+    i16 => { "type": "integer", "format": "int32" },
+    i32 => { "type": "integer", "format": "int32" },
+    i64 => { "type": "integer", "format": "int64" },
+    u32 => { "type": "integer", "format": "int32", "minimum": 0 },
+    u64 => { "type": "integer", "format": "int64", "minimum": 0 },
+    usize => { "type": "integer", "minimum": 0 },
+    f32 => { "type": "number", "format": "float" },
+    f64 => { "type": "number", "format": "double" },
+    String => { "type": "string" },
+    Uuid => { "type": "string", "format": "uuid" },
+    NaiveDate => { "type": "string", "format": "date" },
+    NaiveDateTime => { "type": "string", "format": "date-time" },
+    DateTime<Utc> => { "type": "string", "format": "date-time" },
+    Value => {},
+);
+
+impl<T: ApiSchema> ApiSchema for Option<T> {
+    fn schema(schemas: &mut Schemas) -> Value {
+        match T::schema(schemas) {
+            Value::Object(mut schema) if !schema.contains_key("$ref") => {
+                schema.insert("nullable".to_owned(), json!(true));
+                Value::Object(schema)
+            }
+            schema => schema,
+        }
+    }
+
+    fn required() -> bool {
+        false
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for Vec<T> {
+    fn schema(schemas: &mut Schemas) -> Value {
+        json!({ "type": "array", "items": T::schema(schemas) })
+    }
+}
+
+// Matches the body of `ResponseError for Error`.
+impl ApiSchema for Error {
+    fn schema(schemas: &mut Schemas) -> Value {
+        schemas.register("Error", |_| {
+            json!({
+                "type": "object",
+                "properties": { "message": { "type": "string" } },
+                "required": ["message"],
+            })
+        })
+    }
+}
+
+type SchemaFn = fn(&mut Schemas) -> Value;
+
+#[derive(Clone)]
+struct Parameter {
+    location: &'static str,
+    name: String,
+    required: bool,
+    schema: SchemaFn,
+}
+
+fn page_size(_schemas: &mut Schemas) -> Value {
+    json!({ "type": "integer", "minimum": 1, "maximum": DEFAULT_MAX_SIZE })
+}
+
+#[derive(Clone)]
+pub struct Operation {
+    pub(crate) method: Method,
+    pub(crate) path: String,
+    summary: Option<String>,
+    description: Option<String>,
+    operation_id: Option<String>,
+    tags: Vec<String>,
+    deprecated: bool,
+    parameters: Vec<Parameter>,
+    request_body: Option<SchemaFn>,
+    responses: Vec<(u16, String, Option<SchemaFn>)>,
+}
+
+impl Operation {
+    pub fn new(method: Method, path: &str) -> Self {
+        Operation {
+            method,
+            path: path.to_owned(),
+            summary: None,
+            description: None,
+            operation_id: None,
+            tags: Vec::new(),
+            deprecated: false,
+            parameters: Vec::new(),
+            request_body: None,
+            responses: Vec::new(),
+        }
+    }
+
+    pub fn get(path: &str) -> Self {
+        Self::new(Method::GET, path)
+    }
+
+    pub fn post(path: &str) -> Self {
+        Self::new(Method::POST, path)
+    }
+
+    pub fn put(path: &str) -> Self {
+        Self::new(Method::PUT, path)
+    }
+
+    pub fn patch(path: &str) -> Self {
+        Self::new(Method::PATCH, path)
+    }
+
+    pub fn delete(path: &str) -> Self {
+        Self::new(Method::DELETE, path)
+    }
+
+    pub fn summary(mut self, summary: &str) -> Self {
+        self.summary = Some(summary.to_owned());
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+
+    pub fn operation_id(mut self, operation_id: &str) -> Self {
+        self.operation_id = Some(operation_id.to_owned());
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_owned());
+        self
+    }
+
+    pub fn deprecated(mut self) -> Self {
+        self.deprecated = true;
+        self
+    }
+
+    fn parameter(
+        mut self,
+        location: &'static str,
+        name: &str,
+        required: bool,
+        schema: SchemaFn,
+    ) -> Self {
+        self.parameters.push(Parameter {
+            location,
+            name: name.to_owned(),
+            required,
+            schema,
+        });
+        self
+    }
+
+    pub fn path_param<T: ApiSchema>(self, name: &str) -> Self {
+        self.parameter("path", name, true, T::schema)
+    }
+
+    pub fn query_param<T: ApiSchema>(self, name: &str) -> Self {
+        self.parameter("query", name, T::required(), T::schema)
+    }
+
+    pub fn header_param<T: ApiSchema>(self, name: &str) -> Self {
+        self.parameter("header", name, T::required(), T::schema)
+    }
+
+    // Query parameters of `PaginationArgs`, invalid combinations answer 400.
+    pub fn paginated(self) -> Self {
+        self.parameter("query", "first", false, page_size)
+            .query_param::<Option<String>>("after")
+            .parameter("query", "last", false, page_size)
+            .query_param::<Option<String>>("before")
+            .error(400, "Invalid pagination arguments")
+    }
+
+    pub fn request_body<T: ApiSchema>(mut self) -> Self {
+        self.request_body = Some(T::schema);
+        self
+    }
+
+    pub fn response<T: ApiSchema>(mut self, status: u16, description: &str) -> Self {
+        self.responses
+            .push((status, description.to_owned(), Some(T::schema)));
+        self
+    }
+
+    pub fn empty_response(mut self, status: u16, description: &str) -> Self {
+        self.responses.push((status, description.to_owned(), None));
+        self
+    }
+
+    pub fn error(mut self, status: u16, description: &str) -> Self {
+        self.responses
+            .push((status, description.to_owned(), Some(Error::schema)));
+        self
+    }
+
+    fn to_json(&self, schemas: &mut Schemas) -> Value {
+        let mut operation = json!({});
+
+        if let Some(summary) = self.summary.as_ref() {
+            operation["summary"] = json!(summary);
+        }
+
+        if let Some(description) = self.description.as_ref() {
+            operation["description"] = json!(description);
+        }
+
+        if let Some(operation_id) = self.operation_id.as_ref() {
+            operation["operationId"] = json!(operation_id);
+        }
+
+        if !self.tags.is_empty() {
+            operation["tags"] = json!(self.tags);
+        }
+
+        if self.deprecated {
+            operation["deprecated"] = json!(true);
+        }
+
+        if !self.parameters.is_empty() {
+            let parameters = self
+                .parameters
+                .iter()
+                .map(|parameter| {
+                    json!({
+                        "name": parameter.name,
+                        "in": parameter.location,
+                        "required": parameter.required,
+                        "schema": (parameter.schema)(schemas),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            operation["parameters"] = json!(parameters);
+        }
+
+        if let Some(request_body) = self.request_body {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": request_body(schemas) } },
+            });
+        }
+
+        let mut responses = Map::new();
+        for (status, description, schema) in self.responses.iter() {
+            let mut response = json!({ "description": description });
+
+            if let Some(schema) = schema {
+                response["content"] = json!({ "application/json": { "schema": schema(schemas) } });
+            }
+
+            responses.insert(status.to_string(), response);
+        }
+
+        // Every handler can fail with an internal error.
+        responses.entry("500").or_insert_with(|| {
+            json!({
+                "description": "Internal Server Error",
+                "content": { "application/json": { "schema": Error::schema(schemas) } },
+            })
+        });
+
+        operation["responses"] = Value::Object(responses);
+        operation
+    }
+}
+
+#[derive(Clone)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    description: Option<String>,
+    operations: Vec<Operation>,
+}
+
+impl OpenApi {
+    pub fn new(title: &str, version: &str) -> Self {
+        OpenApi {
+            title: title.to_owned(),
+            version: version.to_owned(),
+            description: None,
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+
+    pub fn operation(mut self, operation: Operation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut schemas = Schemas::default();
+        let mut paths = BTreeMap::<&str, Map<String, Value>>::new();
+
+        for operation in self.operations.iter() {
+            paths.entry(operation.path.as_str()).or_default().insert(
+                operation.method.as_str().to_lowercase(),
+                operation.to_json(&mut schemas),
+            );
+        }
+
+        let mut info = json!({ "title": self.title, "version": self.version });
+        if let Some(description) = self.description.as_ref() {
+            info["description"] = json!(description);
+        }
+
+        json!({
+            "openapi": OPENAPI_VERSION,
+            "info": info,
+            "paths": paths,
+            "components": { "schemas": schemas.0 },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use super::{ApiSchema, OpenApi, Operation, Property, Schemas};
+
+    struct Todo;
+
+    impl ApiSchema for Todo {
+        fn schema(schemas: &mut Schemas) -> Value {
+            schemas.register("Todo", |schemas| {
+                Schemas::object(vec![
+                    Property {
+                        name: "id",
+                        schema: Uuid::schema(schemas),
+                        required: Uuid::required(),
+                        description: None,
+                    },
+                    Property {
+                        name: "note",
+                        schema: Option::<String>::schema(schemas),
+                        required: Option::<String>::required(),
+                        description: Some("Free text"),
+                    },
+                ])
+            })
+        }
+    }
+
+    /// Todo payload
+    #[derive(Serialize, crate::ApiSchema)]
+    #[serde(rename_all = "camelCase")]
+    #[allow(dead_code)]
+    struct NewTodo {
+        /// Shown in lists
+        title: String,
+        due_at: Option<chrono::DateTime<chrono::Utc>>,
+        #[serde(default)]
+        tags: Vec<String>,
+        status: Status,
+        #[serde(skip)]
+        internal: bool,
+    }
+
+    #[derive(Serialize, crate::ApiSchema)]
+    #[serde(rename_all = "snake_case")]
+    #[allow(dead_code)]
+    enum Status {
+        Open,
+        InProgress,
+        #[serde(rename = "closed")]
+        Done,
+    }
+
+    #[test]
+    fn derive() {
+        let mut schemas = Schemas::default();
+
+        assert_eq!(
+            NewTodo::schema(&mut schemas),
+            json!({ "$ref": "#/components/schemas/NewTodo" })
+        );
+        assert_eq!(
+            schemas.get("NewTodo"),
+            Some(&json!({
+                "type": "object",
+                "properties": {
+                    "title": { "type": "string", "description": "Shown in lists" },
+                    "dueAt": { "type": "string", "format": "date-time", "nullable": true },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "status": { "$ref": "#/components/schemas/Status" },
+                },
+                "required": ["title", "status"],
+            }))
+        );
+        assert_eq!(
+            schemas.get("Status"),
+            Some(&json!({ "type": "string", "enum": ["open", "in_progress", "closed"] }))
+        );
+    }
+
+    #[test]
+    fn document() {
+        let document = OpenApi::new("Todos", "1.0.0")
+            .operation(
+                Operation::get("/todos")
+                    .tag("todos")
+                    .paginated()
+                    .response::<Vec<Todo>>(200, "Todos"),
+            )
+            .operation(
+                Operation::get("/todos/{id}")
+                    .path_param::<Uuid>("id")
+                    .response::<Todo>(200, "Todo")
+                    .error(404, "Todo not found"),
+            )
+            .operation(Operation::delete("/todos/{id}").empty_response(204, "Deleted"))
+            .to_json();
+
+        assert_eq!(document["openapi"], "3.0.3");
+        assert_eq!(
+            document["paths"]["/todos"]["get"]["responses"]["200"]["content"]["application/json"]
+                ["schema"],
+            json!({ "type": "array", "items": { "$ref": "#/components/schemas/Todo" } })
+        );
+        assert_eq!(
+            document["paths"]["/todos"]["get"]["parameters"]
+                .as_array()
+                .map(|parameters| parameters.len()),
+            Some(4)
+        );
+        assert_eq!(
+            document["paths"]["/todos/{id}"]["get"]["responses"]["404"]["content"]
+                ["application/json"]["schema"]["$ref"],
+            "#/components/schemas/Error"
+        );
+        assert!(document["paths"]["/todos/{id}"]["delete"]["responses"]["500"].is_object());
+        assert_eq!(
+            document["components"]["schemas"]["Todo"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "format": "uuid" },
+                    "note": { "type": "string", "nullable": true, "description": "Free text" },
+                },
+                "required": ["id"],
+            })
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use super::error::{Error, Result};
+
+pub const DEFAULT_MAX_SIZE: usize = 100;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PaginationArgs {
+    pub first: Option<usize>,
+    pub after: Option<String>,
+    pub last: Option<usize>,
+    pub before: Option<String>,
+}
+
+impl PaginationArgs {
+    pub fn new(
+        first: Option<usize>,
+        after: Option<String>,
+        last: Option<usize>,
+        before: Option<String>,
+    ) -> Self {
+        PaginationArgs {
+            first,
+            after,
+            last,
+            before,
+        }
+    }
+
+    pub fn is_backward(&self) -> bool {
+        self.last.is_some() || self.before.is_some()
+    }
+
+    pub fn validate(self) -> Result<Self> {
+        self.validate_with_max_size(DEFAULT_MAX_SIZE)
+    }
+
+    pub fn validate_with_max_size(self, max_size: usize) -> Result<Self> {
+        let forward = self.first.is_some() || self.after.is_some();
+
+        if forward && self.is_backward() {
+            return Err(Error::BadRequest(
+                "first/after and last/before are mutually exclusive".to_owned(),
+            ));
+        }
+
+        match self.first.or(self.last) {
+            Some(0) => Err(Error::BadRequest(
+                "first/last must be greater than 0".to_owned(),
+            )),
+            Some(size) if size > max_size => Err(Error::BadRequest(format!(
+                "first/last must not exceed {}",
+                max_size
+            ))),
+            _ => Ok(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaginationArgs;
+    use crate::Error;
+
+    #[test]
+    fn validate_success() {
+        let args = PaginationArgs::new(Some(10), Some("cursor".to_owned()), None, None);
+
+        assert_eq!(args.clone().validate(), Ok(args));
+        assert_eq!(
+            PaginationArgs::default().validate(),
+            Ok(PaginationArgs::default())
+        );
+    }
+
+    #[test]
+    fn validate_mutually_exclusive() {
+        let args = PaginationArgs::new(Some(10), None, None, Some("cursor".to_owned()));
+
+        assert_eq!(
+            args.validate(),
+            Err(Error::BadRequest(
+                "first/after and last/before are mutually exclusive".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn validate_size() {
+        assert_eq!(
+            PaginationArgs::new(None, None, Some(0), None).validate(),
+            Err(Error::BadRequest(
+                "first/last must be greater than 0".to_owned()
+            ))
+        );
+        assert_eq!(
+            PaginationArgs::new(Some(20), None, None, None).validate_with_max_size(10),
+            Err(Error::BadRequest(
+                "first/last must not exceed 10".to_owned()
+            ))
+        );
+    }
+}
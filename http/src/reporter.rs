@@ -0,0 +1,161 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use uuid::Uuid;
+
+use super::error::Error;
+
+#[derive(Debug)]
+pub struct ErrorReport<'a> {
+    pub error: &'a Error,
+    pub error_id: Option<&'a str>,
+    pub request_id: Option<&'a str>,
+    pub user_id: Option<Uuid>,
+    pub user_role: Option<&'a str>,
+    pub impersonator_id: Option<Uuid>,
+}
+
+// Runs around every poll of one request, so the reporter state of concurrent requests
+// on the same worker thread don't mix.
+pub trait RequestScope {
+    fn run(&self, f: &mut dyn FnMut());
+}
+
+pub trait ErrorReporter: Send + Sync {
+    fn report(&self, report: &ErrorReport<'_>);
+
+    fn request_scope(&self) -> Option<Box<dyn RequestScope>> {
+        None
+    }
+}
+
+pub struct NoopErrorReporter;
+
+impl ErrorReporter for NoopErrorReporter {
+    fn report(&self, _report: &ErrorReport<'_>) {}
+}
+
+lazy_static::lazy_static! {
+    static ref REPORTER: RwLock<Arc<dyn ErrorReporter>> = RwLock::new(Arc::new(NoopErrorReporter));
+}
+
+pub fn set_error_reporter<R: ErrorReporter + 'static>(reporter: R) {
+    if let Ok(mut current) = REPORTER.write() {
+        *current = Arc::new(reporter);
+    }
+}
+
+pub(crate) fn report(report: &ErrorReport<'_>) {
+    let reporter = match REPORTER.read() {
+        Ok(reporter) => reporter.clone(),
+        _ => return,
+    };
+
+    reporter.report(report);
+}
+
+pub(crate) fn request_scope() -> Option<Box<dyn RequestScope>> {
+    match REPORTER.read() {
+        Ok(reporter) => reporter.request_scope(),
+        _ => None,
+    }
+}
+
+pub(crate) struct Scoped<F> {
+    scope: Option<Box<dyn RequestScope>>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Scoped<F> {
+    pub(crate) fn new(scope: Option<Box<dyn RequestScope>>, future: F) -> Self {
+        Scoped {
+            scope,
+            future: Box::pin(future),
+        }
+    }
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = &mut *self;
+
+        let scope = match this.scope.as_ref() {
+            Some(scope) => scope,
+            None => return this.future.as_mut().poll(cx),
+        };
+
+        let future = &mut this.future;
+        let mut poll = Poll::Pending;
+        scope.run(&mut || poll = future.as_mut().poll(cx));
+
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use std::sync::{Arc, Mutex};
+
+    use super::{report, set_error_reporter, ErrorReport, ErrorReporter, RequestScope, Scoped};
+    use crate::error::Error;
+
+    struct CountingScope(Arc<Mutex<usize>>);
+
+    impl RequestScope for CountingScope {
+        fn run(&self, f: &mut dyn FnMut()) {
+            *self.0.lock().unwrap() += 1;
+            f();
+        }
+    }
+
+    struct RecordingReporter(Arc<Mutex<Vec<String>>>);
+
+    impl ErrorReporter for RecordingReporter {
+        fn report(&self, report: &ErrorReport<'_>) {
+            self.0.lock().unwrap().push(format!(
+                "{}:{}",
+                report.request_id.unwrap_or_default(),
+                report.error
+            ));
+        }
+    }
+
+    #[test]
+    fn report_to_custom_reporter() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        set_error_reporter(RecordingReporter(reports.clone()));
+
+        report(&ErrorReport {
+            error: &Error::Internal("report-test".to_owned()),
+            error_id: None,
+            request_id: Some("request-1"),
+            user_id: None,
+            user_role: None,
+            impersonator_id: None,
+        });
+
+        assert!(reports
+            .lock()
+            .unwrap()
+            .contains(&"request-1:report-test".to_owned()));
+    }
+
+    #[test]
+    fn scoped() {
+        let runs = Arc::new(Mutex::new(0));
+        let scope = CountingScope(runs.clone());
+
+        let value = block_on(Scoped::new(Some(Box::new(scope)), async {
+            futures::future::ready(()).await;
+            42
+        }));
+
+        assert_eq!(value, 42);
+        assert!(*runs.lock().unwrap() >= 1);
+        assert_eq!(block_on(Scoped::new(None, async { 1 })), 1);
+    }
+}
@@ -0,0 +1,63 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::context::Context;
+
+#[derive(Default)]
+pub struct RequestData {
+    values: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl RequestData {
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+}
+
+pub(crate) type RequestDataFactory = Arc<dyn Fn(&Context, &mut RequestData) + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub(crate) struct RequestDataFactories(pub(crate) Vec<RequestDataFactory>);
+
+impl RequestDataFactories {
+    pub(crate) fn build(&self, context: &Context) -> RequestData {
+        let mut data = RequestData::default();
+
+        for factory in self.0.iter() {
+            factory(context, &mut data);
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{RequestData, RequestDataFactories};
+    use crate::context::Context;
+
+    struct Loader(&'static str);
+
+    #[test]
+    fn build() {
+        let factories = RequestDataFactories(vec![Arc::new(
+            |context: &Context, data: &mut RequestData| {
+                data.insert(Loader(context.locale()));
+            },
+        )]);
+
+        let data = factories.build(&Context::default());
+
+        assert_eq!(data.get::<Loader>().map(|loader| loader.0), Some("en"));
+        assert!(data.get::<String>().is_none());
+    }
+}
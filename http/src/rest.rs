@@ -0,0 +1,99 @@
+use actix_web::{web, HttpResponse, Route};
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::openapi::{OpenApi, Operation};
+
+const OPENAPI_PATH: &str = "/openapi.json";
+
+type Register = Arc<dyn Fn(&mut web::ServiceConfig) + Send + Sync>;
+
+// Keeps handlers and their OpenAPI operation side by side so the document can't drift.
+#[derive(Clone)]
+pub struct Rest {
+    document: OpenApi,
+    path: String,
+    routes: Vec<Register>,
+}
+
+impl Rest {
+    pub fn new(document: OpenApi) -> Self {
+        Rest {
+            document,
+            path: OPENAPI_PATH.to_owned(),
+            routes: Vec::new(),
+        }
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.to_owned();
+        self
+    }
+
+    // `.route(Operation::post("/webhooks/stripe"), |route| route.to(stripe))`
+    pub fn route<F>(mut self, operation: Operation, route: F) -> Self
+    where
+        F: Fn(Route) -> Route + Send + Sync + 'static,
+    {
+        let path = operation.path.clone();
+        let method = operation.method.clone();
+
+        self.routes.push(Arc::new(move |cfg| {
+            cfg.route(&path, route(web::method(method.clone())));
+        }));
+        self.document = self.document.operation(operation);
+        self
+    }
+
+    pub fn document(&self) -> &OpenApi {
+        &self.document
+    }
+
+    pub(crate) fn into_routes(self) -> impl Fn(&mut web::ServiceConfig) + Send + Sync + 'static {
+        let document = Arc::new(self.document.to_json());
+        let path = self.path;
+        let routes = self.routes;
+
+        move |cfg| {
+            let document = document.clone();
+
+            cfg.route(&path, web::get().to(move || openapi(document.clone())));
+
+            for register in routes.iter() {
+                register(cfg);
+            }
+        }
+    }
+}
+
+async fn openapi(document: Arc<Value>) -> HttpResponse {
+    HttpResponse::Ok().json(document.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::HttpResponse;
+
+    use super::Rest;
+    use crate::openapi::{OpenApi, Operation};
+
+    async fn ping() -> HttpResponse {
+        HttpResponse::Ok().json("pong")
+    }
+
+    #[test]
+    fn document() {
+        let rest = Rest::new(OpenApi::new("Todos", "1.0.0"))
+            .route(
+                Operation::get("/ping").response::<String>(200, "Pong"),
+                |route| route.to(ping),
+            )
+            .route(Operation::post("/ping"), |route| route.to(ping));
+
+        let document = rest.document().to_json();
+
+        assert_eq!(document["info"]["title"], "Todos");
+        assert!(document["paths"]["/ping"]["get"].is_object());
+        assert!(document["paths"]["/ping"]["post"].is_object());
+    }
+}
@@ -0,0 +1,592 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeKind {
+    Scalar,
+    Object,
+    Interface,
+    Union,
+    Enum,
+    Input,
+}
+
+impl fmt::Display for TypeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            TypeKind::Scalar => "scalar",
+            TypeKind::Object => "type",
+            TypeKind::Interface => "interface",
+            TypeKind::Union => "union",
+            TypeKind::Enum => "enum",
+            TypeKind::Input => "input",
+        };
+
+        write!(f, "{}", kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputValue {
+    pub name: String,
+    pub ty: String,
+    pub default: Option<String>,
+}
+
+impl InputValue {
+    fn is_required(&self) -> bool {
+        self.ty.ends_with('!') && self.default.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDef {
+    pub name: String,
+    pub ty: String,
+    pub args: Vec<InputValue>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDef {
+    pub kind: TypeKind,
+    pub name: String,
+    pub interfaces: Vec<String>,
+    pub fields: Vec<FieldDef>,
+    pub input_fields: Vec<InputValue>,
+    pub values: Vec<String>,
+}
+
+impl TypeDef {
+    fn new(kind: TypeKind, name: &str) -> Self {
+        TypeDef {
+            kind,
+            name: name.to_owned(),
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            input_fields: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaChange {
+    pub breaking: bool,
+    pub message: String,
+}
+
+impl SchemaChange {
+    fn breaking(message: String) -> Self {
+        SchemaChange {
+            breaking: true,
+            message,
+        }
+    }
+
+    fn safe(message: String) -> Self {
+        SchemaChange {
+            breaking: false,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.breaking {
+            write!(f, "[BREAKING] {}", self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
+    }
+}
+
+// Splits on `,` outside of brackets, braces and strings, e.g. `a: [Int] = [1, 2], b: String`.
+fn split_top_level(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '[' | '{' | '(' if !in_string => depth += 1,
+            ']' | '}' | ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                parts.push(value[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    let last = value[start..].trim();
+    if !last.is_empty() {
+        parts.push(last);
+    }
+
+    parts
+}
+
+fn strip_directives(line: &str) -> &str {
+    match line.find(" @") {
+        Some(index) => line[..index].trim_end(),
+        None => line,
+    }
+}
+
+fn parse_input_value(value: &str) -> Result<InputValue, String> {
+    let value = strip_directives(value);
+    let colon = value
+        .find(':')
+        .ok_or_else(|| format!("Invalid input value: {}", value))?;
+    let name = value[..colon].trim();
+    let rest = value[colon + 1..].trim();
+
+    let (ty, default) = match rest.find('=') {
+        Some(index) => (
+            rest[..index].trim(),
+            Some(rest[index + 1..].trim().to_owned()),
+        ),
+        None => (rest, None),
+    };
+
+    Ok(InputValue {
+        name: name.to_owned(),
+        ty: ty.to_owned(),
+        default,
+    })
+}
+
+fn parse_field(line: &str) -> Result<FieldDef, String> {
+    let line = strip_directives(line);
+    let (name, args, rest) = match (line.find('('), line.find(':')) {
+        (Some(open), Some(colon)) if open < colon => {
+            let close = line
+                .rfind(')')
+                .ok_or_else(|| format!("Invalid field: {}", line))?;
+            let args = split_top_level(&line[open + 1..close])
+                .into_iter()
+                .map(parse_input_value)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            (&line[..open], args, &line[close + 1..])
+        }
+        (_, Some(colon)) => (&line[..colon], Vec::new(), &line[colon..]),
+        _ => return Err(format!("Invalid field: {}", line)),
+    };
+
+    let ty = rest
+        .trim()
+        .strip_prefix(':')
+        .ok_or_else(|| format!("Invalid field: {}", line))?
+        .trim();
+
+    Ok(FieldDef {
+        name: name.trim().to_owned(),
+        ty: ty.to_owned(),
+        args,
+    })
+}
+
+fn parse_header(line: &str) -> Result<Option<TypeDef>, String> {
+    let line = strip_directives(line.trim_end_matches('{').trim());
+    let mut words = line.split_whitespace();
+
+    let kind = match words.next() {
+        Some("scalar") => TypeKind::Scalar,
+        Some("type") => TypeKind::Object,
+        Some("interface") => TypeKind::Interface,
+        Some("union") => TypeKind::Union,
+        Some("enum") => TypeKind::Enum,
+        Some("input") => TypeKind::Input,
+        Some("schema") => return Ok(None),
+        _ => return Err(format!("Invalid definition: {}", line)),
+    };
+
+    let name = words
+        .next()
+        .ok_or_else(|| format!("Missing name: {}", line))?;
+    let mut ty = TypeDef::new(kind, name);
+
+    match (kind, words.next()) {
+        (TypeKind::Union, Some("=")) => {
+            ty.values = words
+                .filter(|word| *word != "|")
+                .map(|word| word.to_owned())
+                .collect();
+        }
+        (_, Some("implements")) => {
+            ty.interfaces = words
+                .filter(|word| *word != "&")
+                .map(|word| word.to_owned())
+                .collect();
+        }
+        _ => {}
+    }
+
+    Ok(Some(ty))
+}
+
+// Parses SDL as printed by `introspection_to_sdl`: one definition header per line,
+// one field per line, descriptions and directives are ignored.
+pub fn parse_sdl(sdl: &str) -> Result<BTreeMap<String, TypeDef>, String> {
+    let mut types = BTreeMap::new();
+    let mut current: Option<TypeDef> = None;
+    let mut in_block = false;
+    let mut in_description = false;
+
+    for line in sdl.lines().map(str::trim) {
+        if in_description {
+            in_description = !line.ends_with("\"\"\"");
+            continue;
+        }
+
+        if line.starts_with("\"\"\"") {
+            in_description = line.len() < 6 || !line[3..].ends_with("\"\"\"");
+            continue;
+        }
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line == "}" {
+            if let Some(ty) = current.take() {
+                types.insert(ty.name.clone(), ty);
+            }
+            in_block = false;
+            continue;
+        }
+
+        if !in_block {
+            in_block = line.ends_with('{');
+
+            match parse_header(line)? {
+                Some(ty) if in_block => current = Some(ty),
+                Some(ty) => {
+                    types.insert(ty.name.clone(), ty);
+                }
+                None => {}
+            }
+
+            continue;
+        }
+
+        let ty = match current.as_mut() {
+            Some(ty) => ty,
+            // `schema { ... }` block
+            None => continue,
+        };
+
+        match ty.kind {
+            TypeKind::Enum => ty.values.push(strip_directives(line).to_owned()),
+            TypeKind::Input => ty.input_fields.push(parse_input_value(line)?),
+            _ => ty.fields.push(parse_field(line)?),
+        }
+    }
+
+    Ok(types)
+}
+
+fn unwrap_type(ty: &str) -> Option<&str> {
+    ty.strip_prefix('[').and_then(|ty| ty.strip_suffix(']'))
+}
+
+// Output positions may only become stricter, `String` -> `String!` is safe.
+fn is_safe_output_change(old: &str, new: &str) -> bool {
+    if old == new {
+        return true;
+    }
+
+    match (old.strip_suffix('!'), new.strip_suffix('!')) {
+        (Some(old), Some(new)) => is_safe_output_change(old, new),
+        (None, Some(new)) => is_safe_output_change(old, new),
+        (Some(_), None) => false,
+        (None, None) => match (unwrap_type(old), unwrap_type(new)) {
+            (Some(old), Some(new)) => is_safe_output_change(old, new),
+            _ => false,
+        },
+    }
+}
+
+// Input positions may only become looser, `String!` -> `String` is safe.
+fn is_safe_input_change(old: &str, new: &str) -> bool {
+    is_safe_output_change(new, old)
+}
+
+fn diff_input_values(
+    path: &str,
+    old: &[InputValue],
+    new: &[InputValue],
+    changes: &mut Vec<SchemaChange>,
+) {
+    for old_value in old.iter() {
+        match new.iter().find(|value| value.name == old_value.name) {
+            None => changes.push(SchemaChange::breaking(format!(
+                "{}.{} was removed",
+                path, old_value.name
+            ))),
+            Some(new_value) if !is_safe_input_change(&old_value.ty, &new_value.ty) => {
+                changes.push(SchemaChange::breaking(format!(
+                    "{}.{} changed type from {} to {}",
+                    path, old_value.name, old_value.ty, new_value.ty
+                )))
+            }
+            Some(new_value) if old_value.ty != new_value.ty => {
+                changes.push(SchemaChange::safe(format!(
+                    "{}.{} changed type from {} to {}",
+                    path, old_value.name, old_value.ty, new_value.ty
+                )))
+            }
+            Some(new_value) if old_value.default != new_value.default => changes.push(
+                SchemaChange::safe(format!("{}.{} changed default value", path, old_value.name)),
+            ),
+            Some(_) => {}
+        }
+    }
+
+    for new_value in new.iter() {
+        if old.iter().any(|value| value.name == new_value.name) {
+            continue;
+        }
+
+        let message = format!("{}.{} was added", path, new_value.name);
+        changes.push(if new_value.is_required() {
+            SchemaChange::breaking(format!("required {}", message))
+        } else {
+            SchemaChange::safe(message)
+        });
+    }
+}
+
+fn diff_fields(ty: &str, old: &[FieldDef], new: &[FieldDef], changes: &mut Vec<SchemaChange>) {
+    for old_field in old.iter() {
+        let path = format!("{}.{}", ty, old_field.name);
+
+        let new_field = match new.iter().find(|field| field.name == old_field.name) {
+            Some(new_field) => new_field,
+            None => {
+                changes.push(SchemaChange::breaking(format!("{} was removed", path)));
+                continue;
+            }
+        };
+
+        if old_field.ty != new_field.ty {
+            let message = format!(
+                "{} changed type from {} to {}",
+                path, old_field.ty, new_field.ty
+            );
+
+            changes.push(if is_safe_output_change(&old_field.ty, &new_field.ty) {
+                SchemaChange::safe(message)
+            } else {
+                SchemaChange::breaking(message)
+            });
+        }
+
+        diff_input_values(&path, &old_field.args, &new_field.args, changes);
+    }
+
+    for new_field in new.iter() {
+        if !old.iter().any(|field| field.name == new_field.name) {
+            changes.push(SchemaChange::safe(format!(
+                "{}.{} was added",
+                ty, new_field.name
+            )));
+        }
+    }
+}
+
+fn diff_list(
+    ty: &str,
+    what: &str,
+    old: &[String],
+    new: &[String],
+    changes: &mut Vec<SchemaChange>,
+) {
+    for value in old.iter().filter(|value| !new.contains(value)) {
+        changes.push(SchemaChange::breaking(format!(
+            "{} {} was removed from {}",
+            what, value, ty
+        )));
+    }
+
+    for value in new.iter().filter(|value| !old.contains(value)) {
+        changes.push(SchemaChange::safe(format!(
+            "{} {} was added to {}",
+            what, value, ty
+        )));
+    }
+}
+
+pub fn diff_schema(
+    old: &BTreeMap<String, TypeDef>,
+    new: &BTreeMap<String, TypeDef>,
+) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+
+    for (name, old_type) in old.iter() {
+        let new_type = match new.get(name) {
+            Some(new_type) => new_type,
+            None => {
+                changes.push(SchemaChange::breaking(format!("{} was removed", name)));
+                continue;
+            }
+        };
+
+        if old_type.kind != new_type.kind {
+            changes.push(SchemaChange::breaking(format!(
+                "{} changed from {} to {}",
+                name, old_type.kind, new_type.kind
+            )));
+            continue;
+        }
+
+        diff_fields(name, &old_type.fields, &new_type.fields, &mut changes);
+        diff_input_values(
+            name,
+            &old_type.input_fields,
+            &new_type.input_fields,
+            &mut changes,
+        );
+        diff_list(
+            name,
+            "interface",
+            &old_type.interfaces,
+            &new_type.interfaces,
+            &mut changes,
+        );
+
+        let what = match old_type.kind {
+            TypeKind::Union => "member",
+            _ => "value",
+        };
+        diff_list(name, what, &old_type.values, &new_type.values, &mut changes);
+    }
+
+    for name in new.keys().filter(|name| !old.contains_key(*name)) {
+        changes.push(SchemaChange::safe(format!("{} was added", name)));
+    }
+
+    changes
+}
+
+pub fn diff_sdl(old: &str, new: &str) -> Result<Vec<SchemaChange>, String> {
+    Ok(diff_schema(&parse_sdl(old)?, &parse_sdl(new)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_sdl, parse_sdl, InputValue, TypeKind};
+
+    const SDL: &str = "\"\"\"A task\"\"\"
+type Todo implements Node {
+  id: ID!
+  title: String
+  done: Boolean! @deprecated(reason: \"Use status\")
+  tags(first: Int = 10, filter: [String!] = [\"a, b\"]): [String!]!
+}
+
+enum Status {
+  OPEN
+  DONE
+}
+
+input NewTodo {
+  \"\"\"
+  Shown in lists
+  \"\"\"
+  title: String!
+  note: String
+}
+
+union SearchResult = Project | Todo
+
+scalar DateTime
+";
+
+    #[test]
+    fn parse() {
+        let types = parse_sdl(SDL).unwrap();
+
+        assert_eq!(types.len(), 5);
+
+        let todo = &types["Todo"];
+        assert_eq!(todo.kind, TypeKind::Object);
+        assert_eq!(todo.interfaces, vec!["Node"]);
+        assert_eq!(todo.fields[2].ty, "Boolean!");
+        assert_eq!(
+            todo.fields[3].args,
+            vec![
+                InputValue {
+                    name: "first".to_owned(),
+                    ty: "Int".to_owned(),
+                    default: Some("10".to_owned()),
+                },
+                InputValue {
+                    name: "filter".to_owned(),
+                    ty: "[String!]".to_owned(),
+                    default: Some("[\"a, b\"]".to_owned()),
+                },
+            ]
+        );
+        assert_eq!(todo.fields[3].ty, "[String!]!");
+        assert_eq!(types["Status"].values, vec!["OPEN", "DONE"]);
+        assert_eq!(types["NewTodo"].input_fields.len(), 2);
+        assert_eq!(types["SearchResult"].values, vec!["Project", "Todo"]);
+        assert_eq!(types["DateTime"].kind, TypeKind::Scalar);
+    }
+
+    #[test]
+    fn no_changes() {
+        assert_eq!(diff_sdl(SDL, SDL), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn breaking_changes() {
+        let new = SDL
+            .replace("  title: String\n  done", "  title: Int\n  done")
+            .replace("  id: ID!\n", "")
+            .replace("  DONE\n", "")
+            .replace("  note: String\n", "  note: String\n  owner: ID!\n")
+            .replace("first: Int = 10", "first: Int!")
+            .replace("scalar DateTime\n", "");
+
+        let changes = diff_sdl(SDL, &new)
+            .unwrap()
+            .into_iter()
+            .map(|change| change.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            changes,
+            vec![
+                "[BREAKING] DateTime was removed",
+                "[BREAKING] required NewTodo.owner was added",
+                "[BREAKING] value DONE was removed from Status",
+                "[BREAKING] Todo.id was removed",
+                "[BREAKING] Todo.title changed type from String to Int",
+                "[BREAKING] Todo.tags.first changed type from Int to Int!",
+            ]
+        );
+    }
+
+    #[test]
+    fn safe_changes() {
+        let new = SDL
+            .replace("  title: String\n  done", "  title: String!\n  done")
+            .replace("  title: String!\n  note", "  title: String\n  note")
+            .replace("  DONE\n", "  DONE\n  ARCHIVED\n")
+            .replace("scalar DateTime\n", "scalar DateTime\n\nscalar Date\n");
+
+        let changes = diff_sdl(SDL, &new).unwrap();
+
+        assert!(changes.iter().all(|change| !change.breaking));
+        assert_eq!(changes.len(), 4);
+    }
+}
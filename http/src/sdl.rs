@@ -0,0 +1,392 @@
+use async_graphql::{ObjectType, QueryBuilder, Schema, SubscriptionType};
+use serde_json::Value;
+
+use super::error::{Error, Result};
+
+const BUILTIN_SCALARS: &[&str] = &["Boolean", "Float", "ID", "Int", "String"];
+
+const INTROSPECTION_QUERY: &str = r#"
+query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      kind
+      name
+      description
+      fields(includeDeprecated: true) {
+        name
+        description
+        args { ...InputValue }
+        type { ...TypeRef }
+        isDeprecated
+        deprecationReason
+      }
+      inputFields { ...InputValue }
+      interfaces { name }
+      enumValues(includeDeprecated: true) {
+        name
+        description
+        isDeprecated
+        deprecationReason
+      }
+      possibleTypes { name }
+    }
+  }
+}
+
+fragment InputValue on __InputValue {
+  name
+  description
+  type { ...TypeRef }
+  defaultValue
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType {
+    kind
+    name
+    ofType {
+      kind
+      name
+      ofType {
+        kind
+        name
+        ofType {
+          kind
+          name
+        }
+      }
+    }
+  }
+}
+"#;
+
+fn str_field<'a>(value: &'a Value, name: &str) -> Option<&'a str> {
+    value.get(name).and_then(|value| value.as_str())
+}
+
+fn list<'a>(value: &'a Value, name: &str) -> &'a [Value] {
+    value
+        .get(name)
+        .and_then(|value| value.as_array())
+        .map(|values| values.as_slice())
+        .unwrap_or(&[])
+}
+
+fn type_ref(value: &Value) -> std::result::Result<String, String> {
+    let of_type = || {
+        value
+            .get("ofType")
+            .ok_or_else(|| "Missing ofType".to_owned())
+            .and_then(type_ref)
+    };
+
+    match str_field(value, "kind") {
+        Some("NON_NULL") => Ok(format!("{}!", of_type()?)),
+        Some("LIST") => Ok(format!("[{}]", of_type()?)),
+        _ => str_field(value, "name")
+            .map(|name| name.to_owned())
+            .ok_or_else(|| "Missing type name".to_owned()),
+    }
+}
+
+fn description(output: &mut String, value: &Value, indent: &str) {
+    let description = match str_field(value, "description").map(str::trim) {
+        Some(description) if !description.is_empty() => description,
+        _ => return,
+    };
+
+    if description.contains('\n') {
+        output.push_str(&format!("{}\"\"\"\n", indent));
+        for line in description.lines() {
+            output.push_str(&format!(
+                "{}{}\n",
+                indent,
+                line.replace("\"\"\"", "\\\"\"\"")
+            ));
+        }
+        output.push_str(&format!("{}\"\"\"\n", indent));
+    } else {
+        output.push_str(&format!(
+            "{}\"\"\"{}\"\"\"\n",
+            indent,
+            description.replace("\"\"\"", "\\\"\"\"")
+        ));
+    }
+}
+
+fn deprecated(value: &Value) -> String {
+    if value.get("isDeprecated").and_then(|value| value.as_bool()) != Some(true) {
+        return String::new();
+    }
+
+    match str_field(value, "deprecationReason") {
+        Some(reason) => format!(" @deprecated(reason: {})", Value::from(reason)),
+        None => " @deprecated".to_owned(),
+    }
+}
+
+fn input_value(value: &Value) -> std::result::Result<String, String> {
+    let name = str_field(value, "name").ok_or("Missing input name")?;
+    let ty = type_ref(value.get("type").ok_or("Missing input type")?)?;
+
+    Ok(match str_field(value, "defaultValue") {
+        Some(default) => format!("{}: {} = {}", name, ty, default),
+        None => format!("{}: {}", name, ty),
+    })
+}
+
+fn field(value: &Value) -> std::result::Result<String, String> {
+    let name = str_field(value, "name").ok_or("Missing field name")?;
+    let ty = type_ref(value.get("type").ok_or("Missing field type")?)?;
+    let args = list(value, "args")
+        .iter()
+        .map(input_value)
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let args = if args.is_empty() {
+        String::new()
+    } else {
+        format!("({})", args.join(", "))
+    };
+
+    Ok(format!("{}{}: {}{}", name, args, ty, deprecated(value)))
+}
+
+fn names(value: &Value, name: &str) -> Vec<String> {
+    let mut names = list(value, name)
+        .iter()
+        .filter_map(|value| str_field(value, "name"))
+        .map(|name| name.to_owned())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+// Prints the `data` of the introspection query, types are sorted so snapshots diff cleanly.
+pub fn introspection_to_sdl(data: &Value) -> std::result::Result<String, String> {
+    let schema = data.get("__schema").ok_or("Missing __schema")?;
+
+    let root = |name: &str| {
+        schema
+            .get(name)
+            .and_then(|root| str_field(root, "name"))
+            .map(|name| name.to_owned())
+    };
+    let roots = [
+        ("query", root("queryType"), "Query"),
+        ("mutation", root("mutationType"), "Mutation"),
+        ("subscription", root("subscriptionType"), "Subscription"),
+    ];
+
+    let mut output = String::new();
+
+    if roots
+        .iter()
+        .any(|(_, name, default)| name.as_deref().map_or(false, |name| name != *default))
+    {
+        output.push_str("schema {\n");
+        for (operation, name, _) in roots.iter() {
+            if let Some(name) = name {
+                output.push_str(&format!("  {}: {}\n", operation, name));
+            }
+        }
+        output.push_str("}\n");
+    }
+
+    let mut types = list(schema, "types")
+        .iter()
+        .filter(|ty| {
+            str_field(ty, "name")
+                .map(|name| !name.starts_with("__") && !BUILTIN_SCALARS.contains(&name))
+                .unwrap_or(false)
+        })
+        .collect::<Vec<_>>();
+    types.sort_by(|a, b| str_field(a, "name").cmp(&str_field(b, "name")));
+
+    for ty in types {
+        let name = str_field(ty, "name").unwrap_or_default();
+
+        if !output.is_empty() {
+            output.push('\n');
+        }
+
+        description(&mut output, ty, "");
+
+        let (keyword, body) = match str_field(ty, "kind") {
+            Some("SCALAR") => {
+                output.push_str(&format!("scalar {}\n", name));
+                continue;
+            }
+            Some("UNION") => {
+                output.push_str(&format!(
+                    "union {} = {}\n",
+                    name,
+                    names(ty, "possibleTypes").join(" | ")
+                ));
+                continue;
+            }
+            Some("OBJECT") => ("type", "fields"),
+            Some("INTERFACE") => ("interface", "fields"),
+            Some("INPUT_OBJECT") => ("input", "inputFields"),
+            Some("ENUM") => ("enum", "enumValues"),
+            kind => return Err(format!("Unknown kind {:?} for {}", kind, name)),
+        };
+
+        let interfaces = names(ty, "interfaces");
+        if interfaces.is_empty() {
+            output.push_str(&format!("{} {} {{\n", keyword, name));
+        } else {
+            output.push_str(&format!(
+                "{} {} implements {} {{\n",
+                keyword,
+                name,
+                interfaces.join(" & ")
+            ));
+        }
+
+        for value in list(ty, body) {
+            description(&mut output, value, "  ");
+
+            let line = match keyword {
+                "input" => input_value(value)?,
+                "enum" => format!(
+                    "{}{}",
+                    str_field(value, "name").ok_or("Missing enum value")?,
+                    deprecated(value)
+                ),
+                _ => field(value)?,
+            };
+
+            output.push_str(&format!("  {}\n", line));
+        }
+
+        output.push_str("}\n");
+    }
+
+    Ok(output)
+}
+
+pub async fn export_sdl<Query, Mutation, Subscription>(
+    schema: &Schema<Query, Mutation, Subscription>,
+) -> Result<String>
+where
+    Query: ObjectType + Send + Sync + 'static,
+    Mutation: ObjectType + Send + Sync + 'static,
+    Subscription: SubscriptionType + Send + Sync + 'static,
+{
+    let res = QueryBuilder::new(INTROSPECTION_QUERY)
+        .execute(schema)
+        .await
+        .map_err(|e| Error::Internal(e.to_string()))?;
+
+    introspection_to_sdl(&res.data).map_err(Error::Internal)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::introspection_to_sdl;
+
+    fn named(kind: &str, name: &str) -> serde_json::Value {
+        json!({ "kind": kind, "name": name })
+    }
+
+    fn non_null(of_type: serde_json::Value) -> serde_json::Value {
+        json!({ "kind": "NON_NULL", "name": null, "ofType": of_type })
+    }
+
+    #[test]
+    fn print() {
+        let data = json!({
+            "__schema": {
+                "queryType": { "name": "Query" },
+                "mutationType": null,
+                "subscriptionType": null,
+                "types": [
+                    {
+                        "kind": "OBJECT",
+                        "name": "Query",
+                        "fields": [{
+                            "name": "todos",
+                            "args": [{
+                                "name": "first",
+                                "type": named("SCALAR", "Int"),
+                                "defaultValue": "10",
+                            }],
+                            "type": non_null(json!({
+                                "kind": "LIST",
+                                "name": null,
+                                "ofType": non_null(named("OBJECT", "Todo")),
+                            })),
+                            "isDeprecated": false,
+                        }],
+                        "interfaces": [],
+                    },
+                    {
+                        "kind": "OBJECT",
+                        "name": "Todo",
+                        "description": "A task",
+                        "fields": [
+                            {
+                                "name": "id",
+                                "args": [],
+                                "type": non_null(named("SCALAR", "ID")),
+                                "isDeprecated": false,
+                            },
+                            {
+                                "name": "done",
+                                "args": [],
+                                "type": named("SCALAR", "Boolean"),
+                                "isDeprecated": true,
+                                "deprecationReason": "Use status",
+                            },
+                            {
+                                "name": "status",
+                                "args": [],
+                                "type": non_null(named("ENUM", "Status")),
+                                "isDeprecated": false,
+                            },
+                        ],
+                        "interfaces": [{ "name": "Node" }],
+                    },
+                    {
+                        "kind": "ENUM",
+                        "name": "Status",
+                        "enumValues": [
+                            { "name": "OPEN", "isDeprecated": false },
+                            { "name": "DONE", "isDeprecated": false },
+                        ],
+                    },
+                    { "kind": "SCALAR", "name": "String" },
+                    { "kind": "OBJECT", "name": "__Type", "fields": [] },
+                ],
+            }
+        });
+
+        assert_eq!(
+            introspection_to_sdl(&data).unwrap(),
+            "type Query {
+  todos(first: Int = 10): [Todo!]!
+}
+
+enum Status {
+  OPEN
+  DONE
+}
+
+\"\"\"A task\"\"\"
+type Todo implements Node {
+  id: ID!
+  done: Boolean @deprecated(reason: \"Use status\")
+  status: Status!
+}
+"
+        );
+    }
+}
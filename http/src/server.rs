@@ -0,0 +1,413 @@
+use actix_web::dev::{Server as ActixServer, Service, ServiceRequest};
+use actix_web::error::{ErrorBadRequest, ErrorPayloadTooLarge};
+use actix_web::http::{header, Method};
+use actix_web::{middleware, web, App, HttpRequest, HttpResponse, HttpServer};
+use actix_web_actors::ws;
+use async_graphql::http::{playground_source, GQLRequest, GQLResponse};
+use async_graphql::{Data, IntoQueryBuilder, ObjectType, Schema, SubscriptionType};
+use async_graphql_actix_web::WSSubscription;
+use futures::future::{ok, Either};
+use serde_json::Value;
+use std::io;
+use std::sync::Arc;
+use timada_telemetry::{http_request_span, record_http_status, set_remote_parent};
+use tracing::Span;
+use tracing_futures::Instrument;
+
+use super::apq::{PersistedQueries, PersistedQuery};
+use super::cache::CacheControl;
+use super::context::Context;
+use super::error::Error;
+use super::reporter::{request_scope, Scoped};
+use super::request_data::{RequestData, RequestDataFactories};
+use super::rest::Rest;
+use super::upload::UploadConfig;
+use super::user::{has_valid_gateway_key, has_valid_service_key, GatewayHeaders};
+
+const HEALTH_PATH: &str = "/health";
+
+#[derive(Clone)]
+pub struct ServerConfig {
+    pub addr: String,
+    pub graphql_path: String,
+    pub subscriptions_path: Option<String>,
+    pub json_limit: usize,
+    pub multipart_limit: usize,
+    pub query_limit: usize,
+    pub shutdown_timeout: u64,
+    pub disable_signals: bool,
+    pub gateway_headers: GatewayHeaders,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            addr: "0.0.0.0:8080".to_owned(),
+            graphql_path: "/graphql".to_owned(),
+            subscriptions_path: None,
+            json_limit: 256 * 1024,
+            multipart_limit: 10 * 1024 * 1024,
+            query_limit: 16 * 1024,
+            shutdown_timeout: 30,
+            disable_signals: false,
+            gateway_headers: GatewayHeaders::from_env(),
+        }
+    }
+}
+
+type Routes = Arc<dyn Fn(&mut web::ServiceConfig) + Send + Sync>;
+
+pub struct Server<Query, Mutation, Subscription> {
+    config: ServerConfig,
+    schema: Schema<Query, Mutation, Subscription>,
+    routes: Vec<Routes>,
+    persisted_queries: PersistedQueries,
+    request_data: RequestDataFactories,
+}
+
+async fn health() -> HttpResponse {
+    HttpResponse::Ok().body("OK")
+}
+
+struct QueryLimit(usize);
+
+struct PlaygroundPath(String);
+
+#[derive(Deserialize)]
+struct GraphQLExtensions {
+    #[serde(rename = "persistedQuery")]
+    persisted_query: Option<PersistedQuery>,
+}
+
+#[derive(Deserialize)]
+struct GraphQLRequest {
+    query: Option<String>,
+    #[serde(rename = "operationName")]
+    operation_name: Option<String>,
+    variables: Option<Value>,
+    extensions: Option<GraphQLExtensions>,
+}
+
+#[derive(Deserialize)]
+struct GraphQLGetRequest {
+    query: Option<String>,
+    #[serde(rename = "operationName")]
+    operation_name: Option<String>,
+    variables: Option<String>,
+    extensions: Option<String>,
+}
+
+fn parse_json_param<T: serde::de::DeserializeOwned>(
+    name: &str,
+    value: Option<String>,
+) -> actix_web::Result<Option<T>> {
+    match value {
+        Some(value) => serde_json::from_str(&value)
+            .map(Some)
+            .map_err(|e| ErrorBadRequest(Error::BadRequest(format!("Invalid {}: {}", name, e)))),
+        None => Ok(None),
+    }
+}
+
+async fn graphql_get<Query, Mutation, Subscription>(
+    schema: web::Data<Schema<Query, Mutation, Subscription>>,
+    query_limit: web::Data<QueryLimit>,
+    persisted_queries: web::Data<PersistedQueries>,
+    request_data: web::Data<RequestDataFactories>,
+    playground_path: web::Data<PlaygroundPath>,
+    context: Context,
+    http_req: HttpRequest,
+) -> actix_web::Result<HttpResponse>
+where
+    Query: ObjectType + Send + Sync + 'static,
+    Mutation: ObjectType + Send + Sync + 'static,
+    Subscription: SubscriptionType + Send + Sync + 'static,
+{
+    let req = web::Query::<GraphQLGetRequest>::from_query(http_req.query_string())
+        .map_err(|e| ErrorBadRequest(Error::BadRequest(e.to_string())))?
+        .into_inner();
+
+    if req.query.is_none() && req.extensions.is_none() {
+        return Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(playground_source(&playground_path.0, None)));
+    }
+
+    let req = GraphQLRequest {
+        query: req.query,
+        operation_name: req.operation_name,
+        variables: parse_json_param("variables", req.variables)?,
+        extensions: parse_json_param("extensions", req.extensions)?,
+    };
+
+    execute(
+        schema,
+        query_limit,
+        persisted_queries,
+        request_data,
+        context,
+        req,
+        Method::GET,
+    )
+    .await
+}
+
+async fn graphql<Query, Mutation, Subscription>(
+    schema: web::Data<Schema<Query, Mutation, Subscription>>,
+    query_limit: web::Data<QueryLimit>,
+    persisted_queries: web::Data<PersistedQueries>,
+    request_data: web::Data<RequestDataFactories>,
+    context: Context,
+    req: web::Json<GraphQLRequest>,
+) -> actix_web::Result<HttpResponse>
+where
+    Query: ObjectType + Send + Sync + 'static,
+    Mutation: ObjectType + Send + Sync + 'static,
+    Subscription: SubscriptionType + Send + Sync + 'static,
+{
+    execute(
+        schema,
+        query_limit,
+        persisted_queries,
+        request_data,
+        context,
+        req.into_inner(),
+        Method::POST,
+    )
+    .await
+}
+
+async fn execute<Query, Mutation, Subscription>(
+    schema: web::Data<Schema<Query, Mutation, Subscription>>,
+    query_limit: web::Data<QueryLimit>,
+    persisted_queries: web::Data<PersistedQueries>,
+    request_data: web::Data<RequestDataFactories>,
+    context: Context,
+    req: GraphQLRequest,
+    method: Method,
+) -> actix_web::Result<HttpResponse>
+where
+    Query: ObjectType + Send + Sync + 'static,
+    Mutation: ObjectType + Send + Sync + 'static,
+    Subscription: SubscriptionType + Send + Sync + 'static,
+{
+    let GraphQLRequest {
+        query,
+        operation_name,
+        variables,
+        extensions,
+    } = req;
+
+    // Checked before a persisted query is registered, stored queries are all within the limit.
+    if query
+        .as_ref()
+        .map_or(false, |query| query.len() > query_limit.0)
+    {
+        return Err(ErrorPayloadTooLarge(Error::BadRequest(format!(
+            "Query exceeds {} characters",
+            query_limit.0
+        ))));
+    }
+
+    let persisted_query = extensions.and_then(|extensions| extensions.persisted_query);
+    let query = match persisted_queries.resolve(query, persisted_query.as_ref()) {
+        Ok(query) => query,
+        Err(e) => return Ok(HttpResponse::Ok().json(e.to_response())),
+    };
+
+    let req = GQLRequest {
+        query,
+        operation_name,
+        variables,
+    };
+
+    let builder = req
+        .into_query_builder()
+        .await
+        .map_err(|e| ErrorBadRequest(Error::BadRequest(e.to_string())))?;
+
+    let cache_control = CacheControl::default();
+    let is_private = context.user.is_some();
+    let request_data = request_data.build(&context);
+    let res = builder
+        .data(request_data)
+        .data(context)
+        .data(cache_control.clone())
+        .execute(&schema)
+        .await;
+
+    let mut response = HttpResponse::Ok();
+
+    if method == Method::GET {
+        if let Ok(Some(data)) = res.as_ref().map(|res| res.data.as_object()) {
+            let fields: Vec<&str> = data.keys().map(String::as_str).collect();
+
+            if let Some(value) = cache_control.header_value(&fields, is_private) {
+                response.set_header(header::CACHE_CONTROL, value);
+            }
+        }
+    }
+
+    Ok(response.json(GQLResponse(res)))
+}
+
+async fn graphql_ws<Query, Mutation, Subscription>(
+    schema: web::Data<Schema<Query, Mutation, Subscription>>,
+    context: Context,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> actix_web::Result<HttpResponse>
+where
+    Query: ObjectType + Send + Sync + 'static,
+    Mutation: ObjectType + Send + Sync + 'static,
+    Subscription: SubscriptionType + Send + Sync + 'static,
+{
+    // The connection is authenticated by the gateway headers of the upgrade request.
+    // Its data lives as long as the socket, request data is left out so no loader cache
+    // serves the events of a subscription stale entities.
+    let subscription = WSSubscription::new(schema.get_ref()).init_context_data(move |_| {
+        let mut data = Data::default();
+        data.insert(context.clone());
+        Ok(data)
+    });
+
+    ws::start_with_protocols(subscription, &["graphql-ws"], &req, payload)
+}
+
+fn is_gateway_request(req: &ServiceRequest, names: &GatewayHeaders) -> bool {
+    req.path() == HEALTH_PATH
+        || has_valid_service_key(req.headers(), names)
+        || has_valid_gateway_key(req.headers(), names)
+}
+
+impl<Query, Mutation, Subscription> Server<Query, Mutation, Subscription>
+where
+    Query: ObjectType + Send + Sync + 'static,
+    Mutation: ObjectType + Send + Sync + 'static,
+    Subscription: SubscriptionType + Send + Sync + 'static,
+{
+    pub fn new(config: ServerConfig, schema: Schema<Query, Mutation, Subscription>) -> Self {
+        Server {
+            config,
+            schema,
+            routes: Vec::new(),
+            persisted_queries: PersistedQueries::default(),
+            request_data: RequestDataFactories::default(),
+        }
+    }
+
+    pub fn persisted_queries(mut self, persisted_queries: PersistedQueries) -> Self {
+        self.persisted_queries = persisted_queries;
+        self
+    }
+
+    // Built per http request, subscriptions over WebSocket have no request data.
+    pub fn request_data<F>(mut self, factory: F) -> Self
+    where
+        F: Fn(&Context, &mut RequestData) + Send + Sync + 'static,
+    {
+        self.request_data.0.push(Arc::new(factory));
+        self
+    }
+
+    pub fn routes<F>(mut self, routes: F) -> Self
+    where
+        F: Fn(&mut web::ServiceConfig) + Send + Sync + 'static,
+    {
+        self.routes.push(Arc::new(routes));
+        self
+    }
+
+    pub fn rest(self, rest: Rest) -> Self {
+        self.routes(rest.into_routes())
+    }
+
+    pub fn run(self) -> io::Result<ActixServer> {
+        let Server {
+            config,
+            schema,
+            routes,
+            persisted_queries,
+            request_data,
+        } = self;
+        let json_limit = config.json_limit;
+        let multipart_limit = config.multipart_limit;
+        let query_limit = config.query_limit;
+        let graphql_path = config.graphql_path.clone();
+        let subscriptions_path = config.subscriptions_path.clone();
+        let gateway_headers = config.gateway_headers.clone();
+
+        let mut server = HttpServer::new(move || {
+            let routes = routes.clone();
+            let names = gateway_headers.clone();
+            let subscriptions_path = subscriptions_path.clone();
+
+            App::new()
+                .data(schema.clone())
+                .app_data(gateway_headers.clone())
+                .data(QueryLimit(query_limit))
+                .data(persisted_queries.clone())
+                .data(request_data.clone())
+                .data(PlaygroundPath(graphql_path.clone()))
+                .app_data(web::JsonConfig::default().limit(json_limit))
+                .app_data(web::PayloadConfig::default().limit(json_limit))
+                .app_data(UploadConfig::default().max_size(multipart_limit))
+                .wrap_fn(move |req, srv| {
+                    if is_gateway_request(&req, &names) {
+                        Either::Left(srv.call(req))
+                    } else {
+                        Either::Right(ok(req.into_response(HttpResponse::Unauthorized().finish())))
+                    }
+                })
+                .wrap(middleware::Compress::default())
+                .wrap(middleware::Logger::default())
+                .wrap_fn(|req, srv| {
+                    let span = http_request_span(req.method().as_str(), req.path());
+                    let header = |name: &str| {
+                        req.headers()
+                            .get(name)
+                            .and_then(|value| value.to_str().ok())
+                    };
+                    set_remote_parent(&span, header("traceparent"), header("tracestate"));
+                    let fut = span.in_scope(|| srv.call(req));
+
+                    Scoped::new(request_scope(), async move {
+                        let res = fut.await;
+                        if let Ok(res) = res.as_ref() {
+                            record_http_status(&Span::current(), res.status().as_u16());
+                        }
+                        res
+                    })
+                    .instrument(span)
+                })
+                .route(HEALTH_PATH, web::get().to(health))
+                .route(
+                    &graphql_path,
+                    web::post().to(graphql::<Query, Mutation, Subscription>),
+                )
+                .route(
+                    &graphql_path,
+                    web::get().to(graphql_get::<Query, Mutation, Subscription>),
+                )
+                .configure(move |cfg| {
+                    if let Some(path) = subscriptions_path.as_ref() {
+                        cfg.route(
+                            path,
+                            web::get().to(graphql_ws::<Query, Mutation, Subscription>),
+                        );
+                    }
+
+                    for configure in routes.iter() {
+                        configure(cfg);
+                    }
+                })
+        })
+        .shutdown_timeout(config.shutdown_timeout);
+
+        if config.disable_signals {
+            server = server.disable_signals();
+        }
+
+        Ok(server.bind(&config.addr)?.run())
+    }
+}
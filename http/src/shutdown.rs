@@ -0,0 +1,188 @@
+use actix_rt::signal::unix::{signal, SignalKind};
+use actix_rt::time::delay_for;
+use actix_web::dev::Server;
+use futures::future::select;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use timada_database::Pool;
+
+pub struct Shutdown {
+    deadline: Duration,
+    tasks: Arc<AtomicUsize>,
+    stopping: Arc<AtomicBool>,
+    pools: Vec<Pool>,
+}
+
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    tasks: Arc<AtomicUsize>,
+    stopping: Arc<AtomicBool>,
+}
+
+pub struct TaskGuard {
+    tasks: Arc<AtomicUsize>,
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.tasks.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Shutdown {
+    pub fn new(deadline: Duration) -> Self {
+        Shutdown {
+            deadline,
+            tasks: Arc::new(AtomicUsize::new(0)),
+            stopping: Arc::new(AtomicBool::new(false)),
+            pools: Vec::new(),
+        }
+    }
+
+    // r2d2 can't close a pool shared by other clones, shutdown waits until the connections
+    // of these pools are back, the process exit closes them.
+    pub fn pool(mut self, pool: Pool) -> Self {
+        self.pools.push(pool);
+        self
+    }
+
+    pub fn task(&self) -> TaskGuard {
+        self.tasks.fetch_add(1, Ordering::SeqCst);
+
+        TaskGuard {
+            tasks: self.tasks.clone(),
+        }
+    }
+
+    pub fn pending_tasks(&self) -> usize {
+        self.tasks.load(Ordering::SeqCst)
+    }
+
+    pub fn busy_connections(&self) -> u32 {
+        self.pools
+            .iter()
+            .map(|pool| {
+                let state = pool.state();
+                state.connections - state.idle_connections
+            })
+            .sum()
+    }
+
+    pub fn handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            tasks: self.tasks.clone(),
+            stopping: self.stopping.clone(),
+        }
+    }
+
+    // The server must be built with `disable_signals()` and a `shutdown_timeout()`
+    // matching the deadline, otherwise actix handles SIGTERM on its own.
+    pub async fn run(self, server: Server) -> io::Result<()> {
+        wait_for_signal().await?;
+
+        self.stopping.store(true, Ordering::SeqCst);
+        server.stop(true).await;
+        self.drain().await;
+
+        Ok(())
+    }
+
+    // Same as `run` for processes without an http server, like job workers.
+    pub async fn wait(self) -> io::Result<()> {
+        wait_for_signal().await?;
+
+        self.stopping.store(true, Ordering::SeqCst);
+        self.drain().await;
+
+        Ok(())
+    }
+
+    async fn drain(self) {
+        let started_at = Instant::now();
+
+        while (self.pending_tasks() > 0 || self.busy_connections() > 0)
+            && started_at.elapsed() < self.deadline
+        {
+            delay_for(Duration::from_millis(50)).await;
+        }
+
+        if self.pending_tasks() > 0 {
+            log::warn!(
+                "shutdown deadline reached with {} pending tasks",
+                self.pending_tasks()
+            );
+        }
+
+        if self.busy_connections() > 0 {
+            log::warn!(
+                "shutdown deadline reached with {} database connections in use",
+                self.busy_connections()
+            );
+        }
+    }
+}
+
+async fn wait_for_signal() -> io::Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+
+    select(Box::pin(terminate.recv()), Box::pin(interrupt.recv())).await;
+
+    Ok(())
+}
+
+impl ShutdownHandle {
+    pub fn task(&self) -> TaskGuard {
+        self.tasks.fetch_add(1, Ordering::SeqCst);
+
+        TaskGuard {
+            tasks: self.tasks.clone(),
+        }
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Shutdown;
+
+    #[test]
+    fn task_guard() {
+        let shutdown = Shutdown::new(Duration::from_secs(1));
+
+        let first = shutdown.task();
+        let second = shutdown.task();
+
+        assert_eq!(shutdown.pending_tasks(), 2);
+
+        drop(first);
+
+        assert_eq!(shutdown.pending_tasks(), 1);
+
+        drop(second);
+
+        assert_eq!(shutdown.pending_tasks(), 0);
+    }
+
+    #[test]
+    fn handle() {
+        let shutdown = Shutdown::new(Duration::from_secs(1));
+        let handle = shutdown.handle();
+
+        let task = handle.task();
+
+        assert_eq!(shutdown.pending_tasks(), 1);
+        assert!(!handle.is_stopping());
+
+        drop(task);
+
+        assert_eq!(shutdown.pending_tasks(), 0);
+    }
+}
@@ -0,0 +1,316 @@
+use actix_web::http::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::test::TestRequest;
+use serde_json::Value;
+#[cfg(any(test, feature = "testing"))]
+use timada_util::fake::fake;
+use uuid::Uuid;
+
+use super::context::Context;
+use super::user::{
+    gateway_keys, set_test_gateway_key, set_test_signing_key, sign_impersonator, sign_user,
+    signing_keys, GatewayHeaders, User, UserRole, UserState,
+};
+
+pub const TEST_GATEWAY_SECRET_KEY: &str = "timada";
+pub const TEST_GATEWAY_SIGNING_KEY: &str = "timada-signing";
+
+// Falls back to `TEST_GATEWAY_SECRET_KEY` without touching the environment when no key is set.
+pub fn gateway_secret_key() -> String {
+    if gateway_keys().is_empty() {
+        set_test_gateway_key(TEST_GATEWAY_SECRET_KEY);
+    }
+
+    gateway_keys()
+        .first()
+        .map(|key| key.expose().to_owned())
+        .unwrap_or_else(|| TEST_GATEWAY_SECRET_KEY.to_owned())
+}
+
+// Same as `gateway_secret_key` with `TEST_GATEWAY_SIGNING_KEY`.
+pub fn gateway_signing_key() -> String {
+    if signing_keys().is_empty() {
+        set_test_signing_key(TEST_GATEWAY_SIGNING_KEY);
+    }
+
+    signing_keys()
+        .first()
+        .map(|key| key.expose().to_owned())
+        .unwrap_or_else(|| TEST_GATEWAY_SIGNING_KEY.to_owned())
+}
+
+fn insert_header(headers: &mut HeaderMap, name: &str, value: &str) {
+    headers.insert(
+        HeaderName::from_bytes(name.as_bytes()).expect("Invalid header name"),
+        HeaderValue::from_str(value).expect("Invalid header value"),
+    );
+}
+
+fn insert_signed_user(
+    headers: &mut HeaderMap,
+    user_header: &str,
+    signature_header: &str,
+    user: &User,
+    key: &str,
+) {
+    let user = serde_json::to_string(user).expect("Failed to serialize user");
+
+    insert_header(headers, signature_header, &sign_user(&user, key));
+    insert_header(headers, user_header, &user);
+}
+
+pub fn gateway_headers(user: &User) -> HeaderMap {
+    let names = GatewayHeaders::from_env();
+    let mut headers = HeaderMap::new();
+
+    insert_header(&mut headers, &names.secret_key, &gateway_secret_key());
+    insert_signed_user(
+        &mut headers,
+        &names.user,
+        &names.user_signature,
+        user,
+        &gateway_signing_key(),
+    );
+
+    headers
+}
+
+pub fn impersonated_gateway_headers(user: &User, impersonator: &User) -> HeaderMap {
+    let names = GatewayHeaders::from_env();
+    let mut headers = gateway_headers(user);
+    let user = serde_json::to_string(user).expect("Failed to serialize user");
+    let impersonator = serde_json::to_string(impersonator).expect("Failed to serialize user");
+
+    insert_header(
+        &mut headers,
+        &names.impersonator_signature,
+        &sign_impersonator(&user, &impersonator, &gateway_signing_key()),
+    );
+    insert_header(&mut headers, &names.impersonator, &impersonator);
+
+    headers
+}
+
+pub fn request_with_headers(headers: &HeaderMap) -> TestRequest {
+    headers
+        .iter()
+        .fold(TestRequest::default(), |req, (name, value)| {
+            req.header(name.clone(), value.clone())
+        })
+}
+
+pub fn gateway_request(user: &User) -> TestRequest {
+    request_with_headers(&gateway_headers(user))
+}
+
+pub fn service_request(service_key: &str) -> TestRequest {
+    let names = GatewayHeaders::from_env();
+
+    TestRequest::default().header(names.service_key.as_str(), service_key)
+}
+
+pub struct ContextBuilder {
+    user: User,
+    impersonator: Option<User>,
+    organization_id: Option<Uuid>,
+}
+
+impl Default for ContextBuilder {
+    fn default() -> Self {
+        ContextBuilder {
+            user: User {
+                id: Uuid::new_v4(),
+                email: None,
+                username: None,
+                role: UserRole::User,
+                state: UserState::Enabled,
+                claims: Default::default(),
+            },
+            impersonator: None,
+            organization_id: None,
+        }
+    }
+}
+
+impl ContextBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Realistic id, email and username, reproducible with `FAKER_SEED`. Behind the
+    // `testing` feature so services don't ship the faker.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn fake(mut self) -> Self {
+        let (id, email, username) =
+            fake(|faker| (faker.uuid_v4(), faker.email(), faker.username()));
+
+        self.user.id = id;
+        self.user.email = Some(email);
+        self.user.username = Some(username);
+        self
+    }
+
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.user.id = id;
+        self
+    }
+
+    pub fn email(mut self, email: &str) -> Self {
+        self.user.email = Some(email.to_owned());
+        self
+    }
+
+    pub fn username(mut self, username: &str) -> Self {
+        self.user.username = Some(username.to_owned());
+        self
+    }
+
+    pub fn role<R: Into<UserRole>>(mut self, role: R) -> Self {
+        self.user.role = role.into();
+        self
+    }
+
+    pub fn state(mut self, state: UserState) -> Self {
+        self.user.state = state;
+        self
+    }
+
+    pub fn claim(mut self, name: &str, value: Value) -> Self {
+        self.user.claims.insert(name.to_owned(), value);
+        self
+    }
+
+    pub fn impersonator(mut self, impersonator: User) -> Self {
+        self.impersonator = Some(impersonator);
+        self
+    }
+
+    // Member of the organization and acting in it, as signed by the gateway.
+    pub fn organization(mut self, organization_id: Uuid) -> Self {
+        let mut organizations = self
+            .user
+            .claim::<Vec<Uuid>>("organizations")
+            .unwrap_or_default();
+        organizations.push(organization_id);
+
+        self.user.claims.insert(
+            "organizations".to_owned(),
+            serde_json::to_value(organizations).expect("Failed to serialize organizations"),
+        );
+        self.user = self.user.with_organization(organization_id);
+        self.organization_id = Some(organization_id);
+        self
+    }
+
+    pub fn build(self) -> Context {
+        Context {
+            user: Some(self.user),
+            impersonator: self.impersonator,
+            organization_id: self.organization_id,
+            ..Default::default()
+        }
+    }
+
+    pub fn request(self) -> TestRequest {
+        let headers = match self.impersonator.as_ref() {
+            Some(impersonator) => impersonated_gateway_headers(&self.user, impersonator),
+            None => gateway_headers(&self.user),
+        };
+
+        request_with_headers(&headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use std::convert::TryFrom;
+
+    use super::{
+        gateway_request, impersonated_gateway_headers, request_with_headers, ContextBuilder,
+    };
+    use crate::context::{Context, ContextError};
+    use crate::user::{User, UserRole, UserState};
+
+    #[test]
+    fn build() {
+        let id = Uuid::new_v4();
+        let context = ContextBuilder::new()
+            .id(id)
+            .role(UserRole::Admin)
+            .state(UserState::ReadOnly)
+            .build();
+
+        assert_eq!(context.user.as_ref().map(|user| user.id), Some(id));
+        assert_eq!(
+            context.ensure_is_authorized(Some(vec![UserRole::Admin])),
+            Err(ContextError::UserState(&UserState::ReadOnly))
+        );
+    }
+
+    #[test]
+    fn fake() {
+        let user = ContextBuilder::new().fake().build().user.unwrap();
+
+        assert!(user.email.unwrap().contains('@'));
+        assert!(user.username.is_some());
+        assert_ne!(
+            ContextBuilder::new().build().user.map(|user| user.id),
+            Some(user.id)
+        );
+    }
+
+    #[test]
+    fn request() {
+        let organization_id = Uuid::new_v4();
+        let builder = ContextBuilder::new()
+            .role(UserRole::Staff)
+            .organization(organization_id);
+        let expected = builder.user.clone();
+
+        let req = builder.request().to_http_request();
+        let context = Context::from_http_request(&req).unwrap();
+
+        assert_eq!(context.user, Some(expected));
+        assert_eq!(context.organization_id, Some(organization_id));
+
+        // A client supplied header is ignored.
+        let req = ContextBuilder::new()
+            .request()
+            .header("x-org", organization_id.to_string())
+            .to_http_request();
+        let context = Context::from_http_request(&req).unwrap();
+
+        assert_eq!(context.organization_id, None);
+    }
+
+    #[test]
+    fn gateway_request_user() {
+        let user = ContextBuilder::new()
+            .role(UserRole::Admin)
+            .build()
+            .user
+            .unwrap();
+
+        let req = gateway_request(&user).to_http_request();
+
+        assert_eq!(User::try_from(&req), Ok(user));
+    }
+
+    #[test]
+    fn impersonated_gateway_request() {
+        let user = ContextBuilder::new().build().user.unwrap();
+        let impersonator = ContextBuilder::new()
+            .role(UserRole::Root)
+            .build()
+            .user
+            .unwrap();
+
+        let req = request_with_headers(&impersonated_gateway_headers(&user, &impersonator))
+            .to_http_request();
+        let context = Context::from_http_request(&req).unwrap();
+
+        assert_eq!(context.user, Some(user));
+        assert_eq!(context.impersonator, Some(impersonator));
+    }
+}
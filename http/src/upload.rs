@@ -0,0 +1,151 @@
+use actix_multipart::{Field, Multipart};
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpRequest};
+use bytes::Bytes;
+use futures::future::{ready, Ready};
+use futures::StreamExt;
+
+use super::context::Context;
+use super::error::{Error, Result};
+
+#[derive(Clone)]
+pub struct UploadConfig {
+    max_size: usize,
+    content_types: Vec<String>,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        UploadConfig {
+            max_size: 10 * 1024 * 1024,
+            content_types: Vec::new(),
+        }
+    }
+}
+
+impl UploadConfig {
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_types.push(content_type.to_owned());
+        self
+    }
+
+    fn allows(&self, content_type: &str) -> bool {
+        self.content_types.is_empty()
+            || self
+                .content_types
+                .iter()
+                .any(|allowed| allowed == content_type)
+    }
+}
+
+pub struct Upload {
+    pub context: Context,
+    multipart: Multipart,
+    config: UploadConfig,
+}
+
+impl Upload {
+    pub async fn next_file(&mut self) -> Option<Result<UploadFile>> {
+        let field = match self.multipart.next().await? {
+            Ok(field) => field,
+            Err(e) => return Some(Err(Error::BadRequest(e.to_string()))),
+        };
+
+        let content_type = field.content_type().essence_str().to_owned();
+
+        if !self.config.allows(&content_type) {
+            return Some(Err(Error::UnprocessableEntity(format!(
+                "Content type {} is not allowed",
+                content_type
+            ))));
+        }
+
+        let disposition = field.content_disposition();
+        let name = disposition
+            .as_ref()
+            .and_then(|disposition| disposition.get_name())
+            .map(|name| name.to_owned());
+        let filename = disposition
+            .as_ref()
+            .and_then(|disposition| disposition.get_filename())
+            .map(|filename| filename.to_owned());
+
+        Some(Ok(UploadFile {
+            name,
+            filename,
+            content_type,
+            field,
+            size: 0,
+            max_size: self.config.max_size,
+        }))
+    }
+}
+
+impl FromRequest for Upload {
+    type Future = Ready<actix_web::Result<Upload>>;
+    type Error = actix_web::Error;
+    type Config = UploadConfig;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let context = match Context::from_http_request(req) {
+            Ok(context) => context,
+            Err(e) => return ready(Err(e)),
+        };
+
+        if let Err(e) = context.ensure_is_authorized(None) {
+            return ready(Err(Error::from(e).into()));
+        }
+
+        let config = req.app_data::<Self::Config>().cloned().unwrap_or_default();
+
+        ready(Ok(Upload {
+            context,
+            multipart: Multipart::new(req.headers(), payload.take()),
+            config,
+        }))
+    }
+}
+
+pub struct UploadFile {
+    pub name: Option<String>,
+    pub filename: Option<String>,
+    pub content_type: String,
+    field: Field,
+    size: usize,
+    max_size: usize,
+}
+
+impl UploadFile {
+    pub async fn chunk(&mut self) -> Option<Result<Bytes>> {
+        let chunk = match self.field.next().await? {
+            Ok(chunk) => chunk,
+            Err(e) => return Some(Err(Error::BadRequest(e.to_string()))),
+        };
+
+        self.size += chunk.len();
+
+        if self.size > self.max_size {
+            return Some(Err(Error::PayloadTooLarge(format!(
+                "File exceeds {} bytes",
+                self.max_size
+            ))));
+        }
+
+        Some(Ok(chunk))
+    }
+
+    pub async fn bytes(mut self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+
+        while let Some(chunk) = self.chunk().await {
+            data.extend_from_slice(&chunk?);
+        }
+
+        Ok(data)
+    }
+}
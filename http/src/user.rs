@@ -1,14 +1,44 @@
+use actix_web::http::HeaderMap;
 use actix_web::{HttpRequest, Result};
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use timada_util::config::Cached;
 use timada_util::env;
+use timada_util::secret::Secret;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "String", into = "String")]
 pub enum UserRole {
     Root,
     Admin,
     Staff,
     User,
+    Service,
+    Custom(String),
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &str {
+        match self {
+            UserRole::Root => "Root",
+            UserRole::Admin => "Admin",
+            UserRole::Staff => "Staff",
+            UserRole::User => "User",
+            UserRole::Service => "Service",
+            UserRole::Custom(role) => role.as_str(),
+        }
+    }
+
+    pub fn is(&self, name: &str) -> bool {
+        self.as_str() == name
+    }
 }
 
 impl AsRef<UserRole> for UserRole {
@@ -17,72 +47,530 @@ impl AsRef<UserRole> for UserRole {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl From<&str> for UserRole {
+    fn from(role: &str) -> UserRole {
+        match role {
+            "Root" => UserRole::Root,
+            "Admin" => UserRole::Admin,
+            "Staff" => UserRole::Staff,
+            "User" => UserRole::User,
+            "Service" => UserRole::Service,
+            _ => UserRole::Custom(role.to_owned()),
+        }
+    }
+}
+
+impl From<String> for UserRole {
+    fn from(role: String) -> UserRole {
+        UserRole::from(role.as_str())
+    }
+}
+
+impl From<UserRole> for String {
+    fn from(role: UserRole) -> String {
+        match role {
+            UserRole::Custom(role) => role,
+            _ => role.as_str().to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for UserRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum UserState {
     Enabled,
     Disabled,
     ReadOnly,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum UserStateError {
+    InvalidTransition(UserState, UserState),
+}
+
+impl UserState {
+    pub fn can_transition_to(&self, state: &UserState) -> bool {
+        matches!(
+            (self, state),
+            (UserState::Enabled, UserState::Disabled)
+                | (UserState::Enabled, UserState::ReadOnly)
+                | (UserState::ReadOnly, UserState::Enabled)
+                | (UserState::ReadOnly, UserState::Disabled)
+                | (UserState::Disabled, UserState::Enabled)
+        )
+    }
+
+    pub fn transition(&mut self, state: UserState) -> Result<(), UserStateError> {
+        if !self.can_transition_to(&state) {
+            return Err(UserStateError::InvalidTransition(self.clone(), state));
+        }
+
+        *self = state;
+
+        Ok(())
+    }
+}
+
 impl AsRef<UserState> for UserState {
     fn as_ref(&self) -> &UserState {
         self
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct User {
     pub id: Uuid,
     pub email: Option<String>,
     pub username: Option<String>,
     pub role: UserRole,
     pub state: UserState,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub claims: HashMap<String, Value>,
 }
 
-const GATEWAY_SECRET_KEY_VAR: &str = "GATEWAY_SECRET_KEY";
+pub(crate) const GATEWAY_SECRET_KEY_VAR: &str = "GATEWAY_SECRET_KEY";
+pub(crate) const GATEWAY_SIGNING_KEY_VAR: &str = "GATEWAY_SIGNING_KEY";
 const GATEWAY_SECRET_KEY_HEADER: &str = "x-gateway-key";
 const GATEWAY_USER_HEADER: &str = "x-user";
+const GATEWAY_USER_SIGNATURE_HEADER: &str = "x-user-signature";
+const GATEWAY_IMPERSONATOR_HEADER: &str = "x-impersonator";
+const GATEWAY_IMPERSONATOR_SIGNATURE_HEADER: &str = "x-impersonator-signature";
+const SERVICE_KEYS_VAR: &str = "SERVICE_KEYS";
+const ORGANIZATION_CLAIM: &str = "organization_id";
+const ORGANIZATIONS_CLAIM: &str = "organizations";
+const SERVICE_KEY_HEADER: &str = "x-service-key";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayHeaders {
+    pub secret_key: String,
+    pub user: String,
+    pub user_signature: String,
+    pub impersonator: String,
+    pub impersonator_signature: String,
+    pub service_key: String,
+}
+
+impl Default for GatewayHeaders {
+    fn default() -> Self {
+        GatewayHeaders {
+            secret_key: GATEWAY_SECRET_KEY_HEADER.to_owned(),
+            user: GATEWAY_USER_HEADER.to_owned(),
+            user_signature: GATEWAY_USER_SIGNATURE_HEADER.to_owned(),
+            impersonator: GATEWAY_IMPERSONATOR_HEADER.to_owned(),
+            impersonator_signature: GATEWAY_IMPERSONATOR_SIGNATURE_HEADER.to_owned(),
+            service_key: SERVICE_KEY_HEADER.to_owned(),
+        }
+    }
+}
+
+impl GatewayHeaders {
+    pub fn from_env() -> Self {
+        let var = |key: &str, default: &str| {
+            std::env::var(key)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_lowercase())
+                .unwrap_or_else(|| default.to_owned())
+        };
+
+        GatewayHeaders {
+            secret_key: var("GATEWAY_SECRET_KEY_HEADER", GATEWAY_SECRET_KEY_HEADER),
+            user: var("GATEWAY_USER_HEADER", GATEWAY_USER_HEADER),
+            user_signature: var(
+                "GATEWAY_USER_SIGNATURE_HEADER",
+                GATEWAY_USER_SIGNATURE_HEADER,
+            ),
+            impersonator: var("GATEWAY_IMPERSONATOR_HEADER", GATEWAY_IMPERSONATOR_HEADER),
+            impersonator_signature: var(
+                "GATEWAY_IMPERSONATOR_SIGNATURE_HEADER",
+                GATEWAY_IMPERSONATOR_SIGNATURE_HEADER,
+            ),
+            service_key: var("SERVICE_KEY_HEADER", SERVICE_KEY_HEADER),
+        }
+    }
+
+    // `from_env` loaded once, for callers without a configured `GatewayHeaders`.
+    pub fn current() -> Self {
+        GATEWAY_HEADERS.get().as_ref().clone()
+    }
+
+    pub fn from_request(req: &HttpRequest) -> Self {
+        req.app_data::<GatewayHeaders>()
+            .cloned()
+            .unwrap_or_else(GatewayHeaders::current)
+    }
+}
+
+impl User {
+    pub fn service() -> Self {
+        User {
+            id: Uuid::nil(),
+            email: None,
+            username: None,
+            role: UserRole::Service,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        }
+    }
+
+    pub fn is_service(&self) -> bool {
+        self.role == UserRole::Service
+    }
+
+    pub fn claim<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.claims
+            .get(name)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+
+    // Memberships are part of the signed user, a client can only pick one of them.
+    pub fn is_member_of(&self, organization_id: &Uuid) -> bool {
+        self.claim::<Vec<Uuid>>(ORGANIZATIONS_CLAIM)
+            .map(|organizations| organizations.contains(organization_id))
+            .unwrap_or(false)
+    }
+
+    pub fn organization_id(&self) -> Option<Uuid> {
+        self.claim(ORGANIZATION_CLAIM)
+    }
+
+    // Called by the gateway before signing the user, once `is_member_of` passed.
+    pub fn with_organization(mut self, organization_id: Uuid) -> Self {
+        self.claims.insert(
+            ORGANIZATION_CLAIM.to_owned(),
+            Value::String(organization_id.to_string()),
+        );
+        self
+    }
+
+    pub fn has_role<R: Into<UserRole>>(&self, role: R) -> bool {
+        self.role == role.into()
+    }
+
+    pub fn has_any_role<R: AsRef<UserRole>>(&self, roles: &[R]) -> bool {
+        roles.iter().any(|role| &self.role == role.as_ref())
+    }
+
+    pub fn has_credentials(req: &HttpRequest) -> bool {
+        Self::has_credentials_in(req.headers(), &GatewayHeaders::from_request(req))
+    }
+
+    pub fn has_credentials_in<H: HeaderSource + ?Sized>(
+        headers: &H,
+        names: &GatewayHeaders,
+    ) -> bool {
+        headers.contains(names.service_key.as_str()) || headers.contains(names.user.as_str())
+    }
+
+    pub fn impersonator_from(req: &HttpRequest) -> Result<Option<Self>, String> {
+        Self::impersonator_from_headers(req.headers(), &GatewayHeaders::from_request(req))
+    }
+
+    pub fn impersonator_from_headers<H: HeaderSource + ?Sized>(
+        headers: &H,
+        names: &GatewayHeaders,
+    ) -> Result<Option<Self>, String> {
+        if !headers.contains(names.impersonator.as_str()) {
+            return Ok(None);
+        }
+
+        let impersonator = headers
+            .header(names.impersonator.as_str())
+            .ok_or_else(|| "Invalid impersonator".to_owned())?;
+        let user = headers
+            .header(names.user.as_str())
+            .ok_or_else(|| "Missing user".to_owned())?;
+
+        verify_header(
+            headers,
+            &impersonation(user, impersonator),
+            &names.impersonator_signature,
+            "impersonator",
+        )?;
+
+        serde_json::from_str(impersonator)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn from_headers<H: HeaderSource + ?Sized>(
+        headers: &H,
+        names: &GatewayHeaders,
+    ) -> Result<Self, String> {
+        if headers.contains(names.service_key.as_str()) {
+            return if has_valid_service_key(headers, names) {
+                Ok(User::service())
+            } else {
+                Err("Invalid service key".to_owned())
+            };
+        }
+
+        if !has_valid_gateway_key(headers, names) {
+            return Err("Invalid gateway key".to_owned());
+        }
+
+        if !headers.contains(names.user.as_str()) {
+            return Err("Missing user".to_owned());
+        }
+
+        let user = headers
+            .header(names.user.as_str())
+            .ok_or_else(|| "Invalid user".to_owned())?;
+
+        verify_header(headers, user, &names.user_signature, "user")?;
+
+        serde_json::from_str(user).map_err(|e| e.to_string())
+    }
+}
+
+// Lets credentials be read from any transport, e.g. gRPC metadata.
+pub trait HeaderSource {
+    fn header(&self, name: &str) -> Option<&str>;
+
+    fn contains(&self, name: &str) -> bool {
+        self.header(name).is_some()
+    }
+}
+
+impl HeaderSource for HeaderMap {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.contains_key(name)
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Keyed with `GATEWAY_SIGNING_KEY`, never with the gateway key sent along the request.
+pub fn sign_user(user: &str, key: &str) -> String {
+    let mut mac = HmacSha256::new_varkey(key.as_bytes()).expect("HMAC can take key of any size");
+    mac.input(user.as_bytes());
+
+    hex::encode(mac.result().code())
+}
+
+// The impersonator is signed along the impersonated user so it can't be replayed with another one.
+pub fn sign_impersonator(user: &str, impersonator: &str, key: &str) -> String {
+    sign_user(&impersonation(user, impersonator), key)
+}
+
+// Serialized users never contain a raw newline.
+fn impersonation(user: &str, impersonator: &str) -> String {
+    format!("{}\n{}", user, impersonator)
+}
+
+fn verify_signature(value: &str, signature: &str, key: &str) -> bool {
+    let signature = match hex::decode(signature) {
+        Ok(signature) => signature,
+        _ => return false,
+    };
+
+    let mut mac = HmacSha256::new_varkey(key.as_bytes()).expect("HMAC can take key of any size");
+    mac.input(value.as_bytes());
+
+    mac.verify(&signature).is_ok()
+}
+
+// Any signing key is accepted so the gateway can rotate them.
+fn verify_header<H: HeaderSource + ?Sized>(
+    headers: &H,
+    value: &str,
+    signature_header: &str,
+    name: &str,
+) -> Result<(), String> {
+    let signature = headers
+        .header(signature_header)
+        .ok_or_else(|| format!("Missing {} signature", name))?;
+
+    let valid = signing_keys().iter().fold(false, |valid, key| {
+        verify_signature(value, signature, key.expose()) || valid
+    });
+
+    if !valid {
+        return Err(format!("Invalid {} signature", name));
+    }
+
+    Ok(())
+}
+
+fn load_service_keys() -> Vec<Secret<String>> {
+    env::var_list(SERVICE_KEYS_VAR)
+        .into_iter()
+        .map(Secret::new)
+        .collect()
+}
+
+fn load_gateway_keys() -> Vec<Secret<String>> {
+    let keys = env::parse_list(&env::var(GATEWAY_SECRET_KEY_VAR))
+        .into_iter()
+        .map(Secret::new)
+        .collect::<Vec<_>>();
+
+    if !keys.is_empty() {
+        return keys;
+    }
+
+    match TEST_GATEWAY_KEY.read() {
+        Ok(key) => key.iter().cloned().collect(),
+        Err(e) => e.into_inner().iter().cloned().collect(),
+    }
+}
+
+fn load_signing_keys() -> Vec<Secret<String>> {
+    let keys = env::parse_list(&env::var(GATEWAY_SIGNING_KEY_VAR))
+        .into_iter()
+        .map(Secret::new)
+        .collect::<Vec<_>>();
+
+    if !keys.is_empty() {
+        return keys;
+    }
+
+    match TEST_SIGNING_KEY.read() {
+        Ok(key) => key.iter().cloned().collect(),
+        Err(e) => e.into_inner().iter().cloned().collect(),
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SERVICE_KEYS: Cached<Vec<Secret<String>>> = Cached::new(load_service_keys);
+    static ref GATEWAY_KEYS: Cached<Vec<Secret<String>>> = Cached::new(load_gateway_keys);
+    static ref SIGNING_KEYS: Cached<Vec<Secret<String>>> = Cached::new(load_signing_keys);
+    static ref GATEWAY_HEADERS: Cached<GatewayHeaders> = Cached::new(GatewayHeaders::from_env);
+    // Only set by `testing`, used when `GATEWAY_SECRET_KEY` is empty.
+    static ref TEST_GATEWAY_KEY: RwLock<Option<Secret<String>>> = RwLock::new(None);
+    // Only set by `testing`, used when `GATEWAY_SIGNING_KEY` is empty.
+    static ref TEST_SIGNING_KEY: RwLock<Option<Secret<String>>> = RwLock::new(None);
+}
+
+fn set_test_key(test_key: &RwLock<Option<Secret<String>>>, key: &str) {
+    match test_key.write() {
+        Ok(mut current) => *current = Some(Secret::new(key.to_owned())),
+        Err(e) => *e.into_inner() = Some(Secret::new(key.to_owned())),
+    }
+}
+
+pub(crate) fn set_test_gateway_key(key: &str) {
+    set_test_key(&TEST_GATEWAY_KEY, key);
+    GATEWAY_KEYS.reload();
+}
+
+pub(crate) fn set_test_signing_key(key: &str) {
+    set_test_key(&TEST_SIGNING_KEY, key);
+    SIGNING_KEYS.reload();
+}
+
+pub fn reload_gateway_keys() {
+    SERVICE_KEYS.reload();
+    GATEWAY_KEYS.reload();
+    SIGNING_KEYS.reload();
+    GATEWAY_HEADERS.reload();
+}
+
+pub(crate) fn signing_keys() -> Arc<Vec<Secret<String>>> {
+    SIGNING_KEYS.get()
+}
+
+// Same as `timada_auth::constant_time_eq`, keys are also checked against every configured one
+// so the timing doesn't tell which matched.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn service_keys() -> Arc<Vec<Secret<String>>> {
+    SERVICE_KEYS.get()
+}
+
+pub fn has_valid_service_key<H: HeaderSource + ?Sized>(
+    headers: &H,
+    names: &GatewayHeaders,
+) -> bool {
+    headers
+        .header(names.service_key.as_str())
+        .map(|service_key| {
+            service_keys().iter().fold(false, |valid, key| {
+                constant_time_eq(key.expose(), service_key) || valid
+            })
+        })
+        .unwrap_or(false)
+}
+
+pub(crate) fn gateway_keys() -> Arc<Vec<Secret<String>>> {
+    GATEWAY_KEYS.get()
+}
+
+fn matching_gateway_key<H: HeaderSource + ?Sized>(
+    headers: &H,
+    names: &GatewayHeaders,
+) -> Option<Secret<String>> {
+    let gateway_key = headers.header(names.secret_key.as_str())?;
+
+    let keys = gateway_keys();
+    let (version, key) = keys
+        .iter()
+        .enumerate()
+        .fold(None, |matched, (version, key)| {
+            if constant_time_eq(key.expose(), gateway_key) {
+                matched.or(Some((version, key)))
+            } else {
+                matched
+            }
+        })?;
+
+    log::debug!("gateway key #{} matched", version);
+
+    Some(key.clone())
+}
+
+pub fn has_valid_gateway_key<H: HeaderSource + ?Sized>(
+    headers: &H,
+    names: &GatewayHeaders,
+) -> bool {
+    matching_gateway_key(headers, names).is_some()
+}
 
 impl TryFrom<&HttpRequest> for User {
     type Error = String;
 
     fn try_from(req: &HttpRequest) -> Result<Self, Self::Error> {
-        let key = env::var(GATEWAY_SECRET_KEY_VAR);
-
-        req.headers()
-            .get(GATEWAY_SECRET_KEY_HEADER)
-            .and_then(|gateway_key| gateway_key.to_str().ok())
-            .and_then(|gateway_key| {
-                if gateway_key == key {
-                    Some(gateway_key)
-                } else {
-                    None
-                }
-            })
-            .ok_or("Invalid gateway key")?;
-
-        req.headers()
-            .get(GATEWAY_USER_HEADER)
-            .ok_or_else(|| "Missing user".to_owned())
-            .and_then(|user| user.to_str().map_err(|e| e.to_string()))
-            .and_then(|user| serde_json::from_str(user).map_err(|e| e.to_string()))
+        User::from_headers(req.headers(), &GatewayHeaders::from_request(req))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use actix_web::test::TestRequest;
+    use serde_json::json;
+    use std::collections::HashMap;
     use std::convert::TryFrom;
-    use std::env;
+    use timada_util::env::test_scope;
+    use uuid::Uuid;
 
     use super::{
-        User, UserRole, UserState, GATEWAY_SECRET_KEY_HEADER, GATEWAY_SECRET_KEY_VAR,
-        GATEWAY_USER_HEADER,
+        constant_time_eq, sign_impersonator, sign_user, GatewayHeaders, HeaderSource, User,
+        UserRole, UserState, UserStateError, GATEWAY_IMPERSONATOR_HEADER,
+        GATEWAY_IMPERSONATOR_SIGNATURE_HEADER, GATEWAY_SECRET_KEY_HEADER, GATEWAY_SECRET_KEY_VAR,
+        GATEWAY_SIGNING_KEY_VAR, GATEWAY_USER_HEADER, GATEWAY_USER_SIGNATURE_HEADER,
+        SERVICE_KEYS_VAR, SERVICE_KEY_HEADER,
     };
 
     #[test]
     fn try_from_request_key() {
-        env::set_var(GATEWAY_SECRET_KEY_VAR, "timada");
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
 
         let req = TestRequest::default().to_http_request();
 
@@ -97,7 +585,9 @@ mod tests {
 
     #[test]
     fn try_from_request_missing_user() {
-        env::set_var(GATEWAY_SECRET_KEY_VAR, "timada");
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
 
         let req = TestRequest::default()
             .header(GATEWAY_SECRET_KEY_HEADER, "timada")
@@ -108,17 +598,436 @@ mod tests {
 
     #[test]
     fn try_from_request_success() {
-        env::set_var(GATEWAY_SECRET_KEY_VAR, "timada");
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
         let user = User {
             id: Default::default(),
             email: None,
             username: None,
             role: UserRole::User,
             state: UserState::ReadOnly,
+            claims: Default::default(),
+        };
+        let user_json = serde_json::to_string(&user).unwrap();
+        let req = TestRequest::default()
+            .header(GATEWAY_SECRET_KEY_HEADER, "timada")
+            .header(
+                GATEWAY_USER_SIGNATURE_HEADER,
+                sign_user(&user_json, "signing"),
+            )
+            .header(GATEWAY_USER_HEADER, user_json)
+            .to_http_request();
+
+        assert_eq!(User::try_from(&req), Ok(user));
+    }
+
+    #[test]
+    fn try_from_request_signed_with_gateway_key() {
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
+        let user = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::Root,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        let user_json = serde_json::to_string(&user).unwrap();
+        let req = TestRequest::default()
+            .header(GATEWAY_SECRET_KEY_HEADER, "timada")
+            .header(
+                GATEWAY_USER_SIGNATURE_HEADER,
+                sign_user(&user_json, "timada"),
+            )
+            .header(GATEWAY_USER_HEADER, user_json)
+            .to_http_request();
+
+        assert_eq!(
+            User::try_from(&req),
+            Err("Invalid user signature".to_owned())
+        );
+    }
+
+    #[test]
+    fn try_from_request_missing_signature() {
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
+        let user = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        let user_json = serde_json::to_string(&user).unwrap();
+        let req = TestRequest::default()
+            .header(GATEWAY_SECRET_KEY_HEADER, "timada")
+            .header(GATEWAY_USER_HEADER, user_json)
+            .to_http_request();
+
+        assert_eq!(
+            User::try_from(&req),
+            Err("Missing user signature".to_owned())
+        );
+    }
+
+    #[test]
+    fn try_from_request_tampered_user() {
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
+        let user = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        let signature = sign_user(&serde_json::to_string(&user).unwrap(), "signing");
+        let tampered = User {
+            role: UserRole::Root,
+            ..user
+        };
+        let req = TestRequest::default()
+            .header(GATEWAY_SECRET_KEY_HEADER, "timada")
+            .header(GATEWAY_USER_SIGNATURE_HEADER, signature)
+            .header(
+                GATEWAY_USER_HEADER,
+                serde_json::to_string(&tampered).unwrap(),
+            )
+            .to_http_request();
+
+        assert_eq!(
+            User::try_from(&req),
+            Err("Invalid user signature".to_owned())
+        );
+    }
+
+    #[test]
+    fn from_headers_custom_source() {
+        struct Metadata(HashMap<&'static str, String>);
+
+        impl HeaderSource for Metadata {
+            fn header(&self, name: &str) -> Option<&str> {
+                self.0.get(name).map(|value| value.as_str())
+            }
+        }
+
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
+        let user = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        let user_json = serde_json::to_string(&user).unwrap();
+
+        let mut metadata = HashMap::new();
+        metadata.insert(GATEWAY_SECRET_KEY_HEADER, "timada".to_owned());
+        metadata.insert(
+            GATEWAY_USER_SIGNATURE_HEADER,
+            sign_user(&user_json, "signing"),
+        );
+        metadata.insert(GATEWAY_USER_HEADER, user_json);
+        let metadata = Metadata(metadata);
+
+        let names = GatewayHeaders::default();
+        assert!(User::has_credentials_in(&metadata, &names));
+        assert_eq!(User::from_headers(&metadata, &names), Ok(user));
+        assert_eq!(User::impersonator_from_headers(&metadata, &names), Ok(None));
+    }
+
+    #[test]
+    fn try_from_request_invalid_service_key() {
+        let _env = test_scope().set(SERVICE_KEYS_VAR, "worker_key, cron_key");
+
+        let req = TestRequest::default()
+            .header(SERVICE_KEY_HEADER, "wrong_key")
+            .to_http_request();
+
+        assert_eq!(User::try_from(&req), Err("Invalid service key".to_owned()));
+    }
+
+    #[test]
+    fn try_from_request_service_key() {
+        let _env = test_scope().set(SERVICE_KEYS_VAR, "worker_key, cron_key");
+
+        let req = TestRequest::default()
+            .header(SERVICE_KEY_HEADER, "cron_key")
+            .to_http_request();
+
+        assert_eq!(User::try_from(&req), Ok(User::service()));
+    }
+
+    #[test]
+    fn impersonator_from_request_none() {
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
+
+        let req = TestRequest::default().to_http_request();
+
+        assert_eq!(User::impersonator_from(&req), Ok(None));
+    }
+
+    #[test]
+    fn impersonator_from_request_invalid_signature() {
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
+        let impersonator = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::Admin,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        let impersonator_json = serde_json::to_string(&impersonator).unwrap();
+        let user_json = serde_json::to_string(&User::service()).unwrap();
+        let req = TestRequest::default()
+            .header(
+                GATEWAY_IMPERSONATOR_SIGNATURE_HEADER,
+                sign_impersonator(&user_json, &impersonator_json, "wrong_key"),
+            )
+            .header(GATEWAY_IMPERSONATOR_HEADER, impersonator_json)
+            .header(GATEWAY_USER_HEADER, user_json)
+            .to_http_request();
+
+        assert_eq!(
+            User::impersonator_from(&req),
+            Err("Invalid impersonator signature".to_owned())
+        );
+    }
+
+    #[test]
+    fn impersonator_from_request_success() {
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
+        let impersonator = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::Admin,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        let impersonator_json = serde_json::to_string(&impersonator).unwrap();
+        let user_json = serde_json::to_string(&User::service()).unwrap();
+        let signature = sign_impersonator(&user_json, &impersonator_json, "signing");
+        let req = TestRequest::default()
+            .header(GATEWAY_IMPERSONATOR_SIGNATURE_HEADER, signature.as_str())
+            .header(GATEWAY_IMPERSONATOR_HEADER, impersonator_json.as_str())
+            .header(GATEWAY_USER_HEADER, user_json)
+            .to_http_request();
+
+        assert_eq!(User::impersonator_from(&req), Ok(Some(impersonator)));
+
+        // Replayed along another user.
+        let other = User {
+            id: Uuid::new_v4(),
+            ..User::service()
+        };
+        let req = TestRequest::default()
+            .header(GATEWAY_IMPERSONATOR_SIGNATURE_HEADER, signature.as_str())
+            .header(GATEWAY_IMPERSONATOR_HEADER, impersonator_json.as_str())
+            .header(GATEWAY_USER_HEADER, serde_json::to_string(&other).unwrap())
+            .to_http_request();
+
+        assert_eq!(
+            User::impersonator_from(&req),
+            Err("Invalid impersonator signature".to_owned())
+        );
+    }
+
+    #[test]
+    fn user_role_custom() {
+        assert_eq!(UserRole::from("Admin"), UserRole::Admin);
+        assert_eq!(
+            UserRole::from("Billing"),
+            UserRole::Custom("Billing".to_owned())
+        );
+        assert!(UserRole::Custom("Billing".to_owned()).is("Billing"));
+        assert_eq!(
+            serde_json::to_string(&UserRole::Custom("Billing".to_owned())).unwrap(),
+            "\"Billing\""
+        );
+        assert_eq!(
+            serde_json::from_str::<UserRole>("\"Staff\"").unwrap(),
+            UserRole::Staff
+        );
+    }
+
+    #[test]
+    fn user_has_role() {
+        let user = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::Custom("Billing".to_owned()),
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+
+        assert!(user.has_role("Billing"));
+        assert!(!user.has_role(UserRole::Admin));
+        assert!(user.has_any_role(&[UserRole::Admin, UserRole::from("Billing")]));
+    }
+
+    #[test]
+    fn user_claims() {
+        let mut user = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        user.claims.insert("plan".to_owned(), json!("pro"));
+        user.claims.insert("seats".to_owned(), json!(5));
+
+        assert_eq!(user.claim::<String>("plan"), Some("pro".to_owned()));
+        assert_eq!(user.claim::<u32>("seats"), Some(5));
+        assert_eq!(user.claim::<u32>("plan"), None);
+        assert_eq!(user.claim::<String>("locale"), None);
+
+        let user_json = serde_json::to_string(&user).unwrap();
+
+        assert_eq!(serde_json::from_str::<User>(&user_json).unwrap(), user);
+    }
+
+    #[test]
+    fn user_organizations() {
+        let member_of = Uuid::new_v4();
+        let mut user = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        user.claims
+            .insert("organizations".to_owned(), json!([member_of.to_string()]));
+
+        assert!(user.is_member_of(&member_of));
+        assert!(!user.is_member_of(&Uuid::new_v4()));
+        assert_eq!(user.organization_id(), None);
+        assert_eq!(
+            user.with_organization(member_of).organization_id(),
+            Some(member_of)
+        );
+    }
+
+    #[test]
+    fn user_state_transition() {
+        let mut state = UserState::Enabled;
+
+        assert_eq!(state.transition(UserState::ReadOnly), Ok(()));
+        assert_eq!(state, UserState::ReadOnly);
+        assert_eq!(state.transition(UserState::Disabled), Ok(()));
+        assert_eq!(state, UserState::Disabled);
+        assert_eq!(
+            state.transition(UserState::ReadOnly),
+            Err(UserStateError::InvalidTransition(
+                UserState::Disabled,
+                UserState::ReadOnly
+            ))
+        );
+        assert_eq!(state, UserState::Disabled);
+        assert_eq!(
+            state.transition(UserState::Disabled),
+            Err(UserStateError::InvalidTransition(
+                UserState::Disabled,
+                UserState::Disabled
+            ))
+        );
+        assert_eq!(state.transition(UserState::Enabled), Ok(()));
+    }
+
+    #[test]
+    fn try_from_request_custom_headers() {
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "signing");
+        let names = GatewayHeaders {
+            secret_key: "x-edge-key".to_owned(),
+            user: "x-edge-user".to_owned(),
+            user_signature: "x-edge-user-signature".to_owned(),
+            ..Default::default()
+        };
+        let user = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
+        };
+        let user_json = serde_json::to_string(&user).unwrap();
+        let req = TestRequest::default()
+            .app_data(names)
+            .header("x-edge-key", "timada")
+            .header("x-edge-user-signature", sign_user(&user_json, "signing"))
+            .header("x-edge-user", user_json)
+            .to_http_request();
+
+        assert_eq!(User::try_from(&req), Ok(user));
+    }
+
+    #[test]
+    fn compare_keys() {
+        assert!(constant_time_eq("timada", "timada"));
+        assert!(!constant_time_eq("timada", "timadb"));
+        assert!(!constant_time_eq("timada", "timada2"));
+        assert!(!constant_time_eq("", "timada"));
+    }
+
+    #[test]
+    fn gateway_headers_from_env() {
+        let req = TestRequest::default().to_http_request();
+
+        {
+            let _env = test_scope().set("GATEWAY_USER_HEADER", "X-Edge-User");
+            assert_eq!(GatewayHeaders::from_request(&req).user, "x-edge-user");
+        }
+
+        let _env = test_scope().remove("GATEWAY_USER_HEADER");
+        assert_eq!(GatewayHeaders::from_request(&req).user, GATEWAY_USER_HEADER);
+    }
+
+    #[test]
+    fn try_from_request_previous_gateway_key() {
+        let _env = test_scope()
+            .set(GATEWAY_SECRET_KEY_VAR, "rotated, timada")
+            .set(GATEWAY_SIGNING_KEY_VAR, "rotated, signing");
+        let user = User {
+            id: Default::default(),
+            email: None,
+            username: None,
+            role: UserRole::User,
+            state: UserState::Enabled,
+            claims: Default::default(),
         };
         let user_json = serde_json::to_string(&user).unwrap();
         let req = TestRequest::default()
             .header(GATEWAY_SECRET_KEY_HEADER, "timada")
+            .header(
+                GATEWAY_USER_SIGNATURE_HEADER,
+                sign_user(&user_json, "signing"),
+            )
             .header(GATEWAY_USER_HEADER, user_json)
             .to_http_request();
 
@@ -0,0 +1,68 @@
+use fluent_bundle::{FluentArgs, FluentValue};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgValue {
+    String(String),
+    Number(f64),
+}
+
+impl From<&str> for ArgValue {
+    fn from(value: &str) -> Self {
+        ArgValue::String(value.to_owned())
+    }
+}
+
+impl From<String> for ArgValue {
+    fn from(value: String) -> Self {
+        ArgValue::String(value)
+    }
+}
+
+macro_rules! number_arg {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for ArgValue {
+                fn from(value: $ty) -> Self {
+                    ArgValue::Number(value as f64)
+                }
+            }
+        )*
+    };
+}
+
+number_arg!(i32, i64, u32, u64, usize, f32, f64);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    values: BTreeMap<String, ArgValue>,
+}
+
+impl Args {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set<V: Into<ArgValue>>(mut self, name: &str, value: V) -> Self {
+        self.values.insert(name.to_owned(), value.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub(crate) fn to_fluent(&self) -> FluentArgs {
+        self.values
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    ArgValue::String(value) => FluentValue::from(value.as_str()),
+                    ArgValue::Number(value) => FluentValue::from(*value),
+                };
+
+                (name.as_str(), value)
+            })
+            .collect()
+    }
+}
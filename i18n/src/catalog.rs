@@ -0,0 +1,302 @@
+use fluent_bundle::{FluentBundle, FluentResource};
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use timada_http::Context;
+use unic_langid::LanguageIdentifier;
+
+use super::args::Args;
+use super::error::{I18nError, I18nResult};
+
+fn parse_locale(locale: &str) -> I18nResult<LanguageIdentifier> {
+    locale
+        .parse()
+        .map_err(|_| I18nError::InvalidLocale(locale.to_owned()))
+}
+
+#[derive(Clone)]
+pub struct Catalog {
+    default_locale: LanguageIdentifier,
+    resources: HashMap<LanguageIdentifier, Vec<Arc<FluentResource>>>,
+}
+
+impl Catalog {
+    pub fn new(default_locale: &str) -> I18nResult<Self> {
+        Ok(Catalog {
+            default_locale: parse_locale(default_locale)?,
+            resources: HashMap::new(),
+        })
+    }
+
+    pub fn add(&mut self, locale: &str, source: &str) -> I18nResult<()> {
+        self.add_source(locale, locale, source)
+    }
+
+    fn add_source(&mut self, locale: &str, path: &str, source: &str) -> I18nResult<()> {
+        let locale = parse_locale(locale)?;
+        let resource = FluentResource::try_new(source.to_owned()).map_err(|(_, errors)| {
+            I18nError::Catalog {
+                path: path.to_owned(),
+                message: format!("{:?}", errors),
+            }
+        })?;
+
+        self.resources
+            .entry(locale)
+            .or_default()
+            .push(Arc::new(resource));
+
+        Ok(())
+    }
+
+    // Expects `<dir>/<locale>/*.ftl`.
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> I18nResult<()> {
+        let catalog_error = |path: &Path, e: std::io::Error| I18nError::Catalog {
+            path: path.to_string_lossy().into_owned(),
+            message: e.to_string(),
+        };
+        let dir = dir.as_ref();
+
+        for locale in fs::read_dir(dir).map_err(|e| catalog_error(dir, e))? {
+            let locale = locale.map_err(|e| catalog_error(dir, e))?.path();
+
+            if !locale.is_dir() {
+                continue;
+            }
+
+            let name = locale
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            let mut files = fs::read_dir(&locale)
+                .map_err(|e| catalog_error(&locale, e))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().map(|ext| ext == "ftl").unwrap_or(false))
+                .collect::<Vec<_>>();
+            files.sort();
+
+            for file in files {
+                let source = fs::read_to_string(&file).map_err(|e| catalog_error(&file, e))?;
+                self.add_source(&name, &file.to_string_lossy(), &source)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn default_locale(&self) -> &LanguageIdentifier {
+        &self.default_locale
+    }
+
+    pub fn available_locales(&self) -> Vec<LanguageIdentifier> {
+        let mut locales = self.resources.keys().cloned().collect::<Vec<_>>();
+        locales.sort_by_key(|locale| locale.to_string());
+        locales
+    }
+
+    pub fn negotiate(&self, requested: &str) -> Vec<LanguageIdentifier> {
+        let requested = requested
+            .split(',')
+            .filter_map(|locale| locale.split(';').next())
+            .filter_map(|locale| locale.trim().parse::<LanguageIdentifier>().ok())
+            .collect::<Vec<_>>();
+        let available = self.available_locales();
+
+        negotiate_languages(
+            &requested,
+            &available,
+            Some(&self.default_locale),
+            NegotiationStrategy::Filtering,
+        )
+        .into_iter()
+        .cloned()
+        .collect()
+    }
+
+    fn format(
+        &self,
+        locale: &LanguageIdentifier,
+        key: &str,
+        args: Option<&Args>,
+    ) -> Option<String> {
+        let resources = self.resources.get(locale)?;
+        let mut bundle = FluentBundle::new(&[locale.clone()]);
+        bundle.set_use_isolating(false);
+
+        for resource in resources {
+            if let Err(errors) = bundle.add_resource(resource.clone()) {
+                log::warn!("duplicate i18n messages in {}: {:?}", locale, errors);
+            }
+        }
+
+        let (id, attribute) = match key.find('.') {
+            Some(index) => (&key[..index], Some(&key[index + 1..])),
+            None => (key, None),
+        };
+
+        let message = bundle.get_message(id)?;
+        let pattern = match attribute {
+            Some(attribute) => message.attributes.get(attribute).copied()?,
+            None => message.value?,
+        };
+
+        let fluent_args = args.map(|args| args.to_fluent());
+        let mut errors = Vec::new();
+        let value = bundle.format_pattern(pattern, fluent_args.as_ref(), &mut errors);
+
+        if !errors.is_empty() {
+            log::warn!(
+                "failed to format i18n message {} in {}: {:?}",
+                key,
+                locale,
+                errors
+            );
+        }
+
+        Some(value.into_owned())
+    }
+
+    pub fn try_translate(&self, locale: &str, key: &str, args: Option<&Args>) -> Option<String> {
+        self.negotiate(locale)
+            .iter()
+            .filter_map(|locale| self.format(locale, key, args))
+            .next()
+    }
+
+    pub fn translate(&self, locale: &str, key: &str, args: Option<&Args>) -> String {
+        self.try_translate(locale, key, args)
+            .unwrap_or_else(|| key.to_owned())
+    }
+
+    pub fn translator(&self, locale: &str) -> Translator {
+        Translator {
+            catalog: self.clone(),
+            locale: locale.to_owned(),
+        }
+    }
+
+    pub fn for_context(&self, context: &Context) -> Translator {
+        self.translator(context.locale())
+    }
+}
+
+#[derive(Clone)]
+pub struct Translator {
+    catalog: Catalog,
+    locale: String,
+}
+
+impl Translator {
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    pub fn t(&self, key: &str) -> String {
+        self.catalog.translate(&self.locale, key, None)
+    }
+
+    pub fn t_with(&self, key: &str, args: &Args) -> String {
+        self.catalog.translate(&self.locale, key, Some(args))
+    }
+
+    pub fn try_t(&self, key: &str, args: Option<&Args>) -> Option<String> {
+        self.catalog.try_translate(&self.locale, key, args)
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CATALOG: RwLock<Option<Catalog>> = RwLock::new(None);
+}
+
+pub fn set_catalog(catalog: Catalog) {
+    if let Ok(mut current) = CATALOG.write() {
+        *current = Some(catalog);
+    }
+}
+
+pub fn catalog() -> Option<Catalog> {
+    match CATALOG.read() {
+        Ok(catalog) => catalog.clone(),
+        Err(e) => e.into_inner().clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_http::Context;
+
+    use super::Catalog;
+    use crate::args::Args;
+
+    pub fn catalog() -> Catalog {
+        let mut catalog = Catalog::new("en").unwrap();
+
+        catalog
+            .add(
+                "en",
+                "hello = Hello { $name }\n\
+                 tasks = { $count ->\n    [one] One task\n   *[other] { $count } tasks\n}\n\
+                 login = Login\n    .title = Sign in\n\
+                 only-en = Only english\n",
+            )
+            .unwrap();
+        catalog
+            .add(
+                "fr",
+                "hello = Bonjour { $name }\n\
+                 tasks = { $count ->\n    [one] Une tâche\n   *[other] { $count } tâches\n}\n",
+            )
+            .unwrap();
+
+        catalog
+    }
+
+    #[test]
+    fn translate() {
+        let catalog = catalog();
+        let args = Args::new().set("name", "John");
+
+        assert_eq!(
+            catalog.translate("fr-CA", "hello", Some(&args)),
+            "Bonjour John"
+        );
+        assert_eq!(catalog.translate("de", "hello", Some(&args)), "Hello John");
+        assert_eq!(catalog.translate("fr", "only-en", None), "Only english");
+        assert_eq!(catalog.translate("en", "login.title", None), "Sign in");
+        assert_eq!(catalog.translate("en", "missing", None), "missing");
+    }
+
+    #[test]
+    fn plurals() {
+        let translator = catalog().translator("en");
+
+        assert_eq!(
+            translator.t_with("tasks", &Args::new().set("count", 1)),
+            "One task"
+        );
+        assert_eq!(
+            translator.t_with("tasks", &Args::new().set("count", 3)),
+            "3 tasks"
+        );
+
+        let translator = catalog().for_context(&Context {
+            locale: Some("fr-FR".to_owned()),
+            ..Default::default()
+        });
+        assert_eq!(
+            translator.t_with("tasks", &Args::new().set("count", 2)),
+            "2 tâches"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let mut catalog = Catalog::new("en").unwrap();
+
+        assert!(catalog.add("en", "hello = {").is_err());
+        assert!(catalog.add("not a locale!", "hello = Hello").is_err());
+    }
+}
@@ -0,0 +1,10 @@
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum I18nError {
+    #[error("Invalid locale {0}")]
+    InvalidLocale(String),
+
+    #[error("Invalid catalog {path}: {message}")]
+    Catalog { path: String, message: String },
+}
+
+pub type I18nResult<T> = Result<T, I18nError>;
@@ -0,0 +1,67 @@
+use timada_http::Error;
+
+use super::args::Args;
+use super::catalog::Translator;
+
+fn localize(translator: &Translator, kind: &str, message: &str) -> String {
+    if let Some(message) = translator.try_t(message, None) {
+        return message;
+    }
+
+    translator
+        .try_t(
+            &format!("error-{}", kind),
+            Some(&Args::new().set("message", message)),
+        )
+        .unwrap_or_else(|| message.to_owned())
+}
+
+pub fn localize_error(e: Error, translator: &Translator) -> Error {
+    match e {
+        Error::BadRequest(message) => {
+            Error::BadRequest(localize(translator, "bad-request", &message))
+        }
+        Error::Unauthorized(message) => {
+            Error::Unauthorized(localize(translator, "unauthorized", &message))
+        }
+        Error::Forbidden(message) => Error::Forbidden(localize(translator, "forbidden", &message)),
+        Error::UnprocessableEntity(message) => {
+            Error::UnprocessableEntity(localize(translator, "unprocessable-entity", &message))
+        }
+        e => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_http::Error;
+
+    use super::localize_error;
+    use crate::catalog::Catalog;
+
+    #[test]
+    fn localize() {
+        let mut catalog = Catalog::new("en").unwrap();
+        catalog
+            .add(
+                "fr",
+                "error-forbidden = Accès refusé : { $message }\n\
+                 todo-not-done = La tâche n'est pas terminée\n",
+            )
+            .unwrap();
+        let translator = catalog.translator("fr");
+
+        assert_eq!(
+            localize_error(Error::Forbidden("Forbidden".to_owned()), &translator),
+            Error::Forbidden("Accès refusé : Forbidden".to_owned())
+        );
+        assert_eq!(
+            localize_error(Error::BadRequest("todo-not-done".to_owned()), &translator),
+            Error::BadRequest("La tâche n'est pas terminée".to_owned())
+        );
+        assert_eq!(
+            localize_error(Error::NotFound, &translator),
+            Error::NotFound
+        );
+    }
+}
@@ -0,0 +1,16 @@
+#[macro_use]
+extern crate thiserror;
+
+mod args;
+mod catalog;
+mod error;
+mod error_message;
+#[cfg(feature = "templates")]
+mod templates;
+
+pub use crate::args::{ArgValue, Args};
+pub use crate::catalog::{catalog, set_catalog, Catalog, Translator};
+pub use crate::error::{I18nError, I18nResult};
+pub use crate::error_message::localize_error;
+#[cfg(feature = "templates")]
+pub use crate::templates::{register_helper, TranslateHelper};
@@ -0,0 +1,125 @@
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderError,
+};
+use serde_json::Value;
+
+use super::args::{ArgValue, Args};
+use super::catalog::Catalog;
+
+// Translations are escaped like any `{{value}}`, `t_raw` writes them as is.
+pub struct TranslateHelper {
+    catalog: Catalog,
+    raw: bool,
+}
+
+impl HelperDef for TranslateHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        registry: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let key = h
+            .param(0)
+            .and_then(|param| param.value().as_str())
+            .ok_or_else(|| RenderError::new("t: expected a message key"))?;
+
+        let locale = h
+            .hash_get("locale")
+            .and_then(|locale| locale.value().as_str())
+            .or_else(|| ctx.data().get("locale").and_then(|locale| locale.as_str()))
+            .unwrap_or_default();
+
+        let args = h.hash().iter().filter(|(name, _)| **name != "locale").fold(
+            Args::new(),
+            |args, (name, value)| match value.value() {
+                Value::String(value) => args.set(name, value.as_str()),
+                Value::Number(value) => {
+                    args.set(name, ArgValue::Number(value.as_f64().unwrap_or_default()))
+                }
+                value => args.set(name, value.to_string()),
+            },
+        );
+
+        let value = self.catalog.translate(locale, key, Some(&args));
+
+        if self.raw {
+            out.write(&value)?;
+        } else {
+            out.write(&registry.get_escape_fn()(&value))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn register_helper(registry: &mut Handlebars, catalog: Catalog) {
+    registry.register_helper(
+        "t",
+        Box::new(TranslateHelper {
+            catalog: catalog.clone(),
+            raw: false,
+        }),
+    );
+    registry.register_helper("t_raw", Box::new(TranslateHelper { catalog, raw: true }));
+}
+
+#[cfg(test)]
+mod tests {
+    use handlebars::Handlebars;
+    use serde_json::json;
+
+    use super::register_helper;
+    use crate::catalog::Catalog;
+
+    #[test]
+    fn translate_helper() {
+        let mut catalog = Catalog::new("en").unwrap();
+        catalog.add("en", "welcome = Welcome { $name }").unwrap();
+        catalog.add("fr", "welcome = Bienvenue { $name }").unwrap();
+
+        let mut registry = Handlebars::new();
+        register_helper(&mut registry, catalog);
+        registry
+            .register_template_string("welcome", "{{t \"welcome\" name=user}}")
+            .unwrap();
+
+        assert_eq!(
+            registry
+                .render("welcome", &json!({ "locale": "fr", "user": "John" }))
+                .unwrap(),
+            "Bienvenue John"
+        );
+        assert_eq!(
+            registry
+                .render("welcome", &json!({ "user": "John" }))
+                .unwrap(),
+            "Welcome John"
+        );
+    }
+
+    #[test]
+    fn translate_helper_escape() {
+        let mut catalog = Catalog::new("en").unwrap();
+        catalog.add("en", "welcome = Welcome { $name }").unwrap();
+
+        let mut registry = Handlebars::new();
+        register_helper(&mut registry, catalog);
+        registry
+            .register_template_string("welcome", "{{t \"welcome\" name=user}}")
+            .unwrap();
+        registry
+            .register_template_string("raw", "{{t_raw \"welcome\" name=user}}")
+            .unwrap();
+
+        let data = json!({ "user": "<script>" });
+
+        assert_eq!(
+            registry.render("welcome", &data).unwrap(),
+            "Welcome &lt;script&gt;"
+        );
+        assert_eq!(registry.render("raw", &data).unwrap(), "Welcome <script>");
+    }
+}
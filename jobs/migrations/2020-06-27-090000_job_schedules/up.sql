@@ -0,0 +1,7 @@
+CREATE TABLE job_schedules (
+  name VARCHAR(255) PRIMARY KEY,
+  cron VARCHAR(255) NOT NULL,
+  next_run_at TIMESTAMP NOT NULL,
+  last_run_at TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
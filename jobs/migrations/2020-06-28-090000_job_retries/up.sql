@@ -0,0 +1 @@
+ALTER TABLE jobs ADD COLUMN errors JSONB NOT NULL DEFAULT '[]';
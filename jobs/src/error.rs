@@ -0,0 +1,58 @@
+use diesel::result::Error as DieselError;
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Error)]
+pub enum JobError {
+    #[error("Unknown job type {0}")]
+    UnknownJob(String),
+
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    #[error("Database error: {0}")]
+    Database(String),
+
+    #[error("{0}")]
+    Failed(String),
+
+    #[error("{0}")]
+    Permanent(String),
+
+    // Reaped or picked up again by another worker since it was fetched.
+    #[error("Job {0} lease lost")]
+    LostLease(Uuid),
+}
+
+impl JobError {
+    pub fn failed<E: std::fmt::Display>(e: E) -> Self {
+        JobError::Failed(e.to_string())
+    }
+
+    pub fn permanent<E: std::fmt::Display>(e: E) -> Self {
+        JobError::Permanent(e.to_string())
+    }
+
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            JobError::Permanent(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<DieselError> for JobError {
+    fn from(e: DieselError) -> JobError {
+        JobError::Database(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for JobError {
+    fn from(e: serde_json::Error) -> JobError {
+        JobError::Serialization(e.to_string())
+    }
+}
+
+pub type JobResult<T> = Result<T, JobError>;
@@ -0,0 +1,471 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use timada_database::Pool;
+use uuid::Uuid;
+
+use super::error::{JobError, JobResult};
+use super::retry::RetryPolicy;
+use super::schema::jobs;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Dead,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Dead => "dead",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<JobStatus> {
+        match value {
+            "pending" => Some(JobStatus::Pending),
+            "running" => Some(JobStatus::Running),
+            "completed" => Some(JobStatus::Completed),
+            "failed" => Some(JobStatus::Failed),
+            "dead" => Some(JobStatus::Dead),
+            _ => None,
+        }
+    }
+}
+
+pub struct JobContext {
+    pub id: Uuid,
+    pub attempt: i32,
+    pub pool: Pool,
+}
+
+#[async_trait::async_trait]
+pub trait Job: Serialize + DeserializeOwned + Send + Sync + 'static {
+    const NAME: &'static str;
+    const QUEUE: &'static str = "default";
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    async fn run(&self, ctx: &JobContext) -> JobResult<()>;
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable)]
+#[table_name = "jobs"]
+pub struct JobRecord {
+    pub id: Uuid,
+    pub queue: String,
+    pub job_type: String,
+    pub payload: Value,
+    pub status: String,
+    pub attempts: i32,
+    pub run_at: NaiveDateTime,
+    pub locked_at: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub errors: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobFailure {
+    pub attempt: i32,
+    pub error: String,
+    pub failed_at: NaiveDateTime,
+}
+
+impl JobRecord {
+    pub fn status(&self) -> Option<JobStatus> {
+        JobStatus::parse(&self.status)
+    }
+
+    pub fn failures(&self) -> Vec<JobFailure> {
+        serde_json::from_value(self.errors.clone()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Insertable)]
+#[table_name = "jobs"]
+pub struct NewJob {
+    pub queue: String,
+    pub job_type: String,
+    pub payload: Value,
+    pub run_at: NaiveDateTime,
+}
+
+impl NewJob {
+    pub fn new<J: Job>(job: &J, run_at: NaiveDateTime) -> JobResult<Self> {
+        Ok(NewJob {
+            queue: J::QUEUE.to_owned(),
+            job_type: J::NAME.to_owned(),
+            payload: serde_json::to_value(job)?,
+            run_at,
+        })
+    }
+}
+
+pub fn enqueue<J: Job>(conn: &PgConnection, job: &J) -> JobResult<Uuid> {
+    enqueue_at(conn, job, Utc::now().naive_utc())
+}
+
+pub fn enqueue_at<J: Job>(conn: &PgConnection, job: &J, run_at: NaiveDateTime) -> JobResult<Uuid> {
+    Ok(diesel::insert_into(jobs::table)
+        .values(&NewJob::new(job, run_at)?)
+        .returning(jobs::id)
+        .get_result(conn)?)
+}
+
+pub fn find_job(conn: &PgConnection, id: Uuid) -> JobResult<JobRecord> {
+    jobs::table
+        .find(id)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| JobError::Database(format!("Job {} not found", id)))
+}
+
+pub(crate) fn fetch_next(
+    conn: &PgConnection,
+    queues: &[String],
+    job_types: &[String],
+) -> JobResult<Option<JobRecord>> {
+    conn.transaction(|| {
+        let now = Utc::now().naive_utc();
+
+        let job: Option<JobRecord> = jobs::table
+            .filter(jobs::queue.eq_any(queues))
+            .filter(jobs::job_type.eq_any(job_types))
+            .filter(jobs::status.eq(JobStatus::Pending.as_str()))
+            .filter(jobs::run_at.le(now))
+            .order(jobs::run_at.asc())
+            .for_update()
+            .skip_locked()
+            .first(conn)
+            .optional()?;
+
+        let job = match job {
+            Some(job) => job,
+            None => return Ok(None),
+        };
+
+        Ok(Some(
+            diesel::update(&job)
+                .set((
+                    jobs::status.eq(JobStatus::Running.as_str()),
+                    jobs::attempts.eq(jobs::attempts + 1),
+                    jobs::locked_at.eq(now),
+                    jobs::updated_at.eq(now),
+                ))
+                .get_result(conn)?,
+        ))
+    })
+}
+
+// Running jobs whose worker died before completing or failing them.
+pub(crate) fn stale_jobs(
+    conn: &PgConnection,
+    job_types: &[String],
+    locked_before: NaiveDateTime,
+) -> JobResult<Vec<JobRecord>> {
+    Ok(jobs::table
+        .filter(jobs::job_type.eq_any(job_types))
+        .filter(jobs::status.eq(JobStatus::Running.as_str()))
+        .filter(jobs::locked_at.lt(locked_before))
+        .for_update()
+        .skip_locked()
+        .load(conn)?)
+}
+
+// Only the attempt holding the lease may complete or fail a job, it is lost once the job
+// got reaped or fetched again.
+fn ensure_leased(job: &JobRecord, updated: usize) -> JobResult<()> {
+    if updated == 0 {
+        return Err(JobError::LostLease(job.id));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn complete(conn: &PgConnection, job: &JobRecord) -> JobResult<()> {
+    let updated = diesel::update(
+        jobs::table
+            .find(job.id)
+            .filter(jobs::status.eq(JobStatus::Running.as_str()))
+            .filter(jobs::attempts.eq(job.attempts)),
+    )
+    .set((
+        jobs::status.eq(JobStatus::Completed.as_str()),
+        jobs::locked_at.eq(None::<NaiveDateTime>),
+        jobs::updated_at.eq(Utc::now().naive_utc()),
+    ))
+    .execute(conn)?;
+
+    ensure_leased(job, updated)
+}
+
+fn failure_outcome(
+    job: &JobRecord,
+    policy: &RetryPolicy,
+    now: NaiveDateTime,
+) -> (JobStatus, NaiveDateTime) {
+    if !policy.should_retry(job.attempts) {
+        return (JobStatus::Dead, job.run_at);
+    }
+
+    let delay = Duration::from_std(policy.delay(job.attempts)).unwrap_or_else(|_| Duration::zero());
+
+    (JobStatus::Pending, now + delay)
+}
+
+pub(crate) fn fail(
+    conn: &PgConnection,
+    job: &JobRecord,
+    error: &str,
+    policy: &RetryPolicy,
+) -> JobResult<JobStatus> {
+    let now = Utc::now().naive_utc();
+    let (status, run_at) = failure_outcome(job, policy, now);
+
+    let mut failures = job.failures();
+    failures.push(JobFailure {
+        attempt: job.attempts,
+        error: error.to_owned(),
+        failed_at: now,
+    });
+
+    let updated = diesel::update(
+        jobs::table
+            .find(job.id)
+            .filter(jobs::status.eq(JobStatus::Running.as_str()))
+            .filter(jobs::attempts.eq(job.attempts)),
+    )
+    .set((
+        jobs::status.eq(status.as_str()),
+        jobs::run_at.eq(run_at),
+        jobs::locked_at.eq(None::<NaiveDateTime>),
+        jobs::last_error.eq(error),
+        jobs::errors.eq(serde_json::to_value(&failures)?),
+        jobs::updated_at.eq(now),
+    ))
+    .execute(conn)?;
+
+    ensure_leased(job, updated)?;
+
+    Ok(status)
+}
+
+pub fn list_dead_jobs(conn: &PgConnection) -> JobResult<Vec<JobRecord>> {
+    Ok(jobs::table
+        .filter(jobs::status.eq(JobStatus::Dead.as_str()))
+        .order(jobs::updated_at.desc())
+        .load(conn)?)
+}
+
+pub fn list_jobs(
+    conn: &PgConnection,
+    job_type: &str,
+    status: JobStatus,
+) -> JobResult<Vec<JobRecord>> {
+    Ok(jobs::table
+        .filter(jobs::job_type.eq(job_type))
+        .filter(jobs::status.eq(status.as_str()))
+        .order(jobs::updated_at.desc())
+        .load(conn)?)
+}
+
+pub fn requeue_dead_job(conn: &PgConnection, id: Uuid) -> JobResult<bool> {
+    let now = Utc::now().naive_utc();
+    let updated = diesel::update(
+        jobs::table
+            .find(id)
+            .filter(jobs::status.eq(JobStatus::Dead.as_str())),
+    )
+    .set((
+        jobs::status.eq(JobStatus::Pending.as_str()),
+        jobs::attempts.eq(0),
+        jobs::run_at.eq(now),
+        jobs::updated_at.eq(now),
+    ))
+    .execute(conn)?;
+
+    Ok(updated > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+    use timada_database::testing::test_connection;
+    use uuid::Uuid;
+
+    use super::{
+        complete, enqueue, fail, failure_outcome, fetch_next, find_job, stale_jobs, Job,
+        JobContext, JobRecord, JobStatus, NewJob,
+    };
+    use crate::error::{JobError, JobResult};
+    use crate::retry::RetryPolicy;
+
+    #[derive(Serialize, Deserialize)]
+    struct SendEmail {
+        to: String,
+    }
+
+    #[async_trait::async_trait]
+    impl Job for SendEmail {
+        const NAME: &'static str = "send_email";
+        const QUEUE: &'static str = "mailer";
+
+        async fn run(&self, _ctx: &JobContext) -> JobResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn new_job() {
+        let run_at = Utc::now().naive_utc();
+        let job = NewJob::new(
+            &SendEmail {
+                to: "john@timada.co".to_owned(),
+            },
+            run_at,
+        )
+        .unwrap();
+
+        assert_eq!(job.queue, "mailer");
+        assert_eq!(job.job_type, "send_email");
+        assert_eq!(job.payload, json!({ "to": "john@timada.co" }));
+    }
+
+    #[test]
+    fn status() {
+        for status in [
+            JobStatus::Pending,
+            JobStatus::Running,
+            JobStatus::Completed,
+            JobStatus::Failed,
+            JobStatus::Dead,
+        ]
+        .iter()
+        {
+            assert_eq!(JobStatus::parse(status.as_str()), Some(*status));
+        }
+
+        assert_eq!(JobStatus::parse("unknown"), None);
+    }
+
+    #[test]
+    fn failure() {
+        let now = Utc::now().naive_utc();
+        let mut job = JobRecord {
+            id: Uuid::new_v4(),
+            queue: "default".to_owned(),
+            job_type: "send_email".to_owned(),
+            payload: json!({}),
+            status: "running".to_owned(),
+            attempts: 1,
+            run_at: now,
+            locked_at: Some(now),
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+            errors: json!([{ "attempt": 1, "error": "timeout", "failed_at": now }]),
+        };
+        let policy = RetryPolicy::default()
+            .max_attempts(2)
+            .base_delay(std::time::Duration::from_secs(10))
+            .jitter(false);
+
+        assert_eq!(
+            failure_outcome(&job, &policy, now),
+            (JobStatus::Pending, now + Duration::seconds(10))
+        );
+
+        job.attempts = 2;
+        assert_eq!(failure_outcome(&job, &policy, now), (JobStatus::Dead, now));
+        assert_eq!(job.failures()[0].error, "timeout");
+    }
+
+    #[test]
+    fn stale() {
+        let conn = test_connection("timada_jobs_test");
+        let job_types = vec![SendEmail::NAME.to_owned()];
+        let id = enqueue(
+            &conn,
+            &SendEmail {
+                to: "john@timada.co".to_owned(),
+            },
+        )
+        .unwrap();
+
+        let job = fetch_next(&conn, &["mailer".to_owned()], &job_types)
+            .unwrap()
+            .unwrap();
+        let locked_at = job.locked_at.unwrap();
+
+        assert_eq!(job.id, id);
+        assert!(stale_jobs(&conn, &job_types, locked_at).unwrap().is_empty());
+        assert_eq!(
+            stale_jobs(&conn, &job_types, locked_at + Duration::seconds(1))
+                .unwrap()
+                .iter()
+                .map(|job| job.id)
+                .collect::<Vec<_>>(),
+            vec![id]
+        );
+    }
+
+    #[test]
+    fn lost_lease() {
+        let conn = test_connection("timada_jobs_test");
+        let queues = vec!["mailer".to_owned()];
+        let job_types = vec![SendEmail::NAME.to_owned()];
+        let id = enqueue(
+            &conn,
+            &SendEmail {
+                to: "john@timada.co".to_owned(),
+            },
+        )
+        .unwrap();
+
+        let job = fetch_next(&conn, &queues, &job_types).unwrap().unwrap();
+        let policy = RetryPolicy::default().base_delay(std::time::Duration::from_secs(0));
+
+        // Reaped while its worker was still running it.
+        for stale in stale_jobs(
+            &conn,
+            &job_types,
+            job.locked_at.unwrap() + Duration::seconds(1),
+        )
+        .unwrap()
+        .iter()
+        {
+            fail(&conn, stale, "Lost by its worker", &policy).unwrap();
+        }
+
+        assert_eq!(complete(&conn, &job), Err(JobError::LostLease(id)));
+        assert_eq!(
+            fail(&conn, &job, "timeout", &policy),
+            Err(JobError::LostLease(id))
+        );
+
+        let retried = fetch_next(&conn, &queues, &job_types).unwrap().unwrap();
+
+        assert_eq!(retried.id, id);
+        assert_eq!(complete(&conn, &job), Err(JobError::LostLease(id)));
+        assert_eq!(complete(&conn, &retried), Ok(()));
+        assert_eq!(
+            find_job(&conn, id).unwrap().status(),
+            Some(JobStatus::Completed)
+        );
+    }
+}
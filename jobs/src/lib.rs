@@ -0,0 +1,29 @@
+#[macro_use]
+extern crate diesel;
+
+#[macro_use]
+extern crate diesel_migrations;
+
+#[macro_use]
+extern crate serde;
+
+#[macro_use]
+extern crate thiserror;
+
+mod error;
+mod job;
+mod migration;
+mod retry;
+mod schedule;
+mod schema;
+mod worker;
+
+pub use crate::error::{JobError, JobResult};
+pub use crate::job::{
+    enqueue, enqueue_at, find_job, list_dead_jobs, list_jobs, requeue_dead_job, Job, JobContext,
+    JobFailure, JobRecord, JobStatus, NewJob,
+};
+pub use crate::migration::migrate;
+pub use crate::retry::RetryPolicy;
+pub use crate::schedule::{list_schedules, Schedule, ScheduleRecord, Scheduler};
+pub use crate::worker::Worker;
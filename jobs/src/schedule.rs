@@ -0,0 +1,259 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use cron::Schedule as CronSchedule;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool};
+use diesel::PgConnection;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use timada_database::Pool;
+use timada_http::ShutdownHandle;
+use tokio::time::delay_for;
+
+use super::error::{JobError, JobResult};
+use super::job::{Job, NewJob};
+use super::schema::{job_schedules, jobs};
+
+sql_function!(fn pg_try_advisory_xact_lock(key: BigInt) -> Bool);
+
+#[derive(Debug, Clone, Queryable)]
+pub struct ScheduleRecord {
+    pub name: String,
+    pub cron: String,
+    pub next_run_at: NaiveDateTime,
+    pub last_run_at: Option<NaiveDateTime>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "job_schedules"]
+struct NewSchedule<'a> {
+    name: &'a str,
+    cron: &'a str,
+    next_run_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+pub struct Schedule {
+    name: String,
+    expression: String,
+    cron: CronSchedule,
+    job: NewJob,
+}
+
+impl Schedule {
+    pub fn new<J: Job>(name: &str, expression: &str, job: &J) -> JobResult<Self> {
+        let cron = CronSchedule::from_str(expression)
+            .map_err(|e| JobError::InvalidSchedule(format!("{}: {}", expression, e)))?;
+
+        Ok(Schedule {
+            name: name.to_owned(),
+            expression: expression.to_owned(),
+            cron,
+            job: NewJob::new(job, Utc::now().naive_utc())?,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn next_run_after(&self, after: NaiveDateTime) -> JobResult<NaiveDateTime> {
+        self.cron
+            .after(&DateTime::<Utc>::from_utc(after, Utc))
+            .next()
+            .map(|next| next.naive_utc())
+            .ok_or_else(|| JobError::InvalidSchedule(format!("{} never runs", self.expression)))
+    }
+
+    pub fn next_runs(&self, count: usize) -> Vec<NaiveDateTime> {
+        self.cron
+            .upcoming(Utc)
+            .take(count)
+            .map(|next| next.naive_utc())
+            .collect()
+    }
+
+    fn lock_key(&self) -> i64 {
+        self.name
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            }) as i64
+    }
+
+    pub fn tick(&self, conn: &PgConnection, now: NaiveDateTime) -> JobResult<bool> {
+        conn.transaction(|| {
+            let locked: bool =
+                diesel::select(pg_try_advisory_xact_lock(self.lock_key())).get_result(conn)?;
+
+            if !locked {
+                return Ok(false);
+            }
+
+            diesel::insert_into(job_schedules::table)
+                .values(&NewSchedule {
+                    name: &self.name,
+                    cron: &self.expression,
+                    next_run_at: self.next_run_after(now)?,
+                })
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            let record: ScheduleRecord = job_schedules::table.find(&self.name).first(conn)?;
+
+            if record.cron != self.expression {
+                diesel::update(job_schedules::table.find(&self.name))
+                    .set((
+                        job_schedules::cron.eq(&self.expression),
+                        job_schedules::next_run_at.eq(self.next_run_after(now)?),
+                        job_schedules::updated_at.eq(now),
+                    ))
+                    .execute(conn)?;
+
+                return Ok(false);
+            }
+
+            if record.next_run_at > now {
+                return Ok(false);
+            }
+
+            diesel::insert_into(jobs::table)
+                .values(&NewJob {
+                    run_at: record.next_run_at,
+                    ..self.job.clone()
+                })
+                .execute(conn)?;
+
+            diesel::update(job_schedules::table.find(&self.name))
+                .set((
+                    job_schedules::last_run_at.eq(record.next_run_at),
+                    job_schedules::next_run_at.eq(self.next_run_after(now)?),
+                    job_schedules::updated_at.eq(now),
+                ))
+                .execute(conn)?;
+
+            Ok(true)
+        })
+    }
+}
+
+pub fn list_schedules(conn: &PgConnection) -> JobResult<Vec<ScheduleRecord>> {
+    Ok(job_schedules::table
+        .order(job_schedules::next_run_at.asc())
+        .load(conn)?)
+}
+
+pub struct Scheduler {
+    pool: Pool,
+    schedules: Vec<Schedule>,
+    interval: Duration,
+}
+
+impl Scheduler {
+    pub fn new(pool: Pool) -> Self {
+        Scheduler {
+            pool,
+            schedules: Vec::new(),
+            interval: Duration::from_secs(10),
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.schedules.push(schedule);
+        self
+    }
+
+    pub fn schedules(&self) -> &[Schedule] {
+        &self.schedules
+    }
+
+    pub async fn tick(&self) -> JobResult<usize> {
+        let pool = self.pool.clone();
+        let schedules = Arc::new(self.schedules.clone());
+
+        tokio::task::spawn_blocking(move || {
+            let conn = pool.get().map_err(|e| JobError::Database(e.to_string()))?;
+            let now = Utc::now().naive_utc();
+            let mut enqueued = 0;
+
+            for schedule in schedules.iter() {
+                match schedule.tick(&conn, now) {
+                    Ok(true) => enqueued += 1,
+                    Ok(false) => {}
+                    Err(e) => log::error!("schedule {} failed: {}", schedule.name, e),
+                }
+            }
+
+            Ok(enqueued)
+        })
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?
+    }
+
+    pub async fn run(self, shutdown: ShutdownHandle) {
+        while !shutdown.is_stopping() {
+            if let Err(e) = self.tick().await {
+                log::error!("job scheduler failed: {}", e);
+            }
+
+            delay_for(self.interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::Schedule;
+    use crate::error::{JobError, JobResult};
+    use crate::job::{Job, JobContext};
+
+    #[derive(Serialize, Deserialize)]
+    struct Cleanup;
+
+    #[async_trait::async_trait]
+    impl Job for Cleanup {
+        const NAME: &'static str = "cleanup";
+
+        async fn run(&self, _ctx: &JobContext) -> JobResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn next_run() {
+        let schedule = Schedule::new("cleanup", "0 */15 * * * *", &Cleanup).unwrap();
+        let now = NaiveDate::from_ymd(2020, 6, 27).and_hms(10, 7, 12);
+
+        assert_eq!(
+            schedule.next_run_after(now).unwrap(),
+            NaiveDate::from_ymd(2020, 6, 27).and_hms(10, 15, 0)
+        );
+        assert_eq!(schedule.next_runs(3).len(), 3);
+        assert_eq!(schedule.job.job_type, "cleanup");
+    }
+
+    #[test]
+    fn invalid() {
+        match Schedule::new("cleanup", "every minute", &Cleanup) {
+            Err(JobError::InvalidSchedule(_)) => {}
+            _ => panic!("expected an invalid schedule"),
+        }
+    }
+
+    #[test]
+    fn lock_key() {
+        let first = Schedule::new("cleanup", "0 * * * * *", &Cleanup).unwrap();
+        let second = Schedule::new("reports", "0 * * * * *", &Cleanup).unwrap();
+
+        assert_eq!(first.lock_key(), first.clone().lock_key());
+        assert_ne!(first.lock_key(), second.lock_key());
+    }
+}
@@ -0,0 +1,26 @@
+table! {
+    jobs (id) {
+        id -> Uuid,
+        queue -> Varchar,
+        job_type -> Varchar,
+        payload -> Jsonb,
+        status -> Varchar,
+        attempts -> Int4,
+        run_at -> Timestamp,
+        locked_at -> Nullable<Timestamp>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        errors -> Jsonb,
+    }
+}
+
+table! {
+    job_schedules (name) {
+        name -> Varchar,
+        cron -> Varchar,
+        next_run_at -> Timestamp,
+        last_run_at -> Nullable<Timestamp>,
+        updated_at -> Timestamp,
+    }
+}
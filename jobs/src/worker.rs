@@ -0,0 +1,311 @@
+use diesel::Connection;
+use futures::future::{join_all, BoxFuture};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use timada_database::Pool;
+use timada_http::ShutdownHandle;
+use tokio::time::delay_for;
+
+use super::error::{JobError, JobResult};
+use super::job::{complete, fail, fetch_next, stale_jobs, Job, JobContext, JobRecord, JobStatus};
+use super::retry::RetryPolicy;
+
+type Run = Arc<dyn Fn(Value, JobContext) -> BoxFuture<'static, JobResult<()>> + Send + Sync>;
+
+#[derive(Clone)]
+struct Handler {
+    run: Run,
+    retry_policy: RetryPolicy,
+}
+
+async fn blocking<T, F>(pool: Pool, f: F) -> JobResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&diesel::PgConnection) -> JobResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let conn = pool.get().map_err(|e| JobError::Database(e.to_string()))?;
+        f(&conn)
+    })
+    .await
+    .map_err(|e| JobError::Failed(e.to_string()))?
+}
+
+#[derive(Clone)]
+pub struct Worker {
+    pool: Pool,
+    queues: Vec<String>,
+    handlers: HashMap<String, Handler>,
+    concurrency: usize,
+    poll_interval: Duration,
+    lock_timeout: Duration,
+}
+
+impl Worker {
+    pub fn new(pool: Pool) -> Self {
+        Worker {
+            pool,
+            queues: Vec::new(),
+            handlers: HashMap::new(),
+            concurrency: 1,
+            poll_interval: Duration::from_secs(1),
+            lock_timeout: Duration::from_secs(5 * 60),
+        }
+    }
+
+    pub fn queue(mut self, queue: &str) -> Self {
+        self.queues.push(queue.to_owned());
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    // A job still running after this long is considered lost and failed by `reap`, it has
+    // to be longer than the slowest job.
+    pub fn lock_timeout(mut self, lock_timeout: Duration) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    pub fn register<J: Job>(mut self) -> Self {
+        if !self.queues.iter().any(|queue| queue == J::QUEUE) {
+            self.queues.push(J::QUEUE.to_owned());
+        }
+
+        let run: Run = Arc::new(|payload, ctx| {
+            Box::pin(async move {
+                let job: J = serde_json::from_value(payload)?;
+                job.run(&ctx).await
+            })
+        });
+
+        self.handlers.insert(
+            J::NAME.to_owned(),
+            Handler {
+                run,
+                retry_policy: J::retry_policy(),
+            },
+        );
+        self
+    }
+
+    pub fn job_types(&self) -> Vec<String> {
+        let mut job_types = self.handlers.keys().cloned().collect::<Vec<_>>();
+        job_types.sort();
+        job_types
+    }
+
+    pub async fn run_once(&self) -> JobResult<bool> {
+        let queues = self.queues.clone();
+        let job_types = self.job_types();
+
+        let job = blocking(self.pool.clone(), move |conn| {
+            fetch_next(conn, &queues, &job_types)
+        })
+        .await?;
+
+        match job {
+            Some(job) => {
+                self.execute(job).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub async fn reap(&self) -> JobResult<usize> {
+        let job_types = self.job_types();
+        let handlers = self.handlers.clone();
+        let locked_before = chrono::Utc::now().naive_utc()
+            - chrono::Duration::from_std(self.lock_timeout)
+                .unwrap_or_else(|_| chrono::Duration::zero());
+
+        blocking(self.pool.clone(), move |conn| {
+            conn.transaction(|| {
+                let jobs = stale_jobs(conn, &job_types, locked_before)?;
+
+                for job in jobs.iter() {
+                    let retry_policy = handlers
+                        .get(&job.job_type)
+                        .map(|handler| handler.retry_policy.clone())
+                        .unwrap_or_default();
+
+                    log::warn!("job {} ({}) lost by its worker", job.id, job.job_type);
+                    fail(conn, job, "Lost by its worker", &retry_policy)?;
+                }
+
+                Ok(jobs.len())
+            })
+        })
+        .await
+    }
+
+    async fn execute(&self, job: JobRecord) -> JobResult<()> {
+        let id = job.id;
+        let handler = self.handlers.get(&job.job_type);
+        let retry_policy = handler
+            .map(|handler| handler.retry_policy.clone())
+            .unwrap_or_default();
+
+        let res = match handler {
+            Some(handler) => {
+                let ctx = JobContext {
+                    id,
+                    attempt: job.attempts,
+                    pool: self.pool.clone(),
+                };
+
+                // Spawned so a panicking job fails like any other error.
+                match tokio::spawn((handler.run)(job.payload.clone(), ctx)).await {
+                    Ok(res) => res,
+                    Err(e) => Err(JobError::Failed(format!("Job panicked: {}", e))),
+                }
+            }
+            None => Err(JobError::UnknownJob(job.job_type.clone())),
+        };
+
+        let e = match res {
+            Ok(_) => return blocking(self.pool.clone(), move |conn| complete(conn, &job)).await,
+            Err(e) => e,
+        };
+
+        log::warn!(
+            "job {} ({}) failed on attempt {}: {}",
+            id,
+            job.job_type,
+            job.attempts,
+            e
+        );
+
+        let error = e.to_string();
+        let retry_policy = if e.is_permanent() {
+            RetryPolicy::none()
+        } else {
+            retry_policy
+        };
+        let status = blocking(self.pool.clone(), move |conn| {
+            fail(conn, &job, &error, &retry_policy)
+        })
+        .await?;
+
+        if status == JobStatus::Dead {
+            log::error!("job {} moved to dead letter", id);
+        }
+
+        Ok(())
+    }
+
+    async fn run_loop(&self, shutdown: ShutdownHandle) {
+        while !shutdown.is_stopping() {
+            let res = {
+                let _task = shutdown.task();
+                self.run_once().await
+            };
+
+            match res {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => log::error!("job worker failed: {}", e),
+            }
+
+            delay_for(self.poll_interval).await;
+        }
+    }
+
+    async fn reap_loop(&self, shutdown: ShutdownHandle) {
+        let mut next_reap = Instant::now();
+
+        while !shutdown.is_stopping() {
+            if Instant::now() >= next_reap {
+                match self.reap().await {
+                    Ok(_) => {}
+                    Err(e) => log::error!("job reaper failed: {}", e),
+                }
+
+                next_reap = Instant::now() + self.lock_timeout / 2;
+            }
+
+            delay_for(self.poll_interval).await;
+        }
+    }
+
+    pub async fn run(self, shutdown: ShutdownHandle) {
+        let worker = Arc::new(self);
+        let reaper = {
+            let worker = worker.clone();
+            let shutdown = shutdown.clone();
+
+            async move { worker.reap_loop(shutdown).await }
+        };
+
+        futures::join!(
+            reaper,
+            join_all((0..worker.concurrency).map(|_| {
+                let worker = worker.clone();
+                let shutdown = shutdown.clone();
+
+                async move { worker.run_loop(shutdown).await }
+            }))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::r2d2::{ConnectionManager, Pool};
+    use diesel::PgConnection;
+
+    use super::Worker;
+    use crate::error::JobResult;
+    use crate::job::{Job, JobContext};
+
+    #[derive(Serialize, Deserialize)]
+    struct Export;
+
+    #[async_trait::async_trait]
+    impl Job for Export {
+        const NAME: &'static str = "export";
+        const QUEUE: &'static str = "exports";
+
+        async fn run(&self, _ctx: &JobContext) -> JobResult<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Webhook;
+
+    #[async_trait::async_trait]
+    impl Job for Webhook {
+        const NAME: &'static str = "webhook";
+
+        async fn run(&self, _ctx: &JobContext) -> JobResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn register() {
+        let pool = Pool::builder().build_unchecked(ConnectionManager::<PgConnection>::new(
+            "postgres://localhost",
+        ));
+
+        let worker = Worker::new(pool)
+            .queue("default")
+            .register::<Export>()
+            .register::<Webhook>();
+
+        assert_eq!(worker.queues, vec!["default", "exports"]);
+        assert_eq!(worker.job_types(), vec!["export", "webhook"]);
+    }
+}
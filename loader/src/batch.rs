@@ -0,0 +1,81 @@
+use diesel::pg::PgConnection;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+use timada_database::Pool;
+
+use super::error::{LoaderError, LoaderResult};
+
+#[async_trait::async_trait]
+pub trait BatchFn<K, V>: Send + Sync {
+    async fn load(&self, keys: Vec<K>) -> LoaderResult<HashMap<K, V>>;
+}
+
+type DieselFn<K, V> = Arc<dyn Fn(&PgConnection, &[K]) -> LoaderResult<HashMap<K, V>> + Send + Sync>;
+
+pub struct DieselBatch<K, V> {
+    pool: Pool,
+    f: DieselFn<K, V>,
+}
+
+impl<K, V> DieselBatch<K, V> {
+    pub fn new<F>(pool: Pool, f: F) -> Self
+    where
+        F: Fn(&PgConnection, &[K]) -> LoaderResult<HashMap<K, V>> + Send + Sync + 'static,
+    {
+        DieselBatch {
+            pool,
+            f: Arc::new(f),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<K, V> BatchFn<K, V> for DieselBatch<K, V>
+where
+    K: Send + Sync + 'static,
+    V: Send + 'static,
+{
+    async fn load(&self, keys: Vec<K>) -> LoaderResult<HashMap<K, V>> {
+        let pool = self.pool.clone();
+        let f = self.f.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = pool
+                .get()
+                .map_err(|e| LoaderError::Database(e.to_string()))?;
+
+            f(&conn, &keys)
+        })
+        .await
+        .map_err(|e| LoaderError::Internal(e.to_string()))?
+    }
+}
+
+pub fn group_by<K, V, F>(rows: Vec<V>, key: F) -> HashMap<K, Vec<V>>
+where
+    K: Eq + Hash,
+    F: Fn(&V) -> K,
+{
+    let mut groups: HashMap<K, Vec<V>> = HashMap::new();
+
+    for row in rows {
+        groups.entry(key(&row)).or_default().push(row);
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::group_by;
+
+    #[test]
+    fn groups() {
+        let groups = group_by(vec![(1, "a"), (2, "b"), (1, "c")], |row| row.0);
+
+        assert_eq!(groups[&1], vec![(1, "a"), (1, "c")]);
+        assert_eq!(groups[&2], vec![(2, "b")]);
+        assert!(groups.get(&3).is_none());
+    }
+}
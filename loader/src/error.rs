@@ -0,0 +1,29 @@
+use diesel::result::Error as DieselError;
+use timada_http::Error;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum LoaderError {
+    #[error("{0}")]
+    Database(String),
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl From<DieselError> for LoaderError {
+    fn from(e: DieselError) -> LoaderError {
+        LoaderError::Database(e.to_string())
+    }
+}
+
+impl From<LoaderError> for Error {
+    fn from(e: LoaderError) -> Error {
+        match e {
+            LoaderError::Database(message) | LoaderError::Internal(message) => {
+                Error::Internal(message)
+            }
+        }
+    }
+}
+
+pub type LoaderResult<T> = Result<T, LoaderError>;
@@ -0,0 +1,22 @@
+use async_graphql::Context as GraphQLContext;
+use std::hash::Hash;
+use timada_http::{GraphQLContextExt, Result};
+
+use super::loader::BatchLoader;
+
+pub trait LoaderContextExt {
+    fn loader<K, V>(&self) -> Result<&BatchLoader<K, V>>
+    where
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+        V: Clone + Send + Sync + 'static;
+}
+
+impl LoaderContextExt for GraphQLContext<'_> {
+    fn loader<K, V>(&self) -> Result<&BatchLoader<K, V>>
+    where
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+        V: Clone + Send + Sync + 'static,
+    {
+        self.request_data::<BatchLoader<K, V>>()
+    }
+}
@@ -0,0 +1,47 @@
+#[macro_use]
+extern crate thiserror;
+
+mod batch;
+mod error;
+mod graphql;
+mod loader;
+
+pub use crate::batch::{group_by, BatchFn, DieselBatch};
+pub use crate::error::{LoaderError, LoaderResult};
+pub use crate::graphql::LoaderContextExt;
+pub use crate::loader::BatchLoader;
+
+// id_loader!(pool, todos::table, todos::id, Todo, id)
+#[macro_export]
+macro_rules! id_loader {
+    ($pool:expr, $table:expr, $column:expr, $model:ty, $field:ident) => {
+        $crate::BatchLoader::new($crate::DieselBatch::new($pool, |conn, keys| {
+            use diesel::prelude::*;
+
+            let rows = $table
+                .filter($column.eq_any(keys.to_vec()))
+                .load::<$model>(conn)?;
+
+            Ok(rows
+                .into_iter()
+                .map(|row| (row.$field.clone(), row))
+                .collect())
+        }))
+    };
+}
+
+// foreign_key_loader!(pool, todos::table, todos::project_id, Todo, project_id)
+#[macro_export]
+macro_rules! foreign_key_loader {
+    ($pool:expr, $table:expr, $column:expr, $model:ty, $field:ident) => {
+        $crate::BatchLoader::new($crate::DieselBatch::new($pool, |conn, keys| {
+            use diesel::prelude::*;
+
+            let rows = $table
+                .filter($column.eq_any(keys.to_vec()))
+                .load::<$model>(conn)?;
+
+            Ok($crate::group_by(rows, |row: &$model| row.$field.clone()))
+        }))
+    };
+}
@@ -0,0 +1,235 @@
+use futures::lock::Mutex as AsyncMutex;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use super::batch::BatchFn;
+use super::error::{LoaderError, LoaderResult};
+
+// Missing keys are cached as `None` so they are not queried twice in the same request.
+struct State<K, V> {
+    cache: HashMap<K, Option<V>>,
+    pending: HashSet<K>,
+}
+
+pub struct BatchLoader<K, V> {
+    batch: Arc<dyn BatchFn<K, V>>,
+    state: Mutex<State<K, V>>,
+    lock: AsyncMutex<()>,
+}
+
+impl<K, V> BatchLoader<K, V>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub fn new<B: BatchFn<K, V> + 'static>(batch: B) -> Self {
+        BatchLoader {
+            batch: Arc::new(batch),
+            state: Mutex::new(State {
+                cache: HashMap::new(),
+                pending: HashSet::new(),
+            }),
+            lock: AsyncMutex::new(()),
+        }
+    }
+
+    pub async fn load(&self, key: K) -> LoaderResult<Option<V>> {
+        let mut values = self.load_many(&[key.clone()]).await?;
+
+        Ok(values.remove(&key))
+    }
+
+    pub async fn load_many(&self, keys: &[K]) -> LoaderResult<HashMap<K, V>> {
+        if let Some(values) = self.cached(keys)? {
+            return Ok(values);
+        }
+
+        {
+            let mut state = self.state()?;
+            for key in keys {
+                if !state.cache.contains_key(key) {
+                    state.pending.insert(key.clone());
+                }
+            }
+        }
+
+        // Let sibling resolvers queue their keys before the batch is dispatched.
+        YieldNow(false).await;
+
+        let _guard = self.lock.lock().await;
+
+        if let Some(values) = self.cached(keys)? {
+            return Ok(values);
+        }
+
+        let batch = {
+            let mut state = self.state()?;
+            let mut batch = state.pending.drain().collect::<HashSet<_>>();
+            for key in keys {
+                if !state.cache.contains_key(key) {
+                    batch.insert(key.clone());
+                }
+            }
+            batch.into_iter().collect::<Vec<_>>()
+        };
+
+        let mut values = self.batch.load(batch.clone()).await?;
+
+        {
+            let mut state = self.state()?;
+            for key in batch {
+                let value = values.remove(&key);
+                state.cache.insert(key, value);
+            }
+        }
+
+        Ok(self.cached(keys)?.unwrap_or_default())
+    }
+
+    pub fn prime(&self, key: K, value: V) -> LoaderResult<()> {
+        self.state()?.cache.insert(key, Some(value));
+
+        Ok(())
+    }
+
+    pub fn clear(&self, key: &K) -> LoaderResult<()> {
+        self.state()?.cache.remove(key);
+
+        Ok(())
+    }
+
+    pub fn clear_all(&self) -> LoaderResult<()> {
+        self.state()?.cache.clear();
+
+        Ok(())
+    }
+
+    fn cached(&self, keys: &[K]) -> LoaderResult<Option<HashMap<K, V>>> {
+        let state = self.state()?;
+        let mut values = HashMap::new();
+
+        for key in keys {
+            match state.cache.get(key) {
+                Some(Some(value)) => {
+                    values.insert(key.clone(), value.clone());
+                }
+                Some(None) => {}
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(values))
+    }
+
+    fn state(&self) -> LoaderResult<std::sync::MutexGuard<'_, State<K, V>>> {
+        self.state
+            .lock()
+            .map_err(|e| LoaderError::Internal(e.to_string()))
+    }
+}
+
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+
+        self.0 = true;
+        cx.waker().wake_by_ref();
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::BatchLoader;
+    use crate::batch::BatchFn;
+    use crate::error::{LoaderError, LoaderResult};
+
+    #[derive(Clone, Default)]
+    struct Squares {
+        calls: Arc<Mutex<Vec<Vec<i32>>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl BatchFn<i32, i32> for Squares {
+        async fn load(&self, mut keys: Vec<i32>) -> LoaderResult<HashMap<i32, i32>> {
+            keys.sort();
+            self.calls.lock().unwrap().push(keys.clone());
+
+            if keys.contains(&-1) {
+                return Err(LoaderError::Database("boom".to_owned()));
+            }
+
+            Ok(keys
+                .into_iter()
+                .filter(|key| *key < 10)
+                .map(|key| (key, key * key))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn batches_concurrent_loads() {
+        let squares = Squares::default();
+        let loader = BatchLoader::new(squares.clone());
+
+        let (a, b, c) =
+            block_on(async { futures::join!(loader.load(2), loader.load(3), loader.load(2)) });
+
+        assert_eq!(a, Ok(Some(4)));
+        assert_eq!(b, Ok(Some(9)));
+        assert_eq!(c, Ok(Some(4)));
+        assert_eq!(*squares.calls.lock().unwrap(), vec![vec![2, 3]]);
+    }
+
+    #[test]
+    fn caches_values_and_misses() {
+        let squares = Squares::default();
+        let loader = BatchLoader::new(squares.clone());
+
+        assert_eq!(block_on(loader.load(12)), Ok(None));
+        assert_eq!(block_on(loader.load(12)), Ok(None));
+
+        let values = block_on(loader.load_many(&[1, 2, 12])).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[&2], 4);
+
+        loader.prime(5, 0).unwrap();
+        assert_eq!(block_on(loader.load(5)), Ok(Some(0)));
+
+        loader.clear(&5).unwrap();
+        assert_eq!(block_on(loader.load(5)), Ok(Some(25)));
+
+        assert_eq!(
+            *squares.calls.lock().unwrap(),
+            vec![vec![12], vec![1, 2], vec![5]]
+        );
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let squares = Squares::default();
+        let loader = BatchLoader::new(squares.clone());
+
+        assert_eq!(
+            block_on(loader.load(-1)),
+            Err(LoaderError::Database("boom".to_owned()))
+        );
+        assert_eq!(block_on(loader.load(3)), Ok(Some(9)));
+        assert_eq!(squares.calls.lock().unwrap().len(), 2);
+    }
+}
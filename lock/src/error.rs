@@ -0,0 +1,36 @@
+use diesel::result::Error as DieselError;
+
+#[derive(Debug, PartialEq, Error)]
+pub enum LockError {
+    #[error("Lock {0} was lost")]
+    Lost(String),
+
+    #[error("Database error: {0}")]
+    Database(String),
+
+    #[error("Redis error: {0}")]
+    Redis(String),
+
+    #[error("Pool error: {0}")]
+    Pool(String),
+}
+
+impl From<DieselError> for LockError {
+    fn from(e: DieselError) -> LockError {
+        LockError::Database(e.to_string())
+    }
+}
+
+impl From<::redis::RedisError> for LockError {
+    fn from(e: ::redis::RedisError) -> LockError {
+        LockError::Redis(e.to_string())
+    }
+}
+
+impl From<r2d2::Error> for LockError {
+    fn from(e: r2d2::Error) -> LockError {
+        LockError::Pool(e.to_string())
+    }
+}
+
+pub type LockResult<T> = Result<T, LockError>;
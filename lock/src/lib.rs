@@ -0,0 +1,14 @@
+#[macro_use]
+extern crate thiserror;
+
+mod error;
+mod lock;
+mod memory;
+mod postgres;
+mod redis;
+
+pub use crate::error::{LockError, LockResult};
+pub use crate::lock::{with_lock, DistributedLock, LeaseState};
+pub use crate::memory::{MemoryLease, MemoryLock};
+pub use crate::postgres::{PgLease, PgLock};
+pub use crate::redis::{RedisLease, RedisLock};
@@ -0,0 +1,147 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::error::{LockError, LockResult};
+
+pub trait DistributedLock {
+    type Lease;
+
+    // `None` when the lock is held by another process.
+    fn try_acquire(&self, name: &str, ttl: Duration) -> LockResult<Option<Self::Lease>>;
+
+    // Extends the lease by `ttl`, `LockError::Lost` once it expired or was taken over.
+    fn renew(&self, lease: &mut Self::Lease, ttl: Duration) -> LockResult<()>;
+
+    fn release(&self, lease: Self::Lease) -> LockResult<()>;
+}
+
+#[derive(Clone, Default)]
+pub struct LeaseState {
+    lost: Arc<AtomicBool>,
+}
+
+impl LeaseState {
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+}
+
+// Runs `f` while holding the lock, the lease is renewed every third of `ttl` from a
+// background thread. Returns `None` without running `f` when another process holds the
+// lock, long running work should check `LeaseState::is_lost` and stop early.
+pub fn with_lock<L, T, F>(lock: &L, name: &str, ttl: Duration, f: F) -> LockResult<Option<T>>
+where
+    L: DistributedLock + Clone + Send + 'static,
+    L::Lease: Send + 'static,
+    F: FnOnce(&LeaseState) -> T,
+{
+    let lease = match lock.try_acquire(name, ttl)? {
+        Some(lease) => Arc::new(Mutex::new(lease)),
+        None => return Ok(None),
+    };
+
+    let state = LeaseState::default();
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    let renewal = {
+        let lock = lock.clone();
+        let lease = lease.clone();
+        let state = state.clone();
+        let name = name.to_owned();
+
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(ttl / 3) {
+                let mut lease = match lease.lock() {
+                    Ok(lease) => lease,
+                    Err(_) => break,
+                };
+
+                if let Err(e) = lock.renew(&mut lease, ttl) {
+                    log::error!("Failed to renew lock {}: {}", name, e);
+                    state.lost.store(true, Ordering::SeqCst);
+                    break;
+                }
+            }
+        })
+    };
+
+    let value = f(&state);
+
+    drop(stop);
+    let _ = renewal.join();
+
+    let lease = Arc::try_unwrap(lease)
+        .ok()
+        .and_then(|lease| lease.into_inner().ok())
+        .ok_or_else(|| LockError::Lost(name.to_owned()))?;
+
+    if state.is_lost() {
+        return Err(LockError::Lost(name.to_owned()));
+    }
+
+    lock.release(lease)?;
+
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{with_lock, DistributedLock};
+    use crate::error::LockError;
+    use crate::memory::MemoryLock;
+
+    #[test]
+    fn exclusive() {
+        let lock = MemoryLock::new();
+        let ttl = Duration::from_secs(10);
+
+        let res = with_lock(&lock, "migrations", ttl, |_| {
+            assert_eq!(with_lock(&lock, "migrations", ttl, |_| "nested"), Ok(None));
+            assert_eq!(
+                with_lock(&lock, "scheduler", ttl, |_| "other"),
+                Ok(Some("other"))
+            );
+
+            "done"
+        });
+
+        assert_eq!(res, Ok(Some("done")));
+        assert!(lock.try_acquire("migrations", ttl).unwrap().is_some());
+    }
+
+    #[test]
+    fn renewal() {
+        let lock = MemoryLock::new();
+        let ttl = Duration::from_millis(60);
+
+        let res = with_lock(&lock, "scheduler", ttl, |state| {
+            thread::sleep(ttl * 3);
+
+            assert!(!state.is_lost());
+            lock.try_acquire("scheduler", ttl).unwrap().is_none()
+        });
+
+        assert_eq!(res, Ok(Some(true)));
+    }
+
+    #[test]
+    fn lost() {
+        let lock = MemoryLock::new();
+        let ttl = Duration::from_millis(60);
+
+        let res = with_lock(&lock, "scheduler", ttl, |state| {
+            lock.expire("scheduler");
+            thread::sleep(ttl);
+
+            state.is_lost()
+        });
+
+        assert_eq!(res, Err(LockError::Lost("scheduler".to_owned())));
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::error::{LockError, LockResult};
+use super::lock::DistributedLock;
+
+type Leases = HashMap<String, (Uuid, Instant)>;
+
+// Single process only, for tests and local development.
+#[derive(Clone, Default)]
+pub struct MemoryLock {
+    leases: Arc<Mutex<Leases>>,
+}
+
+pub struct MemoryLease {
+    name: String,
+    token: Uuid,
+}
+
+impl MemoryLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn leases(&self) -> LockResult<MutexGuard<'_, Leases>> {
+        self.leases
+            .lock()
+            .map_err(|e| LockError::Pool(e.to_string()))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn expire(&self, name: &str) {
+        self.leases().unwrap().remove(name);
+    }
+}
+
+impl DistributedLock for MemoryLock {
+    type Lease = MemoryLease;
+
+    fn try_acquire(&self, name: &str, ttl: Duration) -> LockResult<Option<MemoryLease>> {
+        let mut leases = self.leases()?;
+        let now = Instant::now();
+
+        if let Some((_, expires_at)) = leases.get(name) {
+            if *expires_at > now {
+                return Ok(None);
+            }
+        }
+
+        let token = Uuid::new_v4();
+        leases.insert(name.to_owned(), (token, now + ttl));
+
+        Ok(Some(MemoryLease {
+            name: name.to_owned(),
+            token,
+        }))
+    }
+
+    fn renew(&self, lease: &mut MemoryLease, ttl: Duration) -> LockResult<()> {
+        let mut leases = self.leases()?;
+        let now = Instant::now();
+
+        match leases.get_mut(&lease.name) {
+            Some((token, expires_at)) if *token == lease.token && *expires_at > now => {
+                *expires_at = now + ttl;
+                Ok(())
+            }
+            _ => Err(LockError::Lost(lease.name.clone())),
+        }
+    }
+
+    fn release(&self, lease: MemoryLease) -> LockResult<()> {
+        let mut leases = self.leases()?;
+
+        if leases.get(&lease.name).map(|(token, _)| *token) == Some(lease.token) {
+            leases.remove(&lease.name);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool};
+use std::time::Duration;
+use timada_database::{Pool, PooledConnection};
+
+use super::error::{LockError, LockResult};
+use super::lock::DistributedLock;
+
+sql_function!(fn pg_try_advisory_lock(key: BigInt) -> Bool);
+sql_function!(fn pg_advisory_unlock(key: BigInt) -> Bool);
+
+// Session level advisory locks, held as long as the connection of the lease is alive so
+// `ttl` is unused and renewing only checks the connection.
+#[derive(Clone)]
+pub struct PgLock {
+    pool: Pool,
+}
+
+pub struct PgLease {
+    name: String,
+    key: i64,
+    conn: Option<PooledConnection>,
+}
+
+fn lock_key(name: &str) -> i64 {
+    format!("lock:{}", name)
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        }) as i64
+}
+
+impl PgLock {
+    pub fn new(pool: Pool) -> Self {
+        PgLock { pool }
+    }
+}
+
+impl PgLease {
+    fn conn(&self) -> LockResult<&PooledConnection> {
+        self.conn
+            .as_ref()
+            .ok_or_else(|| LockError::Lost(self.name.clone()))
+    }
+
+    fn unlock(&mut self) -> LockResult<()> {
+        let conn = match self.conn.take() {
+            Some(conn) => conn,
+            None => return Ok(()),
+        };
+
+        let unlocked: bool = diesel::select(pg_advisory_unlock(self.key)).get_result(&conn)?;
+
+        if !unlocked {
+            return Err(LockError::Lost(self.name.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for PgLease {
+    fn drop(&mut self) {
+        if let Err(e) = self.unlock() {
+            log::error!("Failed to release lock {}: {}", self.name, e);
+        }
+    }
+}
+
+impl DistributedLock for PgLock {
+    type Lease = PgLease;
+
+    fn try_acquire(&self, name: &str, _ttl: Duration) -> LockResult<Option<PgLease>> {
+        let conn = self.pool.get()?;
+        let key = lock_key(name);
+        let locked: bool = diesel::select(pg_try_advisory_lock(key)).get_result(&conn)?;
+
+        if !locked {
+            return Ok(None);
+        }
+
+        Ok(Some(PgLease {
+            name: name.to_owned(),
+            key,
+            conn: Some(conn),
+        }))
+    }
+
+    fn renew(&self, lease: &mut PgLease, _ttl: Duration) -> LockResult<()> {
+        diesel::select(diesel::dsl::sql::<Bool>("true"))
+            .execute(lease.conn()?)
+            .map_err(|_| LockError::Lost(lease.name.clone()))?;
+
+        Ok(())
+    }
+
+    fn release(&self, mut lease: PgLease) -> LockResult<()> {
+        lease.unlock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::r2d2::ConnectionManager;
+    use diesel::PgConnection;
+    use std::time::Duration;
+    use timada_database::testing::test_config;
+    use timada_database::Pool;
+
+    use super::{lock_key, PgLock};
+    use crate::lock::{with_lock, DistributedLock};
+
+    #[test]
+    fn key() {
+        assert_eq!(lock_key("migrations"), lock_key("migrations"));
+        assert_ne!(lock_key("migrations"), lock_key("scheduler"));
+    }
+
+    #[test]
+    fn exclusive() {
+        // Advisory locks need no tables, the default database is enough.
+        let pool = Pool::builder()
+            .build(ConnectionManager::<PgConnection>::new(
+                test_config("postgres").connection_url(),
+            ))
+            .unwrap();
+        let lock = PgLock::new(pool);
+        let ttl = Duration::from_secs(10);
+
+        let res = with_lock(&lock, "migrations", ttl, |_| {
+            assert!(lock.try_acquire("migrations", ttl).unwrap().is_none());
+            "done"
+        });
+
+        assert_eq!(res, Ok(Some("done")));
+
+        let lease = lock.try_acquire("migrations", ttl).unwrap();
+        assert!(lease.is_some());
+
+        drop(lease);
+        assert!(lock.try_acquire("migrations", ttl).unwrap().is_some());
+    }
+}
@@ -0,0 +1,226 @@
+use ::redis::Script;
+use std::time::{Duration, Instant};
+use timada_cache::RedisCache;
+use uuid::Uuid;
+
+use super::error::{LockError, LockResult};
+use super::lock::DistributedLock;
+
+const RENEW: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+  return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+
+return 0
+"#;
+
+const RELEASE: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+  return redis.call('DEL', KEYS[1])
+end
+
+return 0
+"#;
+
+// A single instance or Redlock over independent instances, a lease is held once a
+// majority of them accepted it within its ttl.
+#[derive(Clone)]
+pub struct RedisLock {
+    caches: Vec<RedisCache>,
+}
+
+#[derive(Debug)]
+pub struct RedisLease {
+    name: String,
+    token: String,
+    // Time left once the quorum was reached, minus the allowed clock drift.
+    validity: Duration,
+}
+
+impl RedisLease {
+    pub fn validity(&self) -> Duration {
+        self.validity
+    }
+}
+
+fn quorum(instances: usize) -> usize {
+    instances / 2 + 1
+}
+
+fn validity(ttl: Duration, elapsed: Duration) -> Option<Duration> {
+    let drift = ttl / 100 + Duration::from_millis(2);
+
+    ttl.checked_sub(elapsed)
+        .and_then(|left| left.checked_sub(drift))
+        .filter(|left| *left > Duration::from_millis(0))
+}
+
+fn ttl_millis(ttl: Duration) -> u64 {
+    (ttl.as_millis() as u64).max(1)
+}
+
+impl RedisLock {
+    pub fn new(cache: RedisCache) -> Self {
+        Self::redlock(vec![cache])
+    }
+
+    pub fn redlock(caches: Vec<RedisCache>) -> Self {
+        assert!(!caches.is_empty(), "Redlock needs at least one instance");
+
+        RedisLock { caches }
+    }
+
+    fn key(cache: &RedisCache, name: &str) -> String {
+        cache.key(&format!("lock:{}", name))
+    }
+
+    fn set(cache: &RedisCache, name: &str, token: &str, ttl: Duration) -> LockResult<bool> {
+        let mut conn = cache.pool().get()?;
+        let reply: Option<String> = ::redis::cmd("SET")
+            .arg(Self::key(cache, name))
+            .arg(token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_millis(ttl))
+            .query(&mut *conn)?;
+
+        Ok(reply.is_some())
+    }
+
+    fn eval(
+        cache: &RedisCache,
+        script: &str,
+        name: &str,
+        token: &str,
+        ttl: Duration,
+    ) -> LockResult<bool> {
+        let mut conn = cache.pool().get()?;
+        let reply: i64 = Script::new(script)
+            .key(Self::key(cache, name))
+            .arg(token)
+            .arg(ttl_millis(ttl))
+            .invoke(&mut *conn)?;
+
+        Ok(reply == 1)
+    }
+
+    // Runs `f` on every instance, `Ok(true)` once a quorum succeeded and an error only when
+    // failures alone made the quorum unreachable.
+    fn on_quorum<F>(&self, f: F) -> LockResult<bool>
+    where
+        F: Fn(&RedisCache) -> LockResult<bool>,
+    {
+        let quorum = quorum(self.caches.len());
+        let mut accepted = 0;
+        let mut failed = 0;
+        let mut error = None;
+
+        for cache in self.caches.iter() {
+            match f(cache) {
+                Ok(true) => accepted += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    log::warn!("Redis lock instance failed: {}", e);
+                    failed += 1;
+                    error = Some(e);
+                }
+            }
+        }
+
+        match error {
+            Some(e) if accepted < quorum && self.caches.len() - failed < quorum => Err(e),
+            _ => Ok(accepted >= quorum),
+        }
+    }
+
+    fn release_all(&self, name: &str, token: &str) {
+        for cache in self.caches.iter() {
+            if let Err(e) = Self::eval(cache, RELEASE, name, token, Duration::from_millis(0)) {
+                log::warn!("Failed to release lock {}: {}", name, e);
+            }
+        }
+    }
+}
+
+impl DistributedLock for RedisLock {
+    type Lease = RedisLease;
+
+    fn try_acquire(&self, name: &str, ttl: Duration) -> LockResult<Option<RedisLease>> {
+        let token = Uuid::new_v4().to_simple().to_string();
+        let started_at = Instant::now();
+
+        let acquired = self.on_quorum(|cache| Self::set(cache, name, &token, ttl));
+
+        match (acquired, validity(ttl, started_at.elapsed())) {
+            (Ok(true), Some(validity)) => Ok(Some(RedisLease {
+                name: name.to_owned(),
+                token,
+                validity,
+            })),
+            (acquired, _) => {
+                // Partial acquisitions would block other processes until they expire.
+                self.release_all(name, &token);
+                acquired.map(|_| None)
+            }
+        }
+    }
+
+    fn renew(&self, lease: &mut RedisLease, ttl: Duration) -> LockResult<()> {
+        let started_at = Instant::now();
+        let renewed =
+            self.on_quorum(|cache| Self::eval(cache, RENEW, &lease.name, &lease.token, ttl))?;
+
+        match validity(ttl, started_at.elapsed()) {
+            Some(validity) if renewed => {
+                lease.validity = validity;
+                Ok(())
+            }
+            _ => Err(LockError::Lost(lease.name.clone())),
+        }
+    }
+
+    fn release(&self, lease: RedisLease) -> LockResult<()> {
+        self.on_quorum(|cache| {
+            Self::eval(
+                cache,
+                RELEASE,
+                &lease.name,
+                &lease.token,
+                Duration::from_millis(0),
+            )
+        })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{quorum, validity};
+
+    #[test]
+    fn majority() {
+        assert_eq!(quorum(1), 1);
+        assert_eq!(quorum(2), 2);
+        assert_eq!(quorum(3), 2);
+        assert_eq!(quorum(5), 3);
+    }
+
+    #[test]
+    fn drift() {
+        assert_eq!(
+            validity(Duration::from_secs(10), Duration::from_millis(48)),
+            Some(Duration::from_millis(9_850))
+        );
+        assert_eq!(
+            validity(Duration::from_millis(100), Duration::from_millis(97)),
+            None
+        );
+        assert_eq!(
+            validity(Duration::from_millis(100), Duration::from_millis(120)),
+            None
+        );
+    }
+}
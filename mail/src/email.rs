@@ -0,0 +1,101 @@
+use serde::{Deserialize, Deserializer, Serializer};
+
+mod base64_data {
+    use super::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        base64::decode(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    #[serde(with = "base64_data")]
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn new(filename: &str, content_type: &str, data: Vec<u8>) -> Self {
+        Attachment {
+            filename: filename.to_owned(),
+            content_type: content_type.to_owned(),
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Email {
+    pub from: String,
+    pub to: Vec<String>,
+    pub reply_to: Option<String>,
+    pub subject: String,
+    pub html: Option<String>,
+    pub text: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+impl Email {
+    pub fn new(from: &str, to: &str, subject: &str) -> Self {
+        Email {
+            from: from.to_owned(),
+            to: vec![to.to_owned()],
+            subject: subject.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to(mut self, to: &str) -> Self {
+        self.to.push(to.to_owned());
+        self
+    }
+
+    pub fn reply_to(mut self, reply_to: &str) -> Self {
+        self.reply_to = Some(reply_to.to_owned());
+        self
+    }
+
+    pub fn html(mut self, html: &str) -> Self {
+        self.html = Some(html.to_owned());
+        self
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_owned());
+        self
+    }
+
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Attachment, Email};
+
+    #[test]
+    fn serde() {
+        let email = Email::new("noreply@timada.co", "john@timada.co", "Welcome")
+            .text("Hello")
+            .attachment(Attachment::new(
+                "hello.txt",
+                "text/plain",
+                b"hello".to_vec(),
+            ));
+
+        let value = serde_json::to_value(&email).unwrap();
+
+        assert_eq!(value["attachments"][0]["data"], "aGVsbG8=");
+        assert_eq!(serde_json::from_value::<Email>(value).unwrap(), email);
+    }
+}
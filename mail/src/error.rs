@@ -0,0 +1,40 @@
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum MailError {
+    #[error("Template {0} not found")]
+    TemplateNotFound(String),
+
+    #[error("Template error: {0}")]
+    Template(String),
+
+    #[error("Invalid message: {0}")]
+    Message(String),
+
+    #[error("Transport error: {0}")]
+    Transport(String),
+
+    #[error("Rejected by server: {0}")]
+    Rejected(String),
+
+    #[error("Queue error: {0}")]
+    Queue(String),
+}
+
+impl From<timada_jobs::JobError> for MailError {
+    fn from(e: timada_jobs::JobError) -> MailError {
+        MailError::Queue(e.to_string())
+    }
+}
+
+impl From<handlebars::RenderError> for MailError {
+    fn from(e: handlebars::RenderError) -> MailError {
+        MailError::Template(e.to_string())
+    }
+}
+
+impl From<handlebars::TemplateError> for MailError {
+    fn from(e: handlebars::TemplateError) -> MailError {
+        MailError::Template(e.to_string())
+    }
+}
+
+pub type MailResult<T> = Result<T, MailError>;
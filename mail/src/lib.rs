@@ -0,0 +1,22 @@
+#[macro_use]
+extern crate serde;
+
+#[macro_use]
+extern crate thiserror;
+
+mod email;
+mod error;
+mod mailer;
+mod queue;
+mod template;
+mod transport;
+
+pub use crate::email::{Attachment, Email};
+pub use crate::error::{MailError, MailResult};
+pub use crate::mailer::Mailer;
+pub use crate::queue::{
+    enqueue_email, enqueue_email_at, list_failed_emails, retry_failed_email,
+    set_delivery_transport, FailedEmail, SendEmail,
+};
+pub use crate::template::{MailTemplate, Templates};
+pub use crate::transport::{SmtpConfig, SmtpTransport, TestTransport, Transport};
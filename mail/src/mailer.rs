@@ -0,0 +1,106 @@
+use diesel::PgConnection;
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::email::Email;
+use super::error::MailResult;
+use super::queue::enqueue_email;
+use super::template::Templates;
+use super::transport::Transport;
+
+#[derive(Clone)]
+pub struct Mailer {
+    transport: Arc<dyn Transport>,
+    templates: Arc<Templates>,
+    from: String,
+}
+
+impl Mailer {
+    pub fn new<T: Transport + 'static>(transport: T, templates: Templates, from: &str) -> Self {
+        Mailer {
+            transport: Arc::new(transport),
+            templates: Arc::new(templates),
+            from: from.to_owned(),
+        }
+    }
+
+    pub fn render<T: Serialize>(
+        &self,
+        name: &str,
+        locale: &str,
+        to: &str,
+        data: &T,
+    ) -> MailResult<Email> {
+        self.templates.render(name, locale, &self.from, to, data)
+    }
+
+    pub fn send(&self, email: &Email) -> MailResult<()> {
+        self.transport.send(email)
+    }
+
+    pub fn send_template<T: Serialize>(
+        &self,
+        name: &str,
+        locale: &str,
+        to: &str,
+        data: &T,
+    ) -> MailResult<()> {
+        self.send(&self.render(name, locale, to, data)?)
+    }
+
+    pub fn enqueue_template<T: Serialize>(
+        &self,
+        conn: &PgConnection,
+        name: &str,
+        locale: &str,
+        to: &str,
+        data: &T,
+    ) -> MailResult<Uuid> {
+        enqueue_email(conn, &self.render(name, locale, to, data)?)
+    }
+
+    pub fn transport(&self) -> Arc<dyn Transport> {
+        self.transport.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Mailer;
+    use crate::template::{MailTemplate, Templates};
+    use crate::transport::TestTransport;
+
+    #[test]
+    fn send_template() {
+        let transport = TestTransport::new();
+        let mut templates = Templates::default();
+        templates
+            .register(
+                "reset",
+                "en",
+                MailTemplate {
+                    subject: "Reset your password",
+                    html: None,
+                    text: Some("{{link}}"),
+                },
+            )
+            .unwrap();
+
+        let mailer = Mailer::new(transport.clone(), templates, "noreply@timada.co");
+        mailer
+            .send_template(
+                "reset",
+                "en",
+                "john@timada.co",
+                &json!({ "link": "https://timada.co" }),
+            )
+            .unwrap();
+
+        let email = transport.assert_sent_to("john@timada.co");
+        assert_eq!(email.from, "noreply@timada.co");
+        assert_eq!(email.text, Some("https://timada.co".to_owned()));
+    }
+}
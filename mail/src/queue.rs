@@ -0,0 +1,196 @@
+use chrono::NaiveDateTime;
+use diesel::PgConnection;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use timada_jobs::{
+    enqueue_at, list_jobs, requeue_dead_job, Job, JobContext, JobError, JobFailure, JobRecord,
+    JobResult, JobStatus, RetryPolicy,
+};
+use uuid::Uuid;
+
+use super::email::Email;
+use super::error::{MailError, MailResult};
+use super::transport::Transport;
+
+lazy_static::lazy_static! {
+    static ref TRANSPORT: RwLock<Option<Arc<dyn Transport>>> = RwLock::new(None);
+}
+
+pub fn set_delivery_transport<T: Transport + 'static>(transport: T) {
+    if let Ok(mut current) = TRANSPORT.write() {
+        *current = Some(Arc::new(transport));
+    }
+}
+
+fn delivery_transport() -> Option<Arc<dyn Transport>> {
+    match TRANSPORT.read() {
+        Ok(transport) => transport.clone(),
+        Err(e) => e.into_inner().clone(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SendEmail {
+    pub email: Email,
+}
+
+#[async_trait::async_trait]
+impl Job for SendEmail {
+    const NAME: &'static str = "mail.send";
+    const QUEUE: &'static str = "mail";
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .max_attempts(8)
+            .base_delay(Duration::from_secs(30))
+            .max_delay(Duration::from_secs(6 * 60 * 60))
+    }
+
+    async fn run(&self, _ctx: &JobContext) -> JobResult<()> {
+        let transport = delivery_transport()
+            .ok_or_else(|| JobError::failed("Mail delivery transport is not configured"))?;
+        let email = self.email.clone();
+
+        tokio::task::spawn_blocking(move || transport.send(&email))
+            .await
+            .map_err(JobError::failed)?
+            .map_err(|e| match e {
+                MailError::Rejected(_) | MailError::Message(_) => JobError::permanent(e),
+                e => JobError::failed(e),
+            })
+    }
+}
+
+pub fn enqueue_email(conn: &PgConnection, email: &Email) -> MailResult<Uuid> {
+    enqueue_email_at(conn, email, chrono::Utc::now().naive_utc())
+}
+
+pub fn enqueue_email_at(
+    conn: &PgConnection,
+    email: &Email,
+    send_at: NaiveDateTime,
+) -> MailResult<Uuid> {
+    let job = SendEmail {
+        email: email.clone(),
+    };
+
+    Ok(enqueue_at(conn, &job, send_at)?)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedEmail {
+    pub job_id: Uuid,
+    pub email: Email,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub failures: Vec<JobFailure>,
+    pub failed_at: NaiveDateTime,
+}
+
+impl FailedEmail {
+    fn from_record(record: JobRecord) -> MailResult<Self> {
+        let job: SendEmail = serde_json::from_value(record.payload.clone())
+            .map_err(|e| MailError::Queue(e.to_string()))?;
+
+        Ok(FailedEmail {
+            job_id: record.id,
+            email: job.email,
+            attempts: record.attempts,
+            failures: record.failures(),
+            last_error: record.last_error,
+            failed_at: record.updated_at,
+        })
+    }
+}
+
+pub fn list_failed_emails(conn: &PgConnection) -> MailResult<Vec<FailedEmail>> {
+    list_jobs(conn, SendEmail::NAME, JobStatus::Dead)?
+        .into_iter()
+        .map(FailedEmail::from_record)
+        .collect()
+}
+
+pub fn retry_failed_email(conn: &PgConnection, job_id: Uuid) -> MailResult<bool> {
+    Ok(requeue_dead_job(conn, job_id)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use diesel::r2d2::{ConnectionManager, Pool};
+    use diesel::PgConnection;
+    use serde_json::json;
+    use timada_jobs::{Job, JobContext, JobRecord};
+    use uuid::Uuid;
+
+    use super::{set_delivery_transport, FailedEmail, SendEmail};
+    use crate::email::Email;
+    use crate::transport::TestTransport;
+
+    fn context() -> JobContext {
+        JobContext {
+            id: Uuid::new_v4(),
+            attempt: 1,
+            pool: Pool::builder().build_unchecked(ConnectionManager::<PgConnection>::new(
+                "postgres://localhost",
+            )),
+        }
+    }
+
+    #[test]
+    fn deliver() {
+        let transport = TestTransport::new();
+        set_delivery_transport(transport.clone());
+
+        let mut runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let job = SendEmail {
+            email: Email::new("noreply@timada.co", "queued@timada.co", "Welcome").text("Hello"),
+        };
+        runtime.block_on(job.run(&context())).unwrap();
+        assert_eq!(transport.assert_sent_to("queued@timada.co"), job.email);
+
+        let job = SendEmail {
+            email: Email::new("noreply@timada.co", "queued@timada.co", "Welcome").attachment(
+                crate::email::Attachment::new("a.bin", "invalid", Vec::new()),
+            ),
+        };
+        assert!(runtime
+            .block_on(job.run(&context()))
+            .map_err(|e| e.is_permanent())
+            .unwrap_err());
+    }
+
+    #[test]
+    fn failed_email() {
+        let now = Utc::now().naive_utc();
+        let email = Email::new("noreply@timada.co", "john@timada.co", "Welcome");
+        let record = JobRecord {
+            id: Uuid::new_v4(),
+            queue: "mail".to_owned(),
+            job_type: "mail.send".to_owned(),
+            payload: serde_json::to_value(&SendEmail {
+                email: email.clone(),
+            })
+            .unwrap(),
+            status: "dead".to_owned(),
+            attempts: 8,
+            run_at: now,
+            locked_at: None,
+            last_error: Some("550 mailbox unavailable".to_owned()),
+            created_at: now,
+            updated_at: now,
+            errors: json!([{ "attempt": 8, "error": "550 mailbox unavailable", "failed_at": now }]),
+        };
+
+        let failed = FailedEmail::from_record(record).unwrap();
+
+        assert_eq!(failed.email, email);
+        assert_eq!(failed.attempts, 8);
+        assert_eq!(failed.failures[0].error, "550 mailbox unavailable");
+    }
+}
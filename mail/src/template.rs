@@ -0,0 +1,284 @@
+use handlebars::{no_escape, Handlebars};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use super::email::Email;
+use super::error::{MailError, MailResult};
+
+const PARTS: [&str; 3] = ["subject", "html", "text"];
+
+pub struct MailTemplate<'a> {
+    pub subject: &'a str,
+    pub html: Option<&'a str>,
+    pub text: Option<&'a str>,
+}
+
+// Only the html part is escaped, subject and text are plain text rendered by `plain`.
+pub struct Templates {
+    registry: Handlebars<'static>,
+    plain: Handlebars<'static>,
+    names: HashSet<String>,
+    default_locale: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Templates::new("en")
+    }
+}
+
+fn key(name: &str, locale: &str, part: &str) -> String {
+    format!("{}.{}.{}", name, locale, part)
+}
+
+impl Templates {
+    pub fn new(default_locale: &str) -> Self {
+        let mut registry = Handlebars::new();
+        registry.set_strict_mode(true);
+
+        let mut plain = Handlebars::new();
+        plain.set_strict_mode(true);
+        plain.register_escape_fn(no_escape);
+
+        Templates {
+            registry,
+            plain,
+            names: HashSet::new(),
+            default_locale: default_locale.to_owned(),
+        }
+    }
+
+    // Runs on both registries so helpers are available to every part.
+    pub fn configure<F>(&mut self, configure: F)
+    where
+        F: Fn(&mut Handlebars<'static>),
+    {
+        configure(&mut self.registry);
+        configure(&mut self.plain);
+    }
+
+    fn registry(&self, part: &str) -> &Handlebars<'static> {
+        match part {
+            "html" => &self.registry,
+            _ => &self.plain,
+        }
+    }
+
+    pub fn register(
+        &mut self,
+        name: &str,
+        locale: &str,
+        template: MailTemplate<'_>,
+    ) -> MailResult<()> {
+        self.register_part(name, locale, "subject", template.subject)?;
+
+        if let Some(html) = template.html {
+            self.register_part(name, locale, "html", html)?;
+        }
+
+        if let Some(text) = template.text {
+            self.register_part(name, locale, "text", text)?;
+        }
+
+        Ok(())
+    }
+
+    fn register_part(
+        &mut self,
+        name: &str,
+        locale: &str,
+        part: &str,
+        source: &str,
+    ) -> MailResult<()> {
+        let key = key(name, locale, part);
+
+        match part {
+            "html" => self.registry.register_template_string(&key, source)?,
+            _ => self.plain.register_template_string(&key, source)?,
+        }
+
+        self.names.insert(key);
+
+        Ok(())
+    }
+
+    // Expects `<dir>/<locale>/<name>.<subject|html|text>.hbs`.
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> MailResult<()> {
+        let locales = fs::read_dir(dir).map_err(|e| MailError::Template(e.to_string()))?;
+
+        for locale in locales.filter_map(|entry| entry.ok()) {
+            if !locale.path().is_dir() {
+                continue;
+            }
+
+            let locale_name = locale.file_name().to_string_lossy().into_owned();
+            let files =
+                fs::read_dir(locale.path()).map_err(|e| MailError::Template(e.to_string()))?;
+
+            for file in files.filter_map(|entry| entry.ok()) {
+                let file_name = file.file_name().to_string_lossy().into_owned();
+                let parts = file_name.rsplitn(3, '.').collect::<Vec<_>>();
+
+                if parts.len() != 3 || parts[0] != "hbs" || !PARTS.contains(&parts[1]) {
+                    continue;
+                }
+
+                let source = fs::read_to_string(file.path())
+                    .map_err(|e| MailError::Template(e.to_string()))?;
+                self.register_part(parts[2], &locale_name, parts[1], &source)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_locale(&self, name: &str, locale: &str) -> MailResult<String> {
+        let language = locale
+            .split(|c| c == '-' || c == '_')
+            .next()
+            .unwrap_or(locale);
+
+        [locale, language, self.default_locale.as_str()]
+            .iter()
+            .find(|locale| self.names.contains(&key(name, locale, "subject")))
+            .map(|locale| (*locale).to_owned())
+            .ok_or_else(|| MailError::TemplateNotFound(name.to_owned()))
+    }
+
+    fn render_part<T: Serialize>(
+        &self,
+        name: &str,
+        locale: &str,
+        part: &str,
+        data: &T,
+    ) -> MailResult<Option<String>> {
+        let key = key(name, locale, part);
+
+        if !self.names.contains(&key) {
+            return Ok(None);
+        }
+
+        Ok(Some(self.registry(part).render(&key, data)?))
+    }
+
+    pub fn render<T: Serialize>(
+        &self,
+        name: &str,
+        locale: &str,
+        from: &str,
+        to: &str,
+        data: &T,
+    ) -> MailResult<Email> {
+        let locale = self.resolve_locale(name, locale)?;
+        let subject = self
+            .render_part(name, &locale, "subject", data)?
+            .unwrap_or_default();
+
+        Ok(Email {
+            html: self.render_part(name, &locale, "html", data)?,
+            text: self.render_part(name, &locale, "text", data)?,
+            ..Email::new(from, to, subject.trim())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{MailTemplate, Templates};
+    use crate::error::MailError;
+
+    fn templates() -> Templates {
+        let mut templates = Templates::default();
+
+        templates
+            .register(
+                "welcome",
+                "en",
+                MailTemplate {
+                    subject: "Welcome {{name}}",
+                    html: Some("<p>Hello {{name}}</p>"),
+                    text: Some("Hello {{name}}"),
+                },
+            )
+            .unwrap();
+        templates
+            .register(
+                "welcome",
+                "fr",
+                MailTemplate {
+                    subject: "Bienvenue {{name}}",
+                    html: None,
+                    text: Some("Bonjour {{name}}"),
+                },
+            )
+            .unwrap();
+
+        templates
+    }
+
+    #[test]
+    fn locales() {
+        let templates = templates();
+        let data = json!({ "name": "John" });
+
+        let email = templates
+            .render(
+                "welcome",
+                "fr-CA",
+                "noreply@timada.co",
+                "john@timada.co",
+                &data,
+            )
+            .unwrap();
+        assert_eq!(email.subject, "Bienvenue John");
+        assert_eq!(email.html, None);
+        assert_eq!(email.text, Some("Bonjour John".to_owned()));
+
+        let email = templates
+            .render(
+                "welcome",
+                "de",
+                "noreply@timada.co",
+                "john@timada.co",
+                &data,
+            )
+            .unwrap();
+        assert_eq!(email.subject, "Welcome John");
+        assert_eq!(email.html, Some("<p>Hello John</p>".to_owned()));
+    }
+
+    #[test]
+    fn escape() {
+        let templates = templates();
+        let data = json!({ "name": "Tom & <Jerry>" });
+
+        let email = templates
+            .render("welcome", "en", "noreply@timada.co", "tom@timada.co", &data)
+            .unwrap();
+        assert_eq!(email.subject, "Welcome Tom & <Jerry>");
+        assert_eq!(email.text, Some("Hello Tom & <Jerry>".to_owned()));
+        assert_eq!(
+            email.html,
+            Some("<p>Hello Tom &amp; &lt;Jerry&gt;</p>".to_owned())
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let templates = templates();
+
+        assert_eq!(
+            templates
+                .render("missing", "en", "a@timada.co", "b@timada.co", &json!({}))
+                .unwrap_err(),
+            MailError::TemplateNotFound("missing".to_owned())
+        );
+        assert!(templates
+            .render("welcome", "en", "a@timada.co", "b@timada.co", &json!({}))
+            .is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use lettre::smtp::authentication::Credentials;
+use lettre::smtp::error::Error as SmtpError;
+use lettre::{SmtpClient, SmtpTransport as LettreSmtpTransport, Transport as LettreTransport};
+use lettre_email::EmailBuilder;
+use std::sync::{Arc, Mutex};
+use timada_util::env;
+use timada_util::secret::{secret, Secret};
+
+use super::email::Email;
+use super::error::{MailError, MailResult};
+
+pub trait Transport: Send + Sync {
+    fn send(&self, email: &Email) -> MailResult<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+}
+
+impl SmtpConfig {
+    pub fn from_env() -> Self {
+        let username = env::var_opt("SMTP_USERNAME");
+        let password = username.as_ref().map(|_| secret("SMTP_PASSWORD"));
+
+        SmtpConfig {
+            host: env::var("SMTP_HOST"),
+            username,
+            password,
+        }
+    }
+}
+
+fn build(email: &Email) -> MailResult<lettre_email::Email> {
+    let mut builder = EmailBuilder::new()
+        .from(email.from.as_str())
+        .subject(email.subject.as_str());
+
+    for to in email.to.iter() {
+        builder = builder.to(to.as_str());
+    }
+
+    if let Some(reply_to) = email.reply_to.as_ref() {
+        builder = builder.reply_to(reply_to.as_str());
+    }
+
+    builder = match (email.html.as_ref(), email.text.as_ref()) {
+        (Some(html), Some(text)) => builder.alternative(html.as_str(), text.as_str()),
+        (Some(html), None) => builder.html(html.as_str()),
+        (None, Some(text)) => builder.text(text.as_str()),
+        (None, None) => builder,
+    };
+
+    for attachment in email.attachments.iter() {
+        let content_type = attachment.content_type.parse().map_err(|_| {
+            MailError::Message(format!("Invalid content type {}", attachment.content_type))
+        })?;
+
+        builder = builder
+            .attachment(&attachment.data, &attachment.filename, &content_type)
+            .map_err(|e| MailError::Message(e.to_string()))?;
+    }
+
+    builder
+        .build()
+        .map_err(|e| MailError::Message(e.to_string()))
+}
+
+pub struct SmtpTransport {
+    transport: Mutex<LettreSmtpTransport>,
+}
+
+impl SmtpTransport {
+    pub fn new(config: SmtpConfig) -> MailResult<Self> {
+        let mut client = SmtpClient::new_simple(&config.host)
+            .map_err(|e| MailError::Transport(e.to_string()))?;
+
+        if let (Some(username), Some(password)) = (config.username, config.password) {
+            client = client.credentials(Credentials::new(username, password.into_inner()));
+        }
+
+        Ok(SmtpTransport {
+            transport: Mutex::new(client.transport()),
+        })
+    }
+
+    pub fn from_env() -> MailResult<Self> {
+        Self::new(SmtpConfig::from_env())
+    }
+}
+
+impl Transport for SmtpTransport {
+    fn send(&self, email: &Email) -> MailResult<()> {
+        let message = build(email)?;
+        let mut transport = self
+            .transport
+            .lock()
+            .map_err(|e| MailError::Transport(e.to_string()))?;
+
+        transport
+            .send(message.into())
+            .map(|_| ())
+            .map_err(|e| match e {
+                SmtpError::Permanent(_) => MailError::Rejected(e.to_string()),
+                e => MailError::Transport(e.to_string()),
+            })
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct TestTransport {
+    sent: Arc<Mutex<Vec<Email>>>,
+}
+
+impl TestTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent
+            .lock()
+            .map(|sent| sent.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn last(&self) -> Option<Email> {
+        self.sent().pop()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut sent) = self.sent.lock() {
+            sent.clear();
+        }
+    }
+
+    pub fn assert_sent_to(&self, to: &str) -> Email {
+        self.sent()
+            .into_iter()
+            .rev()
+            .find(|email| email.to.iter().any(|value| value == to))
+            .unwrap_or_else(|| panic!("expected an email sent to {}", to))
+    }
+}
+
+impl Transport for TestTransport {
+    fn send(&self, email: &Email) -> MailResult<()> {
+        build(email)?;
+
+        self.sent
+            .lock()
+            .map_err(|e| MailError::Transport(e.to_string()))?
+            .push(email.clone());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build, TestTransport, Transport};
+    use crate::email::{Attachment, Email};
+
+    #[test]
+    fn capture() {
+        let transport = TestTransport::new();
+        let email = Email::new("noreply@timada.co", "john@timada.co", "Welcome")
+            .html("<p>Hello</p>")
+            .text("Hello");
+
+        transport.send(&email).unwrap();
+
+        assert_eq!(transport.assert_sent_to("john@timada.co"), email);
+        assert_eq!(transport.sent().len(), 1);
+        transport.clear();
+        assert_eq!(transport.last(), None);
+    }
+
+    #[test]
+    fn invalid_attachment() {
+        let email = Email::new("noreply@timada.co", "john@timada.co", "Report")
+            .attachment(Attachment::new("report.csv", "not a mime", Vec::new()));
+
+        assert!(build(&email).is_err());
+    }
+}
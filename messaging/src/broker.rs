@@ -0,0 +1,160 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+use timada_http::Context;
+
+use super::error::MessagingResult;
+use super::message::{subject, Envelope, Headers, Message};
+
+type Ack = Box<dyn FnOnce() -> MessagingResult<()> + Send>;
+
+pub struct Delivery {
+    pub data: Vec<u8>,
+    ack: Option<Ack>,
+}
+
+impl Delivery {
+    pub fn new(data: Vec<u8>) -> Self {
+        Delivery { data, ack: None }
+    }
+
+    pub fn with_ack<F>(data: Vec<u8>, ack: F) -> Self
+    where
+        F: FnOnce() -> MessagingResult<()> + Send + 'static,
+    {
+        Delivery {
+            data,
+            ack: Some(Box::new(ack)),
+        }
+    }
+
+    pub fn ack(self) -> MessagingResult<()> {
+        match self.ack {
+            Some(ack) => ack(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait Subscription: Send {
+    async fn next(&mut self) -> MessagingResult<Option<Delivery>>;
+}
+
+#[async_trait::async_trait]
+pub trait Broker: Send + Sync {
+    async fn publish(&self, subject: &str, data: Vec<u8>) -> MessagingResult<()>;
+    async fn subscribe(&self, subject: &str, group: &str)
+        -> MessagingResult<Box<dyn Subscription>>;
+}
+
+pub fn dead_letter_subject(subject: &str) -> String {
+    format!("{}.dlq", subject)
+}
+
+pub struct Received<M> {
+    pub envelope: Envelope<M>,
+    delivery: Delivery,
+    broker: Arc<dyn Broker>,
+    subject: String,
+}
+
+impl<M> Received<M> {
+    pub fn ack(self) -> MessagingResult<()> {
+        self.delivery.ack()
+    }
+
+    pub async fn dead_letter(self) -> MessagingResult<()> {
+        self.broker
+            .publish(
+                &dead_letter_subject(&self.subject),
+                self.delivery.data.clone(),
+            )
+            .await?;
+
+        self.delivery.ack()
+    }
+}
+
+pub struct TypedSubscription<M> {
+    inner: Box<dyn Subscription>,
+    broker: Arc<dyn Broker>,
+    subject: String,
+    message: PhantomData<M>,
+}
+
+impl<M: Message> TypedSubscription<M> {
+    pub async fn next(&mut self) -> MessagingResult<Option<Received<M>>> {
+        loop {
+            let delivery = match self.inner.next().await? {
+                Some(delivery) => delivery,
+                None => return Ok(None),
+            };
+
+            match Envelope::decode(&delivery.data) {
+                Ok(envelope) => {
+                    return Ok(Some(Received {
+                        envelope,
+                        delivery,
+                        broker: self.broker.clone(),
+                        subject: self.subject.clone(),
+                    }))
+                }
+                Err(e) => {
+                    log::warn!("poison message on {}: {}", self.subject, e);
+
+                    self.broker
+                        .publish(&dead_letter_subject(&self.subject), delivery.data.clone())
+                        .await?;
+                    delivery.ack()?;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Messaging {
+    broker: Arc<dyn Broker>,
+    prefix: String,
+}
+
+impl Messaging {
+    pub fn new<B: Broker + 'static>(broker: B, prefix: &str) -> Self {
+        Messaging {
+            broker: Arc::new(broker),
+            prefix: prefix.to_owned(),
+        }
+    }
+
+    pub fn subject<M: Message>(&self) -> String {
+        subject::<M>(&self.prefix)
+    }
+
+    pub async fn publish<M: Message>(&self, headers: Headers, message: M) -> MessagingResult<()> {
+        let data = Envelope::new(headers, message).encode()?;
+
+        self.broker.publish(&self.subject::<M>(), data).await
+    }
+
+    pub async fn publish_with_context<M: Message>(
+        &self,
+        context: &Context,
+        message: M,
+    ) -> MessagingResult<()> {
+        self.publish(Headers::from_context(context), message).await
+    }
+
+    pub async fn subscribe<M: Message>(
+        &self,
+        group: &str,
+    ) -> MessagingResult<TypedSubscription<M>> {
+        let subject = self.subject::<M>();
+
+        Ok(TypedSubscription {
+            inner: self.broker.subscribe(&subject, group).await?,
+            broker: self.broker.clone(),
+            subject,
+            message: PhantomData,
+        })
+    }
+}
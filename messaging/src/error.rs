@@ -0,0 +1,25 @@
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum MessagingError {
+    #[error("Connection error: {0}")]
+    Connection(String),
+
+    #[error("Publish error: {0}")]
+    Publish(String),
+
+    #[error("Subscribe error: {0}")]
+    Subscribe(String),
+
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    #[error("Unexpected message type {actual}, expected {expected}")]
+    UnexpectedType { expected: String, actual: String },
+}
+
+impl From<serde_json::Error> for MessagingError {
+    fn from(e: serde_json::Error) -> MessagingError {
+        MessagingError::Serialization(e.to_string())
+    }
+}
+
+pub type MessagingResult<T> = Result<T, MessagingError>;
@@ -0,0 +1,128 @@
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::message::Message as KafkaMessage;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::{Offset, TopicPartitionList};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::broker::{Broker, Delivery, Subscription};
+use super::error::{MessagingError, MessagingResult};
+
+const POLL_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Clone)]
+pub struct KafkaBroker {
+    brokers: String,
+    producer: FutureProducer,
+    timeout: Duration,
+}
+
+impl KafkaBroker {
+    pub fn connect(brokers: &str) -> MessagingResult<Self> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("enable.idempotence", "true")
+            .set("acks", "all")
+            .create()
+            .map_err(|e| MessagingError::Connection(e.to_string()))?;
+
+        Ok(KafkaBroker {
+            brokers: brokers.to_owned(),
+            producer,
+            timeout: Duration::from_secs(5),
+        })
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn consumer(&self, topic: &str, group: &str) -> MessagingResult<BaseConsumer> {
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &self.brokers)
+            .set("group.id", group)
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .map_err(|e| MessagingError::Connection(e.to_string()))?;
+
+        consumer
+            .subscribe(&[topic])
+            .map_err(|e| MessagingError::Subscribe(e.to_string()))?;
+
+        Ok(consumer)
+    }
+}
+
+pub struct KafkaSubscription {
+    consumer: Arc<BaseConsumer>,
+}
+
+fn commit(
+    consumer: &BaseConsumer,
+    topic: &str,
+    partition: i32,
+    offset: i64,
+) -> MessagingResult<()> {
+    let mut offsets = TopicPartitionList::new();
+    offsets.add_partition_offset(topic, partition, Offset::Offset(offset + 1));
+
+    consumer
+        .commit(&offsets, CommitMode::Async)
+        .map_err(|e| MessagingError::Subscribe(e.to_string()))
+}
+
+#[async_trait::async_trait]
+impl Subscription for KafkaSubscription {
+    async fn next(&mut self) -> MessagingResult<Option<Delivery>> {
+        let consumer = self.consumer.clone();
+
+        tokio::task::spawn_blocking(move || loop {
+            let message = match consumer.poll(POLL_TIMEOUT) {
+                Some(message) => message.map_err(|e| MessagingError::Subscribe(e.to_string()))?,
+                None => continue,
+            };
+
+            let data = message
+                .payload()
+                .map(|data| data.to_vec())
+                .unwrap_or_default();
+            let topic = message.topic().to_owned();
+            let partition = message.partition();
+            let offset = message.offset();
+            let consumer = consumer.clone();
+
+            return Ok(Some(Delivery::with_ack(data, move || {
+                commit(&consumer, &topic, partition, offset)
+            })));
+        })
+        .await
+        .map_err(|e| MessagingError::Subscribe(e.to_string()))?
+    }
+}
+
+#[async_trait::async_trait]
+impl Broker for KafkaBroker {
+    async fn publish(&self, subject: &str, data: Vec<u8>) -> MessagingResult<()> {
+        let record: FutureRecord<(), _> = FutureRecord::to(subject).payload(&data);
+
+        self.producer
+            .send(record, self.timeout.as_millis() as i64)
+            .await
+            .map_err(|e| MessagingError::Publish(e.to_string()))?
+            .map(|_| ())
+            .map_err(|(e, _)| MessagingError::Publish(e.to_string()))
+    }
+
+    async fn subscribe(
+        &self,
+        subject: &str,
+        group: &str,
+    ) -> MessagingResult<Box<dyn Subscription>> {
+        Ok(Box::new(KafkaSubscription {
+            consumer: Arc::new(self.consumer(subject, group)?),
+        }))
+    }
+}
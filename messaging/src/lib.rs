@@ -0,0 +1,19 @@
+#[macro_use]
+extern crate serde;
+
+#[macro_use]
+extern crate thiserror;
+
+mod broker;
+mod error;
+mod kafka;
+mod message;
+mod nats;
+
+pub use crate::broker::{
+    dead_letter_subject, Broker, Delivery, Messaging, Received, Subscription, TypedSubscription,
+};
+pub use crate::error::{MessagingError, MessagingResult};
+pub use crate::kafka::KafkaBroker;
+pub use crate::message::{subject, Envelope, Headers, Message, REQUEST_ID_HEADER, TRACE_ID_HEADER};
+pub use crate::nats::NatsBroker;
@@ -0,0 +1,178 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use timada_http::Context;
+use uuid::Uuid;
+
+use super::error::{MessagingError, MessagingResult};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
+
+pub trait Message: Serialize + DeserializeOwned + Send + Sync + 'static {
+    const TYPE_NAME: &'static str;
+}
+
+pub fn subject<M: Message>(prefix: &str) -> String {
+    if prefix.is_empty() {
+        return M::TYPE_NAME.to_owned();
+    }
+
+    format!("{}.{}", prefix, M::TYPE_NAME)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Headers(BTreeMap<String, String>);
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_context(context: &Context) -> Self {
+        let mut headers = Headers::new();
+
+        if let Some(request_id) = context.request_id.as_ref() {
+            headers = headers.set(REQUEST_ID_HEADER, request_id);
+        }
+
+        if let Some(trace_id) = context.trace_id.as_ref() {
+            headers = headers.set(TRACE_ID_HEADER, trace_id);
+        }
+
+        headers
+    }
+
+    pub fn set(mut self, name: &str, value: &str) -> Self {
+        self.0.insert(name.to_lowercase(), value.to_owned());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(&name.to_lowercase()).map(|value| value.as_str())
+    }
+
+    pub fn request_id(&self) -> Option<&str> {
+        self.get(REQUEST_ID_HEADER)
+    }
+
+    pub fn trace_id(&self) -> Option<&str> {
+        self.get(TRACE_ID_HEADER)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    #[serde(default)]
+    pub headers: Headers,
+    pub payload: T,
+}
+
+impl<M: Message> Envelope<M> {
+    pub fn new(headers: Headers, payload: M) -> Self {
+        Envelope {
+            id: Uuid::new_v4(),
+            type_name: M::TYPE_NAME.to_owned(),
+            headers,
+            payload,
+        }
+    }
+
+    pub fn encode(&self) -> MessagingResult<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn decode(data: &[u8]) -> MessagingResult<Self> {
+        let envelope: Envelope<serde_json::Value> = serde_json::from_slice(data)?;
+
+        if envelope.type_name != M::TYPE_NAME {
+            return Err(MessagingError::UnexpectedType {
+                expected: M::TYPE_NAME.to_owned(),
+                actual: envelope.type_name,
+            });
+        }
+
+        Ok(Envelope {
+            id: envelope.id,
+            type_name: envelope.type_name,
+            headers: envelope.headers,
+            payload: serde_json::from_value(envelope.payload)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_http::Context;
+
+    use super::{subject, Envelope, Headers, Message};
+    use crate::error::MessagingError;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct UserCreated {
+        email: String,
+    }
+
+    impl Message for UserCreated {
+        const TYPE_NAME: &'static str = "UserCreated";
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct UserDeleted {
+        email: String,
+    }
+
+    impl Message for UserDeleted {
+        const TYPE_NAME: &'static str = "UserDeleted";
+    }
+
+    #[test]
+    fn subjects() {
+        assert_eq!(
+            subject::<UserCreated>("timada.users"),
+            "timada.users.UserCreated"
+        );
+        assert_eq!(subject::<UserCreated>(""), "UserCreated");
+    }
+
+    #[test]
+    fn headers() {
+        let context = Context {
+            request_id: Some("request-1".to_owned()),
+            trace_id: Some("trace-1".to_owned()),
+            ..Default::default()
+        };
+        let headers = Headers::from_context(&context).set("X-Tenant", "acme");
+
+        assert_eq!(headers.request_id(), Some("request-1"));
+        assert_eq!(headers.trace_id(), Some("trace-1"));
+        assert_eq!(headers.get("x-tenant"), Some("acme"));
+    }
+
+    #[test]
+    fn envelope() {
+        let envelope = Envelope::new(
+            Headers::new().set("x-request-id", "request-1"),
+            UserCreated {
+                email: "john@timada.co".to_owned(),
+            },
+        );
+        let data = envelope.encode().unwrap();
+
+        assert_eq!(Envelope::<UserCreated>::decode(&data), Ok(envelope));
+        assert_eq!(
+            Envelope::<UserDeleted>::decode(&data),
+            Err(MessagingError::UnexpectedType {
+                expected: "UserDeleted".to_owned(),
+                actual: "UserCreated".to_owned(),
+            })
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use ::nats::jetstream::{JetStream, SubscribeOptions};
+use ::nats::Connection;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver};
+use futures::StreamExt;
+
+use super::broker::{Broker, Delivery, Subscription};
+use super::error::{MessagingError, MessagingResult};
+
+async fn blocking<T, F>(f: F) -> MessagingResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> MessagingResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| MessagingError::Connection(e.to_string()))?
+}
+
+#[derive(Clone)]
+pub struct NatsBroker {
+    connection: Connection,
+    jetstream: Option<JetStream>,
+}
+
+impl NatsBroker {
+    pub fn connect(url: &str) -> MessagingResult<Self> {
+        let connection =
+            ::nats::connect(url).map_err(|e| MessagingError::Connection(e.to_string()))?;
+
+        Ok(NatsBroker {
+            connection,
+            jetstream: None,
+        })
+    }
+
+    pub fn jetstream(mut self) -> Self {
+        self.jetstream = Some(::nats::jetstream::new(self.connection.clone()));
+        self
+    }
+}
+
+pub struct NatsSubscription {
+    receiver: UnboundedReceiver<MessagingResult<Delivery>>,
+}
+
+#[async_trait::async_trait]
+impl Subscription for NatsSubscription {
+    async fn next(&mut self) -> MessagingResult<Option<Delivery>> {
+        self.receiver.next().await.transpose()
+    }
+}
+
+#[async_trait::async_trait]
+impl Broker for NatsBroker {
+    async fn publish(&self, subject: &str, data: Vec<u8>) -> MessagingResult<()> {
+        let broker = self.clone();
+        let subject = subject.to_owned();
+
+        blocking(move || {
+            match broker.jetstream.as_ref() {
+                Some(jetstream) => jetstream.publish(&subject, data).map(|_| ()),
+                None => broker.connection.publish(&subject, data),
+            }
+            .map_err(|e| MessagingError::Publish(e.to_string()))
+        })
+        .await
+    }
+
+    async fn subscribe(
+        &self,
+        subject: &str,
+        group: &str,
+    ) -> MessagingResult<Box<dyn Subscription>> {
+        let (sender, receiver) = unbounded();
+        let broker = self.clone();
+        let subject = subject.to_owned();
+        let group = group.to_owned();
+
+        std::thread::spawn(move || {
+            let res = match broker.jetstream.as_ref() {
+                Some(jetstream) => jetstream
+                    .queue_subscribe_with_options(
+                        &subject,
+                        &group,
+                        &SubscribeOptions::new()
+                            .durable_name(group.clone())
+                            .manual_ack(),
+                    )
+                    .map(|subscription| {
+                        for message in subscription.iter() {
+                            let data = message.data.clone();
+                            let delivery = Delivery::with_ack(data, move || {
+                                message
+                                    .ack()
+                                    .map_err(|e| MessagingError::Subscribe(e.to_string()))
+                            });
+
+                            if sender.unbounded_send(Ok(delivery)).is_err() {
+                                break;
+                            }
+                        }
+                    }),
+                None => broker
+                    .connection
+                    .queue_subscribe(&subject, &group)
+                    .map(|subscription| {
+                        for message in subscription.iter() {
+                            if sender
+                                .unbounded_send(Ok(Delivery::new(message.data)))
+                                .is_err()
+                            {
+                                break;
+                            }
+                        }
+                    }),
+            };
+
+            if let Err(e) = res {
+                log::error!("nats subscription to {} failed: {}", subject, e);
+                let _ = sender.unbounded_send(Err(MessagingError::Subscribe(e.to_string())));
+            }
+        });
+
+        Ok(Box::new(NatsSubscription { receiver }))
+    }
+}
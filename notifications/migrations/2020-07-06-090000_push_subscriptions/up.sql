@@ -0,0 +1,16 @@
+CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
+
+CREATE TABLE push_subscriptions (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  user_id uuid NOT NULL,
+  endpoint TEXT NOT NULL UNIQUE,
+  p256dh VARCHAR(255) NOT NULL,
+  auth VARCHAR(255) NOT NULL,
+  user_agent TEXT,
+  failures INTEGER NOT NULL DEFAULT 0,
+  last_used_at TIMESTAMPTZ,
+  expired_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX push_subscriptions_user_id_idx ON push_subscriptions (user_id) WHERE expired_at IS NULL;
@@ -0,0 +1,112 @@
+use diesel::result::Error as DieselError;
+use timada_http::Error;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PushError {
+    #[error("Push subscription not found")]
+    NotFound,
+
+    #[error("Invalid push subscription: {0}")]
+    InvalidSubscription(String),
+
+    #[error("Invalid push message: {0}")]
+    InvalidMessage(String),
+
+    #[error("Invalid VAPID configuration: {0}")]
+    Vapid(String),
+
+    #[error("Push subscription is gone")]
+    Gone,
+
+    #[error("Rejected by push service: {0}")]
+    Rejected(String),
+
+    #[error("Push delivery error: {0}")]
+    Delivery(String),
+
+    #[error("Queue error: {0}")]
+    Queue(String),
+
+    #[error("{0}")]
+    Database(String),
+}
+
+impl From<DieselError> for PushError {
+    fn from(e: DieselError) -> PushError {
+        match e {
+            DieselError::NotFound => PushError::NotFound,
+            e => PushError::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<timada_jobs::JobError> for PushError {
+    fn from(e: timada_jobs::JobError) -> PushError {
+        PushError::Queue(e.to_string())
+    }
+}
+
+impl From<web_push::WebPushError> for PushError {
+    fn from(e: web_push::WebPushError) -> PushError {
+        use web_push::WebPushError;
+
+        match e {
+            WebPushError::EndpointNotValid | WebPushError::EndpointNotFound => PushError::Gone,
+            WebPushError::PayloadTooLarge => PushError::InvalidMessage(e.to_string()),
+            WebPushError::InvalidCryptoKeys | WebPushError::MissingCryptoKeys => {
+                PushError::InvalidSubscription(e.to_string())
+            }
+            WebPushError::Unauthorized | WebPushError::BadRequest(_) => {
+                PushError::Rejected(e.to_string())
+            }
+            e => PushError::Delivery(e.to_string()),
+        }
+    }
+}
+
+impl From<PushError> for Error {
+    fn from(e: PushError) -> Error {
+        match e {
+            PushError::NotFound => Error::NotFound,
+            PushError::InvalidSubscription(message) | PushError::InvalidMessage(message) => {
+                Error::UnprocessableEntity(message)
+            }
+            e => Error::Internal(e.to_string()),
+        }
+    }
+}
+
+pub type PushResult<T> = Result<T, PushError>;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum NotificationError {
+    #[error("Notification not found")]
+    NotFound,
+
+    #[error("Invalid notification: {0}")]
+    Invalid(String),
+
+    #[error("{0}")]
+    Database(String),
+}
+
+impl From<DieselError> for NotificationError {
+    fn from(e: DieselError) -> NotificationError {
+        match e {
+            DieselError::NotFound => NotificationError::NotFound,
+            e => NotificationError::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<NotificationError> for Error {
+    fn from(e: NotificationError) -> Error {
+        match e {
+            NotificationError::NotFound => Error::NotFound,
+            NotificationError::Invalid(message) => Error::UnprocessableEntity(message),
+            NotificationError::Database(message) => Error::Internal(message),
+        }
+    }
+}
+
+pub type NotificationResult<T> = Result<T, NotificationError>;
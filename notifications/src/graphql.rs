@@ -0,0 +1,228 @@
+use async_graphql::{Connection, Context as GraphQLContext, FieldResult, ID};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use timada_database::{Listener, Pool};
+use timada_http::pagination::PaginationArgs;
+use timada_http::{subscribe, Error, GraphQLContextExt};
+use timada_relay::{from_id, to_id, ConnectionError};
+use uuid::Uuid;
+
+use super::inbox::{
+    find_notification, inbox, mark_all_read, mark_read, mark_unread, unread_count, Notification,
+    NOTIFICATION_CHANNEL,
+};
+
+fn connection_error(e: ConnectionError) -> Error {
+    match e {
+        ConnectionError::Cursor(_) => Error::BadRequest("Invalid cursor".to_owned()),
+        ConnectionError::Custom(message) => Error::BadRequest(message),
+        ConnectionError::Diesel(e) => Error::Internal(e.to_string()),
+    }
+}
+
+fn notification_ids(ids: &[ID]) -> Result<Vec<Uuid>, Error> {
+    ids.iter()
+        .map(|id| match from_id(id) {
+            Ok((type_name, id)) if type_name == "Notification" => Ok(id),
+            _ => Err(Error::BadRequest(format!(
+                "Invalid notification id {}",
+                id.as_str()
+            ))),
+        })
+        .collect()
+}
+
+#[async_graphql::Object]
+impl Notification {
+    #[field]
+    async fn id(&self) -> ID {
+        to_id("Notification", &self.id)
+    }
+
+    #[field]
+    async fn kind(&self) -> &str {
+        self.kind.as_str()
+    }
+
+    #[field]
+    async fn payload(&self) -> String {
+        self.payload.to_string()
+    }
+
+    #[field]
+    async fn read(&self) -> bool {
+        self.is_read()
+    }
+
+    #[field]
+    async fn read_at(&self) -> Option<DateTime<Utc>> {
+        self.read_at
+    }
+
+    #[field]
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+pub struct NotificationQuery;
+
+#[async_graphql::Object]
+impl NotificationQuery {
+    #[field]
+    async fn notifications(
+        &self,
+        ctx: &GraphQLContext<'_>,
+        unread: Option<bool>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> FieldResult<Connection<Notification>> {
+        let args = PaginationArgs::new(
+            first.map(|first| first.max(0) as usize),
+            after,
+            last.map(|last| last.max(0) as usize),
+            before,
+        );
+
+        args.validate()
+            .and_then(|args| {
+                let user_id = ctx.user()?.id;
+                let conn = ctx.conn()?;
+
+                inbox(
+                    &conn,
+                    user_id,
+                    unread.unwrap_or(false),
+                    args.first,
+                    args.after,
+                    args.last,
+                    args.before,
+                )
+                .map_err(connection_error)
+            })
+            .map_err(|e| ctx.extend_error(&e))
+    }
+
+    #[field]
+    async fn unread_notifications_count(&self, ctx: &GraphQLContext<'_>) -> FieldResult<i32> {
+        ctx.user()
+            .and_then(|user| {
+                let conn = ctx.conn()?;
+                Ok(unread_count(&conn, user.id)? as i32)
+            })
+            .map_err(|e| ctx.extend_error(&e))
+    }
+}
+
+pub struct NotificationMutation;
+
+#[async_graphql::Object]
+impl NotificationMutation {
+    #[field]
+    async fn mark_notifications_read(
+        &self,
+        ctx: &GraphQLContext<'_>,
+        ids: Vec<ID>,
+    ) -> FieldResult<i32> {
+        ctx.user()
+            .and_then(|user| {
+                let ids = notification_ids(&ids)?;
+                let conn = ctx.conn()?;
+                Ok(mark_read(&conn, user.id, &ids)? as i32)
+            })
+            .map_err(|e| ctx.extend_error(&e))
+    }
+
+    #[field]
+    async fn mark_notifications_unread(
+        &self,
+        ctx: &GraphQLContext<'_>,
+        ids: Vec<ID>,
+    ) -> FieldResult<i32> {
+        ctx.user()
+            .and_then(|user| {
+                let ids = notification_ids(&ids)?;
+                let conn = ctx.conn()?;
+                Ok(mark_unread(&conn, user.id, &ids)? as i32)
+            })
+            .map_err(|e| ctx.extend_error(&e))
+    }
+
+    #[field]
+    async fn mark_all_notifications_read(&self, ctx: &GraphQLContext<'_>) -> FieldResult<i32> {
+        ctx.user()
+            .and_then(|user| {
+                let conn = ctx.conn()?;
+                Ok(mark_all_read(&conn, user.id)? as i32)
+            })
+            .map_err(|e| ctx.extend_error(&e))
+    }
+}
+
+pub struct NotificationSubscription;
+
+#[async_graphql::Subscription]
+impl NotificationSubscription {
+    // Needs a `Listener` of `NOTIFICATION_CHANNEL` in the schema data.
+    #[field]
+    async fn notification_received(
+        &self,
+        ctx: &GraphQLContext<'_>,
+    ) -> FieldResult<impl Stream<Item = Notification>> {
+        let (user_id, pool, ids) = ctx
+            .context()
+            .and_then(|context| {
+                let listener = ctx
+                    .data::<Listener>()
+                    .map_err(|_| Error::InternalServerError)?;
+                let pool = ctx
+                    .data::<Pool>()
+                    .map_err(|_| Error::InternalServerError)?
+                    .clone();
+                let ids = subscribe::<Uuid>(listener, NOTIFICATION_CHANNEL, context)?;
+
+                Ok((ctx.user()?.id, pool, ids))
+            })
+            .map_err(|e| ctx.extend_error(&e))?;
+
+        Ok(ids.filter_map(move |id| {
+            let pool = pool.clone();
+
+            async move {
+                let res = tokio::task::spawn_blocking(move || {
+                    let conn = pool.get().map_err(|e| e.to_string())?;
+                    find_notification(&conn, user_id, id).map_err(|e| e.to_string())
+                })
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|res| res);
+
+                res.map_err(|e| log::warn!("notification {} not delivered: {}", id, e))
+                    .ok()
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::ID;
+    use timada_relay::to_id;
+    use uuid::Uuid;
+
+    use super::notification_ids;
+
+    #[test]
+    fn ids() {
+        let id = Uuid::new_v4();
+
+        assert_eq!(
+            notification_ids(&[to_id("Notification", &id)]).unwrap(),
+            vec![id]
+        );
+        assert!(notification_ids(&[to_id("Todo", &id)]).is_err());
+        assert!(notification_ids(&[ID::from("invalid")]).is_err());
+    }
+}
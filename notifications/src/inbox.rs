@@ -0,0 +1,269 @@
+use async_graphql::Connection;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde::Serialize;
+use serde_json::Value;
+use timada_http::{publish, ChannelEvent};
+use timada_relay::{ConnectionError, ConnectionResult};
+use uuid::Uuid;
+
+use super::error::{NotificationError, NotificationResult};
+use super::schema::notifications;
+
+pub const NOTIFICATION_CHANNEL: &str = "timada_notifications";
+
+#[derive(Debug, Clone, PartialEq, Queryable, Identifiable)]
+#[table_name = "notifications"]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub payload: Value,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Notification {
+    pub fn is_read(&self) -> bool {
+        self.read_at.is_some()
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "notifications"]
+struct NewNotification<'a> {
+    user_id: Uuid,
+    kind: &'a str,
+    payload: Value,
+}
+
+fn validate_kind(kind: &str) -> NotificationResult<()> {
+    let valid = !kind.is_empty()
+        && kind.len() <= 100
+        && kind
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c));
+
+    if !valid {
+        return Err(NotificationError::Invalid(format!(
+            "kind {} must match [a-z0-9._-]{{1,100}}",
+            kind
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn notify<T: Serialize>(
+    conn: &PgConnection,
+    user_id: Uuid,
+    kind: &str,
+    payload: &T,
+) -> NotificationResult<Notification> {
+    validate_kind(kind)?;
+
+    let payload =
+        serde_json::to_value(payload).map_err(|e| NotificationError::Invalid(e.to_string()))?;
+
+    let notification: Notification = diesel::insert_into(notifications::table)
+        .values(&NewNotification {
+            user_id,
+            kind,
+            payload,
+        })
+        .get_result(conn)?;
+
+    // Postgres holds the NOTIFY until the transaction commits and drops it on rollback,
+    // subscribers load the notification by id once it is visible.
+    publish(
+        conn,
+        NOTIFICATION_CHANNEL,
+        &ChannelEvent::user(user_id, notification.id),
+    )
+    .map_err(|e| NotificationError::Database(e.to_string()))?;
+
+    Ok(notification)
+}
+
+pub fn find_notification(
+    conn: &PgConnection,
+    user_id: Uuid,
+    id: Uuid,
+) -> NotificationResult<Notification> {
+    Ok(notifications::table
+        .filter(notifications::id.eq(id))
+        .filter(notifications::user_id.eq(user_id))
+        .first(conn)?)
+}
+
+pub fn unread_count(conn: &PgConnection, user_id: Uuid) -> NotificationResult<i64> {
+    Ok(notifications::table
+        .filter(notifications::user_id.eq(user_id))
+        .filter(notifications::read_at.is_null())
+        .count()
+        .get_result(conn)?)
+}
+
+pub fn mark_read(conn: &PgConnection, user_id: Uuid, ids: &[Uuid]) -> NotificationResult<usize> {
+    Ok(diesel::update(
+        notifications::table
+            .filter(notifications::user_id.eq(user_id))
+            .filter(notifications::id.eq_any(ids))
+            .filter(notifications::read_at.is_null()),
+    )
+    .set(notifications::read_at.eq(Utc::now()))
+    .execute(conn)?)
+}
+
+pub fn mark_all_read(conn: &PgConnection, user_id: Uuid) -> NotificationResult<usize> {
+    Ok(diesel::update(
+        notifications::table
+            .filter(notifications::user_id.eq(user_id))
+            .filter(notifications::read_at.is_null()),
+    )
+    .set(notifications::read_at.eq(Utc::now()))
+    .execute(conn)?)
+}
+
+pub fn mark_unread(conn: &PgConnection, user_id: Uuid, ids: &[Uuid]) -> NotificationResult<usize> {
+    Ok(diesel::update(
+        notifications::table
+            .filter(notifications::user_id.eq(user_id))
+            .filter(notifications::id.eq_any(ids)),
+    )
+    .set(notifications::read_at.eq(None::<DateTime<Utc>>))
+    .execute(conn)?)
+}
+
+fn to_inbox_cursor(notification: &Notification) -> (String, String) {
+    (
+        notification.id.to_string(),
+        notification.created_at.to_rfc3339(),
+    )
+}
+
+fn from_inbox_cursor(
+    key_value: &str,
+    order_value: &str,
+) -> ConnectionResult<(Uuid, DateTime<Utc>)> {
+    let key_value =
+        Uuid::parse_str(key_value).map_err(|e| ConnectionError::Custom(e.to_string()))?;
+    let order_value = DateTime::parse_from_rfc3339(order_value)
+        .map(DateTime::<Utc>::from)
+        .map_err(|e| ConnectionError::Custom(e.to_string()))?;
+
+    Ok((key_value, order_value))
+}
+
+pub fn inbox(
+    conn: &PgConnection,
+    user_id: Uuid,
+    unread_only: bool,
+    first: Option<usize>,
+    after: Option<String>,
+    last: Option<usize>,
+    before: Option<String>,
+) -> ConnectionResult<Connection<Notification>> {
+    use super::schema::notifications::dsl::{created_at, id};
+
+    let mut table = notifications::table
+        .filter(notifications::user_id.eq(user_id))
+        .into_boxed();
+
+    if unread_only {
+        table = table.filter(notifications::read_at.is_null());
+    }
+
+    timada_relay::resolve_connection!(
+        Notification,
+        conn,
+        table,
+        first,
+        after,
+        last,
+        before,
+        id,
+        created_at,
+        to_inbox_cursor,
+        from_inbox_cursor
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+    use timada_database::testing::test_connection;
+    use uuid::Uuid;
+
+    use super::{from_inbox_cursor, inbox, notify, to_inbox_cursor, validate_kind, Notification};
+
+    #[test]
+    fn kind() {
+        assert!(validate_kind("todo.reminder").is_ok());
+        assert!(validate_kind("").is_err());
+        assert!(validate_kind("Todo Reminder").is_err());
+        assert!(validate_kind(&"a".repeat(101)).is_err());
+    }
+
+    #[test]
+    fn cursor() {
+        let notification = Notification {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            kind: "todo.reminder".to_owned(),
+            payload: json!({ "todo_id": 1 }),
+            read_at: None,
+            created_at: Utc::now(),
+        };
+
+        let (key_value, order_value) = to_inbox_cursor(&notification);
+
+        assert_eq!(
+            from_inbox_cursor(&key_value, &order_value).unwrap(),
+            (notification.id, notification.created_at)
+        );
+        assert!(from_inbox_cursor("invalid", &order_value).is_err());
+        assert!(!notification.is_read());
+    }
+
+    #[test]
+    fn inbox_pages() {
+        // Rows of one transaction share CURRENT_TIMESTAMP, pages only differ by id.
+        let conn = test_connection("timada_notifications_test");
+        let user_id = Uuid::new_v4();
+        let other_id = Uuid::new_v4();
+
+        for n in 0..3 {
+            notify(&conn, user_id, "todo.reminder", &json!({ "n": n })).unwrap();
+            notify(&conn, other_id, "todo.reminder", &json!({ "n": n })).unwrap();
+        }
+
+        let mut seen = Vec::new();
+        let mut after = None;
+
+        loop {
+            let page = inbox(&conn, user_id, false, Some(1), after, None, None).unwrap();
+
+            let last = match page.nodes.last() {
+                Some((_, _, notification)) => notification.clone(),
+                None => break,
+            };
+
+            assert!(page.nodes.iter().all(|(_, _, n)| n.user_id == user_id));
+
+            let (key_value, order_value) = to_inbox_cursor(&last);
+            after = Some(timada_relay::to_cursor(&key_value, &order_value));
+            seen.push(last.id);
+        }
+
+        assert_eq!(seen.len(), 3);
+
+        let before = after;
+        let page = inbox(&conn, user_id, false, None, None, Some(5), before).unwrap();
+
+        assert_eq!(page.nodes.len(), 2);
+        assert!(page.nodes.iter().all(|(_, _, n)| n.user_id == user_id));
+    }
+}
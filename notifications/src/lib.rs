@@ -0,0 +1,37 @@
+#[macro_use]
+extern crate diesel;
+
+#[macro_use]
+extern crate diesel_migrations;
+
+#[macro_use]
+extern crate serde;
+
+#[macro_use]
+extern crate thiserror;
+
+mod error;
+mod graphql;
+mod inbox;
+mod migration;
+mod push;
+mod schema;
+mod subscription;
+mod vapid;
+
+pub use crate::error::{NotificationError, NotificationResult, PushError, PushResult};
+pub use crate::graphql::{NotificationMutation, NotificationQuery, NotificationSubscription};
+pub use crate::inbox::{
+    find_notification, inbox, mark_all_read, mark_read, mark_unread, notify, unread_count,
+    Notification, NOTIFICATION_CHANNEL,
+};
+pub use crate::migration::migrate;
+pub use crate::push::{
+    send_push, send_push_with_ttl, set_push_sender, DeliverPush, PushMessage, PushSender,
+    TestSender, WebPushSender, DEFAULT_TTL, MAX_PAYLOAD_SIZE,
+};
+pub use crate::subscription::{
+    delete_expired_subscriptions, expire_subscription, find_subscription, list_subscriptions,
+    subscribe, unsubscribe, NewSubscription, PushSubscription, SubscriptionKeys, MAX_FAILURES,
+};
+pub use crate::vapid::VapidConfig;
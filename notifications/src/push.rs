@@ -0,0 +1,406 @@
+use diesel::PgConnection;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use timada_jobs::{enqueue, Job, JobContext, JobError, JobResult, RetryPolicy};
+use uuid::Uuid;
+use web_push::{
+    ContentEncoding, SubscriptionInfo, VapidSignatureBuilder, WebPushClient, WebPushMessageBuilder,
+};
+
+use super::error::{PushError, PushResult};
+use super::subscription::{
+    expire_subscription, find_subscription, list_subscriptions, record_failure, record_success,
+    PushSubscription,
+};
+use super::vapid::VapidConfig;
+
+// Push services accept 4096 bytes once encrypted, keep room for the aes128gcm header.
+pub const MAX_PAYLOAD_SIZE: usize = 3993;
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PushMessage {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl PushMessage {
+    pub fn new(title: &str) -> Self {
+        PushMessage {
+            title: title.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.body = Some(body.to_owned());
+        self
+    }
+
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_owned());
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tag = Some(tag.to_owned());
+        self
+    }
+
+    pub fn data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    pub fn payload(&self) -> PushResult<Vec<u8>> {
+        let payload =
+            serde_json::to_vec(self).map_err(|e| PushError::InvalidMessage(e.to_string()))?;
+
+        if payload.len() > MAX_PAYLOAD_SIZE {
+            return Err(PushError::InvalidMessage(format!(
+                "payload is {} bytes, maximum is {}",
+                payload.len(),
+                MAX_PAYLOAD_SIZE
+            )));
+        }
+
+        Ok(payload)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PushSender: Send + Sync {
+    async fn send(
+        &self,
+        subscription: &PushSubscription,
+        payload: &[u8],
+        ttl: Duration,
+    ) -> PushResult<()>;
+}
+
+pub struct WebPushSender {
+    config: VapidConfig,
+    client: WebPushClient,
+}
+
+impl WebPushSender {
+    pub fn new(config: VapidConfig) -> PushResult<Self> {
+        config.validate()?;
+
+        Ok(WebPushSender {
+            config,
+            client: WebPushClient::new(),
+        })
+    }
+
+    pub fn from_env() -> PushResult<Self> {
+        Self::new(VapidConfig::from_env())
+    }
+
+    pub fn config(&self) -> &VapidConfig {
+        &self.config
+    }
+}
+
+#[async_trait::async_trait]
+impl PushSender for WebPushSender {
+    async fn send(
+        &self,
+        subscription: &PushSubscription,
+        payload: &[u8],
+        ttl: Duration,
+    ) -> PushResult<()> {
+        let info = SubscriptionInfo::new(
+            &subscription.endpoint,
+            &subscription.p256dh,
+            &subscription.auth,
+        );
+
+        let mut signature =
+            VapidSignatureBuilder::from_pem(self.config.private_key.expose().as_bytes(), &info)
+                .map_err(|e| PushError::Vapid(e.to_string()))?;
+        signature.add_claim("sub", self.config.subject.as_str());
+
+        let mut builder = WebPushMessageBuilder::new(&info)?;
+        builder.set_payload(ContentEncoding::Aes128Gcm, payload);
+        builder.set_ttl(ttl.as_secs() as u32);
+        builder.set_vapid_signature(
+            signature
+                .build()
+                .map_err(|e| PushError::Vapid(e.to_string()))?,
+        );
+
+        Ok(self.client.send(builder.build()?).await?)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct TestSender {
+    sent: Arc<Mutex<Vec<(String, Vec<u8>)>>>,
+    gone: Arc<Mutex<HashSet<String>>>,
+}
+
+impl TestSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sent(&self) -> Vec<(String, Vec<u8>)> {
+        self.sent
+            .lock()
+            .map(|sent| sent.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn sent_to(&self, endpoint: &str) -> Vec<PushMessage> {
+        self.sent()
+            .into_iter()
+            .filter(|(sent_endpoint, _)| sent_endpoint == endpoint)
+            .filter_map(|(_, payload)| serde_json::from_slice(&payload).ok())
+            .collect()
+    }
+
+    pub fn mark_gone(&self, endpoint: &str) {
+        if let Ok(mut gone) = self.gone.lock() {
+            gone.insert(endpoint.to_owned());
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut sent) = self.sent.lock() {
+            sent.clear();
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PushSender for TestSender {
+    async fn send(
+        &self,
+        subscription: &PushSubscription,
+        payload: &[u8],
+        _ttl: Duration,
+    ) -> PushResult<()> {
+        let is_gone = self
+            .gone
+            .lock()
+            .map(|gone| gone.contains(&subscription.endpoint))
+            .unwrap_or(false);
+
+        if is_gone {
+            return Err(PushError::Gone);
+        }
+
+        if let Ok(mut sent) = self.sent.lock() {
+            sent.push((subscription.endpoint.clone(), payload.to_vec()));
+        }
+
+        Ok(())
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SENDER: RwLock<Option<Arc<dyn PushSender>>> = RwLock::new(None);
+}
+
+pub fn set_push_sender<S: PushSender + 'static>(sender: S) {
+    if let Ok(mut current) = SENDER.write() {
+        *current = Some(Arc::new(sender));
+    }
+}
+
+fn push_sender() -> Option<Arc<dyn PushSender>> {
+    match SENDER.read() {
+        Ok(sender) => sender.clone(),
+        Err(e) => e.into_inner().clone(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliverPush {
+    pub subscription_id: Uuid,
+    pub message: PushMessage,
+    pub ttl: u64,
+}
+
+async fn blocking<T, F>(ctx: &JobContext, f: F) -> JobResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&PgConnection) -> PushResult<T> + Send + 'static,
+{
+    let pool = ctx.pool.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = pool.get().map_err(JobError::failed)?;
+        f(&conn).map_err(JobError::failed)
+    })
+    .await
+    .map_err(JobError::failed)?
+}
+
+#[async_trait::async_trait]
+impl Job for DeliverPush {
+    const NAME: &'static str = "notifications.push";
+    const QUEUE: &'static str = "notifications";
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .max_attempts(5)
+            .base_delay(Duration::from_secs(30))
+            .max_delay(Duration::from_secs(60 * 60))
+    }
+
+    async fn run(&self, ctx: &JobContext) -> JobResult<()> {
+        let sender =
+            push_sender().ok_or_else(|| JobError::failed("Push sender is not configured"))?;
+        let id = self.subscription_id;
+
+        let subscription = match blocking(ctx, move |conn| match find_subscription(conn, id) {
+            Ok(subscription) => Ok(Some(subscription)),
+            Err(PushError::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        })
+        .await?
+        {
+            Some(subscription) if !subscription.is_expired() => subscription,
+            _ => return Ok(()),
+        };
+
+        let payload = self.message.payload().map_err(JobError::permanent)?;
+
+        match sender
+            .send(&subscription, &payload, Duration::from_secs(self.ttl))
+            .await
+        {
+            Ok(()) => blocking(ctx, move |conn| record_success(conn, id)).await,
+            Err(PushError::Gone) => {
+                log::info!("push subscription {} is gone, expiring it", id);
+                blocking(ctx, move |conn| expire_subscription(conn, id)).await
+            }
+            Err(e) => {
+                let expired = blocking(ctx, move |conn| record_failure(conn, id)).await?;
+
+                match e {
+                    _ if expired => Err(JobError::permanent(e)),
+                    PushError::InvalidSubscription(_)
+                    | PushError::InvalidMessage(_)
+                    | PushError::Rejected(_) => Err(JobError::permanent(e)),
+                    e => Err(JobError::failed(e)),
+                }
+            }
+        }
+    }
+}
+
+pub fn send_push(
+    conn: &PgConnection,
+    user_id: Uuid,
+    message: &PushMessage,
+) -> PushResult<Vec<Uuid>> {
+    send_push_with_ttl(conn, user_id, message, DEFAULT_TTL)
+}
+
+pub fn send_push_with_ttl(
+    conn: &PgConnection,
+    user_id: Uuid,
+    message: &PushMessage,
+    ttl: Duration,
+) -> PushResult<Vec<Uuid>> {
+    message.payload()?;
+
+    list_subscriptions(conn, user_id)?
+        .into_iter()
+        .map(|subscription| {
+            let job = DeliverPush {
+                subscription_id: subscription.id,
+                message: message.clone(),
+                ttl: ttl.as_secs(),
+            };
+
+            Ok(enqueue(conn, &job)?)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    use super::{PushMessage, PushSender, TestSender, MAX_PAYLOAD_SIZE};
+    use crate::error::PushError;
+    use crate::subscription::PushSubscription;
+
+    fn subscription(endpoint: &str) -> PushSubscription {
+        PushSubscription {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            endpoint: endpoint.to_owned(),
+            p256dh: String::new(),
+            auth: String::new(),
+            user_agent: None,
+            failures: 0,
+            last_used_at: None,
+            expired_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn payload() {
+        let message = PushMessage::new("Reminder")
+            .body("Buy milk")
+            .url("/todos/1")
+            .data(json!({ "todo_id": 1 }));
+
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&message.payload().unwrap()).unwrap(),
+            json!({
+                "title": "Reminder",
+                "body": "Buy milk",
+                "url": "/todos/1",
+                "data": { "todo_id": 1 }
+            })
+        );
+
+        let message = PushMessage::new(&"a".repeat(MAX_PAYLOAD_SIZE));
+        assert!(matches!(
+            message.payload(),
+            Err(PushError::InvalidMessage(_))
+        ));
+    }
+
+    #[test]
+    fn test_sender() {
+        let sender = TestSender::new();
+        let message = PushMessage::new("Reminder");
+        let payload = message.payload().unwrap();
+        let ttl = Duration::from_secs(60);
+
+        futures::executor::block_on(sender.send(&subscription("https://push/1"), &payload, ttl))
+            .unwrap();
+        assert_eq!(sender.sent_to("https://push/1"), vec![message]);
+
+        sender.mark_gone("https://push/2");
+        assert_eq!(
+            futures::executor::block_on(sender.send(
+                &subscription("https://push/2"),
+                &payload,
+                ttl
+            )),
+            Err(PushError::Gone)
+        );
+    }
+}
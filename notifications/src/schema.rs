@@ -0,0 +1,25 @@
+table! {
+    push_subscriptions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        endpoint -> Text,
+        p256dh -> Varchar,
+        auth -> Varchar,
+        user_agent -> Nullable<Text>,
+        failures -> Int4,
+        last_used_at -> Nullable<Timestamptz>,
+        expired_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    notifications (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        kind -> Varchar,
+        payload -> Jsonb,
+        read_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
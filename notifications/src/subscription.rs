@@ -0,0 +1,277 @@
+use chrono::{DateTime, Duration, Utc};
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use url::Url;
+use uuid::Uuid;
+
+use super::error::{PushError, PushResult};
+use super::schema::push_subscriptions;
+use super::vapid::decode_key;
+
+pub const MAX_FAILURES: i32 = 10;
+
+// Push services of Chrome, Firefox, Safari and Edge, the server posts to the endpoint so
+// anything else would let a client make it call arbitrary hosts.
+const PUSH_SERVICE_DOMAINS: [&str; 5] = [
+    "fcm.googleapis.com",
+    "android.googleapis.com",
+    "push.services.mozilla.com",
+    "push.apple.com",
+    "notify.windows.com",
+];
+
+fn is_push_service(endpoint: &str) -> bool {
+    let url = match Url::parse(endpoint) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+
+    if url.scheme() != "https" || url.port().is_some() || url.password().is_some() {
+        return false;
+    }
+
+    let host = match url.host_str() {
+        Some(host) => host.to_lowercase(),
+        None => return false,
+    };
+
+    PUSH_SERVICE_DOMAINS
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Identifiable)]
+#[table_name = "push_subscriptions"]
+pub struct PushSubscription {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub endpoint: String,
+    pub p256dh: String,
+    pub auth: String,
+    pub user_agent: Option<String>,
+    pub failures: i32,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expired_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PushSubscription {
+    pub fn is_expired(&self) -> bool {
+        self.expired_at.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+// Mirrors the browser's `PushSubscription.toJSON()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewSubscription {
+    pub endpoint: String,
+    pub keys: SubscriptionKeys,
+}
+
+impl NewSubscription {
+    pub fn validate(&self) -> PushResult<()> {
+        if !is_push_service(&self.endpoint) {
+            return Err(PushError::InvalidSubscription(
+                "endpoint must be an https URL of a known push service".to_owned(),
+            ));
+        }
+
+        match decode_key(&self.keys.p256dh) {
+            Some(key) if key.len() == 65 => {}
+            _ => {
+                return Err(PushError::InvalidSubscription(
+                    "p256dh must be a base64url encoded P-256 public key".to_owned(),
+                ))
+            }
+        }
+
+        match decode_key(&self.keys.auth) {
+            Some(key) if key.len() == 16 => {}
+            _ => {
+                return Err(PushError::InvalidSubscription(
+                    "auth must be a base64url encoded 16 bytes secret".to_owned(),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "push_subscriptions"]
+struct SubscriptionRow<'a> {
+    user_id: Uuid,
+    endpoint: &'a str,
+    p256dh: &'a str,
+    auth: &'a str,
+    user_agent: Option<&'a str>,
+}
+
+pub fn subscribe(
+    conn: &PgConnection,
+    user_id: Uuid,
+    subscription: &NewSubscription,
+    user_agent: Option<&str>,
+) -> PushResult<PushSubscription> {
+    subscription.validate()?;
+
+    Ok(diesel::insert_into(push_subscriptions::table)
+        .values(&SubscriptionRow {
+            user_id,
+            endpoint: &subscription.endpoint,
+            p256dh: &subscription.keys.p256dh,
+            auth: &subscription.keys.auth,
+            user_agent,
+        })
+        .on_conflict(push_subscriptions::endpoint)
+        .do_update()
+        .set((
+            push_subscriptions::user_id.eq(excluded(push_subscriptions::user_id)),
+            push_subscriptions::p256dh.eq(excluded(push_subscriptions::p256dh)),
+            push_subscriptions::auth.eq(excluded(push_subscriptions::auth)),
+            push_subscriptions::user_agent.eq(excluded(push_subscriptions::user_agent)),
+            push_subscriptions::failures.eq(0),
+            push_subscriptions::expired_at.eq(None::<DateTime<Utc>>),
+        ))
+        .get_result(conn)?)
+}
+
+pub fn unsubscribe(conn: &PgConnection, user_id: Uuid, endpoint: &str) -> PushResult<bool> {
+    let deleted = diesel::delete(
+        push_subscriptions::table
+            .filter(push_subscriptions::user_id.eq(user_id))
+            .filter(push_subscriptions::endpoint.eq(endpoint)),
+    )
+    .execute(conn)?;
+
+    Ok(deleted > 0)
+}
+
+pub fn find_subscription(conn: &PgConnection, id: Uuid) -> PushResult<PushSubscription> {
+    Ok(push_subscriptions::table.find(id).first(conn)?)
+}
+
+pub fn list_subscriptions(conn: &PgConnection, user_id: Uuid) -> PushResult<Vec<PushSubscription>> {
+    Ok(push_subscriptions::table
+        .filter(push_subscriptions::user_id.eq(user_id))
+        .filter(push_subscriptions::expired_at.is_null())
+        .order(push_subscriptions::created_at.asc())
+        .load(conn)?)
+}
+
+pub fn expire_subscription(conn: &PgConnection, id: Uuid) -> PushResult<()> {
+    diesel::update(push_subscriptions::table.find(id))
+        .set(push_subscriptions::expired_at.eq(Utc::now()))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub(crate) fn record_success(conn: &PgConnection, id: Uuid) -> PushResult<()> {
+    diesel::update(push_subscriptions::table.find(id))
+        .set((
+            push_subscriptions::failures.eq(0),
+            push_subscriptions::last_used_at.eq(Utc::now()),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// Expires the subscription once it has failed `MAX_FAILURES` times in a row.
+pub(crate) fn record_failure(conn: &PgConnection, id: Uuid) -> PushResult<bool> {
+    let subscription: PushSubscription = diesel::update(push_subscriptions::table.find(id))
+        .set(push_subscriptions::failures.eq(push_subscriptions::failures + 1))
+        .get_result(conn)?;
+
+    if subscription.failures < MAX_FAILURES {
+        return Ok(false);
+    }
+
+    expire_subscription(conn, id)?;
+
+    Ok(true)
+}
+
+pub fn delete_expired_subscriptions(
+    conn: &PgConnection,
+    older_than: Duration,
+) -> PushResult<usize> {
+    Ok(diesel::delete(
+        push_subscriptions::table
+            .filter(push_subscriptions::expired_at.lt(Utc::now() - older_than)),
+    )
+    .execute(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_push_service, NewSubscription, SubscriptionKeys};
+
+    fn subscription() -> NewSubscription {
+        serde_json::from_value(serde_json::json!({
+            "endpoint": "https://fcm.googleapis.com/fcm/send/abc",
+            "keys": {
+                "p256dh": "BLMbF9ffKBiWQLCKvTHb6LO8Nb6dcUh6TItC455vu2kElga6PQvUmaFyCdykxY2nOSSL3yKgfbmFLRTUaGv4yV8",
+                "auth": "xS03Fi5ErfTNH_l9WHE9Ig"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn validate() {
+        assert!(subscription().validate().is_ok());
+
+        let mut invalid = subscription();
+        invalid.endpoint = "http://push.example.com".to_owned();
+        assert!(invalid.validate().is_err());
+
+        let invalid = NewSubscription {
+            keys: SubscriptionKeys {
+                p256dh: "invalid".to_owned(),
+                ..subscription().keys
+            },
+            ..subscription()
+        };
+        assert!(invalid.validate().is_err());
+
+        let invalid = NewSubscription {
+            keys: SubscriptionKeys {
+                auth: "c2hvcnQ".to_owned(),
+                ..subscription().keys
+            },
+            ..subscription()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn push_service() {
+        assert!(is_push_service("https://fcm.googleapis.com/fcm/send/abc"));
+        assert!(is_push_service(
+            "https://updates.push.services.mozilla.com/wpush/v2/abc"
+        ));
+        assert!(is_push_service("https://web.push.apple.com/abc"));
+        assert!(is_push_service(
+            "https://wns2-par02p.notify.windows.com/w/?token=abc"
+        ));
+
+        assert!(!is_push_service("http://fcm.googleapis.com/fcm/send/abc"));
+        assert!(!is_push_service("https://fcm.googleapis.com:8443/abc"));
+        assert!(!is_push_service(
+            "https://evilfcm.googleapis.com.evil.co/abc"
+        ));
+        assert!(!is_push_service("https://169.254.169.254/latest/meta-data"));
+        assert!(!is_push_service("https://localhost/abc"));
+        assert!(!is_push_service("not an url"));
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+use timada_http::Error;
+
+#[derive(Debug, PartialEq, Error)]
+pub enum RateLimitError {
+    #[error("Rate limit exceeded, retry in {}s", retry_seconds(.0))]
+    Limited(Duration),
+
+    #[error("Redis error: {0}")]
+    Redis(String),
+
+    #[error("Pool error: {0}")]
+    Pool(String),
+}
+
+impl From<redis::RedisError> for RateLimitError {
+    fn from(e: redis::RedisError) -> RateLimitError {
+        RateLimitError::Redis(e.to_string())
+    }
+}
+
+impl From<r2d2::Error> for RateLimitError {
+    fn from(e: r2d2::Error) -> RateLimitError {
+        RateLimitError::Pool(e.to_string())
+    }
+}
+
+impl From<RateLimitError> for Error {
+    fn from(e: RateLimitError) -> Error {
+        match e {
+            RateLimitError::Limited(_) => Error::TooManyRequests(e.to_string()),
+            e => Error::Internal(e.to_string()),
+        }
+    }
+}
+
+// Rounded up, clients retrying after a rounded down delay would be limited again.
+pub(crate) fn retry_seconds(retry_after: &Duration) -> u64 {
+    (retry_after.as_millis() as u64 + 999) / 1000
+}
+
+pub type RateLimitResult<T> = Result<T, RateLimitError>;
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use timada_http::Error;
+
+    use super::RateLimitError;
+
+    #[test]
+    fn into_http_error() {
+        assert_eq!(
+            Error::from(RateLimitError::Limited(Duration::from_millis(1500))),
+            Error::TooManyRequests("Rate limit exceeded, retry in 2s".to_owned())
+        );
+        assert_eq!(
+            Error::from(RateLimitError::Redis("connection refused".to_owned())),
+            Error::Internal("Redis error: connection refused".to_owned())
+        );
+    }
+}
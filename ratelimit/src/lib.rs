@@ -0,0 +1,12 @@
+#[macro_use]
+extern crate thiserror;
+
+mod error;
+mod limiter;
+mod middleware;
+mod policy;
+
+pub use crate::error::{RateLimitError, RateLimitResult};
+pub use crate::limiter::RateLimiter;
+pub use crate::middleware::{KeyBy, RateLimit, RateLimitMiddleware};
+pub use crate::policy::{Algorithm, Decision, Policy};
@@ -0,0 +1,142 @@
+use redis::Script;
+use std::sync::Arc;
+use timada_cache::RedisCache;
+use timada_http::Context;
+use uuid::Uuid;
+
+use super::error::{RateLimitError, RateLimitResult};
+use super::policy::{Algorithm, Decision, Policy};
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    cache: RedisCache,
+    policy: Arc<Policy>,
+    script: Arc<Script>,
+}
+
+impl RateLimiter {
+    pub fn new(cache: RedisCache, policy: Policy) -> Self {
+        RateLimiter {
+            cache,
+            script: Arc::new(policy.script()),
+            policy: Arc::new(policy),
+        }
+    }
+
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    fn key(&self, key: &str) -> String {
+        self.cache
+            .key(&format!("ratelimit:{}:{}", self.policy.name, key))
+    }
+
+    pub fn check_blocking(&self, key: &str, cost: u64) -> RateLimitResult<Decision> {
+        let mut conn = self.cache.pool().get()?;
+        let mut invocation = self.script.prepare_invoke();
+
+        invocation
+            .key(self.key(key))
+            .arg(self.policy.limit)
+            .arg(self.policy.period_millis())
+            .arg(cost);
+
+        if self.policy.algorithm == Algorithm::SlidingWindow {
+            invocation.arg(Uuid::new_v4().to_simple().to_string());
+        }
+
+        let reply = invocation.invoke(&mut *conn)?;
+
+        Ok(self.policy.decision(reply))
+    }
+
+    // `limiter.check(&format!("export:{}", user.id), 10).await?` in resolvers.
+    pub async fn check(&self, key: &str, cost: u64) -> RateLimitResult<Decision> {
+        let limiter = self.clone();
+        let key = key.to_owned();
+
+        tokio::task::spawn_blocking(move || limiter.check_blocking(&key, cost))
+            .await
+            .map_err(|e| RateLimitError::Pool(e.to_string()))?
+    }
+
+    // Same as `check` but a denied request is an error, `Error::TooManyRequests` once
+    // converted to a `timada_http::Error`.
+    pub async fn ensure(&self, key: &str, cost: u64) -> RateLimitResult<Decision> {
+        let decision = self.check(key, cost).await?;
+
+        match decision.retry_after {
+            Some(retry_after) if !decision.allowed => Err(RateLimitError::Limited(retry_after)),
+            _ => Ok(decision),
+        }
+    }
+
+    // Keyed like the middleware with `KeyBy::User` then `KeyBy::Ip`.
+    pub async fn ensure_context(&self, context: &Context, cost: u64) -> RateLimitResult<Decision> {
+        let key = match (context.user.as_ref(), context.client_ip) {
+            (Some(user), _) => format!("user:{}", user.id),
+            (None, Some(ip)) => format!("ip:{}", ip),
+            (None, None) => "anonymous".to_owned(),
+        };
+
+        self.ensure(&key, cost).await
+    }
+}
+
+// Runs the scripts against the Redis of `REDIS_URL`, every test has its own namespace.
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use timada_cache::{RedisCache, RedisConfig};
+    use uuid::Uuid;
+
+    use super::RateLimiter;
+    use crate::policy::Policy;
+
+    fn limiter(policy: Policy) -> RateLimiter {
+        let url =
+            std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_owned());
+        let namespace = format!("test-{}", Uuid::new_v4());
+        let cache = RedisCache::new(RedisConfig::new(&url, &namespace)).unwrap();
+
+        RateLimiter::new(cache, policy)
+    }
+
+    #[test]
+    fn token_bucket() {
+        let limiter = limiter(Policy::token_bucket("api", 3, Duration::from_secs(60)));
+
+        let decision = limiter.check_blocking("user:1", 2).unwrap();
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 1);
+
+        let decision = limiter.check_blocking("user:1", 2).unwrap();
+        assert!(!decision.allowed);
+        assert_eq!(decision.remaining, 1);
+        assert!(decision.retry_after.unwrap() > Duration::from_secs(0));
+        assert!(decision.retry_after.unwrap() <= Duration::from_secs(20));
+
+        assert!(limiter.check_blocking("user:1", 1).unwrap().allowed);
+        assert!(limiter.check_blocking("user:2", 3).unwrap().allowed);
+    }
+
+    #[test]
+    fn sliding_window() {
+        let limiter = limiter(Policy::sliding_window("login", 3, Duration::from_secs(60)));
+
+        let decision = limiter.check_blocking("ip:127.0.0.1", 2).unwrap();
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 1);
+
+        let decision = limiter.check_blocking("ip:127.0.0.1", 2).unwrap();
+        assert!(!decision.allowed);
+        assert_eq!(decision.remaining, 1);
+        assert!(decision.retry_after.unwrap() > Duration::from_secs(59));
+
+        let decision = limiter.check_blocking("ip:127.0.0.1", 1).unwrap();
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert!(!limiter.check_blocking("ip:127.0.0.1", 1).unwrap().allowed);
+    }
+}
@@ -0,0 +1,247 @@
+use actix_service::{Service, Transform};
+use actix_web::body::Body;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::{Error, FromRequest, HttpRequest, HttpResponse};
+use futures::future::{ok, FutureExt, LocalBoxFuture, Ready};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use timada_http::{ClientIp, User};
+
+use super::error::retry_seconds;
+use super::limiter::RateLimiter;
+use super::policy::Decision;
+
+const LIMIT_HEADER: &str = "x-ratelimit-limit";
+const REMAINING_HEADER: &str = "x-ratelimit-remaining";
+const RETRY_AFTER_HEADER: &str = "retry-after";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyBy {
+    User,
+    Ip,
+    // Header carrying an API token, hashed so tokens never end up in Redis keys.
+    ApiToken(String),
+}
+
+impl KeyBy {
+    fn key(&self, req: &HttpRequest) -> Option<String> {
+        match self {
+            KeyBy::User => User::try_from(req)
+                .ok()
+                .map(|user| format!("user:{}", user.id)),
+            KeyBy::Ip => ClientIp::extract(req)
+                .now_or_never()
+                .and_then(|ip| ip.ok())
+                .and_then(|ClientIp(ip)| ip)
+                .map(|ip| format!("ip:{}", ip)),
+            KeyBy::ApiToken(header) => req
+                .headers()
+                .get(header.as_str())
+                .and_then(|value| value.to_str().ok())
+                .map(|token| format!("token:{}", hex::encode(Sha256::digest(token.as_bytes())))),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct RateLimitConfig {
+    limiter: RateLimiter,
+    keys: Vec<KeyBy>,
+    cost: u64,
+    fail_open: bool,
+}
+
+impl RateLimitConfig {
+    // First key that can be resolved, requests without any are not limited.
+    fn key(&self, req: &HttpRequest) -> Option<String> {
+        self.keys.iter().find_map(|key| key.key(req))
+    }
+}
+
+pub struct RateLimit {
+    config: RateLimitConfig,
+}
+
+impl RateLimit {
+    pub fn new(limiter: RateLimiter) -> Self {
+        RateLimit {
+            config: RateLimitConfig {
+                limiter,
+                keys: vec![KeyBy::User, KeyBy::Ip],
+                cost: 1,
+                fail_open: true,
+            },
+        }
+    }
+
+    pub fn keys(mut self, keys: Vec<KeyBy>) -> Self {
+        self.config.keys = keys;
+        self
+    }
+
+    pub fn cost(mut self, cost: u64) -> Self {
+        self.config.cost = cost;
+        self
+    }
+
+    // Rejects requests when Redis is unavailable instead of letting them through.
+    pub fn fail_closed(mut self) -> Self {
+        self.config.fail_open = false;
+        self
+    }
+}
+
+fn insert_header(headers: &mut HeaderMap, name: &'static str, value: u64) {
+    headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+}
+
+fn insert_headers(headers: &mut HeaderMap, decision: &Decision) {
+    insert_header(headers, LIMIT_HEADER, decision.limit);
+    insert_header(headers, REMAINING_HEADER, decision.remaining);
+
+    if let Some(retry_after) = decision.retry_after.as_ref() {
+        insert_header(headers, RETRY_AFTER_HEADER, retry_seconds(retry_after));
+    }
+}
+
+fn limited(decision: &Decision) -> HttpResponse {
+    let mut res = HttpResponse::TooManyRequests().json(json!({
+        "message": "Too Many Requests"
+    }));
+
+    insert_headers(res.headers_mut(), decision);
+
+    res
+}
+
+impl<S> Transform<S> for RateLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            config: Rc::new(self.config.clone()),
+        })
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    // Shared with the response future, the service is only called once the limit is checked.
+    service: Rc<RefCell<S>>,
+    config: Rc<RateLimitConfig>,
+}
+
+impl<S> Service for RateLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let key = match self.config.key(req.request()) {
+            Some(key) => key,
+            None => return Box::pin(self.service.borrow_mut().call(req)),
+        };
+
+        let service = self.service.clone();
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            let decision = match config.limiter.check(&key, config.cost).await {
+                Ok(decision) => decision,
+                Err(e) if config.fail_open => {
+                    log::error!("Rate limit check failed: {}", e);
+                    return service.borrow_mut().call(req).await;
+                }
+                Err(e) => return Err(timada_http::Error::from(e).into()),
+            };
+
+            if !decision.allowed {
+                return Ok(req.into_response(limited(&decision)));
+            }
+
+            let mut res = service.borrow_mut().call(req).await?;
+            insert_headers(res.headers_mut(), &decision);
+
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::HeaderMap;
+    use actix_web::test::TestRequest;
+    use std::time::Duration;
+    use timada_http::testing::ContextBuilder;
+
+    use super::{insert_headers, KeyBy};
+    use crate::policy::Decision;
+
+    #[test]
+    fn keys() {
+        let user = ContextBuilder::new().build().user.unwrap();
+        let req = ContextBuilder::new()
+            .id(user.id)
+            .request()
+            .peer_addr("10.0.0.1:443".parse().unwrap())
+            .header("x-api-token", "secret")
+            .to_http_request();
+
+        assert_eq!(KeyBy::User.key(&req), Some(format!("user:{}", user.id)));
+        assert_eq!(KeyBy::Ip.key(&req), Some("ip:10.0.0.1".to_owned()));
+        assert_eq!(
+            KeyBy::ApiToken("x-api-token".to_owned()).key(&req),
+            Some(
+                "token:2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b".to_owned()
+            )
+        );
+
+        let req = TestRequest::default().to_http_request();
+
+        assert_eq!(KeyBy::User.key(&req), None);
+        assert_eq!(KeyBy::Ip.key(&req), None);
+        assert_eq!(KeyBy::ApiToken("x-api-token".to_owned()).key(&req), None);
+    }
+
+    #[test]
+    fn headers() {
+        let mut headers = HeaderMap::new();
+
+        insert_headers(
+            &mut headers,
+            &Decision {
+                allowed: false,
+                limit: 100,
+                remaining: 0,
+                retry_after: Some(Duration::from_millis(1200)),
+            },
+        );
+
+        assert_eq!(headers.get("x-ratelimit-limit").unwrap(), "100");
+        assert_eq!(headers.get("x-ratelimit-remaining").unwrap(), "0");
+        assert_eq!(headers.get("retry-after").unwrap(), "2");
+    }
+}
@@ -0,0 +1,180 @@
+use redis::Script;
+use std::time::Duration;
+
+// Both scripts read the clock of the Redis server so replicas with skewed clocks share
+// the same windows, `replicate_commands` allows writes after the non deterministic TIME.
+const TOKEN_BUCKET: &str = r#"
+redis.replicate_commands()
+
+local capacity = tonumber(ARGV[1])
+local period = tonumber(ARGV[2])
+local cost = tonumber(ARGV[3])
+
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(bucket[1]) or capacity
+local ts = tonumber(bucket[2]) or now
+local rate = capacity / period
+
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * rate)
+
+local allowed = 0
+local retry_after = 0
+
+if tokens >= cost then
+  tokens = tokens - cost
+  allowed = 1
+else
+  retry_after = math.ceil((cost - tokens) / rate)
+end
+
+redis.call('HMSET', KEYS[1], 'tokens', tokens, 'ts', now)
+redis.call('PEXPIRE', KEYS[1], period)
+
+return {allowed, math.floor(tokens), retry_after}
+"#;
+
+const SLIDING_WINDOW: &str = r#"
+redis.replicate_commands()
+
+local limit = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local cost = tonumber(ARGV[3])
+
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+
+local count = redis.call('ZCARD', KEYS[1])
+
+if count + cost <= limit then
+  for i = 1, cost do
+    redis.call('ZADD', KEYS[1], now, ARGV[4] .. ':' .. i)
+  end
+  redis.call('PEXPIRE', KEYS[1], window)
+
+  return {1, limit - count - cost, 0}
+end
+
+local retry_after = window
+local oldest = redis.call('ZRANGE', KEYS[1], 0, 0, 'WITHSCORES')
+if oldest[2] then
+  retry_after = math.max(1, tonumber(oldest[2]) + window - now)
+end
+
+return {0, math.max(0, limit - count), retry_after}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    // Allows bursts up to the limit then refills continuously over the period.
+    TokenBucket,
+    // Exact count of the requests of the last period, one sorted set member per unit of cost.
+    SlidingWindow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    pub name: String,
+    pub limit: u64,
+    pub period: Duration,
+    pub algorithm: Algorithm,
+}
+
+impl Policy {
+    pub fn token_bucket(name: &str, limit: u64, period: Duration) -> Self {
+        Policy {
+            name: name.to_owned(),
+            limit,
+            period,
+            algorithm: Algorithm::TokenBucket,
+        }
+    }
+
+    pub fn sliding_window(name: &str, limit: u64, period: Duration) -> Self {
+        Policy {
+            name: name.to_owned(),
+            limit,
+            period,
+            algorithm: Algorithm::SlidingWindow,
+        }
+    }
+
+    pub(crate) fn script(&self) -> Script {
+        match self.algorithm {
+            Algorithm::TokenBucket => Script::new(TOKEN_BUCKET),
+            Algorithm::SlidingWindow => Script::new(SLIDING_WINDOW),
+        }
+    }
+
+    pub(crate) fn period_millis(&self) -> u64 {
+        (self.period.as_millis() as u64).max(1)
+    }
+
+    pub(crate) fn decision(&self, (allowed, remaining, retry_after): (i64, i64, i64)) -> Decision {
+        Decision {
+            allowed: allowed == 1,
+            limit: self.limit,
+            remaining: remaining.max(0) as u64,
+            retry_after: if allowed == 1 {
+                None
+            } else {
+                Some(Duration::from_millis(retry_after.max(0) as u64))
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    pub retry_after: Option<Duration>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Decision, Policy};
+
+    #[test]
+    fn decision() {
+        let policy = Policy::token_bucket("api", 100, Duration::from_secs(60));
+
+        assert_eq!(
+            policy.decision((1, 99, 0)),
+            Decision {
+                allowed: true,
+                limit: 100,
+                remaining: 99,
+                retry_after: None,
+            }
+        );
+        assert_eq!(
+            policy.decision((0, 0, 600)),
+            Decision {
+                allowed: false,
+                limit: 100,
+                remaining: 0,
+                retry_after: Some(Duration::from_millis(600)),
+            }
+        );
+    }
+
+    #[test]
+    fn period_millis() {
+        assert_eq!(
+            Policy::sliding_window("login", 5, Duration::from_secs(60)).period_millis(),
+            60_000
+        );
+        assert_eq!(
+            Policy::sliding_window("login", 5, Duration::from_secs(0)).period_millis(),
+            1
+        );
+    }
+}
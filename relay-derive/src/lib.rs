@@ -0,0 +1,128 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Field, Fields, Meta, NestedMeta};
+
+#[proc_macro_derive(Cursor, attributes(cursor))]
+pub fn derive_cursor(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(PartialEq)]
+enum Role {
+    Key,
+    Order,
+}
+
+fn field_role(field: &Field) -> Result<Option<Role>, Error> {
+    let mut role = None;
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cursor"))
+    {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected #[cursor(key|order)]")),
+        };
+
+        for nested in list.nested.iter() {
+            let value = match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("key") => Role::Key,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("order") => Role::Order,
+                nested => return Err(Error::new_spanned(nested, "expected key or order")),
+            };
+
+            if role.is_some() {
+                return Err(Error::new_spanned(
+                    attr,
+                    "a field is either the cursor key or order",
+                ));
+            }
+
+            role = Some(value);
+        }
+    }
+
+    Ok(role)
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    input,
+                    "Cursor requires a struct with named fields",
+                ))
+            }
+        },
+        _ => return Err(Error::new_spanned(input, "Cursor requires a struct")),
+    };
+
+    let mut key = None;
+    let mut order = None;
+
+    for field in fields.iter() {
+        let slot = match field_role(field)? {
+            Some(Role::Key) => &mut key,
+            Some(Role::Order) => &mut order,
+            None => continue,
+        };
+
+        if slot.is_some() {
+            return Err(Error::new_spanned(field, "duplicated cursor field"));
+        }
+
+        *slot = Some(field);
+    }
+
+    let (key, order) = match (key, order) {
+        (Some(key), Some(order)) => (key, order),
+        _ => {
+            return Err(Error::new_spanned(
+                input,
+                "Cursor requires a #[cursor(key)] and a #[cursor(order)] field",
+            ))
+        }
+    };
+
+    let key_ident = key.ident.as_ref().expect("named field");
+    let key_ty = &key.ty;
+    let order_ident = order.ident.as_ref().expect("named field");
+    let order_ty = &order.ty;
+
+    Ok(quote! {
+        impl ::timada_relay::Cursor for #name {
+            type Key = #key_ty;
+            type Order = #order_ty;
+
+            fn to_cursor(&self) -> (String, String) {
+                (
+                    ::timada_relay::CursorValue::to_cursor_value(&self.#key_ident),
+                    ::timada_relay::CursorValue::to_cursor_value(&self.#order_ident),
+                )
+            }
+
+            fn from_cursor(
+                key_value: &str,
+                order_value: &str,
+            ) -> ::timada_relay::ConnectionResult<(Self::Key, Self::Order)> {
+                Ok((
+                    <#key_ty as ::timada_relay::CursorValue>::from_cursor_value(key_value)?,
+                    <#order_ty as ::timada_relay::CursorValue>::from_cursor_value(order_value)?,
+                ))
+            }
+        }
+    })
+}
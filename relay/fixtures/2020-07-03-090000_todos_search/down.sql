@@ -0,0 +1,2 @@
+DROP INDEX todos_search_vector_idx;
+ALTER TABLE todos DROP COLUMN search_vector;
@@ -0,0 +1,2 @@
+ALTER TABLE todos ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (setweight(to_tsvector('english', coalesce(text, '')), 'A')) STORED;
+CREATE INDEX todos_search_vector_idx ON todos USING GIN (search_vector);
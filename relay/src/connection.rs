@@ -26,7 +26,22 @@ pub type ConnectionResult<T> = Result<T, ConnectionError>;
 
 #[macro_export]
 macro_rules! resolve_connection {
-    ($model:ident, $conn:ident, $table:ident, $first:ident, $after:ident, $last:ident, $before:ident, $key_field:ident, $order_field:ident, $to_cursor:ident, $from_cursor:ident) => {{
+    ($model:ident, $conn:ident, $table:ident, $first:ident, $after:ident, $last:ident, $before:ident, $key_field:ident, $order_field:ident) => {
+        $crate::resolve_connection!(
+            $model,
+            $conn,
+            $table,
+            $first,
+            $after,
+            $last,
+            $before,
+            $key_field,
+            $order_field,
+            <$model as $crate::Cursor>::to_cursor,
+            <$model as $crate::Cursor>::from_cursor
+        )
+    };
+    ($model:ident, $conn:ident, $table:ident, $first:ident, $after:ident, $last:ident, $before:ident, $key_field:ident, $order_field:ident, $to_cursor:expr, $from_cursor:expr) => {{
         use async_graphql::{Connection, Cursor, EmptyEdgeFields, PageInfo};
 
         let backward =
@@ -44,14 +59,19 @@ macro_rules! resolve_connection {
             let (key_value, order_value) = $crate::from_cursor(&cursor)?;
             let (key_value, order_value) = $from_cursor(&key_value, &order_value)?;
 
+            // One grouped predicate, an `or_filter` would escape the filters of the caller.
             table = if backward {
-                table
-                    .filter($order_field.lt(order_value))
-                    .or_filter($order_field.eq(order_value).and($key_field.lt(key_value)))
+                table.filter(
+                    $order_field
+                        .lt(order_value)
+                        .or($order_field.eq(order_value).and($key_field.lt(key_value))),
+                )
             } else {
-                table
-                    .filter($order_field.gt(order_value))
-                    .or_filter($order_field.eq(order_value).and($key_field.gt(key_value)))
+                table.filter(
+                    $order_field
+                        .gt(order_value)
+                        .or($order_field.eq(order_value).and($key_field.gt(key_value))),
+                )
             };
         }
 
@@ -116,8 +136,7 @@ mod tests {
     use chrono::{DateTime, Utc};
     use diesel::prelude::*;
     use futures_await_test::async_test;
-    use std::env;
-    use timada_database::DatabaseConnection;
+    use timada_database::testing::test_config;
     use uuid::Uuid;
 
     use super::{ConnectionError, ConnectionResult};
@@ -192,16 +211,7 @@ mod tests {
     }
 
     fn connection() -> diesel::PgConnection {
-        let host = env::var("DB_HOST").unwrap_or_else(|_| "localhost".to_owned());
-        let user = env::var("DB_USER").unwrap_or_else(|_| "root".to_owned());
-        let password = env::var("DB_PASSWORD").unwrap_or_else(|_| "root".to_owned());
-
-        let config = DatabaseConnection {
-            host,
-            user,
-            password,
-            name: Some("timada_relay_dev".to_owned()),
-        };
+        let config = test_config("timada_relay_dev");
 
         timada_database::setup(&config).unwrap();
         timada_database::fixture(&config).unwrap();
@@ -252,6 +262,32 @@ mod tests {
         )
     }
 
+    fn resolve_undone_connection(
+        first: Option<usize>,
+        after: Option<String>,
+        last: Option<usize>,
+        before: Option<String>,
+    ) -> ConnectionResult<Connection<Todo>> {
+        use self::todos::dsl::{created_at, id, is_done, todos};
+
+        let conn = &connection();
+        let table = todos.filter(is_done.eq(false)).into_boxed();
+
+        crate::resolve_connection!(
+            Todo,
+            conn,
+            table,
+            first,
+            after,
+            last,
+            before,
+            id,
+            created_at,
+            to_todo_cursor,
+            from_todo_cursor
+        )
+    }
+
     #[async_test]
     async fn resolve_connection_no_args() {
         let res = resolve_connection(None, None, None, None).unwrap();
@@ -365,4 +401,34 @@ mod tests {
 
         assert_eq!(nodes, vec![&TODO_3.clone(), &TODO_1.clone()]);
     }
+
+    #[async_test]
+    async fn resolve_connection_filtered() {
+        let cursor = crate::to_cursor(&TODO_3.id.to_string(), &TODO_3.created_at.to_rfc3339());
+
+        let mut nodes = Vec::new();
+        let res = resolve_undone_connection(Some(2), Some(cursor), None, None).unwrap();
+        let edges = res.edges().await.unwrap();
+
+        for edge in edges.iter() {
+            let edge = edge.as_ref().unwrap();
+            nodes.push(edge.node().await);
+        }
+
+        // TODO_1 shares the timestamp of TODO_3 but is done.
+        assert_eq!(nodes, vec![&TODO_4.clone(), &TODO_5.clone()]);
+
+        let cursor = crate::to_cursor(&TODO_4.id.to_string(), &TODO_4.created_at.to_rfc3339());
+
+        let mut nodes = Vec::new();
+        let res = resolve_undone_connection(None, None, Some(2), Some(cursor)).unwrap();
+        let edges = res.edges().await.unwrap();
+
+        for edge in edges.iter() {
+            let edge = edge.as_ref().unwrap();
+            nodes.push(edge.node().await);
+        }
+
+        assert_eq!(nodes, vec![&TODO_3.clone()]);
+    }
 }
@@ -1,12 +1,17 @@
 use base64::DecodeError;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use std::convert::From;
 use std::string::FromUtf8Error;
+use uuid::Uuid;
+
+use super::connection::ConnectionResult;
 
 #[derive(Debug, PartialEq)]
 pub enum CursorError {
     FromUtf8,
     Decoded(DecodeError),
     InvalidFormat,
+    InvalidValue(String),
 }
 
 impl From<DecodeError> for CursorError {
@@ -38,9 +43,87 @@ pub fn from_cursor(cursor: &str) -> CursorResult<(String, String)> {
     }
 }
 
+pub trait Cursor {
+    type Key;
+    type Order;
+
+    fn to_cursor(&self) -> (String, String);
+    fn from_cursor(
+        key_value: &str,
+        order_value: &str,
+    ) -> ConnectionResult<(Self::Key, Self::Order)>;
+}
+
+pub trait CursorValue: Sized {
+    fn to_cursor_value(&self) -> String;
+    fn from_cursor_value(value: &str) -> CursorResult<Self>;
+}
+
+fn invalid_value<E: std::fmt::Display>(value: &str, e: E) -> CursorError {
+    CursorError::InvalidValue(format!("{}: {}", value, e))
+}
+
+macro_rules! parsed_cursor_value {
+    ($($ty:ty),*) => {
+        $(
+            impl CursorValue for $ty {
+                fn to_cursor_value(&self) -> String {
+                    self.to_string()
+                }
+
+                fn from_cursor_value(value: &str) -> CursorResult<Self> {
+                    value.parse().map_err(|e| invalid_value(value, e))
+                }
+            }
+        )*
+    };
+}
+
+parsed_cursor_value!(i16, i32, i64, String, NaiveDate);
+
+impl CursorValue for Uuid {
+    fn to_cursor_value(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_cursor_value(value: &str) -> CursorResult<Self> {
+        Uuid::parse_str(value).map_err(|e| invalid_value(value, e))
+    }
+}
+
+impl CursorValue for DateTime<Utc> {
+    fn to_cursor_value(&self) -> String {
+        self.to_rfc3339()
+    }
+
+    fn from_cursor_value(value: &str) -> CursorResult<Self> {
+        DateTime::parse_from_rfc3339(value)
+            .map(DateTime::<Utc>::from)
+            .map_err(|e| invalid_value(value, e))
+    }
+}
+
+const NAIVE_DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+impl CursorValue for NaiveDateTime {
+    fn to_cursor_value(&self) -> String {
+        self.format(NAIVE_DATE_TIME_FORMAT).to_string()
+    }
+
+    fn from_cursor_value(value: &str) -> CursorResult<Self> {
+        NaiveDateTime::parse_from_str(value, NAIVE_DATE_TIME_FORMAT)
+            .map_err(|e| invalid_value(value, e))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::CursorError;
+    use chrono::{DateTime, NaiveDateTime, Utc};
+    use uuid::Uuid;
+
+    use super::{CursorError, CursorValue};
+    use crate::connection::ConnectionError;
+    use crate::Cursor;
 
     #[test]
     fn to_from_cursor_succes() {
@@ -73,4 +156,74 @@ mod tests {
             Ok(("1".to_owned(), "2020-01-01T13:04:00Z".to_owned()))
         );
     }
+
+    #[derive(Cursor)]
+    struct Todo {
+        #[cursor(key)]
+        id: Uuid,
+        #[allow(dead_code)]
+        text: String,
+        #[cursor(order)]
+        created_at: DateTime<Utc>,
+    }
+
+    #[derive(Cursor)]
+    struct Event {
+        #[cursor(key)]
+        position: i64,
+        #[cursor(order)]
+        occurred_at: NaiveDateTime,
+    }
+
+    #[test]
+    fn derive_cursor() {
+        let todo = Todo {
+            id: Uuid::parse_str("fb1de7a6-996f-48c6-9973-f434852ad843").unwrap(),
+            text: "Todo 1".to_owned(),
+            created_at: DateTime::parse_from_rfc3339("2020-01-07T00:00:00.000Z")
+                .map(DateTime::<Utc>::from)
+                .unwrap(),
+        };
+        let (key_value, order_value) = todo.to_cursor();
+
+        assert_eq!(key_value, "fb1de7a6-996f-48c6-9973-f434852ad843");
+        assert_eq!(order_value, "2020-01-07T00:00:00+00:00");
+        assert_eq!(
+            Todo::from_cursor(&key_value, &order_value),
+            Ok((todo.id, todo.created_at))
+        );
+
+        let event = Event {
+            position: 42,
+            occurred_at: NaiveDateTime::parse_from_str(
+                "2020-01-07 10:30:00.250",
+                "%Y-%m-%d %H:%M:%S%.f",
+            )
+            .unwrap(),
+        };
+        let (key_value, order_value) = event.to_cursor();
+
+        assert_eq!(
+            Event::from_cursor(&key_value, &order_value),
+            Ok((event.position, event.occurred_at))
+        );
+    }
+
+    #[test]
+    fn invalid_cursor_value() {
+        assert!(matches!(
+            Todo::from_cursor("42", "2020-01-07T00:00:00+00:00"),
+            Err(ConnectionError::Cursor(CursorError::InvalidValue(_)))
+        ));
+        assert!(matches!(
+            Event::from_cursor("42", "yesterday"),
+            Err(ConnectionError::Cursor(CursorError::InvalidValue(_)))
+        ));
+        assert_eq!(
+            i32::from_cursor_value("x"),
+            Err(CursorError::InvalidValue(
+                "x: invalid digit found in string".to_owned()
+            ))
+        );
+    }
 }
@@ -0,0 +1,247 @@
+use async_graphql::{ScalarType, Value};
+use diesel::PgConnection;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::uuid::{from_typed_id, UuidError};
+
+#[derive(Debug, PartialEq)]
+pub enum FederationError {
+    InvalidRepresentation(String),
+    UnknownType(String),
+    Id(UuidError),
+    Custom(String),
+}
+
+impl From<UuidError> for FederationError {
+    fn from(e: UuidError) -> FederationError {
+        FederationError::Id(e)
+    }
+}
+
+pub type FederationResult<T> = Result<T, FederationError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Any(pub JsonValue);
+
+fn to_json(value: &Value) -> Option<JsonValue> {
+    Some(match value {
+        Value::Null => JsonValue::Null,
+        Value::Boolean(value) => JsonValue::from(*value),
+        Value::Int(value) => JsonValue::from(value.as_i64()?),
+        Value::Float(value) => JsonValue::from(*value),
+        Value::String(value) | Value::Enum(value) => JsonValue::from(value.as_str()),
+        Value::List(values) => JsonValue::Array(values.iter().map(to_json).collect::<Option<_>>()?),
+        Value::Object(values) => JsonValue::Object(
+            values
+                .iter()
+                .map(|(name, value)| Some((name.to_string(), to_json(value)?)))
+                .collect::<Option<_>>()?,
+        ),
+        Value::Variable(_) => return None,
+    })
+}
+
+#[async_graphql::Scalar]
+impl ScalarType for Any {
+    fn type_name() -> &'static str {
+        "_Any"
+    }
+
+    fn parse(value: &Value) -> Option<Self> {
+        to_json(value).map(Any)
+    }
+
+    fn to_json(&self) -> async_graphql::Result<JsonValue> {
+        Ok(self.0.clone())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityKey {
+    pub type_name: String,
+    pub id: Uuid,
+}
+
+// Entities are keyed by their global `ID`, which already carries the type name,
+// so every service resolving `@key(fields: "id")` agrees on the same encoding.
+pub fn entity_key(representation: &JsonValue) -> FederationResult<EntityKey> {
+    let type_name = representation["__typename"]
+        .as_str()
+        .ok_or_else(|| FederationError::InvalidRepresentation("missing __typename".to_owned()))?;
+    let id = representation["id"]
+        .as_str()
+        .ok_or_else(|| FederationError::InvalidRepresentation("missing id".to_owned()))?;
+
+    Ok(EntityKey {
+        type_name: type_name.to_owned(),
+        id: from_typed_id(type_name, &id.into())?,
+    })
+}
+
+type ReferenceResolver<E> =
+    Box<dyn Fn(&PgConnection, Uuid) -> FederationResult<Option<E>> + Send + Sync>;
+
+pub struct EntityResolvers<E> {
+    resolvers: HashMap<String, ReferenceResolver<E>>,
+}
+
+impl<E> Default for EntityResolvers<E> {
+    fn default() -> Self {
+        EntityResolvers {
+            resolvers: HashMap::new(),
+        }
+    }
+}
+
+impl<E> EntityResolvers<E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<F>(mut self, type_name: &str, resolver: F) -> Self
+    where
+        F: Fn(&PgConnection, Uuid) -> FederationResult<Option<E>> + Send + Sync + 'static,
+    {
+        self.resolvers
+            .insert(type_name.to_owned(), Box::new(resolver));
+        self
+    }
+
+    pub fn type_names(&self) -> Vec<&str> {
+        let mut type_names = self
+            .resolvers
+            .keys()
+            .map(|type_name| type_name.as_str())
+            .collect::<Vec<_>>();
+        type_names.sort();
+        type_names
+    }
+
+    pub fn resolve(&self, conn: &PgConnection, key: &EntityKey) -> FederationResult<Option<E>> {
+        let resolver = self
+            .resolvers
+            .get(&key.type_name)
+            .ok_or_else(|| FederationError::UnknownType(key.type_name.clone()))?;
+
+        resolver(conn, key.id)
+    }
+
+    // One result per representation, in order, a bad representation doesn't fail the others.
+    pub fn resolve_all(
+        &self,
+        conn: &PgConnection,
+        representations: &[Any],
+    ) -> Vec<FederationResult<Option<E>>> {
+        representations
+            .iter()
+            .map(|representation| self.resolve(conn, &entity_key(&representation.0)?))
+            .collect()
+    }
+
+    // The value of `_entities`, a representation that can't be resolved is `null`.
+    pub fn resolve_entities(&self, conn: &PgConnection, representations: &[Any]) -> Vec<Option<E>> {
+        self.resolve_all(conn, representations)
+            .into_iter()
+            .enumerate()
+            .map(|(index, entity)| {
+                entity.unwrap_or_else(|e| {
+                    log::warn!("_entities: representation {} {:?}", index, e);
+                    None
+                })
+            })
+            .collect()
+    }
+}
+
+// Declares the `_Entity` union of the entity types, the service then exposes it from
+// its query root, the resolvers returning `Entity::from(model)`:
+//
+// #[field(name = "_entities")]
+// async fn entities(&self, ctx: &Context<'_>, representations: Vec<Any>) -> FieldResult<Vec<Option<Entity>>> {
+//     let conn = ctx.data::<Pool>().get()?;
+//     Ok(ctx.data::<EntityResolvers<Entity>>().resolve_entities(&conn, &representations))
+// }
+#[macro_export]
+macro_rules! entity_union {
+    ($($entity:ident),+) => {
+        #[async_graphql::Union(name = "_Entity")]
+        pub struct Entity($($entity),+);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use timada_database::testing::test_connection;
+
+    use super::{entity_key, Any, EntityResolvers, FederationError};
+    use crate::uuid::{to_id, UuidError};
+
+    #[test]
+    fn key() {
+        let id = Uuid::new_v4();
+        let key = entity_key(&json!({ "__typename": "Todo", "id": to_id("Todo", &id) })).unwrap();
+
+        assert_eq!(key.type_name, "Todo");
+        assert_eq!(key.id, id);
+
+        assert_eq!(
+            entity_key(&json!({ "__typename": "User", "id": to_id("Todo", &id) })),
+            Err(FederationError::Id(UuidError::TypeMismatch(
+                "Todo".to_owned()
+            )))
+        );
+        assert!(matches!(
+            entity_key(&json!({ "id": to_id("Todo", &id) })),
+            Err(FederationError::InvalidRepresentation(_))
+        ));
+    }
+
+    #[test]
+    fn resolve_all() {
+        let conn = test_connection("timada_relay_dev");
+        let id = Uuid::new_v4();
+        let resolvers = EntityResolvers::<Uuid>::new().register("Todo", |_, id| Ok(Some(id)));
+
+        let entities = resolvers.resolve_all(
+            &conn,
+            &[
+                Any(json!({ "__typename": "Todo", "id": to_id("Todo", &id) })),
+                Any(json!({ "__typename": "Project", "id": to_id("Project", &id) })),
+                Any(json!({ "id": to_id("Todo", &id) })),
+            ],
+        );
+
+        assert_eq!(entities.len(), 3);
+        assert_eq!(entities[0], Ok(Some(id)));
+        assert_eq!(
+            entities[1],
+            Err(FederationError::UnknownType("Project".to_owned()))
+        );
+        assert!(entities[2].is_err());
+
+        assert_eq!(
+            resolvers.resolve_entities(
+                &conn,
+                &[
+                    Any(json!({ "__typename": "Project", "id": to_id("Project", &id) })),
+                    Any(json!({ "__typename": "Todo", "id": to_id("Todo", &id) })),
+                ]
+            ),
+            vec![None, Some(id)]
+        );
+    }
+
+    #[test]
+    fn register() {
+        let resolvers = EntityResolvers::<Uuid>::new()
+            .register("Todo", |_, id| Ok(Some(id)))
+            .register("Project", |_, _| Ok(None));
+
+        assert_eq!(resolvers.type_names(), vec!["Project", "Todo"]);
+    }
+}
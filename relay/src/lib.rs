@@ -1,10 +1,20 @@
-#[cfg_attr(test, macro_use)]
+extern crate self as timada_relay;
+
+#[macro_use]
 extern crate diesel;
 
 mod connection;
 mod cursor;
+mod federation;
 mod uuid;
 
+pub mod search;
+
 pub use crate::connection::{ConnectionError, ConnectionResult};
-pub use crate::cursor::{from_cursor, to_cursor, CursorError, CursorResult};
-pub use crate::uuid::{from_id, to_id};
+pub use crate::cursor::{from_cursor, to_cursor, Cursor, CursorError, CursorResult, CursorValue};
+pub use crate::federation::{
+    entity_key, Any, EntityKey, EntityResolvers, FederationError, FederationResult,
+};
+pub use crate::search::{matches, regconfig, search_column_sql, Weight};
+pub use crate::uuid::{from_id, from_typed_id, to_id, UuidError, UuidResult};
+pub use timada_relay_derive::Cursor;
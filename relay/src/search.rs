@@ -0,0 +1,337 @@
+use diesel::dsl::{sql, SqlLiteral};
+
+use super::connection::{ConnectionError, ConnectionResult};
+
+pub mod sql_types {
+    #[derive(Debug, Clone, Copy, Default, QueryId, SqlType)]
+    #[postgres(oid = "3614", array_oid = "3643")]
+    pub struct TsVector;
+
+    #[derive(Debug, Clone, Copy, Default, QueryId, SqlType)]
+    #[postgres(oid = "3615", array_oid = "3645")]
+    pub struct TsQuery;
+
+    #[derive(Debug, Clone, Copy, Default, QueryId, SqlType)]
+    #[postgres(oid = "3734", array_oid = "3735")]
+    pub struct Regconfig;
+}
+
+use self::sql_types::{Regconfig, TsQuery, TsVector};
+
+sql_function!(fn websearch_to_tsquery(config: Regconfig, query: diesel::sql_types::Text) -> TsQuery);
+sql_function!(fn ts_rank_cd(vector: TsVector, query: TsQuery) -> diesel::sql_types::Float4);
+
+diesel_infix_operator!(Matches, " @@ ", backend: diesel::pg::Pg);
+
+pub fn matches<T, U>(vector: T, query: U) -> Matches<T, U>
+where
+    T: diesel::Expression<SqlType = TsVector>,
+    U: diesel::Expression<SqlType = TsQuery>,
+{
+    Matches::new(vector, query)
+}
+
+fn is_valid_config(config: &str) -> bool {
+    !config.is_empty()
+        && config
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+pub fn regconfig(config: &str) -> ConnectionResult<SqlLiteral<Regconfig>> {
+    if !is_valid_config(config) {
+        return Err(ConnectionError::Custom(format!(
+            "Invalid text search config {}",
+            config
+        )));
+    }
+
+    Ok(sql::<Regconfig>(&format!("'{}'::regconfig", config)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Weight {
+    A,
+    B,
+    C,
+    D,
+}
+
+impl Weight {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Weight::A => "A",
+            Weight::B => "B",
+            Weight::C => "C",
+            Weight::D => "D",
+        }
+    }
+}
+
+pub fn search_column_sql(
+    table: &str,
+    column: &str,
+    config: &str,
+    sources: &[(&str, Weight)],
+) -> String {
+    let expression = sources
+        .iter()
+        .map(|(source, weight)| {
+            format!(
+                "setweight(to_tsvector('{}', coalesce({}, '')), '{}')",
+                config,
+                source,
+                weight.as_str()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" || ");
+
+    format!(
+        "ALTER TABLE {table} ADD COLUMN {column} tsvector GENERATED ALWAYS AS ({expression}) STORED;\n\
+         CREATE INDEX {table}_{column}_idx ON {table} USING GIN ({column});",
+        table = table,
+        column = column,
+        expression = expression
+    )
+}
+
+pub fn parse_rank(value: &str) -> ConnectionResult<f32> {
+    value
+        .parse()
+        .map_err(|_| ConnectionError::Custom(format!("Invalid rank {}", value)))
+}
+
+#[macro_export]
+macro_rules! resolve_search_connection {
+    ($model:ident, $conn:ident, $source:expr, $columns:expr, $vector_field:ident, $config:expr, $query:expr, $first:ident, $after:ident, $last:ident, $before:ident, $key_field:ident, $to_key:ident, $from_key:ident) => {{
+        use async_graphql::{Connection, Cursor, EmptyEdgeFields, PageInfo};
+        use $crate::search::{matches, regconfig, ts_rank_cd, websearch_to_tsquery};
+
+        let config = $config;
+        let query: String = $query.to_owned();
+        let tsquery = || -> $crate::ConnectionResult<_> {
+            Ok(websearch_to_tsquery(regconfig(config)?, query.clone()))
+        };
+
+        let backward =
+            ($last.is_some() || $before.is_some()) && $first.is_none() && $after.is_none();
+
+        let (limit, cursor) = if backward {
+            ($last.unwrap_or(40), $before.as_ref())
+        } else {
+            ($first.unwrap_or(40), $after.as_ref())
+        };
+
+        let mut table = $source
+            .select(($columns, ts_rank_cd($vector_field, tsquery()?)))
+            .filter(matches($vector_field, tsquery()?))
+            .limit((limit + 1) as i64)
+            .into_boxed();
+
+        if let Some(cursor) = cursor {
+            let (key_value, rank_value) = $crate::from_cursor(&cursor)?;
+            let key_value = $from_key(&key_value)?;
+            let rank_value = $crate::search::parse_rank(&rank_value)?;
+            let rank = ts_rank_cd($vector_field, tsquery()?);
+
+            table = if backward {
+                table.filter(
+                    rank.gt(rank_value).or(ts_rank_cd($vector_field, tsquery()?)
+                        .eq(rank_value)
+                        .and($key_field.lt(key_value))),
+                )
+            } else {
+                table.filter(
+                    rank.lt(rank_value).or(ts_rank_cd($vector_field, tsquery()?)
+                        .eq(rank_value)
+                        .and($key_field.gt(key_value))),
+                )
+            };
+        }
+
+        let rank = ts_rank_cd($vector_field, tsquery()?);
+
+        table = if backward {
+            table.order((rank.asc(), $key_field.desc()))
+        } else {
+            table.order((rank.desc(), $key_field.asc()))
+        };
+
+        let rows = table
+            .load::<($model, f32)>($conn)?
+            .into_iter()
+            .map(|(row, rank)| {
+                let cursor = $crate::to_cursor(&$to_key(&row), &rank.to_string());
+
+                (Cursor::from(cursor), EmptyEdgeFields {}, row)
+            });
+
+        let mut nodes: Vec<(Cursor, EmptyEdgeFields, $model)> = if backward {
+            rows.rev().collect()
+        } else {
+            rows.collect()
+        };
+
+        let len = nodes.len();
+        let has_more = len > limit as usize;
+
+        if has_more {
+            nodes.remove(if backward { 0 } else { len - 1 });
+        };
+
+        let page_info = if backward {
+            PageInfo {
+                has_previous_page: has_more,
+                has_next_page: false,
+                start_cursor: nodes.first().map(|(cursor, _, _)| cursor.clone()),
+                end_cursor: None,
+            }
+        } else {
+            PageInfo {
+                has_previous_page: false,
+                has_next_page: has_more,
+                start_cursor: None,
+                end_cursor: nodes.last().map(|(cursor, _, _)| cursor.clone()),
+            }
+        };
+
+        Ok(Connection {
+            total_count: None,
+            page_info,
+            nodes,
+        })
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::Connection;
+    use diesel::prelude::*;
+    use futures_await_test::async_test;
+    use timada_database::testing::test_config;
+    use uuid::Uuid;
+
+    use super::{regconfig, search_column_sql, Weight};
+    use crate::connection::{ConnectionError, ConnectionResult};
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::search::sql_types::TsVector;
+
+        todos (id) {
+            id -> Uuid,
+            text -> Varchar,
+            search_vector -> TsVector,
+        }
+    }
+
+    #[derive(Debug, Queryable, PartialEq, Clone)]
+    pub struct Todo {
+        pub id: Uuid,
+        pub text: String,
+    }
+
+    fn connection() -> diesel::PgConnection {
+        let config = test_config("timada_relay_dev");
+
+        timada_database::setup(&config).unwrap();
+        timada_database::fixture(&config).unwrap();
+
+        config.establish().unwrap()
+    }
+
+    fn to_key(todo: &Todo) -> String {
+        todo.id.to_string()
+    }
+
+    fn from_key(value: &str) -> ConnectionResult<Uuid> {
+        Uuid::parse_str(value).map_err(|e| ConnectionError::Custom(e.to_string()))
+    }
+
+    fn search(
+        query: &str,
+        first: Option<usize>,
+        after: Option<String>,
+        last: Option<usize>,
+        before: Option<String>,
+    ) -> ConnectionResult<Connection<Todo>> {
+        use self::todos::dsl::{id, search_vector, text, todos};
+
+        let conn = &connection();
+
+        crate::resolve_search_connection!(
+            Todo,
+            conn,
+            todos,
+            (id, text),
+            search_vector,
+            "english",
+            query,
+            first,
+            after,
+            last,
+            before,
+            id,
+            to_key,
+            from_key
+        )
+    }
+
+    #[test]
+    fn config() {
+        assert!(regconfig("english").is_ok());
+        assert!(regconfig("english'); DROP TABLE todos; --").is_err());
+        assert!(regconfig("").is_err());
+    }
+
+    #[test]
+    fn column_sql() {
+        assert_eq!(
+            search_column_sql(
+                "todos",
+                "search_vector",
+                "english",
+                &[("text", Weight::A), ("description", Weight::C)]
+            ),
+            "ALTER TABLE todos ADD COLUMN search_vector tsvector GENERATED ALWAYS AS \
+             (setweight(to_tsvector('english', coalesce(text, '')), 'A') || \
+             setweight(to_tsvector('english', coalesce(description, '')), 'C')) STORED;\n\
+             CREATE INDEX todos_search_vector_idx ON todos USING GIN (search_vector);"
+        );
+    }
+
+    #[async_test]
+    async fn search_first_after() {
+        let res = search("todo", Some(2), None, None, None).unwrap();
+        let page_info = res.page_info().await;
+
+        assert_eq!(page_info.has_next_page, true);
+
+        let mut nodes = Vec::new();
+        for edge in res.edges().await.unwrap().iter() {
+            nodes.push(edge.as_ref().unwrap().node().await.text.to_owned());
+        }
+        assert_eq!(nodes, vec!["Todo 5", "Todo 2"]);
+
+        let after = page_info.end_cursor.map(|cursor| cursor.to_string());
+        let res = search("todo", Some(10), after, None, None).unwrap();
+
+        let mut nodes = Vec::new();
+        for edge in res.edges().await.unwrap().iter() {
+            nodes.push(edge.as_ref().unwrap().node().await.text.to_owned());
+        }
+        assert_eq!(nodes, vec!["Todo 3", "Todo 4", "Todo 1"]);
+    }
+
+    #[async_test]
+    async fn search_ranked() {
+        let res = search("5 -2", None, None, None, None).unwrap();
+
+        let mut nodes = Vec::new();
+        for edge in res.edges().await.unwrap().iter() {
+            nodes.push(edge.as_ref().unwrap().node().await.text.to_owned());
+        }
+        assert_eq!(nodes, vec!["Todo 5"]);
+    }
+}
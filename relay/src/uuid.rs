@@ -10,6 +10,7 @@ use super::cursor::CursorError;
 pub enum UuidError {
     Cusor(CursorError),
     Convert,
+    TypeMismatch(String),
 }
 
 impl From<CursorError> for UuidError {
@@ -37,3 +38,10 @@ pub fn from_id(id: &ID) -> UuidResult<(String, Uuid)> {
 
     Ok((type_name, id))
 }
+
+pub fn from_typed_id(type_name: &str, id: &ID) -> UuidResult<Uuid> {
+    match from_id(id)? {
+        (id_type_name, id) if id_type_name == type_name => Ok(id),
+        (id_type_name, _) => Err(UuidError::TypeMismatch(id_type_name)),
+    }
+}
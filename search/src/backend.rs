@@ -0,0 +1,521 @@
+use reqwest::{Client, RequestBuilder, StatusCode, Url};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use timada_util::env;
+use timada_util::secret::{try_secret, Secret};
+
+use super::error::{SearchError, SearchResult};
+use super::index::{SearchHits, SearchOperation, SearchQuery};
+
+#[async_trait::async_trait]
+pub trait SearchBackend: Send + Sync {
+    async fn apply(&self, operations: &[SearchOperation]) -> SearchResult<()>;
+
+    async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchHits>;
+}
+
+async fn send(req: RequestBuilder) -> SearchResult<Value> {
+    let res = req.send().await?;
+    let status = res.status();
+    let body = res.json::<Value>().await.unwrap_or(Value::Null);
+
+    if !status.is_success() {
+        return Err(SearchError::Backend(format!("{}: {}", status, body)));
+    }
+
+    Ok(body)
+}
+
+const VERSION_FIELD: &str = "_version";
+
+// Elasticsearch answers a stale external version with a 409 conflict, the newer document
+// is already indexed so only the other failures are errors.
+fn bulk_errors(res: &Value) -> Vec<&Value> {
+    res["items"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_object().and_then(|item| item.values().next()))
+                .filter(|result| {
+                    !result["error"].is_null()
+                        && result["status"].as_u64()
+                            != Some(u64::from(StatusCode::CONFLICT.as_u16()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn hit_ids(hits: Option<&Vec<Value>>, field: &str) -> Vec<String> {
+    hits.map(|hits| {
+        hits.iter()
+            .filter_map(|hit| match hit.get(field) {
+                Some(Value::String(id)) => Some(id.to_owned()),
+                Some(Value::Number(id)) => Some(id.to_string()),
+                _ => None,
+            })
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+#[derive(Clone)]
+pub struct MeilisearchBackend {
+    client: Client,
+    url: String,
+    api_key: Option<Secret<String>>,
+}
+
+impl MeilisearchBackend {
+    pub fn new(url: &str, api_key: Option<Secret<String>>) -> Self {
+        MeilisearchBackend {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_owned(),
+            api_key,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            &env::var("MEILISEARCH_URL"),
+            try_secret("MEILISEARCH_API_KEY").ok(),
+        )
+    }
+
+    fn request(&self, req: RequestBuilder) -> RequestBuilder {
+        match self.api_key.as_ref() {
+            Some(api_key) => req.header("X-Meili-API-Key", api_key.expose().as_str()),
+            None => req,
+        }
+    }
+
+    async fn indexed_version(&self, index: &str, id: &str) -> SearchResult<Option<i64>> {
+        let mut url = Url::parse(&self.url).map_err(|e| SearchError::Backend(e.to_string()))?;
+        url.path_segments_mut()
+            .map_err(|_| SearchError::Backend(format!("Invalid url {}", self.url)))?
+            .extend(&["indexes", index, "documents", id]);
+
+        let res = self.request(self.client.get(url)).send().await?;
+
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let status = res.status();
+        let body = res.json::<Value>().await.unwrap_or(Value::Null);
+
+        if !status.is_success() {
+            return Err(SearchError::Backend(format!("{}: {}", status, body)));
+        }
+
+        Ok(Some(body[VERSION_FIELD].as_i64().unwrap_or_default()))
+    }
+}
+
+// Meilisearch has no conditional writes, the indexed version is read first so a stale
+// operation is skipped. Two jobs of the same document racing can still overwrite each other.
+#[async_trait::async_trait]
+impl SearchBackend for MeilisearchBackend {
+    async fn apply(&self, operations: &[SearchOperation]) -> SearchResult<()> {
+        let mut documents: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+        let mut deletes: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+        for operation in operations {
+            let indexed = self
+                .indexed_version(operation.index_name(), operation.id())
+                .await?;
+
+            if indexed.map_or(false, |version| version > operation.version()) {
+                continue;
+            }
+
+            match operation {
+                SearchOperation::Index {
+                    index,
+                    version,
+                    document,
+                    ..
+                } => {
+                    let mut document = document.clone();
+
+                    if let Value::Object(map) = &mut document {
+                        map.insert(VERSION_FIELD.to_owned(), json!(version));
+                    }
+
+                    documents.entry(index).or_default().push(document);
+                }
+                SearchOperation::Delete { index, id, .. } => {
+                    deletes.entry(index).or_default().push(id)
+                }
+            }
+        }
+
+        for (index, documents) in documents {
+            let url = format!("{}/indexes/{}/documents", self.url, index);
+            send(self.request(self.client.post(&url).json(&documents))).await?;
+        }
+
+        for (index, ids) in deletes {
+            let url = format!("{}/indexes/{}/documents/delete-batch", self.url, index);
+            send(self.request(self.client.post(&url).json(&ids))).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchHits> {
+        let url = format!("{}/indexes/{}/search", self.url, index);
+        let body = json!({
+            "q": query.query,
+            "limit": query.limit,
+            "offset": query.offset,
+            "attributesToRetrieve": ["id"],
+        });
+        let res = send(self.request(self.client.post(&url).json(&body))).await?;
+
+        Ok(SearchHits {
+            ids: hit_ids(res["hits"].as_array(), "id"),
+            total: res["nbHits"].as_u64().map(|total| total as usize),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct ElasticsearchBackend {
+    client: Client,
+    url: String,
+    fields: Vec<String>,
+}
+
+impl ElasticsearchBackend {
+    pub fn new(url: &str) -> Self {
+        ElasticsearchBackend {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_owned(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(&env::var("ELASTICSEARCH_URL"))
+    }
+
+    pub fn field(mut self, field: &str) -> Self {
+        self.fields.push(field.to_owned());
+        self
+    }
+
+    pub fn bulk_body(operations: &[SearchOperation]) -> SearchResult<String> {
+        let mut body = String::new();
+
+        for operation in operations {
+            let (action, document) = match operation {
+                SearchOperation::Index {
+                    index,
+                    id,
+                    version,
+                    document,
+                } => (
+                    json!({ "index": {
+                        "_index": index,
+                        "_id": id,
+                        "version": version,
+                        "version_type": "external_gte",
+                    } }),
+                    Some(document),
+                ),
+                SearchOperation::Delete { index, id, version } => (
+                    json!({ "delete": {
+                        "_index": index,
+                        "_id": id,
+                        "version": version,
+                        "version_type": "external_gte",
+                    } }),
+                    None,
+                ),
+            };
+
+            body.push_str(&serde_json::to_string(&action)?);
+            body.push('\n');
+
+            if let Some(document) = document {
+                body.push_str(&serde_json::to_string(document)?);
+                body.push('\n');
+            }
+        }
+
+        Ok(body)
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchBackend for ElasticsearchBackend {
+    async fn apply(&self, operations: &[SearchOperation]) -> SearchResult<()> {
+        if operations.is_empty() {
+            return Ok(());
+        }
+
+        let req = self
+            .client
+            .post(&format!("{}/_bulk", self.url))
+            .header("content-type", "application/x-ndjson")
+            .body(Self::bulk_body(operations)?);
+        let res = send(req).await?;
+
+        let errors = bulk_errors(&res);
+
+        if !errors.is_empty() {
+            return Err(SearchError::Backend(format!(
+                "Bulk request failed: {:?}",
+                errors
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchHits> {
+        let fields = if self.fields.is_empty() {
+            vec!["*".to_owned()]
+        } else {
+            self.fields.clone()
+        };
+        let body = json!({
+            "query": {
+                "multi_match": {
+                    "query": query.query,
+                    "fields": fields,
+                    "fuzziness": "AUTO",
+                }
+            },
+            "from": query.offset,
+            "size": query.limit,
+            "_source": false,
+        });
+        let url = format!("{}/{}/_search", self.url, index);
+        let res = send(self.client.post(&url).json(&body)).await?;
+
+        Ok(SearchHits {
+            ids: hit_ids(res["hits"]["hits"].as_array(), "_id"),
+            total: res["hits"]["total"]["value"]
+                .as_u64()
+                .map(|total| total as usize),
+        })
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    indexes: Arc<Mutex<HashMap<String, BTreeMap<String, Value>>>>,
+    versions: Arc<Mutex<HashMap<(String, String), i64>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn document(&self, index: &str, id: &str) -> Option<Value> {
+        self.indexes
+            .lock()
+            .ok()
+            .and_then(|indexes| indexes.get(index).and_then(|index| index.get(id).cloned()))
+    }
+
+    pub fn len(&self, index: &str) -> usize {
+        self.indexes
+            .lock()
+            .map(|indexes| indexes.get(index).map(|index| index.len()).unwrap_or(0))
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self, index: &str) -> bool {
+        self.len(index) == 0
+    }
+}
+
+fn contains(value: &Value, query: &str) -> bool {
+    match value {
+        Value::String(value) => value.to_lowercase().contains(query),
+        Value::Array(values) => values.iter().any(|value| contains(value, query)),
+        Value::Object(map) => map.values().any(|value| contains(value, query)),
+        _ => false,
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchBackend for MemoryBackend {
+    async fn apply(&self, operations: &[SearchOperation]) -> SearchResult<()> {
+        let mut indexes = self
+            .indexes
+            .lock()
+            .map_err(|e| SearchError::Backend(e.to_string()))?;
+        let mut versions = self
+            .versions
+            .lock()
+            .map_err(|e| SearchError::Backend(e.to_string()))?;
+
+        for operation in operations {
+            let key = (operation.index_name().to_owned(), operation.id().to_owned());
+
+            if versions
+                .get(&key)
+                .map_or(false, |version| *version > operation.version())
+            {
+                continue;
+            }
+
+            versions.insert(key, operation.version());
+
+            let index = indexes
+                .entry(operation.index_name().to_owned())
+                .or_default();
+
+            match operation {
+                SearchOperation::Index { id, document, .. } => {
+                    index.insert(id.to_owned(), document.clone());
+                }
+                SearchOperation::Delete { id, .. } => {
+                    index.remove(id);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn search(&self, index: &str, query: &SearchQuery) -> SearchResult<SearchHits> {
+        let indexes = self
+            .indexes
+            .lock()
+            .map_err(|e| SearchError::Backend(e.to_string()))?;
+        let needle = query.query.to_lowercase();
+
+        let ids = indexes
+            .get(index)
+            .map(|documents| {
+                documents
+                    .iter()
+                    .filter(|(_, document)| contains(document, &needle))
+                    .map(|(id, _)| id.to_owned())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        Ok(SearchHits {
+            total: Some(ids.len()),
+            ids: ids
+                .into_iter()
+                .skip(query.offset)
+                .take(query.limit)
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    use super::{bulk_errors, ElasticsearchBackend, MemoryBackend, SearchBackend};
+    use crate::index::{SearchOperation, SearchQuery};
+
+    fn index(id: &str, text: &str) -> SearchOperation {
+        versioned(id, text, 1)
+    }
+
+    fn versioned(id: &str, text: &str, version: i64) -> SearchOperation {
+        SearchOperation::Index {
+            index: "todos".to_owned(),
+            id: id.to_owned(),
+            version,
+            document: json!({ "id": id, "text": text }),
+        }
+    }
+
+    #[test]
+    fn memory() {
+        let backend = MemoryBackend::new();
+
+        block_on(backend.apply(&[
+            index("1", "Buy milk"),
+            index("2", "Buy bread"),
+            index("3", "Walk the dog"),
+        ]))
+        .unwrap();
+        block_on(backend.apply(&[SearchOperation::Delete {
+            index: "todos".to_owned(),
+            id: "2".to_owned(),
+            version: 2,
+        }]))
+        .unwrap();
+
+        let hits = block_on(backend.search("todos", &SearchQuery::new("buy"))).unwrap();
+
+        assert_eq!(hits.ids, vec!["1"]);
+        assert_eq!(backend.len("todos"), 2);
+    }
+
+    #[test]
+    fn stale_operations() {
+        let backend = MemoryBackend::new();
+
+        block_on(backend.apply(&[versioned("1", "Buy bread", 2)])).unwrap();
+        block_on(backend.apply(&[versioned("1", "Buy milk", 1)])).unwrap();
+
+        assert_eq!(
+            backend.document("todos", "1"),
+            Some(json!({ "id": "1", "text": "Buy bread" }))
+        );
+
+        block_on(backend.apply(&[SearchOperation::Delete {
+            index: "todos".to_owned(),
+            id: "1".to_owned(),
+            version: 3,
+        }]))
+        .unwrap();
+        block_on(backend.apply(&[versioned("1", "Buy bread", 2)])).unwrap();
+
+        assert!(backend.is_empty("todos"));
+    }
+
+    #[test]
+    fn bulk_conflicts() {
+        let res = json!({
+            "errors": true,
+            "items": [
+                { "index": { "_id": "1", "status": 201 } },
+                { "index": { "_id": "2", "status": 409, "error": { "type": "version_conflict_engine_exception" } } },
+                { "delete": { "_id": "3", "status": 400, "error": { "type": "mapper_parsing_exception" } } }
+            ]
+        });
+
+        let errors = bulk_errors(&res);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["_id"], json!("3"));
+    }
+
+    #[test]
+    fn bulk_body() {
+        let body = ElasticsearchBackend::bulk_body(&[
+            index("1", "Buy milk"),
+            SearchOperation::Delete {
+                index: "todos".to_owned(),
+                id: "2".to_owned(),
+                version: 2,
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(
+            body,
+            "{\"index\":{\"_id\":\"1\",\"_index\":\"todos\",\"version\":1,\"version_type\":\"external_gte\"}}\n\
+             {\"id\":\"1\",\"text\":\"Buy milk\"}\n\
+             {\"delete\":{\"_id\":\"2\",\"_index\":\"todos\",\"version\":2,\"version_type\":\"external_gte\"}}\n"
+        );
+    }
+}
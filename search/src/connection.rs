@@ -0,0 +1,147 @@
+use async_graphql::{Connection, Cursor, EmptyEdgeFields, PageInfo};
+use timada_relay::{from_cursor, to_cursor};
+
+use super::backend::SearchBackend;
+use super::error::{SearchError, SearchResult};
+use super::index::{order_by_ids, SearchHits, SearchQuery};
+
+const OFFSET_CURSOR: &str = "offset";
+
+fn offset_cursor(offset: usize) -> Cursor {
+    Cursor::from(to_cursor(OFFSET_CURSOR, &offset.to_string()))
+}
+
+fn parse_offset_cursor(cursor: &str) -> SearchResult<usize> {
+    match from_cursor(cursor) {
+        Ok((key, value)) if key == OFFSET_CURSOR => value
+            .parse()
+            .map_err(|_| SearchError::Cursor(cursor.to_owned())),
+        _ => Err(SearchError::Cursor(cursor.to_owned())),
+    }
+}
+
+pub fn search_page(
+    query: &str,
+    first: Option<usize>,
+    after: Option<String>,
+) -> SearchResult<SearchQuery> {
+    let offset = match after {
+        Some(after) => parse_offset_cursor(&after)? + 1,
+        None => 0,
+    };
+
+    Ok(SearchQuery::new(query)
+        .limit(first.unwrap_or(20))
+        .offset(offset))
+}
+
+pub fn to_connection<T, F>(
+    query: &SearchQuery,
+    hits: &SearchHits,
+    rows: Vec<T>,
+    key: F,
+) -> Connection<T>
+where
+    F: Fn(&T) -> String,
+{
+    let nodes = order_by_ids(&hits.ids, rows, key)
+        .into_iter()
+        .zip(query.offset..)
+        .map(|(row, offset)| (offset_cursor(offset), EmptyEdgeFields {}, row))
+        .collect::<Vec<_>>();
+
+    let has_next_page = match hits.total {
+        Some(total) => query.offset + hits.ids.len() < total,
+        None => hits.ids.len() >= query.limit,
+    };
+
+    Connection {
+        total_count: hits.total,
+        page_info: PageInfo {
+            has_previous_page: query.offset > 0,
+            has_next_page,
+            start_cursor: nodes.first().map(|(cursor, _, _)| cursor.clone()),
+            end_cursor: nodes.last().map(|(cursor, _, _)| cursor.clone()),
+        },
+        nodes,
+    }
+}
+
+pub async fn search_connection<T, L, F>(
+    backend: &dyn SearchBackend,
+    index: &str,
+    query: SearchQuery,
+    load: L,
+    key: F,
+) -> SearchResult<Connection<T>>
+where
+    L: FnOnce(&SearchHits) -> SearchResult<Vec<T>>,
+    F: Fn(&T) -> String,
+{
+    let hits = backend.search(index, &query).await?;
+    let rows = load(&hits)?;
+
+    Ok(to_connection(&query, &hits, rows, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    use super::{search_connection, search_page};
+    use crate::backend::{MemoryBackend, SearchBackend};
+    use crate::index::SearchOperation;
+
+    #[test]
+    fn paginate() {
+        let backend = MemoryBackend::new();
+        let operations = (1..=5)
+            .map(|id| SearchOperation::Index {
+                index: "todos".to_owned(),
+                id: id.to_string(),
+                version: 1,
+                document: json!({ "text": format!("Todo {}", id) }),
+            })
+            .collect::<Vec<_>>();
+        block_on(backend.apply(&operations)).unwrap();
+
+        let load = |hits: &crate::index::SearchHits| {
+            Ok(hits
+                .ids
+                .iter()
+                .rev()
+                .map(|id| id.parse::<u32>().unwrap())
+                .collect())
+        };
+
+        let query = search_page("todo", Some(2), None).unwrap();
+        let connection = block_on(search_connection(
+            &backend,
+            "todos",
+            query,
+            load,
+            |row: &u32| row.to_string(),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            connection
+                .nodes
+                .iter()
+                .map(|(_, _, row)| *row)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(connection.page_info.has_next_page);
+
+        let after = connection
+            .page_info
+            .end_cursor
+            .map(|cursor| cursor.to_string());
+        let query = search_page("todo", Some(2), after).unwrap();
+        assert_eq!(query.offset, 2);
+
+        assert!(search_page("todo", None, Some("invalid".to_owned())).is_err());
+    }
+}
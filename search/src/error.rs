@@ -0,0 +1,34 @@
+#[derive(Debug, PartialEq, Error)]
+pub enum SearchError {
+    #[error("Backend error: {0}")]
+    Backend(String),
+
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+
+    #[error("Invalid cursor: {0}")]
+    Cursor(String),
+
+    #[error("{0}")]
+    Queue(String),
+}
+
+impl From<serde_json::Error> for SearchError {
+    fn from(e: serde_json::Error) -> SearchError {
+        SearchError::Serialization(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for SearchError {
+    fn from(e: reqwest::Error) -> SearchError {
+        SearchError::Backend(e.to_string())
+    }
+}
+
+impl From<timada_jobs::JobError> for SearchError {
+    fn from(e: timada_jobs::JobError) -> SearchError {
+        SearchError::Queue(e.to_string())
+    }
+}
+
+pub type SearchResult<T> = Result<T, SearchError>;
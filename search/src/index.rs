@@ -0,0 +1,219 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::error::SearchResult;
+
+pub trait Indexable: Serialize {
+    const INDEX: &'static str;
+
+    fn search_id(&self) -> String;
+
+    // Grows with every change of the model, usually `updated_at` in microseconds. Sync jobs
+    // may run out of order, a backend ignores an operation older than the indexed one.
+    fn search_version(&self) -> i64;
+
+    fn document(&self) -> SearchResult<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SearchOperation {
+    Index {
+        index: String,
+        id: String,
+        #[serde(default)]
+        version: i64,
+        document: Value,
+    },
+    Delete {
+        index: String,
+        id: String,
+        #[serde(default)]
+        version: i64,
+    },
+}
+
+// Deletes have no model left to read a version from, they happen now.
+fn now_version() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_micros() as i64)
+        .unwrap_or_default()
+}
+
+impl SearchOperation {
+    pub fn index<M: Indexable>(model: &M) -> SearchResult<Self> {
+        let mut document = model.document()?;
+
+        if let Value::Object(map) = &mut document {
+            map.insert("id".to_owned(), Value::String(model.search_id()));
+        }
+
+        Ok(SearchOperation::Index {
+            index: M::INDEX.to_owned(),
+            id: model.search_id(),
+            version: model.search_version(),
+            document,
+        })
+    }
+
+    pub fn delete<M: Indexable>(id: &str) -> Self {
+        SearchOperation::Delete {
+            index: M::INDEX.to_owned(),
+            id: id.to_owned(),
+            version: now_version(),
+        }
+    }
+
+    pub fn index_name(&self) -> &str {
+        match self {
+            SearchOperation::Index { index, .. } | SearchOperation::Delete { index, .. } => index,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        match self {
+            SearchOperation::Index { id, .. } | SearchOperation::Delete { id, .. } => id,
+        }
+    }
+
+    pub fn version(&self) -> i64 {
+        match self {
+            SearchOperation::Index { version, .. } | SearchOperation::Delete { version, .. } => {
+                *version
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub query: String,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+impl SearchQuery {
+    pub fn new(query: &str) -> Self {
+        SearchQuery {
+            query: query.to_owned(),
+            limit: 20,
+            offset: 0,
+        }
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchHits {
+    pub ids: Vec<String>,
+    pub total: Option<usize>,
+}
+
+impl SearchHits {
+    pub fn uuids(&self) -> Vec<uuid::Uuid> {
+        self.ids
+            .iter()
+            .filter_map(|id| uuid::Uuid::parse_str(id).ok())
+            .collect()
+    }
+}
+
+pub fn order_by_ids<T, F>(ids: &[String], rows: Vec<T>, key: F) -> Vec<T>
+where
+    F: Fn(&T) -> String,
+{
+    let mut rows = rows
+        .into_iter()
+        .map(|row| (key(&row), row))
+        .collect::<Vec<_>>();
+
+    ids.iter()
+        .filter_map(|id| {
+            rows.iter()
+                .position(|(key, _)| key == id)
+                .map(|index| rows.remove(index).1)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{order_by_ids, Indexable, SearchOperation};
+
+    #[derive(Serialize)]
+    struct Todo {
+        #[serde(skip)]
+        id: u32,
+        text: String,
+    }
+
+    impl Indexable for Todo {
+        const INDEX: &'static str = "todos";
+
+        fn search_id(&self) -> String {
+            self.id.to_string()
+        }
+
+        fn search_version(&self) -> i64 {
+            1
+        }
+    }
+
+    #[test]
+    fn operations() {
+        let todo = Todo {
+            id: 1,
+            text: "Buy milk".to_owned(),
+        };
+
+        assert_eq!(
+            SearchOperation::index(&todo).unwrap(),
+            SearchOperation::Index {
+                index: "todos".to_owned(),
+                id: "1".to_owned(),
+                version: 1,
+                document: json!({ "id": "1", "text": "Buy milk" }),
+            }
+        );
+
+        let delete = SearchOperation::delete::<Todo>("1");
+        assert!(delete.version() > 1);
+        assert_eq!(
+            serde_json::to_value(&delete).unwrap(),
+            json!({ "op": "delete", "index": "todos", "id": "1", "version": delete.version() })
+        );
+        assert_eq!(
+            serde_json::from_value::<SearchOperation>(
+                json!({ "op": "delete", "index": "todos", "id": "1" })
+            )
+            .unwrap()
+            .version(),
+            0
+        );
+    }
+
+    #[test]
+    fn order() {
+        let ids = vec!["3".to_owned(), "1".to_owned(), "4".to_owned()];
+
+        assert_eq!(
+            order_by_ids(&ids, vec![1, 2, 3], |row| row.to_string()),
+            vec![3, 1]
+        );
+    }
+}
@@ -0,0 +1,20 @@
+#[macro_use]
+extern crate serde;
+
+#[macro_use]
+extern crate thiserror;
+
+mod backend;
+mod connection;
+mod error;
+mod index;
+mod sync;
+
+pub use crate::backend::{ElasticsearchBackend, MeilisearchBackend, MemoryBackend, SearchBackend};
+pub use crate::connection::{search_connection, search_page, to_connection};
+pub use crate::error::{SearchError, SearchResult};
+pub use crate::index::{order_by_ids, Indexable, SearchHits, SearchOperation, SearchQuery};
+pub use crate::sync::{
+    backfill, enqueue_delete, enqueue_index, enqueue_operations, search_backend,
+    set_search_backend, SyncSearchIndex,
+};
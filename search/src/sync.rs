@@ -0,0 +1,200 @@
+use diesel::PgConnection;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use timada_jobs::{enqueue, Job, JobContext, JobError, JobResult, RetryPolicy};
+use uuid::Uuid;
+
+use super::backend::SearchBackend;
+use super::error::SearchResult;
+use super::index::{Indexable, SearchOperation};
+
+lazy_static::lazy_static! {
+    static ref BACKEND: RwLock<Option<Arc<dyn SearchBackend>>> = RwLock::new(None);
+}
+
+pub fn set_search_backend<B: SearchBackend + 'static>(backend: B) {
+    if let Ok(mut current) = BACKEND.write() {
+        *current = Some(Arc::new(backend));
+    }
+}
+
+pub fn search_backend() -> Option<Arc<dyn SearchBackend>> {
+    match BACKEND.read() {
+        Ok(backend) => backend.clone(),
+        Err(e) => e.into_inner().clone(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncSearchIndex {
+    pub operations: Vec<SearchOperation>,
+}
+
+#[async_trait::async_trait]
+impl Job for SyncSearchIndex {
+    const NAME: &'static str = "search.sync";
+    const QUEUE: &'static str = "search";
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .max_attempts(10)
+            .base_delay(Duration::from_secs(10))
+    }
+
+    async fn run(&self, _ctx: &JobContext) -> JobResult<()> {
+        let backend =
+            search_backend().ok_or_else(|| JobError::failed("Search backend is not configured"))?;
+
+        backend
+            .apply(&self.operations)
+            .await
+            .map_err(JobError::failed)
+    }
+}
+
+pub fn enqueue_operations(
+    conn: &PgConnection,
+    operations: Vec<SearchOperation>,
+) -> SearchResult<Option<Uuid>> {
+    if operations.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(enqueue(conn, &SyncSearchIndex { operations })?))
+}
+
+pub fn enqueue_index<M: Indexable>(
+    conn: &PgConnection,
+    models: &[M],
+) -> SearchResult<Option<Uuid>> {
+    let operations = models
+        .iter()
+        .map(SearchOperation::index)
+        .collect::<SearchResult<Vec<_>>>()?;
+
+    enqueue_operations(conn, operations)
+}
+
+pub fn enqueue_delete<M: Indexable>(
+    conn: &PgConnection,
+    ids: &[String],
+) -> SearchResult<Option<Uuid>> {
+    let operations = ids
+        .iter()
+        .map(|id| SearchOperation::delete::<M>(id))
+        .collect();
+
+    enqueue_operations(conn, operations)
+}
+
+pub async fn backfill<M, F>(
+    backend: &dyn SearchBackend,
+    batch_size: usize,
+    mut load: F,
+) -> SearchResult<usize>
+where
+    M: Indexable,
+    F: FnMut(usize, usize) -> SearchResult<Vec<M>>,
+{
+    let batch_size = batch_size.max(1);
+    let mut offset = 0;
+
+    loop {
+        let models = load(offset, batch_size)?;
+        let len = models.len();
+
+        let operations = models
+            .iter()
+            .map(SearchOperation::index)
+            .collect::<SearchResult<Vec<_>>>()?;
+        backend.apply(&operations).await?;
+
+        offset += len;
+        log::info!("search backfill {}: {} documents indexed", M::INDEX, offset);
+
+        if len < batch_size {
+            return Ok(offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::r2d2::{ConnectionManager, Pool};
+    use diesel::PgConnection;
+    use futures::executor::block_on;
+    use timada_jobs::{Job, JobContext};
+    use uuid::Uuid;
+
+    use super::{backfill, set_search_backend, SyncSearchIndex};
+    use crate::backend::MemoryBackend;
+    use crate::index::{Indexable, SearchOperation};
+
+    #[derive(Serialize)]
+    struct Todo {
+        id: usize,
+        text: String,
+    }
+
+    impl Indexable for Todo {
+        const INDEX: &'static str = "todos";
+
+        fn search_id(&self) -> String {
+            self.id.to_string()
+        }
+
+        fn search_version(&self) -> i64 {
+            1
+        }
+    }
+
+    fn todos(offset: usize, limit: usize) -> Vec<Todo> {
+        (offset..(offset + limit).min(25))
+            .map(|id| Todo {
+                id,
+                text: format!("Todo {}", id),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn backfill_batches() {
+        let backend = MemoryBackend::new();
+        let mut batches = 0;
+
+        let indexed = block_on(backfill(&backend, 10, |offset, limit| {
+            batches += 1;
+            Ok(todos(offset, limit))
+        }))
+        .unwrap();
+
+        assert_eq!(indexed, 25);
+        assert_eq!(batches, 3);
+        assert_eq!(backend.len("todos"), 25);
+    }
+
+    #[test]
+    fn sync_job() {
+        let backend = MemoryBackend::new();
+        set_search_backend(backend.clone());
+
+        let job = SyncSearchIndex {
+            operations: todos(0, 2)
+                .iter()
+                .map(SearchOperation::index)
+                .collect::<Result<_, _>>()
+                .unwrap(),
+        };
+        let ctx = JobContext {
+            id: Uuid::new_v4(),
+            attempt: 1,
+            pool: Pool::builder().build_unchecked(ConnectionManager::<PgConnection>::new(
+                "postgres://localhost",
+            )),
+        };
+
+        block_on(job.run(&ctx)).unwrap();
+
+        assert_eq!(backend.len("todos"), 2);
+    }
+}
@@ -0,0 +1,132 @@
+use sentry::protocol::{Breadcrumb, Map, Value};
+use sentry::Level;
+use std::fmt;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+fn level(level: &tracing::Level) -> Level {
+    match *level {
+        tracing::Level::ERROR => Level::Error,
+        tracing::Level::WARN => Level::Warning,
+        tracing::Level::INFO => Level::Info,
+        _ => Level::Debug,
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    message: Option<String>,
+    data: Map<String, Value>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = Some(value.to_owned()),
+            name => {
+                self.data.insert(name.to_owned(), Value::from(value));
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = Some(format!("{:?}", value)),
+            name => {
+                self.data
+                    .insert(name.to_owned(), Value::from(format!("{:?}", value)));
+            }
+        }
+    }
+}
+
+fn breadcrumb(event: &Event<'_>) -> Breadcrumb {
+    let mut fields = Fields::default();
+    event.record(&mut fields);
+
+    let metadata = event.metadata();
+
+    Breadcrumb {
+        category: Some(metadata.target().to_owned()),
+        level: level(metadata.level()),
+        message: fields.message,
+        data: fields.data,
+        ..Default::default()
+    }
+}
+
+// Events become breadcrumbs of the current hub, the request hub bound by the http server, they
+// are sent along with the next captured error.
+pub struct BreadcrumbLayer {
+    max_level: tracing::Level,
+}
+
+impl BreadcrumbLayer {
+    pub fn new() -> Self {
+        BreadcrumbLayer {
+            max_level: tracing::Level::INFO,
+        }
+    }
+
+    pub fn max_level(mut self, max_level: tracing::Level) -> Self {
+        self.max_level = max_level;
+        self
+    }
+}
+
+impl Default for BreadcrumbLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for BreadcrumbLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().level() > &self.max_level {
+            return;
+        }
+
+        sentry::add_breadcrumb(breadcrumb(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry::protocol::Value;
+    use sentry::Level;
+    use std::sync::{Arc, Mutex};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+    use tracing_subscriber::Registry;
+
+    use super::breadcrumb;
+
+    struct Recorder(Arc<Mutex<Vec<sentry::protocol::Breadcrumb>>>);
+
+    impl<S: Subscriber> Layer<S> for Recorder {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            self.0.lock().unwrap().push(breadcrumb(event));
+        }
+    }
+
+    #[test]
+    fn event_to_breadcrumb() {
+        let breadcrumbs = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = Registry::default().with(Recorder(breadcrumbs.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "todos", todo_id = 1, "todo {} is late", "buy milk");
+        });
+
+        let breadcrumbs = breadcrumbs.lock().unwrap();
+        assert_eq!(breadcrumbs.len(), 1);
+        assert_eq!(breadcrumbs[0].category.as_deref(), Some("todos"));
+        assert_eq!(breadcrumbs[0].level, Level::Warning);
+        assert_eq!(
+            breadcrumbs[0].message.as_deref(),
+            Some("todo buy milk is late")
+        );
+        assert_eq!(breadcrumbs[0].data.get("todo_id"), Some(&Value::from("1")));
+    }
+}
@@ -0,0 +1,104 @@
+use timada_util::env::{self, EnvError};
+use timada_util::secret::{try_secret, Secret, SecretError};
+
+#[derive(Debug, Clone)]
+pub struct SentryConfig {
+    pub dsn: Option<Secret<String>>,
+    pub environment: String,
+    pub release: Option<String>,
+    pub sample_rate: f32,
+}
+
+impl SentryConfig {
+    pub fn new(dsn: &str) -> Self {
+        SentryConfig {
+            dsn: Some(Secret::from(dsn)),
+            environment: "development".to_owned(),
+            release: None,
+            sample_rate: 1.0,
+        }
+    }
+
+    // Reporting is disabled when `SENTRY_DSN` is missing, so local setups need no account.
+    pub fn from_env() -> Self {
+        let sample_rate = match env::try_var_parsed::<f32>("SENTRY_SAMPLE_RATE") {
+            Err(EnvError::Missing(_)) => 1.0,
+            res => res.unwrap_or_else(|e| panic!("{}", e)),
+        };
+
+        if !(0.0..=1.0).contains(&sample_rate) {
+            panic!("SENTRY_SAMPLE_RATE must be between 0 and 1");
+        }
+
+        let dsn = match try_secret("SENTRY_DSN") {
+            Err(SecretError::Missing(_)) => None,
+            res => Some(res.unwrap_or_else(|e| panic!("{}", e))),
+        };
+
+        SentryConfig {
+            dsn,
+            environment: env::var_opt("SENTRY_ENVIRONMENT")
+                .unwrap_or_else(|| env::var_or("APP_ENV", "development")),
+            release: env::var_opt("SENTRY_RELEASE"),
+            sample_rate,
+        }
+    }
+
+    // Usually `concat!(env!("CARGO_PKG_NAME"), "@", env!("CARGO_PKG_VERSION"))`.
+    pub fn release(mut self, release: &str) -> Self {
+        self.release = Some(release.to_owned());
+        self
+    }
+
+    pub fn environment(mut self, environment: &str) -> Self {
+        self.environment = environment.to_owned();
+        self
+    }
+
+    pub fn sample_rate(mut self, sample_rate: f32) -> Self {
+        self.sample_rate = sample_rate.max(0.0).min(1.0);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.dsn.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_util::env::test_scope;
+
+    use super::SentryConfig;
+
+    #[test]
+    fn from_env() {
+        let _env = test_scope()
+            .set("SENTRY_DSN", "https://key@sentry.io/1")
+            .remove("SENTRY_ENVIRONMENT")
+            .set("APP_ENV", "staging")
+            .remove("SENTRY_RELEASE")
+            .set("SENTRY_SAMPLE_RATE", "0.5");
+
+        let config = SentryConfig::from_env().release("todos@1.0.0");
+
+        assert!(config.is_enabled());
+        assert_eq!(config.environment, "staging");
+        assert_eq!(config.release.as_deref(), Some("todos@1.0.0"));
+        assert_eq!(config.sample_rate, 0.5);
+    }
+
+    #[test]
+    fn disabled_without_dsn() {
+        let _env = test_scope()
+            .remove("SENTRY_DSN")
+            .set("SENTRY_ENVIRONMENT", "production")
+            .remove("SENTRY_SAMPLE_RATE");
+
+        let config = SentryConfig::from_env();
+
+        assert!(!config.is_enabled());
+        assert_eq!(config.environment, "production");
+        assert_eq!(config.sample_rate, 1.0);
+    }
+}
@@ -0,0 +1,43 @@
+use sentry::integrations::panic::register_panic_handler;
+use sentry::{ClientInitGuard, ClientOptions};
+use timada_http::set_error_reporter;
+
+use super::config::SentryConfig;
+use super::reporter::SentryErrorReporter;
+
+// Keep it alive for the lifetime of the binary, dropping it flushes pending events.
+pub struct Sentry {
+    guard: Option<ClientInitGuard>,
+}
+
+impl Sentry {
+    pub fn is_enabled(&self) -> bool {
+        self.guard
+            .as_ref()
+            .map(|guard| guard.is_enabled())
+            .unwrap_or(false)
+    }
+}
+
+pub fn init(config: &SentryConfig) -> Sentry {
+    let dsn = match config.dsn.as_ref() {
+        Some(dsn) => dsn.expose().to_owned(),
+        None => return Sentry { guard: None },
+    };
+
+    let guard = sentry::init((
+        dsn,
+        ClientOptions {
+            environment: Some(config.environment.clone().into()),
+            release: config.release.clone().map(Into::into),
+            sample_rate: config.sample_rate,
+            attach_stacktrace: true,
+            ..Default::default()
+        },
+    ));
+
+    register_panic_handler();
+    set_error_reporter(SentryErrorReporter);
+
+    Sentry { guard: Some(guard) }
+}
@@ -0,0 +1,9 @@
+mod breadcrumbs;
+mod config;
+mod init;
+mod reporter;
+
+pub use crate::breadcrumbs::BreadcrumbLayer;
+pub use crate::config::SentryConfig;
+pub use crate::init::{init, Sentry};
+pub use crate::reporter::SentryErrorReporter;
@@ -0,0 +1,54 @@
+use sentry::protocol::User;
+use sentry::{Hub, Level};
+use std::sync::Arc;
+use timada_http::{ErrorReport, ErrorReporter, RequestScope};
+
+pub struct SentryErrorReporter;
+
+// Each request gets its own hub, breadcrumbs and scope of one request are not sent
+// with the events of another running on the same worker thread.
+struct HubScope(Arc<Hub>);
+
+impl RequestScope for HubScope {
+    fn run(&self, f: &mut dyn FnMut()) {
+        Hub::run(self.0.clone(), f)
+    }
+}
+
+impl ErrorReporter for SentryErrorReporter {
+    fn report(&self, report: &ErrorReport<'_>) {
+        sentry::with_scope(
+            |scope| {
+                if let Some(error_id) = report.error_id {
+                    scope.set_tag("error_id", error_id);
+                }
+
+                if let Some(request_id) = report.request_id {
+                    scope.set_tag("request_id", request_id);
+                }
+
+                if let Some(user_role) = report.user_role {
+                    scope.set_tag("user_role", user_role);
+                }
+
+                if let Some(impersonator_id) = report.impersonator_id {
+                    scope.set_tag("impersonator_id", impersonator_id);
+                }
+
+                if let Some(user_id) = report.user_id {
+                    scope.set_user(Some(User {
+                        id: Some(user_id.to_string()),
+                        ..Default::default()
+                    }));
+                }
+            },
+            || sentry::capture_message(&report.error.to_string(), Level::Error),
+        );
+    }
+
+    fn request_scope(&self) -> Option<Box<dyn RequestScope>> {
+        Some(Box::new(HubScope(Arc::new(Hub::new_from_top(
+            Hub::current(),
+        )))))
+    }
+}
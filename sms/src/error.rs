@@ -0,0 +1,43 @@
+use timada_http::Error;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SmsError {
+    #[error("Invalid phone number {0}, expected E.164 format")]
+    InvalidNumber(String),
+
+    #[error("Invalid message: {0}")]
+    InvalidMessage(String),
+
+    #[error("Too many messages sent to {0}")]
+    RateLimited(String),
+
+    #[error("Rejected by provider: {0}")]
+    Rejected(String),
+
+    #[error("Provider error: {0}")]
+    Provider(String),
+
+    #[error("Invalid status callback: {0}")]
+    Callback(String),
+}
+
+impl From<reqwest::Error> for SmsError {
+    fn from(e: reqwest::Error) -> SmsError {
+        SmsError::Provider(e.to_string())
+    }
+}
+
+impl From<SmsError> for Error {
+    fn from(e: SmsError) -> Error {
+        match e {
+            SmsError::InvalidNumber(_) | SmsError::InvalidMessage(_) => {
+                Error::UnprocessableEntity(e.to_string())
+            }
+            SmsError::RateLimited(_) => Error::TooManyRequests(e.to_string()),
+            SmsError::Callback(message) => Error::Forbidden(message),
+            e => Error::Internal(e.to_string()),
+        }
+    }
+}
+
+pub type SmsResult<T> = Result<T, SmsError>;
@@ -0,0 +1,23 @@
+#[macro_use]
+extern crate serde;
+
+#[macro_use]
+extern crate thiserror;
+
+mod error;
+mod limit;
+mod message;
+mod phone;
+mod sender;
+mod twilio;
+
+pub use crate::error::{SmsError, SmsResult};
+pub use crate::limit::RecipientRateLimit;
+pub use crate::message::{
+    CaptureProvider, DeliveryStatus, SentSms, Sms, SmsProvider, StatusListener, StatusUpdate,
+};
+pub use crate::phone::PhoneNumber;
+pub use crate::sender::{SmsSender, MAX_BODY_LENGTH};
+pub use crate::twilio::{
+    parse_status_callback, twilio_status_callback, TwilioCallback, TwilioConfig, TwilioProvider,
+};
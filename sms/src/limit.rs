@@ -0,0 +1,78 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::error::{SmsError, SmsResult};
+use super::phone::PhoneNumber;
+
+pub struct RecipientRateLimit {
+    max: usize,
+    window: Duration,
+    sent: Mutex<HashMap<PhoneNumber, VecDeque<Instant>>>,
+}
+
+impl RecipientRateLimit {
+    pub fn new(max: usize, window: Duration) -> Self {
+        RecipientRateLimit {
+            max,
+            window,
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn check(&self, to: &PhoneNumber) -> SmsResult<()> {
+        self.check_at(to, Instant::now())
+    }
+
+    fn check_at(&self, to: &PhoneNumber, now: Instant) -> SmsResult<()> {
+        let mut sent = self
+            .sent
+            .lock()
+            .map_err(|e| SmsError::Provider(e.to_string()))?;
+
+        let window = self.window;
+        sent.retain(|_, times| {
+            while times
+                .front()
+                .map(|time| now.duration_since(*time) >= window)
+                .unwrap_or(false)
+            {
+                times.pop_front();
+            }
+
+            !times.is_empty()
+        });
+
+        let times = sent.entry(to.clone()).or_default();
+
+        if times.len() >= self.max {
+            return Err(SmsError::RateLimited(to.masked()));
+        }
+
+        times.push_back(now);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::RecipientRateLimit;
+    use crate::phone::PhoneNumber;
+
+    #[test]
+    fn limit() {
+        let limit = RecipientRateLimit::new(2, Duration::from_secs(60));
+        let john = PhoneNumber::parse("+33612345678").unwrap();
+        let jane = PhoneNumber::parse("+33687654321").unwrap();
+        let now = Instant::now();
+
+        assert!(limit.check_at(&john, now).is_ok());
+        assert!(limit.check_at(&john, now).is_ok());
+        assert!(limit.check_at(&john, now).is_err());
+        assert!(limit.check_at(&jane, now).is_ok());
+        assert!(limit.check_at(&john, now + Duration::from_secs(60)).is_ok());
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use super::error::{SmsError, SmsResult};
+use super::phone::PhoneNumber;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sms {
+    pub to: PhoneNumber,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Accepted,
+    Queued,
+    Sending,
+    Sent,
+    Delivered,
+    Undelivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn parse(value: &str) -> SmsResult<Self> {
+        match value {
+            "accepted" => Ok(DeliveryStatus::Accepted),
+            "queued" | "scheduled" => Ok(DeliveryStatus::Queued),
+            "sending" => Ok(DeliveryStatus::Sending),
+            "sent" => Ok(DeliveryStatus::Sent),
+            "delivered" | "read" => Ok(DeliveryStatus::Delivered),
+            "undelivered" => Ok(DeliveryStatus::Undelivered),
+            "failed" | "canceled" => Ok(DeliveryStatus::Failed),
+            _ => Err(SmsError::Callback(format!("unknown status {}", value))),
+        }
+    }
+
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            DeliveryStatus::Delivered | DeliveryStatus::Undelivered | DeliveryStatus::Failed
+        )
+    }
+
+    pub fn is_failure(&self) -> bool {
+        matches!(self, DeliveryStatus::Undelivered | DeliveryStatus::Failed)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SentSms {
+    pub id: String,
+    pub to: PhoneNumber,
+    pub status: DeliveryStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusUpdate {
+    pub message_id: String,
+    pub to: Option<PhoneNumber>,
+    pub status: DeliveryStatus,
+    pub error_code: Option<String>,
+}
+
+#[async_trait::async_trait]
+pub trait SmsProvider: Send + Sync {
+    async fn send(&self, sms: &Sms) -> SmsResult<SentSms>;
+}
+
+pub trait StatusListener: Send + Sync {
+    fn on_status(&self, update: &StatusUpdate);
+}
+
+impl<F> StatusListener for F
+where
+    F: Fn(&StatusUpdate) + Send + Sync,
+{
+    fn on_status(&self, update: &StatusUpdate) {
+        self(update)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CaptureProvider {
+    sent: Arc<Mutex<Vec<Sms>>>,
+    rejected: Arc<Mutex<HashSet<PhoneNumber>>>,
+}
+
+impl CaptureProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sent(&self) -> Vec<Sms> {
+        self.sent
+            .lock()
+            .map(|sent| sent.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn last(&self) -> Option<Sms> {
+        self.sent().pop()
+    }
+
+    pub fn sent_to(&self, to: &str) -> Vec<Sms> {
+        self.sent()
+            .into_iter()
+            .filter(|sms| sms.to.as_str() == to)
+            .collect()
+    }
+
+    pub fn assert_sent_to(&self, to: &str) -> Sms {
+        match self.sent_to(to).pop() {
+            Some(sms) => sms,
+            None => panic!("no sms sent to {}", to),
+        }
+    }
+
+    pub fn reject(&self, to: &PhoneNumber) {
+        if let Ok(mut rejected) = self.rejected.lock() {
+            rejected.insert(to.clone());
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut sent) = self.sent.lock() {
+            sent.clear();
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsProvider for CaptureProvider {
+    async fn send(&self, sms: &Sms) -> SmsResult<SentSms> {
+        let is_rejected = self
+            .rejected
+            .lock()
+            .map(|rejected| rejected.contains(&sms.to))
+            .unwrap_or(false);
+
+        if is_rejected {
+            return Err(SmsError::Rejected(format!("{} is not reachable", sms.to)));
+        }
+
+        let mut sent = self
+            .sent
+            .lock()
+            .map_err(|e| SmsError::Provider(e.to_string()))?;
+        sent.push(sms.clone());
+
+        Ok(SentSms {
+            id: format!("SM{:032}", sent.len()),
+            to: sms.to.clone(),
+            status: DeliveryStatus::Queued,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CaptureProvider, DeliveryStatus, Sms, SmsProvider};
+    use crate::error::SmsError;
+    use crate::phone::PhoneNumber;
+
+    #[test]
+    fn status() {
+        assert_eq!(
+            DeliveryStatus::parse("delivered").unwrap(),
+            DeliveryStatus::Delivered
+        );
+        assert!(DeliveryStatus::parse("undelivered").unwrap().is_failure());
+        assert!(!DeliveryStatus::parse("sent").unwrap().is_final());
+        assert!(DeliveryStatus::parse("lost").is_err());
+    }
+
+    #[test]
+    fn capture() {
+        let provider = CaptureProvider::new();
+        let to = PhoneNumber::parse("+33612345678").unwrap();
+        let sms = Sms {
+            to: to.clone(),
+            body: "Your code is 123456".to_owned(),
+        };
+
+        let sent = futures::executor::block_on(provider.send(&sms)).unwrap();
+        assert_eq!(sent.status, DeliveryStatus::Queued);
+        assert_eq!(provider.assert_sent_to("+33612345678"), sms);
+
+        provider.reject(&to);
+        assert!(matches!(
+            futures::executor::block_on(provider.send(&sms)),
+            Err(SmsError::Rejected(_))
+        ));
+        assert_eq!(provider.sent().len(), 1);
+    }
+}
@@ -0,0 +1,110 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use super::error::{SmsError, SmsResult};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PhoneNumber(String);
+
+impl PhoneNumber {
+    // Accepts common separators (spaces, dashes, dots and parentheses) and
+    // normalizes to `+<country code><subscriber number>`.
+    pub fn parse(value: &str) -> SmsResult<Self> {
+        let invalid = || SmsError::InvalidNumber(value.to_owned());
+        let value = value.trim();
+
+        let digits = value.strip_prefix('+').ok_or_else(invalid)?;
+        let digits = digits
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+            .collect::<String>();
+
+        let valid = (8..=15).contains(&digits.len())
+            && digits.chars().all(|c| c.is_ascii_digit())
+            && !digits.starts_with('0');
+
+        if !valid {
+            return Err(invalid());
+        }
+
+        Ok(PhoneNumber(format!("+{}", digits)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn masked(&self) -> String {
+        let visible = self.0.len().saturating_sub(4);
+
+        format!("{}{}", "*".repeat(visible), &self.0[visible..])
+    }
+}
+
+impl fmt::Display for PhoneNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for PhoneNumber {
+    type Err = SmsError;
+
+    fn from_str(value: &str) -> SmsResult<Self> {
+        Self::parse(value)
+    }
+}
+
+impl TryFrom<String> for PhoneNumber {
+    type Error = SmsError;
+
+    fn try_from(value: String) -> SmsResult<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<PhoneNumber> for String {
+    fn from(number: PhoneNumber) -> String {
+        number.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PhoneNumber;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            PhoneNumber::parse("+33612345678").unwrap().as_str(),
+            "+33612345678"
+        );
+        assert_eq!(
+            PhoneNumber::parse(" +1 (415) 555-0100 ").unwrap().as_str(),
+            "+14155550100"
+        );
+        assert!(PhoneNumber::parse("0612345678").is_err());
+        assert!(PhoneNumber::parse("+0612345678").is_err());
+        assert!(PhoneNumber::parse("+336123").is_err());
+        assert!(PhoneNumber::parse("+3361234567890123").is_err());
+        assert!(PhoneNumber::parse("+3361234567a").is_err());
+    }
+
+    #[test]
+    fn masked() {
+        assert_eq!(
+            PhoneNumber::parse("+33612345678").unwrap().masked(),
+            "********5678"
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let number: PhoneNumber = serde_json::from_str("\"+33 6 12 34 56 78\"").unwrap();
+
+        assert_eq!(serde_json::to_string(&number).unwrap(), "\"+33612345678\"");
+        assert!(serde_json::from_str::<PhoneNumber>("\"612345678\"").is_err());
+    }
+}
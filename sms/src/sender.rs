@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::error::{SmsError, SmsResult};
+use super::limit::RecipientRateLimit;
+use super::message::{SentSms, Sms, SmsProvider};
+use super::phone::PhoneNumber;
+
+pub const MAX_BODY_LENGTH: usize = 1600;
+
+#[derive(Clone)]
+pub struct SmsSender {
+    provider: Arc<dyn SmsProvider>,
+    rate_limit: Option<Arc<RecipientRateLimit>>,
+}
+
+impl SmsSender {
+    pub fn new<P: SmsProvider + 'static>(provider: P) -> Self {
+        SmsSender {
+            provider: Arc::new(provider),
+            rate_limit: None,
+        }
+    }
+
+    pub fn rate_limit(mut self, max: usize, window: Duration) -> Self {
+        self.rate_limit = Some(Arc::new(RecipientRateLimit::new(max, window)));
+        self
+    }
+
+    pub async fn send(&self, to: &str, body: &str) -> SmsResult<SentSms> {
+        let sms = Sms {
+            to: PhoneNumber::parse(to)?,
+            body: body.to_owned(),
+        };
+
+        self.send_sms(&sms).await
+    }
+
+    pub async fn send_sms(&self, sms: &Sms) -> SmsResult<SentSms> {
+        if sms.body.trim().is_empty() {
+            return Err(SmsError::InvalidMessage("body is empty".to_owned()));
+        }
+
+        if sms.body.chars().count() > MAX_BODY_LENGTH {
+            return Err(SmsError::InvalidMessage(format!(
+                "body exceeds {} characters",
+                MAX_BODY_LENGTH
+            )));
+        }
+
+        if let Some(rate_limit) = self.rate_limit.as_ref() {
+            rate_limit.check(&sms.to)?;
+        }
+
+        let sent = self.provider.send(sms).await?;
+        log::info!("sms {} sent to {}", sent.id, sms.to.masked());
+
+        Ok(sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use std::time::Duration;
+
+    use super::SmsSender;
+    use crate::error::SmsError;
+    use crate::message::CaptureProvider;
+
+    #[test]
+    fn send() {
+        let provider = CaptureProvider::new();
+        let sender = SmsSender::new(provider.clone()).rate_limit(1, Duration::from_secs(60));
+
+        block_on(sender.send("+33 6 12 34 56 78", "Your code is 123456")).unwrap();
+        assert_eq!(
+            provider.assert_sent_to("+33612345678").body,
+            "Your code is 123456"
+        );
+
+        assert!(matches!(
+            block_on(sender.send("+33612345678", "Your code is 654321")),
+            Err(SmsError::RateLimited(_))
+        ));
+        assert!(matches!(
+            block_on(sender.send("0612345678", "Hello")),
+            Err(SmsError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            block_on(sender.send("+33687654321", " ")),
+            Err(SmsError::InvalidMessage(_))
+        ));
+        assert_eq!(provider.sent().len(), 1);
+    }
+}
@@ -0,0 +1,347 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use sha1::Sha1;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use timada_http::Error;
+use timada_util::env;
+use timada_util::secret::{secret, Secret};
+
+use super::error::{SmsError, SmsResult};
+use super::message::{DeliveryStatus, SentSms, Sms, SmsProvider, StatusListener, StatusUpdate};
+use super::phone::PhoneNumber;
+
+const SIGNATURE_HEADER: &str = "x-twilio-signature";
+
+type HmacSha1 = Hmac<Sha1>;
+
+#[derive(Debug, Clone)]
+pub struct TwilioConfig {
+    pub account_sid: String,
+    pub auth_token: Secret<String>,
+    pub from: String,
+    pub status_callback: Option<String>,
+    pub api_url: String,
+}
+
+impl TwilioConfig {
+    pub fn new(account_sid: &str, auth_token: &str, from: &str) -> Self {
+        TwilioConfig {
+            account_sid: account_sid.to_owned(),
+            auth_token: Secret::from(auth_token),
+            from: from.to_owned(),
+            status_callback: None,
+            api_url: "https://api.twilio.com".to_owned(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        TwilioConfig {
+            account_sid: env::var("TWILIO_ACCOUNT_SID"),
+            auth_token: secret("TWILIO_AUTH_TOKEN"),
+            from: env::var("TWILIO_FROM"),
+            status_callback: env::var_opt("TWILIO_STATUS_CALLBACK"),
+            api_url: env::var_or("TWILIO_API_URL", "https://api.twilio.com"),
+        }
+    }
+
+    pub fn status_callback(mut self, url: &str) -> Self {
+        self.status_callback = Some(url.to_owned());
+        self
+    }
+
+    pub fn api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_owned();
+        self
+    }
+
+    // Twilio signs the callback URL followed by the POST params sorted by name.
+    pub fn signature(&self, url: &str, params: &BTreeMap<String, String>) -> String {
+        base64::encode(self.mac(url, params).result().code())
+    }
+
+    pub fn verify_signature(
+        &self,
+        url: &str,
+        params: &BTreeMap<String, String>,
+        signature: &str,
+    ) -> bool {
+        match base64::decode(signature) {
+            Ok(signature) => self.mac(url, params).verify(&signature).is_ok(),
+            _ => false,
+        }
+    }
+
+    fn mac(&self, url: &str, params: &BTreeMap<String, String>) -> HmacSha1 {
+        let mut mac = HmacSha1::new_varkey(self.auth_token.expose().as_bytes())
+            .expect("HMAC can take key of any size");
+        mac.input(url.as_bytes());
+
+        for (name, value) in params {
+            mac.input(name.as_bytes());
+            mac.input(value.as_bytes());
+        }
+
+        mac
+    }
+}
+
+pub struct TwilioProvider {
+    config: TwilioConfig,
+    client: Client,
+}
+
+impl TwilioProvider {
+    pub fn new(config: TwilioConfig) -> Self {
+        TwilioProvider {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(TwilioConfig::from_env())
+    }
+
+    pub fn config(&self) -> &TwilioConfig {
+        &self.config
+    }
+
+    fn form<'a>(&'a self, sms: &'a Sms) -> Vec<(&'static str, &'a str)> {
+        let from = if self.config.from.starts_with("MG") {
+            "MessagingServiceSid"
+        } else {
+            "From"
+        };
+
+        let mut form = vec![
+            ("To", sms.to.as_str()),
+            (from, self.config.from.as_str()),
+            ("Body", sms.body.as_str()),
+        ];
+
+        if let Some(url) = self.config.status_callback.as_ref() {
+            form.push(("StatusCallback", url.as_str()));
+        }
+
+        form
+    }
+}
+
+fn provider_error(status: StatusCode, body: &Value) -> SmsError {
+    let message = format!(
+        "{} {}",
+        body["code"]
+            .as_u64()
+            .unwrap_or_else(|| status.as_u16().into()),
+        body["message"].as_str().unwrap_or_else(|| status.as_str())
+    );
+
+    if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+        SmsError::Rejected(message)
+    } else {
+        SmsError::Provider(message)
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsProvider for TwilioProvider {
+    async fn send(&self, sms: &Sms) -> SmsResult<SentSms> {
+        let url = format!(
+            "{}/2010-04-01/Accounts/{}/Messages.json",
+            self.config.api_url, self.config.account_sid
+        );
+
+        let res = self
+            .client
+            .post(&url)
+            .basic_auth(
+                &self.config.account_sid,
+                Some(self.config.auth_token.expose()),
+            )
+            .form(&self.form(sms))
+            .send()
+            .await?;
+
+        let status = res.status();
+        let body: Value = res.json().await.unwrap_or(Value::Null);
+
+        if !status.is_success() {
+            return Err(provider_error(status, &body));
+        }
+
+        Ok(SentSms {
+            id: body["sid"]
+                .as_str()
+                .ok_or_else(|| SmsError::Provider("missing message sid".to_owned()))?
+                .to_owned(),
+            to: sms.to.clone(),
+            status: body["status"]
+                .as_str()
+                .and_then(|status| DeliveryStatus::parse(status).ok())
+                .unwrap_or(DeliveryStatus::Queued),
+        })
+    }
+}
+
+pub fn parse_status_callback(params: &BTreeMap<String, String>) -> SmsResult<StatusUpdate> {
+    let param = |name: &str| {
+        params
+            .get(name)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| SmsError::Callback(format!("missing {}", name)))
+    };
+
+    Ok(StatusUpdate {
+        message_id: param("MessageSid")?.to_owned(),
+        to: params.get("To").and_then(|to| PhoneNumber::parse(to).ok()),
+        status: DeliveryStatus::parse(param("MessageStatus")?)?,
+        error_code: params
+            .get("ErrorCode")
+            .filter(|code| !code.is_empty())
+            .cloned(),
+    })
+}
+
+#[derive(Clone)]
+pub struct TwilioCallback {
+    config: TwilioConfig,
+    listener: Arc<dyn StatusListener>,
+}
+
+impl TwilioCallback {
+    pub fn new<L: StatusListener + 'static>(config: TwilioConfig, listener: L) -> SmsResult<Self> {
+        if config.status_callback.is_none() {
+            return Err(SmsError::Callback(
+                "status callback URL is not configured".to_owned(),
+            ));
+        }
+
+        Ok(TwilioCallback {
+            config,
+            listener: Arc::new(listener),
+        })
+    }
+
+    pub fn handle(
+        &self,
+        signature: Option<&str>,
+        params: &BTreeMap<String, String>,
+    ) -> SmsResult<StatusUpdate> {
+        let url = self.config.status_callback.as_deref().unwrap_or_default();
+        let verified = signature
+            .map(|signature| self.config.verify_signature(url, params, signature))
+            .unwrap_or(false);
+
+        if !verified {
+            return Err(SmsError::Callback("invalid signature".to_owned()));
+        }
+
+        let update = parse_status_callback(params)?;
+
+        if update.status.is_failure() {
+            log::warn!(
+                "sms {} failed with error {}",
+                update.message_id,
+                update.error_code.as_deref().unwrap_or("unknown")
+            );
+        }
+
+        self.listener.on_status(&update);
+
+        Ok(update)
+    }
+}
+
+// Mount with `web::post().to(twilio_status_callback)` and `app_data(web::Data::new(callback))`.
+pub async fn twilio_status_callback(
+    callback: web::Data<TwilioCallback>,
+    req: HttpRequest,
+    params: web::Form<BTreeMap<String, String>>,
+) -> actix_web::Result<HttpResponse> {
+    let signature = req
+        .headers()
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    callback.handle(signature, &params).map_err(Error::from)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::{parse_status_callback, TwilioCallback, TwilioConfig};
+    use crate::message::{DeliveryStatus, StatusUpdate};
+
+    const URL: &str = "https://api.timada.co/sms/status";
+
+    fn params() -> BTreeMap<String, String> {
+        vec![
+            ("MessageSid", "SM123"),
+            ("MessageStatus", "delivered"),
+            ("To", "+33612345678"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .collect()
+    }
+
+    #[test]
+    fn signature() {
+        let config = TwilioConfig::new("AC123", "token", "+14155550100");
+
+        assert_eq!(
+            config.signature(URL, &params()),
+            "xnQJtG1aSJCsKLFfrXOceui9x1w="
+        );
+        assert!(config.verify_signature(URL, &params(), "xnQJtG1aSJCsKLFfrXOceui9x1w="));
+        assert!(!config.verify_signature(URL, &params(), "invalid"));
+        assert!(!config.verify_signature(
+            "https://evil.co/sms/status",
+            &params(),
+            "xnQJtG1aSJCsKLFfrXOceui9x1w="
+        ));
+    }
+
+    #[test]
+    fn parse() {
+        let update = parse_status_callback(&params()).unwrap();
+
+        assert_eq!(update.message_id, "SM123");
+        assert_eq!(update.status, DeliveryStatus::Delivered);
+        assert_eq!(update.to.unwrap().as_str(), "+33612345678");
+        assert!(update.error_code.is_none());
+
+        let mut params = params();
+        params.remove("MessageSid");
+        assert!(parse_status_callback(&params).is_err());
+    }
+
+    #[test]
+    fn callback() {
+        let config = TwilioConfig::new("AC123", "token", "+14155550100");
+        assert!(TwilioCallback::new(config.clone(), |_: &StatusUpdate| {}).is_err());
+
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let received = updates.clone();
+        let callback =
+            TwilioCallback::new(config.status_callback(URL), move |update: &StatusUpdate| {
+                received.lock().unwrap().push(update.clone());
+            })
+            .unwrap();
+
+        assert!(callback.handle(None, &params()).is_err());
+        assert!(callback.handle(Some("invalid"), &params()).is_err());
+        callback
+            .handle(Some("xnQJtG1aSJCsKLFfrXOceui9x1w="), &params())
+            .unwrap();
+
+        assert_eq!(updates.lock().unwrap()[0].message_id, "SM123");
+    }
+}
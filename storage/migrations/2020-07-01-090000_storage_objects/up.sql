@@ -0,0 +1,17 @@
+CREATE TABLE storage_objects (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  bucket VARCHAR(255) NOT NULL,
+  key TEXT NOT NULL,
+  owner_id uuid NOT NULL,
+  organization_id uuid,
+  filename VARCHAR(255) NOT NULL,
+  content_type VARCHAR(255) NOT NULL,
+  size BIGINT NOT NULL,
+  status VARCHAR(20) NOT NULL DEFAULT 'pending',
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  confirmed_at TIMESTAMP,
+  UNIQUE (bucket, key)
+);
+
+CREATE INDEX storage_objects_owner_id_idx ON storage_objects (owner_id);
+CREATE INDEX storage_objects_organization_id_idx ON storage_objects (organization_id);
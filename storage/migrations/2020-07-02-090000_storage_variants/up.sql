@@ -0,0 +1,12 @@
+CREATE TABLE storage_variants (
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4 (),
+  object_id uuid NOT NULL REFERENCES storage_objects (id) ON DELETE CASCADE,
+  name VARCHAR(50) NOT NULL,
+  key TEXT NOT NULL,
+  content_type VARCHAR(255) NOT NULL,
+  width INTEGER NOT NULL,
+  height INTEGER NOT NULL,
+  size BIGINT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (object_id, name)
+);
@@ -0,0 +1,54 @@
+use diesel::result::Error as DieselError;
+use timada_http::{ContextError, Error};
+
+#[derive(Debug, PartialEq, Error)]
+pub enum StorageError {
+    #[error("Object not found")]
+    NotFound,
+
+    #[error("Invalid upload: {0}")]
+    InvalidUpload(String),
+
+    #[error("{0}")]
+    Unauthorized(String),
+
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("Provider error: {0}")]
+    Provider(String),
+
+    #[error("{0}")]
+    Database(String),
+}
+
+impl From<DieselError> for StorageError {
+    fn from(e: DieselError) -> StorageError {
+        StorageError::Database(e.to_string())
+    }
+}
+
+impl From<ContextError<'_>> for StorageError {
+    fn from(e: ContextError<'_>) -> StorageError {
+        match Error::from(e) {
+            Error::Unauthorized(message) => StorageError::Unauthorized(message),
+            e => StorageError::Forbidden(e.to_string()),
+        }
+    }
+}
+
+impl From<StorageError> for Error {
+    fn from(e: StorageError) -> Error {
+        match e {
+            StorageError::NotFound => Error::NotFound,
+            StorageError::InvalidUpload(message) => Error::UnprocessableEntity(message),
+            StorageError::Unauthorized(message) => Error::Unauthorized(message),
+            StorageError::Forbidden(message) => Error::Forbidden(message),
+            StorageError::Provider(message) | StorageError::Database(message) => {
+                Error::Internal(message)
+            }
+        }
+    }
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
@@ -0,0 +1,404 @@
+use ::image::imageops::FilterType;
+use ::image::{DynamicImage, GenericImageView, ImageOutputFormat};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::io::Cursor;
+use std::sync::RwLock;
+use timada_jobs::{Job, JobContext, JobError, JobResult, RetryPolicy};
+use uuid::Uuid;
+
+use super::error::{StorageError, StorageResult};
+use super::object::{find_object, StorageObject};
+use super::presign::Storage;
+use super::schema::storage_variants;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Jpeg(u8),
+    Png,
+    Webp(f32),
+}
+
+impl ImageFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg(_) => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Webp(_) => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg(_) => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::Webp(_) => "webp",
+        }
+    }
+
+    fn encode(&self, image: &DynamicImage) -> StorageResult<Vec<u8>> {
+        let mut data = Vec::new();
+
+        let format = match self {
+            ImageFormat::Webp(quality) => {
+                return Ok(webp::Encoder::from_image(image).encode(*quality).to_vec())
+            }
+            ImageFormat::Jpeg(quality) => ImageOutputFormat::Jpeg(*quality),
+            ImageFormat::Png => ImageOutputFormat::Png,
+        };
+
+        image
+            .write_to(&mut data, format)
+            .map_err(|e| StorageError::InvalidUpload(e.to_string()))?;
+
+        Ok(data)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fit {
+    Contain,
+    Cover,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub fit: Fit,
+    pub format: ImageFormat,
+}
+
+impl Variant {
+    pub fn new(name: &str, width: u32, height: u32, format: ImageFormat) -> Self {
+        Variant {
+            name: name.to_owned(),
+            width,
+            height,
+            fit: Fit::Contain,
+            format,
+        }
+    }
+
+    pub fn cover(mut self) -> Self {
+        self.fit = Fit::Cover;
+        self
+    }
+
+    fn resize(&self, image: &DynamicImage) -> DynamicImage {
+        let (width, height) = image.dimensions();
+
+        match self.fit {
+            Fit::Cover => image.resize_to_fill(self.width, self.height, FilterType::Lanczos3),
+            Fit::Contain if width <= self.width && height <= self.height => image.clone(),
+            Fit::Contain => image.resize(self.width, self.height, FilterType::Lanczos3),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessedVariant {
+    pub name: String,
+    pub content_type: String,
+    pub extension: String,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+const DEFAULT_MAX_PIXELS: u64 = 40_000_000;
+
+#[derive(Debug, Clone)]
+pub struct ImagePipeline {
+    variants: Vec<Variant>,
+    max_pixels: u64,
+}
+
+impl Default for ImagePipeline {
+    fn default() -> Self {
+        ImagePipeline {
+            variants: Vec::new(),
+            max_pixels: DEFAULT_MAX_PIXELS,
+        }
+    }
+}
+
+impl ImagePipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Checked from the header before decoding, a few KB can describe a huge image.
+    pub fn max_pixels(mut self, max_pixels: u64) -> Self {
+        self.max_pixels = max_pixels;
+        self
+    }
+
+    pub fn variant(mut self, variant: Variant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    fn check_dimensions(&self, data: &[u8]) -> StorageResult<()> {
+        let (width, height) = ::image::io::Reader::new(Cursor::new(data))
+            .with_guessed_format()
+            .map_err(|e| StorageError::InvalidUpload(e.to_string()))?
+            .into_dimensions()
+            .map_err(|e| StorageError::InvalidUpload(e.to_string()))?;
+
+        if u64::from(width) * u64::from(height) > self.max_pixels {
+            return Err(StorageError::InvalidUpload(format!(
+                "Image of {}x{} exceeds {} pixels",
+                width, height, self.max_pixels
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn process(&self, data: &[u8]) -> StorageResult<Vec<ProcessedVariant>> {
+        self.check_dimensions(data)?;
+
+        let image = ::image::load_from_memory(data)
+            .map_err(|e| StorageError::InvalidUpload(e.to_string()))?;
+
+        self.variants
+            .iter()
+            .map(|variant| {
+                let resized = variant.resize(&image);
+                let (width, height) = resized.dimensions();
+
+                Ok(ProcessedVariant {
+                    name: variant.name.to_owned(),
+                    content_type: variant.format.content_type().to_owned(),
+                    extension: variant.format.extension().to_owned(),
+                    width,
+                    height,
+                    data: variant.format.encode(&resized)?,
+                })
+            })
+            .collect()
+    }
+}
+
+pub fn variant_key(key: &str, name: &str, extension: &str) -> String {
+    let (dir, filename) = match key.rfind('/') {
+        Some(index) => key.split_at(index + 1),
+        None => ("", key),
+    };
+    let stem = match filename.rfind('.') {
+        Some(index) if index > 0 => &filename[..index],
+        _ => filename,
+    };
+
+    format!("{}{}_{}.{}", dir, stem, name, extension)
+}
+
+pub fn is_image(content_type: &str) -> bool {
+    content_type.starts_with("image/") && content_type != "image/svg+xml"
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Identifiable)]
+#[table_name = "storage_variants"]
+pub struct StorageVariant {
+    pub id: Uuid,
+    pub object_id: Uuid,
+    pub name: String,
+    pub key: String,
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub size: i64,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "storage_variants"]
+struct NewVariant<'a> {
+    object_id: Uuid,
+    name: &'a str,
+    key: &'a str,
+    content_type: &'a str,
+    width: i32,
+    height: i32,
+    size: i64,
+}
+
+fn save_variant(
+    conn: &PgConnection,
+    object: &StorageObject,
+    variant: &ProcessedVariant,
+    key: &str,
+) -> StorageResult<StorageVariant> {
+    let new_variant = NewVariant {
+        object_id: object.id,
+        name: &variant.name,
+        key,
+        content_type: &variant.content_type,
+        width: variant.width as i32,
+        height: variant.height as i32,
+        size: variant.data.len() as i64,
+    };
+
+    Ok(diesel::insert_into(storage_variants::table)
+        .values(&new_variant)
+        .on_conflict((storage_variants::object_id, storage_variants::name))
+        .do_update()
+        .set((
+            storage_variants::key.eq(key),
+            storage_variants::content_type.eq(&variant.content_type),
+            storage_variants::width.eq(new_variant.width),
+            storage_variants::height.eq(new_variant.height),
+            storage_variants::size.eq(new_variant.size),
+        ))
+        .get_result(conn)?)
+}
+
+pub fn list_variants(conn: &PgConnection, object_id: Uuid) -> StorageResult<Vec<StorageVariant>> {
+    Ok(storage_variants::table
+        .filter(storage_variants::object_id.eq(object_id))
+        .order(storage_variants::name.asc())
+        .load(conn)?)
+}
+
+lazy_static::lazy_static! {
+    static ref STORAGE: RwLock<Option<Storage>> = RwLock::new(None);
+}
+
+pub fn set_image_storage(storage: Storage) {
+    if let Ok(mut current) = STORAGE.write() {
+        *current = Some(storage);
+    }
+}
+
+fn image_storage() -> Option<Storage> {
+    match STORAGE.read() {
+        Ok(storage) => storage.clone(),
+        Err(e) => e.into_inner().clone(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessImage {
+    pub object_id: Uuid,
+}
+
+#[async_trait::async_trait]
+impl Job for ProcessImage {
+    const NAME: &'static str = "storage.process_image";
+    const QUEUE: &'static str = "storage";
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy::default().max_attempts(3)
+    }
+
+    async fn run(&self, ctx: &JobContext) -> JobResult<()> {
+        let storage =
+            image_storage().ok_or_else(|| JobError::failed("Image storage is not configured"))?;
+        let pipeline = match storage.pipeline() {
+            Some(pipeline) => pipeline,
+            None => return Ok(()),
+        };
+
+        let pool = ctx.pool.clone();
+        let object_id = self.object_id;
+        let object = tokio::task::spawn_blocking(move || {
+            let conn = pool.get().map_err(JobError::failed)?;
+            find_object(&conn, object_id).map_err(JobError::permanent)
+        })
+        .await
+        .map_err(JobError::failed)??;
+
+        let data = storage.get(&object.key).await.map_err(JobError::failed)?;
+        let variants = tokio::task::spawn_blocking(move || pipeline.process(&data))
+            .await
+            .map_err(JobError::failed)?
+            .map_err(JobError::permanent)?;
+
+        for variant in variants {
+            let key = variant_key(&object.key, &variant.name, &variant.extension);
+            storage
+                .put(&key, variant.data.clone(), &variant.content_type)
+                .await
+                .map_err(JobError::failed)?;
+
+            let pool = ctx.pool.clone();
+            let object = object.clone();
+            tokio::task::spawn_blocking(move || {
+                let conn = pool.get().map_err(JobError::failed)?;
+                save_variant(&conn, &object, &variant, &key).map_err(JobError::failed)
+            })
+            .await
+            .map_err(JobError::failed)??;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::image::{DynamicImage, GenericImageView};
+
+    use super::{is_image, variant_key, ImageFormat, ImagePipeline, Variant};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        DynamicImage::new_rgb8(width, height)
+            .write_to(&mut data, ::image::ImageOutputFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn process() {
+        let pipeline = ImagePipeline::new()
+            .variant(Variant::new("thumb", 64, 64, ImageFormat::Jpeg(80)).cover())
+            .variant(Variant::new("medium", 200, 200, ImageFormat::Png))
+            .variant(Variant::new("large", 1000, 1000, ImageFormat::Webp(75.0)));
+
+        let variants = pipeline.process(&png(400, 200)).unwrap();
+
+        assert_eq!(
+            variants
+                .iter()
+                .map(|variant| (variant.name.as_str(), variant.width, variant.height))
+                .collect::<Vec<_>>(),
+            vec![("thumb", 64, 64), ("medium", 200, 100), ("large", 400, 200)]
+        );
+        assert_eq!(variants[0].content_type, "image/jpeg");
+
+        let thumb = ::image::load_from_memory(&variants[0].data).unwrap();
+        assert_eq!(thumb.dimensions(), (64, 64));
+        assert!(pipeline.process(b"not an image").is_err());
+    }
+
+    #[test]
+    fn max_pixels() {
+        let pipeline = ImagePipeline::new()
+            .max_pixels(100 * 100)
+            .variant(Variant::new("thumb", 64, 64, ImageFormat::Png));
+
+        assert!(pipeline.process(&png(100, 100)).is_ok());
+        assert!(pipeline.process(&png(101, 100)).is_err());
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            variant_key("uploads/a/b/photo.png", "thumb", "webp"),
+            "uploads/a/b/photo_thumb.webp"
+        );
+        assert_eq!(variant_key("photo", "thumb", "jpg"), "photo_thumb.jpg");
+        assert_eq!(variant_key("a/.env", "x", "png"), "a/.env_x.png");
+        assert!(is_image("image/png"));
+        assert!(!is_image("image/svg+xml"));
+        assert!(!is_image("application/pdf"));
+    }
+}
@@ -0,0 +1,27 @@
+#[macro_use]
+extern crate diesel;
+
+#[macro_use]
+extern crate diesel_migrations;
+
+#[macro_use]
+extern crate serde;
+
+#[macro_use]
+extern crate thiserror;
+
+mod error;
+mod image;
+mod migration;
+mod object;
+mod presign;
+mod schema;
+
+pub use crate::error::{StorageError, StorageResult};
+pub use crate::image::{
+    is_image, list_variants, set_image_storage, variant_key, Fit, ImageFormat, ImagePipeline,
+    ProcessImage, ProcessedVariant, StorageVariant, Variant,
+};
+pub use crate::migration::migrate;
+pub use crate::object::{find_object, sanitize_filename, ObjectStatus, StorageObject};
+pub use crate::presign::{PresignedUpload, PresignedUrl, Storage, StorageConfig, UploadRequest};
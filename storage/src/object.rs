@@ -0,0 +1,230 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use timada_http::{Context, UserRole};
+use uuid::Uuid;
+
+use super::error::{StorageError, StorageResult};
+use super::schema::storage_objects;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectStatus {
+    Pending,
+    Confirmed,
+}
+
+impl ObjectStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectStatus::Pending => "pending",
+            ObjectStatus::Confirmed => "confirmed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Identifiable)]
+#[table_name = "storage_objects"]
+pub struct StorageObject {
+    pub id: Uuid,
+    pub bucket: String,
+    pub key: String,
+    pub owner_id: Uuid,
+    pub organization_id: Option<Uuid>,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub status: String,
+    pub created_at: NaiveDateTime,
+    pub confirmed_at: Option<NaiveDateTime>,
+}
+
+impl StorageObject {
+    pub fn is_confirmed(&self) -> bool {
+        self.status == ObjectStatus::Confirmed.as_str()
+    }
+
+    pub fn ensure_owner(&self, context: &Context) -> StorageResult<()> {
+        let user = context.ensure_is_authorized(None)?;
+
+        if user.id != self.owner_id {
+            return Err(StorageError::Forbidden("Not the object owner".to_owned()));
+        }
+
+        Ok(())
+    }
+
+    pub fn ensure_readable(&self, context: &Context) -> StorageResult<()> {
+        let user = context.ensure_is_authorized(None)?;
+
+        if user.id == self.owner_id || user.has_any_role(&[UserRole::Root, UserRole::Admin]) {
+            return Ok(());
+        }
+
+        match self.organization_id.as_ref() {
+            // Membership is checked against the signed user as well, not only the
+            // organization the request acts in.
+            Some(organization_id) if user.is_member_of(organization_id) => {
+                context.ensure_in_organization(organization_id)?;
+                Ok(())
+            }
+            Some(_) => Err(StorageError::Forbidden(
+                "Not a member of the organization".to_owned(),
+            )),
+            None => Err(StorageError::Forbidden("Not the object owner".to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Insertable)]
+#[table_name = "storage_objects"]
+pub struct NewObject {
+    pub id: Uuid,
+    pub bucket: String,
+    pub key: String,
+    pub owner_id: Uuid,
+    pub organization_id: Option<Uuid>,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+}
+
+pub fn sanitize_filename(filename: &str) -> String {
+    let name = filename
+        .rsplit(|c| c == '/' || c == '\\')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    let name = name.trim_start_matches('.');
+
+    if name.is_empty() {
+        "file".to_owned()
+    } else {
+        name.chars().take(200).collect()
+    }
+}
+
+pub fn object_key(prefix: &str, owner_id: &Uuid, id: &Uuid, filename: &str) -> String {
+    format!(
+        "{}{}/{}/{}",
+        prefix,
+        owner_id,
+        id,
+        sanitize_filename(filename)
+    )
+}
+
+pub fn create_object(conn: &PgConnection, object: &NewObject) -> StorageResult<StorageObject> {
+    Ok(diesel::insert_into(storage_objects::table)
+        .values(object)
+        .get_result(conn)?)
+}
+
+pub fn find_object(conn: &PgConnection, id: Uuid) -> StorageResult<StorageObject> {
+    storage_objects::table
+        .find(id)
+        .first(conn)
+        .optional()?
+        .ok_or(StorageError::NotFound)
+}
+
+pub fn confirm_object(conn: &PgConnection, id: Uuid, size: i64) -> StorageResult<StorageObject> {
+    Ok(diesel::update(storage_objects::table.find(id))
+        .set((
+            storage_objects::status.eq(ObjectStatus::Confirmed.as_str()),
+            storage_objects::size.eq(size),
+            storage_objects::confirmed_at.eq(Utc::now().naive_utc()),
+        ))
+        .get_result(conn)?)
+}
+
+pub fn delete_object(conn: &PgConnection, id: Uuid) -> StorageResult<()> {
+    diesel::delete(storage_objects::table.find(id)).execute(conn)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use timada_http::testing::ContextBuilder;
+    use timada_http::{Context, UserRole};
+    use uuid::Uuid;
+
+    use super::{object_key, sanitize_filename, StorageObject};
+    use crate::error::StorageError;
+
+    fn object(owner_id: Uuid, organization_id: Option<Uuid>) -> StorageObject {
+        StorageObject {
+            id: Uuid::new_v4(),
+            bucket: "uploads".to_owned(),
+            key: "avatar.png".to_owned(),
+            owner_id,
+            organization_id,
+            filename: "avatar.png".to_owned(),
+            content_type: "image/png".to_owned(),
+            size: 1024,
+            status: "pending".to_owned(),
+            created_at: Utc::now().naive_utc(),
+            confirmed_at: None,
+        }
+    }
+
+    #[test]
+    fn filenames() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename("my photo (1).png"), "my_photo__1_.png");
+        assert_eq!(sanitize_filename(".."), "file");
+
+        let owner_id = Uuid::nil();
+        assert_eq!(
+            object_key("uploads/", &owner_id, &owner_id, "a.png"),
+            format!("uploads/{}/{}/a.png", owner_id, owner_id)
+        );
+    }
+
+    #[test]
+    fn access() {
+        let owner_id = Uuid::new_v4();
+        let organization_id = Uuid::new_v4();
+        let object = object(owner_id, Some(organization_id));
+
+        let owner = ContextBuilder::new().id(owner_id).build();
+        assert_eq!(object.ensure_owner(&owner), Ok(()));
+        assert_eq!(object.ensure_readable(&owner), Ok(()));
+
+        let member = ContextBuilder::new().organization(organization_id).build();
+        assert_eq!(object.ensure_readable(&member), Ok(()));
+        assert!(object.ensure_owner(&member).is_err());
+
+        let outsider = ContextBuilder::new().organization(Uuid::new_v4()).build();
+        assert!(object.ensure_readable(&outsider).is_err());
+
+        let spoofed = Context {
+            organization_id: Some(organization_id),
+            ..ContextBuilder::new().build()
+        };
+        assert_eq!(
+            object.ensure_readable(&spoofed),
+            Err(StorageError::Forbidden(
+                "Not a member of the organization".to_owned()
+            ))
+        );
+
+        let admin = ContextBuilder::new().role(UserRole::Admin).build();
+        assert_eq!(object.ensure_readable(&admin), Ok(()));
+
+        match object.ensure_readable(&Default::default()) {
+            Err(StorageError::Unauthorized(_)) => {}
+            res => panic!("unexpected {:?}", res),
+        }
+    }
+}
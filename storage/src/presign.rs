@@ -0,0 +1,506 @@
+use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
+use diesel::Connection;
+use futures::TryStreamExt;
+use rusoto_core::credential::{DefaultCredentialsProvider, ProvideAwsCredentials};
+use rusoto_core::{Region, RusotoError};
+use rusoto_s3::util::{PreSignedRequest, PreSignedRequestOption};
+use rusoto_s3::{
+    DeleteObjectRequest, GetObjectRequest, HeadObjectError, HeadObjectRequest, PutObjectRequest,
+    S3Client, S3,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use timada_database::Pool;
+use timada_http::Context;
+use timada_jobs::enqueue;
+use timada_util::env::{self, EnvError};
+use uuid::Uuid;
+
+use super::error::{StorageError, StorageResult};
+use super::image::{is_image, list_variants, ImagePipeline, ProcessImage};
+use super::object::{
+    confirm_object, create_object, delete_object, find_object, object_key, NewObject, StorageObject,
+};
+
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    pub bucket: String,
+    pub region: Region,
+    pub key_prefix: String,
+    pub upload_expires_in: Duration,
+    pub download_expires_in: Duration,
+    pub max_size: u64,
+    pub content_types: Vec<String>,
+}
+
+fn duration_or(key: &str, default: Duration) -> Duration {
+    match env::try_var_duration(key) {
+        Err(EnvError::Missing(_)) => default,
+        res => res.unwrap_or_else(|e| panic!("{}", e)),
+    }
+}
+
+impl StorageConfig {
+    pub fn new(bucket: &str, region: Region) -> Self {
+        StorageConfig {
+            bucket: bucket.to_owned(),
+            region,
+            key_prefix: "uploads/".to_owned(),
+            upload_expires_in: Duration::from_secs(15 * 60),
+            download_expires_in: Duration::from_secs(5 * 60),
+            max_size: 10 * 1024 * 1024,
+            content_types: Vec::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let region = match env::var_opt("STORAGE_ENDPOINT") {
+            Some(endpoint) => Region::Custom {
+                name: env::var_or("STORAGE_REGION", "us-east-1"),
+                endpoint,
+            },
+            None => env::var_or("STORAGE_REGION", "us-east-1")
+                .parse()
+                .unwrap_or_else(|e| panic!("STORAGE_REGION: {}", e)),
+        };
+        let config = StorageConfig::new(&env::var("STORAGE_BUCKET"), region);
+
+        StorageConfig {
+            upload_expires_in: duration_or("STORAGE_UPLOAD_EXPIRES_IN", config.upload_expires_in),
+            download_expires_in: duration_or(
+                "STORAGE_DOWNLOAD_EXPIRES_IN",
+                config.download_expires_in,
+            ),
+            max_size: match env::try_var_bytes("STORAGE_MAX_SIZE") {
+                Err(EnvError::Missing(_)) => config.max_size,
+                res => res.unwrap_or_else(|e| panic!("{}", e)),
+            },
+            content_types: env::try_var_list("STORAGE_CONTENT_TYPES").unwrap_or_default(),
+            ..config
+        }
+    }
+
+    pub fn key_prefix(mut self, key_prefix: &str) -> Self {
+        self.key_prefix = key_prefix.to_owned();
+        self
+    }
+
+    pub fn upload_expires_in(mut self, upload_expires_in: Duration) -> Self {
+        self.upload_expires_in = upload_expires_in;
+        self
+    }
+
+    pub fn download_expires_in(mut self, download_expires_in: Duration) -> Self {
+        self.download_expires_in = download_expires_in;
+        self
+    }
+
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_types.push(content_type.to_owned());
+        self
+    }
+
+    pub fn validate(&self, upload: &UploadRequest) -> StorageResult<()> {
+        if upload.size == 0 || upload.size > self.max_size {
+            return Err(StorageError::InvalidUpload(format!(
+                "File size must be between 1 and {} bytes",
+                self.max_size
+            )));
+        }
+
+        let allowed = self.content_types.is_empty()
+            || self
+                .content_types
+                .iter()
+                .any(|allowed| allowed == &upload.content_type);
+
+        if !allowed {
+            return Err(StorageError::InvalidUpload(format!(
+                "Content type {} is not allowed",
+                upload.content_type
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct UploadRequest {
+    pub filename: String,
+    pub content_type: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PresignedUrl {
+    pub url: String,
+    pub method: String,
+    pub headers: HashMap<String, String>,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PresignedUpload {
+    pub object_id: Uuid,
+    pub key: String,
+    #[serde(flatten)]
+    pub url: PresignedUrl,
+}
+
+async fn blocking<T, F>(pool: Pool, f: F) -> StorageResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&diesel::PgConnection) -> StorageResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let conn = pool
+            .get()
+            .map_err(|e| StorageError::Database(e.to_string()))?;
+        f(&conn)
+    })
+    .await
+    .map_err(|e| StorageError::Database(e.to_string()))?
+}
+
+fn expires_at(expires_in: Duration) -> NaiveDateTime {
+    Utc::now().naive_utc()
+        + ChronoDuration::from_std(expires_in).unwrap_or_else(|_| ChronoDuration::zero())
+}
+
+#[derive(Clone)]
+pub struct Storage {
+    config: StorageConfig,
+    client: S3Client,
+    credentials: Arc<DefaultCredentialsProvider>,
+    pipeline: Option<Arc<ImagePipeline>>,
+}
+
+impl Storage {
+    pub fn new(config: StorageConfig) -> StorageResult<Self> {
+        let credentials =
+            DefaultCredentialsProvider::new().map_err(|e| StorageError::Provider(e.to_string()))?;
+
+        Ok(Storage {
+            client: S3Client::new(config.region.clone()),
+            config,
+            credentials: Arc::new(credentials),
+            pipeline: None,
+        })
+    }
+
+    pub fn image_pipeline(mut self, pipeline: ImagePipeline) -> Self {
+        self.pipeline = Some(Arc::new(pipeline));
+        self
+    }
+
+    pub fn pipeline(&self) -> Option<Arc<ImagePipeline>> {
+        self.pipeline.clone()
+    }
+
+    pub fn from_env() -> StorageResult<Self> {
+        Self::new(StorageConfig::from_env())
+    }
+
+    pub fn config(&self) -> &StorageConfig {
+        &self.config
+    }
+
+    pub async fn presign_put(
+        &self,
+        key: &str,
+        content_type: &str,
+        size: u64,
+    ) -> StorageResult<PresignedUrl> {
+        let credentials = self
+            .credentials
+            .credentials()
+            .await
+            .map_err(|e| StorageError::Provider(e.to_string()))?;
+
+        let url = PutObjectRequest {
+            bucket: self.config.bucket.to_owned(),
+            key: key.to_owned(),
+            content_type: Some(content_type.to_owned()),
+            content_length: Some(size as i64),
+            ..Default::default()
+        }
+        .get_presigned_url(
+            &self.config.region,
+            &credentials,
+            &PreSignedRequestOption {
+                expires_in: self.config.upload_expires_in,
+            },
+        );
+
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_owned(), content_type.to_owned());
+        headers.insert("content-length".to_owned(), size.to_string());
+
+        Ok(PresignedUrl {
+            url,
+            method: "PUT".to_owned(),
+            headers,
+            expires_at: expires_at(self.config.upload_expires_in),
+        })
+    }
+
+    pub async fn presign_get(&self, key: &str) -> StorageResult<PresignedUrl> {
+        let credentials = self
+            .credentials
+            .credentials()
+            .await
+            .map_err(|e| StorageError::Provider(e.to_string()))?;
+
+        let url = GetObjectRequest {
+            bucket: self.config.bucket.to_owned(),
+            key: key.to_owned(),
+            ..Default::default()
+        }
+        .get_presigned_url(
+            &self.config.region,
+            &credentials,
+            &PreSignedRequestOption {
+                expires_in: self.config.download_expires_in,
+            },
+        );
+
+        Ok(PresignedUrl {
+            url,
+            method: "GET".to_owned(),
+            headers: HashMap::new(),
+            expires_at: expires_at(self.config.download_expires_in),
+        })
+    }
+
+    async fn head(&self, key: &str) -> StorageResult<Option<(i64, Option<String>)>> {
+        let res = self
+            .client
+            .head_object(HeadObjectRequest {
+                bucket: self.config.bucket.to_owned(),
+                key: key.to_owned(),
+                ..Default::default()
+            })
+            .await;
+
+        match res {
+            Ok(output) => Ok(Some((
+                output.content_length.unwrap_or_default(),
+                output.content_type,
+            ))),
+            Err(RusotoError::Service(HeadObjectError::NoSuchKey(_))) => Ok(None),
+            Err(RusotoError::Unknown(res)) if res.status.as_u16() == 404 => Ok(None),
+            Err(e) => Err(StorageError::Provider(e.to_string())),
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> StorageResult<Vec<u8>> {
+        let output = self
+            .client
+            .get_object(GetObjectRequest {
+                bucket: self.config.bucket.to_owned(),
+                key: key.to_owned(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| StorageError::Provider(e.to_string()))?;
+
+        let body = output.body.ok_or(StorageError::NotFound)?;
+
+        body.map_ok(|chunk| chunk.to_vec())
+            .try_concat()
+            .await
+            .map_err(|e| StorageError::Provider(e.to_string()))
+    }
+
+    pub async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> StorageResult<()> {
+        self.client
+            .put_object(PutObjectRequest {
+                bucket: self.config.bucket.to_owned(),
+                key: key.to_owned(),
+                content_type: Some(content_type.to_owned()),
+                content_length: Some(data.len() as i64),
+                body: Some(data.into()),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| StorageError::Provider(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn delete(&self, key: &str) -> StorageResult<()> {
+        self.client
+            .delete_object(DeleteObjectRequest {
+                bucket: self.config.bucket.to_owned(),
+                key: key.to_owned(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| StorageError::Provider(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn create_upload(
+        &self,
+        pool: &Pool,
+        context: &Context,
+        upload: UploadRequest,
+    ) -> StorageResult<PresignedUpload> {
+        let user = context.ensure_is_authorized(None)?;
+        self.config.validate(&upload)?;
+
+        let id = Uuid::new_v4();
+        let key = object_key(&self.config.key_prefix, &user.id, &id, &upload.filename);
+        let url = self
+            .presign_put(&key, &upload.content_type, upload.size)
+            .await?;
+
+        let object = NewObject {
+            id,
+            bucket: self.config.bucket.to_owned(),
+            key: key.to_owned(),
+            owner_id: user.id,
+            organization_id: context.organization_id,
+            filename: upload.filename,
+            content_type: upload.content_type,
+            size: upload.size as i64,
+        };
+        blocking(pool.clone(), move |conn| create_object(conn, &object)).await?;
+
+        Ok(PresignedUpload {
+            object_id: id,
+            key,
+            url,
+        })
+    }
+
+    pub async fn confirm_upload(
+        &self,
+        pool: &Pool,
+        context: &Context,
+        id: Uuid,
+    ) -> StorageResult<StorageObject> {
+        let object = blocking(pool.clone(), move |conn| find_object(conn, id)).await?;
+        object.ensure_owner(context)?;
+
+        if object.is_confirmed() {
+            return Ok(object);
+        }
+
+        let (size, content_type) = self.head(&object.key).await?.ok_or_else(|| {
+            StorageError::InvalidUpload("Object has not been uploaded".to_owned())
+        })?;
+
+        let content_type_matches = content_type
+            .map(|content_type| content_type == object.content_type)
+            .unwrap_or(false);
+
+        if size as u64 > self.config.max_size || !content_type_matches {
+            self.delete(&object.key).await?;
+            blocking(pool.clone(), move |conn| delete_object(conn, id)).await?;
+
+            return Err(StorageError::InvalidUpload(
+                "Uploaded object does not match the upload request".to_owned(),
+            ));
+        }
+
+        let process_image = self.pipeline.is_some() && is_image(&object.content_type);
+
+        blocking(pool.clone(), move |conn| {
+            conn.transaction(|| {
+                let object = confirm_object(conn, id, size)?;
+
+                if process_image {
+                    enqueue(conn, &ProcessImage { object_id: id })
+                        .map_err(|e| StorageError::Database(e.to_string()))?;
+                }
+
+                Ok(object)
+            })
+        })
+        .await
+    }
+
+    pub async fn download_url(
+        &self,
+        pool: &Pool,
+        context: &Context,
+        id: Uuid,
+    ) -> StorageResult<PresignedUrl> {
+        let object = blocking(pool.clone(), move |conn| find_object(conn, id)).await?;
+        object.ensure_readable(context)?;
+
+        if !object.is_confirmed() {
+            return Err(StorageError::NotFound);
+        }
+
+        self.presign_get(&object.key).await
+    }
+
+    pub async fn variant_urls(
+        &self,
+        pool: &Pool,
+        context: &Context,
+        id: Uuid,
+    ) -> StorageResult<HashMap<String, PresignedUrl>> {
+        let (object, variants) = blocking(pool.clone(), move |conn| {
+            Ok((find_object(conn, id)?, list_variants(conn, id)?))
+        })
+        .await?;
+        object.ensure_readable(context)?;
+
+        let mut urls = HashMap::new();
+
+        for variant in variants {
+            urls.insert(variant.name, self.presign_get(&variant.key).await?);
+        }
+
+        Ok(urls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusoto_core::Region;
+
+    use super::{StorageConfig, UploadRequest};
+    use crate::error::StorageError;
+
+    fn upload(content_type: &str, size: u64) -> UploadRequest {
+        UploadRequest {
+            filename: "avatar.png".to_owned(),
+            content_type: content_type.to_owned(),
+            size,
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let config = StorageConfig::new("uploads", Region::EuWest1)
+            .max_size(1024)
+            .content_type("image/png");
+
+        assert_eq!(config.validate(&upload("image/png", 512)), Ok(()));
+
+        for upload in [
+            upload("image/png", 0),
+            upload("image/png", 2048),
+            upload("text/html", 512),
+        ]
+        .iter()
+        {
+            match config.validate(upload) {
+                Err(StorageError::InvalidUpload(_)) => {}
+                res => panic!("unexpected {:?}", res),
+            }
+        }
+    }
+}
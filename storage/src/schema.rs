@@ -0,0 +1,33 @@
+table! {
+    storage_objects (id) {
+        id -> Uuid,
+        bucket -> Varchar,
+        key -> Text,
+        owner_id -> Uuid,
+        organization_id -> Nullable<Uuid>,
+        filename -> Varchar,
+        content_type -> Varchar,
+        size -> Int8,
+        status -> Varchar,
+        created_at -> Timestamp,
+        confirmed_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    storage_variants (id) {
+        id -> Uuid,
+        object_id -> Uuid,
+        name -> Varchar,
+        key -> Text,
+        content_type -> Varchar,
+        width -> Int4,
+        height -> Int4,
+        size -> Int8,
+        created_at -> Timestamp,
+    }
+}
+
+joinable!(storage_variants -> storage_objects (object_id));
+
+allow_tables_to_appear_in_same_query!(storage_objects, storage_variants);
@@ -0,0 +1,167 @@
+use timada_util::env::{self, EnvError};
+
+use super::error::{TelemetryError, TelemetryResult};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    AlwaysOn,
+    AlwaysOff,
+    Ratio(f64),
+}
+
+impl Sampling {
+    pub fn parse(sampler: &str, arg: Option<&str>) -> TelemetryResult<Self> {
+        let ratio = || -> TelemetryResult<f64> {
+            let ratio = arg
+                .unwrap_or("1.0")
+                .parse::<f64>()
+                .map_err(|e| TelemetryError::Config(format!("sampler arg: {}", e)))?;
+
+            if !(0.0..=1.0).contains(&ratio) {
+                return Err(TelemetryError::Config(format!(
+                    "sampler arg {} must be between 0 and 1",
+                    ratio
+                )));
+            }
+
+            Ok(ratio)
+        };
+
+        match sampler.to_lowercase().as_str() {
+            "always_on" | "parentbased_always_on" => Ok(Sampling::AlwaysOn),
+            "always_off" | "parentbased_always_off" => Ok(Sampling::AlwaysOff),
+            "traceidratio" | "parentbased_traceidratio" => Ok(Sampling::Ratio(ratio()?)),
+            sampler => Err(TelemetryError::Config(format!(
+                "unknown sampler {}: expected always_on, always_off or traceidratio",
+                sampler
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryConfig {
+    pub service_name: String,
+    pub service_version: String,
+    pub environment: String,
+    pub otlp_endpoint: Option<String>,
+    pub sampling: Sampling,
+}
+
+impl TelemetryConfig {
+    pub fn new(service_name: &str) -> Self {
+        TelemetryConfig {
+            service_name: service_name.to_owned(),
+            service_version: "0.0.0".to_owned(),
+            environment: "development".to_owned(),
+            otlp_endpoint: None,
+            sampling: Sampling::AlwaysOn,
+        }
+    }
+
+    // Follows the OpenTelemetry SDK variables so collectors can be configured
+    // the same way for every service.
+    pub fn from_env() -> Self {
+        let service_name = match env::try_var("OTEL_SERVICE_NAME") {
+            Err(EnvError::Missing(_)) => env::var("SERVICE_NAME"),
+            res => res.unwrap_or_else(|e| panic!("{}", e)),
+        };
+
+        let sampler = env::var_or("OTEL_TRACES_SAMPLER", "parentbased_always_on");
+        let sampler_arg = env::var_opt("OTEL_TRACES_SAMPLER_ARG");
+
+        TelemetryConfig {
+            service_name,
+            service_version: env::var_or("SERVICE_VERSION", "0.0.0"),
+            environment: env::var_or("APP_ENV", "development"),
+            otlp_endpoint: env::var_opt("OTEL_EXPORTER_OTLP_ENDPOINT"),
+            sampling: Sampling::parse(&sampler, sampler_arg.as_deref())
+                .unwrap_or_else(|e| panic!("{}", e)),
+        }
+    }
+
+    // Usually `env!("CARGO_PKG_VERSION")` of the binary.
+    pub fn service_version(mut self, service_version: &str) -> Self {
+        self.service_version = service_version.to_owned();
+        self
+    }
+
+    pub fn environment(mut self, environment: &str) -> Self {
+        self.environment = environment.to_owned();
+        self
+    }
+
+    pub fn otlp_endpoint(mut self, otlp_endpoint: &str) -> Self {
+        self.otlp_endpoint = Some(otlp_endpoint.to_owned());
+        self
+    }
+
+    pub fn sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    pub fn resource_attributes(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("service.name", self.service_name.clone()),
+            ("service.version", self.service_version.clone()),
+            ("deployment.environment", self.environment.clone()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use timada_util::env::test_scope;
+
+    use super::{Sampling, TelemetryConfig};
+
+    #[test]
+    fn sampling() {
+        assert_eq!(Sampling::parse("always_on", None), Ok(Sampling::AlwaysOn));
+        assert_eq!(
+            Sampling::parse("parentbased_always_off", None),
+            Ok(Sampling::AlwaysOff)
+        );
+        assert_eq!(
+            Sampling::parse("traceidratio", Some("0.25")),
+            Ok(Sampling::Ratio(0.25))
+        );
+        assert_eq!(
+            Sampling::parse("traceidratio", None),
+            Ok(Sampling::Ratio(1.0))
+        );
+        assert!(Sampling::parse("traceidratio", Some("2")).is_err());
+        assert!(Sampling::parse("jaeger_remote", None).is_err());
+    }
+
+    #[test]
+    fn from_env() {
+        let _env = test_scope()
+            .remove("OTEL_SERVICE_NAME")
+            .set("SERVICE_NAME", "todos")
+            .set("APP_ENV", "staging")
+            .set("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4317")
+            .set("OTEL_TRACES_SAMPLER", "traceidratio")
+            .set("OTEL_TRACES_SAMPLER_ARG", "0.1")
+            .remove("SERVICE_VERSION");
+
+        let config = TelemetryConfig::from_env().service_version("1.2.0");
+
+        assert_eq!(config.service_name, "todos");
+        assert_eq!(config.environment, "staging");
+        assert_eq!(
+            config.otlp_endpoint.as_deref(),
+            Some("http://collector:4317")
+        );
+        assert_eq!(config.sampling, Sampling::Ratio(0.1));
+        assert_eq!(
+            config.resource_attributes(),
+            vec![
+                ("service.name", "todos".to_owned()),
+                ("service.version", "1.2.0".to_owned()),
+                ("deployment.environment", "staging".to_owned()),
+            ]
+        );
+    }
+}
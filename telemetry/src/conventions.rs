@@ -0,0 +1,75 @@
+use std::fmt::Display;
+use tracing::field::{display, Empty};
+use tracing::Span;
+
+// Field names follow the OpenTelemetry semantic conventions, `otel.*` fields
+// are picked up by tracing-opentelemetry to name and classify exported spans.
+pub fn http_request_span(method: &str, target: &str) -> Span {
+    tracing::info_span!(
+        "http_request",
+        otel.name = %format!("HTTP {}", method),
+        otel.kind = "server",
+        otel.status_code = Empty,
+        http.method = method,
+        http.target = target,
+        http.status_code = Empty,
+        request_id = Empty,
+        enduser.id = Empty,
+    )
+}
+
+pub fn record_http_status(span: &Span, status: u16) {
+    span.record("http.status_code", &status);
+
+    if status >= 500 {
+        span.record("otel.status_code", &"ERROR");
+    }
+}
+
+pub fn record_request_id(span: &Span, request_id: &str) {
+    span.record("request_id", &request_id);
+}
+
+pub fn record_user<T: Display>(span: &Span, user_id: T) {
+    span.record("enduser.id", &display(user_id));
+}
+
+// Continues the trace of the calling service from its W3C `traceparent`/`tracestate`
+// headers, so the parent sampling decision is honoured. A no-op without the otlp exporter.
+pub fn set_remote_parent(span: &Span, traceparent: Option<&str>, tracestate: Option<&str>) {
+    #[cfg(feature = "otlp")]
+    {
+        use opentelemetry::api::{HttpTextFormat, TraceContextPropagator};
+        use std::collections::HashMap;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        let traceparent = match traceparent {
+            Some(traceparent) => traceparent,
+            None => return,
+        };
+
+        let mut carrier = HashMap::new();
+        carrier.insert("traceparent".to_owned(), traceparent.to_owned());
+
+        if let Some(tracestate) = tracestate {
+            carrier.insert("tracestate".to_owned(), tracestate.to_owned());
+        }
+
+        span.set_parent(&TraceContextPropagator::new().extract(&carrier));
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    let _ = (span, traceparent, tracestate);
+}
+
+pub fn db_query_span(operation: &str, table: &str) -> Span {
+    tracing::info_span!(
+        "db_query",
+        otel.name = %format!("{} {}", operation, table),
+        otel.kind = "client",
+        otel.status_code = Empty,
+        db.system = "postgresql",
+        db.operation = operation,
+        db.sql.table = table,
+    )
+}
@@ -0,0 +1,13 @@
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum TelemetryError {
+    #[error("Invalid config: {0}")]
+    Config(String),
+
+    #[error("Exporter error: {0}")]
+    Exporter(String),
+
+    #[error("Subscriber error: {0}")]
+    Subscriber(String),
+}
+
+pub type TelemetryResult<T> = Result<T, TelemetryError>;
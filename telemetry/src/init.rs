@@ -0,0 +1,107 @@
+use timada_util::telemetry::{env_filter, log_format, Format};
+use tracing_subscriber::fmt;
+use tracing_subscriber::layer::{Identity, Layer, Layered, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Registry};
+
+use super::config::TelemetryConfig;
+use super::error::{TelemetryError, TelemetryResult};
+
+// Keep it alive for the lifetime of the binary, dropping it flushes and
+// shuts down the exporter.
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    _uninstall: Option<opentelemetry_otlp::Uninstall>,
+}
+
+#[cfg(feature = "otlp")]
+fn sampler(config: &TelemetryConfig) -> opentelemetry::sdk::Sampler {
+    use super::config::Sampling;
+    use opentelemetry::sdk::Sampler;
+
+    let sampler = match config.sampling {
+        Sampling::AlwaysOn => Sampler::AlwaysOn,
+        Sampling::AlwaysOff => Sampler::AlwaysOff,
+        Sampling::Ratio(ratio) => Sampler::Probability(ratio),
+    };
+
+    // Upstream decisions win so a trace is never partially sampled across services.
+    Sampler::ParentOrElse(Box::new(sampler))
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_pipeline(
+    config: &TelemetryConfig,
+    endpoint: &str,
+) -> (opentelemetry::sdk::Tracer, opentelemetry_otlp::Uninstall) {
+    use opentelemetry::api::KeyValue;
+    use opentelemetry::sdk;
+    use std::sync::Arc;
+
+    let resource = sdk::Resource::new(
+        config
+            .resource_attributes()
+            .into_iter()
+            .map(|(key, value)| KeyValue::new(key, value)),
+    );
+
+    opentelemetry_otlp::new_pipeline()
+        .with_endpoint(endpoint)
+        .with_trace_config(sdk::Config {
+            default_sampler: Box::new(sampler(config)),
+            resource: Arc::new(resource),
+            ..Default::default()
+        })
+        .install()
+}
+
+pub type BaseSubscriber = Layered<EnvFilter, Registry>;
+
+// `layer` sits right after the env filter, e.g. to forward events to an error tracker.
+pub fn try_init_with<L>(config: &TelemetryConfig, layer: L) -> TelemetryResult<Telemetry>
+where
+    L: Layer<BaseSubscriber> + Send + Sync + 'static,
+{
+    let format = log_format().map_err(TelemetryError::Config)?;
+    let registry = Registry::default().with(env_filter()).with(layer);
+
+    #[cfg(feature = "otlp")]
+    let (otel, uninstall) = match config.otlp_endpoint.as_ref() {
+        Some(endpoint) => {
+            let (tracer, uninstall) = otlp_pipeline(config, endpoint);
+
+            (
+                Some(tracing_opentelemetry::layer().with_tracer(tracer)),
+                Some(uninstall),
+            )
+        }
+        None => (None, None),
+    };
+
+    #[cfg(feature = "otlp")]
+    let registry = registry.with(otel);
+
+    #[cfg(not(feature = "otlp"))]
+    let _ = config;
+
+    let res = match format {
+        Format::Json => registry.with(fmt::layer().json()).try_init(),
+        Format::Pretty => registry.with(fmt::layer()).try_init(),
+        Format::Compact => registry.with(fmt::layer().compact()).try_init(),
+    };
+
+    res.map_err(|e| TelemetryError::Subscriber(e.to_string()))?;
+
+    Ok(Telemetry {
+        #[cfg(feature = "otlp")]
+        _uninstall: uninstall,
+    })
+}
+
+pub fn try_init(config: &TelemetryConfig) -> TelemetryResult<Telemetry> {
+    try_init_with(config, Identity::new())
+}
+
+pub fn init(config: &TelemetryConfig) -> Telemetry {
+    try_init(config).unwrap_or_else(|e| panic!("{}", e))
+}
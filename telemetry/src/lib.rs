@@ -0,0 +1,17 @@
+#[macro_use]
+extern crate thiserror;
+
+mod config;
+mod conventions;
+mod error;
+#[cfg(feature = "init")]
+mod init;
+
+pub use crate::config::{Sampling, TelemetryConfig};
+pub use crate::conventions::{
+    db_query_span, http_request_span, record_http_status, record_request_id, record_user,
+    set_remote_parent,
+};
+pub use crate::error::{TelemetryError, TelemetryResult};
+#[cfg(feature = "init")]
+pub use crate::init::{init, try_init, try_init_with, BaseSubscriber, Telemetry};
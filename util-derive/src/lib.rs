@@ -0,0 +1,157 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Field, Fields, GenericArgument, Lit, Meta,
+    NestedMeta, PathArguments, Type,
+};
+
+#[proc_macro_derive(FromEnv, attributes(env))]
+pub fn derive_from_env(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+struct FieldConfig {
+    key: String,
+    default: Option<String>,
+}
+
+fn field_config(field: &Field) -> Result<FieldConfig, Error> {
+    let ident = field.ident.as_ref().expect("named field");
+    let mut config = FieldConfig {
+        key: ident.to_string().to_uppercase(),
+        default: None,
+    };
+
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("env")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected #[env(...)]")),
+        };
+
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Lit(Lit::Str(key)) => config.key = key.value(),
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("default") =>
+                {
+                    match &name_value.lit {
+                        Lit::Str(default) => config.default = Some(default.value()),
+                        lit => return Err(Error::new_spanned(lit, "expected a string")),
+                    }
+                }
+                nested => {
+                    return Err(Error::new_spanned(
+                        nested,
+                        "expected \"KEY\" or default = \"value\"",
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    input,
+                    "FromEnv requires a struct with named fields",
+                ))
+            }
+        },
+        _ => return Err(Error::new_spanned(input, "FromEnv requires a struct")),
+    };
+
+    let mut loads = Vec::new();
+    let mut builds = Vec::new();
+
+    for field in fields.iter() {
+        let ident = field.ident.as_ref().expect("named field");
+        let FieldConfig { key, default } = field_config(field)?;
+
+        let (ty, optional) = match option_inner(&field.ty) {
+            Some(ty) => (ty, true),
+            None => (&field.ty, false),
+        };
+
+        let default = match default {
+            Some(default) => quote! { Some(#default) },
+            None => quote! { None },
+        };
+
+        let missing = if optional {
+            quote! { Err(::timada_util::env::EnvError::Missing(_)) => None, }
+        } else {
+            quote! {}
+        };
+
+        loads.push(quote! {
+            let #ident: Option<#ty> = match layers.try_var_parsed::<#ty>(#key, #default) {
+                Ok(value) => Some(value),
+                #missing
+                Err(e) => {
+                    errors.push(e);
+                    None
+                }
+            };
+        });
+
+        builds.push(if optional {
+            quote! { #ident, }
+        } else {
+            quote! { #ident: #ident.expect("checked"), }
+        });
+    }
+
+    Ok(quote! {
+        impl ::timada_util::env::FromEnv for #name {
+            fn from_layers(
+                layers: &::timada_util::config::Layers,
+            ) -> Result<Self, Vec<::timada_util::env::EnvError>> {
+                let mut errors = Vec::new();
+
+                #(#loads)*
+
+                if !errors.is_empty() {
+                    return Err(errors);
+                }
+
+                Ok(#name {
+                    #(#builds)*
+                })
+            }
+        }
+    })
+}
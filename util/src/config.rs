@@ -0,0 +1,379 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use super::env::{generation, interpolate, parse_var, try_var, EnvError, EnvResult};
+
+type Entry<T> = (Option<usize>, Arc<T>);
+
+// Loaded values are loaded again once a `test_scope` changed the environment, values
+// given to `set` are kept.
+pub struct Cached<T> {
+    value: RwLock<Option<Entry<T>>>,
+    load: fn() -> T,
+}
+
+fn is_current<T>(entry: &Entry<T>, generation: usize) -> bool {
+    entry.0.map_or(true, |loaded| loaded == generation)
+}
+
+impl<T> Cached<T> {
+    pub fn new(load: fn() -> T) -> Self {
+        Cached {
+            value: RwLock::new(None),
+            load,
+        }
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        let generation = generation();
+
+        if let Some((_, value)) = self
+            .value
+            .read()
+            .ok()
+            .and_then(|value| value.clone())
+            .filter(|entry| is_current(entry, generation))
+        {
+            return value;
+        }
+
+        let mut value = match self.value.write() {
+            Ok(value) => value,
+            Err(e) => e.into_inner(),
+        };
+
+        match value.as_ref().filter(|entry| is_current(entry, generation)) {
+            Some((_, value)) => value.clone(),
+            None => {
+                let loaded = Arc::new((self.load)());
+                *value = Some((Some(generation), loaded.clone()));
+                loaded
+            }
+        }
+    }
+
+    fn store(&self, generation: Option<usize>, value: T) -> Arc<T> {
+        let value = Arc::new(value);
+
+        match self.value.write() {
+            Ok(mut current) => *current = Some((generation, value.clone())),
+            Err(e) => *e.into_inner() = Some((generation, value.clone())),
+        }
+
+        value
+    }
+
+    pub fn set(&self, value: T) -> Arc<T> {
+        self.store(None, value)
+    }
+
+    pub fn reload(&self) -> Arc<T> {
+        self.store(Some(generation()), (self.load)())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Provenance {
+    Override,
+    Env,
+    File(String),
+    Default,
+}
+
+impl Display for Provenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Provenance::Override => write!(f, "override"),
+            Provenance::Env => write!(f, "environment"),
+            Provenance::File(path) => write!(f, "file {:?}", path),
+            Provenance::Default => write!(f, "default"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Layers {
+    overrides: HashMap<String, String>,
+    env: bool,
+    files: Vec<(String, HashMap<String, String>)>,
+    interpolate: bool,
+}
+
+fn flatten(prefix: &str, value: &Value, values: &mut HashMap<String, String>) {
+    let key = prefix.to_uppercase();
+
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map {
+                let name = if prefix.is_empty() {
+                    name.to_owned()
+                } else {
+                    format!("{}_{}", prefix, name)
+                };
+
+                flatten(&name, value, values);
+            }
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| match item {
+                    Value::String(item) => item.to_owned(),
+                    item => item.to_string(),
+                })
+                .collect();
+
+            values.insert(key, items.join(","));
+        }
+        Value::String(value) => {
+            values.insert(key, value.to_owned());
+        }
+        Value::Null => {}
+        value => {
+            values.insert(key, value.to_string());
+        }
+    }
+}
+
+fn parse_file(path: &str, content: &str) -> Result<Value, String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default();
+
+    match extension {
+        "toml" => toml::from_str(content).map_err(|e| e.to_string()),
+        "yaml" | "yml" => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+        "json" => serde_json::from_str(content).map_err(|e| e.to_string()),
+        _ => Err("unsupported config format, expected toml, yaml or json".to_owned()),
+    }
+}
+
+impl Layers {
+    pub fn new() -> Self {
+        Layers::default()
+    }
+
+    pub fn env() -> Self {
+        Layers::new().with_env()
+    }
+
+    pub fn with_env(mut self) -> Self {
+        self.env = true;
+        self
+    }
+
+    pub fn file<P: AsRef<Path>>(self, path: P) -> EnvResult<Self> {
+        let path = path.as_ref().to_string_lossy().to_string();
+        let content = fs::read_to_string(&path).map_err(|e| EnvError::Config {
+            path: path.to_owned(),
+            message: e.to_string(),
+        })?;
+
+        self.source(&path, &content)
+    }
+
+    pub fn optional_file<P: AsRef<Path>>(self, path: P) -> EnvResult<Self> {
+        if path.as_ref().exists() {
+            self.file(path)
+        } else {
+            Ok(self)
+        }
+    }
+
+    pub fn source(mut self, path: &str, content: &str) -> EnvResult<Self> {
+        let value = parse_file(path, content).map_err(|message| EnvError::Config {
+            path: path.to_owned(),
+            message,
+        })?;
+
+        let mut values = HashMap::new();
+        flatten("", &value, &mut values);
+        self.files.push((path.to_owned(), values));
+
+        Ok(self)
+    }
+
+    pub fn interpolate(mut self) -> Self {
+        self.interpolate = true;
+        self
+    }
+
+    pub fn set(mut self, key: &str, value: &str) -> Self {
+        self.overrides.insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    pub fn lookup(&self, key: &str) -> EnvResult<Option<(String, Provenance)>> {
+        let (value, provenance) = match self.lookup_raw(key)? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+        if !self.interpolate {
+            return Ok(Some((value, provenance)));
+        }
+
+        let lookup = |name: &str| {
+            self.lookup_raw(name)
+                .ok()
+                .and_then(|found| found.map(|(value, _)| value))
+        };
+
+        interpolate(&value, lookup)
+            .map(|value| Some((value, provenance.clone())))
+            .map_err(|message| EnvError::Sourced {
+                source: provenance.to_string(),
+                error: Box::new(EnvError::Interpolation {
+                    key: key.to_owned(),
+                    message,
+                }),
+            })
+    }
+
+    fn lookup_raw(&self, key: &str) -> EnvResult<Option<(String, Provenance)>> {
+        if let Some(value) = self.overrides.get(key) {
+            return Ok(Some((value.to_owned(), Provenance::Override)));
+        }
+
+        if self.env {
+            match try_var(key) {
+                Ok(value) => return Ok(Some((value, Provenance::Env))),
+                Err(EnvError::Missing(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        for (path, values) in self.files.iter().rev() {
+            if let Some(value) = values.get(key) {
+                return Ok(Some((value.to_owned(), Provenance::File(path.to_owned()))));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn try_var(&self, key: &str) -> EnvResult<String> {
+        self.lookup(key)?
+            .map(|(value, _)| value)
+            .ok_or_else(|| EnvError::Missing(key.to_owned()))
+    }
+
+    pub fn try_var_parsed<T>(&self, key: &str, default: Option<&str>) -> EnvResult<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let (value, provenance) = match (self.lookup(key)?, default) {
+            (Some(found), _) => found,
+            (None, Some(default)) => (default.to_owned(), Provenance::Default),
+            (None, None) => return Err(EnvError::Missing(key.to_owned())),
+        };
+
+        parse_var(key, value).map_err(|e| match provenance {
+            Provenance::Env => e,
+            provenance => EnvError::Sourced {
+                source: provenance.to_string(),
+                error: Box::new(e),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{Cached, Layers, Provenance};
+    use crate::env::{test_scope, EnvError};
+
+    static LOADS: AtomicUsize = AtomicUsize::new(0);
+
+    fn load() -> usize {
+        LOADS.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    #[test]
+    fn cached() {
+        let env = test_scope();
+        let config = Cached::new(load);
+
+        assert_eq!(*config.get(), 1);
+        assert_eq!(*config.get(), 1);
+        assert_eq!(*config.reload(), 2);
+        assert_eq!(*config.get(), 2);
+
+        let env = env.set("UTIL_CONFIG_CACHED", "1");
+        assert_eq!(*config.get(), 3);
+
+        assert_eq!(*config.set(10), 10);
+        drop(env);
+        assert_eq!(*config.get(), 10);
+    }
+
+    #[test]
+    fn layers() {
+        let _env = test_scope().set("UTIL_CONFIG_LAYERS_PORT", "8080");
+
+        let layers = Layers::env()
+            .source(
+                "config.toml",
+                "[util_config_layers]\nhost = \"db\"\nport = 5432\nhosts = [\"a\", \"b\"]",
+            )
+            .unwrap()
+            .set("UTIL_CONFIG_LAYERS_NAME", "todos");
+
+        assert_eq!(
+            layers.lookup("UTIL_CONFIG_LAYERS_HOST"),
+            Ok(Some((
+                "db".to_owned(),
+                Provenance::File("config.toml".to_owned())
+            )))
+        );
+        assert_eq!(
+            layers.lookup("UTIL_CONFIG_LAYERS_PORT"),
+            Ok(Some(("8080".to_owned(), Provenance::Env)))
+        );
+        assert_eq!(
+            layers.try_var("UTIL_CONFIG_LAYERS_HOSTS"),
+            Ok("a,b".to_owned())
+        );
+        assert_eq!(
+            layers.try_var("UTIL_CONFIG_LAYERS_NAME"),
+            Ok("todos".to_owned())
+        );
+    }
+
+    #[test]
+    fn layers_interpolation() {
+        let layers = Layers::new()
+            .interpolate()
+            .set("UTIL_CONFIG_INTERPOLATION_HOST", "db")
+            .set(
+                "UTIL_CONFIG_INTERPOLATION_URL",
+                "postgres://${UTIL_CONFIG_INTERPOLATION_HOST}/app",
+            );
+
+        assert_eq!(
+            layers.try_var("UTIL_CONFIG_INTERPOLATION_URL"),
+            Ok("postgres://db/app".to_owned())
+        );
+    }
+
+    #[test]
+    fn layers_provenance() {
+        let layers = Layers::new()
+            .source("config.yaml", "util_config_provenance:\n  port: abc\n")
+            .unwrap();
+
+        match layers.try_var_parsed::<u16>("UTIL_CONFIG_PROVENANCE_PORT", None) {
+            Err(EnvError::Sourced { source, .. }) => assert_eq!(source, "file \"config.yaml\""),
+            res => panic!("unexpected {:?}", res),
+        }
+    }
+}
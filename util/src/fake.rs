@@ -0,0 +1,299 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::ops::Range;
+use uuid::Uuid;
+
+use super::env;
+
+pub const FAKER_SEED_VAR: &str = "FAKER_SEED";
+
+const FIRST_NAMES: &[&str] = &[
+    "Alice", "Amara", "Bruno", "Camille", "Chen", "Diego", "Elena", "Fatima", "Gabriel", "Hana",
+    "Hugo", "Ines", "Jonathan", "Kenji", "Lea", "Lucas", "Maya", "Nadia", "Noah", "Olivia", "Omar",
+    "Priya", "Rafael", "Sofia", "Theo", "Yuki", "Zoe",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Bernard",
+    "Dubois",
+    "Fernandez",
+    "Garcia",
+    "Ivanova",
+    "Jensen",
+    "Kim",
+    "Laurent",
+    "Martin",
+    "Moreau",
+    "Nakamura",
+    "Nguyen",
+    "Okafor",
+    "Patel",
+    "Petit",
+    "Rossi",
+    "Schmidt",
+    "Silva",
+    "Smith",
+    "Tanaka",
+    "Williams",
+    "Wright",
+];
+
+const DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+const WORDS: &[&str] = &[
+    "alpha", "amber", "anchor", "autumn", "beacon", "breeze", "canyon", "cedar", "cloud", "coral",
+    "crystal", "delta", "ember", "falcon", "forest", "garden", "glacier", "harbor", "horizon",
+    "island", "lantern", "maple", "meadow", "mountain", "nebula", "ocean", "orbit", "pebble",
+    "prairie", "quartz", "river", "sierra", "summit", "thunder", "valley", "willow",
+];
+
+lazy_static::lazy_static! {
+    static ref SEED: u64 = {
+        let seed = env::var_opt(FAKER_SEED_VAR)
+            .map(|seed| {
+                seed.parse()
+                    .unwrap_or_else(|_| panic!("Invalid {} {}", FAKER_SEED_VAR, seed))
+            })
+            .unwrap_or_else(|| rand::thread_rng().gen());
+
+        // Printed once so a failing run can be replayed with the same values.
+        eprintln!("{}={}", FAKER_SEED_VAR, seed);
+
+        seed
+    };
+}
+
+thread_local! {
+    static FAKER: RefCell<Faker> = RefCell::new(Faker::seeded(thread_seed(seed())));
+}
+
+pub fn seed() -> u64 {
+    *SEED
+}
+
+// Test threads are named after the test, so each test gets the same values for a seed
+// whatever the scheduling of the other tests.
+fn thread_seed(seed: u64) -> u64 {
+    let thread = std::thread::current();
+
+    thread
+        .name()
+        .unwrap_or_default()
+        .bytes()
+        .fold(seed ^ 0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+// Runs `f` with the faker of the current thread, seeded from `FAKER_SEED`.
+pub fn fake<T, F: FnOnce(&mut Faker) -> T>(f: F) -> T {
+    FAKER.with(|faker| f(&mut faker.borrow_mut()))
+}
+
+pub struct Faker {
+    rng: StdRng,
+}
+
+impl Faker {
+    pub fn seeded(seed: u64) -> Self {
+        Faker {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn pick<'a, T>(&mut self, values: &'a [T]) -> &'a T {
+        values.choose(&mut self.rng).expect("Nothing to pick from")
+    }
+
+    pub fn number(&mut self, range: Range<i64>) -> i64 {
+        self.rng.gen_range(range.start, range.end)
+    }
+
+    pub fn boolean(&mut self) -> bool {
+        self.rng.gen()
+    }
+
+    pub fn first_name(&mut self) -> &'static str {
+        self.pick(FIRST_NAMES)
+    }
+
+    pub fn last_name(&mut self) -> &'static str {
+        self.pick(LAST_NAMES)
+    }
+
+    pub fn name(&mut self) -> String {
+        format!("{} {}", self.first_name(), self.last_name())
+    }
+
+    pub fn username(&mut self) -> String {
+        format!(
+            "{}{}",
+            self.first_name().to_lowercase(),
+            self.number(1..10_000)
+        )
+    }
+
+    pub fn email(&mut self) -> String {
+        format!(
+            "{}.{}{}@{}",
+            self.first_name().to_lowercase(),
+            self.last_name().to_lowercase(),
+            self.number(1..10_000),
+            self.pick(DOMAINS)
+        )
+    }
+
+    pub fn word(&mut self) -> &'static str {
+        self.pick(WORDS)
+    }
+
+    pub fn words(&mut self, count: usize) -> Vec<&'static str> {
+        (0..count).map(|_| self.word()).collect()
+    }
+
+    pub fn sentence(&mut self) -> String {
+        let count = self.number(4..12) as usize;
+        let sentence = self.words(count).join(" ");
+        let mut chars = sentence.chars();
+
+        match chars.next() {
+            Some(first) => format!("{}{}.", first.to_uppercase(), chars.as_str()),
+            None => String::new(),
+        }
+    }
+
+    pub fn paragraph(&mut self) -> String {
+        let count = self.number(3..7);
+
+        (0..count)
+            .map(|_| self.sentence())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn datetime_between(&mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> DateTime<Utc> {
+        let millis = (end - start).num_milliseconds();
+        if millis <= 0 {
+            return start;
+        }
+
+        start + Duration::milliseconds(self.number(0..millis))
+    }
+
+    pub fn datetime_past(&mut self, within: Duration) -> DateTime<Utc> {
+        let now = Utc::now();
+
+        self.datetime_between(now - within, now)
+    }
+
+    pub fn datetime_future(&mut self, within: Duration) -> DateTime<Utc> {
+        let now = Utc::now();
+
+        self.datetime_between(now, now + within)
+    }
+
+    pub fn uuid_v4(&mut self) -> Uuid {
+        let mut bytes = [0; 16];
+        self.rng.fill_bytes(&mut bytes);
+
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        Uuid::from_bytes(bytes)
+    }
+
+    // Time ordered, the first 48 bits are the unix timestamp in milliseconds.
+    pub fn uuid_v7(&mut self, at: DateTime<Utc>) -> Uuid {
+        let mut bytes = [0; 16];
+        self.rng.fill_bytes(&mut bytes);
+
+        let millis = at.timestamp_millis() as u64;
+        bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+        bytes[6] = (bytes[6] & 0x0f) | 0x70;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        Uuid::from_bytes(bytes)
+    }
+
+    pub fn date(&mut self, year: Range<i32>) -> DateTime<Utc> {
+        let start = Utc.ymd(year.start, 1, 1).and_hms(0, 0, 0);
+        let end = Utc.ymd(year.end, 1, 1).and_hms(0, 0, 0);
+
+        self.datetime_between(start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{fake, Faker};
+
+    #[test]
+    fn reproducible() {
+        let values = |seed| {
+            let mut faker = Faker::seeded(seed);
+            (
+                faker.name(),
+                faker.email(),
+                faker.sentence(),
+                faker.uuid_v4(),
+            )
+        };
+
+        assert_eq!(values(42), values(42));
+        assert_ne!(values(42), values(43));
+    }
+
+    #[test]
+    fn values() {
+        let mut faker = Faker::seeded(7);
+
+        assert!(faker.email().contains('@'));
+        assert!(faker.sentence().ends_with('.'));
+        assert_eq!(faker.words(3).len(), 3);
+
+        let number = faker.number(10..20);
+        assert!(number >= 10 && number < 20);
+
+        let start = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+        let end = Utc.ymd(2020, 2, 1).and_hms(0, 0, 0);
+        let date = faker.datetime_between(start, end);
+        assert!(date >= start && date < end);
+        assert_eq!(faker.datetime_between(end, start), end);
+        assert!(faker.datetime_past(Duration::days(1)) <= Utc::now());
+
+        let date = faker.date(1990..2000);
+        assert!(date >= Utc.ymd(1990, 1, 1).and_hms(0, 0, 0));
+        assert!(date < Utc.ymd(2000, 1, 1).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn uuids() {
+        let mut faker = Faker::seeded(7);
+
+        assert_eq!(faker.uuid_v4().get_version_num(), 4);
+
+        let at = Utc.ymd(2020, 9, 1).and_hms_milli(12, 0, 0, 500);
+        let first = faker.uuid_v7(at);
+        let second = faker.uuid_v7(at + Duration::milliseconds(1));
+
+        assert_eq!(first.get_version_num(), 7);
+        assert_eq!(
+            &first.as_bytes()[..6],
+            &(at.timestamp_millis() as u64).to_be_bytes()[2..]
+        );
+        assert!(first < second);
+    }
+
+    #[test]
+    fn thread_faker() {
+        let first = fake(|faker| faker.uuid_v4());
+        let second = fake(|faker| faker.uuid_v4());
+
+        assert_ne!(first, second);
+    }
+}
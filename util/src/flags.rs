@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::env;
+
+use super::config::Cached;
+use super::env::parse_bool;
+
+const FEATURE_PREFIX: &str = "FEATURE_";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flag {
+    On,
+    Off,
+    Percentage(u8),
+}
+
+impl Flag {
+    pub fn parse(value: &str) -> Result<Flag, String> {
+        let value = value.trim();
+
+        if value.ends_with('%') {
+            return match value[..value.len() - 1].trim().parse::<u8>() {
+                Ok(percentage) if percentage <= 100 => Ok(Flag::Percentage(percentage)),
+                _ => Err(format!(
+                    "expected a percentage between 0% and 100%, got {:?}",
+                    value
+                )),
+            };
+        }
+
+        parse_bool(value).map(|enabled| if enabled { Flag::On } else { Flag::Off })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Flag::On => true,
+            Flag::Off => false,
+            Flag::Percentage(percentage) => *percentage >= 100,
+        }
+    }
+
+    pub fn is_enabled_for(&self, name: &str, subject: &str) -> bool {
+        match self {
+            Flag::Percentage(percentage) => bucket(name, subject) < u64::from(*percentage),
+            flag => flag.is_enabled(),
+        }
+    }
+}
+
+fn bucket(name: &str, subject: &str) -> u64 {
+    let hash = name
+        .bytes()
+        .chain(b":".iter().copied())
+        .chain(subject.bytes())
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+
+    hash % 100
+}
+
+fn flag_name(name: &str) -> String {
+    format!(
+        "{}{}",
+        FEATURE_PREFIX,
+        name.to_uppercase().replace('-', "_")
+    )
+}
+
+fn load() -> HashMap<String, Flag> {
+    env::vars()
+        .filter(|(key, _)| key.starts_with(FEATURE_PREFIX))
+        .filter_map(|(key, value)| match Flag::parse(&value) {
+            Ok(flag) => Some((key, flag)),
+            Err(e) => {
+                log::warn!("ignoring feature flag {}: {}", key, e);
+                None
+            }
+        })
+        .collect()
+}
+
+lazy_static::lazy_static! {
+    static ref FLAGS: Cached<HashMap<String, Flag>> = Cached::new(load);
+}
+
+pub fn flag(name: &str) -> Flag {
+    FLAGS
+        .get()
+        .get(&flag_name(name))
+        .copied()
+        .unwrap_or(Flag::Off)
+}
+
+pub fn is_enabled(name: &str) -> bool {
+    flag(name).is_enabled()
+}
+
+pub fn is_enabled_for(name: &str, subject: &str) -> bool {
+    flag(name).is_enabled_for(name, subject)
+}
+
+pub fn reload() {
+    FLAGS.reload();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bucket, flag_name, Flag};
+
+    #[test]
+    fn parse() {
+        assert_eq!(Flag::parse("on"), Ok(Flag::On));
+        assert_eq!(Flag::parse("false"), Ok(Flag::Off));
+        assert_eq!(Flag::parse("25%"), Ok(Flag::Percentage(25)));
+        assert!(Flag::parse("150%").is_err());
+        assert!(Flag::parse("maybe").is_err());
+    }
+
+    #[test]
+    fn percentage() {
+        let flag = Flag::Percentage(30);
+        let enabled = (0..1000)
+            .filter(|subject| flag.is_enabled_for("new_pagination", &subject.to_string()))
+            .count();
+
+        assert!(enabled > 200 && enabled < 400);
+        assert_eq!(
+            bucket("new_pagination", "user-1"),
+            bucket("new_pagination", "user-1")
+        );
+        assert!(!Flag::Percentage(0).is_enabled_for("new_pagination", "user-1"));
+        assert!(Flag::Percentage(100).is_enabled_for("new_pagination", "user-1"));
+    }
+
+    #[test]
+    fn name() {
+        assert_eq!(flag_name("new-pagination"), "FEATURE_NEW_PAGINATION");
+    }
+}
@@ -1 +1,11 @@
+extern crate self as timada_util;
+
+pub mod config;
 pub mod env;
+#[cfg(feature = "fake")]
+pub mod fake;
+pub mod flags;
+pub mod secret;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+pub mod watch;
@@ -0,0 +1,323 @@
+#[cfg(any(test, feature = "vault", feature = "aws"))]
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(any(test, feature = "vault", feature = "aws"))]
+use std::sync::Mutex;
+use std::sync::{Arc, RwLock};
+
+use super::env::{try_var, EnvError};
+
+#[derive(Clone, Default, PartialEq)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret([REDACTED])")
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[REDACTED]")
+    }
+}
+
+impl From<String> for Secret<String> {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Secret(value.to_owned())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SecretError {
+    Missing(String),
+    Provider(String),
+}
+
+impl fmt::Display for SecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretError::Missing(key) => write!(f, "secret {} not found", key),
+            SecretError::Provider(message) => write!(f, "secret provider failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+pub type SecretResult<T> = Result<T, SecretError>;
+
+pub trait SecretProvider: Send + Sync {
+    fn get(&self, key: &str) -> SecretResult<Option<Secret<String>>>;
+}
+
+pub struct EnvSecretProvider;
+
+impl SecretProvider for EnvSecretProvider {
+    fn get(&self, key: &str) -> SecretResult<Option<Secret<String>>> {
+        match try_var(key) {
+            Ok(value) => Ok(Some(Secret::new(value))),
+            Err(EnvError::Missing(_)) => Ok(None),
+            Err(e) => Err(SecretError::Provider(e.to_string())),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref PROVIDER: RwLock<Arc<dyn SecretProvider>> = RwLock::new(Arc::new(EnvSecretProvider));
+}
+
+pub fn set_secret_provider<P: SecretProvider + 'static>(provider: P) {
+    if let Ok(mut current) = PROVIDER.write() {
+        *current = Arc::new(provider);
+    }
+}
+
+pub fn try_secret(key: &str) -> SecretResult<Secret<String>> {
+    let provider = match PROVIDER.read() {
+        Ok(provider) => provider.clone(),
+        Err(e) => e.into_inner().clone(),
+    };
+
+    if let Some(secret) = provider.get(key)? {
+        return Ok(secret);
+    }
+
+    EnvSecretProvider
+        .get(key)?
+        .ok_or_else(|| SecretError::Missing(key.to_owned()))
+}
+
+pub fn secret(key: &str) -> Secret<String> {
+    match try_secret(key) {
+        Ok(secret) => secret,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+#[cfg(any(feature = "vault", feature = "aws"))]
+fn run_blocking<T, F>(f: F) -> SecretResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> SecretResult<T> + Send + 'static,
+{
+    std::thread::spawn(f)
+        .join()
+        .map_err(|_| SecretError::Provider("secret provider panicked".to_owned()))?
+}
+
+#[cfg(any(test, feature = "vault", feature = "aws"))]
+struct SecretCache(Mutex<Option<HashMap<String, String>>>);
+
+#[cfg(any(test, feature = "vault", feature = "aws"))]
+impl SecretCache {
+    fn new() -> Self {
+        SecretCache(Mutex::new(None))
+    }
+
+    fn get<F>(&self, key: &str, fetch: F) -> SecretResult<Option<Secret<String>>>
+    where
+        F: FnOnce() -> SecretResult<HashMap<String, String>>,
+    {
+        let mut cache = match self.0.lock() {
+            Ok(cache) => cache,
+            Err(e) => e.into_inner(),
+        };
+
+        if cache.is_none() {
+            *cache = Some(fetch()?);
+        }
+
+        Ok(cache
+            .as_ref()
+            .and_then(|values| values.get(key))
+            .map(|value| Secret::new(value.to_owned())))
+    }
+}
+
+#[cfg(any(test, feature = "vault", feature = "aws"))]
+fn string_map(value: &serde_json::Value) -> HashMap<String, String> {
+    value
+        .as_object()
+        .map(|values| {
+            values
+                .iter()
+                .map(|(key, value)| match value {
+                    serde_json::Value::String(value) => (key.to_owned(), value.to_owned()),
+                    value => (key.to_owned(), value.to_string()),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "vault")]
+pub struct VaultSecretProvider {
+    addr: String,
+    token: Secret<String>,
+    path: String,
+    cache: SecretCache,
+}
+
+#[cfg(feature = "vault")]
+impl VaultSecretProvider {
+    pub fn new(addr: &str, token: Secret<String>, path: &str) -> Self {
+        VaultSecretProvider {
+            addr: addr.trim_end_matches('/').to_owned(),
+            token,
+            path: path.trim_start_matches('/').to_owned(),
+            cache: SecretCache::new(),
+        }
+    }
+
+    pub fn from_env(path: &str) -> Self {
+        VaultSecretProvider::new(
+            &super::env::var("VAULT_ADDR"),
+            Secret::new(super::env::var("VAULT_TOKEN")),
+            path,
+        )
+    }
+
+    fn fetch(&self) -> SecretResult<HashMap<String, String>> {
+        let url = format!("{}/v1/{}", self.addr, self.path);
+        let token = self.token.expose().to_owned();
+
+        let body: serde_json::Value = run_blocking(move || {
+            reqwest::blocking::Client::new()
+                .get(&url)
+                .header("X-Vault-Token", token)
+                .send()
+                .and_then(|res| res.error_for_status())
+                .and_then(|res| res.json())
+                .map_err(|e| SecretError::Provider(e.to_string()))
+        })?;
+
+        let data = &body["data"];
+
+        Ok(match data.get("data") {
+            Some(data) if data.is_object() => string_map(data),
+            _ => string_map(data),
+        })
+    }
+}
+
+#[cfg(feature = "vault")]
+impl SecretProvider for VaultSecretProvider {
+    fn get(&self, key: &str) -> SecretResult<Option<Secret<String>>> {
+        self.cache.get(key, || self.fetch())
+    }
+}
+
+#[cfg(feature = "aws")]
+pub struct AwsSecretProvider {
+    region: rusoto_core::Region,
+    secret_id: String,
+    cache: SecretCache,
+}
+
+#[cfg(feature = "aws")]
+impl AwsSecretProvider {
+    pub fn new(region: rusoto_core::Region, secret_id: &str) -> Self {
+        AwsSecretProvider {
+            region,
+            secret_id: secret_id.to_owned(),
+            cache: SecretCache::new(),
+        }
+    }
+
+    fn fetch(&self) -> SecretResult<HashMap<String, String>> {
+        use rusoto_secretsmanager::{GetSecretValueRequest, SecretsManager, SecretsManagerClient};
+
+        let region = self.region.clone();
+        let secret_id = self.secret_id.to_owned();
+
+        let secret = run_blocking(move || {
+            let mut runtime =
+                tokio::runtime::Runtime::new().map_err(|e| SecretError::Provider(e.to_string()))?;
+            let client = SecretsManagerClient::new(region);
+
+            runtime
+                .block_on(client.get_secret_value(GetSecretValueRequest {
+                    secret_id,
+                    ..Default::default()
+                }))
+                .map_err(|e| SecretError::Provider(e.to_string()))
+        })?;
+
+        let secret_string = secret
+            .secret_string
+            .ok_or_else(|| SecretError::Provider("secret has no string value".to_owned()))?;
+
+        serde_json::from_str(&secret_string)
+            .map(|value| string_map(&value))
+            .map_err(|e| SecretError::Provider(e.to_string()))
+    }
+}
+
+#[cfg(feature = "aws")]
+impl SecretProvider for AwsSecretProvider {
+    fn get(&self, key: &str) -> SecretResult<Option<Secret<String>>> {
+        self.cache.get(key, || self.fetch())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{string_map, Secret, SecretCache, SecretError};
+
+    #[test]
+    fn redacted() {
+        let secret = Secret::from("password");
+
+        assert_eq!(format!("{:?}", secret), "Secret([REDACTED])");
+        assert_eq!(format!("{}", secret), "[REDACTED]");
+        assert_eq!(secret.expose(), "password");
+    }
+
+    #[test]
+    fn cache_fetches_once() {
+        let cache = SecretCache::new();
+        let mut values = HashMap::new();
+        values.insert("DB_PASSWORD".to_owned(), "password".to_owned());
+
+        let secret = cache.get("DB_PASSWORD", || Ok(values)).unwrap();
+        assert_eq!(secret, Some(Secret::from("password")));
+
+        let secret = cache
+            .get("DB_USER", || {
+                Err(SecretError::Provider("fetched twice".to_owned()))
+            })
+            .unwrap();
+        assert_eq!(secret, None);
+    }
+
+    #[test]
+    fn json_values() {
+        let values = string_map(&serde_json::json!({ "username": "root", "port": 5432 }));
+
+        assert_eq!(values.get("username"), Some(&"root".to_owned()));
+        assert_eq!(values.get("port"), Some(&"5432".to_owned()));
+    }
+}
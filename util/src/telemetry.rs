@@ -0,0 +1,94 @@
+use tracing_subscriber::EnvFilter;
+
+use super::env::var_or;
+
+const LOG_LEVEL_VAR: &str = "LOG_LEVEL";
+const LOG_FILTERS_VAR: &str = "LOG_FILTERS";
+const LOG_FORMAT_VAR: &str = "LOG_FORMAT";
+
+#[derive(Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Pretty,
+    Compact,
+}
+
+pub fn log_format() -> Result<Format, String> {
+    match var_or(LOG_FORMAT_VAR, "json").to_lowercase().as_str() {
+        "json" => Ok(Format::Json),
+        "pretty" | "text" => Ok(Format::Pretty),
+        "compact" => Ok(Format::Compact),
+        value => Err(format!(
+            "couldn't parse {}={:?}: expected json, pretty or compact",
+            LOG_FORMAT_VAR, value
+        )),
+    }
+}
+
+fn directives(level: &str, filters: &str) -> String {
+    let filters: Vec<&str> = filters
+        .split(',')
+        .map(|filter| filter.trim())
+        .filter(|filter| !filter.is_empty())
+        .collect();
+
+    if filters.is_empty() {
+        level.to_owned()
+    } else {
+        format!("{},{}", level, filters.join(","))
+    }
+}
+
+pub fn env_filter() -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(directives(
+            &var_or(LOG_LEVEL_VAR, "info"),
+            &var_or(LOG_FILTERS_VAR, ""),
+        ))
+    })
+}
+
+pub fn try_init() -> Result<(), String> {
+    let builder = tracing_subscriber::fmt().with_env_filter(env_filter());
+
+    let res = match log_format()? {
+        Format::Json => builder.json().try_init(),
+        Format::Pretty => builder.try_init(),
+        Format::Compact => builder.compact().try_init(),
+    };
+
+    res.map_err(|e| e.to_string())
+}
+
+pub fn init() {
+    if let Err(e) = try_init() {
+        panic!("couldn't initialize telemetry: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{directives, log_format, Format, LOG_FORMAT_VAR};
+    use crate::env::test_scope;
+
+    #[test]
+    fn build_directives() {
+        assert_eq!(directives("info", ""), "info");
+        assert_eq!(
+            directives("warn", "timada_http=debug, actix_web=info,"),
+            "warn,timada_http=debug,actix_web=info"
+        );
+    }
+
+    #[test]
+    fn parse_format() {
+        let env = test_scope().set(LOG_FORMAT_VAR, "Pretty");
+        assert_eq!(log_format(), Ok(Format::Pretty));
+
+        let env = env.remove(LOG_FORMAT_VAR);
+        assert_eq!(log_format(), Ok(Format::Json));
+
+        let _env = env.set(LOG_FORMAT_VAR, "xml");
+        assert!(log_format().is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use super::env::{EnvError, FromEnv};
+
+type Loader<T> = dyn Fn() -> Result<T, Vec<EnvError>> + Send + Sync;
+type Subscriber<T> = Box<dyn Fn(&Arc<T>) + Send + Sync>;
+
+pub struct Watch<T> {
+    current: Arc<RwLock<Arc<T>>>,
+    load: Arc<Loader<T>>,
+    subscribers: Arc<Mutex<Vec<Subscriber<T>>>>,
+}
+
+impl<T> Clone for Watch<T> {
+    fn clone(&self) -> Self {
+        Watch {
+            current: self.current.clone(),
+            load: self.load.clone(),
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+impl<T: FromEnv + Send + Sync + 'static> Watch<T> {
+    pub fn from_env() -> Result<Self, Vec<EnvError>> {
+        Watch::new(T::from_env)
+    }
+}
+
+impl<T: Send + Sync + 'static> Watch<T> {
+    pub fn new<F>(load: F) -> Result<Self, Vec<EnvError>>
+    where
+        F: Fn() -> Result<T, Vec<EnvError>> + Send + Sync + 'static,
+    {
+        let current = load()?;
+
+        Ok(Watch {
+            current: Arc::new(RwLock::new(Arc::new(current))),
+            load: Arc::new(load),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        match self.current.read() {
+            Ok(current) => current.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    pub fn subscribe<F>(&self, subscriber: F)
+    where
+        F: Fn(&Arc<T>) + Send + Sync + 'static,
+    {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(Box::new(subscriber));
+        }
+    }
+
+    pub fn reload(&self) -> Result<Arc<T>, Vec<EnvError>> {
+        let value = Arc::new((self.load)()?);
+
+        match self.current.write() {
+            Ok(mut current) => *current = value.clone(),
+            Err(e) => *e.into_inner() = value.clone(),
+        }
+
+        if let Ok(subscribers) = self.subscribers.lock() {
+            for subscriber in subscribers.iter() {
+                subscriber(&value);
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn reload_or_log(&self) {
+        if let Err(errors) = self.reload() {
+            for e in errors {
+                log::error!("config reload failed: {}", e);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    pub fn reload_on_sighup(&self) -> io::Result<()> {
+        let signals = signal_hook::iterator::Signals::new(&[signal_hook::SIGHUP])?;
+        let watch = self.clone();
+
+        thread::spawn(move || {
+            for _ in signals.forever() {
+                log::info!("SIGHUP received, reloading config");
+                watch.reload_or_log();
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn reload_on_change(&self, paths: Vec<PathBuf>, interval: Duration) -> io::Result<()> {
+        let modified = |paths: &[PathBuf]| -> Vec<Option<SystemTime>> {
+            paths
+                .iter()
+                .map(|path| path.metadata().and_then(|meta| meta.modified()).ok())
+                .collect()
+        };
+
+        let mut last = modified(&paths);
+        let watch = self.clone();
+
+        thread::Builder::new()
+            .name("config-watch".to_owned())
+            .spawn(move || loop {
+                thread::sleep(interval);
+
+                let current = modified(&paths);
+
+                if current != last {
+                    log::info!("config files changed, reloading config");
+                    watch.reload_or_log();
+                    last = current;
+                }
+            })
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::Watch;
+    use crate::env::EnvError;
+
+    #[test]
+    fn reload_and_subscribe() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let notified = Arc::new(AtomicUsize::new(0));
+
+        let counter = loads.clone();
+        let watch = Watch::new(move || Ok(counter.fetch_add(1, Ordering::SeqCst))).unwrap();
+
+        let subscriber = notified.clone();
+        watch.subscribe(move |value| subscriber.store(**value, Ordering::SeqCst));
+
+        assert_eq!(*watch.get(), 0);
+        assert_eq!(*watch.reload().unwrap(), 1);
+        assert_eq!(*watch.get(), 1);
+        assert_eq!(notified.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn reload_failure_keeps_value() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = loads.clone();
+        let watch = Watch::new(move || match counter.fetch_add(1, Ordering::SeqCst) {
+            0 => Ok("initial"),
+            _ => Err(vec![EnvError::Missing("KEY".to_owned())]),
+        })
+        .unwrap();
+
+        assert!(watch.reload().is_err());
+        assert_eq!(*watch.get(), "initial");
+    }
+}